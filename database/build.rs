@@ -0,0 +1,4 @@
+// マイグレーションを追加または変更したときに、埋め込むマイグレーションを更新するために再ビルドする。
+fn main() {
+    println!("cargo:rerun-if-changed=../migrations");
+}
@@ -0,0 +1,371 @@
+//! `COPY ... FROM STDIN`文で、多数の行をまとめてテーブルに登録する。
+
+use sqlx::{PgConnection, PgPool};
+use tokio::task::JoinSet;
+use utils::errors::Failure;
+
+/// COPY文で1回に送信するバイト数の目安。
+const COPY_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// 並列に登録するときに、1つのCOPY文で登録する行の数。
+const PARALLEL_CHUNK_ROWS: usize = 10_000;
+
+/// COPY文で登録する行(値を列の順に並べ、NULLはNoneとする)。
+type Row = Vec<Option<String>>;
+
+/// COPY文のテキスト形式で値を記録できるように、値をエスケープしてバッファーに追加する。
+///
+/// # Arguments
+///
+/// * `buffer` - 値を追加するバッファー。
+/// * `value` - 追加する値。Noneの場合はNULLを示す`\N`を追加する。
+fn push_value(buffer: &mut Vec<u8>, value: Option<&str>) {
+    let value = match value {
+        Some(value) => value,
+        None => {
+            buffer.extend_from_slice(br"\N");
+            return;
+        }
+    };
+    for b in value.bytes() {
+        match b {
+            b'\\' => buffer.extend_from_slice(br"\\"),
+            b'\t' => buffer.extend_from_slice(br"\t"),
+            b'\n' => buffer.extend_from_slice(br"\n"),
+            b'\r' => buffer.extend_from_slice(br"\r"),
+            _ => buffer.push(b),
+        }
+    }
+}
+
+/// 行を`COPY ... FROM STDIN`文のテキスト形式で送信して、テーブルに登録する。
+///
+/// 行は`COPY_CHUNK_SIZE`程度にまとめて送信する。行の作成に失敗した場合は、COPY文を中止してエラーを返す。
+///
+/// # Arguments
+///
+/// * `conn` - データベース接続(トランザクション内で登録する場合は、トランザクションの接続)。
+/// * `table` - 行を登録するテーブル。
+/// * `columns` - 値を登録する列の名前。
+/// * `rows` - 登録する行を返すイテレーター。行の値は`columns`の順に並べ、NULLはNoneとする。
+///
+/// # Returns
+///
+/// 登録した行の数。
+pub async fn copy_rows<I>(
+    conn: &mut PgConnection,
+    table: &str,
+    columns: &[&str],
+    rows: I,
+) -> anyhow::Result<u64>
+where
+    I: IntoIterator<Item = anyhow::Result<Row>>,
+{
+    let statement = format!("COPY {} ({}) FROM STDIN", table, columns.join(", "));
+    let mut copy = conn.copy_in_raw(&statement).await.map_err(|e| {
+        Failure::database(format!("{}にCOPY文で登録を開始できません。{}", table, e))
+    })?;
+    let mut buffer = Vec::with_capacity(COPY_CHUNK_SIZE);
+    for row in rows {
+        let row = match row {
+            Ok(row) => row,
+            Err(e) => {
+                copy.abort(e.to_string()).await.ok();
+                return Err(e);
+            }
+        };
+        for (index, value) in row.iter().enumerate() {
+            if 0 < index {
+                buffer.push(b'\t');
+            }
+            push_value(&mut buffer, value.as_deref());
+        }
+        buffer.push(b'\n');
+        if COPY_CHUNK_SIZE <= buffer.len() {
+            copy.send(buffer.as_slice()).await.map_err(|e| {
+                Failure::database(format!("{}にCOPY文で行を送信できません。{}", table, e))
+            })?;
+            buffer.clear();
+        }
+    }
+    if !buffer.is_empty() {
+        copy.send(buffer.as_slice()).await.map_err(|e| {
+            Failure::database(format!("{}にCOPY文で行を送信できません。{}", table, e))
+        })?;
+    }
+
+    copy.finish().await.map_err(|e| {
+        Failure::database(format!("{}にCOPY文で行を登録できません。{}", table, e)).into()
+    })
+}
+
+/// 行を複数の接続で並列に`COPY ... FROM STDIN`文で送信して、テーブルに登録する。
+///
+/// 行を`PARALLEL_CHUNK_ROWS`行ずつのチャンクに分けて、最大`jobs`個のチャンクを、コネクションプールから
+/// 取得したそれぞれの接続で同時に登録する。各チャンクはトランザクションの外で登録するため、ほかの接続から
+/// 参照できないトランザクションの一時テーブルには登録できない。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+/// * `table` - 行を登録するテーブル。
+/// * `columns` - 値を登録する列の名前。
+/// * `rows` - 登録する行を返すイテレーター。行の値は`columns`の順に並べ、NULLはNoneとする。
+/// * `jobs` - 同時に登録するチャンクの最大数。
+///
+/// # Returns
+///
+/// 登録した行の数。
+pub async fn copy_rows_parallel<I>(
+    pool: &PgPool,
+    table: &str,
+    columns: &[&str],
+    rows: I,
+    jobs: usize,
+) -> anyhow::Result<u64>
+where
+    I: IntoIterator<Item = anyhow::Result<Row>>,
+{
+    let columns: Vec<String> = columns.iter().map(|column| column.to_string()).collect();
+    let mut rows = rows.into_iter();
+    let mut tasks = JoinSet::new();
+    let mut copied = 0;
+    loop {
+        let chunk = rows
+            .by_ref()
+            .take(PARALLEL_CHUNK_ROWS)
+            .collect::<anyhow::Result<Vec<Row>>>()?;
+        if chunk.is_empty() {
+            break;
+        }
+        // 登録中のチャンクが上限に達している場合は、いずれかのチャンクの登録が終わるまで待つ
+        if jobs.max(1) <= tasks.len() {
+            copied += join_copy(tasks.join_next().await.unwrap())?;
+        }
+        let pool = pool.clone();
+        let table = table.to_string();
+        let columns = columns.clone();
+        tasks.spawn(async move {
+            let mut conn = pool
+                .acquire()
+                .await
+                .map_err(|e| Failure::database(format!("データベースに接続できません。{}", e)))?;
+            let columns: Vec<&str> = columns.iter().map(String::as_str).collect();
+            copy_rows(&mut conn, &table, &columns, chunk.into_iter().map(Ok)).await
+        });
+    }
+    while let Some(result) = tasks.join_next().await {
+        copied += join_copy(result)?;
+    }
+
+    Ok(copied)
+}
+
+/// チャンクを登録したタスクの結果から、登録した行の数を取得する。
+///
+/// # Arguments
+///
+/// * `result` - チャンクを登録したタスクの結果。
+///
+/// # Returns
+///
+/// 登録した行の数。
+fn join_copy(result: Result<anyhow::Result<u64>, tokio::task::JoinError>) -> anyhow::Result<u64> {
+    result.map_err(|e| anyhow::anyhow!("行を登録するタスクが異常終了しました。{}", e))?
+}
+
+/// 登録する行をCOPY文でまとめて格納する作業用のテーブル。
+///
+/// 並列に登録しない場合は、トランザクションの終了時に削除する一時テーブルを作成する。並列に登録する場合は、
+/// ほかの接続から行を登録できるように、ログを記録しない(UNLOGGED)テーブルを一意な名前でトランザクションの外で
+/// 作成して、登録が終わったときに削除する。
+///
+/// 作業用のテーブルを参照する文は、[`StagingTable::copy`]で行を登録した後で実行して、その結果を
+/// [`StagingTable::finish`]に渡すこと。並列に登録する場合は、作業用のテーブルを参照した文がロックを
+/// 保持したままにならないように、それらの文をセーブポイントの中で実行する。
+pub struct StagingTable {
+    /// テーブル名
+    name: String,
+    /// 並列に登録するときに行を登録するコネクションプール(並列に登録しない場合はNone)
+    pool: Option<PgPool>,
+    /// 同時に登録するチャンクの最大数
+    jobs: usize,
+}
+
+/// 作業用のテーブルを参照する文を実行するセーブポイントの名前。
+const STAGING_SAVEPOINT: &str = "staging_table";
+
+impl StagingTable {
+    /// 作業用のテーブルを作成する。
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - データベース接続(トランザクションの接続)。
+    /// * `pool` - データベースコネクションプール。
+    /// * `name` - テーブル名。並列に登録する場合は、テーブル名に一意な接尾辞を付ける。
+    /// * `definition` - テーブルの列の定義(`name text, geom geometry`など)。
+    /// * `jobs` - 同時に登録するチャンクの最大数。1以下の場合は並列に登録しない。
+    ///
+    /// # Returns
+    ///
+    /// 作業用のテーブル。
+    pub async fn create(
+        conn: &mut PgConnection,
+        pool: &PgPool,
+        name: &str,
+        definition: &str,
+        jobs: usize,
+    ) -> anyhow::Result<Self> {
+        if jobs <= 1 {
+            let sql = format!("CREATE TEMP TABLE {} ({}) ON COMMIT DROP", name, definition);
+            sqlx::query(&sql).execute(conn).await.map_err(|e| {
+                Failure::database(format!("一時テーブル({})を作成できません。{}", name, e))
+            })?;
+            return Ok(Self {
+                name: name.to_string(),
+                pool: None,
+                jobs: 1,
+            });
+        }
+        let (suffix,): (String,) =
+            sqlx::query_as("SELECT replace(gen_random_uuid()::text, '-', '')")
+                .fetch_one(pool)
+                .await
+                .map_err(|e| {
+                    Failure::database(format!("データベースに問い合わせできません。{}", e))
+                })?;
+        let name = format!("{}_{}", name, suffix);
+        let sql = format!("CREATE UNLOGGED TABLE {} ({})", name, definition);
+        sqlx::query(&sql).execute(pool).await.map_err(|e| {
+            Failure::database(format!("作業用のテーブル({})を作成できません。{}", name, e))
+        })?;
+
+        Ok(Self {
+            name,
+            pool: Some(pool.clone()),
+            jobs,
+        })
+    }
+
+    /// 作業用のテーブル名を返す。
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// 行をCOPY文で作業用のテーブルに登録する。
+    ///
+    /// 並列に登録する場合は、行を登録した後で、作業用のテーブルを参照する文を実行するセーブポイントを
+    /// トランザクションに設定する。行を登録できなかった場合は、作業用のテーブルを削除する。
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - データベース接続(トランザクションの接続)。
+    /// * `columns` - 値を登録する列の名前。
+    /// * `rows` - 登録する行を返すイテレーター。行の値は`columns`の順に並べ、NULLはNoneとする。
+    ///
+    /// # Returns
+    ///
+    /// 登録した行の数。
+    pub async fn copy<I>(
+        &self,
+        conn: &mut PgConnection,
+        columns: &[&str],
+        rows: I,
+    ) -> anyhow::Result<u64>
+    where
+        I: IntoIterator<Item = anyhow::Result<Row>>,
+    {
+        let pool = match &self.pool {
+            Some(pool) => pool,
+            None => return copy_rows(conn, &self.name, columns, rows).await,
+        };
+        let copied = match copy_rows_parallel(pool, &self.name, columns, rows, self.jobs).await {
+            Ok(copied) => copied,
+            Err(e) => {
+                self.drop_table(pool).await;
+                return Err(e);
+            }
+        };
+        if let Err(e) = execute(conn, &format!("SAVEPOINT {}", STAGING_SAVEPOINT)).await {
+            self.drop_table(pool).await;
+            return Err(e);
+        }
+
+        Ok(copied)
+    }
+
+    /// 作業用のテーブルを参照する文の実行を終えて、作業用のテーブルを削除する。
+    ///
+    /// 並列に登録する場合、文の実行に成功したときは、トランザクションの中で作業用のテーブルを削除するため、
+    /// トランザクションをコミットしたときに削除される。文の実行に失敗したときは、セーブポイントまで
+    /// ロールバックして、作業用のテーブルのロックを解放してから削除する。
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - データベース接続(トランザクションの接続)。
+    /// * `result` - 作業用のテーブルを参照した文の実行結果。
+    ///
+    /// # Returns
+    ///
+    /// 作業用のテーブルを参照した文の実行結果。
+    pub async fn finish<T>(
+        self,
+        conn: &mut PgConnection,
+        result: anyhow::Result<T>,
+    ) -> anyhow::Result<T> {
+        let pool = match &self.pool {
+            Some(pool) => pool,
+            None => return result,
+        };
+        match result {
+            Ok(value) => {
+                execute(&mut *conn, &format!("DROP TABLE {}", self.name)).await?;
+                execute(conn, &format!("RELEASE SAVEPOINT {}", STAGING_SAVEPOINT)).await?;
+                Ok(value)
+            }
+            Err(e) => {
+                execute(
+                    conn,
+                    &format!("ROLLBACK TO SAVEPOINT {}", STAGING_SAVEPOINT),
+                )
+                .await
+                .ok();
+                self.drop_table(pool).await;
+                Err(e)
+            }
+        }
+    }
+
+    /// トランザクションの外で作業用のテーブルを削除する。
+    ///
+    /// 削除できなかった場合は、警告をログに出力する。
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - データベースコネクションプール。
+    async fn drop_table(&self, pool: &PgPool) {
+        let sql = format!("DROP TABLE IF EXISTS {}", self.name);
+        let result = match pool.acquire().await {
+            Ok(mut conn) => execute(&mut conn, &sql).await,
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = result {
+            tracing::warn!(table = %self.name, error = %e, "作業用のテーブルを削除できません");
+        }
+    }
+}
+
+/// SQL文を実行する。
+///
+/// # Arguments
+///
+/// * `conn` - データベース接続。
+/// * `sql` - 実行するSQL文。
+async fn execute(conn: &mut PgConnection, sql: &str) -> anyhow::Result<()> {
+    sqlx::query(sql)
+        .execute(conn)
+        .await
+        .map_err(|e| Failure::database(format!("SQL文({})を実行できません。{}", sql, e)))?;
+
+    Ok(())
+}
@@ -0,0 +1,179 @@
+//! データセットを登録した履歴を記録する。
+
+use std::path::Path;
+
+use sqlx::{PgConnection, PgPool};
+use utils::errors::Failure;
+
+/// 登録したデータセット。
+#[derive(Debug, Clone, Copy)]
+pub struct ImportSource<'a> {
+    /// データセットの種類(国土数値情報のデータセットの場合は`N03`などの識別子)
+    pub dataset: &'a str,
+    /// 登録した都道府県コード。都道府県ごとに登録しないデータセットの場合はNone
+    pub code: Option<&'a str>,
+    /// 登録したファイルのパス
+    pub file: &'a str,
+    /// データセットの年度(西暦)。Noneの場合はファイル名から判断する
+    pub year: Option<u16>,
+}
+
+impl<'a> ImportSource<'a> {
+    /// 登録したファイルの名前を返す。
+    pub fn file_name(&self) -> String {
+        Path::new(self.file)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.file.to_string())
+    }
+
+    /// データセットの年度を返す。
+    ///
+    /// # Returns
+    ///
+    /// データセットの年度(西暦)。指定されておらず、ファイル名からも判断できない場合はNone。
+    pub fn year(&self) -> Option<u16> {
+        self.year.or_else(|| source_year(&self.file_name()))
+    }
+}
+
+/// 国土数値情報のファイル名から、データセットの年度を取得する。
+///
+/// 国土数値情報のファイル名は、データセットの識別子(英大文字1文字と数字2文字)とハイフンに続けて、
+/// `P30-13_21.shp`のように西暦の下2桁、`N03-180101_21_GML.zip`のように基準日(西暦の下2桁と月日)、
+/// または`N03-20220101_21_GML.zip`のように基準日(西暦と月日)で年度を示す。
+///
+/// # Arguments
+///
+/// * `file_name` - ファイル名。
+///
+/// # Returns
+///
+/// データセットの年度(西暦)。ファイル名から年度を取得できない場合はNone。
+pub fn source_year(file_name: &str) -> Option<u16> {
+    let bytes = file_name.as_bytes();
+    if bytes.len() < 6
+        || !bytes[0].is_ascii_uppercase()
+        || !bytes[1..3].iter().all(u8::is_ascii_digit)
+        || bytes[3] != b'-'
+    {
+        return None;
+    }
+    let digits = bytes[4..].iter().take_while(|b| b.is_ascii_digit()).count();
+    match digits {
+        2 | 6 => file_name[4..6].parse::<u16>().ok().map(|year| 2000 + year),
+        8 => file_name[4..8].parse::<u16>().ok(),
+        _ => None,
+    }
+}
+
+/// データセットを登録した履歴を記録する。
+///
+/// 登録に失敗した場合に履歴が残らないように、登録と同じトランザクションで記録すること。
+///
+/// # Arguments
+///
+/// * `conn` - データベース接続(トランザクションの接続)。
+/// * `source` - 登録したデータセット。
+/// * `layer` - 登録したレイヤー名(テーブル名)。
+/// * `features` - 登録したフィーチャーの数。
+pub async fn record_import(
+    conn: &mut PgConnection,
+    source: &ImportSource<'_>,
+    layer: &str,
+    features: usize,
+) -> anyhow::Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO imports (layer, prefecture_code, features, dataset, source_file, source_year)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+        layer,
+        source.code,
+        features as i32,
+        source.dataset,
+        source.file_name(),
+        source.year().map(|year| year as i16),
+    )
+    .execute(conn)
+    .await
+    .map_err(|e| Failure::database(format!("登録の履歴を記録できません。{}", e)))?;
+
+    Ok(())
+}
+
+/// データセットを登録した履歴。
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ImportRecord {
+    /// 履歴のID
+    pub id: i64,
+    /// 登録したレイヤー名(テーブル名)
+    pub layer: String,
+    /// 登録した都道府県コード
+    pub prefecture_code: Option<String>,
+    /// 登録したフィーチャーの数
+    pub features: i32,
+    /// データセットの種類
+    pub dataset: Option<String>,
+    /// 登録したファイルの名前
+    pub source_file: Option<String>,
+    /// データセットの年度(西暦)
+    pub source_year: Option<i16>,
+    /// 登録した日時(UTC、RFC 3339形式)
+    pub imported_at: String,
+}
+
+/// データセットを登録した履歴を、新しい順に取得する。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+/// * `layer` - 履歴を取得するレイヤー名。Noneの場合はすべてのレイヤーの履歴を取得する。
+/// * `code` - 履歴を取得する都道府県コード。Noneの場合はすべての都道府県の履歴を取得する。
+/// * `limit` - 取得する履歴の最大数。
+///
+/// # Returns
+///
+/// データセットを登録した履歴を格納したベクタ。
+pub async fn list_imports(
+    pool: &PgPool,
+    layer: Option<&str>,
+    code: Option<&str>,
+    limit: i64,
+) -> sqlx::Result<Vec<ImportRecord>> {
+    sqlx::query_as::<_, ImportRecord>(
+        r#"
+        SELECT
+            id, layer, prefecture_code, features, dataset, source_file, source_year,
+            to_char(imported_at AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24:MI:SS"Z"') as imported_at
+        FROM imports
+        WHERE ($1::text IS NULL OR layer = $1) AND ($2::text IS NULL OR prefecture_code = $2)
+        ORDER BY imports.imported_at DESC, id DESC
+        LIMIT $3
+        "#,
+    )
+    .bind(layer)
+    .bind(code)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// レイヤーに最後にデータセットを登録した履歴のIDを取得する。
+///
+/// 履歴のIDは登録するたびに増加するため、レイヤーのデータセットの版として利用できる。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+/// * `layer` - レイヤー名(テーブル名)。
+///
+/// # Returns
+///
+/// 履歴のID。レイヤーにデータセットを登録した履歴がない場合はNone。
+pub async fn last_import_id(pool: &PgPool, layer: &str) -> sqlx::Result<Option<i64>> {
+    sqlx::query_scalar("SELECT max(id) FROM imports WHERE layer = $1")
+        .bind(layer)
+        .fetch_one(pool)
+        .await
+}
@@ -1,22 +1,38 @@
-use sqlx::{postgres::PgPoolOptions, PgPool};
+pub mod copy;
+pub mod imports;
+pub mod migrations;
+pub mod preflight;
+pub mod settings;
+pub mod upsert;
+
+use sqlx::PgPool;
+
+use crate::settings::DatabaseSettings;
 
 /// 環境変数DATABASE_URLの値を使用して、データベースに接続する。
 ///
+/// コネクションプールの設定は、[`DatabaseSettings::from_env`]に従って環境変数から読み込む。
+///
 /// # Returns
 ///
-/// データベースコネクションプール。
-pub async fn connect_to_database() -> PgPool {
-    let key = "DATABASE_URL";
-    let url = std::env::var(key).unwrap_or_else(|_| {
-        format!(
-            "環境変数にデータベースへの接続URLを示す{}が設定されていません。",
-            key
-        )
-    });
+/// データベースコネクションプール。環境変数が設定されていない場合、またはデータベースに接続できない場合はエラー。
+pub async fn connect_to_database() -> anyhow::Result<PgPool> {
+    DatabaseSettings::from_env()?.connect().await
+}
 
-    PgPoolOptions::new()
-        .max_connections(5)
-        .connect(&url)
+/// 指定されたURLを使用して、データベースに接続する。
+///
+/// # Arguments
+///
+/// * `url` - データベースへの接続URL。
+/// * `max_connections` - コネクションプールの最大接続数。
+///
+/// # Returns
+///
+/// データベースコネクションプール。データベースに接続できない場合はエラー。
+pub async fn connect_to_database_with(url: &str, max_connections: u32) -> anyhow::Result<PgPool> {
+    DatabaseSettings::new(url)
+        .max_connections(max_connections)
+        .connect()
         .await
-        .expect("データベースに接続できません。環境変数DATABASE_URLの値を確認してください。")
 }
@@ -0,0 +1,92 @@
+//! リポジトリの`migrations`ディレクトリにあるマイグレーションを埋め込み、データベースに適用する。
+//!
+//! マイグレーションは、PostGISなどの拡張の有効化と、本アプリが使用するテーブル及び空間インデックスの作成を
+//! 含むため、空のデータベースにすべてのマイグレーションを適用すると、本アプリを利用できる状態になる。
+
+use sqlx::migrate::{Migrate, Migrator};
+use sqlx::PgPool;
+use utils::errors::Failure;
+
+/// ビルド時に埋め込んだマイグレーション。
+pub static MIGRATOR: Migrator = sqlx::migrate!("../migrations");
+
+/// マイグレーションの適用状況。
+#[derive(Debug, Clone)]
+pub struct MigrationStatus {
+    /// バージョン
+    pub version: i64,
+    /// 説明
+    pub description: String,
+    /// 適用済みの場合はtrue
+    pub applied: bool,
+    /// 適用したときとマイグレーションの内容が異なる場合はtrue
+    pub changed: bool,
+}
+
+/// 適用していないマイグレーションを、データベースに適用する。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+///
+/// # Returns
+///
+/// 適用したマイグレーションの数。
+pub async fn run_migrations(pool: &PgPool) -> anyhow::Result<usize> {
+    let pending = migration_statuses(pool)
+        .await?
+        .iter()
+        .filter(|status| !status.applied)
+        .count();
+    MIGRATOR
+        .run(pool)
+        .await
+        .map_err(|e| Failure::database(format!("マイグレーションを適用できません。{}", e)))?;
+
+    Ok(pending)
+}
+
+/// 埋め込んだマイグレーションごとに、データベースへの適用状況を確認する。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+///
+/// # Returns
+///
+/// バージョンの順に並べたマイグレーションの適用状況。
+pub async fn migration_statuses(pool: &PgPool) -> anyhow::Result<Vec<MigrationStatus>> {
+    let database_error = |e: sqlx::migrate::MigrateError| {
+        Failure::database(format!("マイグレーションの適用状況を確認できません。{}", e))
+    };
+    let mut connection = pool
+        .acquire()
+        .await
+        .map_err(|e| Failure::database(format!("データベースに接続できません。{}", e)))?;
+    connection
+        .ensure_migrations_table()
+        .await
+        .map_err(database_error)?;
+    let applied = connection
+        .list_applied_migrations()
+        .await
+        .map_err(database_error)?;
+
+    Ok(MIGRATOR
+        .iter()
+        .filter(|migration| !migration.migration_type.is_down_migration())
+        .map(|migration| {
+            let found = applied
+                .iter()
+                .find(|applied| applied.version == migration.version);
+            MigrationStatus {
+                version: migration.version,
+                description: migration.description.to_string(),
+                applied: found.is_some(),
+                changed: found
+                    .map(|applied| applied.checksum != migration.checksum)
+                    .unwrap_or(false),
+            }
+        })
+        .collect())
+}
@@ -0,0 +1,165 @@
+use sqlx::PgPool;
+
+/// 確認するPostgreSQLの拡張。
+///
+/// 拡張の名前、用途、必須かを格納する。
+const EXTENSIONS: [(&str, &str, bool); 4] = [
+    ("postgis", "ジオメトリの格納と空間演算", true),
+    ("pgcrypto", "gen_random_uuid関数によるUUIDの生成", true),
+    ("pg_trgm", "市区町村と郵便局の名前による検索", true),
+    ("h3", "H3インデックスによる集計", false),
+];
+
+/// 拡張の状態。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExtensionStatus {
+    /// インストール済み(インストールされているバージョン)
+    Installed(String),
+    /// PostgreSQLの標準機能で代替できる
+    BuiltIn,
+    /// サーバーで利用できるが、データベースにインストールされていない
+    NotInstalled,
+    /// サーバーで利用できない
+    Unavailable,
+}
+
+/// 拡張の確認結果。
+#[derive(Debug, Clone)]
+pub struct ExtensionCheck {
+    /// 拡張の名前
+    pub name: &'static str,
+    /// 拡張の用途
+    pub purpose: &'static str,
+    /// 必須の拡張か
+    pub required: bool,
+    /// 拡張の状態
+    pub status: ExtensionStatus,
+}
+
+impl ExtensionCheck {
+    /// 拡張を利用できるか確認する。
+    pub fn is_ok(&self) -> bool {
+        matches!(
+            self.status,
+            ExtensionStatus::Installed(_) | ExtensionStatus::BuiltIn
+        )
+    }
+
+    /// 拡張を利用できるようにする方法を返す。
+    ///
+    /// # Returns
+    ///
+    /// 拡張を利用できる場合はNone。利用できない場合は、実行するSQL文と対処方法。
+    pub fn guidance(&self) -> Option<String> {
+        let statement = format!("CREATE EXTENSION IF NOT EXISTS {};", self.name);
+        match self.status {
+            ExtensionStatus::Installed(_) | ExtensionStatus::BuiltIn => None,
+            ExtensionStatus::NotInstalled => Some(statement),
+            ExtensionStatus::Unavailable => Some(format!(
+                "{}(サーバーに{}拡張のパッケージをインストールしてから実行してください)",
+                statement, self.name
+            )),
+        }
+    }
+}
+
+/// 拡張の確認結果の一覧。
+#[derive(Debug, Clone)]
+pub struct PreflightReport {
+    /// 拡張の確認結果
+    pub checks: Vec<ExtensionCheck>,
+}
+
+impl PreflightReport {
+    /// 利用できない必須の拡張を返す。
+    pub fn missing_required(&self) -> Vec<&ExtensionCheck> {
+        self.checks
+            .iter()
+            .filter(|check| check.required && !check.is_ok())
+            .collect()
+    }
+
+    /// 利用できない任意の拡張を返す。
+    pub fn missing_optional(&self) -> Vec<&ExtensionCheck> {
+        self.checks
+            .iter()
+            .filter(|check| !check.required && !check.is_ok())
+            .collect()
+    }
+
+    /// 必須の拡張をすべて利用できるか確認する。
+    pub fn is_ok(&self) -> bool {
+        self.missing_required().is_empty()
+    }
+
+    /// 利用できない必須の拡張を利用できるようにする方法を、1つの文字列にまとめて返す。
+    pub fn required_guidance(&self) -> String {
+        self.missing_required()
+            .iter()
+            .filter_map(|check| check.guidance())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// データベースで、本アプリが使用する拡張を利用できるか確認する。
+///
+/// gen_random_uuid関数は、PostgreSQL 13以降では標準で利用できるため、関数が存在する場合は
+/// pgcryptoを利用できると判断する。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+///
+/// # Returns
+///
+/// 拡張の確認結果の一覧。
+pub async fn preflight(pool: &PgPool) -> sqlx::Result<PreflightReport> {
+    let names: Vec<String> = EXTENSIONS
+        .iter()
+        .map(|(name, _, _)| name.to_string())
+        .collect();
+    let available = sqlx::query!(
+        r#"
+        SELECT name::text AS "name!", installed_version::text AS installed_version
+        FROM pg_available_extensions
+        WHERE name = ANY($1)
+        "#,
+        &names
+    )
+    .fetch_all(pool)
+    .await?;
+    let gen_random_uuid = sqlx::query!(
+        r#"
+        SELECT EXISTS(SELECT 1 FROM pg_proc WHERE proname = 'gen_random_uuid') AS "exists!"
+        "#
+    )
+    .fetch_one(pool)
+    .await?
+    .exists;
+
+    let checks = EXTENSIONS
+        .iter()
+        .map(|(name, purpose, required)| {
+            let row = available.iter().find(|row| row.name == *name);
+            let installed_version = row.and_then(|row| row.installed_version.clone());
+            let status = if let Some(version) = installed_version {
+                ExtensionStatus::Installed(version)
+            } else if *name == "pgcrypto" && gen_random_uuid {
+                ExtensionStatus::BuiltIn
+            } else if row.is_some() {
+                ExtensionStatus::NotInstalled
+            } else {
+                ExtensionStatus::Unavailable
+            };
+            ExtensionCheck {
+                name,
+                purpose,
+                required: *required,
+                status,
+            }
+        })
+        .collect();
+
+    Ok(PreflightReport { checks })
+}
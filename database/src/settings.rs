@@ -0,0 +1,493 @@
+//! データベースのコネクションプールの設定。
+
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions, PgSslMode};
+use sqlx::PgPool;
+use utils::errors::Failure;
+
+/// データベースへの接続URLを設定する環境変数の名前。
+pub const DATABASE_URL_KEY: &str = "DATABASE_URL";
+
+/// コネクションプールの最大接続数の既定値。
+pub const DEFAULT_MAX_CONNECTIONS: u32 = 5;
+
+/// コネクションプールの最小接続数の既定値。
+pub const DEFAULT_MIN_CONNECTIONS: u32 = 0;
+
+/// コネクションプールから接続を取得するまで待機する秒数の既定値。
+pub const DEFAULT_ACQUIRE_TIMEOUT_SECONDS: u64 = 30;
+
+/// 使用していない接続を切断するまでの秒数の既定値。
+pub const DEFAULT_IDLE_TIMEOUT_SECONDS: u64 = 600;
+
+/// データベースに接続できない場合に、再試行を続ける秒数の既定値。
+pub const DEFAULT_CONNECT_DEADLINE_SECONDS: u64 = 30;
+
+/// データベースへの接続を再試行するまでの最初の待機時間。
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+/// データベースへの接続を再試行するまでの待機時間の最大値。
+const MAX_BACKOFF: Duration = Duration::from_secs(8);
+
+/// 文の実行時間が`statement_timeout`を超えたため取り消されたことを示すSQLSTATE(query_canceled)。
+pub const QUERY_CANCELED: &str = "57014";
+
+/// PostgreSQLが起動中であることを示すSQLSTATE(cannot_connect_now)。
+const CANNOT_CONNECT_NOW: &str = "57P03";
+
+/// データベースのコネクションプールの設定。
+///
+/// ```ignore
+/// let pool = DatabaseSettings::new(url)
+///     .max_connections(10)
+///     .application_name("mapctl")
+///     .connect()
+///     .await?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct DatabaseSettings {
+    /// データベースへの接続URL
+    url: String,
+    /// 参照のみに使用する読み取り専用のデータベース(リードレプリカ)への接続URL(設定されていない場合はNone)
+    read_url: Option<String>,
+    /// コネクションプールの最大接続数
+    max_connections: u32,
+    /// コネクションプールの最小接続数
+    min_connections: u32,
+    /// コネクションプールから接続を取得するまで待機する時間
+    acquire_timeout: Duration,
+    /// 使用していない接続を切断するまでの時間(切断しない場合はNone)
+    idle_timeout: Option<Duration>,
+    /// データベースに通知するアプリケーションの名前(通知しない場合はNone)
+    application_name: Option<String>,
+    /// データベースに接続できない場合に、再試行を続ける時間(0の場合は再試行しない)
+    connect_deadline: Duration,
+    /// 1つの文の実行時間の上限(制限しない場合はNone)
+    statement_timeout: Option<Duration>,
+    /// TLSで接続するかを決めるSSLモード(接続URLの`sslmode`に従う場合はNone)
+    ssl_mode: Option<String>,
+    /// サーバー証明書を検証するルート証明書(PEM)のパス(接続URLの`sslrootcert`に従う場合はNone)
+    ssl_root_cert: Option<PathBuf>,
+}
+
+impl DatabaseSettings {
+    /// 既定値のコネクションプールの設定を構築する。
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - データベースへの接続URL。
+    ///
+    /// # Returns
+    ///
+    /// コネクションプールの設定。
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            read_url: None,
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            min_connections: DEFAULT_MIN_CONNECTIONS,
+            acquire_timeout: Duration::from_secs(DEFAULT_ACQUIRE_TIMEOUT_SECONDS),
+            idle_timeout: Some(Duration::from_secs(DEFAULT_IDLE_TIMEOUT_SECONDS)),
+            application_name: None,
+            connect_deadline: Duration::from_secs(DEFAULT_CONNECT_DEADLINE_SECONDS),
+            statement_timeout: None,
+            ssl_mode: None,
+            ssl_root_cert: None,
+        }
+    }
+
+    /// 環境変数からコネクションプールの設定を構築する。
+    ///
+    /// 接続URLは環境変数DATABASE_URLから読み込み、次の環境変数が設定されている場合は、その値で既定値を
+    /// 上書きする。
+    ///
+    /// * `DATABASE_READ_URL` - 参照のみに使用するリードレプリカへの接続URL。
+    /// * `DATABASE_MAX_CONNECTIONS` - 最大接続数。
+    /// * `DATABASE_MIN_CONNECTIONS` - 最小接続数。
+    /// * `DATABASE_ACQUIRE_TIMEOUT_SECONDS` - 接続を取得するまで待機する秒数。
+    /// * `DATABASE_IDLE_TIMEOUT_SECONDS` - 使用していない接続を切断するまでの秒数(空文字列の場合は切断しない)。
+    /// * `DATABASE_APPLICATION_NAME` - データベースに通知するアプリケーションの名前。
+    /// * `DATABASE_CONNECT_DEADLINE_SECONDS` - データベースに接続できない場合に、再試行を続ける秒数(0の場合は再試行しない)。
+    /// * `DATABASE_STATEMENT_TIMEOUT_MILLIS` - 1つの文の実行時間の上限(ミリ秒、空文字列の場合は制限しない)。
+    /// * `DATABASE_SSL_MODE` - SSLモード(`disable`、`allow`、`prefer`、`require`、`verify-ca`または`verify-full`)。
+    /// * `DATABASE_SSL_ROOT_CERT` - サーバー証明書を検証するルート証明書(PEM)のパス。
+    ///
+    /// # Returns
+    ///
+    /// コネクションプールの設定。環境変数DATABASE_URLが設定されていない場合、または環境変数の値が不正な場合はエラー。
+    pub fn from_env() -> anyhow::Result<Self> {
+        let url = std::env::var(DATABASE_URL_KEY).map_err(|_| {
+            Failure::database(format!(
+                "環境変数にデータベースへの接続URLを示す{}が設定されていません。",
+                DATABASE_URL_KEY
+            ))
+        })?;
+        let mut settings = Self::new(url);
+        if let Ok(value) = std::env::var("DATABASE_READ_URL") {
+            settings.read_url = Some(value).filter(|url| !url.is_empty());
+        }
+        if let Some(value) = parse_env("DATABASE_MAX_CONNECTIONS")? {
+            settings.max_connections = value;
+        }
+        if let Some(value) = parse_env("DATABASE_MIN_CONNECTIONS")? {
+            settings.min_connections = value;
+        }
+        if let Some(value) = parse_env("DATABASE_ACQUIRE_TIMEOUT_SECONDS")? {
+            settings.acquire_timeout = Duration::from_secs(value);
+        }
+        let key = "DATABASE_IDLE_TIMEOUT_SECONDS";
+        if let Ok(value) = std::env::var(key) {
+            settings.idle_timeout = if value.is_empty() {
+                None
+            } else {
+                Some(Duration::from_secs(parse_value(key, &value)?))
+            };
+        }
+        if let Ok(value) = std::env::var("DATABASE_APPLICATION_NAME") {
+            settings.application_name = Some(value).filter(|name| !name.is_empty());
+        }
+        if let Some(value) = parse_env("DATABASE_CONNECT_DEADLINE_SECONDS")? {
+            settings.connect_deadline = Duration::from_secs(value);
+        }
+        let key = "DATABASE_STATEMENT_TIMEOUT_MILLIS";
+        if let Ok(value) = std::env::var(key) {
+            settings.statement_timeout = if value.is_empty() {
+                None
+            } else {
+                Some(Duration::from_millis(parse_value(key, &value)?))
+            };
+        }
+        if let Ok(value) = std::env::var("DATABASE_SSL_MODE") {
+            settings.ssl_mode = Some(value).filter(|mode| !mode.is_empty());
+        }
+        if let Ok(value) = std::env::var("DATABASE_SSL_ROOT_CERT") {
+            settings.ssl_root_cert =
+                Some(PathBuf::from(value)).filter(|path| !path.as_os_str().is_empty());
+        }
+
+        Ok(settings)
+    }
+
+    /// 参照のみに使用する読み取り専用のデータベース(リードレプリカ)への接続URLを設定する。
+    ///
+    /// リードレプリカのコネクションプールには、接続URL以外は同じ設定を使用する。
+    pub fn read_url(mut self, url: impl Into<String>) -> Self {
+        self.read_url = Some(url.into());
+        self
+    }
+
+    /// コネクションプールの最大接続数を設定する。
+    pub fn max_connections(mut self, max_connections: u32) -> Self {
+        self.max_connections = max_connections;
+        self
+    }
+
+    /// コネクションプールの最大接続数が指定された数より少ない場合は、指定された数に増やす。
+    pub fn reserve_connections(mut self, connections: u32) -> Self {
+        self.max_connections = self.max_connections.max(connections);
+        self
+    }
+
+    /// コネクションプールの最小接続数を設定する。
+    pub fn min_connections(mut self, min_connections: u32) -> Self {
+        self.min_connections = min_connections;
+        self
+    }
+
+    /// コネクションプールから接続を取得するまで待機する時間を設定する。
+    pub fn acquire_timeout(mut self, timeout: Duration) -> Self {
+        self.acquire_timeout = timeout;
+        self
+    }
+
+    /// 使用していない接続を切断するまでの時間を設定する。Noneの場合は切断しない。
+    pub fn idle_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.idle_timeout = timeout;
+        self
+    }
+
+    /// データベースに通知するアプリケーションの名前を設定する。
+    ///
+    /// アプリケーションの名前は、`pg_stat_activity`の`application_name`列で確認できる。
+    pub fn application_name(mut self, name: impl Into<String>) -> Self {
+        self.application_name = Some(name.into());
+        self
+    }
+
+    /// データベースに接続できない場合に、再試行を続ける時間を設定する。`Duration::ZERO`の場合は再試行しない。
+    pub fn connect_deadline(mut self, deadline: Duration) -> Self {
+        self.connect_deadline = deadline;
+        self
+    }
+
+    /// 1つの文の実行時間の上限を設定する。Noneの場合は制限しない。
+    ///
+    /// コネクションプールのすべての接続に`statement_timeout`を設定する。上限を超えた文は取り消され、
+    /// SQLSTATEが[`QUERY_CANCELED`]のエラーになる。
+    pub fn statement_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.statement_timeout = timeout;
+        self
+    }
+
+    /// TLSで接続するかを決めるSSLモードを設定する。接続URLの`sslmode`より優先する。
+    ///
+    /// `disable`、`allow`、`prefer`、`require`、`verify-ca`または`verify-full`を指定する。マネージドな
+    /// PostgreSQL(Amazon RDSやCloud SQLなど)に、サーバー証明書を検証して接続する場合は`verify-full`を指定する。
+    pub fn ssl_mode(mut self, mode: impl Into<String>) -> Self {
+        self.ssl_mode = Some(mode.into());
+        self
+    }
+
+    /// サーバー証明書を検証するルート証明書(PEM)のパスを設定する。接続URLの`sslrootcert`より優先する。
+    pub fn ssl_root_cert(mut self, path: impl Into<PathBuf>) -> Self {
+        self.ssl_root_cert = Some(path.into());
+        self
+    }
+
+    /// データベースへの接続URLを返す。
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// 設定を検証して、データベースへの接続オプションを構築する。
+    ///
+    /// # Returns
+    ///
+    /// データベースへの接続オプション。設定が不正な場合はエラー。
+    fn connect_options(&self) -> anyhow::Result<PgConnectOptions> {
+        if self.max_connections == 0 {
+            return Err(Failure::argument(
+                "コネクションプールの最大接続数には、0より大きい値を設定してください。",
+            )
+            .into());
+        }
+        if self.max_connections < self.min_connections {
+            return Err(Failure::argument(format!(
+                "コネクションプールの最小接続数({})が最大接続数({})より大きいです。",
+                self.min_connections, self.max_connections
+            ))
+            .into());
+        }
+        let mut options = PgConnectOptions::from_str(&self.url).map_err(|e| {
+            Failure::database(format!(
+                "データベースへの接続URLが不正です。データベースへの接続URLを確認してください。{}",
+                e
+            ))
+        })?;
+        if let Some(name) = &self.application_name {
+            options = options.application_name(name);
+        }
+        if let Some(timeout) = self.statement_timeout {
+            options = options.options([("statement_timeout", timeout.as_millis())]);
+        }
+        if let Some(mode) = &self.ssl_mode {
+            let mode = PgSslMode::from_str(mode).map_err(|_| {
+                Failure::argument(format!(
+                    "SSLモード({})は、disable、allow、prefer、require、verify-caまたはverify-fullで指定してください。",
+                    mode
+                ))
+            })?;
+            options = options.ssl_mode(mode);
+        }
+        if let Some(path) = &self.ssl_root_cert {
+            if !path.is_file() {
+                return Err(Failure::input(format!(
+                    "ルート証明書({})が見つかりません。",
+                    path.display()
+                ))
+                .into());
+            }
+            options = options.ssl_root_cert(path);
+        }
+
+        Ok(options)
+    }
+
+    /// 設定に従って、コネクションプールのオプションを構築する。
+    fn pool_options(&self) -> PgPoolOptions {
+        PgPoolOptions::new()
+            .max_connections(self.max_connections)
+            .min_connections(self.min_connections)
+            .connect_timeout(self.acquire_timeout)
+            .idle_timeout(self.idle_timeout)
+    }
+
+    /// 設定に従って、データベースに接続する。
+    ///
+    /// データベースが起動中の場合など、一時的に接続できない場合は、再試行を続ける時間を過ぎるまで、
+    /// 待機時間を倍にしながら再試行する。接続の試行は、それぞれトレースする。
+    ///
+    /// # Returns
+    ///
+    /// データベースコネクションプール。設定が不正な場合、またはデータベースに接続できない場合はエラー。
+    pub async fn connect(&self) -> anyhow::Result<PgPool> {
+        let options = self.connect_options()?;
+        let started = Instant::now();
+        let mut backoff = INITIAL_BACKOFF;
+        let mut attempt = 1;
+        loop {
+            let result = self.pool_options().connect_with(options.clone()).await;
+            match result {
+                Ok(pool) => {
+                    tracing::info!(attempt, "データベースに接続");
+                    return Ok(pool);
+                }
+                Err(e)
+                    if is_transient(&e) && started.elapsed() + backoff < self.connect_deadline =>
+                {
+                    tracing::warn!(
+                        attempt,
+                        backoff_millis = backoff.as_millis() as u64,
+                        error = %e,
+                        "データベースに接続できないため再試行"
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                    attempt += 1;
+                }
+                Err(e) => {
+                    tracing::error!(attempt, error = %e, "データベースに接続できません");
+                    return Err(Failure::database(format!(
+                        "データベースに接続できません。データベースへの接続URLを確認してください。{}",
+                        e
+                    ))
+                    .into());
+                }
+            }
+        }
+    }
+
+    /// 設定に従って、更新に使用するデータベースと、参照に使用するリードレプリカに接続する。
+    ///
+    /// リードレプリカへの接続URLが設定されていない場合は、更新に使用するコネクションプールを参照にも使用する。
+    ///
+    /// # Returns
+    ///
+    /// 更新用と参照用のデータベースコネクションプール。設定が不正な場合、またはデータベースに接続できない場合はエラー。
+    pub async fn connect_pools(&self) -> anyhow::Result<DatabasePools> {
+        let write = self.connect().await?;
+        let read = match &self.read_url {
+            Some(url) => {
+                let settings = Self {
+                    url: url.clone(),
+                    read_url: None,
+                    ..self.clone()
+                };
+                settings.connect().await.map_err(|e| {
+                    e.context("参照に使用するデータベース(リードレプリカ)に接続できません。")
+                })?
+            }
+            None => write.clone(),
+        };
+
+        Ok(DatabasePools { read, write })
+    }
+
+    /// 設定に従って、データベースに接続せずにコネクションプールを構築する。
+    ///
+    /// コネクションプールは、最初に接続を取得するときにデータベースに接続するため、データベースが
+    /// 停止していても構築できる。
+    ///
+    /// # Returns
+    ///
+    /// 更新用と参照用のデータベースコネクションプール。設定が不正な場合はエラー。
+    pub fn connect_pools_lazy(&self) -> anyhow::Result<DatabasePools> {
+        let write = self
+            .pool_options()
+            .connect_lazy_with(self.connect_options()?);
+        let read = match &self.read_url {
+            Some(url) => {
+                let settings = Self {
+                    url: url.clone(),
+                    read_url: None,
+                    ..self.clone()
+                };
+                settings
+                    .pool_options()
+                    .connect_lazy_with(settings.connect_options()?)
+            }
+            None => write.clone(),
+        };
+
+        Ok(DatabasePools { read, write })
+    }
+}
+
+/// 参照用と更新用のデータベースコネクションプール。
+#[derive(Debug, Clone)]
+pub struct DatabasePools {
+    /// 参照に使用するコネクションプール(リードレプリカを設定していない場合は、更新用と同じプール)
+    pub read: PgPool,
+    /// 更新に使用するコネクションプール
+    pub write: PgPool,
+}
+
+impl DatabasePools {
+    /// 参照用と更新用のコネクションプールを閉じる。
+    pub async fn close(&self) {
+        self.read.close().await;
+        self.write.close().await;
+    }
+}
+
+impl From<PgPool> for DatabasePools {
+    /// 参照と更新に、同じコネクションプールを使用する。
+    fn from(pool: PgPool) -> Self {
+        Self {
+            read: pool.clone(),
+            write: pool,
+        }
+    }
+}
+
+/// データベースに接続できなかった原因が、データベースの起動中など一時的なものか確認する。
+///
+/// # Arguments
+///
+/// * `error` - データベースに接続したときのエラー。
+///
+/// # Returns
+///
+/// 再試行すると接続できる可能性がある場合はtrue。
+fn is_transient(error: &sqlx::Error) -> bool {
+    match error {
+        sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut => true,
+        sqlx::Error::Database(e) => e.code().as_deref() == Some(CANNOT_CONNECT_NOW),
+        _ => false,
+    }
+}
+
+/// 環境変数が設定されている場合は、その値を解析する。
+///
+/// # Arguments
+///
+/// * `key` - 環境変数の名前。
+///
+/// # Returns
+///
+/// 環境変数の値。環境変数が設定されていない場合はNone、値が不正な場合はエラー。
+fn parse_env<T: FromStr>(key: &str) -> anyhow::Result<Option<T>> {
+    match std::env::var(key) {
+        Ok(value) => parse_value(key, &value).map(Some),
+        Err(_) => Ok(None),
+    }
+}
+
+/// 環境変数の値を解析する。
+///
+/// # Arguments
+///
+/// * `key` - 環境変数の名前(エラーメッセージに使用)。
+/// * `value` - 環境変数の値。
+///
+/// # Returns
+///
+/// 解析した値。値が不正な場合はエラー。
+fn parse_value<T: FromStr>(key: &str, value: &str) -> anyhow::Result<T> {
+    value.parse().map_err(|_| {
+        Failure::argument(format!("環境変数{}の値({})が不正です。", key, value)).into()
+    })
+}
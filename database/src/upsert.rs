@@ -0,0 +1,136 @@
+//! 既存のレコードを削除せずに、自然キーで照合して変更があったレコードだけを登録する。
+//!
+//! 登録するレコードは、自然キー(市区町村コードと名称など)が一致する既存のレコードを
+//! `INSERT ... ON CONFLICT DO UPDATE`文で更新し、値が変わらない既存のレコードは更新しない。
+//! 登録する範囲(都道府県)の既存のレコードのうち、自然キーが一致するレコードを登録しなかったものは削除する。
+
+use sqlx::PgConnection;
+use utils::errors::Failure;
+
+/// 自然キーで照合して登録した結果。
+#[derive(Debug, Default, Clone, Copy)]
+pub struct UpsertSummary {
+    /// 追加または更新したレコードの数
+    pub changed: u64,
+    /// 値が変わらないため更新しなかったレコードの数
+    pub unchanged: u64,
+    /// 登録しなかったため削除した既存のレコードの数
+    pub deleted: u64,
+}
+
+impl UpsertSummary {
+    /// 自然キーで照合して登録した結果を作成する。
+    ///
+    /// # Arguments
+    ///
+    /// * `rows` - 登録した行の数。
+    /// * `changed` - `INSERT ... ON CONFLICT DO UPDATE`文で追加または更新したレコードの数。
+    /// * `deleted` - 削除した既存のレコードの数。
+    ///
+    /// # Returns
+    ///
+    /// 自然キーで照合して登録した結果。
+    pub fn new(rows: u64, changed: u64, deleted: u64) -> Self {
+        Self {
+            changed,
+            unchanged: rows.saturating_sub(changed),
+            deleted,
+        }
+    }
+}
+
+impl std::fmt::Display for UpsertSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "追加または更新{}件、変更なし{}件、削除{}件",
+            self.changed, self.unchanged, self.deleted
+        )
+    }
+}
+
+/// `INSERT`文に続けて、自然キーが一致する既存のレコードを更新する`ON CONFLICT`句を返す。
+///
+/// 既存のレコードの値が登録する値と同じ場合は、既存のレコードを更新しない。自然キーの列には、
+/// 一意インデックスを作成しておくこと。
+///
+/// # Arguments
+///
+/// * `table` - 登録するテーブル。
+/// * `keys` - 自然キーの列の名前。
+/// * `columns` - 更新する列の名前。
+///
+/// # Returns
+///
+/// `ON CONFLICT ... DO UPDATE`句。
+pub fn on_conflict_update(table: &str, keys: &[&str], columns: &[&str]) -> String {
+    let assignments: Vec<String> = columns
+        .iter()
+        .map(|column| format!("{} = EXCLUDED.{}", column, column))
+        .collect();
+    let current: Vec<String> = columns
+        .iter()
+        .map(|column| format!("{}.{}", table, column))
+        .collect();
+    let excluded: Vec<String> = columns
+        .iter()
+        .map(|column| format!("EXCLUDED.{}", column))
+        .collect();
+
+    format!(
+        "ON CONFLICT ({}) DO UPDATE SET {} WHERE ({}) IS DISTINCT FROM ({})",
+        keys.join(", "),
+        assignments.join(", "),
+        current.join(", "),
+        excluded.join(", ")
+    )
+}
+
+/// 登録する範囲の既存のレコードのうち、自然キーが一致する行が一時テーブルにないレコードを削除する。
+///
+/// # Arguments
+///
+/// * `conn` - データベース接続(トランザクションの接続)。
+/// * `table` - 登録したテーブル。
+/// * `staging` - 登録した行を格納した一時テーブル。
+/// * `keys` - 自然キーの列の名前。一時テーブルにも同じ名前の列がなければならない。
+/// * `scope` - 登録する範囲のレコードを選択する条件。テーブルは`t`で参照し、`$1`に`scope_value`を割り当てる。
+/// * `scope_value` - 登録する範囲のレコードを選択する条件に割り当てる値。
+/// * `label` - エラーメッセージに含めるレコードの名前。
+///
+/// # Returns
+///
+/// 削除したレコードの数。
+pub async fn delete_missing(
+    conn: &mut PgConnection,
+    table: &str,
+    staging: &str,
+    keys: &[&str],
+    scope: &str,
+    scope_value: &str,
+    label: &str,
+) -> anyhow::Result<u64> {
+    let matches: Vec<String> = keys
+        .iter()
+        .map(|key| format!("r.{} = t.{}", key, key))
+        .collect();
+    let sql = format!(
+        "DELETE FROM {} t WHERE {} AND NOT EXISTS (SELECT 1 FROM {} r WHERE {})",
+        table,
+        scope,
+        staging,
+        matches.join(" AND ")
+    );
+    let result = sqlx::query(&sql)
+        .bind(scope_value)
+        .execute(conn)
+        .await
+        .map_err(|e| {
+            Failure::database(format!(
+                "登録しなかった{}をデータベースから削除するときにエラーが発生しました。{}",
+                label, e
+            ))
+        })?;
+
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,196 @@
+//! エクスポートしたファイル(成果物)に、brotliで圧縮したファイルと、SHA-256のチェックサムを記録した
+//! マニフェストを添える。
+//!
+//! マニフェストは`sha256sum`の出力と同じ形式で記録するため、`sha256sum -c`で検証できる。
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+/// brotliで圧縮したファイルの拡張子。
+pub const BROTLI_EXTENSION: &str = "br";
+
+/// マニフェストの拡張子。
+pub const MANIFEST_EXTENSION: &str = "sha256";
+
+/// 成果物に添えたファイル。
+#[derive(Debug)]
+pub struct ArtifactFiles {
+    /// brotliで圧縮したファイルのパス(圧縮しない場合はNone)
+    pub brotli: Option<PathBuf>,
+    /// マニフェストのパス
+    pub manifest: PathBuf,
+}
+
+/// ファイルのパスに拡張子を追加したパスを返す。
+///
+/// # Arguments
+///
+/// * `path` - ファイルのパス。
+/// * `extension` - 追加する拡張子。
+///
+/// # Returns
+///
+/// 拡張子を追加したパス。
+pub fn with_added_extension(path: &Path, extension: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_os_string();
+    path.push(".");
+    path.push(extension);
+
+    PathBuf::from(path)
+}
+
+/// ファイルのSHA-256を計算する。
+///
+/// # Arguments
+///
+/// * `path` - ファイルのパス。
+///
+/// # Returns
+///
+/// SHA-256の16進数表記。
+pub fn sha256_file(path: &Path) -> std::io::Result<String> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// ファイルをbrotliで圧縮したファイルを書き込む。
+///
+/// # Arguments
+///
+/// * `path` - 圧縮するファイルのパス。
+/// * `level` - brotliの圧縮レベル(0から11)。
+///
+/// # Returns
+///
+/// 圧縮したファイルのパス(圧縮するファイルのパスに拡張子`.br`を追加したパス)。
+pub fn write_brotli(path: &Path, level: u32) -> std::io::Result<PathBuf> {
+    let brotli = with_added_extension(path, BROTLI_EXTENSION);
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut writer =
+        brotli::CompressorWriter::new(BufWriter::new(File::create(&brotli)?), 64 * 1024, level, 22);
+    std::io::copy(&mut reader, &mut writer)?;
+    writer.into_inner().flush()?;
+
+    Ok(brotli)
+}
+
+/// 成果物にbrotliで圧縮したファイルとマニフェストを添える。
+///
+/// マニフェストには、成果物と圧縮したファイルのSHA-256を、ダウンロードするときのファイル名で記録する。
+/// 圧縮しない場合は、以前に書き込んだ圧縮したファイルを削除する。
+///
+/// # Arguments
+///
+/// * `path` - 成果物のパス。
+/// * `file_name` - 成果物をダウンロードするときのファイル名。
+/// * `brotli_level` - brotliの圧縮レベル。圧縮しない場合はNone。
+///
+/// # Returns
+///
+/// 成果物に添えたファイル。
+pub fn finish_artifact(
+    path: &Path,
+    file_name: &str,
+    brotli_level: Option<u32>,
+) -> std::io::Result<ArtifactFiles> {
+    let mut entries = vec![(sha256_file(path)?, file_name.to_string())];
+    let brotli = match brotli_level {
+        Some(level) => {
+            let brotli = write_brotli(path, level)?;
+            entries.push((
+                sha256_file(&brotli)?,
+                format!("{}.{}", file_name, BROTLI_EXTENSION),
+            ));
+            Some(brotli)
+        }
+        None => {
+            let stale = with_added_extension(path, BROTLI_EXTENSION);
+            if stale.exists() {
+                std::fs::remove_file(stale)?;
+            }
+            None
+        }
+    };
+    let manifest = with_added_extension(path, MANIFEST_EXTENSION);
+    std::fs::write(&manifest, encode_manifest(&entries))?;
+
+    Ok(ArtifactFiles { brotli, manifest })
+}
+
+/// マニフェストの内容を返す。
+///
+/// # Arguments
+///
+/// * `entries` - SHA-256とファイル名。
+///
+/// # Returns
+///
+/// `sha256sum`の出力と同じ形式のマニフェスト。
+fn encode_manifest(entries: &[(String, String)]) -> String {
+    entries
+        .iter()
+        .map(|(checksum, name)| format!("{}  {}\n", checksum, name))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sha256_matches_known_digest() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("abc.txt");
+        std::fs::write(&path, b"abc").unwrap();
+        assert_eq!(
+            sha256_file(&path).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn artifact_is_finished_with_brotli_and_manifest() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("cities-1.csv");
+        let content = "id,name\n".repeat(1000);
+        std::fs::write(&path, &content).unwrap();
+
+        let files = finish_artifact(&path, "cities.csv", Some(5)).unwrap();
+        let brotli = files.brotli.unwrap();
+        assert_eq!(brotli, directory.path().join("cities-1.csv.br"));
+        let mut decoded = String::new();
+        brotli::Decompressor::new(File::open(&brotli).unwrap(), 4096)
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, content);
+        let manifest = std::fs::read_to_string(files.manifest).unwrap();
+        assert_eq!(
+            manifest,
+            format!(
+                "{}  cities.csv\n{}  cities.csv.br\n",
+                sha256_file(&path).unwrap(),
+                sha256_file(&brotli).unwrap()
+            )
+        );
+
+        // 圧縮しない場合は、以前に書き込んだ圧縮したファイルを削除する
+        let files = finish_artifact(&path, "cities.csv", None).unwrap();
+        assert!(files.brotli.is_none());
+        assert!(!brotli.exists());
+        let manifest = std::fs::read_to_string(files.manifest).unwrap();
+        assert_eq!(manifest.lines().count(), 1);
+    }
+}
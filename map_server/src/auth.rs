@@ -0,0 +1,214 @@
+use base64::Engine;
+use serde::Deserialize;
+
+use crate::errors::ApiError;
+
+/// APIキーを指定するリクエストヘッダーの名前。
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// 管理用のエンドポイントをまとめたスコープのパス。
+pub const ADMIN_SCOPE: &str = "/admin";
+
+/// 管理用のエンドポイントの認証で使用する保護領域の名前。
+const ADMIN_REALM: &str = "map_server admin";
+
+/// APIキー。
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiKey {
+    /// APIキーの利用者を識別する名前
+    pub name: String,
+    /// APIキー
+    pub key: String,
+    /// APIキーを有効にするか
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// APIキーによる認証の設定。
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ApiKeyAuth {
+    /// APIキーによる認証を有効にするか
+    pub enabled: bool,
+    /// APIキーを指定しなくてもリクエストできるパス
+    pub public_paths: Vec<String>,
+    /// リクエストを許可するAPIキー
+    pub keys: Vec<ApiKey>,
+}
+
+impl Default for ApiKeyAuth {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            public_paths: vec![
+                "/health_check".to_string(),
+                "/health/live".to_string(),
+                "/health/ready".to_string(),
+            ],
+            keys: vec![],
+        }
+    }
+}
+
+impl ApiKeyAuth {
+    /// リクエストを許可するか確認する。
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - リクエストされたパス。
+    /// * `presented` - リクエストヘッダーに指定されたAPIキー。
+    ///
+    /// # Returns
+    ///
+    /// リクエストを許可する場合はOk。APIキーが指定されていない場合や登録されていない場合は
+    /// 401 Unauthorized、APIキーが無効にされている場合は403 Forbiddenを示すエラー。
+    pub fn authenticate(&self, path: &str, presented: Option<&str>) -> Result<(), ApiError> {
+        if !self.enabled || self.public_paths.iter().any(|public| public == path) {
+            return Ok(());
+        }
+        let presented = presented.ok_or(ApiError::MissingApiKey)?;
+        match self
+            .keys
+            .iter()
+            .find(|api_key| constant_time_eq(api_key.key.as_bytes(), presented.as_bytes()))
+        {
+            Some(api_key) if api_key.enabled => Ok(()),
+            Some(api_key) => {
+                tracing::warn!(
+                    "無効にされたAPIキー({})によるリクエストを拒否",
+                    api_key.name
+                );
+                Err(ApiError::DisabledApiKey)
+            }
+            None => Err(ApiError::InvalidApiKey),
+        }
+    }
+}
+
+/// パスが管理用のエンドポイントのスコープに含まれるか確認する。
+///
+/// # Arguments
+///
+/// * `path` - リクエストされたパス。
+///
+/// # Returns
+///
+/// 管理用のエンドポイントのパスの場合はtrue。
+pub fn is_admin_path(path: &str) -> bool {
+    match path.strip_prefix(ADMIN_SCOPE) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+/// 管理用のエンドポイントの認証の設定。
+///
+/// 管理用のエンドポイントは、APIキーによる認証とは別に、Bearerトークン、またはBasic認証のユーザー名と
+/// パスワードで認証する。どちらも設定されていない場合は、管理用のエンドポイントを公開しない。
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AdminAuth {
+    /// Bearer認証で指定するトークン
+    pub token: Option<String>,
+    /// Basic認証のユーザー名
+    pub username: Option<String>,
+    /// Basic認証のパスワード
+    pub password: Option<String>,
+}
+
+impl AdminAuth {
+    /// 管理用のエンドポイントの認証情報が設定されているか確認する。
+    pub fn is_configured(&self) -> bool {
+        self.token.is_some() || self.basic_credentials().is_some()
+    }
+
+    /// Basic認証のユーザー名とパスワードを返す。
+    fn basic_credentials(&self) -> Option<(&str, &str)> {
+        match (self.username.as_deref(), self.password.as_deref()) {
+            (Some(username), Some(password)) => Some((username, password)),
+            _ => None,
+        }
+    }
+
+    /// 認証に失敗したときに、`WWW-Authenticate`レスポンスヘッダーに設定する値を返す。
+    fn challenge(&self) -> String {
+        let mut schemes = vec![];
+        if self.token.is_some() {
+            schemes.push(format!("Bearer realm=\"{}\"", ADMIN_REALM));
+        }
+        if self.basic_credentials().is_some() {
+            schemes.push(format!(
+                "Basic realm=\"{}\", charset=\"UTF-8\"",
+                ADMIN_REALM
+            ));
+        }
+        schemes.join(", ")
+    }
+
+    /// 管理用のエンドポイントへのリクエストを許可するか確認する。
+    ///
+    /// # Arguments
+    ///
+    /// * `authorization` - リクエストヘッダーに指定された`Authorization`の値。
+    ///
+    /// # Returns
+    ///
+    /// リクエストを許可する場合はOk。認証情報が設定されていない場合は404 Not Found、認証情報が
+    /// 指定されていない場合や一致しない場合は401 Unauthorizedを示すエラー。
+    pub fn authenticate(&self, authorization: Option<&str>) -> Result<(), ApiError> {
+        if !self.is_configured() {
+            return Err(ApiError::NotFound);
+        }
+        let authorized = match authorization.and_then(|value| value.split_once(' ')) {
+            Some((scheme, token)) if scheme.eq_ignore_ascii_case("bearer") => self
+                .token
+                .as_deref()
+                .map(|expected| constant_time_eq(expected.as_bytes(), token.trim().as_bytes()))
+                .unwrap_or(false),
+            Some((scheme, credentials)) if scheme.eq_ignore_ascii_case("basic") => {
+                let decoded = base64::engine::general_purpose::STANDARD
+                    .decode(credentials.trim())
+                    .ok()
+                    .and_then(|decoded| String::from_utf8(decoded).ok());
+                match (self.basic_credentials(), decoded) {
+                    (Some((username, password)), Some(decoded)) => constant_time_eq(
+                        format!("{}:{}", username, password).as_bytes(),
+                        decoded.as_bytes(),
+                    ),
+                    _ => false,
+                }
+            }
+            _ => false,
+        };
+        if !authorized {
+            if authorization.is_some() {
+                tracing::warn!("管理用のエンドポイントへの認証に失敗したリクエストを拒否");
+            }
+            return Err(ApiError::AdminUnauthorized(self.challenge()));
+        }
+
+        Ok(())
+    }
+}
+
+/// 比較にかかる時間から内容を推測されないように、バイト列を比較する。
+///
+/// # Arguments
+///
+/// * `a` - 比較するバイト列。
+/// * `b` - 比較するバイト列。
+///
+/// # Returns
+///
+/// バイト列が一致する場合はtrue。
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -0,0 +1,282 @@
+//! 配信しているレイヤーの一覧と、レイヤーのメタデータ及びデータセットを登録した履歴を返すエンドポイント。
+
+use std::collections::HashMap;
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use database::imports::{list_imports, ImportRecord};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tracing::Instrument;
+
+use utils::prefecture::PrefectureCode;
+use utils::{EPSG_WEB_MERCATOR, EPSG_WGS84};
+
+use crate::custom_layers::CustomLayers;
+use crate::errors::ApiError;
+use crate::layers::Layer;
+use crate::telemetries::query_span;
+
+/// 一覧に含める組み込みのレイヤーの、レイヤー名(テーブル名)とジオメトリの種類(GeoJSONのジオメトリの種類の名前)。
+const CATALOG_LAYERS: [(&str, &str); 8] = [
+    ("prefectures", "MultiPolygon"),
+    ("cities", "Polygon"),
+    ("post_offices", "Point"),
+    ("railways", "MultiLineString"),
+    ("stations", "MultiLineString"),
+    ("schools", "Point"),
+    ("medical_institutions", "Point"),
+    ("rivers", "MultiLineString"),
+];
+
+/// レイヤーのメタデータ。
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LayerMetadata {
+    /// レイヤー名
+    pub name: String,
+    /// カスタムレイヤーか
+    pub custom: bool,
+    /// ジオメトリの種類(カスタムレイヤーの場合はNone)
+    pub geometry_type: Option<&'static str>,
+    /// フィーチャーの数(カスタムレイヤーの場合はNone)
+    pub features: Option<i64>,
+    /// フィーチャーの範囲(west、south、east、northの順のWGS84経緯度、フィーチャーがない場合はNone)
+    pub extent: Option<[f64; 4]>,
+    /// 最後にデータセットを登録した日時(UTC、RFC 3339形式、登録の履歴がない場合はNone)
+    pub last_imported_at: Option<String>,
+    /// 出典
+    pub attribution: String,
+    /// ベクタータイルのURLテンプレート(ベクタータイルを配信しない場合はNone)
+    pub vector_tiles: Option<String>,
+    /// GeoJSONのタイルのURLテンプレート
+    pub geojson_tiles: String,
+}
+
+/// 範囲の各値を、範囲に変換する。
+///
+/// # Returns
+///
+/// 範囲。いずれかの値がNoneの場合はNone。
+fn to_extent(
+    west: Option<f64>,
+    south: Option<f64>,
+    east: Option<f64>,
+    north: Option<f64>,
+) -> Option<[f64; 4]> {
+    Some([west?, south?, east?, north?])
+}
+
+/// レイヤーごとに、最後にデータセットを登録した日時を取得する。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+///
+/// # Returns
+///
+/// レイヤー名と、最後にデータセットを登録した日時(UTC、RFC 3339形式)。
+async fn last_imports(pool: &PgPool) -> sqlx::Result<HashMap<String, String>> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            layer,
+            to_char(max(imported_at) AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24:MI:SS"Z"') as imported_at
+        FROM imports
+        GROUP BY layer
+        "#,
+    )
+    .fetch_all(pool)
+    .instrument(query_span("SELECT last imports"))
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|row| row.imported_at.map(|imported_at| (row.layer, imported_at)))
+        .collect())
+}
+
+/// 組み込みのレイヤーのフィーチャーの数と範囲を取得する。
+///
+/// # Arguments
+///
+/// * `table` - レイヤーのテーブル名。
+/// * `pool` - データベースコネクションプール。
+///
+/// # Returns
+///
+/// フィーチャーの数と範囲(WGS84経緯度)。
+async fn layer_summary(table: &str, pool: &PgPool) -> sqlx::Result<(i64, Option<[f64; 4]>)> {
+    let sql = format!(
+        r#"
+        SELECT
+            e.features,
+            ST_XMin(e.geom) as west, ST_YMin(e.geom) as south,
+            ST_XMax(e.geom) as east, ST_YMax(e.geom) as north
+        FROM (
+            SELECT
+                COUNT(*) as features,
+                ST_Transform(ST_SetSRID(ST_Extent(geom)::geometry, $1), $2) as geom
+            FROM {}
+        ) e
+        "#,
+        table
+    );
+    let (features, west, south, east, north): (
+        i64,
+        Option<f64>,
+        Option<f64>,
+        Option<f64>,
+        Option<f64>,
+    ) = sqlx::query_as(&sql)
+        .bind(EPSG_WEB_MERCATOR)
+        .bind(EPSG_WGS84)
+        .fetch_one(pool)
+        .instrument(query_span("SELECT layer summary"))
+        .await?;
+
+    Ok((features, to_extent(west, south, east, north)))
+}
+
+#[tracing::instrument(name = "Layers", skip(req, pool, custom_layers))]
+pub async fn layers(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    custom_layers: web::Data<CustomLayers>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let base_url = {
+        let info = req.connection_info();
+        format!("{}://{}", info.scheme(), info.host())
+    };
+    let last_imports = last_imports(pool.as_ref()).await.map_err(ApiError::from)?;
+    let mut layers = Vec::new();
+    for (name, geometry_type) in CATALOG_LAYERS {
+        let (features, extent) = layer_summary(name, pool.as_ref())
+            .await
+            .map_err(ApiError::from)?;
+        let layer = Layer::from_name(name);
+        let attribution = layer.unwrap_or(Layer::Cities).attribution();
+        layers.push(LayerMetadata {
+            name: name.to_string(),
+            custom: false,
+            geometry_type: Some(geometry_type),
+            features: Some(features),
+            extent,
+            last_imported_at: last_imports.get(name).cloned(),
+            attribution: attribution.to_string(),
+            vector_tiles: layer
+                .map(|layer| format!("{}/mvt/{}/{{z}}/{{x}}/{{y}}.pbf", base_url, layer.name())),
+            geojson_tiles: format!("{}/tiles/{}/{{z}}/{{x}}/{{y}}", base_url, name),
+        });
+    }
+    for name in custom_layers.names() {
+        let custom = custom_layers.get(name).unwrap();
+        // すべてのパラメーターに既定値がある場合に限り、既定値で範囲を取得する
+        let extent = match custom.param_values(&HashMap::new()) {
+            Ok(values) => {
+                let (west, south, east, north) = custom
+                    .extent(pool.as_ref(), &values)
+                    .await
+                    .map_err(ApiError::from)?;
+                to_extent(west, south, east, north)
+            }
+            Err(_) => None,
+        };
+        layers.push(LayerMetadata {
+            name: name.to_string(),
+            custom: true,
+            geometry_type: None,
+            features: None,
+            extent,
+            last_imported_at: None,
+            attribution: custom.layer.attribution.clone(),
+            vector_tiles: Some(format!("{}/mvt/{}/{{z}}/{{x}}/{{y}}.pbf", base_url, name)),
+            geojson_tiles: format!("{}/tiles/{}/{{z}}/{{x}}/{{y}}", base_url, name),
+        });
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "layers": layers })))
+}
+
+/// 登録の履歴を返すときの、既定の件数。
+const DEFAULT_IMPORTS_LIMIT: u32 = 100;
+
+/// 登録の履歴を返すときの、最大の件数。
+const MAX_IMPORTS_LIMIT: u32 = 1000;
+
+/// 登録の履歴を絞り込むクエリパラメーター。
+#[derive(Debug, Deserialize)]
+pub struct ImportsQuery {
+    /// 履歴を返すレイヤー名
+    layer: Option<String>,
+    /// 履歴を返す都道府県コード
+    code: Option<String>,
+    /// 返す履歴の件数
+    limit: Option<u32>,
+}
+
+/// データセットを登録した履歴。
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportEntry {
+    /// 履歴のID
+    pub id: i64,
+    /// 登録したレイヤー名
+    pub layer: String,
+    /// 登録した都道府県コード(都道府県ごとに登録しないデータセットの場合はNone)
+    pub prefecture_code: Option<String>,
+    /// 登録したフィーチャーの数
+    pub features: i32,
+    /// データセットの種類(`N03`など、記録していない場合はNone)
+    pub dataset: Option<String>,
+    /// 登録したファイルの名前(記録していない場合はNone)
+    pub source_file: Option<String>,
+    /// データセットの年度(西暦、判断できなかった場合はNone)
+    pub source_year: Option<i16>,
+    /// 登録した日時(UTC、RFC 3339形式)
+    pub imported_at: String,
+}
+
+impl From<ImportRecord> for ImportEntry {
+    fn from(record: ImportRecord) -> Self {
+        Self {
+            id: record.id,
+            layer: record.layer,
+            prefecture_code: record.prefecture_code,
+            features: record.features,
+            dataset: record.dataset,
+            source_file: record.source_file,
+            source_year: record.source_year,
+            imported_at: record.imported_at,
+        }
+    }
+}
+
+/// データセットを登録した履歴を、新しい順に返す。
+///
+/// クエリパラメーター`layer`と`code`で、レイヤーと都道府県を絞り込む。
+#[tracing::instrument(name = "Imports", skip(pool))]
+pub async fn imports(
+    query: web::Query<ImportsQuery>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if let Some(code) = &query.code {
+        code.parse::<PrefectureCode>()
+            .map_err(|_| ApiError::InvalidQuery)?;
+    }
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_IMPORTS_LIMIT)
+        .clamp(1, MAX_IMPORTS_LIMIT);
+    let records = list_imports(
+        pool.as_ref(),
+        query.layer.as_deref(),
+        query.code.as_deref(),
+        limit as i64,
+    )
+    .instrument(query_span("SELECT imports"))
+    .await
+    .map_err(ApiError::from)?;
+    let imports: Vec<ImportEntry> = records.into_iter().map(ImportEntry::from).collect();
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "imports": imports })))
+}
@@ -0,0 +1,150 @@
+use std::time::Duration;
+
+use actix_web::web;
+use serde_json::json;
+
+use crate::tile_cache::{TileCache, COMBINED_TILE_LAYER, COMBINED_TILE_LAYERS};
+
+/// CDNのキャッシュ削除を再試行するまでの最初の待機時間。
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// タイルキャッシュを破棄したときに、外部CDNのキャッシュ削除Webhookを呼び出す。
+pub struct CdnPurger {
+    /// HTTPクライアント
+    client: reqwest::Client,
+    /// キャッシュ削除WebhookのURLテンプレート(設定されていない場合はNone)
+    ///
+    /// URLテンプレートに含まれる`{layer}`は、レイヤー名に置き換える。
+    url_template: Option<String>,
+    /// キャッシュ削除に失敗したときに再試行する回数
+    max_retries: u32,
+}
+
+impl CdnPurger {
+    /// CDNのキャッシュ削除を構築する。
+    ///
+    /// # Arguments
+    ///
+    /// * `url_template` - キャッシュ削除WebhookのURLテンプレート。Noneの場合はCDNのキャッシュを削除しない。
+    /// * `max_retries` - キャッシュ削除に失敗したときに再試行する回数。
+    ///
+    /// # Returns
+    ///
+    /// CDNのキャッシュ削除。
+    pub fn new(url_template: Option<String>, max_retries: u32) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url_template,
+            max_retries,
+        }
+    }
+
+    /// CDNのキャッシュ削除Webhookが設定されているか確認する。
+    pub fn is_enabled(&self) -> bool {
+        self.url_template.is_some()
+    }
+
+    /// レイヤーごとに、CDNのキャッシュ削除Webhookを呼び出す。
+    ///
+    /// キャッシュ削除に失敗した場合は、待機時間を倍にしながら再試行する。呼び出しの結果は、
+    /// `audit`ターゲットでトレースする。
+    ///
+    /// # Arguments
+    ///
+    /// * `layers` - CDNのキャッシュを削除するレイヤー名を格納したスライス。
+    pub async fn purge(&self, layers: &[&str]) {
+        let url_template = match self.url_template.as_ref() {
+            Some(url_template) => url_template,
+            None => return,
+        };
+        for layer in layers {
+            let url = url_template.replace("{layer}", layer);
+            let mut backoff = INITIAL_BACKOFF;
+            for attempt in 1..=self.max_retries + 1 {
+                let result = self
+                    .client
+                    .post(&url)
+                    .json(&json!({ "layer": layer }))
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status());
+                match result {
+                    Ok(response) => {
+                        tracing::info!(
+                            target: "audit",
+                            layer,
+                            url = url.as_str(),
+                            attempt,
+                            status = response.status().as_u16(),
+                            "CDNのキャッシュを削除"
+                        );
+                        break;
+                    }
+                    Err(e) if attempt <= self.max_retries => {
+                        tracing::warn!(
+                            target: "audit",
+                            layer,
+                            url = url.as_str(),
+                            attempt,
+                            error = %e,
+                            "CDNのキャッシュ削除に失敗したため再試行"
+                        );
+                        actix_web::rt::time::sleep(backoff).await;
+                        backoff *= 2;
+                    }
+                    Err(e) => {
+                        tracing::error!(
+                            target: "audit",
+                            layer,
+                            url = url.as_str(),
+                            attempt,
+                            error = %e,
+                            "CDNのキャッシュを削除できません"
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// レイヤーのタイルをキャッシュから破棄して、CDNのキャッシュ削除を要求する。
+///
+/// CDNのキャッシュ削除は時間がかかる可能性があるため、レスポンスを返した後で実行する。
+/// 1つにまとめたベクタータイルに含めるレイヤーを破棄する場合は、まとめたベクタータイルも破棄する。
+///
+/// # Arguments
+///
+/// * `cache` - タイルキャッシュ。
+/// * `purger` - CDNのキャッシュ削除。
+/// * `layers` - タイルを破棄するレイヤー名を格納したベクタ。
+///
+/// # Returns
+///
+/// 破棄したタイルの数と、CDNのキャッシュ削除を要求したか。
+pub fn refresh_layers(
+    cache: &TileCache,
+    purger: &web::Data<CdnPurger>,
+    mut layers: Vec<String>,
+) -> (usize, bool) {
+    if layers
+        .iter()
+        .any(|layer| COMBINED_TILE_LAYERS.contains(&layer.as_str()))
+    {
+        layers.push(COMBINED_TILE_LAYER.to_string());
+    }
+    let invalidated = layers
+        .iter()
+        .map(|layer| cache.invalidate(Some(layer)))
+        .sum();
+    let purge_requested = purger.is_enabled();
+    if purge_requested {
+        let purger = purger.clone();
+        actix_web::rt::spawn(async move {
+            let layers: Vec<&str> = layers.iter().map(|layer| layer.as_str()).collect();
+            purger.purge(&layers).await
+        });
+    }
+
+    (invalidated, purge_requested)
+}
@@ -0,0 +1,448 @@
+use std::io::Write;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use actix_web::body::{BodySize, BoxBody, MessageBody};
+use actix_web::dev::ServiceResponse;
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::http::StatusCode;
+use actix_web::web::{self, Bytes};
+use serde::Deserialize;
+
+use crate::events::EVENT_STREAM_CONTENT_TYPE;
+
+/// レスポンスボディの圧縮形式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// brotli
+    Brotli,
+    /// gzip
+    Gzip,
+}
+
+impl Encoding {
+    /// Content-Encodingヘッダーに設定する値を返す。
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+        }
+    }
+}
+
+/// レスポンスボディの圧縮設定。
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct Compression {
+    /// 圧縮するレスポンスボディの最小サイズ(バイト)
+    pub min_size: usize,
+    /// gzipの圧縮レベル(0から9)
+    pub gzip_level: u32,
+    /// brotliの圧縮レベル(0から11)
+    pub brotli_level: u32,
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self {
+            min_size: 1024,
+            gzip_level: 6,
+            brotli_level: 5,
+        }
+    }
+}
+
+impl Compression {
+    /// レスポンスボディを圧縮する。
+    ///
+    /// # Arguments
+    ///
+    /// * `encoding` - 圧縮形式。
+    /// * `body` - レスポンスボディ。
+    ///
+    /// # Returns
+    ///
+    /// 圧縮したレスポンスボディ。
+    pub fn encode(&self, encoding: Encoding, body: &[u8]) -> std::io::Result<Vec<u8>> {
+        match encoding {
+            Encoding::Brotli => {
+                let mut writer =
+                    brotli::CompressorWriter::new(Vec::new(), 4096, self.brotli_level, 22);
+                writer.write_all(body)?;
+                writer.flush()?;
+                Ok(writer.into_inner())
+            }
+            Encoding::Gzip => {
+                let mut encoder = flate2::write::GzEncoder::new(
+                    Vec::new(),
+                    flate2::Compression::new(self.gzip_level),
+                );
+                encoder.write_all(body)?;
+                encoder.finish()
+            }
+        }
+    }
+
+    /// Accept-Encodingヘッダーとレスポンスから、レスポンスボディの圧縮形式を決定する。
+    ///
+    /// ストリーミングするレスポンスはサイズが分からないため、最小サイズに関わらず圧縮する。ただし、
+    /// Server-Sent Eventsのレスポンスは、圧縮するとイベントの送信が遅れるため圧縮しない。
+    ///
+    /// # Arguments
+    ///
+    /// * `accept_encoding` - リクエストのAccept-Encodingヘッダーの値。
+    /// * `res` - レスポンス。
+    ///
+    /// # Returns
+    ///
+    /// 圧縮形式。レスポンスボディを圧縮しない場合はNone。
+    fn negotiate(&self, accept_encoding: &str, res: &ServiceResponse) -> Option<Encoding> {
+        // ボディを含まないレスポンス、及び圧縮済みのレスポンスは圧縮しない
+        // 範囲を指定して取得できるレスポンスは、範囲が圧縮していない内容の位置を示すため圧縮しない
+        if res.status() == StatusCode::NOT_MODIFIED
+            || res.status() == StatusCode::NO_CONTENT
+            || res.headers().contains_key(header::CONTENT_ENCODING)
+            || res.headers().contains_key(header::ACCEPT_RANGES)
+        {
+            return None;
+        }
+        match res.response().body().size() {
+            BodySize::Sized(size) if self.min_size as u64 <= size => {}
+            BodySize::Stream if !is_event_stream(res) => {}
+            _ => return None,
+        }
+
+        accepted_encoding(accept_encoding)
+    }
+}
+
+/// レスポンスがServer-Sent Eventsであるか確認する。
+fn is_event_stream(res: &ServiceResponse) -> bool {
+    res.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with(EVENT_STREAM_CONTENT_TYPE))
+}
+
+/// ストリーミングするレスポンスボディを、チャンクごとに圧縮するエンコーダー。
+enum StreamEncoder {
+    /// brotli
+    Brotli(Box<brotli::CompressorWriter<Vec<u8>>>),
+    /// gzip
+    Gzip(flate2::write::GzEncoder<Vec<u8>>),
+}
+
+impl StreamEncoder {
+    /// エンコーダーを構築する。
+    ///
+    /// # Arguments
+    ///
+    /// * `compression` - レスポンスボディの圧縮設定。
+    /// * `encoding` - 圧縮形式。
+    fn new(compression: &Compression, encoding: Encoding) -> Self {
+        match encoding {
+            Encoding::Brotli => StreamEncoder::Brotli(Box::new(brotli::CompressorWriter::new(
+                Vec::new(),
+                4096,
+                compression.brotli_level,
+                22,
+            ))),
+            Encoding::Gzip => StreamEncoder::Gzip(flate2::write::GzEncoder::new(
+                Vec::new(),
+                flate2::Compression::new(compression.gzip_level),
+            )),
+        }
+    }
+
+    /// チャンクを圧縮する。
+    ///
+    /// # Arguments
+    ///
+    /// * `chunk` - レスポンスボディのチャンク。
+    ///
+    /// # Returns
+    ///
+    /// これまでに圧縮が完了したバイト列。エンコーダーがバッファーに保持している場合は空。
+    fn write(&mut self, chunk: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            StreamEncoder::Brotli(writer) => {
+                writer.write_all(chunk)?;
+                Ok(std::mem::take(writer.get_mut()))
+            }
+            StreamEncoder::Gzip(encoder) => {
+                encoder.write_all(chunk)?;
+                Ok(std::mem::take(encoder.get_mut()))
+            }
+        }
+    }
+
+    /// 圧縮を終了する。
+    ///
+    /// # Returns
+    ///
+    /// エンコーダーがバッファーに保持していた残りのバイト列。
+    fn finish(self) -> std::io::Result<Vec<u8>> {
+        match self {
+            StreamEncoder::Brotli(writer) => Ok(writer.into_inner()),
+            StreamEncoder::Gzip(encoder) => encoder.finish(),
+        }
+    }
+}
+
+/// ストリーミングするレスポンスボディを、チャンクごとに圧縮しながら送信するレスポンスボディ。
+///
+/// 元のレスポンスボディのエラーは、圧縮したレスポンスボディのエラーとして返し、ストリームを途中で終了する。
+struct EncodedBody {
+    /// 元のレスポンスボディ
+    body: BoxBody,
+    /// エンコーダー(圧縮を終了した場合はNone)
+    encoder: Option<StreamEncoder>,
+}
+
+impl MessageBody for EncodedBody {
+    type Error = std::io::Error;
+
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let this = self.get_mut();
+        loop {
+            let encoder = match this.encoder.as_mut() {
+                Some(encoder) => encoder,
+                None => return Poll::Ready(None),
+            };
+            let encoded = match Pin::new(&mut this.body).poll_next(cx) {
+                Poll::Ready(Some(Ok(chunk))) => encoder.write(&chunk)?,
+                Poll::Ready(Some(Err(e))) => {
+                    this.encoder = None;
+                    return Poll::Ready(Some(Err(std::io::Error::other(e.to_string()))));
+                }
+                Poll::Ready(None) => this.encoder.take().unwrap().finish()?,
+                Poll::Pending => return Poll::Pending,
+            };
+            if !encoded.is_empty() {
+                return Poll::Ready(Some(Ok(Bytes::from(encoded))));
+            }
+        }
+    }
+}
+
+/// Accept-Encodingヘッダーから、対応する圧縮形式と品質値を返す。
+///
+/// # Arguments
+///
+/// * `accept_encoding` - Accept-Encodingヘッダーの値。
+///
+/// # Returns
+///
+/// 圧縮形式と品質値。品質値が0の圧縮形式は含めない。
+fn encoding_qualities(accept_encoding: &str) -> impl Iterator<Item = (Encoding, f32)> + '_ {
+    accept_encoding.split(',').filter_map(|item| {
+        let mut params = item.split(';');
+        let name = params
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+        let quality = params
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|value| value.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        let encoding = match name.as_str() {
+            "br" => Encoding::Brotli,
+            "gzip" => Encoding::Gzip,
+            _ => return None,
+        };
+
+        (0.0 < quality).then_some((encoding, quality))
+    })
+}
+
+/// Accept-Encodingヘッダーから、クライアントが受け入れる圧縮形式を選択する。
+///
+/// brotliとgzipの両方を受け入れる場合は、品質値が大きい方を選択し、品質値が同じ場合はbrotliを選択する。
+///
+/// # Arguments
+///
+/// * `accept_encoding` - Accept-Encodingヘッダーの値。
+///
+/// # Returns
+///
+/// 圧縮形式。クライアントがいずれの圧縮形式も受け入れない場合はNone。
+fn accepted_encoding(accept_encoding: &str) -> Option<Encoding> {
+    let mut selected: Option<(Encoding, f32)> = None;
+    for (encoding, quality) in encoding_qualities(accept_encoding) {
+        selected = match selected {
+            Some((_, current)) if quality <= current && encoding == Encoding::Gzip => selected,
+            Some((_, current)) if quality < current => selected,
+            _ => Some((encoding, quality)),
+        };
+    }
+
+    selected.map(|(encoding, _)| encoding)
+}
+
+/// クライアントが圧縮形式を受け入れるか確認する。
+///
+/// # Arguments
+///
+/// * `accept_encoding` - Accept-Encodingヘッダーの値。
+/// * `encoding` - 圧縮形式。
+///
+/// # Returns
+///
+/// クライアントが圧縮形式を受け入れる場合はtrue。
+pub fn accepts_encoding(accept_encoding: &str, encoding: Encoding) -> bool {
+    encoding_qualities(accept_encoding).any(|(accepted, _)| accepted == encoding)
+}
+
+/// クライアントが受け入れる形式でレスポンスボディを圧縮する。
+///
+/// ストリーミングするレスポンスは、レスポンスボディ全体を保持せずに、チャンクごとに圧縮しながら送信する。
+/// 圧縮したレスポンスのETagは、圧縮していないレスポンスと区別するため弱いETagに変換する。
+///
+/// # Arguments
+///
+/// * `compression` - レスポンスボディの圧縮設定。
+/// * `accept_encoding` - リクエストのAccept-Encodingヘッダーの値。
+/// * `res` - レスポンス。
+///
+/// # Returns
+///
+/// 圧縮したレスポンス。圧縮しない場合は元のレスポンス。
+pub async fn compress_response(
+    compression: Compression,
+    accept_encoding: Option<String>,
+    res: ServiceResponse,
+) -> Result<ServiceResponse, actix_web::Error> {
+    let encoding = match accept_encoding {
+        Some(accept_encoding) => compression.negotiate(&accept_encoding, &res),
+        None => None,
+    };
+    let mut res = match encoding {
+        Some(encoding) => {
+            let (req, res) = res.into_parts();
+            let (mut res, body) = res.into_parts();
+            let body = match body.size() {
+                BodySize::Stream => BoxBody::new(EncodedBody {
+                    body,
+                    encoder: Some(StreamEncoder::new(&compression, encoding)),
+                }),
+                _ => {
+                    let body = actix_web::body::to_bytes(body)
+                        .await
+                        .map_err(actix_web::error::ErrorInternalServerError)?;
+                    let encoded = web::block(move || compression.encode(encoding, &body))
+                        .await?
+                        .map_err(actix_web::error::ErrorInternalServerError)?;
+                    BoxBody::new(encoded)
+                }
+            };
+            let headers = res.headers_mut();
+            headers.insert(
+                header::CONTENT_ENCODING,
+                HeaderValue::from_static(encoding.name()),
+            );
+            headers.remove(header::CONTENT_LENGTH);
+            let weak_etag = headers
+                .get(header::ETAG)
+                .and_then(|etag| etag.to_str().ok())
+                .filter(|etag| !etag.starts_with("W/"))
+                .and_then(|etag| HeaderValue::from_str(&format!("W/{}", etag)).ok());
+            if let Some(weak_etag) = weak_etag {
+                headers.insert(header::ETAG, weak_etag);
+            }
+            ServiceResponse::new(req, res.set_body(body))
+        }
+        None => res,
+    };
+    res.headers_mut()
+        .append(header::VARY, HeaderValue::from_static("accept-encoding"));
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+
+    /// 圧縮したバイト列を展開する。
+    fn decode(encoding: Encoding, bytes: &[u8]) -> Vec<u8> {
+        let mut decoded = Vec::new();
+        match encoding {
+            Encoding::Brotli => brotli::Decompressor::new(bytes, 4096)
+                .read_to_end(&mut decoded)
+                .unwrap(),
+            Encoding::Gzip => flate2::read::GzDecoder::new(bytes)
+                .read_to_end(&mut decoded)
+                .unwrap(),
+        };
+
+        decoded
+    }
+
+    #[test]
+    fn accepted_encoding_prefers_higher_quality() {
+        assert_eq!(accepted_encoding("gzip, br"), Some(Encoding::Brotli));
+        assert_eq!(accepted_encoding("br, gzip"), Some(Encoding::Brotli));
+        assert_eq!(accepted_encoding("br;q=0.5, gzip"), Some(Encoding::Gzip));
+        assert_eq!(
+            accepted_encoding("gzip;q=0.8, br;q=0.9"),
+            Some(Encoding::Brotli)
+        );
+        assert_eq!(
+            accepted_encoding("deflate, GZIP ; q=0.3"),
+            Some(Encoding::Gzip)
+        );
+    }
+
+    #[test]
+    fn accepted_encoding_ignores_unsupported_or_refused_encodings() {
+        assert_eq!(accepted_encoding(""), None);
+        assert_eq!(accepted_encoding("identity, deflate"), None);
+        assert_eq!(accepted_encoding("br;q=0, gzip;q=0"), None);
+        assert_eq!(accepted_encoding("br;q=0, gzip"), Some(Encoding::Gzip));
+    }
+
+    #[test]
+    fn accepts_encoding_checks_each_encoding() {
+        assert!(accepts_encoding("gzip;q=1.0, br;q=0.1", Encoding::Brotli));
+        assert!(!accepts_encoding("gzip, br;q=0", Encoding::Brotli));
+        assert!(!accepts_encoding("identity", Encoding::Gzip));
+    }
+
+    #[test]
+    fn encode_round_trips() {
+        let compression = Compression::default();
+        let body = r#"{"type":"FeatureCollection","features":[]}"#.repeat(100);
+        for encoding in [Encoding::Brotli, Encoding::Gzip] {
+            let encoded = compression.encode(encoding, body.as_bytes()).unwrap();
+            assert!(encoded.len() < body.len());
+            assert_eq!(decode(encoding, &encoded), body.as_bytes());
+        }
+    }
+
+    #[test]
+    fn stream_encoder_round_trips_chunks() {
+        let compression = Compression::default();
+        let chunks: Vec<String> = (0..10)
+            .map(|i| format!(r#"{{"id":{}}},"#, i).repeat(1000))
+            .collect();
+        for encoding in [Encoding::Brotli, Encoding::Gzip] {
+            let mut encoder = StreamEncoder::new(&compression, encoding);
+            let mut encoded = Vec::new();
+            for chunk in &chunks {
+                encoded.extend(encoder.write(chunk.as_bytes()).unwrap());
+            }
+            encoded.extend(encoder.finish().unwrap());
+            assert_eq!(decode(encoding, &encoded), chunks.concat().as_bytes());
+        }
+    }
+}
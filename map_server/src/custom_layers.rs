@@ -0,0 +1,503 @@
+use std::collections::HashMap;
+
+use actix_web::web::Bytes;
+use anyhow::anyhow;
+use futures_util::Stream;
+use serde::Deserialize;
+use sqlx::postgres::PgArguments;
+use sqlx::query::QueryAs;
+use sqlx::{Arguments, Column, Either, Executor, PgPool, Postgres, Statement, TypeInfo};
+use tracing::Instrument;
+
+use utils::{EPSG_WEB_MERCATOR, EPSG_WGS84};
+
+use crate::config::TileSettings;
+use crate::errors::ApiError;
+use crate::streaming::stream_feature_collection;
+use crate::telemetries::query_span;
+use crate::tile_cache::{COMBINED_TILE_LAYER, TILE_LAYERS};
+
+/// レイヤー名の最大文字数。
+const MAX_LAYER_NAME_LENGTH: usize = 40;
+
+/// `/features/{layer}`がジオメトリの指定に使用するため、パラメーターの名前に使用できないクエリパラメーター。
+const RESERVED_PARAM_NAMES: [&str; 2] = ["crs", "precision"];
+
+/// カスタムレイヤーのSQLに渡すパラメーター。
+#[derive(Debug, Clone, Deserialize)]
+pub struct LayerParam {
+    /// パラメーターを指定するクエリパラメーターの名前
+    pub name: String,
+    /// クエリパラメーターが指定されていない場合の値(設定されていない場合は指定を必須にする)
+    #[serde(default)]
+    pub default: Option<String>,
+}
+
+/// テーブルの代わりに、SQLで定義したフィーチャーを配信するカスタムレイヤー。
+///
+/// SQLは、ジオメトリ(Webメルカトル座標)を`geom`列で返すSELECT文で定義する。SQLで`$1`、`$2`のように
+/// 参照するパラメーターは、`params`に定義した順に対応する。パラメーターは文字列として渡すため、
+/// 文字列以外の型で参照する場合は、SQLで`$1::integer`のように型変換すること。
+#[derive(Debug, Clone, Deserialize)]
+pub struct CustomLayer {
+    /// レイヤー名
+    pub name: String,
+    /// フィーチャーを取得するSQL
+    pub sql: String,
+    /// レイヤーの出典
+    #[serde(default)]
+    pub attribution: String,
+    /// SQLに渡すパラメーター
+    #[serde(default)]
+    pub params: Vec<LayerParam>,
+}
+
+/// 検証して登録したカスタムレイヤー。
+#[derive(Debug, Clone)]
+pub struct RegisteredLayer {
+    /// カスタムレイヤーの定義
+    pub layer: CustomLayer,
+    /// ジオメトリ以外の列の名前と型
+    pub fields: Vec<(String, &'static str)>,
+}
+
+/// 登録したカスタムレイヤー。
+#[derive(Debug, Clone, Default)]
+pub struct CustomLayers {
+    /// 登録したカスタムレイヤー
+    layers: Vec<RegisteredLayer>,
+}
+
+impl CustomLayers {
+    /// カスタムレイヤーを検証して登録する。
+    ///
+    /// レイヤーのSQLをデータベースで準備して、SQLの構文、パラメーターの数、`geom`列の有無を検証する。
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - データベースコネクションプール。
+    /// * `layers` - カスタムレイヤーの定義を格納したスライス。
+    ///
+    /// # Returns
+    ///
+    /// 登録したカスタムレイヤー。
+    pub async fn register(pool: &PgPool, layers: &[CustomLayer]) -> anyhow::Result<Self> {
+        let mut registered: Vec<RegisteredLayer> = Vec::new();
+        for layer in layers {
+            validate_name(&layer.name)?;
+            if TILE_LAYERS.contains(&layer.name.as_str())
+                || layer.name == COMBINED_TILE_LAYER
+                || registered.iter().any(|r| r.layer.name == layer.name)
+            {
+                return Err(anyhow!(
+                    "レイヤー({})はすでに登録されています。",
+                    layer.name
+                ));
+            }
+            for (i, param) in layer.params.iter().enumerate() {
+                if param.name.is_empty() || layer.params[..i].iter().any(|p| p.name == param.name) {
+                    return Err(anyhow!(
+                        "レイヤー({})のパラメーターの名前({})が空または重複しています。",
+                        layer.name,
+                        param.name
+                    ));
+                }
+                if RESERVED_PARAM_NAMES.contains(&param.name.as_str()) {
+                    return Err(anyhow!(
+                        "レイヤー({})のパラメーターの名前({})は、クエリパラメーターで予約されています。",
+                        layer.name,
+                        param.name
+                    ));
+                }
+            }
+            let fields = describe(pool, layer).await?;
+            tracing::info!(
+                "カスタムレイヤー({})を登録: 属性{}個、パラメーター{}個",
+                layer.name,
+                fields.len(),
+                layer.params.len()
+            );
+            registered.push(RegisteredLayer {
+                layer: layer.clone(),
+                fields,
+            });
+        }
+
+        Ok(Self { layers: registered })
+    }
+
+    /// カスタムレイヤーを取得する。
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - レイヤー名。
+    ///
+    /// # Returns
+    ///
+    /// カスタムレイヤー。登録されていない場合はNone。
+    pub fn get(&self, name: &str) -> Option<&RegisteredLayer> {
+        self.layers.iter().find(|r| r.layer.name == name)
+    }
+
+    /// 登録したカスタムレイヤーのレイヤー名を返す。
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.layers.iter().map(|r| r.layer.name.as_str())
+    }
+}
+
+/// レイヤー名を検証する。
+///
+/// レイヤー名はURLのパスやベクタータイルのレイヤー名に使用するため、小文字の英数字とアンダースコアに限る。
+///
+/// # Arguments
+///
+/// * `name` - レイヤー名。
+fn validate_name(name: &str) -> anyhow::Result<()> {
+    if name.is_empty()
+        || MAX_LAYER_NAME_LENGTH < name.len()
+        || name.starts_with(|c: char| c.is_ascii_digit())
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+    {
+        return Err(anyhow!(
+            "レイヤー名({})は、{}文字以内の小文字の英数字とアンダースコアで指定してください。",
+            name,
+            MAX_LAYER_NAME_LENGTH
+        ));
+    }
+
+    Ok(())
+}
+
+/// カスタムレイヤーのSQLをデータベースで準備して、ジオメトリ以外の列の名前と型を取得する。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+/// * `layer` - カスタムレイヤーの定義。
+///
+/// # Returns
+///
+/// ジオメトリ以外の列の名前と、TileJSONで使用する型のタプルを格納したベクタ。
+async fn describe(
+    pool: &PgPool,
+    layer: &CustomLayer,
+) -> anyhow::Result<Vec<(String, &'static str)>> {
+    let sql = format!("SELECT * FROM ({}) AS src", layer.sql);
+    let statement = pool.prepare(&sql).await.map_err(|e| {
+        anyhow!(
+            "レイヤー({})のSQLをデータベースで準備できません。{}",
+            layer.name,
+            e
+        )
+    })?;
+    let parameters = match statement.parameters() {
+        Some(Either::Left(types)) => types.len(),
+        Some(Either::Right(count)) => count,
+        None => 0,
+    };
+    if parameters != layer.params.len() {
+        return Err(anyhow!(
+            "レイヤー({})のSQLが参照するパラメーターの数({})が、定義したパラメーターの数({})と一致しません。",
+            layer.name,
+            parameters,
+            layer.params.len()
+        ));
+    }
+
+    let mut has_geometry = false;
+    let mut fields: Vec<(String, &'static str)> = Vec::new();
+    for column in statement.columns() {
+        let name = column.name();
+        let type_name = column.type_info().name();
+        if name == "geom" {
+            if !type_name.eq_ignore_ascii_case("geometry") {
+                return Err(anyhow!(
+                    "レイヤー({})のgeom列の型({})がgeometryではありません。",
+                    layer.name,
+                    type_name
+                ));
+            }
+            has_geometry = true;
+            continue;
+        }
+        if fields.iter().any(|(field, _)| field == name) {
+            return Err(anyhow!(
+                "レイヤー({})のSQLが返す列({})が重複しています。",
+                layer.name,
+                name
+            ));
+        }
+        let field_type = match type_name {
+            "INT2" | "INT4" | "INT8" | "FLOAT4" | "FLOAT8" | "NUMERIC" => "Number",
+            "BOOL" => "Boolean",
+            _ => "String",
+        };
+        fields.push((name.to_string(), field_type));
+    }
+    if !has_geometry {
+        return Err(anyhow!(
+            "レイヤー({})のSQLがgeom列を返しません。",
+            layer.name
+        ));
+    }
+
+    Ok(fields)
+}
+
+impl RegisteredLayer {
+    /// クエリパラメーターから、SQLに渡すパラメーターの値を取得する。
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - クエリパラメーター。
+    ///
+    /// # Returns
+    ///
+    /// パラメーターの値を定義した順に格納したベクタ。必須のパラメーターが指定されていない場合は
+    /// 400 Bad Requestを示すエラー。
+    pub fn param_values(&self, query: &HashMap<String, String>) -> Result<Vec<String>, ApiError> {
+        self.layer
+            .params
+            .iter()
+            .map(|param| {
+                query
+                    .get(&param.name)
+                    .or(param.default.as_ref())
+                    .cloned()
+                    .ok_or_else(|| ApiError::MissingParameter(param.name.clone()))
+            })
+            .collect()
+    }
+
+    /// パラメーターの値から、タイルキャッシュのキーに含める文字列を生成する。
+    ///
+    /// # Arguments
+    ///
+    /// * `values` - パラメーターの値。
+    ///
+    /// # Returns
+    ///
+    /// パラメーターの名前と値を連結した文字列。
+    pub fn cache_params(&self, values: &[String]) -> String {
+        self.layer
+            .params
+            .iter()
+            .zip(values)
+            .map(|(param, value)| format!("{}={}", param.name, percent_encode(value)))
+            .collect::<Vec<_>>()
+            .join("&")
+    }
+
+    /// SELECT句の列を生成する。
+    ///
+    /// # Arguments
+    ///
+    /// * `as_mvt` - ベクタータイルに含める列を生成するか。trueの場合は、文字列型の属性をテキストに変換する。
+    /// * `geometry` - ジオメトリを選択する式。
+    ///
+    /// # Returns
+    ///
+    /// SELECT句の列をカンマで連結した文字列。
+    fn select_list(&self, as_mvt: bool, geometry: &str) -> String {
+        self.fields
+            .iter()
+            .map(|(name, field_type)| {
+                let name = name.replace('"', r#""""#);
+                if as_mvt && *field_type == "String" {
+                    format!(r#"src."{}"::text as "{}""#, name, name)
+                } else {
+                    format!(r#"src."{}""#, name)
+                }
+            })
+            .chain(std::iter::once(geometry.to_string()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// パラメーターの値をバインドしたクエリを構築する。
+    fn bind<'q, O>(sql: &'q str, values: &'q [String]) -> QueryAs<'q, Postgres, O, PgArguments>
+    where
+        O: for<'r> sqlx::FromRow<'r, sqlx::postgres::PgRow>,
+    {
+        values
+            .iter()
+            .fold(sqlx::query_as(sql), |query, value| query.bind(value))
+    }
+
+    /// カスタムレイヤーのベクタータイルを生成する。
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - データベースコネクションプール。
+    /// * `values` - パラメーターの値。
+    /// * `zoom` - ズームレベル。
+    /// * `x` - タイルのX座標。
+    /// * `y` - タイルのY座標。
+    /// * `tiles` - タイルの設定。
+    ///
+    /// # Returns
+    ///
+    /// Mapbox Vector Tile形式でエンコードしたタイル。
+    pub async fn vector_tile(
+        &self,
+        pool: &PgPool,
+        values: &[String],
+        zoom: i32,
+        x: i32,
+        y: i32,
+        tiles: &TileSettings,
+    ) -> sqlx::Result<Vec<u8>> {
+        let n = values.len();
+        let envelope = format!("ST_TileEnvelope(${}, ${}, ${})", n + 1, n + 2, n + 3);
+        let sql = format!(
+            r#"
+            SELECT ST_AsMVT(t.*, '{name}', 4096, 'geom') as mvt
+            FROM (
+                SELECT {columns}
+                FROM ({sql}) AS src
+                WHERE src.geom && {envelope}
+            ) t
+            "#,
+            name = self.layer.name,
+            columns = self.select_list(
+                true,
+                &format!(
+                    "ST_AsMVTGeom(src.geom, {}, 4096, ${}, ${}) as geom",
+                    envelope,
+                    n + 4,
+                    n + 5
+                ),
+            ),
+            envelope = envelope,
+            sql = self.layer.sql,
+        );
+        let (mvt,): (Option<Vec<u8>>,) = Self::bind(&sql, values)
+            .bind(zoom)
+            .bind(x)
+            .bind(y)
+            .bind(tiles.mvt_buffer)
+            .bind(tiles.mvt_clip)
+            .fetch_one(pool)
+            .instrument(query_span("SELECT custom layer vector tile"))
+            .await?;
+
+        Ok(mvt.unwrap_or_default())
+    }
+
+    /// カスタムレイヤーのフィーチャーを1件ずつ問い合わせて、GeoJSONのフィーチャーコレクションとして送信する。
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - データベースコネクションプール。
+    /// * `values` - パラメーターの値。
+    /// * `polygon` - フィーチャーを取得する範囲を示すポリゴン(Webメルカトル座標のWKT)。Noneの場合はすべてのフィーチャー。
+    /// * `clip` - ジオメトリを、フィーチャーを取得する範囲で切り抜くか。
+    /// * `srid` - 返すジオメトリの空間参照系のSRID。
+    /// * `digits` - 座標の小数点以下の桁数。
+    ///
+    /// # Returns
+    ///
+    /// フィーチャーコレクションを受信するストリーム。
+    pub async fn features(
+        &self,
+        pool: &PgPool,
+        values: &[String],
+        polygon: Option<&str>,
+        clip: bool,
+        srid: i32,
+        digits: u8,
+    ) -> Result<impl Stream<Item = Result<Bytes, actix_web::Error>>, ApiError> {
+        let n = values.len();
+        let range = format!("ST_GeomFromText(${}, ${})", n + 1, n + 2);
+        let (condition, geometry) = match polygon {
+            Some(_) if clip => (
+                format!("WHERE ST_Intersects(src.geom, {})", range),
+                format!("ST_ClipByBox2D(src.geom, {})", range),
+            ),
+            Some(_) => (
+                format!("WHERE ST_Intersects(src.geom, {})", range),
+                "src.geom".to_string(),
+            ),
+            None => (String::new(), "src.geom".to_string()),
+        };
+        let geometry = match srid {
+            EPSG_WEB_MERCATOR => format!("{} as geom", geometry),
+            srid => format!("ST_Transform({}, {}) as geom", geometry, srid),
+        };
+        let sql = format!(
+            r#"
+            SELECT ST_AsGeoJSON(t.*, 'geom', {digits})::json
+            FROM (
+                SELECT {columns} FROM ({sql}) AS src {condition}
+            ) t
+            "#,
+            digits = digits,
+            columns = self.select_list(false, &geometry),
+            sql = self.layer.sql,
+            condition = condition,
+        );
+        let mut args = PgArguments::default();
+        for value in values {
+            args.add(value.clone());
+        }
+        if let Some(polygon) = polygon {
+            args.add(polygon.to_string());
+            args.add(EPSG_WEB_MERCATOR);
+        }
+
+        stream_feature_collection(pool.clone(), sql, args, None).await
+    }
+
+    /// カスタムレイヤーのフィーチャーの範囲を、WGS84経緯度で取得する。
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - データベースコネクションプール。
+    /// * `values` - パラメーターの値。
+    ///
+    /// # Returns
+    ///
+    /// 西端、南端、東端、北端のタプル。フィーチャーが存在しない場合、各値はNone。
+    pub async fn extent(
+        &self,
+        pool: &PgPool,
+        values: &[String],
+    ) -> sqlx::Result<(Option<f64>, Option<f64>, Option<f64>, Option<f64>)> {
+        let n = values.len();
+        let sql = format!(
+            r#"
+            SELECT
+                ST_XMin(e.geom) as west, ST_YMin(e.geom) as south,
+                ST_XMax(e.geom) as east, ST_YMax(e.geom) as north
+            FROM (
+                SELECT ST_Transform(ST_SetSRID(ST_Extent(src.geom)::geometry, ${}), ${}) as geom
+                FROM ({}) AS src
+            ) e
+            "#,
+            n + 1,
+            n + 2,
+            self.layer.sql,
+        );
+        Self::bind(&sql, values)
+            .bind(EPSG_WEB_MERCATOR)
+            .bind(EPSG_WGS84)
+            .fetch_one(pool)
+            .instrument(query_span("SELECT custom layer extent"))
+            .await
+    }
+}
+
+/// タイルキャッシュのキーで区切り文字と区別できるように、文字列をパーセントエンコードする。
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
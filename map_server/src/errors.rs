@@ -0,0 +1,454 @@
+use actix_web::body::BoxBody;
+use actix_web::dev::ServiceResponse;
+use actix_web::http::header::{self, AcceptLanguage, Header};
+use actix_web::http::StatusCode;
+use actix_web::{HttpMessage, HttpResponse, ResponseError};
+use database::settings::QUERY_CANCELED;
+use serde_json::json;
+
+/// エラーを返すレスポンスのメディアタイプ(RFC 7807)。
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// エラーの種類を示すURIの接頭辞(接頭辞にエラーコードを付けたURIを、エラーの`type`とする)。
+pub const PROBLEM_TYPE_PREFIX: &str = "urn:problem:sample-map-server:";
+
+/// エラーメッセージの言語。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    /// 英語
+    En,
+    /// 日本語
+    Ja,
+}
+
+impl Language {
+    /// リクエストのAccept-Languageヘッダーから、エラーメッセージの言語を決定する。
+    ///
+    /// 日本語と英語のうち、優先度が高い言語を選択する。どちらも指定されていない場合は英語。
+    ///
+    /// # Arguments
+    ///
+    /// * `req` - HTTPリクエスト。
+    ///
+    /// # Returns
+    ///
+    /// エラーメッセージの言語。
+    pub fn from_request<T: HttpMessage>(req: &T) -> Self {
+        let accept_language = match AcceptLanguage::parse(req) {
+            Ok(accept_language) => accept_language,
+            Err(_) => return Language::En,
+        };
+
+        accept_language
+            .ranked()
+            .iter()
+            .filter_map(|preference| preference.item())
+            .find_map(|tag| {
+                let primary = tag.primary_language();
+                if primary.eq_ignore_ascii_case("ja") {
+                    Some(Language::Ja)
+                } else if primary.eq_ignore_ascii_case("en") {
+                    Some(Language::En)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or(Language::En)
+    }
+}
+
+/// エラーメッセージのカタログ。
+///
+/// エラーコード、英語のメッセージ、日本語のメッセージを格納する。メッセージの`{}`は、エラーの引数に置き換える。
+const MESSAGES: [(&str, &str, &str); 27] = [
+    (
+        "invalid_tile",
+        "Invalid tile info",
+        "タイルの指定が不正です。",
+    ),
+    (
+        "tile_out_of_range",
+        "Tile is out of served range",
+        "タイルが配信する範囲に含まれていません。",
+    ),
+    ("unknown_layer", "Unknown layer", "レイヤーが存在しません。"),
+    (
+        "unknown_collection",
+        "Unknown collection",
+        "フィーチャーコレクションが存在しません。",
+    ),
+    (
+        "unknown_feature",
+        "Unknown feature",
+        "フィーチャーが存在しません。",
+    ),
+    (
+        "missing_parameter",
+        "Missing parameter: {}",
+        "パラメーター({})を指定してください。",
+    ),
+    (
+        "unknown_property",
+        "Unknown property: {}",
+        "プロパティ({})が存在しません。",
+    ),
+    (
+        "invalid_query",
+        "Invalid query parameters",
+        "クエリパラメーターが不正です。",
+    ),
+    (
+        "invalid_body",
+        "Invalid request body",
+        "リクエストボディが不正です。",
+    ),
+    ("not_found", "Not found", "リソースが存在しません。"),
+    (
+        "not_acceptable",
+        "None of the acceptable media types can be returned",
+        "要求された形式でレスポンスを返せません。",
+    ),
+    (
+        "missing_api_key",
+        "Missing API key",
+        "APIキーを指定してください。",
+    ),
+    (
+        "invalid_api_key",
+        "Invalid API key",
+        "APIキーが登録されていません。",
+    ),
+    (
+        "disabled_api_key",
+        "API key is disabled",
+        "APIキーが無効にされています。",
+    ),
+    (
+        "too_many_requests",
+        "Too many requests",
+        "リクエストが多すぎます。しばらく待ってから再試行してください。",
+    ),
+    (
+        "query_timeout",
+        "Query took too long to complete",
+        "データベースへの問い合わせが時間内に完了しませんでした。範囲を狭めて再試行してください。",
+    ),
+    (
+        "database_unavailable",
+        "Database is busy",
+        "データベースが混雑しています。しばらく待ってから再試行してください。",
+    ),
+    (
+        "database_error",
+        "Failed to query the database",
+        "データベースに問い合わせできませんでした。",
+    ),
+    (
+        "payload_too_large",
+        "Request body is too large",
+        "リクエストボディが大きすぎます。",
+    ),
+    (
+        "invalid_dataset",
+        "Invalid dataset: {}",
+        "データセットを登録できません。{}",
+    ),
+    (
+        "import_failed",
+        "Failed to register the dataset",
+        "データセットを登録できませんでした。",
+    ),
+    (
+        "delete_failed",
+        "Failed to delete the data",
+        "データを削除できませんでした。",
+    ),
+    (
+        "tile_read_failed",
+        "Failed to read the tile from the archive",
+        "アーカイブからタイルを読み込めませんでした。",
+    ),
+    (
+        "range_not_satisfiable",
+        "The requested range is not satisfiable",
+        "指定された範囲を返せません。",
+    ),
+    (
+        "export_failed",
+        "Failed to export the layer",
+        "レイヤーをエクスポートできませんでした。",
+    ),
+    (
+        "admin_unauthorized",
+        "Administrator authentication required",
+        "管理者の認証が必要です。",
+    ),
+    (
+        "internal_error",
+        "Internal server error",
+        "サーバーの内部でエラーが発生しました。",
+    ),
+];
+
+/// APIが返すエラー。
+#[derive(Debug)]
+pub enum ApiError {
+    /// タイルの指定が不正
+    InvalidTile,
+    /// タイルが配信する範囲に含まれていない
+    TileOutOfRange,
+    /// レイヤーが存在しない
+    UnknownLayer,
+    /// フィーチャーコレクションが存在しない
+    UnknownCollection,
+    /// フィーチャーが存在しない
+    UnknownFeature,
+    /// 必須のパラメーターが指定されていない
+    MissingParameter(String),
+    /// 選択されたプロパティが存在しない
+    UnknownProperty(String),
+    /// クエリパラメーターが不正
+    InvalidQuery,
+    /// リクエストボディが不正
+    InvalidBody,
+    /// リソースが存在しない
+    NotFound,
+    /// Acceptヘッダーに指定されたいずれの形式でもレスポンスを返せない
+    NotAcceptable,
+    /// APIキーが指定されていない
+    MissingApiKey,
+    /// APIキーが登録されていない
+    InvalidApiKey,
+    /// APIキーが無効にされている
+    DisabledApiKey,
+    /// リクエストの頻度が制限を超えた(次のリクエストを許可するまでの秒数)
+    TooManyRequests(u64),
+    /// データベースへの問い合わせが、文の実行時間の上限を超えた
+    QueryTimeout,
+    /// コネクションプールから時間内に接続を取得できなかった
+    DatabaseUnavailable,
+    /// データベースの問い合わせに失敗
+    Database(sqlx::Error),
+    /// リクエストボディが大きすぎる
+    PayloadTooLarge,
+    /// アップロードされたデータセットが不正(登録処理のエラーメッセージ)
+    InvalidDataset(String),
+    /// アップロードされたデータセットの登録に失敗
+    ImportFailed,
+    /// データの削除に失敗
+    DeleteFailed,
+    /// PMTilesのアーカイブからタイルの読み込みに失敗
+    TileReadFailed,
+    /// Rangeヘッダーに指定された範囲がファイルに含まれない(ファイルの大きさ)
+    RangeNotSatisfiable(u64),
+    /// ダウンロードするファイルの生成または読み込みに失敗
+    ExportFailed,
+    /// 管理用のエンドポイントの認証に失敗(`WWW-Authenticate`レスポンスヘッダーに設定する値)
+    AdminUnauthorized(String),
+    /// APIのエラー以外で、リクエストの処理に失敗
+    Internal,
+}
+
+impl ApiError {
+    /// エラーコードを返す。
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::InvalidTile => "invalid_tile",
+            ApiError::TileOutOfRange => "tile_out_of_range",
+            ApiError::UnknownLayer => "unknown_layer",
+            ApiError::UnknownCollection => "unknown_collection",
+            ApiError::UnknownFeature => "unknown_feature",
+            ApiError::MissingParameter(_) => "missing_parameter",
+            ApiError::UnknownProperty(_) => "unknown_property",
+            ApiError::InvalidQuery => "invalid_query",
+            ApiError::InvalidBody => "invalid_body",
+            ApiError::NotFound => "not_found",
+            ApiError::NotAcceptable => "not_acceptable",
+            ApiError::MissingApiKey => "missing_api_key",
+            ApiError::InvalidApiKey => "invalid_api_key",
+            ApiError::DisabledApiKey => "disabled_api_key",
+            ApiError::TooManyRequests(_) => "too_many_requests",
+            ApiError::QueryTimeout => "query_timeout",
+            ApiError::DatabaseUnavailable => "database_unavailable",
+            ApiError::Database(_) => "database_error",
+            ApiError::PayloadTooLarge => "payload_too_large",
+            ApiError::InvalidDataset(_) => "invalid_dataset",
+            ApiError::ImportFailed => "import_failed",
+            ApiError::DeleteFailed => "delete_failed",
+            ApiError::TileReadFailed => "tile_read_failed",
+            ApiError::RangeNotSatisfiable(_) => "range_not_satisfiable",
+            ApiError::ExportFailed => "export_failed",
+            ApiError::AdminUnauthorized(_) => "admin_unauthorized",
+            ApiError::Internal => "internal_error",
+        }
+    }
+
+    /// エラーメッセージを返す。
+    ///
+    /// データベースのエラーの詳細は、クライアントに返さずにログに記録する。
+    ///
+    /// # Arguments
+    ///
+    /// * `language` - エラーメッセージの言語。
+    ///
+    /// # Returns
+    ///
+    /// エラーメッセージ。
+    pub fn message(&self, language: Language) -> String {
+        let code = self.code();
+        let (_, en, ja) = MESSAGES
+            .iter()
+            .find(|(c, _, _)| *c == code)
+            .expect("エラーメッセージのカタログにエラーコードが登録されていません。");
+        let message = match language {
+            Language::En => en,
+            Language::Ja => ja,
+        };
+        match self {
+            ApiError::MissingParameter(name)
+            | ApiError::UnknownProperty(name)
+            | ApiError::InvalidDataset(name) => message.replace("{}", name),
+            _ => message.to_string(),
+        }
+    }
+
+    /// エラーを返すレスポンスを生成する。
+    ///
+    /// レスポンスボディは、RFC 7807の`application/problem+json`で返す。`type`にはエラーコードから生成したURI、
+    /// `title`には英語のメッセージ、`detail`には指定された言語のメッセージを含め、拡張メンバーの`code`に
+    /// エラーコードを含める。
+    ///
+    /// # Arguments
+    ///
+    /// * `language` - エラーメッセージの言語。
+    /// * `instance` - エラーが発生したリクエストのパス。
+    ///
+    /// # Returns
+    ///
+    /// レスポンス。
+    pub fn response(&self, language: Language, instance: Option<&str>) -> HttpResponse {
+        let status = self.status_code();
+        let mut body = json!({
+            "type": format!("{}{}", PROBLEM_TYPE_PREFIX, self.code()),
+            "title": self.message(Language::En),
+            "status": status.as_u16(),
+            "detail": self.message(language),
+            "code": self.code(),
+        });
+        if let Some(instance) = instance {
+            body["instance"] = json!(instance);
+        }
+        let mut builder = HttpResponse::build(status);
+        if let ApiError::TooManyRequests(retry_after) = self {
+            builder.insert_header((header::RETRY_AFTER, retry_after.to_string()));
+        }
+        if let ApiError::RangeNotSatisfiable(length) = self {
+            builder.insert_header((header::CONTENT_RANGE, format!("bytes */{}", length)));
+        }
+        if let ApiError::AdminUnauthorized(challenge) = self {
+            builder.insert_header((header::WWW_AUTHENTICATE, challenge.as_str()));
+        }
+
+        builder.content_type(PROBLEM_JSON).body(body.to_string())
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message(Language::En))
+    }
+}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(e: sqlx::Error) -> Self {
+        match &e {
+            sqlx::Error::Database(error) if error.code().as_deref() == Some(QUERY_CANCELED) => {
+                tracing::warn!(
+                    "データベースへの問い合わせが実行時間の上限を超えました。{}",
+                    e
+                );
+                ApiError::QueryTimeout
+            }
+            sqlx::Error::PoolTimedOut => {
+                tracing::warn!("コネクションプールから接続を取得できません。{}", e);
+                ApiError::DatabaseUnavailable
+            }
+            _ => {
+                tracing::error!("データベースに問い合わせできません。{}", e);
+                ApiError::Database(e)
+            }
+        }
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::InvalidTile
+            | ApiError::MissingParameter(_)
+            | ApiError::UnknownProperty(_)
+            | ApiError::InvalidQuery
+            | ApiError::InvalidBody => StatusCode::BAD_REQUEST,
+            ApiError::TileOutOfRange
+            | ApiError::UnknownLayer
+            | ApiError::UnknownCollection
+            | ApiError::UnknownFeature
+            | ApiError::NotFound => StatusCode::NOT_FOUND,
+            ApiError::NotAcceptable => StatusCode::NOT_ACCEPTABLE,
+            ApiError::MissingApiKey | ApiError::InvalidApiKey | ApiError::AdminUnauthorized(_) => {
+                StatusCode::UNAUTHORIZED
+            }
+            ApiError::DisabledApiKey => StatusCode::FORBIDDEN,
+            ApiError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::QueryTimeout => StatusCode::GATEWAY_TIMEOUT,
+            ApiError::DatabaseUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::Database(_)
+            | ApiError::ImportFailed
+            | ApiError::DeleteFailed
+            | ApiError::TileReadFailed
+            | ApiError::ExportFailed
+            | ApiError::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            ApiError::RangeNotSatisfiable(_) => StatusCode::RANGE_NOT_SATISFIABLE,
+            ApiError::InvalidDataset(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        self.response(Language::En, None)
+    }
+}
+
+/// レスポンスがエラーの場合は、指定された言語のメッセージとリクエストのパスを含めたレスポンスに置き換える。
+///
+/// APIのエラー以外のエラーは、リクエストボディが大きすぎる場合は`payload_too_large`、サーバーのエラーの場合は
+/// 原因をログに記録して`internal_error`のレスポンスに置き換え、エラーの詳細をクライアントに返さない。
+///
+/// # Arguments
+///
+/// * `res` - レスポンス。
+/// * `language` - エラーメッセージの言語。
+///
+/// # Returns
+///
+/// レスポンス。
+pub fn problem_response(res: ServiceResponse<BoxBody>, language: Language) -> ServiceResponse {
+    let error = match res.response().error() {
+        Some(error) => error,
+        None => return res,
+    };
+    let instance = res.request().path();
+    let problem = match error.as_error::<ApiError>() {
+        Some(e) => e.response(language, Some(instance)),
+        None if res.status() == StatusCode::PAYLOAD_TOO_LARGE => {
+            ApiError::PayloadTooLarge.response(language, Some(instance))
+        }
+        None if res.status().is_server_error() => {
+            tracing::error!("リクエスト({})を処理できません。{}", instance, error);
+            ApiError::Internal.response(language, Some(instance))
+        }
+        None => return res,
+    };
+
+    res.into_response(problem)
+}
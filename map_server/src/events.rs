@@ -0,0 +1,177 @@
+//! データセットの登録とデータの削除を、接続しているクライアントにServer-Sent Eventsで通知する。
+//!
+//! 管理用のエンドポイントでデータセットを登録した場合やデータを削除した場合に、変更したレイヤーごとに
+//! イベントを送信する。クライアントは、イベントを受け取ったレイヤーのソースを更新することで、ページを
+//! 再読み込みせずに最新のデータを表示できる。
+
+use std::time::Duration;
+
+use actix_web::http::header::{self, CacheControl, CacheDirective};
+use actix_web::rt::time::{interval_at, Instant};
+use actix_web::web::{self, Bytes};
+use actix_web::HttpResponse;
+use futures_util::stream;
+use serde::Serialize;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// イベントを保持するチャネルの容量。
+///
+/// クライアントが受け取っていないイベントが容量を超えた場合、そのクライアントには`lagged`イベントを送信する。
+pub const EVENT_CHANNEL_CAPACITY: usize = 64;
+
+/// Server-Sent EventsのContent-Typeヘッダーの値。
+pub const EVENT_STREAM_CONTENT_TYPE: &str = "text/event-stream";
+
+/// 接続を維持するために、コメントを送信する間隔。
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// データの変更の種類。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DataEventKind {
+    /// データセットを登録した
+    Import,
+    /// データを削除した
+    Delete,
+}
+
+impl DataEventKind {
+    /// Server-Sent Eventsのイベント名を返す。
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Import => "import",
+            Self::Delete => "delete",
+        }
+    }
+}
+
+/// データの変更を通知するイベント。
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataEvent {
+    /// データの変更の種類
+    pub kind: DataEventKind,
+    /// 変更したレイヤー名
+    pub layer: String,
+    /// 変更した都道府県コード(都道府県ごとに変更しない場合はNone)
+    pub code: Option<String>,
+    /// 変更した日時(UTC、RFC 3339形式)
+    pub timestamp: String,
+}
+
+/// データの変更を通知するイベントを、接続しているクライアントに配信する。
+#[derive(Debug, Clone)]
+pub struct DataEvents {
+    /// イベントを送信するチャネル
+    sender: broadcast::Sender<DataEvent>,
+}
+
+impl Default for DataEvents {
+    fn default() -> Self {
+        Self::new(EVENT_CHANNEL_CAPACITY)
+    }
+}
+
+impl DataEvents {
+    /// データの変更を通知するイベントの配信を構築する。
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - イベントを保持するチャネルの容量。
+    ///
+    /// # Returns
+    ///
+    /// データの変更を通知するイベントの配信。
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+
+        Self { sender }
+    }
+
+    /// レイヤーごとに、データの変更を通知するイベントを配信する。
+    ///
+    /// 接続しているクライアントがない場合は、イベントを破棄する。
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - データの変更の種類。
+    /// * `layers` - 変更したレイヤー名を格納したスライス。
+    /// * `code` - 変更した都道府県コード。
+    pub fn publish(&self, kind: DataEventKind, layers: &[&str], code: Option<&str>) {
+        let timestamp = OffsetDateTime::now_utc()
+            .replace_nanosecond(0)
+            .ok()
+            .and_then(|now| now.format(&Rfc3339).ok())
+            .unwrap_or_default();
+        for layer in layers {
+            let _ = self.sender.send(DataEvent {
+                kind,
+                layer: layer.to_string(),
+                code: code.map(|code| code.to_string()),
+                timestamp: timestamp.clone(),
+            });
+        }
+    }
+
+    /// イベントを受信する。
+    ///
+    /// # Returns
+    ///
+    /// イベントを受信するレシーバー。
+    pub fn subscribe(&self) -> broadcast::Receiver<DataEvent> {
+        self.sender.subscribe()
+    }
+}
+
+/// イベントを、Server-Sent Eventsのメッセージにエンコードする。
+///
+/// # Arguments
+///
+/// * `event` - イベント。
+///
+/// # Returns
+///
+/// Server-Sent Eventsのメッセージ。
+fn encode_event(event: &DataEvent) -> Bytes {
+    let data = serde_json::to_string(event).unwrap();
+
+    Bytes::from(format!("event: {}\ndata: {}\n\n", event.kind.name(), data))
+}
+
+/// データの変更を通知するイベントを、Server-Sent Eventsで送信し続ける。
+///
+/// クライアントがイベントを受け取らずに、チャネルの容量を超えるイベントを取りこぼした場合は、取りこぼした
+/// イベントの数を`lagged`イベントで送信する。`lagged`イベントを受け取ったクライアントは、すべてのレイヤーを
+/// 更新すること。
+#[tracing::instrument(name = "Events", skip(events))]
+pub async fn events(events: web::Data<DataEvents>) -> HttpResponse {
+    let receiver = events.subscribe();
+    let keep_alive = interval_at(Instant::now() + KEEP_ALIVE_INTERVAL, KEEP_ALIVE_INTERVAL);
+    let body = stream::unfold(
+        (receiver, keep_alive),
+        |(mut receiver, mut keep_alive)| async move {
+            let message = tokio::select! {
+                received = receiver.recv() => match received {
+                    Ok(event) => encode_event(&event),
+                    Err(RecvError::Lagged(skipped)) => Bytes::from(format!(
+                        "event: lagged\ndata: {{\"skipped\":{}}}\n\n",
+                        skipped
+                    )),
+                    Err(RecvError::Closed) => return None,
+                },
+                _ = keep_alive.tick() => Bytes::from_static(b": keep-alive\n\n"),
+            };
+
+            Some((Ok::<_, actix_web::Error>(message), (receiver, keep_alive)))
+        },
+    );
+
+    HttpResponse::Ok()
+        .insert_header((header::CONTENT_TYPE, EVENT_STREAM_CONTENT_TYPE))
+        .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+        // リバースプロキシがレスポンスをバッファリングしないようにする
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(body)
+}
@@ -0,0 +1,717 @@
+//! レイヤーのすべてのフィーチャーを、ファイルとしてダウンロードするエンドポイント。
+//!
+//! ダウンロードするファイル(成果物)は、レイヤーとファイルの形式ごとに、データセットの版(最後に登録した
+//! 履歴のID)を名前に含めて一時ディレクトリに保存し、データセットが登録し直されるまで同じファイルを返す。
+//! これにより、Rangeヘッダーで範囲を指定して、中断したダウンロードを再開できる。また、成果物には
+//! brotliで圧縮したファイルと、SHA-256のチェックサムを記録したマニフェストを添える。
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use actix_web::http::header::{self, ContentType, EntityTag, HeaderValue};
+use actix_web::{web, HttpRequest, HttpResponse};
+use futures_util::{Stream, StreamExt, TryStreamExt};
+use geojson::JsonValue;
+use geozero::wkb;
+use sqlx::postgres::{PgArguments, PgRow};
+use sqlx::{PgPool, Row};
+use tokio::io::AsyncWriteExt;
+use tracing::Instrument;
+use wkt::ToWkt;
+
+use database::imports::last_import_id;
+
+use utils::EPSG_WGS84;
+
+use crate::artifacts::{
+    finish_artifact, with_added_extension, BROTLI_EXTENSION, MANIFEST_EXTENSION,
+};
+use crate::compression::{accepts_encoding, Encoding};
+use crate::errors::ApiError;
+use crate::flatgeobuf::FlatGeobufEncoder;
+use crate::handlers::conditional_response;
+use crate::negotiation::CSV_CONTENT_TYPE;
+use crate::ogc_api::{find_collection, Collection};
+use crate::ranges::ranged_file_response;
+use crate::streaming::stream_rows;
+use crate::telemetries::query_span;
+
+/// FlatGeobufのMIMEタイプ。
+const FLATGEOBUF_CONTENT_TYPE: &str = "application/flatgeobuf";
+
+/// Excelが文字コードをUTF-8と判定できるように、CSVの先頭に付与するバイトオーダーマーク。
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// ダウンロードするファイルの形式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExportFormat {
+    /// FlatGeobuf
+    FlatGeobuf,
+    /// CSV
+    Csv,
+}
+
+impl ExportFormat {
+    /// ファイルの拡張子を返す。
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::FlatGeobuf => "fgb",
+            ExportFormat::Csv => "csv",
+        }
+    }
+
+    /// ファイルのMIMEタイプを返す。
+    fn content_type(self) -> ContentType {
+        let mime = match self {
+            ExportFormat::FlatGeobuf => FLATGEOBUF_CONTENT_TYPE,
+            ExportFormat::Csv => CSV_CONTENT_TYPE,
+        };
+
+        ContentType(mime.parse().unwrap())
+    }
+}
+
+/// ダウンロードするファイル(成果物)を保存するディレクトリ。
+///
+/// ディレクトリは一時ディレクトリに作成し、サーバーが終了するときに削除する。
+pub struct ExportArtifacts {
+    /// 成果物を保存するディレクトリ
+    directory: tempfile::TempDir,
+    /// 成果物をbrotliで圧縮したファイルを添える場合は圧縮レベル、添えない場合はNone
+    brotli_level: Option<u32>,
+    /// 成果物の名前ごとに、成果物を生成するタスクを1つに限るロック
+    locks: Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+impl ExportArtifacts {
+    /// 成果物を保存するディレクトリを作成する。
+    ///
+    /// # Arguments
+    ///
+    /// * `brotli_level` - 成果物をbrotliで圧縮したファイルを添える場合は圧縮レベル、添えない場合はNone。
+    ///
+    /// # Returns
+    ///
+    /// 成果物を保存するディレクトリ。
+    pub fn new(brotli_level: Option<u32>) -> std::io::Result<Self> {
+        Ok(Self {
+            directory: tempfile::Builder::new()
+                .prefix("map_server_exports")
+                .tempdir()?,
+            brotli_level,
+            locks: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// 成果物のパスを返す。成果物がない場合は、成果物を生成する。
+    ///
+    /// 成果物を生成した場合は、brotliで圧縮したファイルとマニフェストを添えて、同じレイヤーとファイルの形式の
+    /// 古い版の成果物を削除する。マニフェストを書き込むまで、成果物を生成していないものとみなす。
+    ///
+    /// # Arguments
+    ///
+    /// * `layer` - レイヤー名。
+    /// * `extension` - ファイルの拡張子。
+    /// * `version` - データセットの版。
+    /// * `generate` - 受け取ったパスに成果物の内容を書き込むクロージャー。
+    ///
+    /// # Returns
+    ///
+    /// 成果物のパス。
+    async fn get_or_generate<G, F>(
+        &self,
+        layer: &str,
+        extension: &str,
+        version: i64,
+        generate: G,
+    ) -> Result<PathBuf, ApiError>
+    where
+        G: FnOnce(PathBuf) -> F,
+        F: std::future::Future<Output = Result<(), ApiError>>,
+    {
+        let name = artifact_name(layer, extension, version);
+        let path = self.directory.path().join(&name);
+        let lock = self
+            .locks
+            .lock()
+            .unwrap()
+            .entry(name.clone())
+            .or_default()
+            .clone();
+        let _guard = lock.lock().await;
+        let manifest = with_added_extension(&path, MANIFEST_EXTENSION);
+        if tokio::fs::metadata(&manifest).await.is_ok() {
+            return Ok(path);
+        }
+        let partial = with_added_extension(&path, "partial");
+        if let Err(e) = generate(partial.clone()).await {
+            let _ = tokio::fs::remove_file(&partial).await;
+            return Err(e);
+        }
+        let finished = {
+            let (path, brotli_level) = (path.clone(), self.brotli_level);
+            let file_name = format!("{}.{}", layer, extension);
+            web::block(move || {
+                std::fs::rename(&partial, &path)?;
+                finish_artifact(&path, &file_name, brotli_level)
+            })
+            .await
+        };
+        match finished {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => {
+                tracing::error!("成果物({})を保存できません。{}", name, e);
+                return Err(ApiError::ExportFailed);
+            }
+            Err(_) => return Err(ApiError::Internal),
+        }
+        tracing::info!("成果物({})を生成", name);
+        self.remove_old_versions(layer, extension, &name).await;
+
+        Ok(path)
+    }
+
+    /// 同じレイヤーとファイルの形式の、古い版の成果物と、成果物に添えたファイルを削除する。
+    ///
+    /// # Arguments
+    ///
+    /// * `layer` - レイヤー名。
+    /// * `extension` - ファイルの拡張子。
+    /// * `current` - 削除しない成果物の名前。
+    async fn remove_old_versions(&self, layer: &str, extension: &str, current: &str) {
+        let mut entries = match tokio::fs::read_dir(self.directory.path()).await {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+        let prefix = format!("{}-", layer);
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let artifact = name.strip_prefix(&prefix).and_then(|rest| {
+                let (version, rest) = rest.split_once('.')?;
+                version.parse::<i64>().ok()?;
+                let suffix = rest.strip_prefix(extension)?;
+                (suffix.is_empty() || suffix.starts_with('.'))
+                    .then(|| format!("{}{}.{}", prefix, version, extension))
+            });
+            if artifact.is_some_and(|artifact| artifact != current) {
+                let _ = tokio::fs::remove_file(entry.path()).await;
+                self.locks.lock().unwrap().remove(&name);
+            }
+        }
+    }
+}
+
+/// 成果物の名前を返す。
+///
+/// # Arguments
+///
+/// * `layer` - レイヤー名。
+/// * `extension` - ファイルの拡張子。
+/// * `version` - データセットの版。
+///
+/// # Returns
+///
+/// 成果物の名前。
+fn artifact_name(layer: &str, extension: &str, version: i64) -> String {
+    format!("{}-{}.{}", layer, version, extension)
+}
+
+/// ストリームの内容をファイルに書き込む。
+///
+/// # Arguments
+///
+/// * `path` - ファイルのパス。
+/// * `body` - ファイルの内容のストリーム。
+async fn write_stream<S>(path: &Path, body: S) -> Result<(), ApiError>
+where
+    S: Stream<Item = Result<web::Bytes, actix_web::Error>>,
+{
+    let written = async {
+        let mut file = tokio::fs::File::create(path).await?;
+        let mut body = Box::pin(body);
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk.map_err(|e| anyhow::anyhow!("{}", e))?;
+            file.write_all(&chunk).await?;
+        }
+        file.flush().await?;
+
+        Ok::<_, anyhow::Error>(())
+    }
+    .await;
+
+    written.map_err(|e| export_failed(path, e))
+}
+
+/// 成果物を生成できなかったことをログに出力して、エラーを返す。
+///
+/// # Arguments
+///
+/// * `path` - 生成していた成果物のパス。
+/// * `e` - エラー。
+fn export_failed(path: &Path, e: impl std::fmt::Display) -> ApiError {
+    tracing::error!("成果物({})を生成できません。{}", path.display(), e);
+
+    ApiError::ExportFailed
+}
+
+/// 成果物を、リクエストで指定された範囲に限って返す。
+///
+/// ETagは成果物の名前から生成するため、データセットが登録し直されるまで変わらない。
+///
+/// # Arguments
+///
+/// * `req` - HTTPリクエスト。
+/// * `path` - 成果物のパス。
+/// * `content_type` - 成果物のMIMEタイプ。
+/// * `filename` - ダウンロードするときのファイル名。
+///
+/// # Returns
+///
+/// レスポンス。
+async fn artifact_response(
+    req: &HttpRequest,
+    path: &Path,
+    content_type: ContentType,
+    filename: &str,
+) -> Result<HttpResponse, ApiError> {
+    let name = path.file_name().unwrap().to_string_lossy();
+    let etag = EntityTag::new_strong(name.to_string());
+    let file = tokio::fs::File::open(path).await.map_err(|e| {
+        tracing::error!("成果物({})を開けません。{}", name, e);
+        ApiError::ExportFailed
+    })?;
+
+    ranged_file_response(req, file, etag, content_type, Some(filename)).await
+}
+
+/// データセットの版を返す。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+/// * `collection` - フィーチャーコレクション。
+///
+/// # Returns
+///
+/// データセットの版(最後に登録した履歴のID)。データセットを登録した履歴がない場合は0。
+async fn dataset_version(pool: &PgPool, collection: &Collection) -> Result<i64, ApiError> {
+    Ok(last_import_id(pool, collection.id)
+        .await
+        .map_err(ApiError::from)?
+        .unwrap_or(0))
+}
+
+/// ダウンロードするレイヤーを取得する。
+///
+/// # Arguments
+///
+/// * `layer` - レイヤー名。
+///
+/// # Returns
+///
+/// レイヤーのフィーチャーコレクション。存在しない場合はレイヤーが存在しないことを示すエラー。
+fn find_layer(layer: &str) -> Result<&'static Collection, ApiError> {
+    find_collection(layer).map_err(|_| ApiError::UnknownLayer)
+}
+
+/// ダウンロードするファイルの列の名前を返す。
+///
+/// # Arguments
+///
+/// * `collection` - フィーチャーコレクション。
+///
+/// # Returns
+///
+/// IDとフィーチャーのプロパティの名前を格納したベクタ。
+fn export_columns(collection: &Collection) -> Vec<&'static str> {
+    let mut columns = vec!["id"];
+    columns.extend(collection.properties.iter().map(|(name, _)| *name));
+
+    columns
+}
+
+/// レイヤーのフィーチャーを、WGS84経緯度のジオメトリ、ID、プロパティの順に選択するSQLを返す。
+///
+/// # Arguments
+///
+/// * `collection` - フィーチャーコレクション。
+fn export_sql(collection: &Collection) -> String {
+    format!(
+        "SELECT ST_Transform(geom, {}) as geom, id::text as id, {} FROM {} ORDER BY id",
+        EPSG_WGS84,
+        collection.columns().join(", "),
+        collection.id
+    )
+}
+
+/// レイヤーのフィーチャーを、ID、プロパティ、WGS84経緯度のジオメトリの順に選択するSQLを返す。
+///
+/// ジオメトリは、ポイントのレイヤーの場合は経度と緯度の列、それ以外のレイヤーの場合はWKTの列で選択する。
+///
+/// # Arguments
+///
+/// * `collection` - フィーチャーコレクション。
+fn export_csv_sql(collection: &Collection) -> String {
+    let geometry = if is_point_layer(collection) {
+        format!(
+            "ST_X(ST_Transform(geom, {srid}))::text as lon, ST_Y(ST_Transform(geom, {srid}))::text as lat",
+            srid = EPSG_WGS84
+        )
+    } else {
+        format!("ST_AsText(ST_Transform(geom, {})) as wkt", EPSG_WGS84)
+    };
+    format!(
+        "SELECT id::text as id, {}, {} FROM {} ORDER BY id",
+        collection.columns().join(", "),
+        geometry,
+        collection.id
+    )
+}
+
+/// ポイントのレイヤーか確認する。
+fn is_point_layer(collection: &Collection) -> bool {
+    collection.geometry_type == "Point"
+}
+
+/// FlatGeobufのフィーチャーとして、行をエンコーダーに追加する。
+///
+/// # Arguments
+///
+/// * `encoder` - FlatGeobufのエンコーダー。
+/// * `row` - ジオメトリ、ID、プロパティの順に選択した行。
+/// * `columns` - 列の数(ジオメトリを除く)。
+fn add_flatgeobuf_row(
+    encoder: &mut FlatGeobufEncoder,
+    row: &PgRow,
+    columns: usize,
+) -> anyhow::Result<()> {
+    let geom: Option<wkb::Decode<geo_types::Geometry<f64>>> = row.try_get(0)?;
+    let values = (1..=columns)
+        .map(|i| row.try_get::<Option<String>, _>(i))
+        .collect::<Result<Vec<_>, _>>()?;
+    let geometry = geom.and_then(|geom| geom.geometry);
+
+    encoder.add_feature(geometry.as_ref(), &values)
+}
+
+/// CSVのレコードをエンコードする。
+///
+/// カンマ、ダブルクォーテーション、または改行を含む値は、ダブルクォーテーションで囲む。
+///
+/// # Arguments
+///
+/// * `values` - レコードの値。Noneの値は空の値にする。
+///
+/// # Returns
+///
+/// 改行(CRLF)で終わるレコード。
+pub fn encode_csv_record<S: AsRef<str>>(values: &[Option<S>]) -> Vec<u8> {
+    let fields: Vec<String> = values
+        .iter()
+        .map(|value| match value.as_ref().map(|value| value.as_ref()) {
+            Some(value) if value.contains([',', '"', '\r', '\n']) => {
+                format!(r#""{}""#, value.replace('"', r#""""#))
+            }
+            Some(value) => value.to_string(),
+            None => String::new(),
+        })
+        .collect();
+
+    format!("{}\r\n", fields.join(",")).into_bytes()
+}
+
+/// GeoJSONのフィーチャーを、プロパティ、WKTのジオメトリの順に格納したCSVのレコードにエンコードする。
+///
+/// # Arguments
+///
+/// * `feature` - GeoJSONのフィーチャー。
+/// * `properties` - レコードに格納するプロパティの名前。
+///
+/// # Returns
+///
+/// 改行(CRLF)で終わるレコード。ジオメトリがない場合は、WKTの値を空にする。
+pub fn encode_feature_csv_record(feature: &JsonValue, properties: &[&str]) -> Vec<u8> {
+    let mut values: Vec<Option<String>> = properties
+        .iter()
+        .map(|name| match &feature["properties"][*name] {
+            JsonValue::Null => None,
+            JsonValue::String(value) => Some(value.clone()),
+            value => Some(value.to_string()),
+        })
+        .collect();
+    let wkt = geojson::Geometry::from_json_value(feature["geometry"].clone())
+        .ok()
+        .and_then(|geometry| geo_types::Geometry::<f64>::try_from(geometry).ok())
+        .map(|geometry| geometry.wkt_string());
+    values.push(wkt);
+
+    encode_csv_record(&values)
+}
+
+/// レイヤーの成果物のパスを返す。成果物がない場合は、成果物を生成する。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+/// * `artifacts` - 成果物を保存するディレクトリ。
+/// * `layer` - レイヤー名。
+/// * `format` - ファイルの形式。
+///
+/// # Returns
+///
+/// レイヤーのフィーチャーコレクションと成果物のパス。
+async fn export_artifact(
+    pool: &PgPool,
+    artifacts: &ExportArtifacts,
+    layer: &str,
+    format: ExportFormat,
+) -> Result<(&'static Collection, PathBuf), ApiError> {
+    let collection = find_layer(layer)?;
+    let version = dataset_version(pool, collection).await?;
+    let pool = pool.clone();
+    let path = artifacts
+        .get_or_generate(
+            collection.id,
+            format.extension(),
+            version,
+            |path| async move {
+                match format {
+                    ExportFormat::FlatGeobuf => write_flatgeobuf(pool, collection, path).await,
+                    ExportFormat::Csv => {
+                        write_stream(&path, csv_body(pool, collection).await?).await
+                    }
+                }
+            },
+        )
+        .await?;
+
+    Ok((collection, path))
+}
+
+/// レイヤーの成果物を返す。
+///
+/// クライアントがbrotliを受け入れる場合は、brotliで圧縮したファイルを`Content-Encoding: br`で返す。
+///
+/// # Arguments
+///
+/// * `req` - HTTPリクエスト。
+/// * `pool` - データベースコネクションプール。
+/// * `artifacts` - 成果物を保存するディレクトリ。
+/// * `layer` - レイヤー名。
+/// * `format` - ファイルの形式。
+///
+/// # Returns
+///
+/// レスポンス。
+async fn export(
+    req: &HttpRequest,
+    pool: &PgPool,
+    artifacts: &ExportArtifacts,
+    layer: &str,
+    format: ExportFormat,
+) -> Result<HttpResponse, ApiError> {
+    let (collection, path) = export_artifact(pool, artifacts, layer, format).await?;
+    let filename = format!("{}.{}", collection.id, format.extension());
+    let brotli = with_added_extension(&path, BROTLI_EXTENSION);
+    let accepts_brotli = req
+        .headers()
+        .get(header::ACCEPT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| accepts_encoding(value, Encoding::Brotli));
+    if !accepts_brotli || tokio::fs::metadata(&brotli).await.is_err() {
+        return artifact_response(req, &path, format.content_type(), &filename).await;
+    }
+    let mut response = artifact_response(req, &brotli, format.content_type(), &filename).await?;
+    if response.status().is_success() {
+        response.headers_mut().insert(
+            header::CONTENT_ENCODING,
+            HeaderValue::from_static(Encoding::Brotli.name()),
+        );
+    }
+
+    Ok(response)
+}
+
+/// レイヤーの成果物のマニフェストを返す。
+///
+/// # Arguments
+///
+/// * `req` - HTTPリクエスト。
+/// * `pool` - データベースコネクションプール。
+/// * `artifacts` - 成果物を保存するディレクトリ。
+/// * `layer` - レイヤー名。
+/// * `format` - ファイルの形式。
+///
+/// # Returns
+///
+/// `sha256sum`の出力と同じ形式のマニフェストを返すレスポンス。
+async fn checksums(
+    req: &HttpRequest,
+    pool: &PgPool,
+    artifacts: &ExportArtifacts,
+    layer: &str,
+    format: ExportFormat,
+) -> Result<HttpResponse, ApiError> {
+    let (_, path) = export_artifact(pool, artifacts, layer, format).await?;
+    let manifest = with_added_extension(&path, MANIFEST_EXTENSION);
+    let body = tokio::fs::read(&manifest).await.map_err(|e| {
+        tracing::error!(
+            "マニフェスト({})を読み込めません。{}",
+            manifest.display(),
+            e
+        );
+        ApiError::ExportFailed
+    })?;
+
+    Ok(conditional_response(
+        req,
+        ContentType::plaintext(),
+        web::Bytes::from(body),
+    ))
+}
+
+#[tracing::instrument(name = "Export FlatGeobuf", skip(req, pool, artifacts))]
+pub async fn flatgeobuf(
+    req: HttpRequest,
+    path: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+    artifacts: web::Data<ExportArtifacts>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let layer = path.into_inner().0;
+
+    Ok(export(&req, &pool, &artifacts, &layer, ExportFormat::FlatGeobuf).await?)
+}
+
+#[tracing::instrument(name = "Export FlatGeobuf checksums", skip(req, pool, artifacts))]
+pub async fn flatgeobuf_checksums(
+    req: HttpRequest,
+    path: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+    artifacts: web::Data<ExportArtifacts>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let layer = path.into_inner().0;
+
+    Ok(checksums(&req, &pool, &artifacts, &layer, ExportFormat::FlatGeobuf).await?)
+}
+
+/// レイヤーのすべてのフィーチャーを、FlatGeobufでエンコードしてファイルに書き込む。
+///
+/// フィーチャーは1件ずつ問い合わせてエンコーダーに追加するため、すべてのフィーチャーをメモリーに読み込まない。
+/// エンコーダーはヘッダーと空間インデックスをフィーチャーより前に書き込むため、すべてのフィーチャーを追加してから
+/// ファイルに書き込む。ジオメトリがないフィーチャーは書き込まない。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+/// * `collection` - フィーチャーコレクション。
+/// * `path` - 書き込むファイルのパス。
+async fn write_flatgeobuf(
+    pool: PgPool,
+    collection: &'static Collection,
+    path: PathBuf,
+) -> Result<(), ApiError> {
+    let columns = export_columns(collection);
+    let mut encoder = FlatGeobufEncoder::new(
+        collection.id,
+        collection.geometry_type,
+        &columns,
+        EPSG_WGS84,
+    )
+    .map_err(|e| export_failed(&path, e))?;
+    let column_count = columns.len();
+    let sql = export_sql(collection);
+    async {
+        let mut rows = sqlx::query(&sql).fetch(&pool);
+        while let Some(row) = rows.try_next().await.map_err(ApiError::from)? {
+            add_flatgeobuf_row(&mut encoder, &row, column_count)
+                .map_err(|e| export_failed(&path, e))?;
+        }
+        Ok::<_, ApiError>(())
+    }
+    .instrument(query_span("SELECT export"))
+    .await?;
+    if 0 < encoder.skipped() {
+        tracing::warn!(
+            "ジオメトリがない{}件のフィーチャーを、FlatGeobufに書き込みませんでした。",
+            encoder.skipped()
+        );
+    }
+
+    let written = web::block(move || {
+        let file = std::fs::File::create(&path).map_err(|e| export_failed(&path, e))?;
+        encoder
+            .write(&mut std::io::BufWriter::new(file))
+            .map_err(|e| export_failed(&path, e))
+    })
+    .await;
+    match written {
+        Ok(result) => result,
+        Err(_) => Err(ApiError::Internal),
+    }
+}
+
+#[tracing::instrument(name = "Export CSV", skip(req, pool, artifacts))]
+pub async fn csv(
+    req: HttpRequest,
+    path: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+    artifacts: web::Data<ExportArtifacts>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let layer = path.into_inner().0;
+
+    Ok(export(&req, &pool, &artifacts, &layer, ExportFormat::Csv).await?)
+}
+
+#[tracing::instrument(name = "Export CSV checksums", skip(req, pool, artifacts))]
+pub async fn csv_checksums(
+    req: HttpRequest,
+    path: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+    artifacts: web::Data<ExportArtifacts>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let layer = path.into_inner().0;
+
+    Ok(checksums(&req, &pool, &artifacts, &layer, ExportFormat::Csv).await?)
+}
+
+/// レイヤーのすべてのフィーチャーを、CSVでエンコードするストリームを返す。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+/// * `collection` - フィーチャーコレクション。
+///
+/// # Returns
+///
+/// CSVのストリーム。
+async fn csv_body(
+    pool: PgPool,
+    collection: &'static Collection,
+) -> Result<impl Stream<Item = Result<web::Bytes, actix_web::Error>>, ApiError> {
+    let mut columns = export_columns(collection);
+    if is_point_layer(collection) {
+        columns.extend(["lon", "lat"]);
+    } else {
+        columns.push("wkt");
+    }
+    let mut head = UTF8_BOM.to_vec();
+    let names: Vec<Option<&str>> = columns.iter().map(|column| Some(*column)).collect();
+    head.extend(encode_csv_record(&names));
+    let column_count = columns.len();
+
+    stream_rows(
+        pool,
+        export_csv_sql(collection),
+        PgArguments::default(),
+        head,
+        move |_, row| {
+            let values = (0..column_count)
+                .map(|i| row.try_get::<Option<String>, _>(i))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(encode_csv_record(&values))
+        },
+        |_| Vec::new(),
+    )
+    .await
+}
@@ -0,0 +1,308 @@
+//! FlatGeobuf形式のエンコーダー。
+//!
+//! `flatgeobuf`クレートの`FgbWriter`に、geozeroでジオメトリを渡して書き込む。`FgbWriter`はフィーチャーを
+//! 一時ファイルに書き込んで、最後にヘッダー、空間インデックス及びフィーチャーの順に出力する。
+//!
+//! <https://flatgeobuf.org/>
+
+use std::io::Write;
+
+use ::flatgeobuf::{ColumnType, ColumnValue, Crs, CrsArgs, FgbWriter, GeometryType};
+use geo_types::Geometry;
+
+/// GeoJSONのジオメトリの種類の名前から、FlatGeobufのジオメトリの種類を返す。
+///
+/// # Arguments
+///
+/// * `name` - GeoJSONのジオメトリの種類の名前。
+///
+/// # Returns
+///
+/// ジオメトリの種類。名前に一致する種類がない場合は`Unknown`。
+pub fn geometry_type(name: &str) -> GeometryType {
+    match name {
+        "Point" => GeometryType::Point,
+        "LineString" => GeometryType::LineString,
+        "Polygon" => GeometryType::Polygon,
+        "MultiPoint" => GeometryType::MultiPoint,
+        "MultiLineString" => GeometryType::MultiLineString,
+        "MultiPolygon" => GeometryType::MultiPolygon,
+        "GeometryCollection" => GeometryType::GeometryCollection,
+        _ => GeometryType::Unknown,
+    }
+}
+
+/// フィーチャーをFlatGeobufにエンコードするエンコーダー。
+///
+/// 属性の列は、すべて文字列型とする。
+pub struct FlatGeobufEncoder {
+    /// フィーチャーを書き込むライター
+    writer: FgbWriter<'static>,
+    /// 属性の列の名前
+    columns: Vec<String>,
+    /// ジオメトリがないため書き込まなかったフィーチャーの数
+    skipped: usize,
+}
+
+impl FlatGeobufEncoder {
+    /// エンコーダーを構築する。
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - レイヤー名。
+    /// * `geometry_type` - GeoJSONのジオメトリの種類の名前。
+    /// * `columns` - 属性の列の名前。
+    /// * `epsg` - 座標参照系のEPSGコード。
+    ///
+    /// # Returns
+    ///
+    /// エンコーダー。
+    pub fn new(
+        name: &str,
+        geometry_type: &str,
+        columns: &[&str],
+        epsg: i32,
+    ) -> anyhow::Result<Self> {
+        let mut writer =
+            FgbWriter::create(name, self::geometry_type(geometry_type), |fbb, header| {
+                header.crs = Some(Crs::create(
+                    fbb,
+                    &CrsArgs {
+                        code: epsg,
+                        ..Default::default()
+                    },
+                ));
+            })?;
+        for column in columns {
+            writer.add_column(column, ColumnType::String, |_, column| {
+                column.nullable = true;
+            });
+        }
+
+        Ok(Self {
+            writer,
+            columns: columns.iter().map(|column| column.to_string()).collect(),
+            skipped: 0,
+        })
+    }
+
+    /// フィーチャーを追加する。
+    ///
+    /// FlatGeobufのフィーチャーにはジオメトリが必要なため、ジオメトリがないフィーチャーは追加しない。
+    ///
+    /// # Arguments
+    ///
+    /// * `geometry` - フィーチャーのジオメトリ。
+    /// * `values` - 列の順に並べた属性の値。Noneの値は書き込まない。
+    pub fn add_feature(
+        &mut self,
+        geometry: Option<&Geometry<f64>>,
+        values: &[Option<String>],
+    ) -> anyhow::Result<()> {
+        let geometry = match geometry {
+            Some(geometry) => geometry,
+            None => {
+                self.skipped += 1;
+                return Ok(());
+            }
+        };
+        let columns = &self.columns;
+        let mut result = Ok(());
+        self.writer.add_feature_geom(geometry.clone(), |feature| {
+            for (i, (column, value)) in columns.iter().zip(values).enumerate() {
+                if let Some(value) = value {
+                    if let Err(e) = feature.property(i, column, &ColumnValue::String(value)) {
+                        result = Err(e);
+                        return;
+                    }
+                }
+            }
+        })?;
+
+        Ok(result?)
+    }
+
+    /// ジオメトリがないため書き込まなかったフィーチャーの数を返す。
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    /// ヘッダー、空間インデックス及びフィーチャーを書き込む。
+    ///
+    /// # Arguments
+    ///
+    /// * `out` - 書き込み先。
+    pub fn write<W: Write>(mut self, out: &mut W) -> anyhow::Result<()> {
+        self.writer.write(out)?;
+
+        Ok(out.flush()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use ::flatgeobuf::{FallibleStreamingIterator, FgbReader};
+    use geo_types::{line_string, point, polygon, Geometry};
+    use geozero::{FeatureProperties, ToGeo};
+
+    use super::*;
+
+    /// フィクスチャーのフィーチャーコレクションをエンコードする。
+    fn encode_fixture() -> Vec<u8> {
+        let mut encoder =
+            FlatGeobufEncoder::new("post_offices", "Point", &["id", "name", "address"], 4326)
+                .unwrap();
+        let features = [
+            (
+                point!(x: 136.7223, y: 35.3912),
+                [Some("1"), Some("岐阜中央郵便局"), Some("岐阜市")],
+            ),
+            (
+                point!(x: 136.9066, y: 35.1815),
+                [Some("2"), Some("名古屋中央郵便局"), None],
+            ),
+        ];
+        for (point, values) in features {
+            let values: Vec<Option<String>> = values
+                .iter()
+                .map(|value| value.map(|value| value.to_string()))
+                .collect();
+            encoder
+                .add_feature(Some(&Geometry::Point(point)), &values)
+                .unwrap();
+        }
+        encoder
+            .add_feature(None, &[Some("3".to_string()), None, None])
+            .unwrap();
+        assert_eq!(encoder.skipped(), 1);
+        let mut bytes = Vec::new();
+        encoder.write(&mut bytes).unwrap();
+
+        bytes
+    }
+
+    #[test]
+    fn header_describes_layer() {
+        let bytes = encode_fixture();
+        let mut cursor = Cursor::new(bytes);
+        let reader = FgbReader::open(&mut cursor).unwrap();
+        let header = reader.header();
+        assert_eq!(header.name(), Some("post_offices"));
+        assert_eq!(header.geometry_type(), GeometryType::Point);
+        assert_eq!(header.features_count(), 2);
+        assert_eq!(header.crs().map(|crs| crs.code()), Some(4326));
+        let columns: Vec<(&str, ColumnType)> = header
+            .columns()
+            .unwrap()
+            .iter()
+            .map(|column| (column.name(), column.type_()))
+            .collect();
+        assert_eq!(
+            columns,
+            vec![
+                ("id", ColumnType::String),
+                ("name", ColumnType::String),
+                ("address", ColumnType::String),
+            ]
+        );
+    }
+
+    #[test]
+    fn features_round_trip() {
+        let bytes = encode_fixture();
+        let mut cursor = Cursor::new(bytes);
+        let mut reader = FgbReader::open(&mut cursor).unwrap();
+        reader.select_all().unwrap();
+        let mut features = Vec::new();
+        while let Some(feature) = reader.next().unwrap() {
+            features.push((
+                feature.property::<String>("id").unwrap(),
+                feature.property::<String>("name"),
+                feature.property::<String>("address"),
+                feature.to_geo().unwrap(),
+            ));
+        }
+        // 空間インデックスの順に並ぶため、IDの順に並べ替える
+        features.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            features,
+            vec![
+                (
+                    "1".to_string(),
+                    Some("岐阜中央郵便局".to_string()),
+                    Some("岐阜市".to_string()),
+                    Geometry::Point(point!(x: 136.7223, y: 35.3912)),
+                ),
+                (
+                    "2".to_string(),
+                    Some("名古屋中央郵便局".to_string()),
+                    None,
+                    Geometry::Point(point!(x: 136.9066, y: 35.1815)),
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn polygons_and_lines_round_trip() {
+        let polygon = Geometry::Polygon(polygon!(
+            exterior: [
+                (x: 136.0, y: 35.0),
+                (x: 137.0, y: 35.0),
+                (x: 137.0, y: 36.0),
+                (x: 136.0, y: 35.0),
+            ],
+            interiors: [[
+                (x: 136.6, y: 35.2),
+                (x: 136.8, y: 35.2),
+                (x: 136.8, y: 35.4),
+                (x: 136.6, y: 35.2),
+            ]],
+        ));
+        let line = Geometry::LineString(line_string![
+            (x: 136.1, y: 35.1),
+            (x: 136.5, y: 35.5),
+        ]);
+        for (name, geometry, geometry_type) in [
+            ("Polygon", polygon, GeometryType::Polygon),
+            ("LineString", line, GeometryType::LineString),
+        ] {
+            let mut encoder = FlatGeobufEncoder::new("layer", name, &["id"], 4326).unwrap();
+            encoder
+                .add_feature(Some(&geometry), &[Some("1".to_string())])
+                .unwrap();
+            let mut bytes = Vec::new();
+            encoder.write(&mut bytes).unwrap();
+
+            let mut cursor = Cursor::new(bytes);
+            let mut reader = FgbReader::open(&mut cursor).unwrap();
+            assert_eq!(reader.header().geometry_type(), geometry_type);
+            assert_eq!(reader.header().features_count(), 1);
+            reader.select_all().unwrap();
+            let feature = reader.next().unwrap().unwrap();
+            assert_eq!(feature.to_geo().unwrap(), geometry);
+        }
+    }
+
+    #[test]
+    fn empty_collection_has_header_only() {
+        let encoder = FlatGeobufEncoder::new("schools", "Point", &["id"], 4326).unwrap();
+        let mut bytes = Vec::new();
+        encoder.write(&mut bytes).unwrap();
+
+        let mut cursor = Cursor::new(bytes);
+        let mut reader = FgbReader::open(&mut cursor).unwrap();
+        assert_eq!(reader.header().features_count(), 0);
+        reader.select_all().unwrap();
+        assert!(reader.next().unwrap().is_none());
+    }
+
+    #[test]
+    fn geometry_types_follow_geojson_names() {
+        assert_eq!(geometry_type("Point"), GeometryType::Point);
+        assert_eq!(geometry_type("MultiPolygon"), GeometryType::MultiPolygon);
+        assert_eq!(geometry_type("Unknown"), GeometryType::Unknown);
+    }
+}
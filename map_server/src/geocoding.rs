@@ -0,0 +1,612 @@
+//! 経緯度から、その位置を含む都道府県と市区町村や、最も近い郵便局を求めるエンドポイント。
+//!
+//! 逆ジオコーディングのリクエストは特定の地域に集中する傾向があるため、求めた結果を、位置を含む格子
+//! (Webメルカトル座標で一辺が既定で50メートルの正方形)ごとに、有効期限付きでキャッシュする。
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use actix_web::{web, HttpResponse};
+use lru::LruCache;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tracing::Instrument;
+
+use utils::tiles::{MAX_LATITUDE, WEB_MERCATOR_EXTENT};
+use utils::{EPSG_WEB_MERCATOR, EPSG_WGS84};
+
+use crate::config::GeocodingCacheSettings;
+use crate::errors::ApiError;
+use crate::telemetries::query_span;
+
+/// 位置を指定するクエリパラメーター。
+#[derive(Debug, Deserialize)]
+pub struct PointQuery {
+    /// 経度(WGS84)
+    lon: Option<f64>,
+    /// 緯度(WGS84)
+    lat: Option<f64>,
+    /// キャッシュを使用するか(既定値はtrue、falseの場合は常にデータベースに問い合わせる)
+    cache: Option<bool>,
+}
+
+impl PointQuery {
+    /// 経度と緯度を返す。
+    ///
+    /// # Returns
+    ///
+    /// 経度と緯度。経度または緯度が指定されていないか、範囲外の場合はエラー。
+    pub fn lon_lat(&self) -> Result<(f64, f64), ApiError> {
+        check_lon_lat(self.lon, self.lat)
+    }
+
+    /// キャッシュを使用するか返す。
+    pub fn uses_cache(&self) -> bool {
+        self.cache.unwrap_or(true)
+    }
+}
+
+/// クエリパラメーターに指定された経度と緯度を確認する。
+///
+/// # Arguments
+///
+/// * `lon` - 経度(WGS84)。
+/// * `lat` - 緯度(WGS84)。
+///
+/// # Returns
+///
+/// 経度と緯度。経度または緯度が指定されていないか、範囲外の場合はエラー。
+pub fn check_lon_lat(lon: Option<f64>, lat: Option<f64>) -> Result<(f64, f64), ApiError> {
+    let lon = lon.ok_or_else(|| ApiError::MissingParameter("lon".to_string()))?;
+    let lat = lat.ok_or_else(|| ApiError::MissingParameter("lat".to_string()))?;
+    if !(-180.0..=180.0).contains(&lon) || !(-90.0..=90.0).contains(&lat) {
+        return Err(ApiError::InvalidQuery);
+    }
+
+    Ok((lon, lat))
+}
+
+/// 位置を含む都道府県
+#[derive(Debug, Clone, Serialize)]
+pub struct LocatedPrefecture {
+    /// 都道府県コード
+    pub code: String,
+    /// 都道府県名
+    pub name: String,
+}
+
+/// 位置を含む市区町村
+#[derive(Debug, Clone, Serialize)]
+pub struct LocatedCity {
+    /// 市区町村コード
+    pub code: String,
+    /// 郡名または支庁名
+    pub area: Option<String>,
+    /// 市区町村名
+    pub name: String,
+}
+
+/// 位置を含む都道府県と市区町村
+#[derive(Debug, Clone, Serialize)]
+pub struct Location {
+    /// 位置を含む都道府県(含む都道府県がない場合はNone)
+    pub prefecture: Option<LocatedPrefecture>,
+    /// 位置を含む市区町村(含む市区町村がない場合はNone)
+    pub city: Option<LocatedCity>,
+}
+
+/// 位置を含む都道府県と市区町村を求める。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+/// * `lon` - 経度(WGS84)。
+/// * `lat` - 緯度(WGS84)。
+///
+/// # Returns
+///
+/// 位置を含む都道府県と市区町村。
+pub async fn locate_point(pool: &PgPool, lon: f64, lat: f64) -> Result<Location, ApiError> {
+    let prefecture = sqlx::query_as!(
+        LocatedPrefecture,
+        r#"
+        SELECT code as "code!", name
+        FROM prefectures
+        WHERE ST_Contains(geom, ST_Transform(ST_SetSRID(ST_MakePoint($1, $2), $3), $4))
+        ORDER BY code
+        LIMIT 1
+        "#,
+        lon,
+        lat,
+        EPSG_WGS84,
+        EPSG_WEB_MERCATOR,
+    )
+    .fetch_optional(pool)
+    .instrument(query_span("SELECT prefectures containing point"))
+    .await
+    .map_err(ApiError::from)?;
+    let city = sqlx::query_as!(
+        LocatedCity,
+        r#"
+        SELECT code as "code!", area, name
+        FROM cities
+        WHERE ST_Contains(geom, ST_Transform(ST_SetSRID(ST_MakePoint($1, $2), $3), $4))
+        ORDER BY code
+        LIMIT 1
+        "#,
+        lon,
+        lat,
+        EPSG_WGS84,
+        EPSG_WEB_MERCATOR,
+    )
+    .fetch_optional(pool)
+    .instrument(query_span("SELECT cities containing point"))
+    .await
+    .map_err(ApiError::from)?;
+
+    Ok(Location { prefecture, city })
+}
+
+/// 位置から最も近い郵便局
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClosestPostOffice {
+    /// 郵便局コード
+    pub post_office_code: String,
+    /// 郵便局名
+    pub name: String,
+    /// 住所
+    pub address: String,
+    /// 位置からの距離(メートル)
+    pub distance: f64,
+}
+
+/// 逆ジオコーディングの結果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReverseGeocode {
+    /// 位置を含む都道府県と市区町村
+    #[serde(flatten)]
+    pub location: Location,
+    /// 位置から最も近い郵便局(郵便局が登録されていない場合はNone)
+    pub post_office: Option<ClosestPostOffice>,
+}
+
+/// 位置から最も近い郵便局を求める。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+/// * `lon` - 経度(WGS84)。
+/// * `lat` - 緯度(WGS84)。
+///
+/// # Returns
+///
+/// 位置から最も近い郵便局。郵便局が登録されていない場合はNone。
+pub async fn closest_post_office(
+    pool: &PgPool,
+    lon: f64,
+    lat: f64,
+) -> Result<Option<ClosestPostOffice>, ApiError> {
+    sqlx::query_as!(
+        ClosestPostOffice,
+        r#"
+        WITH target AS (
+            SELECT ST_SetSRID(ST_MakePoint($1, $2), $3) as point
+        )
+        SELECT
+            p.post_office_code as "post_office_code!", p.name, p.address,
+            ST_Distance(ST_Transform(p.geom, $3)::geography, t.point::geography) as "distance!"
+        FROM post_offices p, target t
+        ORDER BY p.geom <-> ST_Transform(t.point, $4)
+        LIMIT 1
+        "#,
+        lon,
+        lat,
+        EPSG_WGS84,
+        EPSG_WEB_MERCATOR,
+    )
+    .fetch_optional(pool)
+    .instrument(query_span("SELECT closest post_office"))
+    .await
+    .map_err(ApiError::from)
+}
+
+/// 逆ジオコーディングに使用するレイヤーの名前。
+const GEOCODING_LAYERS: [&str; 3] = ["prefectures", "cities", "post_offices"];
+
+/// 逆ジオコーディングの結果をキャッシュする格子。
+///
+/// 格子は、Webメルカトル座標の原点(経度0度、緯度0度)から、X座標とY座標を格子の大きさで割った値の整数部で識別する。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct GridCell {
+    /// 格子のX方向の位置
+    x: i64,
+    /// 格子のY方向の位置
+    y: i64,
+}
+
+impl GridCell {
+    /// 経緯度を含む格子を返す。
+    ///
+    /// # Arguments
+    ///
+    /// * `lon` - 経度(WGS84)。
+    /// * `lat` - 緯度(WGS84)。緯度は、Webメルカトル投影法で表せる範囲に収める。
+    /// * `size` - 格子の大きさ(Webメルカトル座標のメートル)。
+    ///
+    /// # Returns
+    ///
+    /// 格子。
+    fn containing(lon: f64, lat: f64, size: f64) -> Self {
+        let lat = lat.clamp(-MAX_LATITUDE, MAX_LATITUDE).to_radians();
+        let x = lon / 180.0 * WEB_MERCATOR_EXTENT;
+        let y = lat.tan().asinh() / std::f64::consts::PI * WEB_MERCATOR_EXTENT;
+
+        Self {
+            x: (x / size).floor() as i64,
+            y: (y / size).floor() as i64,
+        }
+    }
+}
+
+/// 格子ごとにキャッシュした結果。
+struct CachedResult<T> {
+    /// 結果
+    value: T,
+    /// 結果をキャッシュした日時
+    stored_at: Instant,
+}
+
+/// 最近使用されていない格子から破棄する、有効期限付きの結果のキャッシュ。
+struct CellCache<T> {
+    /// 格子ごとにキャッシュした結果
+    entries: Mutex<LruCache<GridCell, CachedResult<T>>>,
+    /// キャッシュした結果を返した回数
+    hits: AtomicU64,
+    /// 結果をキャッシュしていなかった回数
+    misses: AtomicU64,
+}
+
+/// 結果のキャッシュの使用状況。
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CellCacheStats {
+    /// キャッシュしている格子の数
+    pub entries: usize,
+    /// キャッシュした結果を返した回数
+    pub hits: u64,
+    /// 結果をキャッシュしていなかった回数(有効期限が切れていた場合を含む)
+    pub misses: u64,
+}
+
+impl<T: Clone> CellCache<T> {
+    /// 結果のキャッシュを構築する。
+    fn new(capacity: usize) -> Self {
+        Self {
+            entries: Mutex::new(LruCache::new(capacity)),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// 格子の結果を取得する。
+    ///
+    /// # Arguments
+    ///
+    /// * `cell` - 格子。
+    /// * `ttl` - 結果をキャッシュする期間。
+    ///
+    /// # Returns
+    ///
+    /// キャッシュした結果。キャッシュしていない場合、または有効期限が切れている場合はNone。
+    fn get(&self, cell: &GridCell, ttl: Duration) -> Option<T> {
+        let mut entries = self.entries.lock().unwrap();
+        let value = match entries.get(cell) {
+            Some(entry) if entry.stored_at.elapsed() < ttl => Some(entry.value.clone()),
+            Some(_) => {
+                entries.pop(cell);
+                None
+            }
+            None => None,
+        };
+        let counter = if value.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+
+        value
+    }
+
+    /// 格子の結果をキャッシュする。
+    fn insert(&self, cell: GridCell, value: T) {
+        let entry = CachedResult {
+            value,
+            stored_at: Instant::now(),
+        };
+        self.entries.lock().unwrap().put(cell, entry);
+    }
+
+    /// キャッシュした結果をすべて破棄する。
+    fn clear(&self) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        let count = entries.len();
+        entries.clear();
+
+        count
+    }
+
+    /// 結果のキャッシュの使用状況を返す。
+    fn stats(&self) -> CellCacheStats {
+        CellCacheStats {
+            entries: self.entries.lock().unwrap().len(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// 逆ジオコーディングの結果のキャッシュの使用状況。
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeocodingCacheStats {
+    /// 結果をキャッシュするか
+    pub enabled: bool,
+    /// 格子ごとにキャッシュする結果の最大数
+    pub capacity: usize,
+    /// 結果をキャッシュする期間(秒)
+    pub ttl_seconds: u64,
+    /// 格子の大きさ(メートル)
+    pub cell_size_meters: f64,
+    /// 位置を含む都道府県と市区町村のキャッシュの使用状況(キャッシュしない場合はNone)
+    pub locations: Option<CellCacheStats>,
+    /// 位置から最も近い郵便局のキャッシュの使用状況(キャッシュしない場合はNone)
+    pub post_offices: Option<CellCacheStats>,
+}
+
+/// 逆ジオコーディングの結果を、位置を含む格子ごとにキャッシュする。
+///
+/// 同じ格子に含まれる位置には、最初に問い合わせた位置の結果を返すため、格子の大きさの範囲で結果が不正確になる。
+/// 特に、最も近い郵便局までの距離は、最初に問い合わせた位置からの距離である。
+pub struct GeocodingCache {
+    /// 位置を含む都道府県と市区町村(キャッシュしない場合はNone)
+    locations: Option<CellCache<Location>>,
+    /// 位置から最も近い郵便局(キャッシュしない場合はNone)
+    post_offices: Option<CellCache<Option<ClosestPostOffice>>>,
+    /// 格子ごとにキャッシュする結果の最大数
+    capacity: usize,
+    /// 結果をキャッシュする期間
+    ttl: Duration,
+    /// 格子の大きさ(メートル)
+    cell_size: f64,
+}
+
+impl GeocodingCache {
+    /// 逆ジオコーディングの結果のキャッシュを構築する。
+    ///
+    /// # Arguments
+    ///
+    /// * `settings` - 逆ジオコーディングの結果のキャッシュの設定。最大数または期間が0の場合はキャッシュしない。
+    ///
+    /// # Returns
+    ///
+    /// 逆ジオコーディングの結果のキャッシュ。
+    pub fn new(settings: &GeocodingCacheSettings) -> Self {
+        let enabled = 0 < settings.capacity && 0 < settings.ttl_seconds;
+        Self {
+            locations: enabled.then(|| CellCache::new(settings.capacity)),
+            post_offices: enabled.then(|| CellCache::new(settings.capacity)),
+            capacity: if enabled { settings.capacity } else { 0 },
+            ttl: Duration::from_secs(settings.ttl_seconds),
+            cell_size: settings.cell_size_meters,
+        }
+    }
+
+    /// キャッシュした結果をすべて破棄する。
+    ///
+    /// # Returns
+    ///
+    /// 破棄した結果の数。
+    pub fn invalidate(&self) -> usize {
+        self.locations.as_ref().map_or(0, CellCache::clear)
+            + self.post_offices.as_ref().map_or(0, CellCache::clear)
+    }
+
+    /// 逆ジオコーディングに使用するレイヤーが変更された場合に、キャッシュした結果をすべて破棄する。
+    ///
+    /// # Arguments
+    ///
+    /// * `layers` - 変更されたレイヤーの名前。
+    ///
+    /// # Returns
+    ///
+    /// 破棄した結果の数。
+    pub fn invalidate_layers(&self, layers: &[&str]) -> usize {
+        if layers.iter().any(|layer| GEOCODING_LAYERS.contains(layer)) {
+            self.invalidate()
+        } else {
+            0
+        }
+    }
+
+    /// 逆ジオコーディングの結果のキャッシュの使用状況を返す。
+    pub fn stats(&self) -> GeocodingCacheStats {
+        GeocodingCacheStats {
+            enabled: self.locations.is_some(),
+            capacity: self.capacity,
+            ttl_seconds: self.ttl.as_secs(),
+            cell_size_meters: self.cell_size,
+            locations: self.locations.as_ref().map(CellCache::stats),
+            post_offices: self.post_offices.as_ref().map(CellCache::stats),
+        }
+    }
+
+    /// 位置を含む都道府県と市区町村を、キャッシュから取得するか、データベースに問い合わせて求める。
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - データベースコネクションプール。
+    /// * `lon` - 経度(WGS84)。
+    /// * `lat` - 緯度(WGS84)。
+    /// * `use_cache` - キャッシュを使用するか。falseの場合は、キャッシュを参照も更新もしない。
+    ///
+    /// # Returns
+    ///
+    /// 位置を含む都道府県と市区町村。
+    pub async fn locate(
+        &self,
+        pool: &PgPool,
+        lon: f64,
+        lat: f64,
+        use_cache: bool,
+    ) -> Result<Location, ApiError> {
+        let cache = self.locations.as_ref().filter(|_| use_cache);
+        let cell = GridCell::containing(lon, lat, self.cell_size);
+        if let Some(location) = cache.and_then(|cache| cache.get(&cell, self.ttl)) {
+            return Ok(location);
+        }
+        let location = locate_point(pool, lon, lat).await?;
+        if let Some(cache) = cache {
+            cache.insert(cell, location.clone());
+        }
+
+        Ok(location)
+    }
+
+    /// 位置から最も近い郵便局を、キャッシュから取得するか、データベースに問い合わせて求める。
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - データベースコネクションプール。
+    /// * `lon` - 経度(WGS84)。
+    /// * `lat` - 緯度(WGS84)。
+    /// * `use_cache` - キャッシュを使用するか。falseの場合は、キャッシュを参照も更新もしない。
+    ///
+    /// # Returns
+    ///
+    /// 位置から最も近い郵便局。郵便局が登録されていない場合はNone。
+    pub async fn closest_post_office(
+        &self,
+        pool: &PgPool,
+        lon: f64,
+        lat: f64,
+        use_cache: bool,
+    ) -> Result<Option<ClosestPostOffice>, ApiError> {
+        let cache = self.post_offices.as_ref().filter(|_| use_cache);
+        let cell = GridCell::containing(lon, lat, self.cell_size);
+        if let Some(post_office) = cache.and_then(|cache| cache.get(&cell, self.ttl)) {
+            return Ok(post_office);
+        }
+        let post_office = closest_post_office(pool, lon, lat).await?;
+        if let Some(cache) = cache {
+            cache.insert(cell, post_office.clone());
+        }
+
+        Ok(post_office)
+    }
+}
+
+#[tracing::instrument(name = "Locate", skip(pool, cache))]
+pub async fn locate(
+    query: web::Query<PointQuery>,
+    pool: web::Data<PgPool>,
+    cache: web::Data<GeocodingCache>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (lon, lat) = query.lon_lat()?;
+    let location = cache
+        .locate(pool.as_ref(), lon, lat, query.uses_cache())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(location))
+}
+
+#[tracing::instrument(name = "Reverse geocode", skip(pool, cache))]
+pub async fn reverse_geocode(
+    query: web::Query<PointQuery>,
+    pool: web::Data<PgPool>,
+    cache: web::Data<GeocodingCache>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (lon, lat) = query.lon_lat()?;
+    let location = cache
+        .locate(pool.as_ref(), lon, lat, query.uses_cache())
+        .await?;
+    let post_office = cache
+        .closest_post_office(pool.as_ref(), lon, lat, query.uses_cache())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(ReverseGeocode {
+        location,
+        post_office,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grid_cell_contains_nearby_points() {
+        // 東京駅付近の格子の大きさ(50メートル)より十分に近い2点は、同じ格子に含まれる
+        let cell = GridCell::containing(139.76715, 35.68125, 50.0);
+        assert_eq!(cell, GridCell::containing(139.767151, 35.681251, 50.0));
+        assert_ne!(cell, GridCell::containing(139.7681, 35.68125, 50.0));
+        assert_ne!(cell, GridCell::containing(139.76715, 35.6822, 50.0));
+    }
+
+    #[test]
+    fn grid_cell_is_counted_from_origin() {
+        assert_eq!(
+            GridCell::containing(0.0, 0.0, 50.0),
+            GridCell { x: 0, y: 0 }
+        );
+        assert_eq!(
+            GridCell::containing(-0.0001, -0.0001, 50.0),
+            GridCell { x: -1, y: -1 }
+        );
+        // 緯度はWebメルカトル投影法で表せる範囲に収める
+        assert_eq!(
+            GridCell::containing(180.0, 90.0, 1000.0),
+            GridCell::containing(180.0, MAX_LATITUDE, 1000.0)
+        );
+    }
+
+    #[test]
+    fn cell_cache_counts_hits_and_expires() {
+        let cache = CellCache::new(2);
+        let cell = GridCell { x: 1, y: 2 };
+        assert_eq!(cache.get(&cell, Duration::from_secs(60)), None::<u32>);
+        cache.insert(cell, 7);
+        assert_eq!(cache.get(&cell, Duration::from_secs(60)), Some(7));
+        assert_eq!(cache.get(&cell, Duration::ZERO), None);
+        let stats = cache.stats();
+        assert_eq!((stats.entries, stats.hits, stats.misses), (0, 1, 2));
+    }
+
+    #[test]
+    fn geocoding_cache_is_invalidated_by_geocoding_layers() {
+        let cache = GeocodingCache::new(&GeocodingCacheSettings::default());
+        let location = Location {
+            prefecture: None,
+            city: None,
+        };
+        let locations = cache.locations.as_ref().unwrap();
+        locations.insert(GridCell { x: 0, y: 0 }, location);
+        assert_eq!(cache.invalidate_layers(&["railways"]), 0);
+        assert_eq!(cache.invalidate_layers(&["post_offices"]), 1);
+        assert_eq!(locations.stats().entries, 0);
+    }
+
+    #[test]
+    fn geocoding_cache_is_disabled_by_zero_capacity() {
+        let cache = GeocodingCache::new(&GeocodingCacheSettings {
+            capacity: 0,
+            ..Default::default()
+        });
+        let stats = cache.stats();
+        assert!(!stats.enabled);
+        assert!(stats.locations.is_none());
+    }
+}
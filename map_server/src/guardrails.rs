@@ -0,0 +1,104 @@
+use serde::Deserialize;
+use utils::tiles::Tile;
+
+use crate::layers::{TILE_MAX_ZOOM, TILE_MIN_ZOOM};
+
+/// 配信する範囲(WGS84経緯度)。
+///
+/// 設定ファイルや環境変数には`west,south,east,north`形式の文字列で指定する。
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Bounds {
+    /// 西端の経度
+    pub west: f64,
+    /// 南端の緯度
+    pub south: f64,
+    /// 東端の経度
+    pub east: f64,
+    /// 北端の緯度
+    pub north: f64,
+}
+
+impl std::str::FromStr for Bounds {
+    type Err = String;
+
+    /// `west,south,east,north`形式の文字列から、配信する範囲を構築する。
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s
+            .split(',')
+            .map(|value| value.trim().parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()
+            .map_err(|e| format!("{}", e))?;
+        if values.len() != 4 {
+            return Err("範囲はwest,south,east,northの形式で指定してください。".to_string());
+        }
+        let bounds = Self {
+            west: values[0],
+            south: values[1],
+            east: values[2],
+            north: values[3],
+        };
+        if bounds.east <= bounds.west || bounds.north <= bounds.south {
+            return Err("範囲の西端と東端、または南端と北端が逆です。".to_string());
+        }
+
+        Ok(bounds)
+    }
+}
+
+impl TryFrom<String> for Bounds {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// デプロイごとに、タイルを配信するズームレベルと範囲を制限する。
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct Guardrails {
+    /// 配信するズームレベルの最小値
+    pub min_zoom: u8,
+    /// 配信するズームレベルの最大値
+    pub max_zoom: u8,
+    /// 配信する範囲(制限しない場合はNone)
+    pub bounds: Option<Bounds>,
+}
+
+impl Default for Guardrails {
+    fn default() -> Self {
+        Self {
+            min_zoom: TILE_MIN_ZOOM,
+            max_zoom: TILE_MAX_ZOOM,
+            bounds: None,
+        }
+    }
+}
+
+impl Guardrails {
+    /// タイルが配信するズームレベルと範囲に含まれるか確認する。
+    ///
+    /// # Arguments
+    ///
+    /// * `tile` - タイル。
+    ///
+    /// # Returns
+    ///
+    /// タイルを配信する場合はtrue。
+    pub fn allows(&self, tile: &Tile) -> bool {
+        if tile.zoom() < self.min_zoom || self.max_zoom < tile.zoom() {
+            return false;
+        }
+        match self.bounds {
+            Some(bounds) => {
+                let tile = tile.lon_lat_bounds();
+                tile.min_x < bounds.east
+                    && bounds.west < tile.max_x
+                    && tile.min_y < bounds.north
+                    && bounds.south < tile.max_y
+            }
+            None => true,
+        }
+    }
+}
@@ -1,193 +1,2878 @@
-use actix_web::{web, HttpResponse, Responder};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hasher;
+use std::time::Instant;
+
+use actix_web::http::header::{self, ContentType, EntityTag, Header, IfNoneMatch};
+use actix_web::{web, web::Bytes, HttpRequest, HttpResponse, Responder};
+use futures_util::TryStreamExt;
 use geojson::{JsonObject, JsonValue};
-use geozero::wkb;
-use proj::Proj;
-use slippy_map_tiles as smt;
-use sqlx::{types::Uuid, PgPool};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::postgres::PgArguments;
+use sqlx::{Arguments, PgPool};
+use tracing::Instrument;
 
+use database::imports::{last_import_id, record_import, ImportSource};
+use register_post_office::delete_post_offices;
+use register_prefecture::delete_prefectures_and_cities;
+use utils::prefecture::PrefectureCode;
+use utils::tiles::Tile;
 use utils::{EPSG_WEB_MERCATOR, EPSG_WGS84};
 
+use crate::cdn_purge::{refresh_layers, CdnPurger};
+use crate::config::{
+    HealthSettings, TileSettings, DEFAULT_GEOJSON_PRECISION, MAX_GEOJSON_PRECISION,
+};
+use crate::custom_layers::CustomLayers;
+use crate::errors::ApiError;
+use crate::events::{DataEventKind, DataEvents};
+use crate::geocoding::{check_lon_lat, GeocodingCache};
+use crate::guardrails::{Bounds, Guardrails};
+use crate::http_cache::HttpCache;
+use crate::layers::{FilterKind, Layer, LayerTable};
+use crate::negotiation::{
+    negotiate, MediaFormat, COLLECTION_FORMATS, MVT_CONTENT_TYPE, TILE_FORMATS,
+};
+use crate::ogc_api::CRS84;
+use crate::pmtiles::PmtilesArchives;
+use crate::ranges::ranged_file_response;
+use crate::startup::WritePool;
+use crate::streaming::{stream_feature_collection, stream_feature_csv};
+use crate::styles::layer_style;
+use crate::telemetries::query_span;
+use crate::tile_cache::{
+    TileCache, TileFormat, TileKey, COMBINED_TILE_LAYER, COMBINED_TILE_LAYERS, TILE_LAYERS,
+};
+use crate::topojson::{encode_topology, DEFAULT_QUANTIZATION};
+use crate::waypoints::{encode_gpx, encode_kml, Waypoint};
+
 #[tracing::instrument(name = "Health check")]
 pub async fn health_check() -> impl Responder {
     "Are you ready?"
 }
 
-#[tracing::instrument(name = "Prefectures", skip(pool))]
-pub async fn prefectures(pool: web::Data<PgPool>) -> HttpResponse {
+#[tracing::instrument(name = "Liveness")]
+pub async fn liveness() -> HttpResponse {
+    HttpResponse::Ok().json(json!({ "status": "ok" }))
+}
+
+/// パスに一致するエンドポイントがない場合に、404 Not Foundを返す。
+pub async fn not_found() -> Result<HttpResponse, actix_web::Error> {
+    Err(ApiError::NotFound.into())
+}
+
+/// 経過時間をミリ秒で返す。
+fn elapsed_milliseconds(started_at: Instant) -> f64 {
+    started_at.elapsed().as_secs_f64() * 1000.0
+}
+
+#[tracing::instrument(name = "Readiness", skip(pool, health))]
+pub async fn readiness(pool: web::Data<PgPool>, health: web::Data<HealthSettings>) -> HttpResponse {
+    let mut ready = true;
+    let mut checks = JsonObject::new();
+
+    // データベースに問い合わせできるか確認
+    let started_at = Instant::now();
+    let database = match sqlx::query!("SELECT 1 as one")
+        .fetch_one(pool.as_ref())
+        .instrument(query_span("SELECT 1"))
+        .await
+    {
+        Ok(_) => json!({
+            "status": "ok",
+            "latencyMs": elapsed_milliseconds(started_at),
+        }),
+        Err(e) => {
+            ready = false;
+            json!({
+                "status": "unavailable",
+                "latencyMs": elapsed_milliseconds(started_at),
+                "error": ApiError::from(e).code(),
+            })
+        }
+    };
+    checks.insert("database".to_string(), database);
+
+    // PostGISを利用できるか確認
+    if health.check_postgis {
+        let started_at = Instant::now();
+        let postgis = match sqlx::query!("SELECT postgis_lib_version() as version")
+            .fetch_one(pool.as_ref())
+            .instrument(query_span("SELECT postgis_lib_version"))
+            .await
+        {
+            Ok(result) => json!({
+                "status": "ok",
+                "version": result.version,
+                "latencyMs": elapsed_milliseconds(started_at),
+            }),
+            Err(e) => {
+                ready = false;
+                json!({
+                    "status": "unavailable",
+                    "latencyMs": elapsed_milliseconds(started_at),
+                    "error": ApiError::from(e).code(),
+                })
+            }
+        };
+        checks.insert("postgis".to_string(), postgis);
+    }
+
+    let body = json!({
+        "status": if ready { "ok" } else { "unavailable" },
+        "checks": checks,
+    });
+    if ready {
+        HttpResponse::Ok().json(body)
+    } else {
+        HttpResponse::ServiceUnavailable().json(body)
+    }
+}
+
+/// フィーチャーを取得する範囲を指定するクエリパラメーター。
+#[derive(Debug, Deserialize)]
+pub struct BboxQuery {
+    /// フィーチャーを取得する範囲(`minx,miny,maxx,maxy`形式)
+    bbox: Option<Bounds>,
+    /// 範囲の座標の空間参照系のSRID(4326または3857、既定値は4326)
+    srid: Option<i32>,
+}
+
+/// 範囲を表現する矩形の座標とSRID。
+type Envelope = (Option<f64>, Option<f64>, Option<f64>, Option<f64>, i32);
+
+impl BboxQuery {
+    /// 範囲を、ST_MakeEnvelope関数に渡す座標とSRIDに変換する。
+    ///
+    /// # Returns
+    ///
+    /// 範囲の座標(範囲が指定されていない場合はNone)とSRID。SRIDが4326または3857でない場合はエラー。
+    fn envelope(&self) -> Result<Envelope, ApiError> {
+        let srid = self.srid.unwrap_or(EPSG_WGS84);
+        if srid != EPSG_WGS84 && srid != EPSG_WEB_MERCATOR {
+            return Err(ApiError::InvalidQuery);
+        }
+
+        Ok(match &self.bbox {
+            Some(bbox) => (
+                Some(bbox.west),
+                Some(bbox.south),
+                Some(bbox.east),
+                Some(bbox.north),
+                srid,
+            ),
+            None => (None, None, None, None, srid),
+        })
+    }
+}
+
+/// 都道府県を属性で絞り込むクエリパラメーター。
+#[derive(Debug, Deserialize)]
+pub struct PrefectureFilter {
+    /// 都道府県コード
+    code: Option<PrefectureCode>,
+    /// 名前に含まれる文字列
+    name: Option<String>,
+}
+
+/// 市区町村を属性で絞り込むクエリパラメーター。
+#[derive(Debug, Deserialize)]
+pub struct CityFilter {
+    /// 市区町村コードの先頭の文字列
+    code_prefix: Option<String>,
+    /// 名前に含まれる文字列
+    name: Option<String>,
+}
+
+/// 郵便局を属性で絞り込むクエリパラメーター。
+#[derive(Debug, Deserialize)]
+pub struct PostOfficeFilter {
+    /// 郵便局コード
+    post_office_code: Option<String>,
+    /// 郵便局が所在する市区町村の市区町村コード
+    city_code: Option<String>,
+    /// 名前に含まれる文字列
+    name: Option<String>,
+}
+
+/// 郵便局のタイルに含める郵便局を、分類で絞り込むクエリパラメーター。
+///
+/// それぞれのパラメーターには、カンマで区切った複数のコードを指定できる。
+#[derive(Debug, Deserialize)]
+pub struct PostOfficeCategoryFilter {
+    /// 郵便局コード
+    post_office_code: Option<String>,
+    /// 分類コード
+    category_code: Option<String>,
+    /// 細分類コード
+    subcategory_code: Option<String>,
+}
+
+/// 郵便局の分類の絞り込みに指定されたコード。
+#[derive(Default)]
+struct PostOfficeCategories {
+    /// 郵便局コード
+    post_office_codes: Option<Vec<String>>,
+    /// 分類コード
+    category_codes: Option<Vec<String>>,
+    /// 細分類コード
+    subcategory_codes: Option<Vec<String>>,
+}
+
+/// カンマで区切ったコードを、重複を除いて昇順に並べたベクタに変換する。
+///
+/// # Arguments
+///
+/// * `codes` - カンマで区切ったコード。
+///
+/// # Returns
+///
+/// コードのベクタ。コードが指定されていない場合はNone。英数字以外の文字を含むコードが指定された場合はエラー。
+fn split_codes(codes: Option<&str>) -> Result<Option<Vec<String>>, ApiError> {
+    let codes = match codes {
+        Some(codes) => codes,
+        None => return Ok(None),
+    };
+    let mut codes: Vec<String> = codes
+        .split(',')
+        .map(str::trim)
+        .filter(|code| !code.is_empty())
+        .map(str::to_string)
+        .collect();
+    if codes.is_empty() {
+        return Ok(None);
+    }
+    if !codes
+        .iter()
+        .all(|code| code.chars().all(|c| c.is_ascii_alphanumeric()))
+    {
+        return Err(ApiError::InvalidQuery);
+    }
+    codes.sort();
+    codes.dedup();
+
+    Ok(Some(codes))
+}
+
+impl PostOfficeCategoryFilter {
+    /// 指定されたコードを返す。
+    fn categories(&self) -> Result<PostOfficeCategories, ApiError> {
+        Ok(PostOfficeCategories {
+            post_office_codes: split_codes(self.post_office_code.as_deref())?,
+            category_codes: split_codes(self.category_code.as_deref())?,
+            subcategory_codes: split_codes(self.subcategory_code.as_deref())?,
+        })
+    }
+}
+
+impl PostOfficeCategories {
+    /// タイルキャッシュのキーに設定する、パラメーターの名前と値を連結した文字列を返す。
+    fn cache_params(&self) -> String {
+        [
+            ("post_office_code", &self.post_office_codes),
+            ("category_code", &self.category_codes),
+            ("subcategory_code", &self.subcategory_codes),
+        ]
+        .iter()
+        .filter_map(|(name, codes)| {
+            codes
+                .as_ref()
+                .map(|codes| format!("{}={}", name, codes.join(",")))
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+    }
+}
+
+/// ページで返すフィーチャーの件数の既定値。
+const DEFAULT_PAGE_LIMIT: u32 = 1000;
+
+/// ページで返すフィーチャーの件数の最大値。
+const MAX_PAGE_LIMIT: u32 = 10000;
+
+/// フィーチャーをページに分けて取得するクエリパラメーター。
+#[derive(Debug, Deserialize)]
+pub struct PageQuery {
+    /// 取得するフィーチャーの件数
+    limit: Option<u32>,
+    /// 取得を開始するフィーチャーの位置
+    offset: Option<u32>,
+}
+
+impl PageQuery {
+    /// 取得するフィーチャーの件数と、取得を開始するフィーチャーの位置を返す。
+    ///
+    /// 件数は、1以上で最大値以下に収める。
+    fn limit_offset(&self) -> (i64, i64) {
+        let limit = self
+            .limit
+            .unwrap_or(DEFAULT_PAGE_LIMIT)
+            .clamp(1, MAX_PAGE_LIMIT);
+
+        (limit as i64, self.offset.unwrap_or(0) as i64)
+    }
+}
+
+/// フィーチャーのプロパティの名前と、プロパティの値を選択する列の式。
+pub type PropertyColumns = [(&'static str, &'static str)];
+
+/// `/prefectures`が返すフィーチャーのプロパティ。
+const PREFECTURE_PROPERTIES: [(&str, &str); 2] = [("id", "id"), ("name", "name")];
+
+/// `/cities`が返すフィーチャーのプロパティ。
+const CITY_PROPERTIES: [(&str, &str); 4] = [
+    ("id", "id"),
+    ("code", "code"),
+    ("area", "area"),
+    ("name", "name"),
+];
+
+/// `/post_offices`が返すフィーチャーのプロパティ。
+///
+/// プロパティの名前は、タイル及びOGC API - Featuresが返すフィーチャーのプロパティの名前と同じにする。
+const POST_OFFICE_PROPERTIES: [(&str, &str); 10] = [
+    ("id", "id"),
+    ("cityCode", r#"city_code as "cityCode""#),
+    ("categoryCode", r#"category_code as "categoryCode""#),
+    (
+        "subcategoryCode",
+        r#"subcategory_code as "subcategoryCode""#,
+    ),
+    ("postOfficeCode", r#"post_office_code as "postOfficeCode""#),
+    ("name", "name"),
+    ("address", "address"),
+    ("cityName", r#"city_name as "cityName""#),
+    ("town", "town"),
+    ("block", "block"),
+];
+
+/// フィーチャーのプロパティとして返す属性を選択するクエリパラメーター。
+#[derive(Debug, Deserialize)]
+pub struct PropertiesQuery {
+    /// プロパティの名前をカンマで区切った文字列(指定しない場合はすべてのプロパティ)
+    properties: Option<String>,
+}
+
+impl PropertiesQuery {
+    /// プロパティが選択されているか確認する。
+    pub fn is_selected(&self) -> bool {
+        self.properties.is_some()
+    }
+
+    /// 選択されたプロパティの名前と列の式を返す。
+    ///
+    /// 選択された順ではなく、フィーチャーのプロパティの順に並べる。プロパティが選択されていない場合は、
+    /// すべてのプロパティを返す。
+    ///
+    /// # Arguments
+    ///
+    /// * `columns` - フィーチャーのプロパティの名前と列の式。
+    ///
+    /// # Returns
+    ///
+    /// 選択されたプロパティの名前と列の式のベクタ。存在しないプロパティが選択された場合はエラー。
+    pub fn select(
+        &self,
+        columns: &'static PropertyColumns,
+    ) -> Result<Vec<(&'static str, &'static str)>, ApiError> {
+        let selected = match &self.properties {
+            Some(properties) => properties
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .collect::<Vec<_>>(),
+            None => return Ok(columns.to_vec()),
+        };
+        if let Some(unknown) = selected
+            .iter()
+            .find(|name| !columns.iter().any(|(property, _)| property == *name))
+        {
+            return Err(ApiError::UnknownProperty(unknown.to_string()));
+        }
+
+        Ok(columns
+            .iter()
+            .filter(|(property, _)| selected.contains(property))
+            .copied()
+            .collect())
+    }
+
+    /// 選択されたプロパティの名前を返す。
+    ///
+    /// # Arguments
+    ///
+    /// * `columns` - フィーチャーのプロパティの名前と列の式。
+    ///
+    /// # Returns
+    ///
+    /// 選択されたプロパティの名前のベクタ。存在しないプロパティが選択された場合はエラー。
+    pub fn names(&self, columns: &'static PropertyColumns) -> Result<Vec<&'static str>, ApiError> {
+        Ok(self
+            .select(columns)?
+            .iter()
+            .map(|(name, _)| *name)
+            .collect())
+    }
+
+    /// 選択されたプロパティの列とジオメトリの列を、SELECT句の列のリストに変換する。
+    ///
+    /// # Arguments
+    ///
+    /// * `columns` - フィーチャーのプロパティの名前と列の式。
+    /// * `geometry` - ジオメトリの列の式。
+    ///
+    /// # Returns
+    ///
+    /// SELECT句の列のリスト。存在しないプロパティが選択された場合はエラー。
+    fn select_list(
+        &self,
+        columns: &'static PropertyColumns,
+        geometry: &str,
+    ) -> Result<String, ApiError> {
+        let mut select_list: Vec<&str> = self
+            .select(columns)?
+            .iter()
+            .map(|(_, column)| *column)
+            .collect();
+        select_list.push(geometry);
+
+        Ok(select_list.join(", "))
+    }
+}
+
+/// レスポンスの空間参照系を示すヘッダーの名前(OGC API - Features - Part 2)。
+const CONTENT_CRS_HEADER: &str = "content-crs";
+
+/// レスポンスのジオメトリを指定するクエリパラメーター。
+#[derive(Debug, Deserialize)]
+pub struct GeometryQuery {
+    /// 座標の小数点以下の桁数(`0`から`15`、指定しない場合は`9`)
+    precision: Option<u8>,
+    /// ジオメトリの空間参照系のSRID(4326または3857、既定値は4326)
+    crs: Option<i32>,
+}
+
+impl GeometryQuery {
+    /// 座標の小数点以下の桁数を返す。
+    ///
+    /// # Returns
+    ///
+    /// 座標の小数点以下の桁数。最大値を超える桁数が指定された場合はエラー。
+    pub fn digits(&self) -> Result<u8, ApiError> {
+        match self.precision {
+            Some(precision) if MAX_GEOJSON_PRECISION < precision => Err(ApiError::InvalidQuery),
+            precision => Ok(precision.unwrap_or(DEFAULT_GEOJSON_PRECISION)),
+        }
+    }
+
+    /// ジオメトリの空間参照系のSRIDを返す。
+    ///
+    /// # Returns
+    ///
+    /// SRID。SRIDが4326または3857でない場合はエラー。
+    pub fn srid(&self) -> Result<i32, ApiError> {
+        match self.crs.unwrap_or(EPSG_WGS84) {
+            srid @ (EPSG_WGS84 | EPSG_WEB_MERCATOR) => Ok(srid),
+            _ => Err(ApiError::InvalidQuery),
+        }
+    }
+
+    /// ジオメトリを、指定された空間参照系の`geom`列として選択する式を返す。
+    ///
+    /// # Returns
+    ///
+    /// SELECT句の列の式。SRIDが4326または3857でない場合はエラー。
+    fn column(&self) -> Result<String, ApiError> {
+        Ok(match self.srid()? {
+            EPSG_WEB_MERCATOR => "geom".to_string(),
+            srid => format!("ST_Transform(geom, {}) as geom", srid),
+        })
+    }
+}
+
+/// 空間参照系を示す`Content-Crs`ヘッダーを、レスポンスに追加する。
+///
+/// WGS84経緯度の座標は経度、緯度の順に並べるため、`CRS84`のURIで示す。
+///
+/// # Arguments
+///
+/// * `response` - レスポンス。
+/// * `srid` - ジオメトリの空間参照系のSRID。
+pub fn insert_content_crs(response: &mut HttpResponse, srid: i32) {
+    let uri = match srid {
+        EPSG_WGS84 => CRS84.to_string(),
+        srid => format!("http://www.opengis.net/def/crs/EPSG/0/{}", srid),
+    };
+    response.headers_mut().insert(
+        header::HeaderName::from_static(CONTENT_CRS_HEADER),
+        header::HeaderValue::from_str(&format!("<{}>", uri)).unwrap(),
+    );
+}
+
+/// フィーチャーのGeoJSONを選択する式を返す。
+///
+/// # Arguments
+///
+/// * `alias` - `geom`列にジオメトリを格納した行の別名。
+/// * `digits` - 座標の小数点以下の桁数。
+///
+/// # Returns
+///
+/// 行をGeoJSONのフィーチャーに変換する式。
+fn feature_geojson(alias: &str, digits: u8) -> String {
+    format!("ST_AsGeoJSON({}.*, 'geom', {})::json", alias, digits)
+}
+
+/// 範囲のパラメーター(`$1`から`$6`)を格納した、SQLのパラメーターを構築する。
+///
+/// `$1`から`$5`には範囲の座標とSRID、`$6`にはフィーチャーのジオメトリのSRIDを格納する。
+///
+/// # Arguments
+///
+/// * `envelope` - 範囲を表現する矩形の座標とSRID。
+///
+/// # Returns
+///
+/// SQLのパラメーター。
+fn envelope_arguments(envelope: Envelope) -> PgArguments {
+    let (min_x, min_y, max_x, max_y, srid) = envelope;
+    let mut args = PgArguments::default();
+    args.add(min_x);
+    args.add(min_y);
+    args.add(max_x);
+    args.add(max_y);
+    args.add(srid);
+    args.add(EPSG_WEB_MERCATOR);
+
+    args
+}
+
+/// 条件に一致するフィーチャーの数を数える。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+/// * `table` - フィーチャーを格納したテーブルの名前。
+/// * `condition` - フィーチャーを絞り込む条件。
+/// * `args` - 条件のパラメーター。
+///
+/// # Returns
+///
+/// 条件に一致するフィーチャーの数。
+async fn count_matched(
+    pool: &PgPool,
+    table: &str,
+    condition: &str,
+    args: PgArguments,
+) -> Result<i64, ApiError> {
+    let sql = format!("SELECT COUNT(*) FROM {} WHERE {}", table, condition);
+    let (count,): (i64,) = sqlx::query_as_with(&sql, args)
+        .fetch_one(pool)
+        .instrument(query_span("SELECT matched count"))
+        .await
+        .map_err(ApiError::from)?;
+
+    Ok(count)
+}
+
+/// レスポンスが`Accept`ヘッダーによって変わることを示す`Vary`ヘッダーを、レスポンスに追加する。
+///
+/// # Arguments
+///
+/// * `response` - レスポンス。
+pub fn vary_accept(response: &mut HttpResponse) {
+    response
+        .headers_mut()
+        .append(header::VARY, header::HeaderValue::from_static("Accept"));
+}
+
+/// データセットの版とリクエストから、フィーチャーコレクションのETagを生成する。
+///
+/// フィーチャーコレクションはストリーミングで返すため、レスポンスボディではなく、レイヤーに最後にデータセットを
+/// 登録した履歴のIDと、リクエストのURI(クエリパラメーターを含む)及びレスポンスの形式からETagを生成する。
+///
+/// # Arguments
+///
+/// * `req` - HTTPリクエスト。
+/// * `format` - レスポンスの形式。
+/// * `version` - レイヤーに最後にデータセットを登録した履歴のID。
+///
+/// # Returns
+///
+/// ETag。
+fn dataset_entity_tag(req: &HttpRequest, format: MediaFormat, version: i64) -> EntityTag {
+    let mut hasher = DefaultHasher::new();
+    hasher.write_i64(version);
+    hasher.write(req.uri().to_string().as_bytes());
+    hasher.write(format.header().to_string().as_bytes());
+
+    EntityTag::new_strong(format!("{:016x}", hasher.finish()))
+}
+
+/// フィーチャーコレクションを、指定された形式で返すレスポンスを構築する。
+///
+/// GeoJSONとCSVはストリーミングで返す。TopoJSONは、すべてのフィーチャーを問い合わせた後にトポロジーに変換して
+/// 返す。CSVは、選択されたプロパティの列とWKTのジオメトリの列(`wkt`)で構成する。
+///
+/// レイヤーにデータセットを登録した履歴がある場合は、データセットの版から生成したETagを付与し、
+/// リクエストのIf-None-MatchヘッダーがETagと一致する場合は、フィーチャーを問い合わせずに
+/// 304 Not Modifiedを返す。
+///
+/// # Arguments
+///
+/// * `req` - HTTPリクエスト。
+/// * `pool` - データベースコネクションプール。
+/// * `layer` - レイヤー名(テーブル名)。
+/// * `sql` - 1列目にフィーチャーのGeoJSONを選択するSQL。
+/// * `args` - SQLのパラメーター。
+/// * `number_matched` - 条件に一致したフィーチャーの数。ページに分けない場合はNone。
+/// * `format` - レスポンスの形式。
+/// * `properties` - 選択されたプロパティの名前。
+/// * `srid` - ジオメトリの空間参照系のSRID。
+///
+/// # Returns
+///
+/// 空間参照系を`Content-Crs`ヘッダーで示したレスポンス。
+#[allow(clippy::too_many_arguments)]
+async fn feature_collection_response(
+    req: &HttpRequest,
+    pool: &PgPool,
+    layer: &str,
+    sql: String,
+    args: PgArguments,
+    number_matched: Option<i64>,
+    format: MediaFormat,
+    properties: Vec<&'static str>,
+    srid: i32,
+) -> Result<HttpResponse, actix_web::Error> {
+    let etag = last_import_id(pool, layer)
+        .instrument(query_span("SELECT dataset version"))
+        .await
+        .map_err(ApiError::from)?
+        .map(|version| dataset_entity_tag(req, format, version));
+    let mut response = match &etag {
+        Some(etag) if is_not_modified(req, etag) => HttpResponse::NotModified().finish(),
+        _ => match format {
+            MediaFormat::Csv => HttpResponse::Ok()
+                .content_type(format.header())
+                .streaming(stream_feature_csv(pool.clone(), sql, args, properties).await?),
+            MediaFormat::TopoJson => {
+                let features: Vec<JsonValue> = sqlx::query_scalar_with(&sql, args)
+                    .fetch_all(pool)
+                    .instrument(query_span("SELECT topojson features"))
+                    .await
+                    .map_err(ApiError::from)?;
+                HttpResponse::Ok()
+                    .content_type(format.header())
+                    .body(encode_topology("features", &features, DEFAULT_QUANTIZATION).to_string())
+            }
+            _ => HttpResponse::Ok().content_type(format.header()).streaming(
+                stream_feature_collection(pool.clone(), sql, args, number_matched).await?,
+            ),
+        },
+    };
+    if let Some(etag) = etag {
+        response.headers_mut().insert(
+            header::ETAG,
+            header::HeaderValue::from_str(&etag.to_string()).unwrap(),
+        );
+    }
+    vary_accept(&mut response);
+    insert_content_crs(&mut response, srid);
+
+    Ok(response)
+}
+
+/// `/prefectures`で都道府県を絞り込む条件。
+///
+/// `$1`から`$6`は範囲、`$7`と`$8`は`PrefectureFilter`の値を表す。
+const PREFECTURE_CONDITION: &str = r#"
+    (
+        $1::float8 IS NULL
+        OR ST_Intersects(geom, ST_Transform(ST_MakeEnvelope($1, $2, $3, $4, $5), $6))
+    )
+    AND ($7::text IS NULL OR code = $7)
+    AND ($8::text IS NULL OR strpos(name, $8) > 0)
+"#;
+
+#[tracing::instrument(name = "Prefectures", skip(req, pool))]
+pub async fn prefectures(
+    req: HttpRequest,
+    query: web::Query<BboxQuery>,
+    filter: web::Query<PrefectureFilter>,
+    properties: web::Query<PropertiesQuery>,
+    geometry: web::Query<GeometryQuery>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let format = negotiate(req.head(), &COLLECTION_FORMATS)?;
+    let mut args = envelope_arguments(query.envelope()?);
+    args.add(filter.code.map(|code| code.to_string()));
+    args.add(filter.name.clone());
+    let sql = format!(
+        r#"
+        SELECT {}
+        FROM (SELECT {} FROM prefectures WHERE {}) p
+        "#,
+        feature_geojson("p", geometry.digits()?),
+        properties.select_list(&PREFECTURE_PROPERTIES, &geometry.column()?)?,
+        PREFECTURE_CONDITION
+    );
+
+    feature_collection_response(
+        &req,
+        pool.as_ref(),
+        "prefectures",
+        sql,
+        args,
+        None,
+        format,
+        properties.names(&PREFECTURE_PROPERTIES)?,
+        geometry.srid()?,
+    )
+    .await
+}
+
+/// `/cities`で市区町村を絞り込む条件。
+///
+/// `$1`から`$6`は範囲、`$7`と`$8`は`CityFilter`の値を表す。
+const CITY_CONDITION: &str = r#"
+    (
+        $1::float8 IS NULL
+        OR ST_Intersects(geom, ST_Transform(ST_MakeEnvelope($1, $2, $3, $4, $5), $6))
+    )
+    AND ($7::text IS NULL OR left(code, length($7)) = $7)
+    AND ($8::text IS NULL OR strpos(name, $8) > 0)
+"#;
+
+#[tracing::instrument(name = "Cities", skip(req, pool))]
+pub async fn cities(
+    req: HttpRequest,
+    query: web::Query<BboxQuery>,
+    filter: web::Query<CityFilter>,
+    page: web::Query<PageQuery>,
+    properties: web::Query<PropertiesQuery>,
+    geometry: web::Query<GeometryQuery>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let format = negotiate(req.head(), &COLLECTION_FORMATS)?;
+    let envelope = query.envelope()?;
+    let (limit, offset) = page.limit_offset();
+    let condition_args = || {
+        let mut args = envelope_arguments(envelope);
+        args.add(filter.code_prefix.clone());
+        args.add(filter.name.clone());
+        args
+    };
+    let matched = count_matched(pool.as_ref(), "cities", CITY_CONDITION, condition_args()).await?;
+    // ページのフィーチャーのGeoJSONには、選択されたプロパティの列だけを含める
+    let sql = format!(
+        r#"
+        SELECT (SELECT {} FROM (SELECT {}) c)
+        FROM (
+            SELECT id, code, area, name, geom FROM cities
+            WHERE {}
+            ORDER BY id LIMIT $9 OFFSET $10
+        ) page
+        ORDER BY page.id
+        "#,
+        feature_geojson("c", geometry.digits()?),
+        properties.select_list(&CITY_PROPERTIES, &geometry.column()?)?,
+        CITY_CONDITION
+    );
+    let mut args = condition_args();
+    args.add(limit);
+    args.add(offset);
+
+    feature_collection_response(
+        &req,
+        pool.as_ref(),
+        "cities",
+        sql,
+        args,
+        Some(matched),
+        format,
+        properties.names(&CITY_PROPERTIES)?,
+        geometry.srid()?,
+    )
+    .await
+}
+
+/// `/post_offices`が返すことのできる形式。
+const POST_OFFICE_FORMATS: [MediaFormat; 5] = [
+    MediaFormat::GeoJson,
+    MediaFormat::Csv,
+    MediaFormat::TopoJson,
+    MediaFormat::Kml,
+    MediaFormat::Gpx,
+];
+
+/// `/post_offices`で郵便局を絞り込む条件。
+///
+/// `$1`から`$6`は範囲、`$7`から`$9`は`PostOfficeFilter`の値を表す。
+const POST_OFFICE_CONDITION: &str = r#"
+    (
+        $1::float8 IS NULL
+        OR ST_Intersects(geom, ST_Transform(ST_MakeEnvelope($1, $2, $3, $4, $5), $6))
+    )
+    AND ($7::text IS NULL OR post_office_code = $7)
+    AND ($8::text IS NULL OR city_code = $8)
+    AND ($9::text IS NULL OR strpos(name, $9) > 0)
+"#;
+
+#[tracing::instrument(name = "Post offices", skip(req, pool))]
+pub async fn post_offices(
+    req: HttpRequest,
+    query: web::Query<BboxQuery>,
+    filter: web::Query<PostOfficeFilter>,
+    page: web::Query<PageQuery>,
+    properties: web::Query<PropertiesQuery>,
+    geometry: web::Query<GeometryQuery>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let format = negotiate(req.head(), &POST_OFFICE_FORMATS)?;
+    let envelope = query.envelope()?;
+    let (limit, offset) = page.limit_offset();
+    let condition_args = || {
+        let mut args = envelope_arguments(envelope);
+        args.add(filter.post_office_code.clone());
+        args.add(filter.city_code.clone());
+        args.add(filter.name.clone());
+        args
+    };
+    let mut args = condition_args();
+    args.add(limit);
+    args.add(offset);
+    if matches!(format, MediaFormat::Kml | MediaFormat::Gpx) {
+        let sql = format!(
+            r#"
+            SELECT
+                id, name, address,
+                ST_X(ST_Transform(geom, $12)) as lon, ST_Y(ST_Transform(geom, $12)) as lat
+            FROM post_offices
+            WHERE {}
+            ORDER BY id LIMIT $10 OFFSET $11
+            "#,
+            POST_OFFICE_CONDITION
+        );
+        args.add(EPSG_WGS84);
+        let waypoints: Vec<Waypoint> = sqlx::query_as_with(&sql, args)
+            .fetch_all(pool.as_ref())
+            .instrument(query_span("SELECT post_offices waypoints"))
+            .await
+            .map_err(ApiError::from)?;
+        let body = match format {
+            MediaFormat::Kml => encode_kml("post_offices", &waypoints),
+            _ => encode_gpx("post_offices", &waypoints),
+        };
+        let mut response = conditional_response(&req, format.header(), Bytes::from(body));
+        vary_accept(&mut response);
+
+        return Ok(response);
+    }
+    let matched = count_matched(
+        pool.as_ref(),
+        "post_offices",
+        POST_OFFICE_CONDITION,
+        condition_args(),
+    )
+    .await?;
+    // ページのフィーチャーのGeoJSONには、選択されたプロパティの列だけを含める
+    let sql = format!(
+        r#"
+        SELECT (SELECT {} FROM (SELECT {}) p)
+        FROM (
+            SELECT
+                id, city_code, category_code, subcategory_code, post_office_code,
+                name, address, city_name, town, block, geom
+            FROM post_offices
+            WHERE {}
+            ORDER BY id LIMIT $10 OFFSET $11
+        ) page
+        ORDER BY page.id
+        "#,
+        feature_geojson("p", geometry.digits()?),
+        properties.select_list(&POST_OFFICE_PROPERTIES, &geometry.column()?)?,
+        POST_OFFICE_CONDITION
+    );
+
+    feature_collection_response(
+        &req,
+        pool.as_ref(),
+        "post_offices",
+        sql,
+        args,
+        Some(matched),
+        format,
+        properties.names(&POST_OFFICE_PROPERTIES)?,
+        geometry.srid()?,
+    )
+    .await
+}
+
+/// `/cities/{code}/post_offices`で市区町村の郵便局を絞り込む条件。
+///
+/// `$1`は市区町村コードを表す。データセットの年度によって市区町村コードが異なる場合があるため、郵便局の
+/// 市区町村コードが一致しない場合でも、市区町村の区域に含まれる郵便局を含める。
+const CITY_POST_OFFICE_CONDITION: &str = r#"
+    city_code = $1
+    OR EXISTS (
+        SELECT 1 FROM cities c WHERE c.code = $1 AND ST_Contains(c.geom, post_offices.geom)
+    )
+"#;
+
+/// 市区町村コードが5桁の数字であるか確認する。
+///
+/// # Arguments
+///
+/// * `code` - 市区町村コード。
+///
+/// # Returns
+///
+/// 市区町村コード。5桁の数字でない場合はリソースが見つからないことを示すエラー。
+pub fn check_city_code(code: String) -> Result<String, ApiError> {
+    if code.len() == 5 && code.bytes().all(|b| b.is_ascii_digit()) {
+        Ok(code)
+    } else {
+        Err(ApiError::NotFound)
+    }
+}
+
+#[tracing::instrument(name = "City post offices", skip(req, pool))]
+pub async fn city_post_offices(
+    req: HttpRequest,
+    path: web::Path<(String,)>,
+    page: web::Query<PageQuery>,
+    properties: web::Query<PropertiesQuery>,
+    geometry: web::Query<GeometryQuery>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let format = negotiate(req.head(), &COLLECTION_FORMATS)?;
+    let code = check_city_code(path.into_inner().0)?;
+    let (limit, offset) = page.limit_offset();
+    let condition_args = || {
+        let mut args = PgArguments::default();
+        args.add(code.clone());
+        args
+    };
+    let matched = count_matched(
+        pool.as_ref(),
+        "post_offices",
+        CITY_POST_OFFICE_CONDITION,
+        condition_args(),
+    )
+    .await?;
+    // 郵便局がない場合は、市区町村が登録されているか確認する
+    if matched == 0 {
+        let (exists,): (bool,) =
+            sqlx::query_as("SELECT EXISTS (SELECT 1 FROM cities WHERE code = $1)")
+                .bind(&code)
+                .fetch_one(pool.as_ref())
+                .instrument(query_span("SELECT city exists"))
+                .await
+                .map_err(ApiError::from)?;
+        if !exists {
+            return Err(ApiError::NotFound.into());
+        }
+    }
+    let mut args = condition_args();
+    args.add(limit);
+    args.add(offset);
+    let sql = format!(
+        r#"
+        SELECT (SELECT {} FROM (SELECT {}) p)
+        FROM (
+            SELECT
+                id, city_code, category_code, subcategory_code, post_office_code,
+                name, address, city_name, town, block, geom
+            FROM post_offices
+            WHERE {}
+            ORDER BY id LIMIT $2 OFFSET $3
+        ) page
+        ORDER BY page.id
+        "#,
+        feature_geojson("p", geometry.digits()?),
+        properties.select_list(&POST_OFFICE_PROPERTIES, &geometry.column()?)?,
+        CITY_POST_OFFICE_CONDITION
+    );
+
+    feature_collection_response(
+        &req,
+        pool.as_ref(),
+        "post_offices",
+        sql,
+        args,
+        Some(matched),
+        format,
+        properties.names(&POST_OFFICE_PROPERTIES)?,
+        geometry.srid()?,
+    )
+    .await
+}
+
+/// 最寄りの郵便局を返すときに、返す郵便局の件数の既定値。
+const DEFAULT_NEAREST_COUNT: u32 = 5;
+
+/// 最寄りの郵便局を返すときに、返す郵便局の件数の最大値。
+const MAX_NEAREST_COUNT: u32 = 100;
+
+/// 最寄りの郵便局を取得するクエリパラメーター。
+#[derive(Debug, Deserialize)]
+pub struct NearestQuery {
+    /// 経度(WGS84)
+    lon: Option<f64>,
+    /// 緯度(WGS84)
+    lat: Option<f64>,
+    /// 返す郵便局の件数
+    n: Option<u32>,
+}
+
+impl NearestQuery {
+    /// 経度、緯度及び返す郵便局の件数を返す。
+    ///
+    /// 件数は、1以上で最大値以下に収める。
+    ///
+    /// # Returns
+    ///
+    /// 経度、緯度及び返す郵便局の件数。経度または緯度が指定されていないか、範囲外の場合はエラー。
+    fn point_count(&self) -> Result<(f64, f64, i64), ApiError> {
+        let (lon, lat) = check_lon_lat(self.lon, self.lat)?;
+        let n = self
+            .n
+            .unwrap_or(DEFAULT_NEAREST_COUNT)
+            .clamp(1, MAX_NEAREST_COUNT);
+
+        Ok((lon, lat, n as i64))
+    }
+}
+
+#[tracing::instrument(name = "Nearest post offices", skip(req, pool))]
+pub async fn nearest_post_offices(
+    req: HttpRequest,
+    query: web::Query<NearestQuery>,
+    geometry: web::Query<GeometryQuery>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (lon, lat, n) = query.point_count()?;
+    let digits = i32::from(geometry.digits()?);
+    let srid = geometry.srid()?;
+    // 空間インデックスを使用する<->演算子で最寄りの郵便局を選択した後で、回転楕円体上の距離を計算する
     let result = sqlx::query!(
         r#"
+        WITH target AS (
+            SELECT ST_SetSRID(ST_MakePoint($1, $2), $3) as point
+        ), nearest AS (
+            SELECT
+                p.id, p.city_code as "cityCode", p.category_code as "categoryCode",
+                p.subcategory_code as "subcategoryCode", p.post_office_code as "postOfficeCode",
+                p.name, p.address, p.city_name as "cityName", p.town, p.block,
+                ST_Transform(p.geom, $7) as geom,
+                ST_Distance(ST_Transform(p.geom, $3)::geography, t.point::geography) as distance
+            FROM post_offices p, target t
+            ORDER BY p.geom <-> ST_Transform(t.point, $4)
+            LIMIT $5
+        )
         SELECT json_build_object(
             'type', 'FeatureCollection',
-            'features', json_agg(ST_AsGeoJSON(p.*)::json)
+            'features', COALESCE(
+                json_agg(
+                    ST_AsGeoJSON(nearest.*, 'geom', $6)::json
+                    ORDER BY nearest.distance, nearest.id
+                ),
+                '[]'::json
+            )
         ) as fc
-        FROM (
-            SELECT id, name, geom  FROM prefectures
-        ) p
+        FROM nearest
         "#,
+        lon,
+        lat,
+        EPSG_WGS84,
+        EPSG_WEB_MERCATOR,
+        n,
+        digits,
+        srid,
     )
     .fetch_one(pool.as_ref())
-    .await;
+    .instrument(query_span("SELECT nearest post_offices"))
+    .await
+    .map_err(ApiError::from)?;
+
+    let mut response = conditional_response(
+        &req,
+        ContentType::json(),
+        Bytes::from(result.fc.unwrap().to_string()),
+    );
+    insert_content_crs(&mut response, srid);
+
+    Ok(response)
+}
+
+/// クエリパラメーターで指定された、地物を絞り込む値。
+enum FilterValue {
+    /// 文字列(指定されていない場合はNone)
+    Text(Option<String>),
+    /// コードのベクタ(指定されていない場合はNone)
+    Codes(Option<Vec<String>>),
+}
+
+/// レイヤーの地物を絞り込む値を、クエリパラメーターから取得する。
+///
+/// # Arguments
+///
+/// * `table` - レイヤーのテーブルの定義。
+/// * `tile` - タイルに適用する値(コードによる絞り込み)に限る場合はtrue。
+/// * `query` - クエリパラメーター。
+///
+/// # Returns
+///
+/// 絞り込む値を、テーブルの定義の絞り込みの順に格納したベクタ。不正な値が指定された場合はエラー。
+fn filter_values(
+    table: &LayerTable,
+    tile: bool,
+    query: &HashMap<String, String>,
+) -> Result<Vec<FilterValue>, ApiError> {
+    table
+        .filters(tile)
+        .map(|filter| {
+            let value = query.get(filter.param);
+            Ok(match filter.kind {
+                FilterKind::Codes => FilterValue::Codes(split_codes(value.map(String::as_str))?),
+                FilterKind::PrefectureCode => FilterValue::Text(
+                    value
+                        .map(|code| code.parse::<PrefectureCode>())
+                        .transpose()
+                        .map_err(|_| ApiError::InvalidQuery)?
+                        .map(|code| code.to_string()),
+                ),
+                _ => FilterValue::Text(value.cloned()),
+            })
+        })
+        .collect()
+}
 
-    match result {
-        Ok(result) => HttpResponse::Ok().json(result.fc.unwrap()),
-        Err(e) => HttpResponse::InternalServerError().body(format!("{}", e)),
+/// タイルの地物を絞り込まない場合の、絞り込む値を返す。
+///
+/// # Arguments
+///
+/// * `table` - レイヤーのテーブルの定義。
+///
+/// # Returns
+///
+/// タイルに適用する絞り込みの値を、すべて指定されていないとしたベクタ。
+fn unfiltered_values(table: &LayerTable) -> Vec<FilterValue> {
+    table
+        .filters(true)
+        .map(|_| FilterValue::Codes(None))
+        .collect()
+}
+
+/// 地物を絞り込む値を、SQLのパラメーターに追加する。
+///
+/// # Arguments
+///
+/// * `args` - SQLのパラメーター。
+/// * `values` - 地物を絞り込む値。
+fn add_filter_values(args: &mut PgArguments, values: &[FilterValue]) {
+    for value in values {
+        match value {
+            FilterValue::Text(text) => args.add(text.clone()),
+            FilterValue::Codes(codes) => args.add(codes.clone()),
+        }
     }
 }
 
-#[tracing::instrument(name = "Cities", skip(pool))]
-pub async fn cities(pool: web::Data<PgPool>) -> HttpResponse {
-    let result = sqlx::query!(
+/// タイルキャッシュのキーに設定する、タイルに適用する絞り込みのパラメーターの名前と値を連結した文字列を返す。
+///
+/// # Arguments
+///
+/// * `table` - レイヤーのテーブルの定義。
+/// * `values` - タイルに適用する絞り込みの値。
+///
+/// # Returns
+///
+/// パラメーターの名前と値を連結した文字列。
+fn filter_cache_params(table: &LayerTable, values: &[FilterValue]) -> String {
+    table
+        .filters(true)
+        .zip(values)
+        .filter_map(|(filter, value)| match value {
+            FilterValue::Codes(Some(codes)) => {
+                Some(format!("{}={}", filter.param, codes.join(",")))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// `/{layer}`で地物を絞り込む条件を返す。
+///
+/// `$1`から`$6`は範囲、`$7`以降はテーブルの定義の絞り込みの値を表す。
+///
+/// # Arguments
+///
+/// * `table` - レイヤーのテーブルの定義。
+///
+/// # Returns
+///
+/// 地物を絞り込む条件。
+fn table_condition(table: &LayerTable) -> String {
+    format!(
+        r#"
+    (
+        $1::float8 IS NULL
+        OR ST_Intersects(geom, ST_Transform(ST_MakeEnvelope($1, $2, $3, $4, $5), $6))
+    ){}
+"#,
+        table.filter_condition(false, 7)
+    )
+}
+
+/// 鉄道路線、駅、学校、医療機関及び河川のように、テーブルを定義したレイヤーのフィーチャーを返す。
+///
+/// 地物は、テーブルの定義のクエリパラメーターで絞り込む。
+#[tracing::instrument(name = "Layer features", skip(req, pool))]
+#[allow(clippy::too_many_arguments)]
+pub async fn layer_features(
+    req: HttpRequest,
+    path: web::Path<(Layer,)>,
+    query: web::Query<BboxQuery>,
+    filter: web::Query<HashMap<String, String>>,
+    page: web::Query<PageQuery>,
+    properties: web::Query<PropertiesQuery>,
+    geometry: web::Query<GeometryQuery>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let table = path.into_inner().0.table().ok_or(ApiError::UnknownLayer)?;
+    let format = negotiate(req.head(), &COLLECTION_FORMATS)?;
+    let envelope = query.envelope()?;
+    let values = filter_values(table, false, &filter)?;
+    let (limit, offset) = page.limit_offset();
+    let condition = table_condition(table);
+    let condition_args = || {
+        let mut args = envelope_arguments(envelope);
+        add_filter_values(&mut args, &values);
+        args
+    };
+    let matched = count_matched(pool.as_ref(), table.table, &condition, condition_args()).await?;
+    // ページのフィーチャーのGeoJSONには、選択されたプロパティの列だけを含める
+    let limit_index = 7 + values.len();
+    let sql = format!(
+        r#"
+        SELECT (SELECT {} FROM (SELECT {}) f)
+        FROM (
+            SELECT * FROM {}
+            WHERE {}
+            ORDER BY id LIMIT ${} OFFSET ${}
+        ) page
+        ORDER BY page.id
+        "#,
+        feature_geojson("f", geometry.digits()?),
+        properties.select_list(table.properties, &geometry.column()?)?,
+        table.table,
+        condition,
+        limit_index,
+        limit_index + 1
+    );
+    let mut args = condition_args();
+    args.add(limit);
+    args.add(offset);
+
+    feature_collection_response(
+        &req,
+        pool.as_ref(),
+        table.table,
+        sql,
+        args,
+        Some(matched),
+        format,
+        properties.names(table.properties)?,
+        geometry.srid()?,
+    )
+    .await
+}
+
+/// レスポンスボディのハッシュ値からETagを生成する。
+///
+/// # Arguments
+///
+/// * `body` - レスポンスボディ。
+///
+/// # Returns
+///
+/// ETag。
+fn entity_tag(body: &[u8]) -> EntityTag {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(body);
+
+    EntityTag::new_strong(format!("{:016x}", hasher.finish()))
+}
+
+/// リクエストのIf-None-MatchヘッダーがETagと一致するか確認する。
+///
+/// # Arguments
+///
+/// * `req` - HTTPリクエスト。
+/// * `etag` - レスポンスのETag。
+///
+/// # Returns
+///
+/// If-None-MatchヘッダーがETagと一致する場合はtrue。
+pub fn is_not_modified(req: &HttpRequest, etag: &EntityTag) -> bool {
+    match IfNoneMatch::parse(req) {
+        Ok(IfNoneMatch::Any) => true,
+        Ok(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(etag)),
+        Err(_) => false,
+    }
+}
+
+/// ETagを付与したレスポンスを返す。
+///
+/// リクエストのIf-None-MatchヘッダーがETagと一致する場合は、ボディを含めずに
+/// 304 Not Modifiedを返す。
+///
+/// # Arguments
+///
+/// * `req` - HTTPリクエスト。
+/// * `content_type` - レスポンスボディのMIMEタイプ。
+/// * `body` - レスポンスボディ。
+///
+/// # Returns
+///
+/// レスポンス。
+pub fn conditional_response(
+    req: &HttpRequest,
+    content_type: ContentType,
+    body: Bytes,
+) -> HttpResponse {
+    let etag = entity_tag(&body);
+    if is_not_modified(req, &etag) {
+        return HttpResponse::NotModified()
+            .insert_header(header::ETag(etag))
+            .finish();
+    }
+
+    HttpResponse::Ok()
+        .content_type(content_type)
+        .insert_header(header::ETag(etag))
+        .body(body)
+}
+
+/// タイルが配信するズームレベルと範囲に含まれるか確認する。
+///
+/// # Arguments
+///
+/// * `guardrails` - 配信するズームレベルと範囲の制限。
+/// * `zoom` - ズームレベル。
+/// * `x` - タイルのX座標。
+/// * `y` - タイルのY座標。
+///
+/// # Returns
+///
+/// タイルが不正な場合は400 Bad Request、配信するズームレベルと範囲に含まれない場合は
+/// 404 Not Foundを示すエラー。
+fn check_served_tile(
+    guardrails: &Guardrails,
+    zoom: u8,
+    x: u32,
+    y: u32,
+) -> Result<(), actix_web::Error> {
+    let tile = Tile::new(zoom, x, y).ok_or(ApiError::InvalidTile)?;
+    if !guardrails.allows(&tile) {
+        return Err(ApiError::TileOutOfRange.into());
+    }
+
+    Ok(())
+}
+
+/// キャッシュしたタイルを返す。
+///
+/// タイルをキャッシュしていない場合は、タイルを生成してキャッシュした後で返す。
+///
+/// # Arguments
+///
+/// * `req` - HTTPリクエスト。
+/// * `cache` - タイルキャッシュ。
+/// * `http_cache` - タイルのレスポンスのキャッシュを制御するヘッダーの設定。
+/// * `key` - タイルキャッシュのキー。
+/// * `content_type` - タイルのMIMEタイプ。
+/// * `generate` - タイルを生成するフューチャー。
+///
+/// # Returns
+///
+/// タイルを返すレスポンス。If-None-Matchヘッダーがタイルと一致する場合は304 Not Modified。
+async fn cached_tile<Fut>(
+    req: &HttpRequest,
+    cache: &TileCache,
+    http_cache: &HttpCache,
+    key: TileKey,
+    content_type: ContentType,
+    generate: Fut,
+) -> Result<HttpResponse, actix_web::Error>
+where
+    Fut: Future<Output = Result<Bytes, actix_web::Error>>,
+{
+    let layer = key.layer.clone();
+    let body = match cache.get(&key) {
+        Some(body) => body,
+        None => {
+            let body = generate.await?;
+            cache.insert(key, body.clone());
+            body
+        }
+    };
+    let mut response = conditional_response(req, content_type, body);
+    http_cache.apply(&layer, &mut response);
+    vary_accept(&mut response);
+
+    Ok(response)
+}
+
+/// タイルをGeoJSONで返すときの`Content-Type`ヘッダーを返す。
+///
+/// Mapbox Vector Tileを要求されたリクエストは、ルーティングで`vector_tiles`に振り分けるため、GeoJSONを返す
+/// ことのできるリクエストであるかだけを確認する。
+///
+/// # Arguments
+///
+/// * `req` - リクエスト。
+///
+/// # Returns
+///
+/// GeoJSONの`Content-Type`ヘッダー。GeoJSONを返すことのできないリクエストの場合はエラー。
+fn geojson_tile_header(req: &HttpRequest) -> Result<ContentType, ApiError> {
+    negotiate(req.head(), &TILE_FORMATS)?;
+
+    Ok(MediaFormat::GeoJson.header())
+}
+
+/// 簡略化したジオメトリの列(`geom_low`)を使用する最大のズームレベル。
+const LOW_ZOOM_MAX: u8 = 6;
+
+/// 簡略化したジオメトリの列(`geom_mid`)を使用する最大のズームレベル。
+const MID_ZOOM_MAX: u8 = 9;
+
+/// 都道府県と市区町村のタイルを生成するときに使用する、ジオメトリの列名を返す。
+///
+/// 低いズームレベルのタイルは、登録したときに簡略化したジオメトリから生成する。
+///
+/// # Arguments
+///
+/// * `zoom` - ズームレベル。
+///
+/// # Returns
+///
+/// ジオメトリの列名。
+fn geometry_column(zoom: u8) -> &'static str {
+    if zoom <= LOW_ZOOM_MAX {
+        "geom_low"
+    } else if zoom <= MID_ZOOM_MAX {
+        "geom_mid"
+    } else {
+        "geom"
+    }
+}
+
+#[tracing::instrument(
+    name = "Tiled prefectures",
+    skip(req, pool, cache, http_cache, tiles, guardrails)
+)]
+pub async fn tiled_prefectures(
+    req: HttpRequest,
+    path: web::Path<(u8, u32, u32)>,
+    pool: web::Data<PgPool>,
+    cache: web::Data<TileCache>,
+    http_cache: web::Data<HttpCache>,
+    tiles: web::Data<TileSettings>,
+    guardrails: web::Data<Guardrails>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (zoom, x, y) = path.into_inner();
+    check_served_tile(&guardrails, zoom, x, y)?;
+    let key = TileKey::new("prefectures", TileFormat::GeoJson, zoom, x, y);
+    cached_tile(
+        &req,
+        &cache,
+        &http_cache,
+        key,
+        geojson_tile_header(&req)?,
+        prefecture_geojson_tile(zoom, x, y, &tiles, pool.as_ref()),
+    )
+    .await
+}
+
+/// 都道府県のGeoJSONのタイルを生成する。
+///
+/// # Arguments
+///
+/// * `zoom` - ズームレベル。
+/// * `x` - タイルのX座標。
+/// * `y` - タイルのY座標。
+/// * `tiles` - タイルの設定。
+/// * `pool` - データベースコネクションプール。
+///
+/// # Returns
+///
+/// FeatureCollectionを格納したGeoJSON。
+async fn prefecture_geojson_tile(
+    zoom: u8,
+    x: u32,
+    y: u32,
+    tiles: &TileSettings,
+    pool: &PgPool,
+) -> Result<Bytes, actix_web::Error> {
+    let polygon = tile_polygon(zoom, x, y, tiles.geojson_buffer_ratio)?;
+    let sql = format!(
         r#"
         SELECT json_build_object(
             'type', 'FeatureCollection',
-            'features', json_agg(ST_AsGeoJSON(c.*)::json)
+            'features', COALESCE(json_agg(ST_AsGeoJSON(p.*, 'geom', $4)::json), '[]'::json)
         ) as fc
         FROM (
-            SELECT id, code, area, name, geom FROM cities
+            SELECT
+                id, name,
+                CASE
+                    WHEN $3 THEN ST_ClipByBox2D({geom}, ST_GeomFromText($1, $2))
+                    ELSE {geom}
+                END as geom
+            FROM prefectures
+            WHERE ST_Intersects({geom}, ST_GeomFromText($1, $2))
+        ) p
+        "#,
+        geom = geometry_column(zoom),
+    );
+    let (fc,): (Option<JsonValue>,) = sqlx::query_as(&sql)
+        .bind(polygon)
+        .bind(EPSG_WEB_MERCATOR)
+        .bind(tiles.geojson_clip)
+        .bind(tiles.geojson_digits())
+        .fetch_one(pool)
+        .instrument(query_span("SELECT prefectures tile"))
+        .await
+        .map_err(ApiError::from)?;
+
+    Ok(Bytes::from(fc.unwrap().to_string()))
+}
+
+#[tracing::instrument(
+    name = "Tiled cities",
+    skip(req, pool, cache, http_cache, tiles, guardrails)
+)]
+pub async fn tiled_cities(
+    req: HttpRequest,
+    path: web::Path<(u8, u32, u32)>,
+    pool: web::Data<PgPool>,
+    cache: web::Data<TileCache>,
+    http_cache: web::Data<HttpCache>,
+    tiles: web::Data<TileSettings>,
+    guardrails: web::Data<Guardrails>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (zoom, x, y) = path.into_inner();
+    check_served_tile(&guardrails, zoom, x, y)?;
+    let key = TileKey::new("cities", TileFormat::GeoJson, zoom, x, y);
+    cached_tile(
+        &req,
+        &cache,
+        &http_cache,
+        key,
+        geojson_tile_header(&req)?,
+        city_geojson_tile(zoom, x, y, &tiles, pool.as_ref()),
+    )
+    .await
+}
+
+/// 市区町村のGeoJSONのタイルを生成する。
+///
+/// # Arguments
+///
+/// * `zoom` - ズームレベル。
+/// * `x` - タイルのX座標。
+/// * `y` - タイルのY座標。
+/// * `tiles` - タイルの設定。
+/// * `pool` - データベースコネクションプール。
+///
+/// # Returns
+///
+/// FeatureCollectionを格納したGeoJSON。
+async fn city_geojson_tile(
+    zoom: u8,
+    x: u32,
+    y: u32,
+    tiles: &TileSettings,
+    pool: &PgPool,
+) -> Result<Bytes, actix_web::Error> {
+    let polygon = tile_polygon(zoom, x, y, tiles.geojson_buffer_ratio)?;
+    let sql = format!(
+        r#"
+        SELECT json_build_object(
+            'type', 'FeatureCollection',
+            'features', COALESCE(json_agg(ST_AsGeoJSON(c.*, 'geom', $4)::json), '[]'::json)
+        ) as fc
+        FROM (
+            SELECT
+                id, code, area, name,
+                CASE
+                    WHEN $3 THEN ST_ClipByBox2D({geom}, ST_GeomFromText($1, $2))
+                    ELSE {geom}
+                END as geom
+            FROM cities
+            WHERE ST_Intersects({geom}, ST_GeomFromText($1, $2))
         ) c
         "#,
+        geom = geometry_column(zoom),
+    );
+    let (fc,): (Option<JsonValue>,) = sqlx::query_as(&sql)
+        .bind(polygon)
+        .bind(EPSG_WEB_MERCATOR)
+        .bind(tiles.geojson_clip)
+        .bind(tiles.geojson_digits())
+        .fetch_one(pool)
+        .instrument(query_span("SELECT cities tile"))
+        .await
+        .map_err(ApiError::from)?;
+
+    Ok(Bytes::from(fc.unwrap().to_string()))
+}
+
+#[tracing::instrument(
+    name = "Tiled post offices",
+    skip(req, pool, cache, http_cache, tiles, guardrails)
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn tiled_post_offices(
+    req: HttpRequest,
+    path: web::Path<(u8, u32, u32)>,
+    filter: web::Query<PostOfficeCategoryFilter>,
+    pool: web::Data<PgPool>,
+    cache: web::Data<TileCache>,
+    http_cache: web::Data<HttpCache>,
+    tiles: web::Data<TileSettings>,
+    guardrails: web::Data<Guardrails>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (zoom, x, y) = path.into_inner();
+    check_served_tile(&guardrails, zoom, x, y)?;
+    let categories = filter.categories()?;
+    let key = TileKey::new("post_offices", TileFormat::GeoJson, zoom, x, y)
+        .with_params(categories.cache_params());
+    cached_tile(
+        &req,
+        &cache,
+        &http_cache,
+        key,
+        geojson_tile_header(&req)?,
+        post_office_geojson_tile(zoom, x, y, &tiles, &categories, pool.as_ref()),
     )
-    .fetch_one(pool.as_ref())
-    .await;
-
-    match result {
-        Ok(result) => HttpResponse::Ok().json(result.fc.unwrap()),
-        Err(e) => HttpResponse::InternalServerError().body(format!("{}", e)),
-    }
-}
-
-struct PostOffice {
-    id: Uuid,
-    city_code: String,
-    category_code: String,
-    subcategory_code: String,
-    post_office_code: String,
-    name: String,
-    address: String,
-    geom: wkb::Decode<geo_types::Geometry<f64>>,
-}
-
-fn generate_post_office_feature(post_office: &PostOffice) -> String {
-    let mut properties = JsonObject::new();
-    properties.insert(
-        "cityCode".to_string(),
-        JsonValue::from(post_office.city_code.to_string()),
-    );
-    properties.insert(
-        "categoryCode".to_string(),
-        JsonValue::from(post_office.category_code.to_string()),
-    );
-    properties.insert(
-        "subcategoryCode".to_string(),
-        JsonValue::from(post_office.subcategory_code.to_string()),
-    );
-    properties.insert(
-        "postOfficeCode".to_string(),
-        JsonValue::from(post_office.post_office_code.to_string()),
-    );
-    properties.insert(
-        "name".to_string(),
-        JsonValue::from(post_office.name.to_string()),
-    );
-    properties.insert(
-        "address".to_string(),
-        JsonValue::from(post_office.address.to_string()),
-    );
-    let geometry = geojson::Value::from(post_office.geom.geometry.as_ref().unwrap());
-    let feature = geojson::Feature {
-        bbox: None,
-        geometry: Some(geojson::Geometry {
-            value: geometry,
-            bbox: None,
-            foreign_members: None,
-        }),
-        id: Some(geojson::feature::Id::String(post_office.id.to_string())),
-        properties: Some(properties),
-        foreign_members: None,
+    .await
+}
+
+/// 郵便局のGeoJSONのタイルを生成する。
+///
+/// # Arguments
+///
+/// * `zoom` - ズームレベル。
+/// * `x` - タイルのX座標。
+/// * `y` - タイルのY座標。
+/// * `tiles` - タイルの設定。
+/// * `categories` - タイルに含める郵便局の分類。
+/// * `pool` - データベースコネクションプール。
+///
+/// # Returns
+///
+/// FeatureCollectionを格納したGeoJSON。
+async fn post_office_geojson_tile(
+    zoom: u8,
+    x: u32,
+    y: u32,
+    tiles: &TileSettings,
+    categories: &PostOfficeCategories,
+    pool: &PgPool,
+) -> Result<Bytes, actix_web::Error> {
+    let polygon = tile_polygon(zoom, x, y, tiles.geojson_buffer_ratio)?;
+    let columns: Vec<&str> = POST_OFFICE_PROPERTIES
+        .iter()
+        .map(|(_, column)| *column)
+        .collect();
+    let sql = format!(
+        r#"
+        SELECT {}
+        FROM (
+            SELECT {}, geom
+            FROM post_offices
+            WHERE
+                ST_Intersects(geom, ST_GeomFromText($1, $2))
+                AND ($3::text[] IS NULL OR post_office_code = ANY($3))
+                AND ($4::text[] IS NULL OR category_code = ANY($4))
+                AND ($5::text[] IS NULL OR subcategory_code = ANY($5))
+        ) p
+        "#,
+        feature_geojson("p", tiles.geojson_digits() as u8),
+        columns.join(", "),
+    );
+    let mut args = PgArguments::default();
+    args.add(polygon);
+    args.add(EPSG_WEB_MERCATOR);
+    args.add(categories.post_office_codes.clone());
+    args.add(categories.category_codes.clone());
+    args.add(categories.subcategory_codes.clone());
+    // 郵便局を1件ずつ問い合わせて、コレクションのエンドポイントと同じストリームでフィーチャーを書き込む
+    let body = async {
+        stream_feature_collection(pool.clone(), sql, args, None)
+            .await?
+            .try_fold(Vec::new(), |mut body, chunk| async move {
+                body.extend_from_slice(&chunk);
+                Ok(body)
+            })
+            .await
+    }
+    .instrument(query_span("SELECT post_offices tile"))
+    .await?;
+
+    Ok(Bytes::from(body))
+}
+
+#[tracing::instrument(
+    name = "Tiled layer",
+    skip(req, pool, cache, http_cache, tiles, guardrails)
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn tiled_layer(
+    req: HttpRequest,
+    path: web::Path<(Layer, u8, u32, u32)>,
+    query: web::Query<HashMap<String, String>>,
+    pool: web::Data<PgPool>,
+    cache: web::Data<TileCache>,
+    http_cache: web::Data<HttpCache>,
+    tiles: web::Data<TileSettings>,
+    guardrails: web::Data<Guardrails>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (layer, zoom, x, y) = path.into_inner();
+    let table = layer.table().ok_or(ApiError::UnknownLayer)?;
+    check_served_tile(&guardrails, zoom, x, y)?;
+    let values = filter_values(table, true, &query)?;
+    let key = TileKey::new(layer.name(), TileFormat::GeoJson, zoom, x, y)
+        .with_params(filter_cache_params(table, &values));
+    cached_tile(
+        &req,
+        &cache,
+        &http_cache,
+        key,
+        geojson_tile_header(&req)?,
+        table_geojson_tile(table, zoom, x, y, &tiles, &values, pool.as_ref()),
+    )
+    .await
+}
+
+/// テーブルを定義したレイヤーのGeoJSONのタイルを生成する。
+///
+/// 線のレイヤーは、設定で切り取りを有効にした場合に、ジオメトリをタイルの範囲で切り取る。
+///
+/// # Arguments
+///
+/// * `table` - レイヤーのテーブルの定義。
+/// * `zoom` - ズームレベル。
+/// * `x` - タイルのX座標。
+/// * `y` - タイルのY座標。
+/// * `tiles` - タイルの設定。
+/// * `values` - タイルに適用する絞り込みの値。
+/// * `pool` - データベースコネクションプール。
+///
+/// # Returns
+///
+/// FeatureCollectionを格納したGeoJSON。
+async fn table_geojson_tile(
+    table: &LayerTable,
+    zoom: u8,
+    x: u32,
+    y: u32,
+    tiles: &TileSettings,
+    values: &[FilterValue],
+    pool: &PgPool,
+) -> Result<Bytes, actix_web::Error> {
+    let polygon = tile_polygon(zoom, x, y, tiles.geojson_buffer_ratio)?;
+    let columns: Vec<&str> = table.properties.iter().map(|(_, column)| *column).collect();
+    let geom = if table.clip && tiles.geojson_clip {
+        "ST_ClipByBox2D(geom, ST_GeomFromText($1, $2))"
+    } else {
+        "geom"
     };
+    let sql = format!(
+        r#"
+        SELECT json_build_object(
+            'type', 'FeatureCollection',
+            'features', COALESCE(json_agg(ST_AsGeoJSON(t.*, 'geom', $3)::json), '[]'::json)
+        ) as fc
+        FROM (
+            SELECT {}, {} as geom
+            FROM {}
+            WHERE ST_Intersects(geom, ST_GeomFromText($1, $2)){}
+        ) t
+        "#,
+        columns.join(", "),
+        geom,
+        table.table,
+        table.filter_condition(true, 4)
+    );
+    let mut args = PgArguments::default();
+    args.add(polygon);
+    args.add(EPSG_WEB_MERCATOR);
+    args.add(tiles.geojson_digits());
+    add_filter_values(&mut args, values);
+    let (fc,): (Option<JsonValue>,) = sqlx::query_as_with(&sql, args)
+        .fetch_one(pool)
+        .instrument(query_span("SELECT layer tile"))
+        .await
+        .map_err(ApiError::from)?;
 
-    feature.to_string()
+    Ok(Bytes::from(fc.unwrap().to_string()))
 }
 
-async fn generate_post_office_features(post_offices: &[PostOffice]) -> String {
-    let mut features = String::from("[");
-    for post_office in post_offices {
-        features.push_str(&generate_post_office_feature(post_office));
-        features.push(',');
-    }
-    if !post_offices.is_empty() {
-        features.remove(features.len() - 1);
-    }
-    features.push(']');
+#[tracing::instrument(
+    name = "Tiled custom layer",
+    skip(req, pool, cache, http_cache, tiles, guardrails, custom_layers)
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn tiled_custom_layer(
+    req: HttpRequest,
+    path: web::Path<(String, u8, u32, u32)>,
+    query: web::Query<HashMap<String, String>>,
+    pool: web::Data<PgPool>,
+    cache: web::Data<TileCache>,
+    http_cache: web::Data<HttpCache>,
+    tiles: web::Data<TileSettings>,
+    guardrails: web::Data<Guardrails>,
+    custom_layers: web::Data<CustomLayers>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (name, zoom, x, y) = path.into_inner();
+    let custom = custom_layers.get(&name).ok_or(ApiError::UnknownLayer)?;
+    check_served_tile(&guardrails, zoom, x, y)?;
+    let values = custom.param_values(&query)?;
+    let key = TileKey::new(&name, TileFormat::GeoJson, zoom, x, y)
+        .with_params(custom.cache_params(&values));
+    cached_tile(
+        &req,
+        &cache,
+        &http_cache,
+        key,
+        geojson_tile_header(&req)?,
+        async {
+            let polygon = tile_polygon(zoom, x, y, tiles.geojson_buffer_ratio)?;
+            // フィーチャーを1件ずつ問い合わせて、コレクションのエンドポイントと同じストリームでフィーチャーを書き込む
+            let body = custom
+                .features(
+                    pool.as_ref(),
+                    &values,
+                    Some(&polygon),
+                    tiles.geojson_clip,
+                    EPSG_WEB_MERCATOR,
+                    tiles.geojson_digits() as u8,
+                )
+                .await?
+                .try_fold(Vec::new(), |mut body, chunk| async move {
+                    body.extend_from_slice(&chunk);
+                    Ok(body)
+                })
+                .instrument(query_span("SELECT custom layer tile"))
+                .await?;
 
-    features
+            Ok(Bytes::from(body))
+        },
+    )
+    .await
 }
 
-#[tracing::instrument(name = "Post offices", skip(pool))]
-pub async fn post_offices(
+#[tracing::instrument(name = "Custom layer features", skip(pool, custom_layers))]
+pub async fn custom_layer_features(
+    path: web::Path<(String,)>,
+    query: web::Query<HashMap<String, String>>,
+    geometry: web::Query<GeometryQuery>,
+    pool: web::Data<PgPool>,
+    custom_layers: web::Data<CustomLayers>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let name = path.into_inner().0;
+    let custom = custom_layers.get(&name).ok_or(ApiError::UnknownLayer)?;
+    let values = custom.param_values(&query)?;
+    let srid = geometry.srid()?;
+    let features = custom
+        .features(
+            pool.as_ref(),
+            &values,
+            None,
+            false,
+            srid,
+            geometry.digits()?,
+        )
+        .await?;
+    let mut response = HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .streaming(features);
+    insert_content_crs(&mut response, srid);
+
+    Ok(response)
+}
+
+#[tracing::instrument(
+    name = "Vector tiles",
+    skip(
+        req,
+        pool,
+        cache,
+        http_cache,
+        tiles,
+        guardrails,
+        custom_layers,
+        pmtiles
+    )
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn vector_tiles(
+    req: HttpRequest,
+    path: web::Path<(String, u8, u32, u32)>,
+    query: web::Query<HashMap<String, String>>,
+    pool: web::Data<PgPool>,
+    cache: web::Data<TileCache>,
+    http_cache: web::Data<HttpCache>,
+    tiles: web::Data<TileSettings>,
+    guardrails: web::Data<Guardrails>,
+    custom_layers: web::Data<CustomLayers>,
+    pmtiles: web::Data<PmtilesArchives>,
+    filter: web::Query<PostOfficeCategoryFilter>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (name, zoom, x, y) = path.into_inner();
+    check_served_tile(&guardrails, zoom, x, y)?;
+    let content_type = ContentType(MVT_CONTENT_TYPE.parse().unwrap());
+    // PMTilesのアーカイブから配信するレイヤーは、データベースに問い合わせずにアーカイブのタイルを返す
+    if let Some(archive) = pmtiles.get(&name) {
+        let tile = web::block(move || archive.tile(zoom, x, y))
+            .await?
+            .map_err(|e| {
+                tracing::error!(
+                    "レイヤー({})のタイル({}/{}/{})をアーカイブから読み込めません。{}",
+                    name,
+                    zoom,
+                    x,
+                    y,
+                    e
+                );
+                ApiError::TileReadFailed
+            })?;
+        let mut response =
+            conditional_response(&req, content_type, Bytes::from(tile.unwrap_or_default()));
+        http_cache.apply(&name, &mut response);
+        vary_accept(&mut response);
+
+        return Ok(response);
+    }
+    let layer = match Layer::from_name(&name) {
+        Some(layer) => layer,
+        None => {
+            // 組み込みのレイヤーでない場合は、カスタムレイヤーのタイルを返す
+            let custom = custom_layers.get(&name).ok_or(ApiError::UnknownLayer)?;
+            let values = custom.param_values(&query)?;
+            let key = TileKey::new(&name, TileFormat::Mvt, zoom, x, y)
+                .with_params(custom.cache_params(&values));
+            return cached_tile(&req, &cache, &http_cache, key, content_type, async {
+                custom
+                    .vector_tile(
+                        pool.as_ref(),
+                        &values,
+                        zoom as i32,
+                        x as i32,
+                        y as i32,
+                        &tiles,
+                    )
+                    .await
+                    .map(Bytes::from)
+                    .map_err(|e| ApiError::from(e).into())
+            })
+            .await;
+        }
+    };
+    // 郵便局の分類と、テーブルを定義したレイヤーのコードによる絞り込みは、それぞれのレイヤーのタイルに
+    // 限り適用する
+    let categories = filter.categories()?;
+    let values = match layer.table() {
+        Some(table) => filter_values(table, true, &query)?,
+        None => Vec::new(),
+    };
+    let params = match layer.table() {
+        Some(table) => filter_cache_params(table, &values),
+        None if layer == Layer::PostOffices => categories.cache_params(),
+        None => String::new(),
+    };
+    let key = TileKey::new(layer.name(), TileFormat::Mvt, zoom, x, y).with_params(params);
+    cached_tile(&req, &cache, &http_cache, key, content_type, async {
+        match filtered_vector_tile(
+            layer,
+            zoom,
+            x,
+            y,
+            &tiles,
+            &categories,
+            &values,
+            pool.as_ref(),
+        )
+        .await
+        {
+            Ok(tile) => Ok(Bytes::from(tile)),
+            Err(e) => Err(ApiError::from(e).into()),
+        }
+    })
+    .await
+}
+
+#[tracing::instrument(
+    name = "Combined vector tiles",
+    skip(req, pool, cache, http_cache, tiles, guardrails)
+)]
+pub async fn combined_vector_tiles(
+    req: HttpRequest,
     path: web::Path<(u8, u32, u32)>,
     pool: web::Data<PgPool>,
+    cache: web::Data<TileCache>,
+    http_cache: web::Data<HttpCache>,
+    tiles: web::Data<TileSettings>,
+    guardrails: web::Data<Guardrails>,
 ) -> Result<HttpResponse, actix_web::Error> {
-    let polygon = tile_polygon(path.0, path.1, path.2)?;
-    let result = sqlx::query_as!(
-        PostOffice,
+    let (zoom, x, y) = path.into_inner();
+    check_served_tile(&guardrails, zoom, x, y)?;
+    let key = TileKey::new(COMBINED_TILE_LAYER, TileFormat::Mvt, zoom, x, y);
+    let content_type = ContentType(MVT_CONTENT_TYPE.parse().unwrap());
+    cached_tile(&req, &cache, &http_cache, key, content_type, async {
+        combined_vector_tile(zoom, x as i32, y as i32, &tiles, pool.as_ref())
+            .await
+            .map(Bytes::from)
+            .map_err(|e| ApiError::from(e).into())
+    })
+    .await
+}
+
+/// 組み込みのレイヤーの、絞り込みの条件を適用したベクタータイルを生成する。
+///
+/// # Arguments
+///
+/// * `layer` - レイヤー。
+/// * `zoom` - ズームレベル。
+/// * `x` - タイルのX座標。
+/// * `y` - タイルのY座標。
+/// * `tiles` - タイルの設定。
+/// * `categories` - タイルに含める郵便局の分類。郵便局のレイヤーに限り適用する。
+/// * `values` - タイルに適用する絞り込みの値。テーブルを定義したレイヤーに限り適用する。
+/// * `pool` - データベースコネクションプール。
+///
+/// # Returns
+///
+/// Mapbox Vector Tile形式でエンコードしたタイル。
+#[allow(clippy::too_many_arguments)]
+async fn filtered_vector_tile(
+    layer: Layer,
+    zoom: u8,
+    x: u32,
+    y: u32,
+    tiles: &TileSettings,
+    categories: &PostOfficeCategories,
+    values: &[FilterValue],
+    pool: &PgPool,
+) -> sqlx::Result<Vec<u8>> {
+    let (zoom, x, y) = (zoom as i32, x as i32, y as i32);
+    if let Some(table) = layer.table() {
+        return table_vector_tile(layer, table, zoom, x, y, tiles, values, pool).await;
+    }
+    // テーブルを定義していないレイヤーは、都道府県、市区町村及び郵便局に限る
+    match layer {
+        Layer::Prefectures => prefecture_vector_tile(zoom, x, y, tiles, pool).await,
+        Layer::PostOffices => post_office_vector_tile(zoom, x, y, tiles, categories, pool).await,
+        _ => city_vector_tile(zoom, x, y, tiles, pool).await,
+    }
+}
+
+/// 組み込みのレイヤーのGeoJSONのタイルを、`/tiles/{layer}/{z}/{x}/{y}`と同じ処理で生成する。
+///
+/// 地物を絞り込まずに生成するため、クエリパラメーターを指定しないリクエストに返すタイルと同じ内容になる。
+///
+/// # Arguments
+///
+/// * `layer` - レイヤー名。
+/// * `zoom` - ズームレベル。
+/// * `x` - タイルのX座標。
+/// * `y` - タイルのY座標。
+/// * `tiles` - タイルの設定。
+/// * `pool` - データベースコネクションプール。
+///
+/// # Returns
+///
+/// FeatureCollectionを格納したGeoJSON。組み込みのレイヤーでない場合は404 Not Foundを示すエラー。
+pub async fn layer_geojson_tile(
+    layer: &str,
+    zoom: u8,
+    x: u32,
+    y: u32,
+    tiles: &TileSettings,
+    pool: &PgPool,
+) -> Result<Bytes, actix_web::Error> {
+    match layer {
+        "prefectures" => prefecture_geojson_tile(zoom, x, y, tiles, pool).await,
+        "cities" => city_geojson_tile(zoom, x, y, tiles, pool).await,
+        "post_offices" => {
+            let categories = PostOfficeCategories::default();
+            post_office_geojson_tile(zoom, x, y, tiles, &categories, pool).await
+        }
+        _ => {
+            let table = Layer::from_name(layer)
+                .and_then(|layer| layer.table())
+                .ok_or(ApiError::UnknownLayer)?;
+            table_geojson_tile(table, zoom, x, y, tiles, &unfiltered_values(table), pool).await
+        }
+    }
+}
+
+/// 組み込みのレイヤーのベクタータイルを、`/mvt/{layer}/{z}/{x}/{y}.pbf`と同じ処理で生成する。
+///
+/// 地物を絞り込まずに生成するため、クエリパラメーターを指定しないリクエストに返すタイルと同じ内容になる。
+///
+/// # Arguments
+///
+/// * `layer` - レイヤー。
+/// * `zoom` - ズームレベル。
+/// * `x` - タイルのX座標。
+/// * `y` - タイルのY座標。
+/// * `tiles` - タイルの設定。
+/// * `pool` - データベースコネクションプール。
+///
+/// # Returns
+///
+/// Mapbox Vector Tile形式でエンコードしたタイル。
+pub async fn layer_vector_tile(
+    layer: Layer,
+    zoom: u8,
+    x: u32,
+    y: u32,
+    tiles: &TileSettings,
+    pool: &PgPool,
+) -> sqlx::Result<Vec<u8>> {
+    let values = layer.table().map(unfiltered_values).unwrap_or_default();
+    filtered_vector_tile(
+        layer,
+        zoom,
+        x,
+        y,
+        tiles,
+        &PostOfficeCategories::default(),
+        &values,
+        pool,
+    )
+    .await
+}
+
+/// 都道府県のベクタータイルを生成する。
+///
+/// # Arguments
+///
+/// * `zoom` - ズームレベル。
+/// * `x` - タイルのX座標。
+/// * `y` - タイルのY座標。
+/// * `tiles` - タイルの設定。
+/// * `pool` - データベースコネクションプール。
+///
+/// # Returns
+///
+/// Mapbox Vector Tile形式でエンコードしたタイル。
+async fn prefecture_vector_tile(
+    zoom: i32,
+    x: i32,
+    y: i32,
+    tiles: &TileSettings,
+    pool: &PgPool,
+) -> sqlx::Result<Vec<u8>> {
+    let sql = format!(
         r#"
-        SELECT
-            id, city_code, category_code, subcategory_code, post_office_code,
-            name, address, geom as "geom!: _"
-        FROM
-            post_offices
-        WHERE
-            ST_Intersects(geom, ST_GeomFromText($1, $2))
+        SELECT ST_AsMVT(p.*, 'prefectures', 4096, 'geom') as mvt
+        FROM (
+            SELECT
+                id::text, name,
+                ST_AsMVTGeom({geom}, ST_TileEnvelope($1, $2, $3), 4096, $4, $5) as geom
+            FROM
+                prefectures
+            WHERE
+                {geom} && ST_TileEnvelope($1, $2, $3)
+        ) p
         "#,
-        polygon,
-        EPSG_WEB_MERCATOR,
+        geom = geometry_column(zoom as u8),
+    );
+    let (mvt,): (Option<Vec<u8>>,) = sqlx::query_as(&sql)
+        .bind(zoom)
+        .bind(x)
+        .bind(y)
+        .bind(tiles.mvt_buffer)
+        .bind(tiles.mvt_clip)
+        .fetch_one(pool)
+        .instrument(query_span("SELECT prefectures vector tile"))
+        .await?;
+
+    Ok(mvt.unwrap_or_default())
+}
+
+/// 市区町村のベクタータイルを生成する。
+///
+/// # Arguments
+///
+/// * `zoom` - ズームレベル。
+/// * `x` - タイルのX座標。
+/// * `y` - タイルのY座標。
+/// * `tiles` - タイルの設定。
+/// * `pool` - データベースコネクションプール。
+///
+/// # Returns
+///
+/// Mapbox Vector Tile形式でエンコードしたタイル。
+async fn city_vector_tile(
+    zoom: i32,
+    x: i32,
+    y: i32,
+    tiles: &TileSettings,
+    pool: &PgPool,
+) -> sqlx::Result<Vec<u8>> {
+    let sql = format!(
+        r#"
+        SELECT ST_AsMVT(c.*, 'cities', 4096, 'geom') as mvt
+        FROM (
+            SELECT
+                id::text, code, area, name,
+                ST_AsMVTGeom({geom}, ST_TileEnvelope($1, $2, $3), 4096, $4, $5) as geom
+            FROM
+                cities
+            WHERE
+                {geom} && ST_TileEnvelope($1, $2, $3)
+        ) c
+        "#,
+        geom = geometry_column(zoom as u8),
+    );
+    let (mvt,): (Option<Vec<u8>>,) = sqlx::query_as(&sql)
+        .bind(zoom)
+        .bind(x)
+        .bind(y)
+        .bind(tiles.mvt_buffer)
+        .bind(tiles.mvt_clip)
+        .fetch_one(pool)
+        .instrument(query_span("SELECT cities vector tile"))
+        .await?;
+
+    Ok(mvt.unwrap_or_default())
+}
+
+/// 郵便局のベクタータイルを生成する。
+///
+/// # Arguments
+///
+/// * `zoom` - ズームレベル。
+/// * `x` - タイルのX座標。
+/// * `y` - タイルのY座標。
+/// * `tiles` - タイルの設定。
+/// * `categories` - タイルに含める郵便局の分類。
+/// * `pool` - データベースコネクションプール。
+///
+/// # Returns
+///
+/// Mapbox Vector Tile形式でエンコードしたタイル。
+async fn post_office_vector_tile(
+    zoom: i32,
+    x: i32,
+    y: i32,
+    tiles: &TileSettings,
+    categories: &PostOfficeCategories,
+    pool: &PgPool,
+) -> sqlx::Result<Vec<u8>> {
+    let result = sqlx::query!(
+        r#"
+        SELECT ST_AsMVT(p.*, 'post_offices', 4096, 'geom') as mvt
+        FROM (
+            SELECT
+                id::text, city_code as "cityCode", category_code as "categoryCode",
+                subcategory_code as "subcategoryCode", post_office_code as "postOfficeCode",
+                name, address, city_name as "cityName", town, block,
+                ST_AsMVTGeom(geom, ST_TileEnvelope($1, $2, $3), 4096, $4, $5) as geom
+            FROM
+                post_offices
+            WHERE
+                geom && ST_TileEnvelope($1, $2, $3)
+                AND ($6::text[] IS NULL OR post_office_code = ANY($6))
+                AND ($7::text[] IS NULL OR category_code = ANY($7))
+                AND ($8::text[] IS NULL OR subcategory_code = ANY($8))
+        ) p
+        "#,
+        zoom,
+        x,
+        y,
+        tiles.mvt_buffer,
+        tiles.mvt_clip,
+        categories.post_office_codes.as_deref() as _,
+        categories.category_codes.as_deref() as _,
+        categories.subcategory_codes.as_deref() as _,
     )
-    .fetch_all(pool.as_ref())
-    .await;
+    .fetch_one(pool)
+    .instrument(query_span("SELECT post_offices vector tile"))
+    .await?;
 
-    match result {
-        Ok(result) => {
-            let features = generate_post_office_features(&result).await;
-            Ok(HttpResponse::Ok().body(format!(
-                r#"{{"features": {}, "type": "FeatureCollection"}}"#,
-                features,
-            )))
-        }
-        Err(e) => Err(actix_web::error::ErrorInternalServerError(format!("{}", e))),
+    Ok(result.mvt.unwrap_or_default())
+}
+
+/// 都道府県、市区町村及び郵便局を、それぞれのレイヤーに格納したベクタータイルを生成する。
+///
+/// Mapbox Vector Tileはレイヤーを連結したものであるため、レイヤーごとにエンコードしたタイルを連結して、
+/// 1回の問い合わせで生成する。
+///
+/// # Arguments
+///
+/// * `zoom` - ズームレベル。
+/// * `x` - タイルのX座標。
+/// * `y` - タイルのY座標。
+/// * `tiles` - タイルの設定。
+/// * `pool` - データベースコネクションプール。
+///
+/// # Returns
+///
+/// Mapbox Vector Tile形式でエンコードしたタイル。
+async fn combined_vector_tile(
+    zoom: u8,
+    x: i32,
+    y: i32,
+    tiles: &TileSettings,
+    pool: &PgPool,
+) -> sqlx::Result<Vec<u8>> {
+    let sql = format!(
+        r#"
+        SELECT
+            COALESCE((
+                SELECT ST_AsMVT(p.*, 'prefectures', 4096, 'geom')
+                FROM (
+                    SELECT
+                        id::text, name,
+                        ST_AsMVTGeom({geom}, ST_TileEnvelope($1, $2, $3), 4096, $4, $5) as geom
+                    FROM prefectures
+                    WHERE {geom} && ST_TileEnvelope($1, $2, $3)
+                ) p
+            ), ''::bytea)
+            || COALESCE((
+                SELECT ST_AsMVT(c.*, 'cities', 4096, 'geom')
+                FROM (
+                    SELECT
+                        id::text, code, area, name,
+                        ST_AsMVTGeom({geom}, ST_TileEnvelope($1, $2, $3), 4096, $4, $5) as geom
+                    FROM cities
+                    WHERE {geom} && ST_TileEnvelope($1, $2, $3)
+                ) c
+            ), ''::bytea)
+            || COALESCE((
+                SELECT ST_AsMVT(o.*, 'post_offices', 4096, 'geom')
+                FROM (
+                    SELECT
+                        id::text, city_code as "cityCode", category_code as "categoryCode",
+                        subcategory_code as "subcategoryCode",
+                        post_office_code as "postOfficeCode", name, address,
+                        city_name as "cityName", town, block,
+                        ST_AsMVTGeom(geom, ST_TileEnvelope($1, $2, $3), 4096, $4, $5) as geom
+                    FROM post_offices
+                    WHERE geom && ST_TileEnvelope($1, $2, $3)
+                ) o
+            ), ''::bytea) as mvt
+        "#,
+        geom = geometry_column(zoom),
+    );
+    let (mvt,): (Option<Vec<u8>>,) = sqlx::query_as(&sql)
+        .bind(zoom as i32)
+        .bind(x)
+        .bind(y)
+        .bind(tiles.mvt_buffer)
+        .bind(tiles.mvt_clip)
+        .fetch_one(pool)
+        .instrument(query_span("SELECT combined vector tile"))
+        .await?;
+
+    Ok(mvt.unwrap_or_default())
+}
+
+/// テーブルを定義したレイヤーのベクタータイルを生成する。
+///
+/// # Arguments
+///
+/// * `layer` - レイヤー。
+/// * `table` - レイヤーのテーブルの定義。
+/// * `zoom` - ズームレベル。
+/// * `x` - タイルのX座標。
+/// * `y` - タイルのY座標。
+/// * `tiles` - タイルの設定。
+/// * `values` - タイルに適用する絞り込みの値。
+/// * `pool` - データベースコネクションプール。
+///
+/// # Returns
+///
+/// Mapbox Vector Tile形式でエンコードしたタイル。
+#[allow(clippy::too_many_arguments)]
+async fn table_vector_tile(
+    layer: Layer,
+    table: &LayerTable,
+    zoom: i32,
+    x: i32,
+    y: i32,
+    tiles: &TileSettings,
+    values: &[FilterValue],
+    pool: &PgPool,
+) -> sqlx::Result<Vec<u8>> {
+    // ベクタータイルの属性は、TileJSONのフィールドの型に合わせて、IDも文字列で格納する
+    let columns: Vec<&str> = table
+        .properties
+        .iter()
+        .map(|(name, column)| if *name == "id" { "id::text" } else { *column })
+        .collect();
+    let sql = format!(
+        r#"
+        SELECT ST_AsMVT(t.*, '{}', 4096, 'geom') as mvt
+        FROM (
+            SELECT
+                {},
+                ST_AsMVTGeom(geom, ST_TileEnvelope($1, $2, $3), 4096, $4, $5) as geom
+            FROM
+                {}
+            WHERE
+                geom && ST_TileEnvelope($1, $2, $3){}
+        ) t
+        "#,
+        layer.name(),
+        columns.join(", "),
+        table.table,
+        table.filter_condition(true, 6)
+    );
+    let mut args = PgArguments::default();
+    args.add(zoom);
+    args.add(x);
+    args.add(y);
+    args.add(tiles.mvt_buffer);
+    args.add(tiles.mvt_clip);
+    add_filter_values(&mut args, values);
+    let (mvt,): (Option<Vec<u8>>,) = sqlx::query_as_with(&sql, args)
+        .fetch_one(pool)
+        .instrument(query_span("SELECT layer vector tile"))
+        .await?;
+
+    Ok(mvt.unwrap_or_default())
+}
+
+/// タイルキャッシュを破棄するときのクエリパラメーター。
+#[derive(Debug, Deserialize)]
+pub struct InvalidateTileCacheQuery {
+    /// タイルを破棄するレイヤー名。指定されていない場合はすべてのレイヤーのタイルを破棄する。
+    layer: Option<String>,
+}
+
+#[tracing::instrument(name = "Invalidate tile cache", skip(cache, purger, custom_layers))]
+pub async fn invalidate_tile_cache(
+    query: web::Query<InvalidateTileCacheQuery>,
+    cache: web::Data<TileCache>,
+    purger: web::Data<CdnPurger>,
+    custom_layers: web::Data<CustomLayers>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let known_layers: Vec<String> = TILE_LAYERS
+        .iter()
+        .copied()
+        .chain([COMBINED_TILE_LAYER])
+        .chain(custom_layers.names())
+        .map(|name| name.to_string())
+        .collect();
+    let mut layers: Vec<String> = match query.layer.as_deref() {
+        Some(layer) => match known_layers.iter().find(|name| *name == layer) {
+            Some(name) => vec![name.clone()],
+            None => return Err(ApiError::UnknownLayer.into()),
+        },
+        None => known_layers,
+    };
+    let mut invalidated = cache.invalidate(query.layer.as_deref());
+    // 1つにまとめたベクタータイルに含めるレイヤーを指定した場合は、まとめたベクタータイルも破棄
+    if query
+        .layer
+        .as_deref()
+        .is_some_and(|layer| COMBINED_TILE_LAYERS.contains(&layer))
+    {
+        invalidated += cache.invalidate(Some(COMBINED_TILE_LAYER));
+        layers.push(COMBINED_TILE_LAYER.to_string());
+    }
+    tracing::info!("{}個のタイルをキャッシュから破棄", invalidated);
+
+    // CDNのキャッシュ削除は時間がかかる可能性があるため、レスポンスを返した後で実行
+    let purge_requested = purger.is_enabled();
+    if purge_requested {
+        let purger = purger.clone();
+        actix_web::rt::spawn(async move {
+            let layers: Vec<&str> = layers.iter().map(|layer| layer.as_str()).collect();
+            purger.purge(&layers).await
+        });
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
+        "invalidated": invalidated,
+        "purgeRequested": purge_requested,
+    })))
+}
+
+/// サーバーの運用状況を返す。
+///
+/// タイルキャッシュと逆ジオコーディングの結果のキャッシュの使用状況と、データベースコネクションプールの接続数を返す。
+#[tracing::instrument(
+    name = "Admin stats",
+    skip(cache, geocoding_cache, pool, write_pool, purger)
+)]
+pub async fn admin_stats(
+    cache: web::Data<TileCache>,
+    geocoding_cache: web::Data<GeocodingCache>,
+    pool: web::Data<PgPool>,
+    write_pool: web::Data<WritePool>,
+    purger: web::Data<CdnPurger>,
+) -> HttpResponse {
+    let pool_stats = |pool: &PgPool| {
+        json!({
+            "size": pool.size(),
+            "idle": pool.num_idle(),
+        })
+    };
+
+    HttpResponse::Ok().json(json!({
+        "tileCache": cache.stats(),
+        "geocodingCache": geocoding_cache.stats(),
+        "database": {
+            "read": pool_stats(pool.as_ref()),
+            "write": pool_stats(&write_pool.0),
+        },
+        "cdnPurgeEnabled": purger.is_enabled(),
+    }))
+}
+
+#[tracing::instrument(
+    name = "Delete prefecture",
+    skip(pool, cache, geocoding_cache, purger, events)
+)]
+pub async fn delete_prefecture(
+    path: web::Path<(String,)>,
+    pool: web::Data<WritePool>,
+    cache: web::Data<TileCache>,
+    geocoding_cache: web::Data<GeocodingCache>,
+    purger: web::Data<CdnPurger>,
+    events: web::Data<DataEvents>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let code: PrefectureCode = path
+        .into_inner()
+        .0
+        .parse()
+        .map_err(|_| ApiError::NotFound)?;
+    let delete_error = |e: anyhow::Error| {
+        tracing::error!("都道府県({})のデータを削除できません。{}", code, e);
+        ApiError::DeleteFailed
+    };
+
+    // 郵便局、市区町村及び都道府県を1つのトランザクションで削除
+    let mut tx = pool.0.begin().await.map_err(ApiError::from)?;
+    let post_offices = delete_post_offices(&mut tx, code.as_str())
+        .await
+        .map_err(delete_error)?;
+    let (prefectures, cities) = delete_prefectures_and_cities(&mut tx, code.as_str())
+        .await
+        .map_err(delete_error)?;
+    if prefectures + cities + post_offices == 0 {
+        return Err(ApiError::NotFound.into());
+    }
+    // 削除したレイヤーのデータセットの版を更新するため、フィーチャーの数を0件として履歴に記録
+    let source = ImportSource {
+        dataset: "delete",
+        code: Some(code.as_str()),
+        file: "",
+        year: None,
+    };
+    for layer in ["prefectures", "cities", "post_offices"] {
+        record_import(&mut tx, &source, layer, 0)
+            .await
+            .map_err(delete_error)?;
+    }
+    tx.commit().await.map_err(ApiError::from)?;
+    tracing::info!(
+        target: "audit",
+        code = code.as_str(),
+        prefectures,
+        cities,
+        post_offices,
+        "都道府県のデータを削除"
+    );
+
+    let layers = vec![
+        "prefectures".to_string(),
+        "cities".to_string(),
+        "post_offices".to_string(),
+    ];
+    let changed: Vec<&str> = layers.iter().map(|layer| layer.as_str()).collect();
+    events.publish(DataEventKind::Delete, &changed, Some(code.as_str()));
+    geocoding_cache.invalidate_layers(&changed);
+    let (invalidated, purge_requested) = refresh_layers(&cache, &purger, layers);
+
+    Ok(HttpResponse::Ok().json(json!({
+        "code": code,
+        "prefectures": prefectures,
+        "cities": cities,
+        "postOffices": post_offices,
+        "invalidated": invalidated,
+        "purgeRequested": purge_requested,
+    })))
+}
+
+/// レイヤーの範囲(WGS84経緯度)。
+struct Extent {
+    west: Option<f64>,
+    south: Option<f64>,
+    east: Option<f64>,
+    north: Option<f64>,
+}
+
+/// PMTilesのアーカイブのMIMEタイプ。
+const PMTILES_CONTENT_TYPE: &str = "application/vnd.pmtiles";
+
+/// PMTilesのアーカイブのファイルを、Rangeヘッダーで指定された範囲に限って返す。
+///
+/// PMTilesに対応したクライアントは、ヘッダー、ディレクトリ及びタイルの範囲を指定して、アーカイブを直接読み込める。
+/// ETagは、アーカイブのファイルの大きさと更新日時から生成する。
+#[tracing::instrument(name = "PMTiles archive", skip(req, pmtiles))]
+pub async fn pmtiles_archive(
+    req: HttpRequest,
+    path: web::Path<(String,)>,
+    pmtiles: web::Data<PmtilesArchives>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let name = path.into_inner().0;
+    let archive = pmtiles.get(&name).ok_or(ApiError::UnknownLayer)?;
+    let read_error = |e: std::io::Error| {
+        tracing::error!(
+            "レイヤー({})のアーカイブ({})を読み込めません。{}",
+            name,
+            archive.path().display(),
+            e
+        );
+        ApiError::TileReadFailed
+    };
+    let file = tokio::fs::File::open(archive.path())
+        .await
+        .map_err(read_error)?;
+    let metadata = file.metadata().await.map_err(read_error)?;
+    let mut hasher = DefaultHasher::new();
+    hasher.write_u64(metadata.len());
+    if let Ok(modified) = metadata.modified() {
+        hasher.write(format!("{:?}", modified).as_bytes());
     }
+    let etag = EntityTag::new_strong(format!("{:016x}", hasher.finish()));
+    let content_type = ContentType(PMTILES_CONTENT_TYPE.parse().unwrap());
+
+    Ok(ranged_file_response(&req, file, etag, content_type, None).await?)
 }
 
-fn tile_polygon(zoom: u8, x: u32, y: u32) -> Result<String, actix_web::Error> {
-    let tile = smt::Tile::new(zoom, x, y);
-    if tile.is_none() {
-        return Err(actix_web::error::ErrorBadRequest("Invalid tile info"));
+#[tracing::instrument(name = "TileJSON", skip(req, pool, guardrails, custom_layers))]
+pub async fn tile_json(
+    req: HttpRequest,
+    path: web::Path<(String,)>,
+    query: web::Query<HashMap<String, String>>,
+    pool: web::Data<PgPool>,
+    guardrails: web::Data<Guardrails>,
+    custom_layers: web::Data<CustomLayers>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let name = path.into_inner().0;
+    let (extent, attribution, fields) = match Layer::from_name(&name) {
+        Some(layer) => {
+            let extent = layer_extent(layer, pool.as_ref())
+                .await
+                .map_err(ApiError::from)?;
+            let fields: JsonObject = layer
+                .fields()
+                .iter()
+                .map(|(name, field_type)| (name.to_string(), JsonValue::from(*field_type)))
+                .collect();
+            (extent, layer.attribution().to_string(), fields)
+        }
+        None => {
+            let custom = custom_layers.get(&name).ok_or(ApiError::UnknownLayer)?;
+            let values = custom.param_values(&query)?;
+            let (west, south, east, north) = custom
+                .extent(pool.as_ref(), &values)
+                .await
+                .map_err(ApiError::from)?;
+            let fields: JsonObject = custom
+                .fields
+                .iter()
+                .map(|(name, field_type)| (name.clone(), JsonValue::from(*field_type)))
+                .collect();
+            let extent = Extent {
+                west,
+                south,
+                east,
+                north,
+            };
+            (extent, custom.layer.attribution.clone(), fields)
+        }
+    };
+    // レイヤーにフィーチャーが存在しない場合は、Webメルカトルの全範囲を返す
+    let bounds = match extent {
+        Extent {
+            west: Some(west),
+            south: Some(south),
+            east: Some(east),
+            north: Some(north),
+        } => [west, south, east, north],
+        _ => [-180.0, -85.051129, 180.0, 85.051129],
+    };
+    // 配信する範囲を制限している場合は、レイヤーの範囲を配信する範囲に収める
+    let bounds = match guardrails.bounds {
+        Some(served) => [
+            bounds[0].max(served.west),
+            bounds[1].max(served.south),
+            bounds[2].min(served.east),
+            bounds[3].min(served.north),
+        ],
+        None => bounds,
     };
-    let tile = tile.unwrap();
-    // タイルの範囲をWGS84緯度経度で取得
-    let lb = (tile.left(), tile.bottom());
-    let rt = (tile.right(), tile.top());
-    // タイルの範囲をWebメルカトル座標に変換
-    let from = format!("EPSG:{}", EPSG_WGS84);
-    let to = format!("EPSG:{}", EPSG_WEB_MERCATOR);
-    let ft_to_m = Proj::new_known_crs(&from, &to, None).unwrap();
-    let mut lb = ft_to_m.convert(lb).unwrap();
-    let mut rt = ft_to_m.convert(rt).unwrap();
+    let style = layer_style(pool.as_ref(), &name)
+        .await
+        .map_err(ApiError::from)?;
+    let info = req.connection_info();
+    let mut tiles = format!(
+        "{}://{}/mvt/{}/{{z}}/{{x}}/{{y}}.pbf",
+        info.scheme(),
+        info.host(),
+        name
+    );
+    // カスタムレイヤーのパラメーターを指定された場合は、タイルのURLにも同じパラメーターを指定する
+    if !req.query_string().is_empty() {
+        tiles.push('?');
+        tiles.push_str(req.query_string());
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
+        "tilejson": "3.0.0",
+        "name": name,
+        "attribution": attribution,
+        "scheme": "xyz",
+        "tiles": [tiles],
+        "minzoom": guardrails.min_zoom,
+        "maxzoom": guardrails.max_zoom,
+        "bounds": bounds,
+        "vector_layers": [{
+            "id": name,
+            "fields": fields,
+            "minzoom": guardrails.min_zoom,
+            "maxzoom": guardrails.max_zoom,
+            "style": style,
+        }],
+    })))
+}
+
+/// レイヤーに登録されているフィーチャーの範囲を、WGS84経緯度で取得する。
+///
+/// # Arguments
+///
+/// * `layer` - レイヤー。
+/// * `pool` - データベースコネクションプール。
+///
+/// # Returns
+///
+/// レイヤーの範囲。フィーチャーが登録されていない場合、範囲の各値はNone。
+async fn layer_extent(layer: Layer, pool: &PgPool) -> sqlx::Result<Extent> {
+    // テーブルを定義したレイヤーは、テーブルの定義から範囲を問い合わせる
+    if let Some(table) = layer.table() {
+        let sql = format!(
+            r#"
+            SELECT
+                ST_XMin(e.geom) as west, ST_YMin(e.geom) as south,
+                ST_XMax(e.geom) as east, ST_YMax(e.geom) as north
+            FROM (
+                SELECT ST_Transform(ST_SetSRID(ST_Extent(geom)::geometry, $1), $2) as geom
+                FROM {}
+            ) e
+            "#,
+            table.table
+        );
+        let (west, south, east, north) = sqlx::query_as(&sql)
+            .bind(EPSG_WEB_MERCATOR)
+            .bind(EPSG_WGS84)
+            .fetch_one(pool)
+            .instrument(query_span("SELECT layer extent"))
+            .await?;
+
+        return Ok(Extent {
+            west,
+            south,
+            east,
+            north,
+        });
+    }
+    // テーブルを定義していないレイヤーは、都道府県、市区町村及び郵便局に限る
+    match layer {
+        Layer::Prefectures => {
+            sqlx::query_as!(
+                Extent,
+                r#"
+                SELECT
+                    ST_XMin(e.geom) as west, ST_YMin(e.geom) as south,
+                    ST_XMax(e.geom) as east, ST_YMax(e.geom) as north
+                FROM (
+                    SELECT ST_Transform(ST_SetSRID(ST_Extent(geom)::geometry, $1), $2) as geom
+                    FROM prefectures
+                ) e
+                "#,
+                EPSG_WEB_MERCATOR,
+                EPSG_WGS84,
+            )
+            .fetch_one(pool)
+            .instrument(query_span("SELECT prefectures extent"))
+            .await
+        }
+        Layer::Cities => {
+            sqlx::query_as!(
+                Extent,
+                r#"
+                SELECT
+                    ST_XMin(e.geom) as west, ST_YMin(e.geom) as south,
+                    ST_XMax(e.geom) as east, ST_YMax(e.geom) as north
+                FROM (
+                    SELECT ST_Transform(ST_SetSRID(ST_Extent(geom)::geometry, $1), $2) as geom
+                    FROM cities
+                ) e
+                "#,
+                EPSG_WEB_MERCATOR,
+                EPSG_WGS84,
+            )
+            .fetch_one(pool)
+            .instrument(query_span("SELECT cities extent"))
+            .await
+        }
+        _ => {
+            sqlx::query_as!(
+                Extent,
+                r#"
+                SELECT
+                    ST_XMin(e.geom) as west, ST_YMin(e.geom) as south,
+                    ST_XMax(e.geom) as east, ST_YMax(e.geom) as north
+                FROM (
+                    SELECT ST_Transform(ST_SetSRID(ST_Extent(geom)::geometry, $1), $2) as geom
+                    FROM post_offices
+                ) e
+                "#,
+                EPSG_WEB_MERCATOR,
+                EPSG_WGS84,
+            )
+            .fetch_one(pool)
+            .instrument(query_span("SELECT post_offices extent"))
+            .await
+        }
+    }
+}
+
+fn tile_polygon(zoom: u8, x: u32, y: u32, buffer_ratio: f64) -> Result<String, actix_web::Error> {
+    let tile = Tile::new(zoom, x, y).ok_or(ApiError::InvalidTile)?;
     /*
         タイル範囲を拡張
         https://stackoverflow.com/questions/63527124/openlayers-vector-tiles-styling-features-at-edges
         If you are producing your own tiles make sure they have a buffer overlapping the adjacent tiles
         docs.mapbox.com/vector-tiles/specification/#encoding-geometry – Mike Aug 21, 2020 at 20:53
     */
-    let x_expand = (rt.0 - lb.0) * 2.0 / 10.0;
-    lb.0 -= x_expand;
-    rt.0 += x_expand;
-    let y_expand = (rt.1 - lb.1) * 2.0 / 10.0;
-    lb.1 -= y_expand;
-    rt.1 += y_expand;
-    // タイルの範囲を示すポリゴンを定義
-    Ok(format!(
-        "POLYGON(({} {}, {} {}, {} {}, {} {}, {} {}))",
-        lb.0, lb.1, rt.0, lb.1, rt.0, rt.1, lb.0, rt.1, lb.0, lb.1,
-    ))
+    // タイルの範囲を示すポリゴンを、Webメルカトル座標で定義
+    Ok(tile.buffered_bounds(buffer_ratio).to_wkt())
 }
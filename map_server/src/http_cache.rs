@@ -0,0 +1,77 @@
+//! タイルのレスポンスに、ブラウザやCDNにキャッシュさせるためのヘッダーを付ける。
+
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+use actix_web::http::header::{
+    CacheControl, CacheDirective, Expires, Header, HttpDate, TryIntoHeaderValue,
+};
+use actix_web::HttpResponse;
+use serde::Deserialize;
+
+/// タイルのレスポンスのキャッシュを制御するヘッダーの設定。
+///
+/// キャッシュさせる秒数は、レイヤーごとに設定した秒数、既定の秒数の順に選択する。いずれも設定されていない
+/// レイヤーのタイルには、`Cache-Control`ヘッダーと`Expires`ヘッダーを付けない。
+///
+/// ```toml
+/// [http_cache]
+/// max_age_seconds = 3600
+///
+/// [http_cache.layers]
+/// prefectures = 604800
+/// cities = 604800
+/// post_offices = 600
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct HttpCache {
+    /// タイルをキャッシュさせる秒数の既定値(設定されていない場合はヘッダーを付けない)
+    pub max_age_seconds: Option<u32>,
+    /// レイヤー名と、そのレイヤーのタイルをキャッシュさせる秒数
+    pub layers: HashMap<String, u32>,
+}
+
+impl HttpCache {
+    /// レイヤーのタイルをキャッシュさせる秒数を返す。
+    ///
+    /// # Arguments
+    ///
+    /// * `layer` - レイヤー名。
+    ///
+    /// # Returns
+    ///
+    /// キャッシュさせる秒数。設定されていない場合はNone。
+    pub fn max_age(&self, layer: &str) -> Option<u32> {
+        self.layers.get(layer).copied().or(self.max_age_seconds)
+    }
+
+    /// レスポンスに、レイヤーのタイルをキャッシュさせる秒数を指定する`Cache-Control`ヘッダーと
+    /// `Expires`ヘッダーを付ける。
+    ///
+    /// 304 Not Modifiedのレスポンスにも、キャッシュの有効期限を更新するために同じヘッダーを付ける。
+    ///
+    /// # Arguments
+    ///
+    /// * `layer` - レイヤー名。
+    /// * `response` - タイルを返すレスポンス。
+    pub fn apply(&self, layer: &str, response: &mut HttpResponse) {
+        let max_age = match self.max_age(layer) {
+            Some(max_age) => max_age,
+            None => return,
+        };
+        let cache_control = CacheControl(vec![
+            CacheDirective::Public,
+            CacheDirective::MaxAge(max_age),
+        ]);
+        let expires = Expires(HttpDate::from(
+            SystemTime::now() + Duration::from_secs(max_age as u64),
+        ));
+        let headers = response.headers_mut();
+        headers.insert(
+            CacheControl::name(),
+            cache_control.try_into_value().unwrap(),
+        );
+        headers.insert(Expires::name(), expires.try_into_value().unwrap());
+    }
+}
@@ -0,0 +1,567 @@
+use serde::Deserialize;
+
+use crate::handlers::PropertyColumns;
+
+/// ベクタータイルのズームレベルの最小値。
+pub const TILE_MIN_ZOOM: u8 = 0;
+/// ベクタータイルのズームレベルの最大値。
+pub const TILE_MAX_ZOOM: u8 = 18;
+
+/// タイルとして配信するレイヤー。
+///
+/// パスパラメーターから`cities`や`post_offices`のようなスネークケースの名前でデシリアライズする。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Layer {
+    /// 都道府県
+    Prefectures,
+    /// 市区町村
+    Cities,
+    /// 郵便局
+    PostOffices,
+    /// 鉄道路線
+    Railways,
+    /// 駅
+    Stations,
+    /// 学校
+    Schools,
+    /// 医療機関
+    MedicalInstitutions,
+    /// 河川
+    Rivers,
+}
+
+impl Layer {
+    /// レイヤー名からレイヤーを返す。
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - レイヤー名。
+    ///
+    /// # Returns
+    ///
+    /// レイヤー。レイヤー名に一致するレイヤーがない場合はNone。
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "prefectures" => Some(Layer::Prefectures),
+            "cities" => Some(Layer::Cities),
+            "post_offices" => Some(Layer::PostOffices),
+            "railways" => Some(Layer::Railways),
+            "stations" => Some(Layer::Stations),
+            "schools" => Some(Layer::Schools),
+            "medical_institutions" => Some(Layer::MedicalInstitutions),
+            "rivers" => Some(Layer::Rivers),
+            _ => None,
+        }
+    }
+
+    /// すべてのレイヤーを返す。
+    pub fn all() -> [Self; 8] {
+        [
+            Layer::Prefectures,
+            Layer::Cities,
+            Layer::PostOffices,
+            Layer::Railways,
+            Layer::Stations,
+            Layer::Schools,
+            Layer::MedicalInstitutions,
+            Layer::Rivers,
+        ]
+    }
+
+    /// 属性で絞り込んで配信するレイヤーの名前に一致する、パスパラメーターの正規表現を返す。
+    ///
+    /// # Returns
+    ///
+    /// テーブルを定義したレイヤーの名前を`|`で連結した正規表現。
+    pub fn table_pattern() -> String {
+        Self::all()
+            .iter()
+            .filter(|layer| layer.table().is_some())
+            .map(Layer::name)
+            .collect::<Vec<_>>()
+            .join("|")
+    }
+
+    /// レイヤー名を返す。
+    ///
+    /// # Returns
+    ///
+    /// パスパラメーターやベクタータイルのレイヤー名として使用する名前。
+    pub fn name(&self) -> &'static str {
+        match self {
+            Layer::Prefectures => "prefectures",
+            Layer::Cities => "cities",
+            Layer::PostOffices => "post_offices",
+            Layer::Railways => "railways",
+            Layer::Stations => "stations",
+            Layer::Schools => "schools",
+            Layer::MedicalInstitutions => "medical_institutions",
+            Layer::Rivers => "rivers",
+        }
+    }
+
+    /// レイヤーの出典を返す。
+    ///
+    /// # Returns
+    ///
+    /// 国土数値情報ダウンロードサイトコンテンツ利用規約に従った出典。
+    pub fn attribution(&self) -> &'static str {
+        match self {
+            // 都道府県は、市区町村と同じ行政区域データから登録している
+            Layer::Prefectures | Layer::Cities => {
+                "「国土数値情報（行政区域データ）」（国土交通省）を加工して作成"
+            }
+            Layer::PostOffices => "「国土数値情報（郵便局データ）」（国土交通省）を加工して作成",
+            Layer::Railways | Layer::Stations => {
+                "「国土数値情報（鉄道データ）」（国土交通省）を加工して作成"
+            }
+            Layer::Schools => "「国土数値情報（学校データ）」（国土交通省）を加工して作成",
+            Layer::MedicalInstitutions => {
+                "「国土数値情報（医療機関データ）」（国土交通省）を加工して作成"
+            }
+            Layer::Rivers => "「国土数値情報（河川データ）」（国土交通省）を加工して作成",
+        }
+    }
+
+    /// ベクタータイルに含める属性の名前と型を返す。
+    ///
+    /// # Returns
+    ///
+    /// 属性の名前と型のタプルを格納したスライス。
+    pub fn fields(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Layer::Prefectures => &[("id", "String"), ("name", "String")],
+            Layer::Cities => &[
+                ("id", "String"),
+                ("code", "String"),
+                ("area", "String"),
+                ("name", "String"),
+            ],
+            Layer::PostOffices => &[
+                ("id", "String"),
+                ("cityCode", "String"),
+                ("categoryCode", "String"),
+                ("subcategoryCode", "String"),
+                ("postOfficeCode", "String"),
+                ("name", "String"),
+                ("address", "String"),
+                ("cityName", "String"),
+                ("town", "String"),
+                ("block", "String"),
+            ],
+            Layer::Railways => &[
+                ("id", "String"),
+                ("railwayType", "String"),
+                ("operatorType", "String"),
+                ("lineName", "String"),
+                ("operator", "String"),
+            ],
+            Layer::Stations => &[
+                ("id", "String"),
+                ("railwayType", "String"),
+                ("operatorType", "String"),
+                ("lineName", "String"),
+                ("operator", "String"),
+                ("name", "String"),
+            ],
+            Layer::Schools => &[
+                ("id", "String"),
+                ("cityCode", "String"),
+                ("schoolCode", "String"),
+                ("categoryCode", "String"),
+                ("name", "String"),
+                ("address", "String"),
+                ("administratorCode", "String"),
+            ],
+            Layer::MedicalInstitutions => &[
+                ("id", "String"),
+                ("prefectureCode", "String"),
+                ("institutionType", "String"),
+                ("name", "String"),
+                ("address", "String"),
+                ("departments", "String"),
+            ],
+            Layer::Rivers => &[
+                ("id", "String"),
+                ("prefectureCode", "String"),
+                ("waterSystemCode", "String"),
+                ("riverCode", "String"),
+                ("sectionType", "String"),
+                ("name", "String"),
+            ],
+        }
+    }
+
+    /// 属性で絞り込んで配信するレイヤーのテーブルの定義を返す。
+    ///
+    /// 都道府県、市区町村及び郵便局は、ジオメトリの簡略化や住所の検索などの固有の処理があるため、
+    /// テーブルの定義を持たない。
+    ///
+    /// # Returns
+    ///
+    /// テーブルの定義。テーブルを定義していないレイヤーの場合はNone。
+    pub fn table(&self) -> Option<&'static LayerTable> {
+        match self {
+            Layer::Prefectures | Layer::Cities | Layer::PostOffices => None,
+            Layer::Railways => Some(&RAILWAY_TABLE),
+            Layer::Stations => Some(&STATION_TABLE),
+            Layer::Schools => Some(&SCHOOL_TABLE),
+            Layer::MedicalInstitutions => Some(&MEDICAL_INSTITUTION_TABLE),
+            Layer::Rivers => Some(&RIVER_TABLE),
+        }
+    }
+}
+
+/// 地物を絞り込むクエリパラメーターの種類。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterKind {
+    /// 列の値と一致する地物に絞り込む
+    Equals,
+    /// 列の値が都道府県コードと一致する地物に絞り込む
+    PrefectureCode,
+    /// 列の値に文字列を含む地物に絞り込む
+    Contains,
+    /// 列に空白で区切って記録した値のいずれかと一致する地物に絞り込む
+    Word,
+    /// 列の値が、カンマで区切ったコードのいずれかと一致する地物に絞り込む
+    ///
+    /// コードによる絞り込みは、タイルにも適用する。
+    Codes,
+}
+
+/// 地物を絞り込むクエリパラメーター。
+#[derive(Debug)]
+pub struct LayerFilter {
+    /// クエリパラメーターの名前
+    pub param: &'static str,
+    /// 絞り込む列の名前
+    pub column: &'static str,
+    /// 絞り込みの種類
+    pub kind: FilterKind,
+}
+
+impl LayerFilter {
+    /// 絞り込みの条件を返す。
+    ///
+    /// クエリパラメーターが指定されていない場合は、すべての地物が条件に一致する。
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - 絞り込む値を表すSQLのパラメーターの番号。
+    ///
+    /// # Returns
+    ///
+    /// 絞り込みの条件。
+    pub fn condition(&self, index: usize) -> String {
+        match self.kind {
+            FilterKind::Equals | FilterKind::PrefectureCode => {
+                format!("(${}::text IS NULL OR {} = ${})", index, self.column, index)
+            }
+            FilterKind::Contains => format!(
+                "(${}::text IS NULL OR strpos({}, ${}) > 0)",
+                index, self.column, index
+            ),
+            FilterKind::Word => format!(
+                "(${}::text IS NULL OR ${} = ANY(string_to_array({}, ' ')))",
+                index, index, self.column
+            ),
+            FilterKind::Codes => format!(
+                "(${}::text[] IS NULL OR {} = ANY(${}))",
+                index, self.column, index
+            ),
+        }
+    }
+}
+
+/// 属性で絞り込んで配信するレイヤーのテーブルの定義。
+///
+/// フィーチャーコレクション、GeoJSONのタイル、ベクタータイル及びTileJSONの範囲は、この定義から
+/// 問い合わせるSQLを構築する。
+#[derive(Debug)]
+pub struct LayerTable {
+    /// 地物を格納したテーブルの名前
+    pub table: &'static str,
+    /// 地物のプロパティの名前と、プロパティの値を選択する列の式
+    ///
+    /// プロパティの名前は、`fields`が返すベクタータイルの属性の名前と同じにする。
+    pub properties: &'static PropertyColumns,
+    /// 地物を絞り込むクエリパラメーター
+    pub filters: &'static [LayerFilter],
+    /// GeoJSONのタイルで、ジオメトリをタイルの範囲で切り取る場合はtrue
+    pub clip: bool,
+}
+
+impl LayerTable {
+    /// 地物を絞り込むクエリパラメーターを返す。
+    ///
+    /// # Arguments
+    ///
+    /// * `tile` - タイルに適用するクエリパラメーター(コードによる絞り込み)に限る場合はtrue。
+    ///
+    /// # Returns
+    ///
+    /// クエリパラメーターのイテレーター。
+    pub fn filters(&self, tile: bool) -> impl Iterator<Item = &'static LayerFilter> {
+        self.filters
+            .iter()
+            .filter(move |filter| !tile || filter.kind == FilterKind::Codes)
+    }
+
+    /// 地物を絞り込む条件を、先頭に`AND`を付けて連結して返す。
+    ///
+    /// # Arguments
+    ///
+    /// * `tile` - タイルに適用する条件に限る場合はtrue。
+    /// * `first` - 最初の絞り込む値を表すSQLのパラメーターの番号。
+    ///
+    /// # Returns
+    ///
+    /// 絞り込みの条件。
+    pub fn filter_condition(&self, tile: bool, first: usize) -> String {
+        self.filters(tile)
+            .enumerate()
+            .map(|(offset, filter)| format!(" AND {}", filter.condition(first + offset)))
+            .collect()
+    }
+}
+
+/// 鉄道路線のテーブルの定義。
+const RAILWAY_TABLE: LayerTable = LayerTable {
+    table: "railways",
+    properties: &[
+        ("id", "id"),
+        ("railwayType", r#"railway_type as "railwayType""#),
+        ("operatorType", r#"operator_type as "operatorType""#),
+        ("lineName", r#"line_name as "lineName""#),
+        ("operator", "operator"),
+    ],
+    filters: &[
+        LayerFilter {
+            param: "operator_type",
+            column: "operator_type",
+            kind: FilterKind::Equals,
+        },
+        LayerFilter {
+            param: "operator",
+            column: "operator",
+            kind: FilterKind::Contains,
+        },
+        LayerFilter {
+            param: "line_name",
+            column: "line_name",
+            kind: FilterKind::Contains,
+        },
+    ],
+    clip: true,
+};
+
+/// 駅のテーブルの定義。
+const STATION_TABLE: LayerTable = LayerTable {
+    table: "stations",
+    properties: &[
+        ("id", "id"),
+        ("railwayType", r#"railway_type as "railwayType""#),
+        ("operatorType", r#"operator_type as "operatorType""#),
+        ("lineName", r#"line_name as "lineName""#),
+        ("operator", "operator"),
+        ("name", "name"),
+    ],
+    filters: &[
+        LayerFilter {
+            param: "operator_type",
+            column: "operator_type",
+            kind: FilterKind::Equals,
+        },
+        LayerFilter {
+            param: "operator",
+            column: "operator",
+            kind: FilterKind::Contains,
+        },
+        LayerFilter {
+            param: "line_name",
+            column: "line_name",
+            kind: FilterKind::Contains,
+        },
+        LayerFilter {
+            param: "name",
+            column: "name",
+            kind: FilterKind::Contains,
+        },
+    ],
+    clip: true,
+};
+
+/// 学校のテーブルの定義。
+const SCHOOL_TABLE: LayerTable = LayerTable {
+    table: "schools",
+    properties: &[
+        ("id", "id"),
+        ("cityCode", r#"city_code as "cityCode""#),
+        ("schoolCode", r#"school_code as "schoolCode""#),
+        ("categoryCode", r#"category_code as "categoryCode""#),
+        ("name", "name"),
+        ("address", "address"),
+        (
+            "administratorCode",
+            r#"administrator_code as "administratorCode""#,
+        ),
+    ],
+    filters: &[
+        LayerFilter {
+            param: "city_code",
+            column: "city_code",
+            kind: FilterKind::Equals,
+        },
+        LayerFilter {
+            param: "name",
+            column: "name",
+            kind: FilterKind::Contains,
+        },
+        LayerFilter {
+            param: "category_code",
+            column: "category_code",
+            kind: FilterKind::Codes,
+        },
+        LayerFilter {
+            param: "administrator_code",
+            column: "administrator_code",
+            kind: FilterKind::Codes,
+        },
+    ],
+    clip: false,
+};
+
+/// 医療機関のテーブルの定義。
+const MEDICAL_INSTITUTION_TABLE: LayerTable = LayerTable {
+    table: "medical_institutions",
+    properties: &[
+        ("id", "id"),
+        ("prefectureCode", r#"prefecture_code as "prefectureCode""#),
+        (
+            "institutionType",
+            r#"institution_type as "institutionType""#,
+        ),
+        ("name", "name"),
+        ("address", "address"),
+        ("departments", "departments"),
+    ],
+    filters: &[
+        LayerFilter {
+            param: "prefecture_code",
+            column: "prefecture_code",
+            kind: FilterKind::PrefectureCode,
+        },
+        LayerFilter {
+            param: "name",
+            column: "name",
+            kind: FilterKind::Contains,
+        },
+        LayerFilter {
+            param: "department",
+            column: "departments",
+            kind: FilterKind::Word,
+        },
+        LayerFilter {
+            param: "institution_type",
+            column: "institution_type",
+            kind: FilterKind::Codes,
+        },
+    ],
+    clip: false,
+};
+
+/// 河川のテーブルの定義。
+const RIVER_TABLE: LayerTable = LayerTable {
+    table: "rivers",
+    properties: &[
+        ("id", "id"),
+        ("prefectureCode", r#"prefecture_code as "prefectureCode""#),
+        (
+            "waterSystemCode",
+            r#"water_system_code as "waterSystemCode""#,
+        ),
+        ("riverCode", r#"river_code as "riverCode""#),
+        ("sectionType", r#"section_type as "sectionType""#),
+        ("name", "name"),
+    ],
+    filters: &[
+        LayerFilter {
+            param: "prefecture_code",
+            column: "prefecture_code",
+            kind: FilterKind::PrefectureCode,
+        },
+        LayerFilter {
+            param: "water_system_code",
+            column: "water_system_code",
+            kind: FilterKind::Equals,
+        },
+        LayerFilter {
+            param: "name",
+            column: "name",
+            kind: FilterKind::Contains,
+        },
+        LayerFilter {
+            param: "section_type",
+            column: "section_type",
+            kind: FilterKind::Codes,
+        },
+    ],
+    clip: true,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_properties_match_vector_tile_fields() {
+        for layer in Layer::all() {
+            if let Some(table) = layer.table() {
+                let properties: Vec<&str> =
+                    table.properties.iter().map(|(name, _)| *name).collect();
+                let fields: Vec<&str> = layer.fields().iter().map(|(name, _)| *name).collect();
+                assert_eq!(properties, fields, "{}", layer.name());
+            }
+        }
+    }
+
+    #[test]
+    fn from_name_accepts_every_layer_name() {
+        for layer in Layer::all() {
+            assert_eq!(Layer::from_name(layer.name()), Some(layer));
+        }
+        assert_eq!(Layer::from_name("unknown"), None);
+    }
+
+    #[test]
+    fn table_pattern_lists_table_layers() {
+        assert_eq!(
+            Layer::table_pattern(),
+            "railways|stations|schools|medical_institutions|rivers"
+        );
+    }
+
+    #[test]
+    fn filter_condition_numbers_parameters() {
+        let table = Layer::MedicalInstitutions.table().unwrap();
+        assert_eq!(
+            table.filter_condition(false, 7),
+            concat!(
+                " AND ($7::text IS NULL OR prefecture_code = $7)",
+                " AND ($8::text IS NULL OR strpos(name, $8) > 0)",
+                " AND ($9::text IS NULL OR $9 = ANY(string_to_array(departments, ' ')))",
+                " AND ($10::text[] IS NULL OR institution_type = ANY($10))",
+            )
+        );
+        // タイルにはコードによる絞り込みに限り適用する
+        assert_eq!(
+            table.filter_condition(true, 4),
+            " AND ($4::text[] IS NULL OR institution_type = ANY($4))"
+        );
+        assert_eq!(
+            Layer::Railways.table().unwrap().filter_condition(true, 4),
+            ""
+        );
+    }
+}
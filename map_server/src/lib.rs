@@ -1,2 +1,36 @@
+pub mod artifacts;
+pub mod auth;
+pub mod catalog;
+pub mod cdn_purge;
+pub mod compression;
+pub mod config;
+pub mod custom_layers;
+pub mod errors;
+pub mod events;
+pub mod export;
+pub mod flatgeobuf;
+pub mod geocoding;
+pub mod guardrails;
 pub mod handlers;
+pub mod http_cache;
+pub mod layers;
+pub mod negotiation;
+pub mod ogc_api;
+pub mod pmtiles;
+pub mod ranges;
+pub mod rate_limit;
+pub mod request_id;
+pub mod search;
+pub mod shutdown;
+pub mod startup;
+pub mod stats;
+pub mod streaming;
+pub mod styles;
 pub mod telemetries;
+pub mod tile_cache;
+pub mod tls;
+pub mod topojson;
+pub mod uploads;
+pub mod viewer;
+pub mod waypoints;
+pub mod wfs;
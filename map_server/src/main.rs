@@ -1,41 +1,104 @@
-use actix_cors::Cors;
-use actix_web::http::header;
-use actix_web::{web, App, HttpServer};
-use database::connect_to_database;
+use std::net::TcpListener;
+
+use database::preflight::preflight;
 use dotenvy::dotenv;
 
-use map_server::handlers;
-use map_server::telemetries::{get_subscriber, init_subscriber};
+use map_server::config::Settings;
+use map_server::custom_layers::CustomLayers;
+use map_server::shutdown::shutdown_signal;
+use map_server::startup::run;
+use map_server::telemetries::{
+    env_filter, file_writer, get_subscriber, init_subscriber, shutdown_tracer_provider,
+};
+use tracing_subscriber::fmt::writer::{BoxMakeWriter, MakeWriterExt};
 
 #[tokio::main]
 async fn main() -> std::io::Result<()> {
     dotenv().ok();
 
-    let subscriber = get_subscriber("sample_map_server".into(), "info".into(), std::io::stdout);
+    let settings = Settings::load();
+
+    // ログファイルに出力する場合、ガードを破棄するまでログを書き込むスレッドが動作する
+    let (sink, _log_guard) = match &settings.logging.directory {
+        Some(directory) => {
+            let (writer, guard) = file_writer(
+                directory,
+                &settings.logging.file_prefix,
+                settings.logging.rotation,
+                settings.logging.max_files,
+            )
+            .unwrap_or_else(|e| panic!("{}", e));
+            (BoxMakeWriter::new(std::io::stdout.and(writer)), Some(guard))
+        }
+        None => (BoxMakeWriter::new(std::io::stdout), None),
+    };
+    let subscriber = get_subscriber(
+        "sample_map_server".into(),
+        env_filter(&settings.server.log_level).unwrap_or_else(|e| panic!("{}", e)),
+        settings.logging.format,
+        sink,
+        settings.telemetry.otlp_endpoint.as_deref(),
+    );
     init_subscriber(subscriber);
 
-    tracing::info!("データベースと接続");
-    let pool = web::Data::new(connect_to_database().await);
+    let pools = if settings.pmtiles.require_database {
+        tracing::info!("データベースと接続");
+        settings.database.pool_settings().connect_pools().await
+    } else {
+        // PMTilesのアーカイブから配信するレイヤーは、PostGISを利用できなくてもタイルを返せる
+        tracing::info!("データベースに接続せずに起動");
+        settings.database.pool_settings().connect_pools_lazy()
+    }
+    .unwrap_or_else(|e| panic!("{}", e));
+    if settings.health.preflight && settings.pmtiles.require_database {
+        tracing::info!("データベースの拡張を確認");
+        let report = preflight(&pools.write)
+            .await
+            .unwrap_or_else(|e| panic!("データベースの拡張を確認できません。{}", e));
+        for check in report.missing_optional() {
+            tracing::warn!(
+                "任意の拡張{}を利用できないため、{}を利用できません。{}",
+                check.name,
+                check.purpose,
+                check.guidance().unwrap_or_default()
+            );
+        }
+        if !report.is_ok() {
+            let names = report
+                .missing_required()
+                .iter()
+                .map(|check| check.name)
+                .collect::<Vec<_>>()
+                .join("、");
+            panic!(
+                "必須の拡張({})を利用できません。データベースで次のSQL文を実行してください。{}",
+                names,
+                report.required_guidance()
+            );
+        }
+    }
+    tracing::info!("カスタムレイヤーを登録");
+    let custom_layers = CustomLayers::register(&pools.read, &settings.layers)
+        .await
+        .unwrap_or_else(|e| panic!("カスタムレイヤーを登録できません。{}", e));
+    let listener = TcpListener::bind((settings.server.host.as_str(), settings.server.port))?;
 
     tracing::info!("Webサーバーを起動");
-    HttpServer::new(move || {
-        App::new()
-            .wrap(
-                Cors::default()
-                    .allow_any_origin()
-                    .allowed_methods(["GET"])
-                    .allowed_header(header::CONTENT_TYPE),
-            )
-            .route("/health_check", web::get().to(handlers::health_check))
-            .route("/prefectures", web::get().to(handlers::prefectures))
-            .route("/cities", web::get().to(handlers::cities))
-            .route(
-                "/post_offices/{zoom}/{x}/{z}",
-                web::get().to(handlers::post_offices),
-            )
-            .app_data(pool.clone())
-    })
-    .bind(("127.0.0.1", 8080))?
-    .run()
-    .await
+    let server = run(listener, pools.clone(), &settings, custom_layers)?;
+
+    // シグナルを受信したら、処理中のリクエストが完了するまで待機してからWebサーバーを停止
+    let handle = server.handle();
+    tokio::spawn(async move {
+        let signal = shutdown_signal().await;
+        tracing::info!("{}を受信したため、Webサーバーを停止", signal);
+        handle.stop(true).await;
+    });
+    server.await?;
+
+    tracing::info!("データベースとの接続を切断");
+    pools.close().await;
+    tracing::info!("Webサーバーを停止");
+    shutdown_tracer_provider().await;
+
+    Ok(())
 }
@@ -0,0 +1,330 @@
+//! フィーチャーを返すエンドポイントで、レスポンスの形式を決める。
+//!
+//! レスポンスの形式は、クエリパラメーター`format`で指定された場合はその形式、指定されていない場合は
+//! `Accept`ヘッダーに指定されたメディアタイプの品質値(`q`)が高い順に、エンドポイントが返すことのできる
+//! 最初の形式にする。
+
+use actix_web::dev::RequestHead;
+use actix_web::http::header::{self, ContentType};
+
+use crate::errors::ApiError;
+
+/// GeoJSONのMIMEタイプ。
+pub const GEOJSON_CONTENT_TYPE: &str = "application/geo+json";
+
+/// Mapbox Vector TileのMIMEタイプ。
+pub const MVT_CONTENT_TYPE: &str = "application/vnd.mapbox-vector-tile";
+
+/// CSVのMIMEタイプ。
+pub const CSV_CONTENT_TYPE: &str = "text/csv; charset=utf-8";
+
+/// TopoJSONのMIMEタイプ。
+pub const TOPOJSON_CONTENT_TYPE: &str = "application/topo+json";
+
+/// KMLのMIMEタイプ。
+pub const KML_CONTENT_TYPE: &str = "application/vnd.google-earth.kml+xml";
+
+/// GPXのMIMEタイプ。
+pub const GPX_CONTENT_TYPE: &str = "application/gpx+xml";
+
+/// レスポンスの形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaFormat {
+    /// GeoJSON
+    GeoJson,
+    /// Mapbox Vector Tile
+    Mvt,
+    /// CSV
+    Csv,
+    /// TopoJSON
+    TopoJson,
+    /// KML
+    Kml,
+    /// GPX
+    Gpx,
+}
+
+/// フィーチャーコレクションを返すエンドポイントが返すことのできる形式。
+pub const COLLECTION_FORMATS: [MediaFormat; 3] = [
+    MediaFormat::GeoJson,
+    MediaFormat::Csv,
+    MediaFormat::TopoJson,
+];
+
+/// タイルを返すエンドポイントが返すことのできる形式。
+pub const TILE_FORMATS: [MediaFormat; 2] = [MediaFormat::GeoJson, MediaFormat::Mvt];
+
+/// すべての形式。
+const FORMATS: [MediaFormat; 6] = [
+    MediaFormat::GeoJson,
+    MediaFormat::Mvt,
+    MediaFormat::Csv,
+    MediaFormat::TopoJson,
+    MediaFormat::Kml,
+    MediaFormat::Gpx,
+];
+
+impl MediaFormat {
+    /// クエリパラメーター`format`で指定する形式の名前を返す。
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::GeoJson => "geojson",
+            Self::Mvt => "mvt",
+            Self::Csv => "csv",
+            Self::TopoJson => "topojson",
+            Self::Kml => "kml",
+            Self::Gpx => "gpx",
+        }
+    }
+
+    /// `Content-Type`ヘッダーに指定するMIMEタイプを返す。
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::GeoJson => GEOJSON_CONTENT_TYPE,
+            Self::Mvt => MVT_CONTENT_TYPE,
+            Self::Csv => CSV_CONTENT_TYPE,
+            Self::TopoJson => TOPOJSON_CONTENT_TYPE,
+            Self::Kml => KML_CONTENT_TYPE,
+            Self::Gpx => GPX_CONTENT_TYPE,
+        }
+    }
+
+    /// `Content-Type`ヘッダーを返す。
+    pub fn header(&self) -> ContentType {
+        ContentType(self.content_type().parse().unwrap())
+    }
+
+    /// 形式の名前から形式を返す。
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - 形式の名前。
+    ///
+    /// # Returns
+    ///
+    /// 形式。名前に一致する形式がない場合はNone。
+    pub fn from_name(name: &str) -> Option<Self> {
+        FORMATS.into_iter().find(|format| format.name() == name)
+    }
+
+    /// メディアタイプから形式を返す。
+    ///
+    /// `application/json`はGeoJSON、`application/x-protobuf`はMapbox Vector Tileとみなす。
+    ///
+    /// # Arguments
+    ///
+    /// * `media_type` - パラメーターを除いたメディアタイプ。
+    ///
+    /// # Returns
+    ///
+    /// 形式。メディアタイプに一致する形式がない場合はNone。
+    pub fn from_media_type(media_type: &str) -> Option<Self> {
+        match media_type.to_ascii_lowercase().as_str() {
+            "application/json" => Some(Self::GeoJson),
+            "application/x-protobuf" => Some(Self::Mvt),
+            media_type => FORMATS
+                .into_iter()
+                .find(|format| essence(format.content_type()) == media_type),
+        }
+    }
+}
+
+/// パラメーターを除いたメディアタイプを返す。
+fn essence(media_type: &str) -> &str {
+    media_type.split(';').next().unwrap_or_default().trim()
+}
+
+/// `Accept`ヘッダーに指定されたメディアタイプを、品質値が高い順に並べて返す。
+///
+/// 品質値が同じメディアタイプは、指定された順に並べる。品質値が0のメディアタイプは除く。
+///
+/// # Arguments
+///
+/// * `accept` - `Accept`ヘッダーの値。
+///
+/// # Returns
+///
+/// パラメーターを除いたメディアタイプを格納したベクタ。
+fn accepted_media_types(accept: &str) -> Vec<&str> {
+    let mut media_types: Vec<(&str, f32)> = accept
+        .split(',')
+        .filter_map(|range| {
+            let mut parts = range.split(';');
+            let media_type = parts.next()?.trim();
+            if media_type.is_empty() {
+                return None;
+            }
+            let quality = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            (0.0 < quality).then_some((media_type, quality))
+        })
+        .collect();
+    // ソートは安定しているため、品質値が同じメディアタイプは指定された順に並ぶ
+    media_types.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    media_types
+        .into_iter()
+        .map(|(media_type, _)| media_type)
+        .collect()
+}
+
+/// リクエストに応じて、レスポンスの形式を決める。
+///
+/// クエリパラメーター`format`が指定されている場合は、その形式を返す。指定されていない場合は、`Accept`ヘッダーに
+/// 指定されたメディアタイプのうち、品質値が最も高く返すことのできる形式を返す。`*/*`は返すことのできる最初の
+/// 形式、`application/*`などは主タイプが一致する最初の形式とみなす。`Accept`ヘッダーがない場合は、返すことの
+/// できる最初の形式を返す。
+///
+/// # Arguments
+///
+/// * `head` - リクエストのヘッダー。
+/// * `supported` - エンドポイントが返すことのできる形式。先頭を既定の形式とする。
+///
+/// # Returns
+///
+/// レスポンスの形式。`format`に返すことのできない形式が指定された場合はクエリパラメーターが不正であることを示す
+/// エラー、`Accept`ヘッダーに返すことのできる形式がない場合は406 Not Acceptableを示すエラー。
+pub fn negotiate(head: &RequestHead, supported: &[MediaFormat]) -> Result<MediaFormat, ApiError> {
+    let format = head
+        .uri
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("format="));
+    if let Some(name) = format {
+        return MediaFormat::from_name(name)
+            .filter(|format| supported.contains(format))
+            .ok_or(ApiError::InvalidQuery);
+    }
+    let accept = match head
+        .headers
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+    {
+        Some(accept) if !accept.trim().is_empty() => accept,
+        _ => return Ok(supported[0]),
+    };
+    for media_type in accepted_media_types(accept) {
+        let matched = if media_type == "*/*" {
+            Some(supported[0])
+        } else if let Some(main_type) = media_type.strip_suffix("/*") {
+            supported.iter().copied().find(|format| {
+                format
+                    .content_type()
+                    .split('/')
+                    .next()
+                    .is_some_and(|t| t.eq_ignore_ascii_case(main_type))
+            })
+        } else {
+            MediaFormat::from_media_type(media_type).filter(|format| supported.contains(format))
+        };
+        if let Some(format) = matched {
+            return Ok(format);
+        }
+    }
+
+    Err(ApiError::NotAcceptable)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+
+    use super::*;
+
+    /// リクエストのURIとAcceptヘッダーから、レスポンスの形式を決める。
+    fn negotiate_request(
+        uri: &str,
+        accept: Option<&str>,
+        supported: &[MediaFormat],
+    ) -> Result<MediaFormat, ApiError> {
+        let mut req = TestRequest::default().uri(uri);
+        if let Some(accept) = accept {
+            req = req.insert_header((header::ACCEPT, accept));
+        }
+        negotiate(req.to_http_request().head(), supported)
+    }
+
+    #[test]
+    fn accepted_media_types_are_sorted_by_quality() {
+        assert_eq!(
+            accepted_media_types("text/csv;q=0.5, application/geo+json, */*;q=0.1"),
+            ["application/geo+json", "text/csv", "*/*"]
+        );
+        // 品質値が同じメディアタイプは指定された順に並べ、品質値が0のメディアタイプは除く
+        assert_eq!(
+            accepted_media_types("text/csv, application/topo+json, application/json;q=0"),
+            ["text/csv", "application/topo+json"]
+        );
+        assert!(accepted_media_types(" , ").is_empty());
+    }
+
+    #[test]
+    fn media_types_are_mapped_to_formats() {
+        assert_eq!(
+            MediaFormat::from_media_type("application/json"),
+            Some(MediaFormat::GeoJson)
+        );
+        assert_eq!(
+            MediaFormat::from_media_type("application/x-protobuf"),
+            Some(MediaFormat::Mvt)
+        );
+        assert_eq!(
+            MediaFormat::from_media_type("TEXT/CSV"),
+            Some(MediaFormat::Csv)
+        );
+        assert_eq!(MediaFormat::from_media_type("text/html"), None);
+        assert_eq!(
+            MediaFormat::from_name("topojson"),
+            Some(MediaFormat::TopoJson)
+        );
+        assert_eq!(MediaFormat::from_name("shapefile"), None);
+    }
+
+    #[test]
+    fn negotiate_prefers_format_query_parameter() {
+        let format = negotiate_request(
+            "/cities?format=csv",
+            Some("application/geo+json"),
+            &COLLECTION_FORMATS,
+        );
+        assert_eq!(format.unwrap(), MediaFormat::Csv);
+        let format = negotiate_request("/cities?format=mvt", None, &COLLECTION_FORMATS);
+        assert!(matches!(format, Err(ApiError::InvalidQuery)));
+    }
+
+    #[test]
+    fn negotiate_selects_format_from_accept_header() {
+        for (accept, expected) in [
+            (None, MediaFormat::GeoJson),
+            (Some(""), MediaFormat::GeoJson),
+            (Some("*/*"), MediaFormat::GeoJson),
+            (Some("text/*"), MediaFormat::Csv),
+            (
+                Some("text/html, application/topo+json;q=0.9"),
+                MediaFormat::TopoJson,
+            ),
+            (
+                Some("application/geo+json;q=0.5, text/csv"),
+                MediaFormat::Csv,
+            ),
+        ] {
+            let format = negotiate_request("/cities", accept, &COLLECTION_FORMATS);
+            assert_eq!(format.unwrap(), expected, "{:?}", accept);
+        }
+        let format = negotiate_request(
+            "/tiles/cities/10/909/403",
+            Some("application/x-protobuf"),
+            &TILE_FORMATS,
+        );
+        assert_eq!(format.unwrap(), MediaFormat::Mvt);
+    }
+
+    #[test]
+    fn negotiate_rejects_unacceptable_media_types() {
+        let format = negotiate_request("/cities", Some("text/html, image/*"), &COLLECTION_FORMATS);
+        assert!(matches!(format, Err(ApiError::NotAcceptable)));
+    }
+}
@@ -0,0 +1,466 @@
+//! OGC API - Features(Part 1: Core)のエンドポイント。
+//!
+//! <https://docs.ogc.org/is/17-069r4/17-069r4.html>
+
+use actix_web::http::header::ContentType;
+use actix_web::{web, web::Bytes, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sqlx::{types::Uuid, PgPool};
+use tracing::Instrument;
+
+use utils::{EPSG_WEB_MERCATOR, EPSG_WGS84};
+
+use crate::errors::ApiError;
+use crate::guardrails::Bounds;
+use crate::handlers::{PropertiesQuery, PropertyColumns};
+use crate::negotiation::GEOJSON_CONTENT_TYPE;
+use crate::telemetries::query_span;
+use crate::viewer::{prefers_html, viewer_response};
+
+/// フィーチャーを取得するときに、件数を指定されなかった場合の件数。
+const DEFAULT_LIMIT: u32 = 10;
+/// 1回のリクエストで取得できるフィーチャーの最大件数。
+const MAX_LIMIT: u32 = 1000;
+/// 準拠する適合クラス。
+const CONFORMANCE_CLASSES: [&str; 2] = [
+    "http://www.opengis.net/spec/ogcapi-features-1/1.0/conf/core",
+    "http://www.opengis.net/spec/ogcapi-features-1/1.0/conf/geojson",
+];
+/// 座標参照系(WGS84経度緯度)のURI。
+pub const CRS84: &str = "http://www.opengis.net/def/crs/OGC/1.3/CRS84";
+
+/// フィーチャーコレクションとして公開するテーブル。
+pub struct Collection {
+    /// コレクションの識別子(テーブル名)
+    pub id: &'static str,
+    /// コレクションのタイトル
+    pub title: &'static str,
+    /// コレクションの説明
+    pub description: &'static str,
+    /// フィーチャーのジオメトリの種類(GeoJSONのジオメトリの種類の名前)
+    pub geometry_type: &'static str,
+    /// フィーチャーのプロパティの名前と、プロパティの値を選択する列の式
+    pub properties: &'static PropertyColumns,
+}
+
+impl Collection {
+    /// フィーチャーのプロパティとして選択するすべての列の式を返す。
+    pub fn columns(&self) -> Vec<&'static str> {
+        self.properties.iter().map(|(_, column)| *column).collect()
+    }
+}
+
+/// 公開するフィーチャーコレクション。
+pub const COLLECTIONS: [Collection; 3] = [
+    Collection {
+        id: "prefectures",
+        title: "都道府県",
+        description: "「国土数値情報（行政区域データ）」（国土交通省）を加工して作成",
+        geometry_type: "MultiPolygon",
+        properties: &[("code", "code"), ("name", "name")],
+    },
+    Collection {
+        id: "cities",
+        title: "市区町村",
+        description: "「国土数値情報（行政区域データ）」（国土交通省）を加工して作成",
+        geometry_type: "Polygon",
+        properties: &[("code", "code"), ("area", "area"), ("name", "name")],
+    },
+    Collection {
+        id: "post_offices",
+        title: "郵便局",
+        description: "「国土数値情報（郵便局データ）」（国土交通省）を加工して作成",
+        geometry_type: "Point",
+        properties: &[
+            ("cityCode", r#"city_code as "cityCode""#),
+            ("categoryCode", r#"category_code as "categoryCode""#),
+            (
+                "subcategoryCode",
+                r#"subcategory_code as "subcategoryCode""#,
+            ),
+            ("postOfficeCode", r#"post_office_code as "postOfficeCode""#),
+            ("name", "name"),
+            ("address", "address"),
+            ("cityName", r#"city_name as "cityName""#),
+            ("town", "town"),
+            ("block", "block"),
+        ],
+    },
+];
+
+/// フィーチャーコレクションを取得する。
+///
+/// # Arguments
+///
+/// * `id` - コレクションの識別子。
+///
+/// # Returns
+///
+/// フィーチャーコレクション。存在しない場合は404 Not Foundを示すエラー。
+pub fn find_collection(id: &str) -> Result<&'static Collection, ApiError> {
+    COLLECTIONS
+        .iter()
+        .find(|collection| collection.id == id)
+        .ok_or(ApiError::UnknownCollection)
+}
+
+/// リクエストされたサーバーのURLを返す。
+fn base_url(req: &HttpRequest) -> String {
+    let info = req.connection_info();
+
+    format!("{}://{}", info.scheme(), info.host())
+}
+
+/// フィーチャーコレクションの範囲を、WGS84経緯度で取得する。
+///
+/// # Arguments
+///
+/// * `collection` - フィーチャーコレクション。
+/// * `pool` - データベースコネクションプール。
+///
+/// # Returns
+///
+/// 西端、南端、東端、北端を格納した配列。フィーチャーが存在しない場合はNone。
+pub async fn collection_extent(
+    collection: &Collection,
+    pool: &PgPool,
+) -> sqlx::Result<Option<[f64; 4]>> {
+    let sql = format!(
+        r#"
+        SELECT
+            ST_XMin(e.geom) as west, ST_YMin(e.geom) as south,
+            ST_XMax(e.geom) as east, ST_YMax(e.geom) as north
+        FROM (
+            SELECT ST_Transform(ST_SetSRID(ST_Extent(geom)::geometry, $1), $2) as geom
+            FROM {}
+        ) e
+        "#,
+        collection.id
+    );
+    let extent: (Option<f64>, Option<f64>, Option<f64>, Option<f64>) = sqlx::query_as(&sql)
+        .bind(EPSG_WEB_MERCATOR)
+        .bind(EPSG_WGS84)
+        .fetch_one(pool)
+        .instrument(query_span("SELECT collection extent"))
+        .await?;
+
+    Ok(match extent {
+        (Some(west), Some(south), Some(east), Some(north)) => Some([west, south, east, north]),
+        _ => None,
+    })
+}
+
+/// フィーチャーコレクションの情報を生成する。
+///
+/// # Arguments
+///
+/// * `req` - HTTPリクエスト。
+/// * `collection` - フィーチャーコレクション。
+/// * `pool` - データベースコネクションプール。
+///
+/// # Returns
+///
+/// フィーチャーコレクションの情報。
+async fn collection_info(
+    req: &HttpRequest,
+    collection: &Collection,
+    pool: &PgPool,
+) -> Result<Value, actix_web::Error> {
+    let base_url = base_url(req);
+    let extent = collection_extent(collection, pool)
+        .await
+        .map_err(ApiError::from)?;
+    let mut info = json!({
+        "id": collection.id,
+        "title": collection.title,
+        "description": collection.description,
+        "itemType": "feature",
+        "crs": [CRS84],
+        "links": [
+            {
+                "href": format!("{}/collections/{}", base_url, collection.id),
+                "rel": "self",
+                "type": "application/json",
+                "title": "This document",
+            },
+            {
+                "href": format!("{}/collections/{}/items", base_url, collection.id),
+                "rel": "items",
+                "type": GEOJSON_CONTENT_TYPE,
+                "title": "Items",
+            },
+        ],
+    });
+    if let Some(bbox) = extent {
+        info["extent"] = json!({ "spatial": { "bbox": [bbox], "crs": CRS84 } });
+    }
+
+    Ok(info)
+}
+
+#[tracing::instrument(name = "OGC API landing page", skip(req))]
+pub async fn landing_page(req: HttpRequest) -> HttpResponse {
+    // ブラウザで開いた場合は、地図ビューアーを返す
+    if prefers_html(&req) {
+        return viewer_response();
+    }
+    let base_url = base_url(&req);
+
+    HttpResponse::Ok().json(json!({
+        "title": "Sample Map Server",
+        "description": "国土数値情報の行政区域と郵便局を配信するOGC API - Features",
+        "links": [
+            {
+                "href": format!("{}/", base_url),
+                "rel": "self",
+                "type": "application/json",
+                "title": "This document",
+            },
+            {
+                "href": format!("{}/?f=html", base_url),
+                "rel": "alternate",
+                "type": "text/html",
+                "title": "Map viewer",
+            },
+            {
+                "href": format!("{}/conformance", base_url),
+                "rel": "conformance",
+                "type": "application/json",
+                "title": "Conformance classes",
+            },
+            {
+                "href": format!("{}/collections", base_url),
+                "rel": "data",
+                "type": "application/json",
+                "title": "Feature collections",
+            },
+        ],
+    }))
+}
+
+#[tracing::instrument(name = "OGC API conformance")]
+pub async fn conformance() -> HttpResponse {
+    HttpResponse::Ok().json(json!({ "conformsTo": CONFORMANCE_CLASSES }))
+}
+
+#[tracing::instrument(name = "OGC API collections", skip(req, pool))]
+pub async fn collections(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut collections = Vec::new();
+    for collection in COLLECTIONS.iter() {
+        collections.push(collection_info(&req, collection, pool.as_ref()).await?);
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
+        "links": [{
+            "href": format!("{}/collections", base_url(&req)),
+            "rel": "self",
+            "type": "application/json",
+            "title": "This document",
+        }],
+        "collections": collections,
+    })))
+}
+
+#[tracing::instrument(name = "OGC API collection", skip(req, pool))]
+pub async fn collection(
+    req: HttpRequest,
+    path: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let collection = find_collection(&path.into_inner().0)?;
+
+    Ok(HttpResponse::Ok().json(collection_info(&req, collection, pool.as_ref()).await?))
+}
+
+/// フィーチャーを取得するときのクエリパラメーター。
+#[derive(Debug, Deserialize)]
+pub struct ItemsQuery {
+    /// フィーチャーを取得する範囲(`west,south,east,north`形式のWGS84経緯度)
+    bbox: Option<Bounds>,
+    /// 取得するフィーチャーの件数
+    limit: Option<u32>,
+    /// 取得を開始するフィーチャーの位置
+    offset: Option<u32>,
+}
+
+/// フィーチャーのGeoJSONを生成するSELECT句を返す。
+///
+/// # Arguments
+///
+/// * `collection` - フィーチャーコレクション。
+/// * `columns` - フィーチャーのプロパティとして選択する列。
+pub fn feature_select(collection: &Collection, columns: &[&str]) -> String {
+    let mut select_list = vec!["id"];
+    select_list.extend_from_slice(columns);
+    select_list.push("geom");
+    format!(
+        r#"
+        SELECT json_build_object(
+            'type', 'Feature',
+            'id', t.id,
+            'geometry', ST_AsGeoJSON(ST_Transform(t.geom, {wgs84}))::json,
+            'properties', to_jsonb(t.*) - 'id' - 'geom'
+        )::text as feature
+        FROM (SELECT {select_list} FROM {table}) t
+        "#,
+        wgs84 = EPSG_WGS84,
+        select_list = select_list.join(", "),
+        table = collection.id,
+    )
+}
+
+#[tracing::instrument(name = "OGC API items", skip(req, pool))]
+pub async fn items(
+    req: HttpRequest,
+    path: web::Path<(String,)>,
+    query: web::Query<ItemsQuery>,
+    properties: web::Query<PropertiesQuery>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let collection = find_collection(&path.into_inner().0)?;
+    let selected = properties.select(collection.properties)?;
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let offset = query.offset.unwrap_or(0);
+    let condition = match query.bbox {
+        Some(_) => format!(
+            "WHERE t.geom && ST_Transform(ST_MakeEnvelope($1, $2, $3, $4, {}), {})",
+            EPSG_WGS84, EPSG_WEB_MERCATOR
+        ),
+        None => String::new(),
+    };
+    let bbox = query
+        .bbox
+        .map(|b| vec![b.west, b.south, b.east, b.north])
+        .unwrap_or_default();
+    let n = bbox.len();
+
+    let count_sql = format!(
+        r#"
+        SELECT COUNT(*), to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24:MI:SS"Z"')
+        FROM {} t {}
+        "#,
+        collection.id, condition
+    );
+    let count_query = bbox
+        .iter()
+        .fold(sqlx::query_as(&count_sql), |query, value| query.bind(value));
+    let (matched, time_stamp): (i64, String) = count_query
+        .fetch_one(pool.as_ref())
+        .instrument(query_span("SELECT collection count"))
+        .await
+        .map_err(ApiError::from)?;
+
+    let columns: Vec<&str> = selected.iter().map(|(_, column)| *column).collect();
+    let items_sql = format!(
+        "{} {} ORDER BY t.id LIMIT ${} OFFSET ${}",
+        feature_select(collection, &columns),
+        condition,
+        n + 1,
+        n + 2
+    );
+    let items_query = bbox
+        .iter()
+        .fold(sqlx::query_as(&items_sql), |query, value| query.bind(value));
+    let features: Vec<(String,)> = items_query
+        .bind(limit as i64)
+        .bind(offset as i64)
+        .fetch_all(pool.as_ref())
+        .instrument(query_span("SELECT collection items"))
+        .await
+        .map_err(ApiError::from)?;
+
+    // ページングのリンクには、件数と位置以外のクエリパラメーターを引き継ぐ
+    let items_url = format!("{}/collections/{}/items", base_url(&req), collection.id);
+    let properties_param = if properties.is_selected() {
+        let names: Vec<&str> = selected.iter().map(|(name, _)| *name).collect();
+        format!("&properties={}", names.join(","))
+    } else {
+        String::new()
+    };
+    let page_url = |offset: u32| match query.bbox {
+        Some(b) => format!(
+            "{}?bbox={},{},{},{}&limit={}&offset={}{}",
+            items_url, b.west, b.south, b.east, b.north, limit, offset, properties_param
+        ),
+        None => format!(
+            "{}?limit={}&offset={}{}",
+            items_url, limit, offset, properties_param
+        ),
+    };
+    let mut links = vec![json!({
+        "href": page_url(offset),
+        "rel": "self",
+        "type": GEOJSON_CONTENT_TYPE,
+        "title": "This document",
+    })];
+    if ((offset + features.len() as u32) as i64) < matched {
+        links.push(json!({
+            "href": page_url(offset + limit),
+            "rel": "next",
+            "type": GEOJSON_CONTENT_TYPE,
+            "title": "Next page",
+        }));
+    }
+    if 0 < offset {
+        links.push(json!({
+            "href": page_url(offset.saturating_sub(limit)),
+            "rel": "prev",
+            "type": GEOJSON_CONTENT_TYPE,
+            "title": "Previous page",
+        }));
+    }
+
+    // フィーチャーはデータベースで生成したGeoJSONをそのまま連結する
+    let returned = features.len();
+    let features = features
+        .iter()
+        .map(|(feature,)| feature.as_str())
+        .collect::<Vec<_>>()
+        .join(",");
+    let body = format!(
+        r#"{{"type":"FeatureCollection","numberMatched":{},"numberReturned":{},"timeStamp":{},"links":{},"features":[{}]}}"#,
+        matched,
+        returned,
+        json!(time_stamp),
+        Value::from(links),
+        features
+    );
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType(GEOJSON_CONTENT_TYPE.parse().unwrap()))
+        .body(Bytes::from(body)))
+}
+
+#[tracing::instrument(name = "OGC API item", skip(pool))]
+pub async fn item(
+    path: web::Path<(String, String)>,
+    properties: web::Query<PropertiesQuery>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (collection_id, feature_id) = path.into_inner();
+    let collection = find_collection(&collection_id)?;
+    let columns: Vec<&str> = properties
+        .select(collection.properties)?
+        .iter()
+        .map(|(_, column)| *column)
+        .collect();
+    let feature_id = Uuid::parse_str(&feature_id).map_err(|_| ApiError::UnknownFeature)?;
+    let sql = format!("{} WHERE t.id = $1", feature_select(collection, &columns));
+    let feature: Option<(String,)> = sqlx::query_as(&sql)
+        .bind(feature_id)
+        .fetch_optional(pool.as_ref())
+        .instrument(query_span("SELECT collection item"))
+        .await
+        .map_err(ApiError::from)?;
+
+    match feature {
+        Some((feature,)) => Ok(HttpResponse::Ok()
+            .content_type(ContentType(GEOJSON_CONTENT_TYPE.parse().unwrap()))
+            .body(feature)),
+        None => Err(ApiError::UnknownFeature.into()),
+    }
+}
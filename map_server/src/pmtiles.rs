@@ -0,0 +1,604 @@
+//! PMTiles(バージョン3)のアーカイブからタイルを読み込む。
+//!
+//! アーカイブはローカルのファイルとし、ヘッダーとルートディレクトリを開いたときに読み込んで、タイルと
+//! リーフディレクトリはタイルを要求されたときに範囲を指定して読み込む。
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use lru::LruCache;
+use serde::Deserialize;
+
+/// アーカイブの先頭に記録されているシグネチャ。
+const MAGIC: &[u8] = b"PMTiles";
+
+/// 読み込めるアーカイブのバージョン。
+const VERSION: u8 = 3;
+
+/// ヘッダーの大きさ(バイト)。
+const HEADER_LENGTH: usize = 127;
+
+/// ルートディレクトリからたどるリーフディレクトリの最大の深さ。
+const MAX_DEPTH: usize = 3;
+
+/// タイルのIDを計算できる最大のズームレベル。
+const MAX_ZOOM: u8 = 31;
+
+/// キャッシュするリーフディレクトリの最大数。
+const LEAF_CACHE_CAPACITY: usize = 64;
+
+/// 圧縮しない。
+const COMPRESSION_NONE: u8 = 1;
+/// gzipで圧縮する。
+const COMPRESSION_GZIP: u8 = 2;
+/// brotliで圧縮する。
+const COMPRESSION_BROTLI: u8 = 3;
+
+/// タイルの種類(Mapbox Vector Tile)。
+const TILE_TYPE_MVT: u8 = 1;
+
+/// PMTilesのアーカイブから配信するレイヤーの設定。
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PmtilesSettings {
+    /// 起動するときに、データベースに接続できることを要求するか
+    ///
+    /// falseの場合はデータベースに接続せずに起動するため、PostGISを利用できなくてもアーカイブから
+    /// 配信するレイヤーのタイルを返せる。
+    pub require_database: bool,
+    /// レイヤー名と、そのレイヤーのタイルを格納したアーカイブのパス
+    pub layers: HashMap<String, PathBuf>,
+}
+
+impl Default for PmtilesSettings {
+    fn default() -> Self {
+        Self {
+            require_database: true,
+            layers: HashMap::new(),
+        }
+    }
+}
+
+/// アーカイブのヘッダー。
+#[derive(Debug, Clone, Copy)]
+pub struct PmtilesHeader {
+    /// ルートディレクトリの位置
+    root_offset: u64,
+    /// ルートディレクトリの大きさ
+    root_length: u64,
+    /// リーフディレクトリを格納した領域の位置
+    leaf_offset: u64,
+    /// タイルを格納した領域の位置
+    tile_offset: u64,
+    /// ディレクトリの圧縮形式
+    internal_compression: u8,
+    /// タイルの圧縮形式
+    tile_compression: u8,
+    /// タイルを格納したズームレベルの最小値
+    pub min_zoom: u8,
+    /// タイルを格納したズームレベルの最大値
+    pub max_zoom: u8,
+    /// タイルを格納した範囲(west、south、east、northの順のWGS84経緯度)
+    pub bounds: [f64; 4],
+}
+
+impl PmtilesHeader {
+    /// ヘッダーを解析する。
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - アーカイブの先頭から読み込んだバイト列。
+    ///
+    /// # Returns
+    ///
+    /// ヘッダー。PMTiles(バージョン3)のベクタータイルのアーカイブでない場合はエラー。
+    fn parse(bytes: &[u8; HEADER_LENGTH]) -> std::io::Result<Self> {
+        if &bytes[..MAGIC.len()] != MAGIC {
+            return Err(invalid_data(
+                "PMTilesのアーカイブではありません。".to_string(),
+            ));
+        }
+        if bytes[7] != VERSION {
+            return Err(invalid_data(format!(
+                "PMTilesのバージョン({})に対応していません。",
+                bytes[7]
+            )));
+        }
+        let u64_at = |at: usize| u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap());
+        let degrees_at =
+            |at: usize| i32::from_le_bytes(bytes[at..at + 4].try_into().unwrap()) as f64 / 1e7;
+        let header = Self {
+            root_offset: u64_at(8),
+            root_length: u64_at(16),
+            leaf_offset: u64_at(40),
+            tile_offset: u64_at(56),
+            internal_compression: bytes[97],
+            tile_compression: bytes[98],
+            min_zoom: bytes[100],
+            max_zoom: bytes[101],
+            bounds: [
+                degrees_at(102),
+                degrees_at(106),
+                degrees_at(110),
+                degrees_at(114),
+            ],
+        };
+        for compression in [header.internal_compression, header.tile_compression] {
+            if ![COMPRESSION_NONE, COMPRESSION_GZIP, COMPRESSION_BROTLI].contains(&compression) {
+                return Err(invalid_data(format!(
+                    "圧縮形式({})に対応していません。",
+                    compression
+                )));
+            }
+        }
+        if bytes[99] != TILE_TYPE_MVT {
+            return Err(invalid_data(format!(
+                "タイルの種類({})がベクタータイルではありません。",
+                bytes[99]
+            )));
+        }
+
+        Ok(header)
+    }
+}
+
+/// ディレクトリのエントリ。
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    /// 最初のタイルのID
+    tile_id: u64,
+    /// タイルまたはリーフディレクトリの位置
+    offset: u64,
+    /// タイルまたはリーフディレクトリの大きさ
+    length: u64,
+    /// 同じ内容のタイルが連続する数(0の場合はリーフディレクトリ)
+    run_length: u64,
+}
+
+/// PMTilesのアーカイブ。
+#[derive(Debug)]
+pub struct PmtilesArchive {
+    /// アーカイブのパス
+    path: PathBuf,
+    /// アーカイブのファイル
+    file: Mutex<File>,
+    /// ヘッダー
+    header: PmtilesHeader,
+    /// ルートディレクトリのエントリ
+    root: Arc<Vec<Entry>>,
+    /// 読み込んだリーフディレクトリのエントリ(リーフディレクトリの位置をキーとする)
+    leaves: Mutex<LruCache<u64, Arc<Vec<Entry>>>>,
+}
+
+impl PmtilesArchive {
+    /// アーカイブを開いて、ヘッダーとルートディレクトリを読み込む。
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - アーカイブのパス。
+    ///
+    /// # Returns
+    ///
+    /// アーカイブ。
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        let mut file = File::open(path)?;
+        let mut bytes = [0; HEADER_LENGTH];
+        file.read_exact(&mut bytes)?;
+        let header = PmtilesHeader::parse(&bytes)?;
+        let file = Mutex::new(file);
+        let root = read_directory(&file, &header, header.root_offset, header.root_length)?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            header,
+            root: Arc::new(root),
+            leaves: Mutex::new(LruCache::new(LEAF_CACHE_CAPACITY)),
+        })
+    }
+
+    /// アーカイブのパスを返す。
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// アーカイブのヘッダーを返す。
+    pub fn header(&self) -> &PmtilesHeader {
+        &self.header
+    }
+
+    /// タイルを読み込む。
+    ///
+    /// # Arguments
+    ///
+    /// * `zoom` - ズームレベル。
+    /// * `x` - タイルのX座標。
+    /// * `y` - タイルのY座標。
+    ///
+    /// # Returns
+    ///
+    /// 展開したタイルの内容。アーカイブにタイルが格納されていない場合はNone。
+    pub fn tile(&self, zoom: u8, x: u32, y: u32) -> std::io::Result<Option<Vec<u8>>> {
+        if MAX_ZOOM < zoom || zoom < self.header.min_zoom || self.header.max_zoom < zoom {
+            return Ok(None);
+        }
+        let tile_id = tile_id(zoom, x, y);
+        let mut directory = self.root.clone();
+        for _ in 0..=MAX_DEPTH {
+            let entry = match find_entry(&directory, tile_id) {
+                Some(entry) => entry,
+                None => return Ok(None),
+            };
+            if 0 < entry.run_length {
+                let bytes = read_range(
+                    &self.file,
+                    self.header.tile_offset + entry.offset,
+                    entry.length,
+                )?;
+                return decompress(bytes, self.header.tile_compression).map(Some);
+            }
+            directory = self.leaf_directory(&entry)?;
+        }
+        Err(invalid_data(format!(
+            "アーカイブ({})のリーフディレクトリが深すぎます。",
+            self.path.display()
+        )))
+    }
+
+    /// リーフディレクトリを、キャッシュまたはアーカイブから読み込む。
+    ///
+    /// # Arguments
+    ///
+    /// * `entry` - リーフディレクトリを指すエントリ。
+    ///
+    /// # Returns
+    ///
+    /// リーフディレクトリのエントリ。
+    fn leaf_directory(&self, entry: &Entry) -> std::io::Result<Arc<Vec<Entry>>> {
+        let offset = self.header.leaf_offset + entry.offset;
+        if let Some(directory) = self.leaves.lock().unwrap().get(&offset) {
+            return Ok(directory.clone());
+        }
+        let directory = Arc::new(read_directory(
+            &self.file,
+            &self.header,
+            offset,
+            entry.length,
+        )?);
+        self.leaves.lock().unwrap().put(offset, directory.clone());
+
+        Ok(directory)
+    }
+}
+
+/// レイヤー名と、そのレイヤーのタイルを格納したアーカイブ。
+#[derive(Debug, Default)]
+pub struct PmtilesArchives(HashMap<String, Arc<PmtilesArchive>>);
+
+impl PmtilesArchives {
+    /// 設定されたアーカイブをすべて開く。
+    ///
+    /// # Arguments
+    ///
+    /// * `settings` - PMTilesのアーカイブから配信するレイヤーの設定。
+    ///
+    /// # Returns
+    ///
+    /// レイヤー名とアーカイブ。アーカイブを開けない場合はエラー。
+    pub fn open(settings: &PmtilesSettings) -> std::io::Result<Self> {
+        let mut archives = HashMap::new();
+        for (name, path) in &settings.layers {
+            let archive = PmtilesArchive::open(path).map_err(|e| {
+                std::io::Error::new(
+                    e.kind(),
+                    format!(
+                        "レイヤー({})のPMTilesのアーカイブ({})を開けません。{}",
+                        name,
+                        path.display(),
+                        e
+                    ),
+                )
+            })?;
+            tracing::info!(
+                "レイヤー({})のタイルを、PMTilesのアーカイブ({})から配信",
+                name,
+                path.display()
+            );
+            archives.insert(name.clone(), Arc::new(archive));
+        }
+
+        Ok(Self(archives))
+    }
+
+    /// レイヤーのアーカイブを返す。
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - レイヤー名。
+    ///
+    /// # Returns
+    ///
+    /// アーカイブ。レイヤーをアーカイブから配信しない場合はNone。
+    pub fn get(&self, name: &str) -> Option<Arc<PmtilesArchive>> {
+        self.0.get(name).cloned()
+    }
+}
+
+/// タイルのズームレベルと座標を、ヒルベルト曲線に沿って数えたタイルのIDに変換する。
+///
+/// # Arguments
+///
+/// * `zoom` - ズームレベル。
+/// * `x` - タイルのX座標。
+/// * `y` - タイルのY座標。
+///
+/// # Returns
+///
+/// タイルのID。
+fn tile_id(zoom: u8, x: u32, y: u32) -> u64 {
+    // 低いズームレベルのタイルの数
+    let base = ((1u64 << (2 * zoom as u32)) - 1) / 3;
+    let (mut x, mut y) = (x as u64, y as u64);
+    let mut d = 0;
+    let mut s = (1u64 << zoom) / 2;
+    while 0 < s {
+        let rx = u64::from(x & s != 0);
+        let ry = u64::from(y & s != 0);
+        d += s * s * ((3 * rx) ^ ry);
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - (x & (s - 1));
+                y = s - 1 - (y & (s - 1));
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+
+    base + d
+}
+
+/// ディレクトリから、タイルを格納したエントリまたはタイルを含むリーフディレクトリのエントリを探す。
+///
+/// # Arguments
+///
+/// * `entries` - タイルのIDの昇順に並んだエントリ。
+/// * `tile_id` - タイルのID。
+///
+/// # Returns
+///
+/// エントリ。見つからない場合はNone。
+fn find_entry(entries: &[Entry], tile_id: u64) -> Option<Entry> {
+    let index = match entries.binary_search_by_key(&tile_id, |entry| entry.tile_id) {
+        Ok(index) => return Some(entries[index]),
+        Err(0) => return None,
+        Err(index) => index - 1,
+    };
+    let entry = entries[index];
+    if entry.run_length == 0 || tile_id - entry.tile_id < entry.run_length {
+        Some(entry)
+    } else {
+        None
+    }
+}
+
+/// アーカイブの範囲を読み込む。
+///
+/// # Arguments
+///
+/// * `file` - アーカイブのファイル。
+/// * `offset` - 読み込む範囲の位置。
+/// * `length` - 読み込む範囲の大きさ。
+///
+/// # Returns
+///
+/// 読み込んだバイト列。
+fn read_range(file: &Mutex<File>, offset: u64, length: u64) -> std::io::Result<Vec<u8>> {
+    let mut bytes = vec![0; length as usize];
+    let mut file = file.lock().unwrap();
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut bytes)?;
+
+    Ok(bytes)
+}
+
+/// 圧縮されたバイト列を展開する。
+///
+/// # Arguments
+///
+/// * `bytes` - バイト列。
+/// * `compression` - 圧縮形式。
+///
+/// # Returns
+///
+/// 展開したバイト列。
+fn decompress(bytes: Vec<u8>, compression: u8) -> std::io::Result<Vec<u8>> {
+    let mut decompressed = Vec::new();
+    match compression {
+        COMPRESSION_GZIP => {
+            flate2::read::GzDecoder::new(bytes.as_slice()).read_to_end(&mut decompressed)?;
+        }
+        COMPRESSION_BROTLI => {
+            brotli::Decompressor::new(bytes.as_slice(), 4096).read_to_end(&mut decompressed)?;
+        }
+        _ => return Ok(bytes),
+    }
+
+    Ok(decompressed)
+}
+
+/// ディレクトリを読み込む。
+///
+/// # Arguments
+///
+/// * `file` - アーカイブのファイル。
+/// * `header` - アーカイブのヘッダー。
+/// * `offset` - ディレクトリの位置。
+/// * `length` - ディレクトリの大きさ。
+///
+/// # Returns
+///
+/// ディレクトリのエントリ。
+fn read_directory(
+    file: &Mutex<File>,
+    header: &PmtilesHeader,
+    offset: u64,
+    length: u64,
+) -> std::io::Result<Vec<Entry>> {
+    let bytes = decompress(
+        read_range(file, offset, length)?,
+        header.internal_compression,
+    )?;
+    parse_directory(&bytes)
+}
+
+/// ディレクトリを解析する。
+///
+/// ディレクトリは、エントリの数に続いて、タイルのIDの差分、連続する数、大きさ及び位置を、それぞれ
+/// すべてのエントリについて可変長整数で記録している。位置が0の場合は、直前のエントリに続く位置を示す。
+///
+/// # Arguments
+///
+/// * `bytes` - 展開したディレクトリのバイト列。
+///
+/// # Returns
+///
+/// ディレクトリのエントリ。
+fn parse_directory(mut bytes: &[u8]) -> std::io::Result<Vec<Entry>> {
+    let count = read_varint(&mut bytes)? as usize;
+    let mut entries = vec![
+        Entry {
+            tile_id: 0,
+            offset: 0,
+            length: 0,
+            run_length: 0,
+        };
+        count
+    ];
+    let mut tile_id = 0;
+    for entry in entries.iter_mut() {
+        tile_id += read_varint(&mut bytes)?;
+        entry.tile_id = tile_id;
+    }
+    for entry in entries.iter_mut() {
+        entry.run_length = read_varint(&mut bytes)?;
+    }
+    for entry in entries.iter_mut() {
+        entry.length = read_varint(&mut bytes)?;
+    }
+    for index in 0..count {
+        let offset = read_varint(&mut bytes)?;
+        entries[index].offset = match (offset, index) {
+            (0, 1..) => entries[index - 1].offset + entries[index - 1].length,
+            _ => offset.saturating_sub(1),
+        };
+    }
+
+    Ok(entries)
+}
+
+/// 可変長整数(LEB128)を読み込む。
+///
+/// # Arguments
+///
+/// * `bytes` - バイト列。読み込んだバイトを取り除く。
+///
+/// # Returns
+///
+/// 読み込んだ整数。
+fn read_varint(bytes: &mut &[u8]) -> std::io::Result<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let (byte, rest) = bytes
+            .split_first()
+            .ok_or_else(|| invalid_data("ディレクトリが途中で終わっています。".to_string()))?;
+        *bytes = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(invalid_data("ディレクトリの整数が長すぎます。".to_string()))
+}
+
+/// アーカイブの内容が不正であることを示すエラーを返す。
+fn invalid_data(message: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// エントリを、タイルのID、位置、大きさ、連続する数のタプルに変換する。
+    fn fields(entry: &Entry) -> (u64, u64, u64, u64) {
+        (entry.tile_id, entry.offset, entry.length, entry.run_length)
+    }
+
+    #[test]
+    fn tile_id_follows_hilbert_curve() {
+        // PMTiles v3の仕様に記載された、ズームレベル0から2のタイルのID
+        assert_eq!(tile_id(0, 0, 0), 0);
+        assert_eq!(tile_id(1, 0, 0), 1);
+        assert_eq!(tile_id(1, 0, 1), 2);
+        assert_eq!(tile_id(1, 1, 1), 3);
+        assert_eq!(tile_id(1, 1, 0), 4);
+        assert_eq!(tile_id(2, 0, 0), 5);
+        // ズームレベル20の最初のタイルのIDは、低いズームレベルのタイルの数((4^20 - 1) / 3)
+        assert_eq!(tile_id(20, 0, 0), 366503875925);
+        // リファレンス実装(JavaScript)の`zxyToTileId`で求めた値
+        assert_eq!(tile_id(10, 909, 403), 1146697);
+    }
+
+    #[test]
+    fn read_varint_decodes_leb128() {
+        for (bytes, expected) in [
+            (&[0x00][..], 0),
+            (&[0x01][..], 1),
+            (&[0x7f][..], 127),
+            (&[0x80, 0x01][..], 128),
+            (&[0xac, 0x02][..], 300),
+            (&[0xe5, 0x8e, 0x26][..], 624485),
+        ] {
+            let mut rest = bytes;
+            assert_eq!(read_varint(&mut rest).unwrap(), expected);
+            assert!(rest.is_empty());
+        }
+
+        let mut rest = &[0xac, 0x02, 0x05][..];
+        assert_eq!(read_varint(&mut rest).unwrap(), 300);
+        assert_eq!(rest, [0x05]);
+    }
+
+    #[test]
+    fn read_varint_rejects_truncated_or_too_long_integer() {
+        assert!(read_varint(&mut &[][..]).is_err());
+        assert!(read_varint(&mut &[0x80][..]).is_err());
+        assert!(read_varint(&mut &[0x80; 10][..]).is_err());
+    }
+
+    #[test]
+    fn parse_directory_decodes_columns_and_contiguous_offsets() {
+        // エントリの数、タイルのIDの差分、連続する数、大きさ、位置(位置+1、0は直前のエントリに続く位置)
+        let bytes = [3, 0, 1, 4, 1, 2, 0, 10, 20, 30, 1, 0, 101];
+        let entries = parse_directory(&bytes).unwrap();
+        let entries: Vec<_> = entries.iter().map(fields).collect();
+        assert_eq!(entries, [(0, 0, 10, 1), (1, 10, 20, 2), (5, 100, 30, 0)]);
+
+        assert!(parse_directory(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn find_entry_honors_run_length_and_leaf_directories() {
+        let entries = parse_directory(&[3, 0, 1, 4, 1, 2, 0, 10, 20, 30, 1, 0, 101]).unwrap();
+        assert_eq!(find_entry(&entries, 0).map(|e| e.tile_id), Some(0));
+        assert_eq!(find_entry(&entries, 2).map(|e| e.tile_id), Some(1));
+        assert_eq!(find_entry(&entries, 3).map(|e| e.tile_id), None);
+        // 連続する数が0のエントリはリーフディレクトリで、後続のすべてのタイルを含む可能性がある
+        assert_eq!(find_entry(&entries, 1000).map(|e| e.tile_id), Some(5));
+        assert!(find_entry(&entries[1..], 0).is_none());
+    }
+}
@@ -0,0 +1,226 @@
+//! ファイルを、Rangeヘッダーで指定された範囲に限って返す。
+//!
+//! 中断したダウンロードの再開や、PMTilesに対応したクライアントがアーカイブの必要な部分だけを
+//! 読み込めるように、`Accept-Ranges: bytes`を付与して、1つの範囲を指定したリクエストには
+//! 206 Partial Contentで範囲の内容を返す。複数の範囲を指定したリクエストには、ファイル全体を返す。
+
+use std::io::SeekFrom;
+
+use actix_web::http::header::{
+    self, ContentRange, ContentRangeSpec, ContentType, EntityTag, Header, IfRange, Range,
+};
+use actix_web::{web, HttpRequest, HttpResponse};
+use futures_util::stream;
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+use crate::errors::ApiError;
+use crate::handlers::is_not_modified;
+
+/// ファイルを読み込むときのチャンクの大きさ(バイト)。
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// リクエストで指定された、ファイルの範囲を返す。
+///
+/// If-Rangeヘッダーが指定されていて、ファイルのETagと強い比較で一致しない場合は、範囲の指定を無視する。
+///
+/// # Arguments
+///
+/// * `req` - HTTPリクエスト。
+/// * `length` - ファイルの大きさ(バイト)。
+/// * `etag` - ファイルのETag。
+///
+/// # Returns
+///
+/// 範囲の最初と最後の位置(両端を含む)。ファイル全体を返す場合はNone。範囲がファイルに含まれない場合はエラー。
+pub fn requested_range(
+    req: &HttpRequest,
+    length: u64,
+    etag: &EntityTag,
+) -> Result<Option<(u64, u64)>, ApiError> {
+    let specs = match Range::parse(req) {
+        Ok(Range::Bytes(specs)) => specs,
+        _ => return Ok(None),
+    };
+    if req.headers().contains_key(header::IF_RANGE) {
+        match IfRange::parse(req) {
+            Ok(IfRange::EntityTag(tag)) if tag.strong_eq(etag) => {}
+            _ => return Ok(None),
+        }
+    }
+    match specs.as_slice() {
+        [spec] => spec
+            .to_satisfiable_range(length)
+            .map(Some)
+            .ok_or(ApiError::RangeNotSatisfiable(length)),
+        _ => Ok(None),
+    }
+}
+
+/// ファイルを、リクエストで指定された範囲に限って返すレスポンスを構築する。
+///
+/// リクエストのIf-None-MatchヘッダーがETagと一致する場合は、304 Not Modifiedを返す。
+///
+/// # Arguments
+///
+/// * `req` - HTTPリクエスト。
+/// * `file` - ファイル。
+/// * `etag` - ファイルのETag。ファイルの内容が変わらない限り同じ強いETagを指定する。
+/// * `content_type` - ファイルのMIMEタイプ。
+/// * `filename` - ダウンロードするときのファイル名。ダウンロードさせない場合はNone。
+///
+/// # Returns
+///
+/// レスポンス。
+pub async fn ranged_file_response(
+    req: &HttpRequest,
+    mut file: File,
+    etag: EntityTag,
+    content_type: ContentType,
+    filename: Option<&str>,
+) -> Result<HttpResponse, ApiError> {
+    if is_not_modified(req, &etag) {
+        return Ok(HttpResponse::NotModified()
+            .insert_header(header::ETag(etag))
+            .finish());
+    }
+    let length = file.metadata().await.map_err(read_error)?.len();
+    let range = requested_range(req, length, &etag)?;
+    let mut builder = match range {
+        Some((start, end)) => {
+            file.seek(SeekFrom::Start(start))
+                .await
+                .map_err(read_error)?;
+            let mut builder = HttpResponse::PartialContent();
+            builder.insert_header(ContentRange(ContentRangeSpec::Bytes {
+                range: Some((start, end)),
+                instance_length: Some(length),
+            }));
+            builder
+        }
+        None => HttpResponse::Ok(),
+    };
+    let size = range.map_or(length, |(start, end)| end - start + 1);
+    builder
+        .content_type(content_type)
+        .insert_header((header::ACCEPT_RANGES, "bytes"))
+        .insert_header(header::ETag(etag))
+        .no_chunking(size);
+    if let Some(filename) = filename {
+        builder.insert_header((
+            header::CONTENT_DISPOSITION,
+            format!(r#"attachment; filename="{}""#, filename),
+        ));
+    }
+
+    Ok(builder.streaming(file_chunks(file, size)))
+}
+
+/// ファイルの現在の位置から、指定された大きさを読み込むストリームを返す。
+///
+/// # Arguments
+///
+/// * `file` - ファイル。
+/// * `size` - 読み込む大きさ(バイト)。
+///
+/// # Returns
+///
+/// ファイルの内容のチャンクのストリーム。
+fn file_chunks(
+    file: File,
+    size: u64,
+) -> impl futures_util::Stream<Item = Result<web::Bytes, std::io::Error>> {
+    stream::try_unfold((file, size), |(mut file, remaining)| async move {
+        if remaining == 0 {
+            return Ok(None);
+        }
+        let mut chunk = vec![0; remaining.min(CHUNK_SIZE as u64) as usize];
+        file.read_exact(&mut chunk).await?;
+        let read = chunk.len() as u64;
+
+        Ok(Some((web::Bytes::from(chunk), (file, remaining - read))))
+    })
+}
+
+/// ファイルを読み込めなかったことをログに記録して、エラーを返す。
+fn read_error(e: std::io::Error) -> ApiError {
+    tracing::error!("ダウンロードするファイルを読み込めません。{}", e);
+
+    ApiError::ExportFailed
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+
+    use super::*;
+
+    fn etag() -> EntityTag {
+        EntityTag::new_strong("v1".to_string())
+    }
+
+    #[test]
+    fn whole_file_without_range() {
+        let req = TestRequest::default().to_http_request();
+        assert_eq!(requested_range(&req, 100, &etag()).unwrap(), None);
+    }
+
+    #[test]
+    fn single_ranges_are_satisfied() {
+        for (range, expected) in [
+            ("bytes=0-9", (0, 9)),
+            ("bytes=90-", (90, 99)),
+            ("bytes=-10", (90, 99)),
+            ("bytes=95-200", (95, 99)),
+        ] {
+            let req = TestRequest::default()
+                .insert_header((header::RANGE, range))
+                .to_http_request();
+            assert_eq!(
+                requested_range(&req, 100, &etag()).unwrap(),
+                Some(expected),
+                "{}",
+                range
+            );
+        }
+    }
+
+    #[test]
+    fn unsatisfiable_range_is_rejected() {
+        let req = TestRequest::default()
+            .insert_header((header::RANGE, "bytes=100-"))
+            .to_http_request();
+        assert!(matches!(
+            requested_range(&req, 100, &etag()),
+            Err(ApiError::RangeNotSatisfiable(100))
+        ));
+        let req = TestRequest::default()
+            .insert_header((header::RANGE, "bytes=-1"))
+            .to_http_request();
+        assert!(requested_range(&req, 0, &etag()).is_err());
+    }
+
+    #[test]
+    fn multiple_ranges_return_whole_file() {
+        let req = TestRequest::default()
+            .insert_header((header::RANGE, "bytes=0-9,20-29"))
+            .to_http_request();
+        assert_eq!(requested_range(&req, 100, &etag()).unwrap(), None);
+    }
+
+    #[test]
+    fn if_range_must_match_etag() {
+        let req = TestRequest::default()
+            .insert_header((header::RANGE, "bytes=0-9"))
+            .insert_header((header::IF_RANGE, r#""v1""#))
+            .to_http_request();
+        assert_eq!(requested_range(&req, 100, &etag()).unwrap(), Some((0, 9)));
+        for if_range in [r#""v0""#, r#"W/"v1""#, "Wed, 21 Oct 2015 07:28:00 GMT"] {
+            let req = TestRequest::default()
+                .insert_header((header::RANGE, "bytes=0-9"))
+                .insert_header((header::IF_RANGE, if_range))
+                .to_http_request();
+            assert_eq!(requested_range(&req, 100, &etag()).unwrap(), None);
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use actix_web::dev::ServiceRequest;
+use lru::LruCache;
+use serde::Deserialize;
+
+use crate::auth::API_KEY_HEADER;
+use crate::errors::ApiError;
+
+/// リクエストの頻度の制限。
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RateLimit {
+    /// リクエストの頻度を制限するか
+    pub enabled: bool,
+    /// クライアントごとに、1秒あたりに許可するリクエストの数
+    pub requests_per_second: f64,
+    /// クライアントごとに、連続して許可するリクエストの最大数
+    pub burst: u32,
+    /// 頻度を記録するクライアントの最大数(超えた場合は最も古いクライアントの記録を破棄する)
+    pub max_clients: usize,
+    /// X-Forwarded-Forヘッダーなどに指定されたIPアドレスでクライアントを識別するか
+    pub trust_forwarded_for: bool,
+    /// リクエストの頻度を制限するパスの接頭辞
+    pub paths: Vec<String>,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            requests_per_second: 10.0,
+            burst: 20,
+            max_clients: 10_000,
+            trust_forwarded_for: false,
+            paths: vec![
+                "/prefectures".to_string(),
+                "/cities".to_string(),
+                "/post_offices".to_string(),
+                "/search".to_string(),
+                "/features/".to_string(),
+                "/collections".to_string(),
+                "/wfs".to_string(),
+                "/tiles/".to_string(),
+                "/mvt/".to_string(),
+                "/export/".to_string(),
+            ],
+        }
+    }
+}
+
+/// クライアントごとのトークンバケット。
+struct Bucket {
+    /// 残っているトークンの数
+    tokens: f64,
+    /// トークンの数を更新した日時
+    updated_at: Instant,
+}
+
+/// トークンバケットで、クライアントごとにリクエストの頻度を制限する。
+pub struct RateLimiter {
+    /// リクエストの頻度の制限
+    limit: RateLimit,
+    /// APIキーでクライアントを識別するか
+    identify_by_api_key: bool,
+    /// クライアントごとのトークンバケット
+    buckets: Mutex<LruCache<String, Bucket>>,
+}
+
+impl RateLimiter {
+    /// リクエストの頻度を制限するリミッターを構築する。
+    ///
+    /// # Arguments
+    ///
+    /// * `limit` - リクエストの頻度の制限。
+    /// * `identify_by_api_key` - APIキーでクライアントを識別するか。APIキーを検証しない場合に
+    ///   trueを指定すると、APIキーを変えるだけで制限を回避できるため、APIキーによる認証を有効にした
+    ///   場合に限りtrueを指定する。
+    ///
+    /// # Returns
+    ///
+    /// リミッター。
+    pub fn new(limit: RateLimit, identify_by_api_key: bool) -> Self {
+        let capacity = limit.max_clients.max(1);
+        Self {
+            limit,
+            identify_by_api_key,
+            buckets: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+
+    /// リクエストを識別するクライアントを返す。
+    ///
+    /// APIキーでクライアントを識別する場合はAPIキー、それ以外の場合はIPアドレスでクライアントを識別する。
+    fn client(&self, req: &ServiceRequest) -> String {
+        if self.identify_by_api_key {
+            if let Some(api_key) = req
+                .headers()
+                .get(API_KEY_HEADER)
+                .and_then(|value| value.to_str().ok())
+            {
+                return format!("key:{}", api_key);
+            }
+        }
+        let address = if self.limit.trust_forwarded_for {
+            req.connection_info()
+                .realip_remote_addr()
+                .map(|address| address.to_string())
+        } else {
+            req.peer_addr().map(|address| address.ip().to_string())
+        };
+
+        format!("ip:{}", address.unwrap_or_default())
+    }
+
+    /// クライアントのトークンバケットからトークンを1つ取り出す。
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - クライアント。
+    ///
+    /// # Returns
+    ///
+    /// トークンを取り出せた場合はOk。取り出せなかった場合は、次のトークンが補充されるまでの時間。
+    fn take(&self, client: &str) -> Result<(), Duration> {
+        let rate = self.limit.requests_per_second;
+        let burst = self.limit.burst as f64;
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        if !buckets.contains(client) {
+            buckets.put(
+                client.to_string(),
+                Bucket {
+                    tokens: burst,
+                    updated_at: now,
+                },
+            );
+        }
+        let bucket = buckets.get_mut(client).unwrap();
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(burst);
+        bucket.updated_at = now;
+        if 1.0 <= bucket.tokens {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / rate))
+        }
+    }
+
+    /// リクエストを許可するか確認する。
+    ///
+    /// # Arguments
+    ///
+    /// * `req` - リクエスト。
+    ///
+    /// # Returns
+    ///
+    /// リクエストを許可する場合はOk。許可しない場合は、Retry-Afterヘッダーを設定した
+    /// 429 Too Many Requestsを示すエラー。
+    pub fn check(&self, req: &ServiceRequest) -> Result<(), ApiError> {
+        let path = req.path();
+        if !self.limit.enabled || !self.limit.paths.iter().any(|p| path.starts_with(p)) {
+            return Ok(());
+        }
+        let client = self.client(req);
+        self.take(&client).map_err(|wait| {
+            let retry_after = wait.as_secs_f64().ceil().max(1.0) as u64;
+            ApiError::TooManyRequests(retry_after)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// リクエストの頻度の制限を有効にしたリミッターを構築する。
+    fn limiter(requests_per_second: f64, burst: u32, max_clients: usize) -> RateLimiter {
+        RateLimiter::new(
+            RateLimit {
+                enabled: true,
+                requests_per_second,
+                burst,
+                max_clients,
+                ..Default::default()
+            },
+            false,
+        )
+    }
+
+    #[test]
+    fn take_allows_burst_then_rejects_until_refilled() {
+        let limiter = limiter(0.5, 2, 10);
+        assert!(limiter.take("ip:127.0.0.1").is_ok());
+        assert!(limiter.take("ip:127.0.0.1").is_ok());
+        let retry_after = limiter.take("ip:127.0.0.1").unwrap_err();
+        // 1秒あたり0.5個のトークンを補充するため、次のトークンまで2秒弱待つ
+        assert!(Duration::from_millis(1900) < retry_after);
+        assert!(retry_after <= Duration::from_secs(2));
+    }
+
+    #[test]
+    fn take_refills_tokens_over_time() {
+        let limiter = limiter(50.0, 1, 10);
+        assert!(limiter.take("ip:127.0.0.1").is_ok());
+        assert!(limiter.take("ip:127.0.0.1").is_err());
+        std::thread::sleep(Duration::from_millis(40));
+        assert!(limiter.take("ip:127.0.0.1").is_ok());
+    }
+
+    #[test]
+    fn take_limits_each_client_separately() {
+        let limiter = limiter(0.5, 1, 10);
+        assert!(limiter.take("ip:127.0.0.1").is_ok());
+        assert!(limiter.take("ip:127.0.0.1").is_err());
+        assert!(limiter.take("ip:127.0.0.2").is_ok());
+        assert!(limiter.take("key:secret").is_ok());
+    }
+
+    #[test]
+    fn take_forgets_least_recently_used_client() {
+        let limiter = limiter(0.5, 1, 1);
+        assert!(limiter.take("ip:127.0.0.1").is_ok());
+        assert!(limiter.take("ip:127.0.0.2").is_ok());
+        // 記録が破棄されたクライアントには、改めてトークンを満たしたバケットを割り当てる
+        assert!(limiter.take("ip:127.0.0.1").is_ok());
+        assert!(limiter.take("ip:127.0.0.1").is_err());
+    }
+}
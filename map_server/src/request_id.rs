@@ -0,0 +1,108 @@
+use std::time::Instant;
+
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use tracing::Span;
+
+/// リクエストIDを指定するリクエストヘッダー及びレスポンスヘッダーの名前。
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// クライアントから受け取るリクエストIDの最大の長さ。
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+/// リクエストを識別するID。
+///
+/// ミドルウェアがリクエストの拡張データに格納するため、ハンドラーは`web::ReqData<RequestId>`で受け取れる。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+    /// リクエストのリクエストIDを決める。
+    ///
+    /// リクエストヘッダーにリクエストIDが指定されている場合は、そのリクエストIDを引き継ぐ。指定されていない
+    /// 場合、または空の場合や長すぎる場合、表示できない文字を含む場合は、UUID(バージョン4)を生成する。
+    ///
+    /// # Arguments
+    ///
+    /// * `req` - リクエスト。
+    ///
+    /// # Returns
+    ///
+    /// リクエストID。
+    pub fn from_request(req: &ServiceRequest) -> Self {
+        let forwarded = req
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|id| {
+                !id.is_empty()
+                    && id.len() <= MAX_REQUEST_ID_LENGTH
+                    && id.chars().all(|c| c.is_ascii_graphic())
+            });
+
+        match forwarded {
+            Some(id) => Self(id.to_string()),
+            None => Self(uuid::Uuid::new_v4().to_string()),
+        }
+    }
+}
+
+/// リクエストごとのルートスパンを生成する。
+///
+/// ハンドラーやSQLのクエリのスパンは、このスパンの子になるため、ログのリクエストIDでリクエストの処理を
+/// 追跡できる。
+///
+/// # Arguments
+///
+/// * `req` - リクエスト。
+/// * `request_id` - リクエストID。
+///
+/// # Returns
+///
+/// リクエストのルートスパン。
+pub fn request_span(req: &ServiceRequest, request_id: &RequestId) -> Span {
+    tracing::info_span!(
+        "HTTP request",
+        request_id = %request_id.0,
+        http.method = %req.method(),
+        http.target = %req.uri(),
+        http.status_code = tracing::field::Empty,
+        otel.name = %format!("{} {}", req.method(), req.path()),
+        otel.kind = "server",
+    )
+}
+
+/// リクエストの処理の完了を記録して、レスポンスヘッダーにリクエストIDを設定する。
+///
+/// # Arguments
+///
+/// * `res` - レスポンス。
+/// * `request_id` - リクエストID。
+/// * `span` - リクエストのルートスパン。
+/// * `started` - リクエストの処理を開始した時刻。
+///
+/// # Returns
+///
+/// リクエストIDを設定したレスポンス。
+pub fn finish_request<B>(
+    mut res: ServiceResponse<B>,
+    request_id: &RequestId,
+    span: &Span,
+    started: Instant,
+) -> ServiceResponse<B> {
+    let status = res.status();
+    span.record("http.status_code", status.as_u16());
+    span.in_scope(|| {
+        tracing::info!(
+            elapsed_millis = started.elapsed().as_millis() as u64,
+            "リクエストを処理しました。"
+        )
+    });
+    if let Ok(value) = HeaderValue::from_str(&request_id.0) {
+        res.headers_mut()
+            .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+    }
+
+    res
+}
@@ -0,0 +1,250 @@
+use actix_web::http::header::ContentType;
+use actix_web::{web, web::Bytes, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use sqlx::PgPool;
+use tracing::Instrument;
+
+use crate::errors::ApiError;
+use crate::handlers::conditional_response;
+use crate::telemetries::query_span;
+
+/// 名前で検索するときに返すフィーチャーの件数の既定値。
+const DEFAULT_SEARCH_LIMIT: u32 = 10;
+
+/// 名前で検索するときに返すフィーチャーの件数の最大値。
+const MAX_SEARCH_LIMIT: u32 = 50;
+
+/// 名前で検索できるレイヤー。
+const SEARCH_LAYERS: [&str; 2] = ["cities", "post_offices"];
+
+/// 検索するときのクエリパラメーター。
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    /// 郵便番号(`500-8570`または`5008570`形式)
+    postal: Option<String>,
+    /// 名前に含まれる文字列
+    q: Option<String>,
+    /// 検索するレイヤー(`cities`または`post_offices`、指定しない場合は両方)
+    layer: Option<String>,
+    /// 返すフィーチャーの件数
+    limit: Option<u32>,
+}
+
+/// 郵便番号を、ハイフンを除いた7桁の数字に変換する。
+///
+/// # Arguments
+///
+/// * `postal` - 郵便番号。
+///
+/// # Returns
+///
+/// 7桁の数字の郵便番号。郵便番号が不正な場合はエラー。
+fn normalize_postal_code(postal: &str) -> Result<String, ApiError> {
+    let postal: String = postal
+        .trim()
+        .chars()
+        .filter(|c| *c != '-' && *c != '－')
+        .collect();
+    if postal.len() != 7 || !postal.chars().all(|c| c.is_ascii_digit()) {
+        return Err(ApiError::InvalidQuery);
+    }
+
+    Ok(postal)
+}
+
+#[tracing::instrument(name = "Search", skip(req, pool))]
+pub async fn search(
+    req: HttpRequest,
+    query: web::Query<SearchQuery>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let body = match (&query.postal, &query.q) {
+        (Some(postal), _) => {
+            let postal = normalize_postal_code(postal)?;
+            search_postal_code(pool.as_ref(), &postal).await?
+        }
+        (None, Some(q)) if !q.trim().is_empty() => {
+            let layers = match query.layer.as_deref() {
+                Some(layer) if SEARCH_LAYERS.contains(&layer) => vec![layer],
+                Some(_) => return Err(ApiError::InvalidQuery.into()),
+                None => SEARCH_LAYERS.to_vec(),
+            };
+            let limit = query
+                .limit
+                .unwrap_or(DEFAULT_SEARCH_LIMIT)
+                .clamp(1, MAX_SEARCH_LIMIT);
+            search_name(pool.as_ref(), q.trim(), &layers, limit).await?
+        }
+        _ => return Err(ApiError::MissingParameter("q".to_string()).into()),
+    };
+
+    Ok(conditional_response(
+        &req,
+        ContentType::json(),
+        Bytes::from(body),
+    ))
+}
+
+/// LIKE演算子のパターンとして扱われる文字をエスケープする。
+///
+/// # Arguments
+///
+/// * `value` - エスケープする文字列。
+///
+/// # Returns
+///
+/// エスケープした文字列。
+fn escape_like(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+/// 市区町村と郵便局を名前で検索する。
+///
+/// 名前に検索する文字列を含むか、名前と検索する文字列のトライグラムの類似度が高いフィーチャーを、
+/// 類似度が高い順に返す。フィーチャーのジオメトリは、市区町村または郵便局の重心とする。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+/// * `q` - 検索する文字列。
+/// * `layers` - 検索するレイヤー。
+/// * `limit` - 返すフィーチャーの件数。
+///
+/// # Returns
+///
+/// 検索したフィーチャーのフィーチャーコレクション。
+async fn search_name(
+    pool: &PgPool,
+    q: &str,
+    layers: &[&str],
+    limit: u32,
+) -> Result<String, ApiError> {
+    let pattern = format!("%{}%", escape_like(q));
+    let result = sqlx::query!(
+        r#"
+        WITH hits AS (
+            SELECT
+                'cities' as layer, NULL::text as id, code::text as code, name,
+                NULL::text as address, ST_Centroid(ST_Collect(geom)) as centroid,
+                similarity(name, $1) as score
+            FROM cities
+            WHERE 'cities' = ANY($3) AND (name ILIKE $2 OR name % $1)
+            GROUP BY code, name
+            UNION ALL
+            SELECT
+                'post_offices', id::text, post_office_code::text, name,
+                address::text, ST_Centroid(geom), similarity(name, $1)
+            FROM post_offices
+            WHERE 'post_offices' = ANY($3) AND (name ILIKE $2 OR name % $1)
+        ), ranked AS (
+            SELECT * FROM hits ORDER BY score DESC, name, code LIMIT $4
+        )
+        SELECT json_build_object(
+            'type', 'FeatureCollection',
+            'features', COALESCE(
+                json_agg(
+                    json_build_object(
+                        'type', 'Feature',
+                        'geometry', ST_AsGeoJSON(r.centroid)::json,
+                        'properties', json_build_object(
+                            'layer', r.layer,
+                            'id', r.id,
+                            'code', r.code,
+                            'name', r.name,
+                            'address', r.address
+                        )
+                    )
+                    ORDER BY r.score DESC, r.name, r.code
+                ),
+                '[]'::json
+            )
+        ) as fc
+        FROM ranked r
+        "#,
+        q,
+        pattern,
+        layers as _,
+        limit as i64,
+    )
+    .fetch_one(pool)
+    .instrument(query_span("SELECT name search"))
+    .await
+    .map_err(ApiError::from)?;
+
+    Ok(result.fc.unwrap().to_string())
+}
+
+/// 郵便番号の町域を検索する。
+///
+/// 登録されている市区町村の町域に限り、市区町村のジオメトリと、郵便番号が一致する郵便局を返す。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+/// * `postal` - 7桁の数字の郵便番号。
+///
+/// # Returns
+///
+/// 町域のフィーチャーコレクション。
+async fn search_postal_code(pool: &PgPool, postal: &str) -> Result<String, ApiError> {
+    let result = sqlx::query!(
+        r#"
+        SELECT json_build_object(
+            'type', 'FeatureCollection',
+            'features', COALESCE(
+                json_agg(
+                    json_build_object(
+                        'type', 'Feature',
+                        'geometry', (
+                            SELECT ST_AsGeoJSON(ST_Collect(c.geom))::json
+                            FROM cities c WHERE c.code = z.city_code
+                        ),
+                        'properties', json_build_object(
+                            'postalCode', z.postal_code,
+                            'cityCode', z.city_code,
+                            'prefectureName', z.prefecture_name,
+                            'cityName', z.city_name,
+                            'town', z.town,
+                            'postOffices', COALESCE(
+                                (
+                                    SELECT json_agg(
+                                        json_build_object(
+                                            'id', p.id, 'name', p.name, 'address', p.address
+                                        )
+                                        ORDER BY p.name
+                                    )
+                                    FROM post_offices p
+                                    WHERE p.postal_code = z.postal_code
+                                        AND p.city_code = z.city_code
+                                ),
+                                '[]'::json
+                            )
+                        )
+                    )
+                    ORDER BY z.city_code, z.town
+                ),
+                '[]'::json
+            )
+        ) as fc
+        FROM postal_codes z
+        WHERE
+            z.postal_code = $1
+            AND EXISTS (SELECT 1 FROM cities c WHERE c.code = z.city_code)
+        "#,
+        postal,
+    )
+    .fetch_one(pool)
+    .instrument(query_span("SELECT postal codes"))
+    .await
+    .map_err(ApiError::from)?;
+
+    Ok(result.fc.unwrap().to_string())
+}
@@ -0,0 +1,28 @@
+/// Webサーバーを停止するシグナルを受信するまで待機する。
+///
+/// Unix系OSではSIGINTとSIGTERM、それ以外のOSではCtrl+Cを待機する。
+///
+/// # Returns
+///
+/// 受信したシグナルの名前。
+pub async fn shutdown_signal() -> &'static str {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut sigterm = signal(SignalKind::terminate()).expect("SIGTERMを待機できません。");
+        let mut sigint = signal(SignalKind::interrupt()).expect("SIGINTを待機できません。");
+        tokio::select! {
+            _ = sigterm.recv() => "SIGTERM",
+            _ = sigint.recv() => "SIGINT",
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Ctrl+Cを待機できません。");
+        "Ctrl+C"
+    }
+}
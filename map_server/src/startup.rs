@@ -0,0 +1,403 @@
+use std::net::TcpListener;
+use std::time::{Duration, Instant};
+
+use actix_cors::Cors;
+use actix_web::dev::{Server, Service};
+use actix_web::error::JsonPayloadError;
+use actix_web::http::header;
+use actix_web::{guard, web, App, HttpMessage, HttpServer};
+use database::settings::DatabasePools;
+use futures_util::future::{ready, Either};
+use sqlx::PgPool;
+use tracing::Instrument;
+
+use crate::auth::{is_admin_path, ADMIN_SCOPE, API_KEY_HEADER};
+use crate::catalog;
+use crate::cdn_purge::CdnPurger;
+use crate::compression::compress_response;
+use crate::config::Settings;
+use crate::custom_layers::CustomLayers;
+use crate::errors::{problem_response, ApiError, Language};
+use crate::events::{self, DataEvents};
+use crate::export::{self, ExportArtifacts};
+use crate::geocoding::{self, GeocodingCache};
+use crate::handlers;
+use crate::layers::Layer;
+use crate::negotiation::{negotiate, MediaFormat, TILE_FORMATS};
+use crate::ogc_api;
+use crate::pmtiles::PmtilesArchives;
+use crate::rate_limit::RateLimiter;
+use crate::request_id::{finish_request, request_span, RequestId, REQUEST_ID_HEADER};
+use crate::search;
+use crate::stats;
+use crate::styles;
+use crate::tile_cache::TileCache;
+use crate::tls::{self, HttpsRedirect};
+use crate::uploads;
+use crate::viewer;
+use crate::wfs;
+
+/// 更新に使用するデータベースコネクションプール。
+///
+/// ハンドラーには、参照に使用するコネクションプールを`web::Data<PgPool>`で、更新に使用するコネクション
+/// プールをこの型で渡す。
+#[derive(Debug, Clone)]
+pub struct WritePool(pub PgPool);
+
+/// Webサーバーを構築する。
+///
+/// シグナルは処理しないため、呼び出し側でWebサーバーを停止すること。
+///
+/// # Arguments
+///
+/// * `listener` - 接続を待ち受けるリスナー。
+/// * `pools` - 参照用と更新用のデータベースコネクションプール。
+/// * `settings` - 地図APIサーバーの設定。
+/// * `custom_layers` - 登録したカスタムレイヤー。
+///
+/// # Returns
+///
+/// Webサーバー。
+pub fn run(
+    listener: TcpListener,
+    pools: DatabasePools,
+    settings: &Settings,
+    custom_layers: CustomLayers,
+) -> std::io::Result<Server> {
+    let pool = web::Data::new(pools.read);
+    let write_pool = web::Data::new(WritePool(pools.write));
+    let tile_cache = web::Data::new(TileCache::new(
+        settings.tile_cache.capacity,
+        Duration::from_secs(settings.tile_cache.ttl_seconds),
+    ));
+    let geocoding_cache = web::Data::new(GeocodingCache::new(&settings.geocoding_cache));
+    let export_artifacts = web::Data::new(ExportArtifacts::new(
+        settings
+            .export
+            .brotli
+            .then_some(settings.compression.brotli_level),
+    )?);
+    let cdn_purger = web::Data::new(CdnPurger::new(
+        settings.cdn_purge.url_template.clone(),
+        settings.cdn_purge.max_retries,
+    ));
+    let http_cache = web::Data::new(settings.http_cache.clone());
+    let data_events = web::Data::new(DataEvents::default());
+    let tiles = web::Data::new(settings.tiles);
+    let health = web::Data::new(settings.health);
+    let guardrails = web::Data::new(settings.guardrails);
+    let custom_layers = web::Data::new(custom_layers);
+    let upload_settings = web::Data::new(settings.uploads);
+    let pmtiles = web::Data::new(PmtilesArchives::open(&settings.pmtiles)?);
+    let compression = settings.compression;
+    let auth = web::Data::new(settings.auth.clone());
+    let admin_auth = web::Data::new(settings.admin.clone());
+    let rate_limiter = web::Data::new(RateLimiter::new(
+        settings.rate_limit.clone(),
+        settings.auth.enabled,
+    ));
+
+    let tls_config = settings.tls.server_config()?;
+    let https_redirect = match settings.tls.redirect_port {
+        Some(_) => Some(HttpsRedirect {
+            port: listener.local_addr()?.port(),
+        }),
+        None => None,
+    };
+    let max_json_size = settings.server.max_json_size_kb * 1024;
+    let max_payload_size = settings.server.max_payload_size_kb * 1024;
+    let keep_alive = Duration::from_secs(settings.server.keep_alive_seconds);
+    let client_request_timeout =
+        Duration::from_millis(settings.server.client_request_timeout_millis);
+
+    let table_layers = Layer::table_pattern();
+
+    let app = move || {
+        App::new()
+            .wrap_fn(move |req, srv| {
+                let accept_encoding = req
+                    .headers()
+                    .get(header::ACCEPT_ENCODING)
+                    .and_then(|value| value.to_str().ok())
+                    .map(|value| value.to_string());
+                let res = srv.call(req);
+                async move {
+                    let res = res.await?.map_into_boxed_body();
+                    compress_response(compression, accept_encoding, res).await
+                }
+            })
+            .wrap_fn({
+                let rate_limiter = rate_limiter.clone();
+                move |req, srv| match rate_limiter.check(&req) {
+                    Ok(()) => Either::Left(srv.call(req)),
+                    Err(e) => Either::Right(ready(Ok(req.error_response(e)))),
+                }
+            })
+            // 管理用のエンドポイントは、APIキーではなく管理用の認証情報で認証する
+            .wrap_fn({
+                let auth = auth.clone();
+                let admin_auth = admin_auth.clone();
+                move |req, srv| {
+                    let authenticated = if is_admin_path(req.path()) {
+                        let authorization = req
+                            .headers()
+                            .get(header::AUTHORIZATION)
+                            .and_then(|value| value.to_str().ok());
+                        admin_auth.authenticate(authorization)
+                    } else {
+                        let api_key = req
+                            .headers()
+                            .get(API_KEY_HEADER)
+                            .and_then(|value| value.to_str().ok());
+                        auth.authenticate(req.path(), api_key)
+                    };
+                    match authenticated {
+                        Ok(()) => Either::Left(srv.call(req)),
+                        Err(e) => Either::Right(ready(Ok(req.error_response(e)))),
+                    }
+                }
+            })
+            .wrap_fn(|req, srv| {
+                let language = Language::from_request(&req);
+                let res = srv.call(req);
+                async move { Ok(problem_response(res.await?, language)) }
+            })
+            .wrap(
+                Cors::default()
+                    .allow_any_origin()
+                    .allowed_methods(["GET"])
+                    .allowed_header(header::CONTENT_TYPE)
+                    .allowed_header(API_KEY_HEADER)
+                    .allowed_header(REQUEST_ID_HEADER)
+                    .allowed_header(header::RANGE)
+                    .expose_headers([
+                        header::HeaderName::from_static(REQUEST_ID_HEADER),
+                        header::CONTENT_RANGE,
+                        header::ETAG,
+                    ]),
+            )
+            // リクエストIDを決めて、リクエストの処理をリクエストIDを記録したスパンの中で実行する
+            .wrap_fn(move |req, srv| {
+                let started = Instant::now();
+                let request_id = RequestId::from_request(&req);
+                let span = request_span(&req, &request_id);
+                req.extensions_mut().insert(request_id.clone());
+                let res = span.in_scope(|| srv.call(req));
+                async move {
+                    let res = res.instrument(span.clone()).await?;
+                    Ok(finish_request(res, &request_id, &span, started))
+                }
+            })
+            // HTTPSにリダイレクトするポートで受け付けたリクエストは、処理せずにリダイレクトする
+            .wrap_fn(move |req, srv| match https_redirect {
+                Some(redirect) if !req.app_config().secure() => {
+                    let res = tls::redirect_to_https(req.request(), redirect);
+                    Either::Right(ready(Ok(req.into_response(res))))
+                }
+                _ => {
+                    let res = srv.call(req);
+                    Either::Left(async move {
+                        Ok::<_, actix_web::Error>(res.await?.map_into_boxed_body())
+                    })
+                }
+            })
+            .app_data(
+                web::QueryConfig::default().error_handler(|_, _| ApiError::InvalidQuery.into()),
+            )
+            // タイルの座標が数値でない場合は、存在しないリソースではなく不正なタイルとみなす
+            .app_data(web::PathConfig::default().error_handler(|_, req| {
+                if is_tile_path(req.path()) {
+                    ApiError::InvalidTile.into()
+                } else {
+                    ApiError::NotFound.into()
+                }
+            }))
+            .app_data(
+                web::JsonConfig::default()
+                    .limit(max_json_size)
+                    .error_handler(|e, _| match e {
+                        JsonPayloadError::OverflowKnownLength { .. }
+                        | JsonPayloadError::Overflow { .. } => ApiError::PayloadTooLarge.into(),
+                        _ => ApiError::InvalidBody.into(),
+                    }),
+            )
+            .app_data(web::PayloadConfig::new(max_payload_size))
+            .route("/health_check", web::get().to(handlers::health_check))
+            .route("/health/live", web::get().to(handlers::liveness))
+            .route("/health/ready", web::get().to(handlers::readiness))
+            .route("/prefectures", web::get().to(handlers::prefectures))
+            .route("/cities", web::get().to(handlers::cities))
+            .route(
+                "/prefectures/{code}/measure",
+                web::get().to(stats::measure_prefecture),
+            )
+            .route("/cities/{code}/measure", web::get().to(stats::measure_city))
+            .route(
+                "/cities/{code}/post_offices",
+                web::get().to(handlers::city_post_offices),
+            )
+            .route("/post_offices", web::get().to(handlers::post_offices))
+            .route(
+                "/post_offices/nearest",
+                web::get().to(handlers::nearest_post_offices),
+            )
+            // テーブルを定義したレイヤーは、レイヤー名をパスパラメーターとして1つのハンドラーで処理する
+            .route(
+                &format!("/{{layer:{}}}", table_layers),
+                web::get().to(handlers::layer_features),
+            )
+            .route("/search", web::get().to(search::search))
+            .route("/locate", web::get().to(geocoding::locate))
+            .route("/stats", web::get().to(stats::stats))
+            .route(
+                "/reverse_geocode",
+                web::get().to(geocoding::reverse_geocode),
+            )
+            .route(
+                "/features/{layer}",
+                web::get().to(handlers::custom_layer_features),
+            )
+            // 複数のレイヤーを1つにまとめたベクタータイルは、レイヤーごとのタイルより先に照合する
+            .route(
+                "/tiles/all/{zoom}/{x}/{y}.pbf",
+                web::get().to(handlers::combined_vector_tiles),
+            )
+            // Mapbox Vector Tileを要求されたタイルは、GeoJSONのタイルを返すハンドラーではなく、
+            // Mapbox Vector Tileを返すハンドラーで処理する
+            .service(
+                web::resource("/tiles/{layer}/{zoom}/{x}/{y}")
+                    .guard(guard::fn_guard(|ctx| {
+                        matches!(negotiate(ctx.head(), &TILE_FORMATS), Ok(MediaFormat::Mvt))
+                    }))
+                    .route(web::get().to(handlers::vector_tiles)),
+            )
+            .route(
+                "/tiles/prefectures/{zoom}/{x}/{y}",
+                web::get().to(handlers::tiled_prefectures),
+            )
+            .route(
+                "/tiles/cities/{zoom}/{x}/{y}",
+                web::get().to(handlers::tiled_cities),
+            )
+            .route(
+                "/tiles/post_offices/{zoom}/{x}/{y}",
+                web::get().to(handlers::tiled_post_offices),
+            )
+            .route(
+                &format!("/tiles/{{layer:{}}}/{{zoom}}/{{x}}/{{y}}", table_layers),
+                web::get().to(handlers::tiled_layer),
+            )
+            .route(
+                "/tiles/{layer}/{zoom}/{x}/{y}",
+                web::get().to(handlers::tiled_custom_layer),
+            )
+            .route(
+                "/mvt/{layer}/{zoom}/{x}/{y}.pbf",
+                web::get().to(handlers::vector_tiles),
+            )
+            .route("/tiles/{layer}.json", web::get().to(handlers::tile_json))
+            .route(
+                "/pmtiles/{layer}.pmtiles",
+                web::get().to(handlers::pmtiles_archive),
+            )
+            .route("/style.json", web::get().to(styles::style_json))
+            .route("/layers", web::get().to(catalog::layers))
+            .route("/imports", web::get().to(catalog::imports))
+            .route("/events", web::get().to(events::events))
+            .route("/", web::get().to(ogc_api::landing_page))
+            .route("/viewer", web::get().to(viewer::viewer))
+            .route("/conformance", web::get().to(ogc_api::conformance))
+            .route("/collections", web::get().to(ogc_api::collections))
+            .route("/collections/{id}", web::get().to(ogc_api::collection))
+            .route("/collections/{id}/items", web::get().to(ogc_api::items))
+            .route(
+                "/collections/{id}/items/{feature_id}",
+                web::get().to(ogc_api::item),
+            )
+            .route("/wfs", web::get().to(wfs::wfs))
+            .route("/export/{layer}.fgb", web::get().to(export::flatgeobuf))
+            .route("/export/{layer}.csv", web::get().to(export::csv))
+            .route(
+                "/export/{layer}.fgb.sha256",
+                web::get().to(export::flatgeobuf_checksums),
+            )
+            .route(
+                "/export/{layer}.csv.sha256",
+                web::get().to(export::csv_checksums),
+            )
+            .service(
+                web::scope(ADMIN_SCOPE)
+                    .route("/stats", web::get().to(handlers::admin_stats))
+                    .route(
+                        "/tile_cache",
+                        web::delete().to(handlers::invalidate_tile_cache),
+                    )
+                    .route(
+                        "/layers/{layer}/style",
+                        web::put().to(styles::put_layer_style),
+                    )
+                    .route(
+                        "/layers/{layer}/style",
+                        web::delete().to(styles::delete_layer_style),
+                    )
+                    .route(
+                        "/prefectures/{code}",
+                        web::delete().to(handlers::delete_prefecture),
+                    )
+                    .route("/datasets/{layer}", web::post().to(uploads::upload_dataset)),
+            )
+            .default_service(web::route().to(handlers::not_found))
+            .app_data(pool.clone())
+            .app_data(write_pool.clone())
+            .app_data(tile_cache.clone())
+            .app_data(geocoding_cache.clone())
+            .app_data(export_artifacts.clone())
+            .app_data(http_cache.clone())
+            .app_data(cdn_purger.clone())
+            .app_data(data_events.clone())
+            .app_data(tiles.clone())
+            .app_data(health.clone())
+            .app_data(guardrails.clone())
+            .app_data(custom_layers.clone())
+            .app_data(upload_settings.clone())
+            .app_data(pmtiles.clone())
+    };
+
+    let mut server = HttpServer::new(app)
+        .keep_alive(keep_alive)
+        .client_request_timeout(client_request_timeout);
+    if let Some(workers) = settings.server.workers {
+        server = server.workers(workers);
+    }
+    server = match tls_config {
+        Some(config) => server.listen_rustls_0_23(listener, config)?,
+        None => server.listen(listener)?,
+    };
+    if let Some(redirect_port) = settings.tls.redirect_port {
+        tracing::info!(
+            "HTTPSにリダイレクトするため、ポート{}で待ち受け",
+            redirect_port
+        );
+        let redirect_listener = TcpListener::bind((settings.server.host.as_str(), redirect_port))?;
+        server = server.listen(redirect_listener)?;
+    }
+    let server = server
+        .disable_signals()
+        .shutdown_timeout(settings.server.shutdown_timeout_seconds)
+        .run();
+
+    Ok(server)
+}
+
+/// リクエストされたパスが、タイルのパスであるか確認する。
+///
+/// # Arguments
+///
+/// * `path` - リクエストされたパス。
+///
+/// # Returns
+///
+/// タイルのパスの場合はtrue。
+fn is_tile_path(path: &str) -> bool {
+    path.starts_with("/tiles/") || path.starts_with("/mvt/")
+}
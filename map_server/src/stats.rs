@@ -0,0 +1,170 @@
+//! 都道府県ごとの市区町村と郵便局の数や面積を集計するエンドポイントと、都道府県や市区町村の面積と周囲長を
+//! 計測するエンドポイント。
+
+use actix_web::http::header::ContentType;
+use actix_web::{web, web::Bytes, HttpRequest, HttpResponse};
+use sqlx::PgPool;
+use tracing::Instrument;
+
+use utils::prefecture::PrefectureCode;
+use utils::EPSG_WGS84;
+
+use crate::errors::ApiError;
+use crate::handlers::{check_city_code, conditional_response};
+use crate::telemetries::query_span;
+
+/// 都道府県ごとの市区町村の数、郵便局の数及び面積を集計する。
+///
+/// 面積は、WGS84経緯度に変換したジオメトリを回転楕円体上で計算した面積(平方キロメートル)とする。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+///
+/// # Returns
+///
+/// 都道府県ごとの集計と、すべての都道府県の合計を格納したJSON。
+async fn aggregate_stats(pool: &PgPool) -> Result<String, ApiError> {
+    let result = sqlx::query!(
+        r#"
+        WITH prefecture_stats AS (
+            SELECT
+                p.code, p.name,
+                (
+                    SELECT COUNT(DISTINCT c.code) FROM cities c WHERE left(c.code, 2) = p.code
+                ) as cities,
+                (
+                    SELECT COUNT(*) FROM post_offices o WHERE left(o.city_code, 2) = p.code
+                ) as post_offices,
+                SUM(ST_Area(ST_Transform(p.geom, $1)::geography)) / 1000000 as area
+            FROM prefectures p
+            GROUP BY p.code, p.name
+        )
+        SELECT json_build_object(
+            'prefectures', COALESCE(
+                json_agg(
+                    json_build_object(
+                        'code', s.code,
+                        'name', s.name,
+                        'cities', s.cities,
+                        'postOffices', s.post_offices,
+                        'areaKm2', round(s.area::numeric, 2)
+                    )
+                    ORDER BY s.code
+                ),
+                '[]'::json
+            ),
+            'total', json_build_object(
+                'prefectures', COUNT(*),
+                'cities', COALESCE(SUM(s.cities), 0),
+                'postOffices', COALESCE(SUM(s.post_offices), 0),
+                'areaKm2', round(COALESCE(SUM(s.area), 0)::numeric, 2)
+            )
+        ) as stats
+        FROM prefecture_stats s
+        "#,
+        EPSG_WGS84,
+    )
+    .fetch_one(pool)
+    .instrument(query_span("SELECT stats"))
+    .await
+    .map_err(ApiError::from)?;
+
+    Ok(result.stats.unwrap().to_string())
+}
+
+#[tracing::instrument(name = "Stats", skip(req, pool))]
+pub async fn stats(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    Ok(conditional_response(
+        &req,
+        ContentType::json(),
+        Bytes::from(aggregate_stats(pool.as_ref()).await?),
+    ))
+}
+
+/// 都道府県または市区町村の面積と周囲長を計測する。
+///
+/// 同じコードの複数のジオメトリを結合して、WGS84経緯度に変換したジオメトリを回転楕円体上で計測する。
+/// 面積は平方キロメートル、周囲長はキロメートルとする。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+/// * `table` - ジオメトリを格納したテーブルの名前(`prefectures`または`cities`)。
+/// * `code` - 都道府県コードまたは市区町村コード。
+///
+/// # Returns
+///
+/// コード、名前、面積及び周囲長を格納したJSON。コードに一致するジオメトリがない場合はNone。
+async fn measure(pool: &PgPool, table: &str, code: &str) -> Result<Option<String>, ApiError> {
+    let sql = format!(
+        r#"
+        SELECT json_build_object(
+            'code', m.code,
+            'name', m.name,
+            'areaKm2', round((ST_Area(m.geog) / 1000000)::numeric, 2),
+            'perimeterKm', round((ST_Perimeter(m.geog) / 1000)::numeric, 2)
+        )
+        FROM (
+            SELECT
+                code, min(name) as name, ST_Union(ST_Transform(geom, $2))::geography as geog
+            FROM {}
+            WHERE code = $1
+            GROUP BY code
+        ) m
+        "#,
+        table
+    );
+    let measured: Option<(serde_json::Value,)> = sqlx::query_as(&sql)
+        .bind(code)
+        .bind(EPSG_WGS84)
+        .fetch_optional(pool)
+        .instrument(query_span("SELECT measure"))
+        .await
+        .map_err(ApiError::from)?;
+
+    Ok(measured.map(|(measured,)| measured.to_string()))
+}
+
+#[tracing::instrument(name = "Measure prefecture", skip(req, pool))]
+pub async fn measure_prefecture(
+    req: HttpRequest,
+    path: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let code: PrefectureCode = path
+        .into_inner()
+        .0
+        .parse()
+        .map_err(|_| ApiError::NotFound)?;
+    let measured = measure(pool.as_ref(), "prefectures", code.as_str())
+        .await?
+        .ok_or(ApiError::NotFound)?;
+
+    Ok(conditional_response(
+        &req,
+        ContentType::json(),
+        Bytes::from(measured),
+    ))
+}
+
+#[tracing::instrument(name = "Measure city", skip(req, pool))]
+pub async fn measure_city(
+    req: HttpRequest,
+    path: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let code = check_city_code(path.into_inner().0)?;
+    let measured = measure(pool.as_ref(), "cities", &code)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+
+    Ok(conditional_response(
+        &req,
+        ContentType::json(),
+        Bytes::from(measured),
+    ))
+}
@@ -0,0 +1,203 @@
+//! 問い合わせた行を、レスポンスボディとして少しずつ送信するストリーム。
+
+use actix_web::web::Bytes;
+use futures_util::{stream, Stream, TryStreamExt};
+use geojson::JsonValue;
+use sqlx::postgres::{PgArguments, PgRow};
+use sqlx::{PgPool, Row};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::errors::ApiError;
+use crate::export::{encode_csv_record, encode_feature_csv_record};
+
+/// ストリーミングするときに、1回で送信するバイト数の目安。
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// 行を1件ずつ問い合わせて、エンコードしたバイト列をチャネルに送信する。
+///
+/// 送信するバイト列は、`CHUNK_SIZE`程度にまとめてから送信する。クライアントが切断した場合は、問い合わせを中止する。
+///
+/// 最初の行を問い合わせて(行がない場合は問い合わせが終わって)エンコードするまで待機するため、問い合わせの
+/// タイムアウトなど、それまでに発生したエラーはエラーとして返す。レスポンスのステータスコードとヘッダーは
+/// ストリームを返した時点で送信されるため、その後に発生したエラーはストリームを途中で終了することしかできない。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+/// * `sql` - 行を選択するSQL。
+/// * `args` - SQLのパラメーター。
+/// * `head` - 行の前に送信するバイト列。
+/// * `encode` - 行の位置と問い合わせた行を受け取り、行をエンコードする関数。
+/// * `tail` - 問い合わせた行の数を受け取り、行の後に送信するバイト列を返す関数。
+///
+/// # Returns
+///
+/// エンコードしたバイト列を受信するストリーム。
+pub async fn stream_rows<F, T>(
+    pool: PgPool,
+    sql: String,
+    args: PgArguments,
+    head: Vec<u8>,
+    mut encode: F,
+    tail: T,
+) -> Result<impl Stream<Item = Result<Bytes, actix_web::Error>>, ApiError>
+where
+    F: FnMut(usize, &PgRow) -> Result<Vec<u8>, sqlx::Error> + Send + 'static,
+    T: FnOnce(usize) -> Vec<u8> + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel::<Result<Bytes, actix_web::Error>>(4);
+    let (started, first) = oneshot::channel::<Result<(), ApiError>>();
+    actix_web::rt::spawn(async move {
+        let mut started = Some(started);
+        let mut chunk = head;
+        let mut count = 0;
+        let mut rows = sqlx::query_with(&sql, args).fetch(&pool);
+        loop {
+            let encoded = match rows.try_next().await {
+                Ok(Some(row)) => encode(count, &row),
+                Ok(None) => break,
+                Err(e) => Err(e),
+            };
+            match encoded {
+                Ok(bytes) => chunk.extend_from_slice(&bytes),
+                Err(e) => {
+                    abort(started, &sender, ApiError::from(e)).await;
+                    return;
+                }
+            }
+            count += 1;
+            if let Some(started) = started.take() {
+                let _ = started.send(Ok(()));
+            }
+            if CHUNK_SIZE <= chunk.len()
+                && sender
+                    .send(Ok(Bytes::from(std::mem::take(&mut chunk))))
+                    .await
+                    .is_err()
+            {
+                tracing::info!("クライアントが切断したため、ストリーミングを中止");
+                return;
+            }
+        }
+        if let Some(started) = started.take() {
+            let _ = started.send(Ok(()));
+        }
+        chunk.extend(tail(count));
+        if !chunk.is_empty() {
+            let _ = sender.send(Ok(Bytes::from(chunk))).await;
+        }
+    });
+    first.await.map_err(|_| ApiError::Internal)??;
+
+    Ok(stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|chunk| (chunk, receiver))
+    }))
+}
+
+/// 行の問い合わせまたはエンコードに失敗したことを通知する。
+///
+/// レスポンスを返す前であればエラーを返し、ストリーミングを始めた後であればストリームにエラーを送信して
+/// ストリームを途中で終了する。
+///
+/// # Arguments
+///
+/// * `started` - レスポンスを返す前に、最初の行を問い合わせたことを通知するチャネル。
+///   ストリーミングを始めた後はNone。
+/// * `sender` - エンコードしたバイト列を送信するチャネル。
+/// * `error` - エラー。
+async fn abort(
+    started: Option<oneshot::Sender<Result<(), ApiError>>>,
+    sender: &mpsc::Sender<Result<Bytes, actix_web::Error>>,
+    error: ApiError,
+) {
+    match started {
+        Some(started) => {
+            let _ = started.send(Err(error));
+        }
+        None => {
+            tracing::error!("ストリーミングを途中で終了: {}", error);
+            let _ = sender.send(Err(error.into())).await;
+        }
+    }
+}
+
+/// フィーチャーを1件ずつ問い合わせて、GeoJSONのフィーチャーコレクションとして送信する。
+///
+/// フィーチャーコレクションのメンバーは、`features`、`numberMatched`、`numberReturned`、`type`の順に並べる。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+/// * `sql` - 1列目にフィーチャーのGeoJSONを選択するSQL。
+/// * `args` - SQLのパラメーター。
+/// * `number_matched` - 条件に一致したフィーチャーの数。ページに分けない場合はNone。
+///
+/// # Returns
+///
+/// フィーチャーコレクションを受信するストリーム。ページに分ける場合は、`numberMatched`と`numberReturned`を含める。
+pub async fn stream_feature_collection(
+    pool: PgPool,
+    sql: String,
+    args: PgArguments,
+    number_matched: Option<i64>,
+) -> Result<impl Stream<Item = Result<Bytes, actix_web::Error>>, ApiError> {
+    stream_rows(
+        pool,
+        sql,
+        args,
+        br#"{"features":["#.to_vec(),
+        |index, row| {
+            let feature: JsonValue = row.try_get(0)?;
+            let mut bytes = if index == 0 { vec![] } else { vec![b','] };
+            bytes.extend(feature.to_string().into_bytes());
+            Ok(bytes)
+        },
+        move |count| {
+            let members = match number_matched {
+                Some(matched) => {
+                    format!(r#","numberMatched":{},"numberReturned":{}"#, matched, count)
+                }
+                None => String::new(),
+            };
+            format!(r#"]{},"type":"FeatureCollection"}}"#, members).into_bytes()
+        },
+    )
+    .await
+}
+
+/// フィーチャーを1件ずつ問い合わせて、CSVのレコードとして送信する。
+///
+/// 先頭のレコードには、プロパティの名前とWKTのジオメトリの列の名前(`wkt`)を格納する。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+/// * `sql` - 1列目にフィーチャーのGeoJSONを選択するSQL。
+/// * `args` - SQLのパラメーター。
+/// * `properties` - レコードに格納するプロパティの名前。
+///
+/// # Returns
+///
+/// CSVのレコードを受信するストリーム。
+pub async fn stream_feature_csv(
+    pool: PgPool,
+    sql: String,
+    args: PgArguments,
+    properties: Vec<&'static str>,
+) -> Result<impl Stream<Item = Result<Bytes, actix_web::Error>>, ApiError> {
+    let mut names: Vec<Option<&str>> = properties.iter().map(|name| Some(*name)).collect();
+    names.push(Some("wkt"));
+
+    stream_rows(
+        pool,
+        sql,
+        args,
+        encode_csv_record(&names),
+        move |_, row| {
+            let feature: JsonValue = row.try_get(0)?;
+            Ok(encode_feature_csv_record(&feature, &properties))
+        },
+        |_| Vec::new(),
+    )
+    .await
+}
@@ -0,0 +1,396 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+use tracing::Instrument;
+
+use crate::custom_layers::CustomLayers;
+use crate::errors::ApiError;
+use crate::guardrails::Guardrails;
+use crate::layers::{Layer, TILE_MAX_ZOOM};
+use crate::startup::WritePool;
+use crate::telemetries::query_span;
+use crate::tile_cache::TILE_LAYERS;
+
+/// アイコンの名前の最大文字数。
+const MAX_ICON_LENGTH: usize = 40;
+
+/// レイヤーを描画するときの既定のスタイル。
+///
+/// 地図のフロントエンドが、同じ色やアイコンでレイヤーを描画できるように、TileJSONでスタイルを配信する。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct LayerStyle {
+    /// 塗りつぶしの色(`#rrggbb`形式など)
+    #[serde(default)]
+    pub fill_color: Option<String>,
+    /// 線の色(`#rrggbb`形式など)
+    #[serde(default)]
+    pub stroke_color: Option<String>,
+    /// ポイントを描画するアイコンの名前
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// ラベルを表示する最小ズームレベル
+    #[serde(default)]
+    pub min_label_zoom: Option<u8>,
+}
+
+impl LayerStyle {
+    /// スタイルを検証する。
+    ///
+    /// # Returns
+    ///
+    /// スタイルが正しい場合はOk。色が`#`で始まる3、4、6または8桁の16進数でない場合や、アイコンの名前が
+    /// 英数字、`-`及び`_`以外を含む場合、ズームレベルが最大値を超える場合は、リクエストボディが不正であることを示すエラー。
+    pub fn validate(&self) -> Result<(), ApiError> {
+        let valid_color = |color: &Option<String>| match color {
+            Some(color) => {
+                let digits = color.strip_prefix('#').unwrap_or("");
+                [3, 4, 6, 8].contains(&digits.len())
+                    && digits.chars().all(|c| c.is_ascii_hexdigit())
+            }
+            None => true,
+        };
+        let valid_icon = match &self.icon {
+            Some(icon) => {
+                !icon.is_empty()
+                    && icon.len() <= MAX_ICON_LENGTH
+                    && icon
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            }
+            None => true,
+        };
+        let valid_zoom = self.min_label_zoom.unwrap_or(TILE_MAX_ZOOM) <= TILE_MAX_ZOOM;
+        if valid_color(&self.fill_color)
+            && valid_color(&self.stroke_color)
+            && valid_icon
+            && valid_zoom
+        {
+            Ok(())
+        } else {
+            Err(ApiError::InvalidBody)
+        }
+    }
+}
+
+/// レイヤーの既定のスタイルを返す。
+///
+/// # Arguments
+///
+/// * `layer` - レイヤー名。
+///
+/// # Returns
+///
+/// レイヤーの既定のスタイル。カスタムレイヤーには既定のスタイルがないため、すべての値がNoneのスタイル。
+pub fn default_style(layer: &str) -> LayerStyle {
+    let style = |fill: Option<&str>, stroke: &str, icon: Option<&str>, zoom: u8| LayerStyle {
+        fill_color: fill.map(str::to_string),
+        stroke_color: Some(stroke.to_string()),
+        icon: icon.map(str::to_string),
+        min_label_zoom: Some(zoom),
+    };
+    match layer {
+        "prefectures" => style(Some("#f2efe9"), "#7f7f7f", None, 5),
+        "cities" => style(Some("#fbf8f3"), "#b3b3b3", None, 9),
+        "post_offices" => style(Some("#e60012"), "#ffffff", Some("post"), 13),
+        "railways" => style(None, "#4d4d4d", None, 11),
+        "stations" => style(None, "#1a1a1a", Some("rail"), 12),
+        "schools" => style(Some("#2e7d32"), "#ffffff", Some("school"), 14),
+        "medical_institutions" => style(Some("#c2185b"), "#ffffff", Some("hospital"), 14),
+        "rivers" => style(None, "#4a90d9", None, 12),
+        _ => LayerStyle::default(),
+    }
+}
+
+/// レイヤーのスタイルを取得する。
+///
+/// 管理APIで登録されたスタイルがある場合はそのスタイル、ない場合は既定のスタイルを返す。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+/// * `layer` - レイヤー名。
+///
+/// # Returns
+///
+/// レイヤーのスタイル。
+pub async fn layer_style(pool: &PgPool, layer: &str) -> sqlx::Result<LayerStyle> {
+    let stored = sqlx::query!(
+        r#"
+        SELECT fill_color, stroke_color, icon, min_label_zoom
+        FROM layer_styles
+        WHERE layer = $1
+        "#,
+        layer,
+    )
+    .fetch_optional(pool)
+    .instrument(query_span("SELECT layer style"))
+    .await?;
+
+    Ok(match stored {
+        Some(stored) => LayerStyle {
+            fill_color: stored.fill_color,
+            stroke_color: stored.stroke_color,
+            icon: stored.icon,
+            min_label_zoom: stored.min_label_zoom.map(|zoom| zoom as u8),
+        },
+        None => default_style(layer),
+    })
+}
+
+/// 配信しているレイヤーか確認する。
+///
+/// # Arguments
+///
+/// * `layer` - レイヤー名。
+/// * `custom_layers` - 登録したカスタムレイヤー。
+///
+/// # Returns
+///
+/// 配信しているレイヤーの場合はOk。配信していない場合は、レイヤーが存在しないことを示すエラー。
+fn check_layer(layer: &str, custom_layers: &CustomLayers) -> Result<(), ApiError> {
+    if TILE_LAYERS.contains(&layer) || custom_layers.get(layer).is_some() {
+        Ok(())
+    } else {
+        Err(ApiError::UnknownLayer)
+    }
+}
+
+#[tracing::instrument(name = "Put layer style", skip(pool, custom_layers))]
+pub async fn put_layer_style(
+    path: web::Path<(String,)>,
+    style: web::Json<LayerStyle>,
+    pool: web::Data<WritePool>,
+    custom_layers: web::Data<CustomLayers>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let layer = path.into_inner().0;
+    check_layer(&layer, &custom_layers)?;
+    style.validate()?;
+    sqlx::query!(
+        r#"
+        INSERT INTO layer_styles (layer, fill_color, stroke_color, icon, min_label_zoom)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (layer) DO UPDATE SET
+            fill_color = EXCLUDED.fill_color,
+            stroke_color = EXCLUDED.stroke_color,
+            icon = EXCLUDED.icon,
+            min_label_zoom = EXCLUDED.min_label_zoom,
+            updated_at = now()
+        "#,
+        layer,
+        style.fill_color,
+        style.stroke_color,
+        style.icon,
+        style.min_label_zoom.map(|zoom| zoom as i16),
+    )
+    .execute(&pool.0)
+    .instrument(query_span("UPSERT layer style"))
+    .await
+    .map_err(ApiError::from)?;
+    tracing::info!("レイヤー({})のスタイルを登録", layer);
+
+    Ok(HttpResponse::Ok().json(json!({ "layer": layer, "style": style.into_inner() })))
+}
+
+#[tracing::instrument(name = "Delete layer style", skip(pool, custom_layers))]
+pub async fn delete_layer_style(
+    path: web::Path<(String,)>,
+    pool: web::Data<WritePool>,
+    custom_layers: web::Data<CustomLayers>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let layer = path.into_inner().0;
+    check_layer(&layer, &custom_layers)?;
+    sqlx::query!(
+        r#"
+        DELETE FROM layer_styles WHERE layer = $1
+        "#,
+        layer,
+    )
+    .execute(&pool.0)
+    .instrument(query_span("DELETE layer style"))
+    .await
+    .map_err(ApiError::from)?;
+    tracing::info!("レイヤー({})のスタイルを既定のスタイルに戻す", layer);
+
+    Ok(HttpResponse::Ok().json(json!({ "layer": layer, "style": default_style(&layer) })))
+}
+
+/// `/style.json`で、ラベルの描画に使用するフォントのグリフのURLテンプレート。
+const GLYPHS_URL: &str = "https://demotiles.maplibre.org/font/{fontstack}/{range}.pbf";
+
+/// `/style.json`で、ラベルの描画に使用するフォント。
+const LABEL_FONT: &str = "Open Sans Semibold";
+
+/// `/style.json`でレイヤーを描画する順序(先に描画するレイヤーから順)。
+const STYLE_LAYER_ORDER: [Layer; 8] = [
+    Layer::Prefectures,
+    Layer::Cities,
+    Layer::Rivers,
+    Layer::Railways,
+    Layer::Stations,
+    Layer::Schools,
+    Layer::MedicalInstitutions,
+    Layer::PostOffices,
+];
+
+/// `/style.json`のクエリパラメーター。
+#[derive(Debug, Deserialize)]
+pub struct StyleQuery {
+    /// スタイルに含めるレイヤー名(カンマ区切り、指定しない場合はすべての組み込みのレイヤー)
+    pub layers: Option<String>,
+}
+
+impl StyleQuery {
+    /// スタイルに含めるレイヤーを、描画する順序で返す。
+    ///
+    /// # Returns
+    ///
+    /// レイヤーを格納したベクタ。組み込みのレイヤーでないレイヤー名を指定された場合はエラー。
+    fn layers(&self) -> Result<Vec<Layer>, ApiError> {
+        let names = match &self.layers {
+            Some(names) => names,
+            None => return Ok(STYLE_LAYER_ORDER.to_vec()),
+        };
+        let requested = names
+            .split(',')
+            .map(|name| Layer::from_name(name.trim()).ok_or(ApiError::UnknownLayer))
+            .collect::<Result<Vec<Layer>, ApiError>>()?;
+
+        Ok(STYLE_LAYER_ORDER
+            .into_iter()
+            .filter(|layer| requested.contains(layer))
+            .collect())
+    }
+}
+
+/// レイヤーを描画するMapLibre GLのスタイルレイヤーを返す。
+///
+/// 都道府県と市区町村は塗りつぶしと境界線、鉄道路線と河川は線、それ以外はポイントを円で描画して、
+/// ラベルを表示する最小ズームレベル以上で名前を表示する。
+///
+/// # Arguments
+///
+/// * `layer` - レイヤー。
+/// * `style` - レイヤーのスタイル。
+///
+/// # Returns
+///
+/// スタイルレイヤーを格納したベクタ。
+fn style_layers(layer: Layer, style: &LayerStyle) -> Vec<serde_json::Value> {
+    let name = layer.name();
+    let fill_color = style.fill_color.as_deref().unwrap_or("#cccccc");
+    let stroke_color = style.stroke_color.as_deref().unwrap_or("#666666");
+    let base = |suffix: &str, layer_type: &str| {
+        json!({
+            "id": format!("{}-{}", name, suffix),
+            "type": layer_type,
+            "source": name,
+            "source-layer": name,
+        })
+    };
+    let mut layers = match layer {
+        Layer::Prefectures | Layer::Cities => {
+            let mut fill = base("fill", "fill");
+            fill["paint"] = json!({ "fill-color": fill_color, "fill-opacity": 0.6 });
+            let mut line = base("line", "line");
+            line["paint"] = json!({ "line-color": stroke_color, "line-width": 1 });
+            vec![fill, line]
+        }
+        Layer::Railways | Layer::Rivers => {
+            let mut line = base("line", "line");
+            line["paint"] = json!({ "line-color": stroke_color, "line-width": 2 });
+            vec![line]
+        }
+        _ => {
+            let mut circle = base("circle", "circle");
+            circle["paint"] = json!({
+                "circle-color": fill_color,
+                "circle-radius": 5,
+                "circle-stroke-color": stroke_color,
+                "circle-stroke-width": 1,
+            });
+            vec![circle]
+        }
+    };
+    let mut label = base("label", "symbol");
+    let (field, placement) = match layer {
+        Layer::Railways => ("lineName", "line"),
+        Layer::Rivers => ("name", "line"),
+        _ => ("name", "point"),
+    };
+    label["minzoom"] = json!(style.min_label_zoom.unwrap_or(TILE_MAX_ZOOM));
+    label["layout"] = json!({
+        "text-field": ["get", field],
+        "text-font": [LABEL_FONT],
+        "text-size": 12,
+        "symbol-placement": placement,
+        "text-offset": if placement == "point" { json!([0, 1.2]) } else { json!([0, 0]) },
+    });
+    label["paint"] = json!({
+        "text-color": "#333333",
+        "text-halo-color": "#ffffff",
+        "text-halo-width": 1,
+    });
+    layers.push(label);
+
+    layers
+}
+
+#[tracing::instrument(name = "Style JSON", skip(req, pool, guardrails))]
+pub async fn style_json(
+    req: HttpRequest,
+    query: web::Query<StyleQuery>,
+    pool: web::Data<PgPool>,
+    guardrails: web::Data<Guardrails>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let layers = query.layers()?;
+    let base_url = {
+        let info = req.connection_info();
+        format!("{}://{}", info.scheme(), info.host())
+    };
+    let mut sources = serde_json::Map::new();
+    let mut style_layers_json = vec![json!({
+        "id": "background",
+        "type": "background",
+        "paint": { "background-color": "#f8f8f8" },
+    })];
+    for layer in layers {
+        let mut source = json!({
+            "type": "vector",
+            "tiles": [format!("{}/mvt/{}/{{z}}/{{x}}/{{y}}.pbf", base_url, layer.name())],
+            "minzoom": guardrails.min_zoom,
+            "maxzoom": guardrails.max_zoom,
+            "attribution": layer.attribution(),
+        });
+        if let Some(bounds) = guardrails.bounds {
+            source["bounds"] = json!([bounds.west, bounds.south, bounds.east, bounds.north]);
+        }
+        sources.insert(layer.name().to_string(), source);
+        let style = layer_style(pool.as_ref(), layer.name())
+            .await
+            .map_err(ApiError::from)?;
+        style_layers_json.extend(style_layers(layer, &style));
+    }
+    // 配信する範囲を制限している場合は、その範囲の中心を表示する
+    let (center, zoom) = match guardrails.bounds {
+        Some(bounds) => (
+            [
+                (bounds.west + bounds.east) / 2.0,
+                (bounds.south + bounds.north) / 2.0,
+            ],
+            8,
+        ),
+        None => ([137.0, 36.0], 5),
+    };
+
+    Ok(HttpResponse::Ok().json(json!({
+        "version": 8,
+        "name": "Sample Map Server",
+        "center": center,
+        "zoom": zoom,
+        "glyphs": GLYPHS_URL,
+        "sources": sources,
+        "layers": style_layers_json,
+    })))
+}
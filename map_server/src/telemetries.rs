@@ -1,13 +1,165 @@
+use std::path::Path;
+use std::str::FromStr;
+
 use actix_web::rt::task::JoinHandle;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::trace::TracerProvider;
+use opentelemetry_sdk::{runtime, Resource};
+use serde::Deserialize;
 use tracing::subscriber::set_global_default;
-use tracing::Subscriber;
+use tracing::{Span, Subscriber};
+use tracing_appender::non_blocking::{NonBlocking, WorkerGuard};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
 use tracing_bunyan_formatter::{BunyanFormattingLayer, JsonStorageLayer};
 use tracing_log::LogTracer;
 use tracing_subscriber::fmt::MakeWriter;
-use tracing_subscriber::{layer::SubscriberExt, EnvFilter, Registry};
+use tracing_subscriber::layer::{Layer, SubscriberExt};
+use tracing_subscriber::{EnvFilter, Registry};
+
+/// ログの出力形式。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Bunyan形式のJSON(スパンのフィールドを含める)
+    #[default]
+    Bunyan,
+    /// 1行に1つのイベントを出力するJSON(LokiやCloudWatch Logsなどへの取り込み用)
+    Json,
+    /// 人が読むためのテキスト
+    Text,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bunyan" => Ok(Self::Bunyan),
+            "json" => Ok(Self::Json),
+            "text" => Ok(Self::Text),
+            _ => Err(format!(
+                "ログの出力形式({})が不正です。bunyan、jsonまたはtextを指定してください。",
+                s
+            )),
+        }
+    }
+}
+
+/// ログファイルを切り替える間隔。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    /// 1分ごと
+    Minutely,
+    /// 1時間ごと
+    Hourly,
+    /// 1日ごと
+    #[default]
+    Daily,
+    /// 切り替えない
+    Never,
+}
+
+impl FromStr for LogRotation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "minutely" => Ok(Self::Minutely),
+            "hourly" => Ok(Self::Hourly),
+            "daily" => Ok(Self::Daily),
+            "never" => Ok(Self::Never),
+            _ => Err(format!(
+                "ログファイルを切り替える間隔({})が不正です。minutely、hourly、dailyまたはneverを指定してください。",
+                s
+            )),
+        }
+    }
+}
+
+impl From<LogRotation> for Rotation {
+    fn from(rotation: LogRotation) -> Self {
+        match rotation {
+            LogRotation::Minutely => Rotation::MINUTELY,
+            LogRotation::Hourly => Rotation::HOURLY,
+            LogRotation::Daily => Rotation::DAILY,
+            LogRotation::Never => Rotation::NEVER,
+        }
+    }
+}
+
+/// ログの出力レベルを指定するフィルターを構築する。
+///
+/// 環境変数`RUST_LOG`が設定されている場合は、設定の値より優先する。
+///
+/// # Arguments
+///
+/// * `level` - 設定されたログの出力レベル(`info`、`map_server=debug,sqlx=warn`など)。
+///
+/// # Returns
+///
+/// ログの出力レベルを指定するフィルター。出力レベルが不正な場合はエラー。
+pub fn env_filter(level: &str) -> Result<EnvFilter, String> {
+    match std::env::var(EnvFilter::DEFAULT_ENV) {
+        Ok(env) if !env.is_empty() => EnvFilter::try_new(&env).map_err(|e| {
+            format!(
+                "環境変数{}の値({})が不正です。{}",
+                EnvFilter::DEFAULT_ENV,
+                env,
+                e
+            )
+        }),
+        _ => EnvFilter::try_new(level)
+            .map_err(|e| format!("ログの出力レベル({})が不正です。{}", level, e)),
+    }
+}
+
+/// ログを出力するファイルのライターを構築する。
+///
+/// ログファイルは`rotation`の間隔で切り替え、ファイル名は`prefix`に日時を付けた名前とする。ログは
+/// 別のスレッドで書き込むため、返されたガードを破棄すると、書き込んでいないログを書き込んで停止する。
+///
+/// # Arguments
+///
+/// * `directory` - ログファイルを保存するディレクトリ。
+/// * `prefix` - ログファイルの名前の接頭辞。
+/// * `rotation` - ログファイルを切り替える間隔。
+/// * `max_files` - 残すログファイルの最大数。Noneの場合はすべて残す。
+///
+/// # Returns
+///
+/// ログファイルのライターと、書き込むスレッドのガード。
+pub fn file_writer(
+    directory: &Path,
+    prefix: &str,
+    rotation: LogRotation,
+    max_files: Option<usize>,
+) -> anyhow::Result<(NonBlocking, WorkerGuard)> {
+    let mut builder = RollingFileAppender::builder()
+        .rotation(rotation.into())
+        .filename_prefix(prefix);
+    if let Some(max_files) = max_files {
+        builder = builder.max_log_files(max_files);
+    }
+    let appender = builder.build(directory).map_err(|e| {
+        anyhow::anyhow!(
+            "ログファイルを保存するディレクトリ({})を使用できません。{}",
+            directory.display(),
+            e
+        )
+    })?;
+
+    Ok(tracing_appender::non_blocking(appender))
+}
 
 /// Compose multiple layers into a `tracing`'s subscriber.
 ///
+/// ログは`format`の形式で`sink`に出力する。`otlp_endpoint`が指定された場合は、スパンをOTLPで
+/// エクスポートするレイヤーを追加する。エクスポーターはTokioのランタイムで動作するため、ランタイムの
+/// 中で呼び出すこと。
+///
 /// # Implementation Notes
 ///
 /// We are using `impl Subscriber` as return type to avoid having to spell out the actual
@@ -15,17 +167,108 @@ use tracing_subscriber::{layer::SubscriberExt, EnvFilter, Registry};
 pub fn get_subscriber<Sink>(
     name: String,
     env_filter: EnvFilter,
+    format: LogFormat,
     sink: Sink,
+    otlp_endpoint: Option<&str>,
 ) -> impl Subscriber + Sync + Send
 where
     Sink: for<'a> MakeWriter<'a> + Send + Sync + 'static,
 {
+    // スパンをOTLPでエクスポートするレイヤーを設定
+    let otlp_layer = otlp_endpoint.map(|endpoint| {
+        let tracer = init_tracer_provider(&name, endpoint).tracer(name.clone());
+        tracing_opentelemetry::layer().with_tracer(tracer)
+    });
     // ログの出力フォーマットを設定
-    let formatting_layer = BunyanFormattingLayer::new(name, sink);
+    let (bunyan_layer, json_layer, text_layer) = match format {
+        LogFormat::Bunyan => (
+            Some((JsonStorageLayer, BunyanFormattingLayer::new(name, sink))),
+            None,
+            None,
+        ),
+        LogFormat::Json => (
+            None,
+            Some(
+                tracing_subscriber::fmt::layer()
+                    .json()
+                    .flatten_event(true)
+                    .with_current_span(true)
+                    .with_span_list(false)
+                    .with_writer(sink),
+            ),
+            None,
+        ),
+        LogFormat::Text => (
+            None,
+            None,
+            Some(
+                tracing_subscriber::fmt::layer()
+                    .with_ansi(false)
+                    .with_writer(sink),
+            ),
+        ),
+    };
     Registry::default()
         .with(env_filter)
-        .with(JsonStorageLayer)
-        .with(formatting_layer)
+        .with(otlp_layer)
+        .with(bunyan_layer.map(|(storage, formatting)| storage.and_then(formatting)))
+        .with(json_layer)
+        .with(text_layer)
+}
+
+/// スパンをOTLPでエクスポートするトレーサープロバイダーを構築して、グローバルに登録する。
+///
+/// # Arguments
+///
+/// * `name` - トレースに記録するサービス名。
+/// * `endpoint` - OTLP(gRPC)でスパンを受け付けるコレクターのエンドポイント。
+///
+/// # Returns
+///
+/// トレーサープロバイダー。
+fn init_tracer_provider(name: &str, endpoint: &str) -> TracerProvider {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()
+        .expect("Failed to build OTLP exporter");
+    let provider = TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_resource(Resource::new([KeyValue::new(
+            "service.name",
+            name.to_string(),
+        )]))
+        .build();
+    opentelemetry::global::set_tracer_provider(provider.clone());
+
+    provider
+}
+
+/// エクスポートしていないスパンをエクスポートして、トレーサープロバイダーを停止する。
+///
+/// スパンをOTLPでエクスポートしていない場合は何もしない。
+pub async fn shutdown_tracer_provider() {
+    actix_web::rt::task::spawn_blocking(opentelemetry::global::shutdown_tracer_provider)
+        .await
+        .ok();
+}
+
+/// SQLのクエリを実行するスパンを生成する。
+///
+/// # Arguments
+///
+/// * `statement` - スパンの名前とする、クエリの概要。
+///
+/// # Returns
+///
+/// クエリを実行するスパン。
+pub fn query_span(statement: &'static str) -> Span {
+    tracing::info_span!(
+        "SQL query",
+        otel.name = statement,
+        otel.kind = "client",
+        db.system = "postgresql",
+    )
 }
 
 /// Register a subscriber as global default to process span data.
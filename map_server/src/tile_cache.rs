@@ -0,0 +1,233 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use actix_web::web::Bytes;
+use lru::LruCache;
+use serde::Serialize;
+
+/// タイルとして配信するレイヤー名。
+pub const TILE_LAYERS: [&str; 8] = [
+    "prefectures",
+    "cities",
+    "post_offices",
+    "railways",
+    "stations",
+    "schools",
+    "medical_institutions",
+    "rivers",
+];
+
+/// 複数のレイヤーを1つにまとめたベクタータイルのレイヤー名。
+pub const COMBINED_TILE_LAYER: &str = "all";
+
+/// 1つにまとめたベクタータイルに含めるレイヤー名。
+pub const COMBINED_TILE_LAYERS: [&str; 3] = ["prefectures", "cities", "post_offices"];
+
+/// タイルの形式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TileFormat {
+    /// GeoJSON
+    GeoJson,
+    /// Mapbox Vector Tile
+    Mvt,
+}
+
+/// タイルキャッシュのキー。
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TileKey {
+    /// レイヤー名
+    pub layer: String,
+    /// カスタムレイヤーのSQLに渡したパラメーター(パラメーターがない場合は空文字列)
+    pub params: String,
+    /// タイルの形式
+    pub format: TileFormat,
+    /// ズームレベル
+    pub zoom: u8,
+    /// タイルのX座標
+    pub x: u32,
+    /// タイルのY座標
+    pub y: u32,
+}
+
+impl TileKey {
+    pub fn new(layer: &str, format: TileFormat, zoom: u8, x: u32, y: u32) -> Self {
+        Self {
+            layer: layer.to_string(),
+            params: String::new(),
+            format,
+            zoom,
+            x,
+            y,
+        }
+    }
+
+    /// タイルを生成するSQLに渡したパラメーター(カスタムレイヤーのパラメーターや郵便局の分類)を設定する。
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - パラメーターの名前と値を連結した文字列。
+    ///
+    /// # Returns
+    ///
+    /// パラメーターを設定したタイルキャッシュのキー。
+    pub fn with_params(mut self, params: String) -> Self {
+        self.params = params;
+        self
+    }
+}
+
+/// キャッシュしたタイル。
+struct CachedTile {
+    /// タイルの内容
+    body: Bytes,
+    /// タイルをキャッシュした日時
+    stored_at: Instant,
+}
+
+/// タイルキャッシュの使用状況。
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TileCacheStats {
+    /// タイルをキャッシュするか
+    pub enabled: bool,
+    /// キャッシュするタイルの最大数
+    pub capacity: usize,
+    /// タイルをキャッシュする期間(秒)
+    pub ttl_seconds: u64,
+    /// キャッシュしているタイルの数
+    pub entries: usize,
+    /// レイヤーごとのキャッシュしているタイルの数
+    pub layers: BTreeMap<String, usize>,
+}
+
+/// 最近使用されていないタイルから破棄する、有効期限付きのタイルキャッシュ。
+pub struct TileCache {
+    /// キャッシュしたタイル(キャッシュを無効にした場合はNone)
+    tiles: Option<Mutex<LruCache<TileKey, CachedTile>>>,
+    /// タイルをキャッシュする期間
+    ttl: Duration,
+}
+
+impl TileCache {
+    /// タイルキャッシュを構築する。
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - キャッシュするタイルの最大数。0の場合はキャッシュしない。
+    /// * `ttl` - タイルをキャッシュする期間。
+    ///
+    /// # Returns
+    ///
+    /// タイルキャッシュ。
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        let tiles = if 0 < capacity && !ttl.is_zero() {
+            Some(Mutex::new(LruCache::new(capacity)))
+        } else {
+            None
+        };
+
+        Self { tiles, ttl }
+    }
+
+    /// キャッシュしたタイルを取得する。
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - タイルキャッシュのキー。
+    ///
+    /// # Returns
+    ///
+    /// キャッシュしたタイルの内容。キャッシュしていない場合、または有効期限が切れている場合はNone。
+    pub fn get(&self, key: &TileKey) -> Option<Bytes> {
+        let mut tiles = self.tiles.as_ref()?.lock().unwrap();
+        match tiles.get(key) {
+            Some(tile) if tile.stored_at.elapsed() < self.ttl => Some(tile.body.clone()),
+            Some(_) => {
+                tiles.pop(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// タイルをキャッシュする。
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - タイルキャッシュのキー。
+    /// * `body` - タイルの内容。
+    pub fn insert(&self, key: TileKey, body: Bytes) {
+        if let Some(tiles) = self.tiles.as_ref() {
+            let tile = CachedTile {
+                body,
+                stored_at: Instant::now(),
+            };
+            tiles.lock().unwrap().put(key, tile);
+        }
+    }
+
+    /// キャッシュしたタイルを破棄する。
+    ///
+    /// # Arguments
+    ///
+    /// * `layer` - タイルを破棄するレイヤー名。Noneの場合はすべてのレイヤーのタイルを破棄する。
+    ///
+    /// # Returns
+    ///
+    /// 破棄したタイルの数。
+    pub fn invalidate(&self, layer: Option<&str>) -> usize {
+        let mut tiles = match self.tiles.as_ref() {
+            Some(tiles) => tiles.lock().unwrap(),
+            None => return 0,
+        };
+        match layer {
+            Some(layer) => {
+                let keys: Vec<TileKey> = tiles
+                    .iter()
+                    .filter(|(key, _)| key.layer == layer)
+                    .map(|(key, _)| key.clone())
+                    .collect();
+                for key in keys.iter() {
+                    tiles.pop(key);
+                }
+                keys.len()
+            }
+            None => {
+                let count = tiles.len();
+                tiles.clear();
+                count
+            }
+        }
+    }
+
+    /// タイルキャッシュの使用状況を返す。
+    ///
+    /// 有効期限が切れたタイルは、次に取得するときに破棄するため、キャッシュしているタイルの数に含める。
+    pub fn stats(&self) -> TileCacheStats {
+        let tiles = match self.tiles.as_ref() {
+            Some(tiles) => tiles.lock().unwrap(),
+            None => {
+                return TileCacheStats {
+                    enabled: false,
+                    capacity: 0,
+                    ttl_seconds: self.ttl.as_secs(),
+                    entries: 0,
+                    layers: BTreeMap::new(),
+                }
+            }
+        };
+        let mut layers = BTreeMap::new();
+        for (key, _) in tiles.iter() {
+            *layers.entry(key.layer.clone()).or_insert(0) += 1;
+        }
+
+        TileCacheStats {
+            enabled: true,
+            capacity: tiles.cap(),
+            ttl_seconds: self.ttl.as_secs(),
+            entries: tiles.len(),
+            layers,
+        }
+    }
+}
@@ -0,0 +1,166 @@
+//! TLSで通信を暗号化して待ち受ける設定と、HTTPのリクエストをHTTPSにリダイレクトするハンドラー。
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use actix_web::http::header;
+use actix_web::{HttpRequest, HttpResponse};
+use rustls::crypto::ring;
+use rustls::ServerConfig;
+use serde::Deserialize;
+
+/// TLSの設定。
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct TlsSettings {
+    /// サーバー証明書(PEM)のパス(設定されていない場合はHTTPで待ち受ける)
+    pub cert_path: Option<PathBuf>,
+    /// サーバー証明書の秘密鍵(PEM)のパス
+    pub key_path: Option<PathBuf>,
+    /// HTTPのリクエストをHTTPSにリダイレクトするために待ち受けるポート番号(設定されていない場合はリダイレクトしない)
+    pub redirect_port: Option<u16>,
+}
+
+impl TlsSettings {
+    /// TLSで待ち受けるか確認する。
+    pub fn enabled(&self) -> bool {
+        self.cert_path.is_some() && self.key_path.is_some()
+    }
+
+    /// サーバー証明書と秘密鍵を読み込んで、TLSで待ち受けるサーバーの設定を構築する。
+    ///
+    /// # Returns
+    ///
+    /// TLSで待ち受けるサーバーの設定。TLSで待ち受けない場合はNone。
+    pub fn server_config(&self) -> std::io::Result<Option<ServerConfig>> {
+        let (cert_path, key_path) = match (&self.cert_path, &self.key_path) {
+            (Some(cert_path), Some(key_path)) => (cert_path, key_path),
+            _ => return Ok(None),
+        };
+        let certs = rustls_pemfile::certs(&mut open_pem(cert_path, "サーバー証明書")?)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| {
+                invalid_data(format!(
+                    "サーバー証明書({})を読み込めません。{}",
+                    cert_path.display(),
+                    e
+                ))
+            })?;
+        if certs.is_empty() {
+            return Err(invalid_data(format!(
+                "サーバー証明書({})に証明書がありません。",
+                cert_path.display()
+            )));
+        }
+        let key = rustls_pemfile::private_key(&mut open_pem(key_path, "秘密鍵")?)
+            .map_err(|e| {
+                invalid_data(format!(
+                    "秘密鍵({})を読み込めません。{}",
+                    key_path.display(),
+                    e
+                ))
+            })?
+            .ok_or_else(|| {
+                invalid_data(format!(
+                    "秘密鍵({})に秘密鍵がありません。",
+                    key_path.display()
+                ))
+            })?;
+        // 依存するクレートが複数の暗号ライブラリを有効にしても構築できるように、暗号ライブラリを明示する
+        let config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(|e| invalid_data(format!("TLSのプロトコルを設定できません。{}", e)))?
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .map_err(|e| {
+                invalid_data(format!(
+                    "サーバー証明書({})と秘密鍵({})を使用できません。{}",
+                    cert_path.display(),
+                    key_path.display(),
+                    e
+                ))
+            })?;
+
+        Ok(Some(config))
+    }
+}
+
+/// PEMのファイルを開く。
+///
+/// # Arguments
+///
+/// * `path` - ファイルのパス。
+/// * `name` - エラーメッセージに使用する、ファイルの内容の名前。
+///
+/// # Returns
+///
+/// ファイルを読み込むリーダー。
+fn open_pem(path: &Path, name: &str) -> std::io::Result<BufReader<File>> {
+    File::open(path).map(BufReader::new).map_err(|e| {
+        std::io::Error::new(
+            e.kind(),
+            format!("{}({})を読み込めません。{}", name, path.display(), e),
+        )
+    })
+}
+
+/// 不正なデータを示すIOエラーを構築する。
+fn invalid_data(message: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
+/// HTTPのリクエストのリダイレクト先
+#[derive(Debug, Clone, Copy)]
+pub struct HttpsRedirect {
+    /// HTTPSで待ち受けるポート番号
+    pub port: u16,
+}
+
+/// リクエストのホストとパスから、HTTPSのURLを構築する。
+///
+/// # Arguments
+///
+/// * `host` - リクエストのホスト(ポート番号を含む場合がある)。
+/// * `port` - HTTPSで待ち受けるポート番号。
+/// * `path_and_query` - リクエストのパスとクエリ文字列。
+///
+/// # Returns
+///
+/// HTTPSのURL。ポート番号が443の場合は、ポート番号を省略する。
+pub fn https_url(host: &str, port: u16, path_and_query: &str) -> String {
+    let host = match host.rfind(':') {
+        // IPv6アドレスのコロンと区別するため、`]`より後のコロンだけをポート番号の区切りとする
+        Some(index) if !host[index..].contains(']') => &host[..index],
+        _ => host,
+    };
+    if port == 443 {
+        format!("https://{}{}", host, path_and_query)
+    } else {
+        format!("https://{}:{}{}", host, port, path_and_query)
+    }
+}
+
+/// リクエストと同じパスのHTTPSのURLにリダイレクトするレスポンスを構築する。
+///
+/// # Arguments
+///
+/// * `req` - HTTPのリクエスト。
+/// * `redirect` - リダイレクト先。
+///
+/// # Returns
+///
+/// `308 Permanent Redirect`のレスポンス。
+pub fn redirect_to_https(req: &HttpRequest, redirect: HttpsRedirect) -> HttpResponse {
+    let path_and_query = req
+        .uri()
+        .path_and_query()
+        .map(|value| value.as_str())
+        .unwrap_or("/");
+    let url = https_url(req.connection_info().host(), redirect.port, path_and_query);
+
+    HttpResponse::PermanentRedirect()
+        .insert_header((header::LOCATION, url))
+        .finish()
+}
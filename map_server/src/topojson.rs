@@ -0,0 +1,664 @@
+//! GeoJSONのフィーチャーを、TopoJSONのトポロジーに変換する。
+//!
+//! <https://github.com/topojson/topojson-specification>
+//!
+//! 座標は、フィーチャーの範囲を格子に分割して量子化し、アークには差分で格納する。ラインストリングと
+//! ポリゴンの輪は、ほかの線と合流または分岐する点(接合点)でアークに分割し、座標の並びが同じアーク
+//! (逆順を含む)を共有するため、隣接するポリゴンの境界は1つのアークになる。
+
+use std::collections::{HashMap, HashSet};
+
+use serde_json::{json, Map, Value};
+
+/// 既定の量子化の段階数。
+pub const DEFAULT_QUANTIZATION: u32 = 1_000_000;
+
+/// 量子化した座標。
+type Point = (i64, i64);
+
+/// 量子化した座標の並び。
+#[derive(Debug)]
+struct Line {
+    /// 座標(ポリゴンの輪の場合は、始点と終点が同じ座標)
+    points: Vec<Point>,
+    /// ポリゴンの輪の場合はtrue
+    ring: bool,
+}
+
+/// アークに変換する前のジオメトリ。線は、線のインデックスで参照する。
+#[derive(Debug)]
+enum Shape {
+    Null,
+    Point(Point),
+    MultiPoint(Vec<Point>),
+    LineString(usize),
+    MultiLineString(Vec<usize>),
+    Polygon(Vec<usize>),
+    MultiPolygon(Vec<Vec<usize>>),
+    GeometryCollection(Vec<Shape>),
+}
+
+/// 座標を量子化する変換。
+#[derive(Debug)]
+struct Quantizer {
+    /// 量子化した座標を経度と緯度に戻すときに加算する値
+    translate: (f64, f64),
+    /// 量子化した座標を経度と緯度に戻すときに乗算する値
+    scale: (f64, f64),
+}
+
+impl Quantizer {
+    /// フィーチャーの範囲を、量子化の段階数で分割する変換を構築する。
+    ///
+    /// 範囲の幅が0の場合は、その軸の座標を1単位で量子化する。
+    fn new(bbox: [f64; 4], quantization: u32) -> Self {
+        let steps = (quantization.max(2) - 1) as f64;
+        let scale = |min: f64, max: f64| {
+            if min < max {
+                (max - min) / steps
+            } else {
+                1.0
+            }
+        };
+
+        Self {
+            translate: (bbox[0], bbox[1]),
+            scale: (scale(bbox[0], bbox[2]), scale(bbox[1], bbox[3])),
+        }
+    }
+
+    /// 座標を量子化する。
+    fn quantize(&self, position: (f64, f64)) -> Point {
+        (
+            ((position.0 - self.translate.0) / self.scale.0).round() as i64,
+            ((position.1 - self.translate.1) / self.scale.1).round() as i64,
+        )
+    }
+
+    /// TopoJSONの`transform`メンバー。
+    fn transform(&self) -> Value {
+        json!({
+            "scale": [self.scale.0, self.scale.1],
+            "translate": [self.translate.0, self.translate.1],
+        })
+    }
+}
+
+/// フィーチャーをTopoJSONのトポロジーに変換する。
+///
+/// # Arguments
+///
+/// * `name` - トポロジーのオブジェクトの名前。
+/// * `features` - GeoJSONのフィーチャー。
+/// * `quantization` - 量子化の段階数。
+///
+/// # Returns
+///
+/// フィーチャーをジオメトリコレクションのオブジェクトとして格納したトポロジー。
+pub fn encode_topology(name: &str, features: &[Value], quantization: u32) -> Value {
+    let bbox = features.iter().fold(None, |bbox, feature| {
+        extend_bbox(bbox, &feature["geometry"])
+    });
+    let quantizer = Quantizer::new(bbox.unwrap_or([0.0, 0.0, 0.0, 0.0]), quantization);
+    let mut lines = Vec::new();
+    let shapes: Vec<Shape> = features
+        .iter()
+        .map(|feature| parse_geometry(&feature["geometry"], &quantizer, &mut lines))
+        .collect();
+    let (arcs, line_arcs) = build_arcs(&lines);
+
+    let geometries: Vec<Value> = features
+        .iter()
+        .zip(shapes.iter())
+        .map(|(feature, shape)| {
+            let mut geometry = render_shape(shape, &line_arcs);
+            if let Some(id) = feature.get("id") {
+                geometry["id"] = id.clone();
+            }
+            if let Some(properties) = feature.get("properties").filter(|p| !p.is_null()) {
+                geometry["properties"] = properties.clone();
+            }
+            geometry
+        })
+        .collect();
+    let mut objects = Map::new();
+    objects.insert(
+        name.to_string(),
+        json!({ "type": "GeometryCollection", "geometries": geometries }),
+    );
+    let arcs: Vec<Value> = arcs.iter().map(Vec::as_slice).map(encode_arc).collect();
+
+    let mut topology = json!({
+        "type": "Topology",
+        "transform": quantizer.transform(),
+        "objects": objects,
+        "arcs": arcs,
+    });
+    if let Some(bbox) = bbox {
+        topology["bbox"] = json!(bbox);
+    }
+
+    topology
+}
+
+/// GeoJSONの座標から、経度と緯度を取得する。
+fn position(value: &Value) -> Option<(f64, f64)> {
+    let position = value.as_array()?;
+
+    Some((position.first()?.as_f64()?, position.get(1)?.as_f64()?))
+}
+
+/// ジオメトリの座標を、経度と緯度の順に呼び出す。
+fn for_each_position(geometry: &Value, f: &mut impl FnMut((f64, f64))) {
+    fn visit(coordinates: &Value, f: &mut impl FnMut((f64, f64))) {
+        if let Some(position) = position(coordinates) {
+            f(position);
+        } else if let Some(values) = coordinates.as_array() {
+            values.iter().for_each(|value| visit(value, f));
+        }
+    }
+    match geometry["type"].as_str() {
+        Some("GeometryCollection") => {
+            if let Some(members) = geometry["geometries"].as_array() {
+                members
+                    .iter()
+                    .for_each(|member| for_each_position(member, f));
+            }
+        }
+        Some(_) => visit(&geometry["coordinates"], f),
+        None => {}
+    }
+}
+
+/// 範囲を、ジオメトリの座標を含むように広げる。
+fn extend_bbox(bbox: Option<[f64; 4]>, geometry: &Value) -> Option<[f64; 4]> {
+    let mut bbox = bbox;
+    for_each_position(geometry, &mut |(x, y)| {
+        bbox = Some(match bbox {
+            Some([west, south, east, north]) => {
+                [west.min(x), south.min(y), east.max(x), north.max(y)]
+            }
+            None => [x, y, x, y],
+        });
+    });
+
+    bbox
+}
+
+/// 座標の配列を量子化した線として追加する。
+///
+/// 量子化して同じ座標になった連続する点は、1つの点にまとめる。閉じていないポリゴンの輪は、
+/// 始点を終点に追加して閉じる。
+///
+/// # Returns
+///
+/// 追加した線のインデックス。
+fn push_line(
+    coordinates: &Value,
+    ring: bool,
+    quantizer: &Quantizer,
+    lines: &mut Vec<Line>,
+) -> usize {
+    let mut points: Vec<Point> = coordinates
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(position)
+        .map(|position| quantizer.quantize(position))
+        .collect();
+    points.dedup();
+    if ring && points.first() != points.last() {
+        points.push(points[0]);
+    }
+    lines.push(Line { points, ring });
+
+    lines.len() - 1
+}
+
+/// 座標の配列の配列を、線として追加する。
+fn push_lines(
+    coordinates: &Value,
+    ring: bool,
+    quantizer: &Quantizer,
+    lines: &mut Vec<Line>,
+) -> Vec<usize> {
+    coordinates
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .map(|line| push_line(line, ring, quantizer, lines))
+        .collect()
+}
+
+/// GeoJSONのジオメトリの座標を量子化して、線を追加する。
+fn parse_geometry(geometry: &Value, quantizer: &Quantizer, lines: &mut Vec<Line>) -> Shape {
+    let coordinates = &geometry["coordinates"];
+    let points = |coordinates: &Value| -> Vec<Point> {
+        coordinates
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(position)
+            .map(|position| quantizer.quantize(position))
+            .collect()
+    };
+    match geometry["type"].as_str() {
+        Some("Point") => match position(coordinates) {
+            Some(position) => Shape::Point(quantizer.quantize(position)),
+            None => Shape::Null,
+        },
+        Some("MultiPoint") => Shape::MultiPoint(points(coordinates)),
+        Some("LineString") => Shape::LineString(push_line(coordinates, false, quantizer, lines)),
+        Some("MultiLineString") => {
+            Shape::MultiLineString(push_lines(coordinates, false, quantizer, lines))
+        }
+        Some("Polygon") => Shape::Polygon(push_lines(coordinates, true, quantizer, lines)),
+        Some("MultiPolygon") => Shape::MultiPolygon(
+            coordinates
+                .as_array()
+                .map(Vec::as_slice)
+                .unwrap_or_default()
+                .iter()
+                .map(|polygon| push_lines(polygon, true, quantizer, lines))
+                .collect(),
+        ),
+        Some("GeometryCollection") => Shape::GeometryCollection(
+            geometry["geometries"]
+                .as_array()
+                .map(Vec::as_slice)
+                .unwrap_or_default()
+                .iter()
+                .map(|member| parse_geometry(member, quantizer, lines))
+                .collect(),
+        ),
+        _ => Shape::Null,
+    }
+}
+
+/// 線が合流または分岐する点(接合点)を求める。
+///
+/// ラインストリングの始点と終点と、通過するときの前後の点が、ほかに通過したときと異なる点を接合点とする。
+fn find_junctions(lines: &[Line]) -> HashSet<Point> {
+    let mut neighbors: HashMap<Point, (Option<Point>, Option<Point>)> = HashMap::new();
+    let mut junctions = HashSet::new();
+    let mut visit =
+        |point: Point, previous: Option<Point>, next: Option<Point>| match neighbors.get(&point) {
+            Some(&(p, n)) => {
+                if !((p == previous && n == next) || (p == next && n == previous)) {
+                    junctions.insert(point);
+                }
+            }
+            None => {
+                neighbors.insert(point, (previous, next));
+            }
+        };
+    for line in lines {
+        let points = &line.points;
+        if line.ring {
+            // 輪の終点は始点と同じ座標のため、終点を除いて前後の点を求める
+            let len = points.len().saturating_sub(1);
+            for i in 0..len {
+                visit(
+                    points[i],
+                    Some(points[(i + len - 1) % len]),
+                    Some(points[(i + 1) % len]),
+                );
+            }
+        } else {
+            for (i, point) in points.iter().enumerate() {
+                let previous = i.checked_sub(1).map(|j| points[j]);
+                visit(*point, previous, points.get(i + 1).copied());
+            }
+        }
+    }
+    for line in lines.iter().filter(|line| !line.ring) {
+        junctions.extend(line.points.first());
+        junctions.extend(line.points.last());
+    }
+
+    junctions
+}
+
+/// 線を接合点で分割する。
+///
+/// 接合点を含む輪は、最初の接合点から始まるように回転してから分割する。接合点を含まない輪は、
+/// 同じ輪を逆順で記録した場合(ポリゴンの内周と、内周を埋める別のポリゴンの外周など)にも共有できるように、
+/// 最も小さい座標から始まるように回転する。
+fn cut_line(line: &Line, junctions: &HashSet<Point>) -> Vec<Vec<Point>> {
+    let points = &line.points;
+    if points.len() < 2 {
+        return vec![points.clone()];
+    }
+    let points = if line.ring {
+        let len = points.len() - 1;
+        let start = (0..len).find(|i| junctions.contains(&points[*i]));
+        let rotated = |start: usize| -> Vec<Point> {
+            let mut rotated = points[start..len].to_vec();
+            rotated.extend_from_slice(&points[..=start]);
+            rotated
+        };
+        match start {
+            Some(start) => rotated(start),
+            None => {
+                let start = (0..len).min_by_key(|i| points[*i]).unwrap_or(0);
+                return vec![rotated(start)];
+            }
+        }
+    } else {
+        points.clone()
+    };
+    let mut arcs = Vec::new();
+    let mut arc = vec![points[0]];
+    for (i, point) in points.iter().enumerate().skip(1) {
+        arc.push(*point);
+        if i < points.len() - 1 && junctions.contains(point) {
+            arcs.push(std::mem::replace(&mut arc, vec![*point]));
+        }
+    }
+    arcs.push(arc);
+
+    arcs
+}
+
+/// 線をアークに分割して、座標の並びが同じアークを共有する。
+///
+/// # Returns
+///
+/// アークと、線ごとのアークのインデックス。逆順のアークを参照する場合は、インデックスの1の補数。
+fn build_arcs(lines: &[Line]) -> (Vec<Vec<Point>>, Vec<Vec<i64>>) {
+    let junctions = find_junctions(lines);
+    let mut arcs: Vec<Vec<Point>> = Vec::new();
+    let mut indexes: HashMap<Vec<Point>, usize> = HashMap::new();
+    let line_arcs = lines
+        .iter()
+        .map(|line| {
+            cut_line(line, &junctions)
+                .into_iter()
+                .map(|arc| {
+                    if let Some(index) = indexes.get(&arc) {
+                        return *index as i64;
+                    }
+                    let reversed: Vec<Point> = arc.iter().rev().copied().collect();
+                    if let Some(index) = indexes.get(&reversed) {
+                        return !(*index as i64);
+                    }
+                    indexes.insert(arc.clone(), arcs.len());
+                    arcs.push(arc);
+                    (arcs.len() - 1) as i64
+                })
+                .collect()
+        })
+        .collect();
+
+    (arcs, line_arcs)
+}
+
+/// アークの座標を、始点からの差分で表現する。
+fn encode_arc(arc: &[Point]) -> Value {
+    let mut previous = (0, 0);
+    let deltas: Vec<Value> = arc
+        .iter()
+        .map(|point| {
+            let delta = json!([point.0 - previous.0, point.1 - previous.1]);
+            previous = *point;
+            delta
+        })
+        .collect();
+
+    Value::Array(deltas)
+}
+
+/// ジオメトリを、アークを参照するTopoJSONのジオメトリに変換する。
+///
+/// # Returns
+///
+/// TopoJSONのジオメトリ。ジオメトリがない場合は、種類がnullのジオメトリ。
+fn render_shape(shape: &Shape, line_arcs: &[Vec<i64>]) -> Value {
+    let arcs = |lines: &[usize]| -> Vec<&Vec<i64>> {
+        lines.iter().map(|line| &line_arcs[*line]).collect()
+    };
+    match shape {
+        Shape::Null => json!({ "type": null }),
+        Shape::Point(point) => json!({ "type": "Point", "coordinates": [point.0, point.1] }),
+        Shape::MultiPoint(points) => {
+            let coordinates: Vec<[i64; 2]> = points.iter().map(|p| [p.0, p.1]).collect();
+            json!({ "type": "MultiPoint", "coordinates": coordinates })
+        }
+        Shape::LineString(line) => json!({ "type": "LineString", "arcs": line_arcs[*line] }),
+        Shape::MultiLineString(lines) => {
+            json!({ "type": "MultiLineString", "arcs": arcs(lines) })
+        }
+        Shape::Polygon(rings) => json!({ "type": "Polygon", "arcs": arcs(rings) }),
+        Shape::MultiPolygon(polygons) => {
+            let polygons: Vec<Vec<&Vec<i64>>> =
+                polygons.iter().map(Vec::as_slice).map(arcs).collect();
+            json!({ "type": "MultiPolygon", "arcs": polygons })
+        }
+        Shape::GeometryCollection(members) => {
+            let geometries: Vec<Value> = members
+                .iter()
+                .map(|member| render_shape(member, line_arcs))
+                .collect();
+            json!({ "type": "GeometryCollection", "geometries": geometries })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// ポリゴンのフィーチャーを構築する。
+    fn polygon(id: &str, ring: &[[f64; 2]]) -> Value {
+        json!({
+            "type": "Feature",
+            "id": id,
+            "geometry": { "type": "Polygon", "coordinates": [ring] },
+            "properties": { "name": id },
+        })
+    }
+
+    /// アークの差分を累積して、経度と緯度に戻す。
+    fn decode_arcs(topology: &Value) -> Vec<Vec<[f64; 2]>> {
+        let scale = &topology["transform"]["scale"];
+        let translate = &topology["transform"]["translate"];
+        topology["arcs"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|arc| {
+                let (mut x, mut y) = (0, 0);
+                arc.as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|delta| {
+                        x += delta[0].as_i64().unwrap();
+                        y += delta[1].as_i64().unwrap();
+                        [
+                            x as f64 * scale[0].as_f64().unwrap() + translate[0].as_f64().unwrap(),
+                            y as f64 * scale[1].as_f64().unwrap() + translate[1].as_f64().unwrap(),
+                        ]
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// アークのインデックスの配列から、線の座標を組み立てる。
+    fn decode_line(arcs: &[Vec<[f64; 2]>], indexes: &Value) -> Vec<[f64; 2]> {
+        let mut line: Vec<[f64; 2]> = Vec::new();
+        for index in indexes.as_array().unwrap() {
+            let index = index.as_i64().unwrap();
+            let mut arc = if index < 0 {
+                arcs[!index as usize]
+                    .iter()
+                    .rev()
+                    .copied()
+                    .collect::<Vec<_>>()
+            } else {
+                arcs[index as usize].clone()
+            };
+            // 前のアークの終点と、次のアークの始点は同じ座標
+            if !line.is_empty() {
+                arc.remove(0);
+            }
+            line.extend(arc);
+        }
+        line
+    }
+
+    /// 2つの座標の並びが、許容誤差の範囲で一致するか確認する。
+    fn assert_close(actual: &[[f64; 2]], expected: &[[f64; 2]], tolerance: f64) {
+        assert_eq!(actual.len(), expected.len(), "{:?}", actual);
+        for (a, e) in actual.iter().zip(expected) {
+            assert!(
+                (a[0] - e[0]).abs() <= tolerance && (a[1] - e[1]).abs() <= tolerance,
+                "{:?} != {:?}",
+                a,
+                e
+            );
+        }
+    }
+
+    #[test]
+    fn adjacent_polygons_share_one_arc() {
+        let features = [
+            polygon(
+                "west",
+                &[[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0], [0.0, 0.0]],
+            ),
+            polygon(
+                "east",
+                &[[1.0, 0.0], [2.0, 0.0], [2.0, 1.0], [1.0, 1.0], [1.0, 0.0]],
+            ),
+        ];
+        let topology = encode_topology("features", &features, DEFAULT_QUANTIZATION);
+        assert_eq!(topology["type"], "Topology");
+        assert_eq!(topology["bbox"], json!([0.0, 0.0, 2.0, 1.0]));
+
+        // 共有する境界と、それぞれのポリゴンの残りの境界
+        assert_eq!(topology["arcs"].as_array().unwrap().len(), 3);
+        let geometries = &topology["objects"]["features"]["geometries"];
+        assert_eq!(geometries[0]["id"], "west");
+        assert_eq!(geometries[1]["properties"]["name"], "east");
+        let rings: Vec<Vec<i64>> = (0..2)
+            .map(|i| {
+                assert_eq!(geometries[i]["type"], "Polygon");
+                geometries[i]["arcs"][0]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|index| index.as_i64().unwrap())
+                    .collect()
+            })
+            .collect();
+        // 隣接するポリゴンは、共有する境界を逆向きにたどる
+        let shared: Vec<i64> = rings[0]
+            .iter()
+            .copied()
+            .filter(|index| rings[1].contains(&!index))
+            .collect();
+        assert_eq!(shared.len(), 1, "{:?}", rings);
+        let arcs = decode_arcs(&topology);
+        let mut shared = arcs[shared[0] as usize].clone();
+        shared.sort_by(|a, b| a[1].total_cmp(&b[1]));
+        assert_close(&shared, &[[1.0, 0.0], [1.0, 1.0]], 1e-5);
+    }
+
+    #[test]
+    fn identical_rings_share_one_arc() {
+        // 内周を持つポリゴンと、内周を埋めるポリゴン
+        let hole = [
+            [0.25, 0.25],
+            [0.25, 0.75],
+            [0.75, 0.75],
+            [0.75, 0.25],
+            [0.25, 0.25],
+        ];
+        let island: Vec<[f64; 2]> = hole.iter().rev().copied().collect();
+        let features = [
+            json!({
+                "type": "Feature",
+                "geometry": {
+                    "type": "Polygon",
+                    "coordinates": [
+                        [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0], [0.0, 0.0]],
+                        hole,
+                    ],
+                },
+            }),
+            polygon("island", &island),
+        ];
+        let topology = encode_topology("features", &features, DEFAULT_QUANTIZATION);
+        assert_eq!(topology["arcs"].as_array().unwrap().len(), 2);
+        let geometries = &topology["objects"]["features"]["geometries"];
+        let hole = geometries[0]["arcs"][1][0].as_i64().unwrap();
+        let island = geometries[1]["arcs"][0][0].as_i64().unwrap();
+        assert_eq!(hole, !island);
+    }
+
+    #[test]
+    fn quantized_coordinates_decode_within_tolerance() {
+        let ring = [
+            [136.7211, 35.4231],
+            [136.8123, 35.4012],
+            [136.8501, 35.4789],
+            [136.7655, 35.5123],
+            [136.7211, 35.4231],
+        ];
+        let line = [[136.70001, 35.40002], [136.75003, 35.45004], [136.9, 35.5]];
+        let features = [
+            polygon("polygon", &ring),
+            json!({
+                "type": "Feature",
+                "geometry": { "type": "LineString", "coordinates": line },
+            }),
+            json!({
+                "type": "Feature",
+                "geometry": { "type": "Point", "coordinates": [136.76, 35.42] },
+            }),
+            json!({ "type": "Feature", "geometry": null }),
+        ];
+        let quantization = 10_000;
+        let topology = encode_topology("features", &features, quantization);
+        let scale = &topology["transform"]["scale"];
+        let tolerance = scale[0].as_f64().unwrap().max(scale[1].as_f64().unwrap()) / 2.0 + 1e-12;
+        assert!(tolerance < 1e-4);
+
+        let arcs = decode_arcs(&topology);
+        let geometries = &topology["objects"]["features"]["geometries"];
+        // 接合点のない輪は、最も小さい座標(最も西の点)から始まる
+        assert_close(
+            &decode_line(&arcs, &geometries[0]["arcs"][0]),
+            &ring,
+            tolerance,
+        );
+        assert_close(
+            &decode_line(&arcs, &geometries[1]["arcs"]),
+            &line,
+            tolerance,
+        );
+
+        let point = &geometries[2]["coordinates"];
+        let translate = &topology["transform"]["translate"];
+        let point = [
+            point[0].as_i64().unwrap() as f64 * scale[0].as_f64().unwrap()
+                + translate[0].as_f64().unwrap(),
+            point[1].as_i64().unwrap() as f64 * scale[1].as_f64().unwrap()
+                + translate[1].as_f64().unwrap(),
+        ];
+        assert_close(&[point], &[[136.76, 35.42]], tolerance);
+        assert_eq!(geometries[3]["type"], Value::Null);
+    }
+
+    #[test]
+    fn empty_collection_has_no_arcs() {
+        let topology = encode_topology("features", &[], DEFAULT_QUANTIZATION);
+        assert_eq!(topology["arcs"], json!([]));
+        assert_eq!(topology["objects"]["features"]["geometries"], json!([]));
+        assert!(topology.get("bbox").is_none());
+    }
+}
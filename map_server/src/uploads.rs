@@ -0,0 +1,387 @@
+use std::path::Path;
+use std::time::Instant;
+
+use actix_web::{web, HttpResponse};
+use futures_util::StreamExt;
+use register_mapping::{read_mapped, register_mapped_dataset, BuiltinMapping};
+use register_post_office::{read_post_offices, register_post_office_dataset};
+use register_prefecture::{read_administrative_boundaries, register_boundary_dataset};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use utils::archive::ExtractedArchive;
+use utils::errors::{failure_kind, FailureKind};
+use utils::import::ImportOptions;
+use utils::prefecture::PrefectureCode;
+use utils::prompt::Prompter;
+
+use crate::cdn_purge::{refresh_layers, CdnPurger};
+use crate::errors::ApiError;
+use crate::events::{DataEventKind, DataEvents};
+use crate::geocoding::GeocodingCache;
+use crate::startup::WritePool;
+use crate::tile_cache::TileCache;
+
+/// ZIPアーカイブの先頭に記録されているシグネチャ。
+const ZIP_SIGNATURE: &[u8] = b"PK\x03\x04";
+
+/// アップロードして登録できるレイヤー。
+pub const UPLOAD_LAYERS: [&str; 5] = [
+    "prefectures",
+    "post_offices",
+    "schools",
+    "medical_institutions",
+    "rivers",
+];
+
+/// データセットのアップロードの設定。
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct UploadSettings {
+    /// データセットのアップロードを有効にするか
+    pub enabled: bool,
+    /// アップロードできるデータセットの最大の大きさ(メガバイト)
+    pub max_size_mb: u64,
+}
+
+impl Default for UploadSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_size_mb: 512,
+        }
+    }
+}
+
+/// データセットをアップロードするときのクエリパラメーター。
+#[derive(Debug, Deserialize)]
+pub struct UploadQuery {
+    /// 登録する都道府県コード
+    pub code: Option<PrefectureCode>,
+    /// データセットの空間参照ID(指定しない場合はデータセットから判断する)
+    pub srid: Option<i32>,
+    /// Shapeファイルの属性データの文字エンコーディング
+    pub encoding: Option<String>,
+}
+
+/// データセットの登録結果。
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatasetJob {
+    /// 登録したレイヤー名
+    pub layer: String,
+    /// 登録した都道府県コード
+    pub code: PrefectureCode,
+    /// 登録したフィーチャーの数
+    pub features: usize,
+    /// 登録結果の概要
+    pub summary: String,
+    /// 登録に要した時間(ミリ秒)
+    pub elapsed_millis: u64,
+    /// CDNのキャッシュ削除を要求したか
+    pub purge_requested: bool,
+}
+
+/// 確認せずにすべて許可して、確認した内容をログに記録する。
+///
+/// 地図APIサーバーは端末に接続していないため、登録処理が確認する内容を標準出力ではなくログに出力する。
+#[derive(Debug, Clone, Copy, Default)]
+struct LoggingPrompter;
+
+impl Prompter for LoggingPrompter {
+    fn confirm(&self, question: &str, _default: Option<bool>) -> bool {
+        tracing::info!("{} (アップロードされたデータセットのため許可)", question);
+        true
+    }
+
+    fn notify(&self, message: &str) {
+        tracing::info!("{}", message);
+    }
+}
+
+/// リクエストボディを一時ディレクトリのファイルに書き込む。
+///
+/// ワーカーのスレッドを止めないように、ファイルには非同期に書き込む。
+///
+/// # Arguments
+///
+/// * `payload` - リクエストボディ。
+/// * `directory` - ファイルを書き込むディレクトリ。
+/// * `max_size` - 書き込めるリクエストボディの最大の大きさ(バイト)。
+///
+/// # Returns
+///
+/// 書き込んだファイルのパス。リクエストボディがZIPアーカイブの場合は拡張子を`.zip`、それ以外の場合は
+/// `.geojson`とする。リクエストボディが大きすぎる場合はエラー。
+async fn save_payload(
+    mut payload: web::Payload,
+    directory: &Path,
+    max_size: usize,
+) -> Result<String, ApiError> {
+    let save_error = |e: std::io::Error| {
+        tracing::error!("アップロードされたデータセットを保存できません。{}", e);
+        ApiError::ImportFailed
+    };
+    let received = directory.join("upload");
+    let mut file = tokio::fs::File::create(&received)
+        .await
+        .map_err(save_error)?;
+    let mut size = 0;
+    let mut head = Vec::with_capacity(ZIP_SIGNATURE.len());
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk.map_err(|_| ApiError::InvalidBody)?;
+        size += chunk.len();
+        if max_size < size {
+            return Err(ApiError::PayloadTooLarge);
+        }
+        let remaining = ZIP_SIGNATURE.len() - head.len();
+        head.extend_from_slice(&chunk[..remaining.min(chunk.len())]);
+        file.write_all(&chunk).await.map_err(save_error)?;
+    }
+    file.flush().await.map_err(save_error)?;
+    if size == 0 {
+        return Err(ApiError::InvalidBody);
+    }
+    let name = if head == ZIP_SIGNATURE {
+        "upload.zip"
+    } else {
+        "upload.geojson"
+    };
+    let path = directory.join(name);
+    tokio::fs::rename(&received, &path)
+        .await
+        .map_err(save_error)?;
+
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// 登録処理のエラーを、APIのエラーに変換する。
+///
+/// データセットの内容が不正な場合は、クライアントがデータセットを修正できるように、エラーメッセージを返す。
+fn import_error(e: anyhow::Error) -> ApiError {
+    match failure_kind(&e) {
+        Some(FailureKind::Argument | FailureKind::Input | FailureKind::Data) => {
+            tracing::warn!("アップロードされたデータセットを登録できません。{}", e);
+            ApiError::InvalidDataset(format!("{}", e))
+        }
+        _ => {
+            tracing::error!("アップロードされたデータセットを登録できません。{}", e);
+            ApiError::ImportFailed
+        }
+    }
+}
+
+/// データセットを読み込む処理を、ワーカーのスレッドとは別のスレッドで実行する。
+///
+/// # Arguments
+///
+/// * `read` - データセットを読み込む処理。
+///
+/// # Returns
+///
+/// 読み込んだデータセット。
+async fn read_blocking<T, F>(read: F) -> Result<T, ApiError>
+where
+    F: FnOnce() -> anyhow::Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    web::block(read)
+        .await
+        .map_err(|e| {
+            tracing::error!("アップロードされたデータセットを読み込めません。{}", e);
+            ApiError::ImportFailed
+        })?
+        .map_err(import_error)
+}
+
+/// アップロードされたデータセットを、コマンドラインツールと同じ処理で登録する。
+///
+/// 既存のレコードは、確認せずに削除して登録する。データセットはトランザクションの中で登録するため、
+/// 登録に失敗した場合は既存のレコードが残る。登録に成功した場合は、レイヤーのタイルをキャッシュから
+/// 破棄して、CDNのキャッシュ削除を要求し、`/events`に接続しているクライアントに登録を通知する。
+#[tracing::instrument(
+    name = "Upload dataset",
+    skip(payload, pool, uploads, cache, geocoding_cache, purger, events)
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn upload_dataset(
+    path: web::Path<(String,)>,
+    query: web::Query<UploadQuery>,
+    payload: web::Payload,
+    pool: web::Data<WritePool>,
+    uploads: web::Data<UploadSettings>,
+    cache: web::Data<TileCache>,
+    geocoding_cache: web::Data<GeocodingCache>,
+    purger: web::Data<CdnPurger>,
+    events: web::Data<DataEvents>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if !uploads.enabled {
+        return Err(ApiError::NotFound.into());
+    }
+    let layer = path.into_inner().0;
+    if !UPLOAD_LAYERS.contains(&layer.as_str()) {
+        return Err(ApiError::UnknownLayer.into());
+    }
+    let code = query
+        .code
+        .ok_or_else(|| ApiError::MissingParameter("code".to_string()))?;
+    let started = Instant::now();
+
+    // ZIPアーカイブを展開したディレクトリを含めて、登録が終わったら削除する
+    let directory = tempfile::tempdir().map_err(|e| {
+        tracing::error!("一時ディレクトリを作成できません。{}", e);
+        ApiError::ImportFailed
+    })?;
+    let max_size = (uploads.max_size_mb * 1024 * 1024) as usize;
+    let mut file = save_payload(payload, directory.path(), max_size).await?;
+    let extensions: &[&str] = match layer.as_str() {
+        "prefectures" => &["geojson", "xml", "shp"],
+        _ => &["shp", "geojson"],
+    };
+    let _archive = if file.ends_with(".zip") {
+        // ZIPアーカイブの展開はファイルを同期的に読み書きするため、ワーカーのスレッドとは別のスレッドで展開する
+        let zip = file.clone();
+        let archive = web::block(move || ExtractedArchive::extract(&zip))
+            .await
+            .map_err(|e| {
+                tracing::error!("ZIPアーカイブを展開できません。{}", e);
+                ApiError::ImportFailed
+            })?
+            .map_err(import_error)?;
+        file = archive.find(extensions).map_err(import_error)?;
+        Some(archive)
+    } else {
+        None
+    };
+
+    // データセットの読み込みはファイルを同期的に読み込むため、ワーカーのスレッドとは別のスレッドで読み込み、
+    // データベースへの登録のみをワーカーのスレッドで実行する
+    let options = ImportOptions::default();
+    let srid = query.srid;
+    let encoding = query
+        .encoding
+        .clone()
+        .unwrap_or_else(|| "shift_jis".to_string());
+    let (features, summary) = match layer.as_str() {
+        "prefectures" => {
+            let path = file.clone();
+            let dataset = read_blocking(move || {
+                read_administrative_boundaries(&path, None, None, srid, &options)
+            })
+            .await?;
+            register_boundary_dataset(
+                &pool.0,
+                dataset,
+                &file,
+                code.as_str(),
+                &options,
+                &LoggingPrompter,
+            )
+            .await
+            .map(|summary| {
+                summary.map(|summary| (summary.prefectures + summary.cities, summary.to_string()))
+            })
+        }
+        "post_offices" => {
+            let path = file.clone();
+            let dataset =
+                read_blocking(move || read_post_offices(&path, srid, &encoding, &options)).await?;
+            register_post_office_dataset(
+                &pool.0,
+                dataset,
+                &file,
+                code.as_str(),
+                &options,
+                &LoggingPrompter,
+            )
+            .await
+            .map(|count| count.map(|count| (count, format!("郵便局{}件", count))))
+        }
+        _ => {
+            let mapping = match layer.as_str() {
+                "schools" => BuiltinMapping::Schools,
+                "medical_institutions" => BuiltinMapping::MedicalInstitutions,
+                _ => BuiltinMapping::Rivers,
+            }
+            .mapping();
+            let path = file.clone();
+            let prefecture = code.as_str();
+            let (mapping, dataset) = read_blocking(move || {
+                read_mapped(
+                    &mapping,
+                    &path,
+                    Some(prefecture),
+                    srid,
+                    Some(&encoding),
+                    &options,
+                )
+                .map(|dataset| (mapping, dataset))
+            })
+            .await?;
+            register_mapped_dataset(
+                &pool.0,
+                &mapping,
+                dataset,
+                &file,
+                Some(code.as_str()),
+                &options,
+                &LoggingPrompter,
+            )
+            .await
+            .map(|count| count.map(|count| (count, format!("{}{}件", mapping.name, count))))
+        }
+    }
+    .map_err(import_error)?
+    // 既存のレコードを削除して登録するため、登録を中止することはない
+    .unwrap_or_default();
+    tracing::info!(
+        "アップロードされたデータセットを、レイヤー({})に登録: {}",
+        layer,
+        summary
+    );
+
+    // 行政区域データは、都道府県と市区町村のレイヤーに登録する
+    let layers: Vec<String> = match layer.as_str() {
+        "prefectures" => vec!["prefectures".to_string(), "cities".to_string()],
+        _ => vec![layer.clone()],
+    };
+    let changed: Vec<&str> = layers.iter().map(|layer| layer.as_str()).collect();
+    events.publish(DataEventKind::Import, &changed, Some(code.as_str()));
+    geocoding_cache.invalidate_layers(&changed);
+    let (_, purge_requested) = refresh_layers(&cache, &purger, layers);
+
+    Ok(HttpResponse::Ok().json(DatasetJob {
+        layer,
+        code,
+        features,
+        summary,
+        elapsed_millis: started.elapsed().as_millis() as u64,
+        purge_requested,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::ResponseError;
+    use geo_types::{Geometry, Point};
+    use utils::coordinates::BboxGuard;
+
+    use super::*;
+
+    #[test]
+    fn coordinates_outside_bbox_are_invalid_dataset() {
+        // 緯度0度、経度0度のWebメルカトル座標は、日本の範囲の外側にある
+        let geom = Geometry::Point(Point::new(0.0, 0.0));
+        let e = BboxGuard::default().validate(&geom, "郵便局").unwrap_err();
+        let error = import_error(e);
+        assert_eq!(error.code(), "invalid_dataset");
+        assert_eq!(error.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[test]
+    fn unclassified_errors_are_import_failed() {
+        let error = import_error(anyhow::anyhow!("原因の種類を持たないエラー"));
+        assert_eq!(error.code(), "import_failed");
+        assert_eq!(error.status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
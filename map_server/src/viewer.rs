@@ -0,0 +1,49 @@
+//! 登録したデータを確認するための地図ビューアー。
+//!
+//! ビューアーは、本サーバーが配信する都道府県、市区町村及び郵便局のタイルをOpenLayersで表示する
+//! HTMLで、バイナリに埋め込んで配信する。
+
+use actix_web::http::header::{self, ContentType};
+use actix_web::{HttpRequest, HttpResponse};
+
+/// 地図ビューアーのHTML。
+const VIEWER_HTML: &str = include_str!("../static/viewer.html");
+
+/// リクエストがHTMLを要求しているか確認する。
+///
+/// クエリパラメーター`f`に`html`または`json`が指定されている場合はその値に従い、指定されていない場合は
+/// `Accept`ヘッダーに`text/html`が含まれているか確認する。
+///
+/// # Arguments
+///
+/// * `req` - リクエスト。
+///
+/// # Returns
+///
+/// HTMLを要求している場合はtrue。
+pub fn prefers_html(req: &HttpRequest) -> bool {
+    let format = req
+        .query_string()
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("f="));
+    match format {
+        Some(format) => format == "html",
+        None => req
+            .headers()
+            .get(header::ACCEPT)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|accept| accept.contains("text/html")),
+    }
+}
+
+/// 地図ビューアーのHTMLを返すレスポンスを構築する。
+pub fn viewer_response() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(VIEWER_HTML)
+}
+
+#[tracing::instrument(name = "Viewer")]
+pub async fn viewer() -> HttpResponse {
+    viewer_response()
+}
@@ -0,0 +1,185 @@
+//! 郵便局の位置を、KMLのプレースマークまたはGPXのウェイポイントとして出力する。
+
+use sqlx::types::Uuid;
+
+/// 郵便局の位置
+#[derive(Debug, sqlx::FromRow)]
+pub struct Waypoint {
+    /// ID
+    pub id: Uuid,
+    /// 名前
+    pub name: String,
+    /// 住所
+    pub address: String,
+    /// 経度(WGS84)
+    pub lon: f64,
+    /// 緯度(WGS84)
+    pub lat: f64,
+}
+
+/// XMLの文字データや属性値として出力できるように、文字列をエスケープする。
+///
+/// # Arguments
+///
+/// * `value` - エスケープする文字列。
+///
+/// # Returns
+///
+/// エスケープした文字列。
+fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// 郵便局の位置をKMLのドキュメントとして出力する。
+///
+/// # Arguments
+///
+/// * `name` - ドキュメントの名前。
+/// * `waypoints` - 郵便局の位置。
+///
+/// # Returns
+///
+/// 郵便局ごとにプレースマークを格納したKMLドキュメント。
+pub fn encode_kml(name: &str, waypoints: &[Waypoint]) -> String {
+    let mut kml = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    kml.push('\n');
+    kml.push_str(r#"<kml xmlns="http://www.opengis.net/kml/2.2">"#);
+    kml.push_str(&format!("<Document><name>{}</name>", escape_xml(name)));
+    for waypoint in waypoints {
+        kml.push_str(&format!(
+            r#"<Placemark id="post_office.{}"><name>{}</name><address>{}</address><description>{}</description><Point><coordinates>{},{}</coordinates></Point></Placemark>"#,
+            waypoint.id,
+            escape_xml(&waypoint.name),
+            escape_xml(&waypoint.address),
+            escape_xml(&waypoint.address),
+            waypoint.lon,
+            waypoint.lat,
+        ));
+    }
+    kml.push_str("</Document></kml>\n");
+
+    kml
+}
+
+/// 郵便局の位置をGPX 1.1のドキュメントとして出力する。
+///
+/// # Arguments
+///
+/// * `name` - ドキュメントの名前。
+/// * `waypoints` - 郵便局の位置。
+///
+/// # Returns
+///
+/// 郵便局ごとにウェイポイントを格納したGPXドキュメント。
+pub fn encode_gpx(name: &str, waypoints: &[Waypoint]) -> String {
+    let mut gpx = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    gpx.push('\n');
+    gpx.push_str(
+        r#"<gpx version="1.1" creator="sample_map_server" xmlns="http://www.topografix.com/GPX/1/1">"#,
+    );
+    gpx.push_str(&format!(
+        "<metadata><name>{}</name></metadata>",
+        escape_xml(name)
+    ));
+    for waypoint in waypoints {
+        gpx.push_str(&format!(
+            r#"<wpt lat="{}" lon="{}"><name>{}</name><desc>{}</desc><sym>Post Office</sym></wpt>"#,
+            waypoint.lat,
+            waypoint.lon,
+            escape_xml(&waypoint.name),
+            escape_xml(&waypoint.address),
+        ));
+    }
+    gpx.push_str("</gpx>\n");
+
+    gpx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn waypoint(name: &str) -> Waypoint {
+        Waypoint {
+            id: Uuid::nil(),
+            name: name.to_string(),
+            address: "岐阜県岐阜市清住町1-1".to_string(),
+            lon: 136.7581,
+            lat: 35.4095,
+        }
+    }
+
+    #[test]
+    fn escape_xml_escapes_markup_characters() {
+        assert_eq!(
+            escape_xml(r#"A&B <C> "D" 'E'"#),
+            "A&amp;B &lt;C&gt; &quot;D&quot; &apos;E&apos;"
+        );
+        assert_eq!(escape_xml("岐阜中央郵便局"), "岐阜中央郵便局");
+    }
+
+    #[test]
+    fn encode_kml_escapes_names() {
+        let kml = encode_kml(r#"<"郵便局" & 'ATM'>"#, &[waypoint(r#"A&B <局> "本店""#)]);
+        assert!(
+            kml.contains("<Document><name>&lt;&quot;郵便局&quot; &amp; &apos;ATM&apos;&gt;</name>")
+        );
+        assert!(kml.contains("<name>A&amp;B &lt;局&gt; &quot;本店&quot;</name>"));
+        assert!(!kml.contains("A&B"));
+    }
+
+    #[test]
+    fn encode_gpx_escapes_names() {
+        let gpx = encode_gpx("a&b", &[waypoint(r#"A&B <局> "本店""#)]);
+        assert!(gpx.contains("<metadata><name>a&amp;b</name></metadata>"));
+        assert!(gpx.contains("<name>A&amp;B &lt;局&gt; &quot;本店&quot;</name>"));
+    }
+
+    #[test]
+    fn encode_kml_writes_longitude_before_latitude() {
+        let kml = encode_kml("post_offices", &[waypoint("岐阜中央郵便局")]);
+        assert!(kml.contains(&format!(r#"<Placemark id="post_office.{}">"#, Uuid::nil())));
+        assert!(kml.contains("<Point><coordinates>136.7581,35.4095</coordinates></Point>"));
+    }
+
+    #[test]
+    fn encode_gpx_writes_latitude_and_longitude_attributes() {
+        let gpx = encode_gpx("post_offices", &[waypoint("岐阜中央郵便局")]);
+        assert!(gpx.contains(r#"<wpt lat="35.4095" lon="136.7581">"#));
+        assert!(gpx.contains("<sym>Post Office</sym>"));
+    }
+
+    #[test]
+    fn encode_empty_collections() {
+        assert_eq!(
+            encode_kml("post_offices", &[]),
+            concat!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+                "\n",
+                r#"<kml xmlns="http://www.opengis.net/kml/2.2">"#,
+                "<Document><name>post_offices</name></Document></kml>\n",
+            )
+        );
+        assert_eq!(
+            encode_gpx("post_offices", &[]),
+            concat!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+                "\n",
+                r#"<gpx version="1.1" creator="sample_map_server" xmlns="http://www.topografix.com/GPX/1/1">"#,
+                "<metadata><name>post_offices</name></metadata></gpx>\n",
+            )
+        );
+    }
+}
@@ -0,0 +1,537 @@
+//! WFS 2.0のGetCapabilitiesとGetFeature(KVP形式)のエンドポイント。
+//!
+//! <https://www.ogc.org/standard/wfs/>
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use actix_web::http::header::ContentType;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, HttpResponse, ResponseError};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use tracing::Instrument;
+
+use utils::EPSG_WEB_MERCATOR;
+
+use crate::errors::ApiError;
+use crate::ogc_api::{collection_extent, feature_select, Collection, COLLECTIONS};
+use crate::telemetries::query_span;
+
+/// 対応するWFSのバージョン。
+const VERSIONS: [&str; 2] = ["2.0.0", "2.0.2"];
+/// フィーチャーの型名の名前空間の接頭辞。
+const NAMESPACE_PREFIX: &str = "sms";
+/// フィーチャーの型名の名前空間のURI。
+const NAMESPACE_URI: &str = "https://github.com/xjr1300/sample_map_server";
+/// GetFeatureで件数を指定されなかった場合に返すフィーチャーの最大件数。
+const DEFAULT_COUNT: u32 = 1000;
+/// GML 3.2のMIMEタイプ。
+const GML_CONTENT_TYPE: &str = "application/gml+xml; version=3.2";
+/// フィーチャーを返す座標参照系(Webメルカトル)の名前。
+const DEFAULT_CRS: &str = "urn:ogc:def:crs:EPSG::3857";
+/// Webメルカトルを示す座標参照系の名前。
+const WEB_MERCATOR_CRS_NAMES: [&str; 3] = [
+    "EPSG:3857",
+    "urn:ogc:def:crs:EPSG::3857",
+    "http://www.opengis.net/def/crs/EPSG/0/3857",
+];
+
+/// WFSの例外。
+///
+/// OWSの例外報告(ExceptionReport)として返す。
+#[derive(Debug)]
+pub struct WfsException {
+    /// 例外コード
+    code: &'static str,
+    /// 例外の原因となったパラメーター
+    locator: String,
+    /// 例外の説明
+    text: String,
+}
+
+impl WfsException {
+    /// 必須のパラメーターが指定されていないことを示す例外を構築する。
+    fn missing(locator: &str) -> Self {
+        Self {
+            code: "MissingParameterValue",
+            locator: locator.to_string(),
+            text: format!("Missing parameter: {}", locator),
+        }
+    }
+
+    /// パラメーターの値が不正であることを示す例外を構築する。
+    fn invalid(locator: &str, value: &str) -> Self {
+        Self {
+            code: "InvalidParameterValue",
+            locator: locator.to_string(),
+            text: format!("Invalid value for {}: {}", locator, value),
+        }
+    }
+}
+
+impl std::fmt::Display for WfsException {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+impl ResponseError for WfsException {
+    fn status_code(&self) -> StatusCode {
+        match self.code {
+            "OperationNotSupported" => StatusCode::NOT_IMPLEMENTED,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let body = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<ows:ExceptionReport xmlns:ows="http://www.opengis.net/ows/1.1" version="2.0.0" xml:lang="en">
+  <ows:Exception exceptionCode="{}" locator="{}">
+    <ows:ExceptionText>{}</ows:ExceptionText>
+  </ows:Exception>
+</ows:ExceptionReport>
+"#,
+            self.code,
+            escape_xml(&self.locator),
+            escape_xml(&self.text)
+        );
+
+        HttpResponse::build(self.status_code())
+            .content_type(ContentType::xml())
+            .body(body)
+    }
+}
+
+/// XMLの特殊文字をエスケープする。
+fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// リクエストされたWFSエンドポイントのURLを返す。
+fn endpoint_url(req: &HttpRequest) -> String {
+    let info = req.connection_info();
+
+    format!("{}://{}/wfs", info.scheme(), info.host())
+}
+
+#[tracing::instrument(name = "WFS", skip(req, query, pool))]
+pub async fn wfs(
+    req: HttpRequest,
+    query: web::Query<HashMap<String, String>>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    // KVP形式のパラメーター名は大文字と小文字を区別しない
+    let params: HashMap<String, String> = query
+        .into_inner()
+        .into_iter()
+        .map(|(key, value)| (key.to_ascii_uppercase(), value))
+        .collect();
+    let service = params
+        .get("SERVICE")
+        .ok_or_else(|| WfsException::missing("service"))?;
+    if !service.eq_ignore_ascii_case("WFS") {
+        return Err(WfsException::invalid("service", service).into());
+    }
+    let request = params
+        .get("REQUEST")
+        .ok_or_else(|| WfsException::missing("request"))?;
+    if request.eq_ignore_ascii_case("GetCapabilities") {
+        get_capabilities(&req, pool.as_ref()).await
+    } else if request.eq_ignore_ascii_case("GetFeature") {
+        if let Some(version) = params.get("VERSION") {
+            if !VERSIONS.contains(&version.as_str()) {
+                return Err(WfsException::invalid("version", version).into());
+            }
+        }
+        get_feature(&params, pool.as_ref()).await
+    } else {
+        Err(WfsException {
+            code: "OperationNotSupported",
+            locator: request.to_string(),
+            text: format!("Operation is not supported: {}", request),
+        }
+        .into())
+    }
+}
+
+/// GetCapabilitiesの応答を返す。
+///
+/// # Arguments
+///
+/// * `req` - HTTPリクエスト。
+/// * `pool` - データベースコネクションプール。
+///
+/// # Returns
+///
+/// WFS_Capabilities文書を返すレスポンス。
+async fn get_capabilities(
+    req: &HttpRequest,
+    pool: &PgPool,
+) -> Result<HttpResponse, actix_web::Error> {
+    let url = escape_xml(&endpoint_url(req));
+    let mut feature_types = String::new();
+    for collection in COLLECTIONS.iter() {
+        let bbox = collection_extent(collection, pool)
+            .await
+            .map_err(ApiError::from)?
+            .unwrap_or([-180.0, -85.051129, 180.0, 85.051129]);
+        write!(
+            feature_types,
+            r#"
+    <FeatureType>
+      <Name>{prefix}:{name}</Name>
+      <Title>{title}</Title>
+      <Abstract>{description}</Abstract>
+      <DefaultCRS>{crs}</DefaultCRS>
+      <ows:WGS84BoundingBox>
+        <ows:LowerCorner>{} {}</ows:LowerCorner>
+        <ows:UpperCorner>{} {}</ows:UpperCorner>
+      </ows:WGS84BoundingBox>
+    </FeatureType>"#,
+            bbox[0],
+            bbox[1],
+            bbox[2],
+            bbox[3],
+            prefix = NAMESPACE_PREFIX,
+            name = collection.id,
+            title = escape_xml(collection.title),
+            description = escape_xml(collection.description),
+            crs = DEFAULT_CRS,
+        )
+        .unwrap();
+    }
+    let operation = |name: &str| {
+        format!(
+            r#"
+    <ows:Operation name="{}">
+      <ows:DCP><ows:HTTP><ows:Get xlink:href="{}?"/></ows:HTTP></ows:DCP>
+    </ows:Operation>"#,
+            name, url
+        )
+    };
+    let body = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<WFS_Capabilities version="2.0.0"
+    xmlns="http://www.opengis.net/wfs/2.0"
+    xmlns:ows="http://www.opengis.net/ows/1.1"
+    xmlns:xlink="http://www.w3.org/1999/xlink"
+    xmlns:{prefix}="{namespace}">
+  <ows:ServiceIdentification>
+    <ows:Title>Sample Map Server</ows:Title>
+    <ows:Abstract>国土数値情報の行政区域と郵便局を配信するWFS</ows:Abstract>
+    <ows:ServiceType>WFS</ows:ServiceType>
+    <ows:ServiceTypeVersion>2.0.0</ows:ServiceTypeVersion>
+  </ows:ServiceIdentification>
+  <ows:OperationsMetadata>{get_capabilities}{get_feature}
+    <ows:Parameter name="outputFormat">
+      <ows:AllowedValues>
+        <ows:Value>{gml}</ows:Value>
+        <ows:Value>application/json</ows:Value>
+      </ows:AllowedValues>
+    </ows:Parameter>
+    <ows:Constraint name="ImplementsBasicWFS"><ows:NoValues/><ows:DefaultValue>FALSE</ows:DefaultValue></ows:Constraint>
+    <ows:Constraint name="KVPEncoding"><ows:NoValues/><ows:DefaultValue>TRUE</ows:DefaultValue></ows:Constraint>
+    <ows:Constraint name="ImplementsResultPaging"><ows:NoValues/><ows:DefaultValue>TRUE</ows:DefaultValue></ows:Constraint>
+    <ows:Constraint name="CountDefault"><ows:NoValues/><ows:DefaultValue>{count}</ows:DefaultValue></ows:Constraint>
+  </ows:OperationsMetadata>
+  <FeatureTypeList>{feature_types}
+  </FeatureTypeList>
+</WFS_Capabilities>
+"#,
+        prefix = NAMESPACE_PREFIX,
+        namespace = NAMESPACE_URI,
+        get_capabilities = operation("GetCapabilities"),
+        get_feature = operation("GetFeature"),
+        gml = GML_CONTENT_TYPE,
+        count = DEFAULT_COUNT,
+        feature_types = feature_types,
+    );
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::xml())
+        .body(body))
+}
+
+/// GetFeatureで取得するフィーチャーの範囲を解析する。
+///
+/// # Arguments
+///
+/// * `value` - `minx,miny,maxx,maxy[,crs]`形式の範囲(Webメルカトル座標)。
+///
+/// # Returns
+///
+/// 西端、南端、東端、北端を格納したベクタ。
+fn parse_bbox(value: &str) -> Result<Vec<f64>, WfsException> {
+    let mut parts: Vec<&str> = value.split(',').map(|part| part.trim()).collect();
+    if parts.len() == 5 {
+        let crs = parts.pop().unwrap();
+        if !WEB_MERCATOR_CRS_NAMES.contains(&crs) {
+            return Err(WfsException::invalid("bbox", value));
+        }
+    }
+    let values = parts
+        .iter()
+        .map(|part| part.parse::<f64>())
+        .collect::<Result<Vec<f64>, _>>()
+        .map_err(|_| WfsException::invalid("bbox", value))?;
+    if values.len() != 4 || values[2] < values[0] || values[3] < values[1] {
+        return Err(WfsException::invalid("bbox", value));
+    }
+
+    Ok(values)
+}
+
+/// 数値のパラメーターを解析する。
+fn parse_number(
+    params: &HashMap<String, String>,
+    keys: &[&str],
+) -> Result<Option<u32>, WfsException> {
+    for key in keys {
+        if let Some(value) = params.get(*key) {
+            return value
+                .parse()
+                .map(Some)
+                .map_err(|_| WfsException::invalid(&key.to_ascii_lowercase(), value));
+        }
+    }
+
+    Ok(None)
+}
+
+/// GetFeatureの応答を返す。
+///
+/// # Arguments
+///
+/// * `params` - 名前を大文字にしたKVP形式のパラメーター。
+/// * `pool` - データベースコネクションプール。
+///
+/// # Returns
+///
+/// GML 3.2またはGeoJSONのフィーチャーコレクションを返すレスポンス。
+async fn get_feature(
+    params: &HashMap<String, String>,
+    pool: &PgPool,
+) -> Result<HttpResponse, actix_web::Error> {
+    let type_names = params
+        .get("TYPENAMES")
+        .or_else(|| params.get("TYPENAME"))
+        .ok_or_else(|| WfsException::missing("typeNames"))?;
+    let type_name = type_names
+        .strip_prefix(&format!("{}:", NAMESPACE_PREFIX))
+        .unwrap_or(type_names);
+    let collection = COLLECTIONS
+        .iter()
+        .find(|collection| collection.id == type_name)
+        .ok_or_else(|| WfsException::invalid("typeNames", type_names))?;
+    let geojson = match params
+        .get("OUTPUTFORMAT")
+        .map(|format| format.to_ascii_lowercase())
+    {
+        None => false,
+        Some(format) if format.starts_with("application/gml+xml") || format.contains("gml/3.2") => {
+            false
+        }
+        Some(format)
+            if [
+                "application/json",
+                "application/geo+json",
+                "json",
+                "geojson",
+            ]
+            .contains(&format.as_str()) =>
+        {
+            true
+        }
+        Some(format) => return Err(WfsException::invalid("outputFormat", &format).into()),
+    };
+    if let Some(srs_name) = params.get("SRSNAME") {
+        if !geojson && !WEB_MERCATOR_CRS_NAMES.contains(&srs_name.as_str()) {
+            return Err(WfsException::invalid("srsName", srs_name).into());
+        }
+    }
+    let bbox = match params.get("BBOX") {
+        Some(bbox) => parse_bbox(bbox)?,
+        None => vec![],
+    };
+    let count = parse_number(params, &["COUNT", "MAXFEATURES"])?.unwrap_or(DEFAULT_COUNT);
+    let start_index = parse_number(params, &["STARTINDEX"])?.unwrap_or(0);
+    let hits = params
+        .get("RESULTTYPE")
+        .map(|result_type| result_type.eq_ignore_ascii_case("hits"))
+        .unwrap_or(false);
+
+    let condition = if bbox.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "WHERE t.geom && ST_MakeEnvelope($1, $2, $3, $4, {})",
+            EPSG_WEB_MERCATOR
+        )
+    };
+    let count_sql = format!(
+        r#"
+        SELECT COUNT(*), to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24:MI:SS"Z"')
+        FROM {} t {}
+        "#,
+        collection.id, condition
+    );
+    let (matched, time_stamp): (i64, String) = bbox
+        .iter()
+        .fold(sqlx::query_as(&count_sql), |query, value| query.bind(value))
+        .fetch_one(pool)
+        .instrument(query_span("SELECT WFS feature count"))
+        .await
+        .map_err(ApiError::from)?;
+    let (count, start_index) = if hits { (0, 0) } else { (count, start_index) };
+
+    if geojson {
+        let sql = format!(
+            "{} {} ORDER BY t.id LIMIT ${} OFFSET ${}",
+            feature_select(collection, &collection.columns()),
+            condition,
+            bbox.len() + 1,
+            bbox.len() + 2
+        );
+        let features: Vec<(String,)> = bbox
+            .iter()
+            .fold(sqlx::query_as(&sql), |query, value| query.bind(value))
+            .bind(count as i64)
+            .bind(start_index as i64)
+            .fetch_all(pool)
+            .instrument(query_span("SELECT WFS features"))
+            .await
+            .map_err(ApiError::from)?;
+        let body = format!(
+            r#"{{"type":"FeatureCollection","numberMatched":{},"numberReturned":{},"timeStamp":{},"features":[{}]}}"#,
+            matched,
+            features.len(),
+            json!(time_stamp),
+            features
+                .iter()
+                .map(|(feature,)| feature.as_str())
+                .collect::<Vec<_>>()
+                .join(",")
+        );
+        return Ok(HttpResponse::Ok()
+            .content_type(ContentType::json())
+            .body(body));
+    }
+
+    let sql = format!(
+        r#"
+        SELECT
+            t.id::text, (to_jsonb(t.*) - 'id' - 'geom')::text,
+            ST_AsGML(3, t.geom, 6, 1, NULL, 'geom.' || t.id::text)
+        FROM (SELECT id, {columns}, geom FROM {table}) t
+        {condition}
+        ORDER BY t.id LIMIT ${limit} OFFSET ${offset}
+        "#,
+        columns = collection.columns().join(", "),
+        table = collection.id,
+        condition = condition,
+        limit = bbox.len() + 1,
+        offset = bbox.len() + 2,
+    );
+    let features: Vec<(String, String, Option<String>)> = bbox
+        .iter()
+        .fold(sqlx::query_as(&sql), |query, value| query.bind(value))
+        .bind(count as i64)
+        .bind(start_index as i64)
+        .fetch_all(pool)
+        .instrument(query_span("SELECT WFS features"))
+        .await
+        .map_err(ApiError::from)?;
+
+    let mut body = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<wfs:FeatureCollection
+    xmlns:wfs="http://www.opengis.net/wfs/2.0"
+    xmlns:gml="http://www.opengis.net/gml/3.2"
+    xmlns:{prefix}="{namespace}"
+    numberMatched="{matched}" numberReturned="{returned}" timeStamp="{time_stamp}">"#,
+        prefix = NAMESPACE_PREFIX,
+        namespace = NAMESPACE_URI,
+        matched = matched,
+        returned = features.len(),
+        time_stamp = time_stamp,
+    );
+    for (id, properties, gml) in features.iter() {
+        body.push_str(&gml_member(collection, id, properties, gml.as_deref()));
+    }
+    body.push_str("\n</wfs:FeatureCollection>\n");
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType(GML_CONTENT_TYPE.parse().unwrap()))
+        .body(body))
+}
+
+/// フィーチャーをGML 3.2のメンバーに変換する。
+///
+/// # Arguments
+///
+/// * `collection` - フィーチャーコレクション。
+/// * `id` - フィーチャーのID。
+/// * `properties` - フィーチャーのプロパティ(JSON)。
+/// * `gml` - フィーチャーのジオメトリ(GML)。
+///
+/// # Returns
+///
+/// `wfs:member`要素。
+fn gml_member(collection: &Collection, id: &str, properties: &str, gml: Option<&str>) -> String {
+    let mut member = format!(
+        "\n  <wfs:member>\n    <{prefix}:{name} gml:id=\"{name}.{id}\">",
+        prefix = NAMESPACE_PREFIX,
+        name = collection.id,
+        id = id
+    );
+    let properties: Value = serde_json::from_str(properties).unwrap_or_default();
+    if let Some(properties) = properties.as_object() {
+        for (key, value) in properties {
+            let value = match value {
+                Value::Null => continue,
+                Value::String(value) => value.clone(),
+                value => value.to_string(),
+            };
+            write!(
+                member,
+                "\n      <{prefix}:{key}>{value}</{prefix}:{key}>",
+                prefix = NAMESPACE_PREFIX,
+                key = key,
+                value = escape_xml(&value)
+            )
+            .unwrap();
+        }
+    }
+    if let Some(gml) = gml {
+        write!(
+            member,
+            "\n      <{prefix}:geom>{gml}</{prefix}:geom>",
+            prefix = NAMESPACE_PREFIX,
+            gml = gml
+        )
+        .unwrap();
+    }
+    write!(
+        member,
+        "\n    </{prefix}:{name}>\n  </wfs:member>",
+        prefix = NAMESPACE_PREFIX,
+        name = collection.id
+    )
+    .unwrap();
+
+    member
+}
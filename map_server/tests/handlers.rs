@@ -0,0 +1,639 @@
+//! 同梱したフィクスチャーを登録したデータベースに対して、ハンドラーが返すフィーチャーを確認するテスト。
+//!
+//! `test_support`クレートが起動したPostGISのコンテナ(環境変数TEST_DATABASE_URLを設定した場合はそのサーバー)に
+//! テストごとのデータベースを作成して、フィクスチャーを登録する。
+//! TEST_DATABASE_URLを設定しておらず、Dockerも利用できない場合はテストが失敗する。
+
+use std::net::TcpListener;
+
+use map_server::config::Settings;
+use map_server::custom_layers::CustomLayers;
+use map_server::layers::Layer;
+use map_server::startup::run;
+use test_support::TestDatabase;
+
+/// テストで起動した地図APIサーバー。
+struct TestApp {
+    /// 地図APIサーバーのアドレス
+    address: String,
+    /// 地図APIサーバーが使用するデータベース(テストが終わるまで破棄しない)
+    _database: TestDatabase,
+}
+
+/// フィクスチャーを登録したデータベースを作成して、既定の設定で地図APIサーバーを起動する。
+///
+/// # Returns
+///
+/// 起動した地図APIサーバー。
+async fn spawn_app() -> TestApp {
+    spawn_app_with(&Settings::default()).await
+}
+
+/// フィクスチャーを登録したデータベースを作成して、地図APIサーバーを起動する。
+///
+/// # Arguments
+///
+/// * `settings` - 地図APIサーバーの設定。
+///
+/// # Returns
+///
+/// 起動した地図APIサーバー。
+async fn spawn_app_with(settings: &Settings) -> TestApp {
+    let database = TestDatabase::with_fixtures().await;
+
+    let listener = TcpListener::bind("127.0.0.1:0").expect("ポートを割り当てできません。");
+    let port = listener.local_addr().unwrap().port();
+    let server = run(
+        listener,
+        database.pool.clone().into(),
+        settings,
+        CustomLayers::default(),
+    )
+    .expect("Webサーバーを起動できません。");
+    tokio::spawn(server);
+
+    TestApp {
+        address: format!("http://127.0.0.1:{}", port),
+        _database: database,
+    }
+}
+
+/// 地図APIサーバーにGETリクエストを送信して、ステータスコードとレスポンスボディのJSONを取得する。
+///
+/// # Arguments
+///
+/// * `app` - 地図APIサーバー。
+/// * `path` - リクエストするパス。
+///
+/// # Returns
+///
+/// ステータスコードとレスポンスボディのJSON。
+async fn get_json(app: &TestApp, path: &str) -> (u16, serde_json::Value) {
+    let response = reqwest::get(format!("{}{}", app.address, path))
+        .await
+        .expect("リクエストを送信できません。");
+    let status = response.status().as_u16();
+    let body = response
+        .json()
+        .await
+        .unwrap_or_else(|e| panic!("{}のレスポンスボディがJSONではありません。{}", path, e));
+
+    (status, body)
+}
+
+/// FeatureCollectionのフィーチャーの、プロパティの値を返す。
+///
+/// # Arguments
+///
+/// * `fc` - FeatureCollection。
+/// * `property` - プロパティの名前。
+///
+/// # Returns
+///
+/// フィーチャーのプロパティの値を、昇順に並べ替えて格納したベクタ。
+fn property_values(fc: &serde_json::Value, property: &str) -> Vec<String> {
+    let mut values: Vec<String> = fc["features"]
+        .as_array()
+        .expect("FeatureCollectionにfeaturesがありません。")
+        .iter()
+        .map(|feature| {
+            feature["properties"][property]
+                .as_str()
+                .unwrap()
+                .to_string()
+        })
+        .collect();
+    values.sort();
+
+    values
+}
+
+#[tokio::test]
+async fn tiled_cities_returns_cities_in_tile() {
+    let app = spawn_app().await;
+
+    let (status, fc) = get_json(&app, "/tiles/cities/10/909/403").await;
+    assert_eq!(status, 200);
+    assert_eq!(fc["type"], "FeatureCollection");
+    assert_eq!(property_values(&fc, "code"), ["13101", "13102"]);
+}
+
+#[tokio::test]
+async fn tiled_cities_returns_empty_collection_outside_fixtures() {
+    let app = spawn_app().await;
+
+    let (status, fc) = get_json(&app, "/tiles/cities/10/0/0").await;
+    assert_eq!(status, 200);
+    assert!(fc["features"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn post_offices_filters_by_city_code_and_name() {
+    let app = spawn_app().await;
+
+    let (status, fc) = get_json(&app, "/post_offices").await;
+    assert_eq!(status, 200);
+    assert_eq!(property_values(&fc, "cityCode"), ["13101", "13102"]);
+
+    let (status, fc) = get_json(&app, "/post_offices?city_code=13102").await;
+    assert_eq!(status, 200);
+    assert_eq!(property_values(&fc, "name"), ["中央郵便局"]);
+
+    let (status, fc) = get_json(&app, "/post_offices?name=%E5%8D%83%E4%BB%A3%E7%94%B0").await;
+    assert_eq!(status, 200);
+    assert_eq!(property_values(&fc, "name"), ["千代田郵便局"]);
+}
+
+#[tokio::test]
+async fn post_offices_rejects_unsupported_srid() {
+    let app = spawn_app().await;
+
+    let (status, _) = get_json(&app, "/post_offices?bbox=136,35,137,36&srid=2451").await;
+    assert_eq!(status, 400);
+}
+
+#[tokio::test]
+async fn layer_style_rejects_json_larger_than_limit() {
+    let mut settings = Settings::default();
+    settings.admin.token = Some("admin-token".to_string());
+    settings.server.max_json_size_kb = 1;
+    let app = spawn_app_with(&settings).await;
+
+    let response = reqwest::Client::new()
+        .put(format!("{}/admin/layers/cities/style", app.address))
+        .bearer_auth("admin-token")
+        .header("content-type", "application/json")
+        .body(format!(r#"{{"fill":"{}"}}"#, "a".repeat(2048)))
+        .send()
+        .await
+        .expect("リクエストを送信できません。");
+    assert_eq!(response.status().as_u16(), 413);
+}
+
+#[tokio::test]
+async fn events_notify_deleted_layers() {
+    let mut settings = Settings::default();
+    settings.admin.token = Some("admin-token".to_string());
+    let app = spawn_app_with(&settings).await;
+
+    let mut events = reqwest::get(format!("{}/events", app.address))
+        .await
+        .expect("リクエストを送信できません。");
+    assert_eq!(events.status().as_u16(), 200);
+    assert_eq!(events.headers()["content-type"], "text/event-stream");
+
+    let response = reqwest::Client::new()
+        .delete(format!("{}/admin/prefectures/13", app.address))
+        .bearer_auth("admin-token")
+        .send()
+        .await
+        .expect("リクエストを送信できません。");
+    assert_eq!(response.status().as_u16(), 200);
+
+    // 削除した都道府県、市区町村及び郵便局のレイヤーごとに、イベントを受け取る
+    let mut received = String::new();
+    while received.matches("event: delete").count() < 3 {
+        let chunk = events
+            .chunk()
+            .await
+            .unwrap()
+            .expect("イベントのストリームが終了しました。");
+        received.push_str(std::str::from_utf8(&chunk).unwrap());
+    }
+    for layer in ["prefectures", "cities", "post_offices"] {
+        assert!(received.contains(&format!(r#""layer":"{}","code":"13""#, layer)));
+    }
+}
+
+/// 地図APIサーバーに`Accept`ヘッダーを指定してGETリクエストを送信する。
+///
+/// # Arguments
+///
+/// * `app` - 地図APIサーバー。
+/// * `path` - リクエストするパス。
+/// * `accept` - `Accept`ヘッダーの値。
+///
+/// # Returns
+///
+/// レスポンス。
+async fn get_accept(app: &TestApp, path: &str, accept: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .get(format!("{}{}", app.address, path))
+        .header("Accept", accept)
+        .send()
+        .await
+        .expect("リクエストを送信できません。")
+}
+
+#[tokio::test]
+async fn cities_negotiates_csv_and_topojson() {
+    let app = spawn_app().await;
+
+    let response = get_accept(&app, "/cities?properties=code", "text/csv").await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers()["content-type"],
+        "text/csv; charset=utf-8"
+    );
+    assert!(response.headers()["vary"]
+        .to_str()
+        .unwrap()
+        .contains("Accept"));
+    let csv = response.text().await.unwrap();
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some("code,wkt"));
+    assert!(lines.all(|line| line.starts_with("1310") && line.contains("POLYGON")));
+
+    let response = get_accept(&app, "/cities", "application/topo+json").await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["content-type"], "application/topo+json");
+    let topology: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(topology["type"], "Topology");
+    assert_eq!(
+        topology["objects"]["features"]["geometries"]
+            .as_array()
+            .unwrap()
+            .len(),
+        2
+    );
+
+    // formatを指定した場合は、Acceptヘッダーよりもformatを優先する
+    let response = get_accept(&app, "/cities?format=geojson", "text/csv").await;
+    assert_eq!(response.headers()["content-type"], "application/geo+json");
+
+    let response = get_accept(&app, "/cities", "text/html").await;
+    assert_eq!(response.status().as_u16(), 406);
+}
+
+#[tokio::test]
+async fn cities_return_not_modified_until_dataset_is_imported_again() {
+    let app = spawn_app().await;
+    let record_import = || async {
+        sqlx::query("INSERT INTO imports (layer, features) VALUES ('cities', 2)")
+            .execute(&app._database.pool)
+            .await
+            .expect("登録の履歴を記録できません。");
+    };
+    let get_if_none_match = |etag: String| {
+        reqwest::Client::new()
+            .get(format!("{}/cities", app.address))
+            .header("If-None-Match", etag)
+            .send()
+    };
+
+    record_import().await;
+    let response = get_accept(&app, "/cities", "application/geo+json").await;
+    assert_eq!(response.status().as_u16(), 200);
+    let etag = response.headers()["etag"].to_str().unwrap().to_string();
+
+    let response = get_if_none_match(etag.clone()).await.unwrap();
+    assert_eq!(response.status().as_u16(), 304);
+    assert_eq!(response.headers()["etag"].to_str().unwrap(), etag);
+
+    // データセットを登録し直した場合は、ETagが変わる
+    record_import().await;
+    let response = get_if_none_match(etag.clone()).await.unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert_ne!(response.headers()["etag"].to_str().unwrap(), etag);
+}
+
+#[tokio::test]
+async fn cities_compress_streamed_collection() {
+    use std::io::Read;
+
+    let app = spawn_app().await;
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/cities", app.address))
+        .header("Accept-Encoding", "gzip")
+        .send()
+        .await
+        .expect("リクエストを送信できません。");
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["content-encoding"], "gzip");
+    let body = response.bytes().await.unwrap();
+    let mut json = String::new();
+    flate2::read::GzDecoder::new(&body[..])
+        .read_to_string(&mut json)
+        .expect("レスポンスボディをgzipで展開できません。");
+    let fc: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(fc["type"], "FeatureCollection");
+    assert_eq!(fc["numberReturned"], 2);
+}
+
+#[tokio::test]
+async fn cities_export_resumes_with_range_requests() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let url = format!("{}/export/cities.csv", app.address);
+
+    let response = client
+        .get(&url)
+        .header("Accept-Encoding", "gzip")
+        .send()
+        .await
+        .expect("リクエストを送信できません。");
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["accept-ranges"], "bytes");
+    assert!(response.headers().get("content-encoding").is_none());
+    let etag = response.headers()["etag"].to_str().unwrap().to_string();
+    let whole = response.bytes().await.unwrap();
+
+    let response = client
+        .get(&url)
+        .header("Range", "bytes=10-")
+        .header("If-Range", &etag)
+        .send()
+        .await
+        .expect("リクエストを送信できません。");
+    assert_eq!(response.status().as_u16(), 206);
+    assert_eq!(
+        response.headers()["content-range"],
+        format!("bytes 10-{}/{}", whole.len() - 1, whole.len()).as_str()
+    );
+    assert_eq!(response.bytes().await.unwrap(), whole.slice(10..));
+
+    let response = client
+        .get(&url)
+        .header("Range", format!("bytes={}-", whole.len()))
+        .send()
+        .await
+        .expect("リクエストを送信できません。");
+    assert_eq!(response.status().as_u16(), 416);
+    assert_eq!(
+        response.headers()["content-range"],
+        format!("bytes */{}", whole.len()).as_str()
+    );
+}
+
+#[tokio::test]
+async fn cities_export_has_brotli_variant_and_checksums() {
+    use sha2::{Digest, Sha256};
+    use std::io::Read;
+
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let url = format!("{}/export/cities.fgb", app.address);
+
+    let whole = client
+        .get(&url)
+        .send()
+        .await
+        .unwrap()
+        .bytes()
+        .await
+        .unwrap();
+    let response = client
+        .get(&url)
+        .header("Accept-Encoding", "br")
+        .send()
+        .await
+        .expect("リクエストを送信できません。");
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["content-encoding"], "br");
+    let encoded = response.bytes().await.unwrap();
+    let mut decoded = Vec::new();
+    brotli::Decompressor::new(&encoded[..], 4096)
+        .read_to_end(&mut decoded)
+        .expect("レスポンスボディをbrotliで展開できません。");
+    assert_eq!(decoded, whole);
+
+    let manifest = client
+        .get(format!("{}.sha256", url))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(
+        manifest,
+        format!(
+            "{:x}  cities.fgb\n{:x}  cities.fgb.br\n",
+            Sha256::digest(&whole),
+            Sha256::digest(&encoded)
+        )
+    );
+}
+
+#[tokio::test]
+async fn errors_are_returned_as_problem_json() {
+    let app = spawn_app().await;
+
+    for (path, status, code) in [
+        ("/tiles/cities/10/abc/403", 400, "invalid_tile"),
+        ("/tiles/cities/3/100/1", 400, "invalid_tile"),
+        ("/mvt/unknown/10/905/403.pbf", 404, "unknown_layer"),
+    ] {
+        let response = reqwest::get(format!("{}{}", app.address, path))
+            .await
+            .expect("リクエストを送信できません。");
+        assert_eq!(response.status().as_u16(), status);
+        assert_eq!(
+            response.headers()["content-type"],
+            "application/problem+json"
+        );
+        let problem: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(
+            problem["type"],
+            format!("urn:problem:sample-map-server:{}", code)
+        );
+        assert_eq!(problem["status"], status);
+        assert_eq!(problem["code"], code);
+        assert_eq!(problem["instance"], path);
+    }
+}
+
+#[tokio::test]
+async fn tiles_negotiate_vector_tiles() {
+    let app = spawn_app().await;
+
+    let response = get_accept(
+        &app,
+        "/tiles/cities/10/909/403",
+        "application/vnd.mapbox-vector-tile",
+    )
+    .await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers()["content-type"],
+        "application/vnd.mapbox-vector-tile"
+    );
+    assert!(!response.bytes().await.unwrap().is_empty());
+
+    let response = get_accept(&app, "/tiles/cities/10/909/403", "*/*").await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["content-type"], "application/geo+json");
+}
+
+#[tokio::test]
+async fn cities_round_coordinates_to_precision() {
+    let app = spawn_app().await;
+
+    let (status, fc) = get_json(&app, "/cities?precision=0").await;
+    assert_eq!(status, 200);
+    for feature in fc["features"].as_array().unwrap() {
+        for ring in feature["geometry"]["coordinates"].as_array().unwrap() {
+            for position in ring.as_array().unwrap() {
+                for coordinate in position.as_array().unwrap() {
+                    assert_eq!(coordinate.as_f64().unwrap().fract(), 0.0);
+                }
+            }
+        }
+    }
+
+    let (status, _) = get_json(&app, "/cities?precision=16").await;
+    assert_eq!(status, 400);
+}
+
+#[tokio::test]
+async fn post_offices_return_geometry_in_requested_crs() {
+    let app = spawn_app().await;
+
+    // 既定ではWGS84経緯度で返す
+    let response = reqwest::get(format!("{}/post_offices", app.address))
+        .await
+        .expect("リクエストを送信できません。");
+    assert_eq!(
+        response.headers()["content-crs"],
+        "<http://www.opengis.net/def/crs/OGC/1.3/CRS84>"
+    );
+    let fc: serde_json::Value = response.json().await.unwrap();
+    for feature in fc["features"].as_array().unwrap() {
+        let lon = feature["geometry"]["coordinates"][0].as_f64().unwrap();
+        let lat = feature["geometry"]["coordinates"][1].as_f64().unwrap();
+        assert!((139.0..140.0).contains(&lon) && (35.0..36.0).contains(&lat));
+    }
+
+    let response = reqwest::get(format!("{}/post_offices?crs=3857", app.address))
+        .await
+        .expect("リクエストを送信できません。");
+    assert_eq!(
+        response.headers()["content-crs"],
+        "<http://www.opengis.net/def/crs/EPSG/0/3857>"
+    );
+    let fc: serde_json::Value = response.json().await.unwrap();
+    for feature in fc["features"].as_array().unwrap() {
+        let x = feature["geometry"]["coordinates"][0].as_f64().unwrap();
+        assert!(15_000_000.0 < x);
+    }
+
+    let (status, _) = get_json(&app, "/post_offices?crs=6668").await;
+    assert_eq!(status, 400);
+}
+
+#[tokio::test]
+async fn combined_vector_tile_contains_all_layers() {
+    let app = spawn_app().await;
+
+    let response = reqwest::get(format!("{}/tiles/all/10/909/403.pbf", app.address))
+        .await
+        .expect("リクエストを送信できません。");
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers()["content-type"],
+        "application/vnd.mapbox-vector-tile"
+    );
+    let tile = response.bytes().await.unwrap();
+    // レイヤー名は、Mapbox Vector Tileのレイヤーに文字列として格納される
+    for layer in ["prefectures", "cities", "post_offices"] {
+        assert!(tile
+            .windows(layer.len())
+            .any(|window| window == layer.as_bytes()));
+    }
+}
+
+#[tokio::test]
+async fn vector_tiles_are_served_for_every_layer() {
+    let app = spawn_app().await;
+
+    for layer in Layer::all() {
+        for response in [
+            reqwest::get(format!(
+                "{}/mvt/{}/10/909/403.pbf",
+                app.address,
+                layer.name()
+            ))
+            .await
+            .expect("リクエストを送信できません。"),
+            get_accept(
+                &app,
+                &format!("/tiles/{}/10/909/403", layer.name()),
+                "application/vnd.mapbox-vector-tile",
+            )
+            .await,
+        ] {
+            assert_eq!(response.status().as_u16(), 200, "{}", layer.name());
+            assert_eq!(
+                response.headers()["content-type"],
+                "application/vnd.mapbox-vector-tile"
+            );
+        }
+    }
+
+    // フィクスチャーを登録したレイヤーのタイルには、レイヤー名が文字列として格納される
+    for layer in ["prefectures", "cities", "post_offices"] {
+        let tile = reqwest::get(format!("{}/mvt/{}/10/909/403.pbf", app.address, layer))
+            .await
+            .expect("リクエストを送信できません。")
+            .bytes()
+            .await
+            .unwrap();
+        assert!(tile
+            .windows(layer.len())
+            .any(|window| window == layer.as_bytes()));
+    }
+}
+
+#[tokio::test]
+async fn tile_json_describes_prefectures() {
+    let app = spawn_app().await;
+
+    let (status, tile_json) = get_json(&app, "/tiles/prefectures.json").await;
+    assert_eq!(status, 200);
+    assert_eq!(tile_json["name"], "prefectures");
+    assert_eq!(
+        tile_json["tiles"][0],
+        format!("{}/mvt/prefectures/{{z}}/{{x}}/{{y}}.pbf", app.address)
+    );
+    assert_eq!(tile_json["vector_layers"][0]["id"], "prefectures");
+    assert_eq!(
+        tile_json["vector_layers"][0]["fields"],
+        serde_json::json!({ "id": "String", "name": "String" })
+    );
+    // フィクスチャーの東京都は、東経139度付近にある
+    let bounds = tile_json["bounds"].as_array().unwrap();
+    assert!((139.0..141.0).contains(&bounds[0].as_f64().unwrap()));
+}
+
+#[tokio::test]
+async fn city_post_offices_returns_post_offices_in_city() {
+    let app = spawn_app().await;
+
+    let (status, fc) = get_json(&app, "/cities/13101/post_offices").await;
+    assert_eq!(status, 200);
+    assert_eq!(property_values(&fc, "name"), ["千代田郵便局"]);
+    assert_eq!(fc["numberMatched"], 1);
+
+    let (status, _) = get_json(&app, "/cities/99999/post_offices").await;
+    assert_eq!(status, 404);
+}
+
+#[tokio::test]
+async fn measure_returns_area_and_perimeter() {
+    let app = spawn_app().await;
+
+    let (status, city) = get_json(&app, "/cities/13101/measure").await;
+    assert_eq!(status, 200);
+    assert_eq!(city["code"], "13101");
+    assert!(0.0 < city["areaKm2"].as_f64().unwrap());
+    assert!(0.0 < city["perimeterKm"].as_f64().unwrap());
+
+    // 都道府県の面積は、都道府県に含まれる市区町村の面積以上になる
+    let (status, prefecture) = get_json(&app, "/prefectures/13/measure").await;
+    assert_eq!(status, 200);
+    assert!(city["areaKm2"].as_f64().unwrap() <= prefecture["areaKm2"].as_f64().unwrap());
+
+    let (status, _) = get_json(&app, "/cities/99999/measure").await;
+    assert_eq!(status, 404);
+}
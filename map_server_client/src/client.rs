@@ -0,0 +1,900 @@
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT_LANGUAGE, RANGE};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::errors::{ApiError, ClientError};
+use crate::models::{
+    CityProperties, CityQuery, Collection, Collections, Feature, FeatureCollection, Imports, Items,
+    ItemsQuery, Layers, Location, Measure, MedicalInstitutionProperties, MedicalInstitutionQuery,
+    MedicalInstitutionTileFilter, NearestPostOfficeProperties, PostOfficeProperties,
+    PostOfficeQuery, PostOfficeTileFilter, PostalCodeProperties, PrefectureProperties,
+    PrefectureQuery, RailwayProperties, RailwayQuery, Readiness, ReverseGeocode, RiverProperties,
+    RiverQuery, RiverTileFilter, SchoolProperties, SchoolQuery, SchoolTileFilter, SearchProperties,
+    StationProperties, StationQuery, Stats, TileJson,
+};
+
+/// APIキーを指定するヘッダーの名前。
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// 郵便局をKMLまたはGPXで取得する条件。
+#[derive(Serialize)]
+struct WaypointQuery<'a> {
+    /// 郵便局を取得する条件
+    #[serde(flatten)]
+    query: &'a PostOfficeQuery,
+    /// レスポンスの形式(`kml`または`gpx`)
+    format: &'static str,
+}
+
+/// 地図APIサーバーのクライアント。
+#[derive(Debug, Clone)]
+pub struct Client {
+    /// 地図APIサーバーのURL(末尾のスラッシュを除く)
+    base_url: String,
+    /// HTTPクライアント
+    http: reqwest::Client,
+    /// リクエストに付与するヘッダー
+    headers: HeaderMap,
+}
+
+impl Client {
+    /// 地図APIサーバーのクライアントを構築する。
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - 地図APIサーバーのURL(例: `http://localhost:8080`)。
+    ///
+    /// # Returns
+    ///
+    /// クライアント。
+    pub fn new(base_url: &str) -> Self {
+        Self::with_http_client(base_url, reqwest::Client::new())
+    }
+
+    /// 指定したHTTPクライアントを使用する、地図APIサーバーのクライアントを構築する。
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - 地図APIサーバーのURL(例: `http://localhost:8080`)。
+    /// * `http` - HTTPクライアント。
+    ///
+    /// # Returns
+    ///
+    /// クライアント。
+    pub fn with_http_client(base_url: &str, http: reqwest::Client) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            http,
+            headers: HeaderMap::new(),
+        }
+    }
+
+    /// リクエストにAPIキーを付与する。
+    ///
+    /// # Arguments
+    ///
+    /// * `api_key` - APIキー。
+    ///
+    /// # Returns
+    ///
+    /// クライアント。
+    pub fn api_key(mut self, api_key: &str) -> Self {
+        let value =
+            HeaderValue::from_str(api_key).expect("APIキーに使用できない文字が含まれています。");
+        self.headers.insert(API_KEY_HEADER, value);
+        self
+    }
+
+    /// エラーメッセージの言語を指定する。
+    ///
+    /// # Arguments
+    ///
+    /// * `language` - Accept-Languageヘッダーの値(例: `ja`)。
+    ///
+    /// # Returns
+    ///
+    /// クライアント。
+    pub fn language(mut self, language: &str) -> Self {
+        let value =
+            HeaderValue::from_str(language).expect("言語に使用できない文字が含まれています。");
+        self.headers.insert(ACCEPT_LANGUAGE, value);
+        self
+    }
+
+    /// GETリクエストを送信して、成功した場合はレスポンスを返す。
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - リクエストするパス。
+    /// * `query` - クエリパラメーター。
+    ///
+    /// # Returns
+    ///
+    /// レスポンス。地図APIサーバーがエラーを返した場合は、エラーの内容。
+    async fn get<Q: Serialize + ?Sized>(
+        &self,
+        path: &str,
+        query: &Q,
+    ) -> Result<reqwest::Response, ClientError> {
+        let request = self
+            .http
+            .get(format!("{}{}", self.base_url, path))
+            .headers(self.headers.clone())
+            .query(query);
+
+        self.send(request).await
+    }
+
+    /// バイトの範囲を指定したGETリクエストを送信して、範囲の内容を返す。
+    ///
+    /// 地図APIサーバーが範囲の指定を無視してファイル全体を返した場合は、ファイル全体から範囲を切り出す。
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - リクエストするパス。
+    /// * `start` - 範囲の最初の位置。
+    /// * `end` - 範囲の最後の位置(範囲に含む)。ファイルの末尾までの場合はNone。
+    ///
+    /// # Returns
+    ///
+    /// 範囲の内容。
+    async fn get_range(
+        &self,
+        path: &str,
+        start: u64,
+        end: Option<u64>,
+    ) -> Result<Vec<u8>, ClientError> {
+        let range = match end {
+            Some(end) => format!("bytes={}-{}", start, end),
+            None => format!("bytes={}-", start),
+        };
+        let request = self
+            .http
+            .get(format!("{}{}", self.base_url, path))
+            .headers(self.headers.clone())
+            .header(RANGE, range);
+        let response = self.send(request).await?;
+        let partial = response.status() == StatusCode::PARTIAL_CONTENT;
+        let bytes = response.bytes().await?;
+        if partial {
+            return Ok(bytes.to_vec());
+        }
+        let start = (start as usize).min(bytes.len());
+        let end = end.map_or(bytes.len(), |end| (end as usize + 1).min(bytes.len()));
+
+        Ok(bytes[start..end.max(start)].to_vec())
+    }
+
+    /// リクエストを送信して、成功した場合はレスポンスを返す。
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - リクエスト。
+    ///
+    /// # Returns
+    ///
+    /// レスポンス。地図APIサーバーがエラーを返した場合は、エラーの内容。
+    async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, ClientError> {
+        let response = request.send().await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let body = response.text().await?;
+
+        Err(ClientError::Api(ApiError::from_response(
+            status.as_u16(),
+            &body,
+        )))
+    }
+
+    /// GETリクエストを送信して、レスポンスボディのJSONを読み込む。
+    async fn get_json<T: DeserializeOwned, Q: Serialize + ?Sized>(
+        &self,
+        path: &str,
+        query: &Q,
+    ) -> Result<T, ClientError> {
+        Ok(self.get(path, query).await?.json().await?)
+    }
+
+    /// レディネスチェックの結果を取得する。
+    ///
+    /// 地図APIサーバーが利用できない場合は、503 Service Unavailableを示すエラーを返す。
+    pub async fn readiness(&self) -> Result<Readiness, ClientError> {
+        self.get_json("/health/ready", &()).await
+    }
+
+    /// 都道府県を取得する。
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - 都道府県を取得する条件。
+    ///
+    /// # Returns
+    ///
+    /// 都道府県のフィーチャーコレクション。
+    pub async fn prefectures(
+        &self,
+        query: &PrefectureQuery,
+    ) -> Result<FeatureCollection<PrefectureProperties>, ClientError> {
+        self.get_json("/prefectures", query).await
+    }
+
+    /// 市区町村を取得する。
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - 市区町村を取得する条件。
+    ///
+    /// # Returns
+    ///
+    /// 市区町村のフィーチャーコレクション。
+    pub async fn cities(
+        &self,
+        query: &CityQuery,
+    ) -> Result<FeatureCollection<CityProperties>, ClientError> {
+        self.get_json("/cities", query).await
+    }
+
+    /// 郵便局を取得する。
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - 郵便局を取得する条件。
+    ///
+    /// # Returns
+    ///
+    /// 郵便局のフィーチャーコレクション。
+    pub async fn post_offices(
+        &self,
+        query: &PostOfficeQuery,
+    ) -> Result<FeatureCollection<PostOfficeProperties>, ClientError> {
+        self.get_json("/post_offices", query).await
+    }
+
+    /// 鉄道路線を取得する。
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - 鉄道路線を取得する条件。
+    ///
+    /// # Returns
+    ///
+    /// 鉄道路線のフィーチャーコレクション。
+    pub async fn railways(
+        &self,
+        query: &RailwayQuery,
+    ) -> Result<FeatureCollection<RailwayProperties>, ClientError> {
+        self.get_json("/railways", query).await
+    }
+
+    /// 駅を取得する。
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - 駅を取得する条件。
+    ///
+    /// # Returns
+    ///
+    /// 駅のフィーチャーコレクション。
+    pub async fn stations(
+        &self,
+        query: &StationQuery,
+    ) -> Result<FeatureCollection<StationProperties>, ClientError> {
+        self.get_json("/stations", query).await
+    }
+
+    /// 学校を取得する。
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - 学校を取得する条件。
+    ///
+    /// # Returns
+    ///
+    /// 学校のフィーチャーコレクション。
+    pub async fn schools(
+        &self,
+        query: &SchoolQuery,
+    ) -> Result<FeatureCollection<SchoolProperties>, ClientError> {
+        self.get_json("/schools", query).await
+    }
+
+    /// 医療機関を取得する。
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - 医療機関を取得する条件。
+    ///
+    /// # Returns
+    ///
+    /// 医療機関のフィーチャーコレクション。
+    pub async fn medical_institutions(
+        &self,
+        query: &MedicalInstitutionQuery,
+    ) -> Result<FeatureCollection<MedicalInstitutionProperties>, ClientError> {
+        self.get_json("/medical_institutions", query).await
+    }
+
+    /// 河川を取得する。
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - 河川を取得する条件。
+    ///
+    /// # Returns
+    ///
+    /// 河川のフィーチャーコレクション。
+    pub async fn rivers(
+        &self,
+        query: &RiverQuery,
+    ) -> Result<FeatureCollection<RiverProperties>, ClientError> {
+        self.get_json("/rivers", query).await
+    }
+
+    /// 指定した位置から近い順に郵便局を取得する。
+    ///
+    /// # Arguments
+    ///
+    /// * `lon` - 経度(WGS84)。
+    /// * `lat` - 緯度(WGS84)。
+    /// * `n` - 取得する郵便局の件数。Noneの場合は5件。
+    ///
+    /// # Returns
+    ///
+    /// 近い順に並べた、距離(メートル)を含む郵便局のフィーチャーコレクション。
+    pub async fn nearest_post_offices(
+        &self,
+        lon: f64,
+        lat: f64,
+        n: Option<u32>,
+    ) -> Result<FeatureCollection<NearestPostOfficeProperties>, ClientError> {
+        let mut query = vec![("lon", lon.to_string()), ("lat", lat.to_string())];
+        if let Some(n) = n {
+            query.push(("n", n.to_string()));
+        }
+
+        self.get_json("/post_offices/nearest", &query).await
+    }
+
+    /// 市区町村の郵便局を取得する。
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - 市区町村コード。
+    /// * `limit` - 取得する郵便局の件数。Noneの場合は既定の件数。
+    /// * `offset` - 取得を開始する郵便局の位置。Noneの場合は先頭から取得する。
+    ///
+    /// # Returns
+    ///
+    /// 市区町村コードが一致するか、市区町村の区域に含まれる郵便局のフィーチャーコレクション。
+    pub async fn city_post_offices(
+        &self,
+        code: &str,
+        limit: Option<u32>,
+        offset: Option<u32>,
+    ) -> Result<FeatureCollection<PostOfficeProperties>, ClientError> {
+        let mut query = Vec::new();
+        if let Some(limit) = limit {
+            query.push(("limit", limit.to_string()));
+        }
+        if let Some(offset) = offset {
+            query.push(("offset", offset.to_string()));
+        }
+        let path = format!("/cities/{}/post_offices", code);
+
+        self.get_json(&path, &query).await
+    }
+
+    /// 郵便局を、名前と住所を持つプレースマークを格納したKMLドキュメントとして取得する。
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - 郵便局を取得する条件。
+    ///
+    /// # Returns
+    ///
+    /// KMLドキュメント。
+    pub async fn post_offices_kml(&self, query: &PostOfficeQuery) -> Result<String, ClientError> {
+        let query = WaypointQuery {
+            query,
+            format: "kml",
+        };
+
+        Ok(self.get("/post_offices", &query).await?.text().await?)
+    }
+
+    /// 郵便局を、名前と住所を持つウェイポイントを格納したGPXドキュメントとして取得する。
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - 郵便局を取得する条件。
+    ///
+    /// # Returns
+    ///
+    /// GPXドキュメント。
+    pub async fn post_offices_gpx(&self, query: &PostOfficeQuery) -> Result<String, ClientError> {
+        let query = WaypointQuery {
+            query,
+            format: "gpx",
+        };
+
+        Ok(self.get("/post_offices", &query).await?.text().await?)
+    }
+
+    /// 郵便番号の町域を検索する。
+    ///
+    /// # Arguments
+    ///
+    /// * `postal` - 郵便番号(`500-8570`または`5008570`形式)。
+    ///
+    /// # Returns
+    ///
+    /// 登録されている市区町村に含まれる町域のフィーチャーコレクション。
+    pub async fn search_postal_code(
+        &self,
+        postal: &str,
+    ) -> Result<FeatureCollection<PostalCodeProperties>, ClientError> {
+        self.get_json("/search", &[("postal", postal)]).await
+    }
+
+    /// 市区町村と郵便局を名前で検索する。
+    ///
+    /// # Arguments
+    ///
+    /// * `q` - 名前に含まれる文字列。
+    /// * `layer` - 検索するレイヤー(`cities`または`post_offices`)。Noneの場合は両方を検索する。
+    ///
+    /// # Returns
+    ///
+    /// 類似度が高い順に並べた、重心をジオメトリとするフィーチャーコレクション。
+    pub async fn search_name(
+        &self,
+        q: &str,
+        layer: Option<&str>,
+    ) -> Result<FeatureCollection<SearchProperties>, ClientError> {
+        let mut query = vec![("q", q)];
+        if let Some(layer) = layer {
+            query.push(("layer", layer));
+        }
+
+        self.get_json("/search", &query).await
+    }
+
+    /// 位置を含む都道府県と市区町村を取得する。
+    ///
+    /// # Arguments
+    ///
+    /// * `lon` - 経度(WGS84)。
+    /// * `lat` - 緯度(WGS84)。
+    ///
+    /// # Returns
+    ///
+    /// 位置を含む都道府県と市区町村。
+    pub async fn locate(&self, lon: f64, lat: f64) -> Result<Location, ClientError> {
+        self.get_json("/locate", &[("lon", lon), ("lat", lat)])
+            .await
+    }
+
+    /// 位置を含む都道府県と市区町村、及び位置から最も近い郵便局を取得する。
+    ///
+    /// # Arguments
+    ///
+    /// * `lon` - 経度(WGS84)。
+    /// * `lat` - 緯度(WGS84)。
+    ///
+    /// # Returns
+    ///
+    /// 逆ジオコーディングの結果。
+    pub async fn reverse_geocode(&self, lon: f64, lat: f64) -> Result<ReverseGeocode, ClientError> {
+        self.get_json("/reverse_geocode", &[("lon", lon), ("lat", lat)])
+            .await
+    }
+
+    /// 都道府県ごとの市区町村の数、郵便局の数及び面積を取得する。
+    pub async fn stats(&self) -> Result<Stats, ClientError> {
+        self.get_json("/stats", &()).await
+    }
+
+    /// 都道府県の面積と周囲長を取得する。
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - 都道府県コード。
+    ///
+    /// # Returns
+    ///
+    /// 都道府県の面積と周囲長。
+    pub async fn measure_prefecture(&self, code: &str) -> Result<Measure, ClientError> {
+        let path = format!("/prefectures/{}/measure", code);
+
+        self.get_json(&path, &()).await
+    }
+
+    /// 市区町村の面積と周囲長を取得する。
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - 市区町村コード。
+    ///
+    /// # Returns
+    ///
+    /// 市区町村の面積と周囲長。
+    pub async fn measure_city(&self, code: &str) -> Result<Measure, ClientError> {
+        let path = format!("/cities/{}/measure", code);
+
+        self.get_json(&path, &()).await
+    }
+
+    /// タイルの範囲のフィーチャーをGeoJSONで取得する。
+    ///
+    /// # Arguments
+    ///
+    /// * `layer` - レイヤー名(`prefectures`、`cities`、`post_offices`、`railways`、`stations`、`schools`、
+    ///   `medical_institutions`、`rivers`またはカスタムレイヤー)。
+    /// * `zoom` - ズームレベル。
+    /// * `x` - タイルのX座標。
+    /// * `y` - タイルのY座標。
+    ///
+    /// # Returns
+    ///
+    /// タイルの範囲のフィーチャーコレクション。
+    pub async fn tile(
+        &self,
+        layer: &str,
+        zoom: u8,
+        x: u32,
+        y: u32,
+    ) -> Result<geojson::FeatureCollection, ClientError> {
+        let path = format!("/tiles/{}/{}/{}/{}", layer, zoom, x, y);
+
+        self.get_json(&path, &()).await
+    }
+
+    /// Mapbox Vector Tileを取得する。
+    ///
+    /// # Arguments
+    ///
+    /// * `layer` - レイヤー名(`cities`、`post_offices`、`railways`、`stations`、`schools`、
+    ///   `medical_institutions`、`rivers`またはカスタムレイヤー)。
+    /// * `zoom` - ズームレベル。
+    /// * `x` - タイルのX座標。
+    /// * `y` - タイルのY座標。
+    ///
+    /// # Returns
+    ///
+    /// Mapbox Vector Tileのバイト列。
+    pub async fn vector_tile(
+        &self,
+        layer: &str,
+        zoom: u8,
+        x: u32,
+        y: u32,
+    ) -> Result<Vec<u8>, ClientError> {
+        let path = format!("/mvt/{}/{}/{}/{}.pbf", layer, zoom, x, y);
+
+        Ok(self.get(&path, &()).await?.bytes().await?.to_vec())
+    }
+
+    /// 都道府県、市区町村及び郵便局のレイヤーを1つにまとめたMapbox Vector Tileを取得する。
+    ///
+    /// # Arguments
+    ///
+    /// * `zoom` - ズームレベル。
+    /// * `x` - タイルのX座標。
+    /// * `y` - タイルのY座標。
+    ///
+    /// # Returns
+    ///
+    /// Mapbox Vector Tileのバイト列。
+    pub async fn combined_vector_tile(
+        &self,
+        zoom: u8,
+        x: u32,
+        y: u32,
+    ) -> Result<Vec<u8>, ClientError> {
+        let path = format!("/tiles/all/{}/{}/{}.pbf", zoom, x, y);
+
+        Ok(self.get(&path, &()).await?.bytes().await?.to_vec())
+    }
+
+    /// レイヤーのすべてのフィーチャーを、FlatGeobuf形式で取得する。
+    ///
+    /// # Arguments
+    ///
+    /// * `layer` - レイヤー名(`prefectures`、`cities`または`post_offices`)。
+    ///
+    /// # Returns
+    ///
+    /// FlatGeobuf形式のバイト列。
+    pub async fn export_flatgeobuf(&self, layer: &str) -> Result<Vec<u8>, ClientError> {
+        let path = format!("/export/{}.fgb", layer);
+
+        Ok(self.get(&path, &()).await?.bytes().await?.to_vec())
+    }
+
+    /// レイヤーのすべてのフィーチャーの属性を、CSV形式で取得する。
+    ///
+    /// # Arguments
+    ///
+    /// * `layer` - レイヤー名(`prefectures`、`cities`または`post_offices`)。
+    ///
+    /// # Returns
+    ///
+    /// CSV形式の文字列(先頭にバイトオーダーマークを含む)。
+    pub async fn export_csv(&self, layer: &str) -> Result<String, ClientError> {
+        let path = format!("/export/{}.csv", layer);
+
+        Ok(self.get(&path, &()).await?.text().await?)
+    }
+
+    /// レイヤーのエクスポートしたファイルの、SHA-256のマニフェストを取得する。
+    ///
+    /// # Arguments
+    ///
+    /// * `layer` - レイヤー名(`prefectures`、`cities`または`post_offices`)。
+    /// * `extension` - ファイルの拡張子(`fgb`または`csv`)。
+    ///
+    /// # Returns
+    ///
+    /// `sha256sum`の出力と同じ形式のマニフェスト。
+    pub async fn export_checksums(
+        &self,
+        layer: &str,
+        extension: &str,
+    ) -> Result<String, ClientError> {
+        let path = format!("/export/{}.{}.sha256", layer, extension);
+
+        Ok(self.get(&path, &()).await?.text().await?)
+    }
+
+    /// レイヤーのエクスポートしたファイルを、指定した位置から末尾まで取得する。
+    ///
+    /// 中断したダウンロードを再開するときに、受信済みの大きさを指定する。
+    ///
+    /// # Arguments
+    ///
+    /// * `layer` - レイヤー名(`prefectures`、`cities`または`post_offices`)。
+    /// * `extension` - ファイルの拡張子(`fgb`または`csv`)。
+    /// * `offset` - 取得を始める位置。
+    ///
+    /// # Returns
+    ///
+    /// 指定した位置から末尾までのバイト列。
+    pub async fn resume_export(
+        &self,
+        layer: &str,
+        extension: &str,
+        offset: u64,
+    ) -> Result<Vec<u8>, ClientError> {
+        let path = format!("/export/{}.{}", layer, extension);
+
+        self.get_range(&path, offset, None).await
+    }
+
+    /// PMTilesのアーカイブから配信するレイヤーの、アーカイブのファイルの範囲を取得する。
+    ///
+    /// # Arguments
+    ///
+    /// * `layer` - レイヤー名。
+    /// * `offset` - 範囲の最初の位置。
+    /// * `length` - 範囲の大きさ(1以上)。
+    ///
+    /// # Returns
+    ///
+    /// 範囲のバイト列。
+    pub async fn pmtiles_range(
+        &self,
+        layer: &str,
+        offset: u64,
+        length: u64,
+    ) -> Result<Vec<u8>, ClientError> {
+        let path = format!("/pmtiles/{}.pmtiles", layer);
+
+        self.get_range(&path, offset, Some(offset + length.max(1) - 1))
+            .await
+    }
+
+    /// 分類で絞り込んだ郵便局のMapbox Vector Tileを取得する。
+    ///
+    /// # Arguments
+    ///
+    /// * `zoom` - ズームレベル。
+    /// * `x` - タイルのX座標。
+    /// * `y` - タイルのY座標。
+    /// * `filter` - タイルに含める郵便局の分類。
+    ///
+    /// # Returns
+    ///
+    /// Mapbox Vector Tileのバイト列。
+    pub async fn post_office_vector_tile(
+        &self,
+        zoom: u8,
+        x: u32,
+        y: u32,
+        filter: &PostOfficeTileFilter,
+    ) -> Result<Vec<u8>, ClientError> {
+        let path = format!("/mvt/post_offices/{}/{}/{}.pbf", zoom, x, y);
+
+        Ok(self.get(&path, filter).await?.bytes().await?.to_vec())
+    }
+
+    /// 分類で絞り込んだ学校のMapbox Vector Tileを取得する。
+    ///
+    /// # Arguments
+    ///
+    /// * `zoom` - ズームレベル。
+    /// * `x` - タイルのX座標。
+    /// * `y` - タイルのY座標。
+    /// * `filter` - タイルに含める学校の分類。
+    ///
+    /// # Returns
+    ///
+    /// Mapbox Vector Tileのバイト列。
+    pub async fn school_vector_tile(
+        &self,
+        zoom: u8,
+        x: u32,
+        y: u32,
+        filter: &SchoolTileFilter,
+    ) -> Result<Vec<u8>, ClientError> {
+        let path = format!("/mvt/schools/{}/{}/{}.pbf", zoom, x, y);
+
+        Ok(self.get(&path, filter).await?.bytes().await?.to_vec())
+    }
+
+    /// 分類で絞り込んだ医療機関のMapbox Vector Tileを取得する。
+    ///
+    /// # Arguments
+    ///
+    /// * `zoom` - ズームレベル。
+    /// * `x` - タイルのX座標。
+    /// * `y` - タイルのY座標。
+    /// * `filter` - タイルに含める医療機関の分類。
+    ///
+    /// # Returns
+    ///
+    /// Mapbox Vector Tileのバイト列。
+    pub async fn medical_institution_vector_tile(
+        &self,
+        zoom: u8,
+        x: u32,
+        y: u32,
+        filter: &MedicalInstitutionTileFilter,
+    ) -> Result<Vec<u8>, ClientError> {
+        let path = format!("/mvt/medical_institutions/{}/{}/{}.pbf", zoom, x, y);
+
+        Ok(self.get(&path, filter).await?.bytes().await?.to_vec())
+    }
+
+    /// 区間種別で絞り込んだ河川のMapbox Vector Tileを取得する。
+    ///
+    /// # Arguments
+    ///
+    /// * `zoom` - ズームレベル。
+    /// * `x` - タイルのX座標。
+    /// * `y` - タイルのY座標。
+    /// * `filter` - タイルに含める河川の区間種別。
+    ///
+    /// # Returns
+    ///
+    /// Mapbox Vector Tileのバイト列。
+    pub async fn river_vector_tile(
+        &self,
+        zoom: u8,
+        x: u32,
+        y: u32,
+        filter: &RiverTileFilter,
+    ) -> Result<Vec<u8>, ClientError> {
+        let path = format!("/mvt/rivers/{}/{}/{}.pbf", zoom, x, y);
+
+        Ok(self.get(&path, filter).await?.bytes().await?.to_vec())
+    }
+
+    /// レイヤーのTileJSONを取得する。
+    ///
+    /// # Arguments
+    ///
+    /// * `layer` - レイヤー名(`cities`、`post_offices`、`railways`、`stations`、`schools`、
+    ///   `medical_institutions`、`rivers`またはカスタムレイヤー)。
+    ///
+    /// # Returns
+    ///
+    /// TileJSON。
+    pub async fn tile_json(&self, layer: &str) -> Result<TileJson, ClientError> {
+        self.get_json(&format!("/tiles/{}.json", layer), &()).await
+    }
+
+    /// 配信しているレイヤーの一覧とメタデータを取得する。
+    pub async fn layers(&self) -> Result<Layers, ClientError> {
+        self.get_json("/layers", &()).await
+    }
+
+    /// データセットを登録した履歴を、新しい順に取得する。
+    ///
+    /// # Arguments
+    ///
+    /// * `layer` - 履歴を取得するレイヤー名。Noneの場合はすべてのレイヤーの履歴を取得する。
+    /// * `code` - 履歴を取得する都道府県コード。Noneの場合はすべての都道府県の履歴を取得する。
+    ///
+    /// # Returns
+    ///
+    /// データセットを登録した履歴。
+    pub async fn imports(
+        &self,
+        layer: Option<&str>,
+        code: Option<&str>,
+    ) -> Result<Imports, ClientError> {
+        let mut query = Vec::new();
+        if let Some(layer) = layer {
+            query.push(("layer", layer));
+        }
+        if let Some(code) = code {
+            query.push(("code", code));
+        }
+
+        self.get_json("/imports", &query).await
+    }
+
+    /// OGC API - Featuresのフィーチャーコレクションの一覧を取得する。
+    pub async fn collections(&self) -> Result<Collections, ClientError> {
+        self.get_json("/collections", &()).await
+    }
+
+    /// OGC API - Featuresのフィーチャーコレクションの情報を取得する。
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - フィーチャーコレクションのID。
+    ///
+    /// # Returns
+    ///
+    /// フィーチャーコレクションの情報。
+    pub async fn collection(&self, id: &str) -> Result<Collection, ClientError> {
+        self.get_json(&format!("/collections/{}", id), &()).await
+    }
+
+    /// OGC API - Featuresのフィーチャーの一覧を取得する。
+    ///
+    /// プロパティの型には、フィーチャーコレクションに応じて`PrefectureProperties`などを指定する。
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - フィーチャーコレクションのID。
+    /// * `query` - フィーチャーを取得する条件。
+    ///
+    /// # Returns
+    ///
+    /// フィーチャーの一覧。
+    pub async fn items<P: DeserializeOwned>(
+        &self,
+        id: &str,
+        query: &ItemsQuery,
+    ) -> Result<Items<P>, ClientError> {
+        self.get_json(&format!("/collections/{}/items", id), query)
+            .await
+    }
+
+    /// OGC API - Featuresのフィーチャーを取得する。
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - フィーチャーコレクションのID。
+    /// * `feature_id` - フィーチャーのID。
+    ///
+    /// # Returns
+    ///
+    /// フィーチャー。
+    pub async fn item<P: DeserializeOwned>(
+        &self,
+        id: &str,
+        feature_id: &str,
+    ) -> Result<Feature<P>, ClientError> {
+        self.get_json(&format!("/collections/{}/items/{}", id, feature_id), &())
+            .await
+    }
+}
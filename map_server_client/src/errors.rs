@@ -0,0 +1,91 @@
+use serde::Deserialize;
+
+/// 地図APIサーバーが返したエラーのレスポンスボディ(RFC 7807の`application/problem+json`)。
+#[derive(Debug, Clone, Deserialize)]
+struct ErrorBody {
+    /// エラーコード
+    code: String,
+    /// 英語のメッセージ
+    title: String,
+    /// Accept-Languageヘッダーで指定した言語のメッセージ
+    detail: Option<String>,
+}
+
+/// 地図APIサーバーが返したエラー。
+#[derive(Debug, Clone)]
+pub struct ApiError {
+    /// HTTPステータスコード
+    pub status: u16,
+    /// エラーコード(レスポンスボディがエラーのJSONでない場合は`unknown`)
+    pub code: String,
+    /// 英語のメッセージ(レスポンスボディがエラーのJSONでない場合はレスポンスボディ)
+    pub message: String,
+    /// Accept-Languageヘッダーで指定した言語のメッセージ(英語のメッセージと異なる場合)
+    pub localized_message: Option<String>,
+}
+
+impl ApiError {
+    /// ステータスコードとレスポンスボディから、エラーを構築する。
+    ///
+    /// # Arguments
+    ///
+    /// * `status` - HTTPステータスコード。
+    /// * `body` - レスポンスボディ。
+    ///
+    /// # Returns
+    ///
+    /// エラー。
+    pub fn from_response(status: u16, body: &str) -> Self {
+        match serde_json::from_str::<ErrorBody>(body) {
+            Ok(body) => Self {
+                status,
+                code: body.code,
+                localized_message: body.detail.filter(|detail| *detail != body.title),
+                message: body.title,
+            },
+            Err(_) => Self {
+                status,
+                code: "unknown".to_string(),
+                message: body.to_string(),
+                localized_message: None,
+            },
+        }
+    }
+}
+
+/// クライアントのエラー。
+#[derive(Debug)]
+pub enum ClientError {
+    /// リクエストを送信できないか、レスポンスを読み込めない
+    Http(reqwest::Error),
+    /// 地図APIサーバーがエラーを返した
+    Api(ApiError),
+}
+
+impl std::fmt::Display for ClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClientError::Http(e) => write!(f, "地図APIサーバーと通信できません。{}", e),
+            ClientError::Api(e) => write!(
+                f,
+                "地図APIサーバーがエラーを返しました。{} {}: {}",
+                e.status, e.code, e.message
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ClientError::Http(e) => Some(e),
+            ClientError::Api(_) => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ClientError {
+    fn from(e: reqwest::Error) -> Self {
+        ClientError::Http(e)
+    }
+}
@@ -0,0 +1,8 @@
+//! 郵便局地図APIサーバーのクライアント。
+
+pub mod client;
+pub mod errors;
+pub mod models;
+
+pub use client::Client;
+pub use errors::{ApiError, ClientError};
@@ -0,0 +1,880 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// GeoJSONのフィーチャーコレクション。
+#[derive(Debug, Clone, Deserialize)]
+pub struct FeatureCollection<P> {
+    /// 条件に一致したフィーチャーの件数(ページに分けて返すエンドポイントの場合)
+    #[serde(default, rename = "numberMatched")]
+    pub number_matched: Option<u64>,
+    /// 返されたフィーチャーの件数(ページに分けて返すエンドポイントの場合)
+    #[serde(default, rename = "numberReturned")]
+    pub number_returned: Option<u64>,
+    /// フィーチャー
+    pub features: Vec<Feature<P>>,
+}
+
+/// GeoJSONのフィーチャー。
+#[derive(Debug, Clone, Deserialize)]
+pub struct Feature<P> {
+    /// フィーチャーのID
+    #[serde(default)]
+    pub id: Option<serde_json::Value>,
+    /// ジオメトリ
+    pub geometry: Option<geojson::Geometry>,
+    /// プロパティ
+    pub properties: P,
+}
+
+/// 都道府県のプロパティ。
+#[derive(Debug, Clone, Deserialize)]
+pub struct PrefectureProperties {
+    /// ID
+    pub id: String,
+    /// 都道府県名
+    pub name: String,
+}
+
+/// 市区町村のプロパティ。
+#[derive(Debug, Clone, Deserialize)]
+pub struct CityProperties {
+    /// ID
+    pub id: String,
+    /// 市区町村コード
+    pub code: String,
+    /// 郡・政令市名
+    pub area: Option<String>,
+    /// 市区町村名
+    pub name: String,
+}
+
+/// 郵便局のプロパティ。
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostOfficeProperties {
+    /// ID
+    pub id: String,
+    /// 市区町村コード
+    pub city_code: String,
+    /// 分類コード
+    pub category_code: String,
+    /// 細分類コード
+    pub subcategory_code: String,
+    /// 郵便局コード
+    pub post_office_code: String,
+    /// 郵便局名
+    pub name: String,
+    /// 住所
+    pub address: String,
+    /// 住所の市区町村名
+    pub city_name: Option<String>,
+    /// 住所の町域名
+    pub town: Option<String>,
+    /// 住所の番地
+    pub block: Option<String>,
+}
+
+/// 鉄道路線のプロパティ。
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RailwayProperties {
+    /// ID
+    pub id: String,
+    /// 鉄道区分コード
+    pub railway_type: String,
+    /// 事業者種別コード
+    pub operator_type: String,
+    /// 路線名
+    pub line_name: String,
+    /// 運営会社
+    pub operator: String,
+}
+
+/// 駅のプロパティ。
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StationProperties {
+    /// ID
+    pub id: String,
+    /// 鉄道区分コード
+    pub railway_type: String,
+    /// 事業者種別コード
+    pub operator_type: String,
+    /// 路線名
+    pub line_name: String,
+    /// 運営会社
+    pub operator: String,
+    /// 駅名
+    pub name: String,
+}
+
+/// 学校のプロパティ。
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchoolProperties {
+    /// ID
+    pub id: String,
+    /// 市区町村コード
+    pub city_code: String,
+    /// 学校コード
+    pub school_code: String,
+    /// 学校分類コード
+    pub category_code: String,
+    /// 学校名
+    pub name: String,
+    /// 所在地
+    pub address: String,
+    /// 管理者コード
+    pub administrator_code: Option<String>,
+}
+
+/// 医療機関のプロパティ。
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MedicalInstitutionProperties {
+    /// ID
+    pub id: String,
+    /// 都道府県コード
+    pub prefecture_code: String,
+    /// 医療機関分類(`1`: 病院、`2`: 診療所、`3`: 歯科診療所)
+    pub institution_type: String,
+    /// 施設名
+    pub name: String,
+    /// 所在地
+    pub address: String,
+    /// 空白で区切った診療科目
+    pub departments: Option<String>,
+}
+
+/// 河川のプロパティ。
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RiverProperties {
+    /// ID
+    pub id: String,
+    /// 都道府県コード
+    pub prefecture_code: String,
+    /// 水系域コード
+    pub water_system_code: String,
+    /// 河川コード
+    pub river_code: String,
+    /// 区間種別コード
+    pub section_type: String,
+    /// 河川名
+    pub name: Option<String>,
+}
+
+/// 最寄りの郵便局のプロパティ。
+#[derive(Debug, Clone, Deserialize)]
+pub struct NearestPostOfficeProperties {
+    /// 郵便局のプロパティ
+    #[serde(flatten)]
+    pub post_office: PostOfficeProperties,
+    /// 指定した位置からの距離(メートル)
+    pub distance: f64,
+}
+
+/// 郵便番号の町域のプロパティ。
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostalCodeProperties {
+    /// 郵便番号(7桁)
+    pub postal_code: String,
+    /// 市区町村コード
+    pub city_code: String,
+    /// 都道府県名
+    pub prefecture_name: String,
+    /// 市区町村名
+    pub city_name: String,
+    /// 町域名(市区町村全体の場合は空文字列)
+    pub town: String,
+    /// 郵便番号が一致する郵便局
+    pub post_offices: Vec<PostalCodePostOffice>,
+}
+
+/// 名前で検索した市区町村または郵便局のプロパティ。
+#[derive(Debug, Clone, Deserialize)]
+pub struct SearchProperties {
+    /// レイヤー名(`cities`または`post_offices`)
+    pub layer: String,
+    /// 郵便局のID(市区町村の場合はNone)
+    pub id: Option<String>,
+    /// 市区町村コードまたは郵便局コード
+    pub code: String,
+    /// 名前
+    pub name: String,
+    /// 郵便局の住所(市区町村の場合はNone)
+    pub address: Option<String>,
+}
+
+/// 郵便番号が一致する郵便局。
+#[derive(Debug, Clone, Deserialize)]
+pub struct PostalCodePostOffice {
+    /// ID
+    pub id: String,
+    /// 郵便局名
+    pub name: String,
+    /// 住所
+    pub address: String,
+}
+
+/// フィーチャーを取得する範囲。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bbox {
+    /// 最小のX座標(経度)
+    pub min_x: f64,
+    /// 最小のY座標(緯度)
+    pub min_y: f64,
+    /// 最大のX座標(経度)
+    pub max_x: f64,
+    /// 最大のY座標(緯度)
+    pub max_y: f64,
+}
+
+impl std::fmt::Display for Bbox {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{},{},{},{}",
+            self.min_x, self.min_y, self.max_x, self.max_y
+        )
+    }
+}
+
+impl Serialize for Bbox {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// 範囲の座標、または返すジオメトリの空間参照系。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Srid {
+    /// WGS84経緯度(EPSG:4326)
+    Wgs84,
+    /// Webメルカトル(EPSG:3857)
+    WebMercator,
+}
+
+impl Serialize for Srid {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let srid = match self {
+            Srid::Wgs84 => 4326,
+            Srid::WebMercator => 3857,
+        };
+        serializer.serialize_i32(srid)
+    }
+}
+
+/// 都道府県を取得する条件。
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PrefectureQuery {
+    /// 取得する範囲
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bbox: Option<Bbox>,
+    /// 範囲の座標の空間参照系(指定しない場合はWGS84経緯度)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub srid: Option<Srid>,
+    /// 返すジオメトリの空間参照系(指定しない場合はWGS84経緯度)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crs: Option<Srid>,
+    /// 都道府県コード
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    /// 名前に含まれる文字列
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// 市区町村を取得する条件。
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CityQuery {
+    /// 取得する範囲
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bbox: Option<Bbox>,
+    /// 範囲の座標の空間参照系(指定しない場合はWGS84経緯度)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub srid: Option<Srid>,
+    /// 返すジオメトリの空間参照系(指定しない場合はWGS84経緯度)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crs: Option<Srid>,
+    /// 市区町村コードの先頭の文字列
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_prefix: Option<String>,
+    /// 名前に含まれる文字列
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// 取得するフィーチャーの件数
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    /// 取得を開始するフィーチャーの位置
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u32>,
+}
+
+/// 郵便局を取得する条件。
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PostOfficeQuery {
+    /// 取得する範囲
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bbox: Option<Bbox>,
+    /// 範囲の座標の空間参照系(指定しない場合はWGS84経緯度)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub srid: Option<Srid>,
+    /// 返すジオメトリの空間参照系(指定しない場合はWGS84経緯度)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crs: Option<Srid>,
+    /// 郵便局コード
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_office_code: Option<String>,
+    /// 郵便局が所在する市区町村の市区町村コード
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub city_code: Option<String>,
+    /// 名前に含まれる文字列
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// 取得するフィーチャーの件数
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    /// 取得を開始するフィーチャーの位置
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u32>,
+}
+
+/// 鉄道路線を取得する条件。
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RailwayQuery {
+    /// 取得する範囲
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bbox: Option<Bbox>,
+    /// 範囲の座標の空間参照系(指定しない場合はWGS84経緯度)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub srid: Option<Srid>,
+    /// 返すジオメトリの空間参照系(指定しない場合はWGS84経緯度)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crs: Option<Srid>,
+    /// 事業者種別コード
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub operator_type: Option<String>,
+    /// 運営会社に含まれる文字列
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub operator: Option<String>,
+    /// 路線名に含まれる文字列
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line_name: Option<String>,
+    /// 取得するフィーチャーの件数
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    /// 取得を開始するフィーチャーの位置
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u32>,
+}
+
+/// 駅を取得する条件。
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StationQuery {
+    /// 取得する範囲
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bbox: Option<Bbox>,
+    /// 範囲の座標の空間参照系(指定しない場合はWGS84経緯度)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub srid: Option<Srid>,
+    /// 返すジオメトリの空間参照系(指定しない場合はWGS84経緯度)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crs: Option<Srid>,
+    /// 事業者種別コード
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub operator_type: Option<String>,
+    /// 運営会社に含まれる文字列
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub operator: Option<String>,
+    /// 路線名に含まれる文字列
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line_name: Option<String>,
+    /// 駅名に含まれる文字列
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// 取得するフィーチャーの件数
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    /// 取得を開始するフィーチャーの位置
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u32>,
+}
+
+/// 学校を取得する条件。
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SchoolQuery {
+    /// 取得する範囲
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bbox: Option<Bbox>,
+    /// 範囲の座標の空間参照系(指定しない場合はWGS84経緯度)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub srid: Option<Srid>,
+    /// 返すジオメトリの空間参照系(指定しない場合はWGS84経緯度)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crs: Option<Srid>,
+    /// 学校が所在する市区町村の市区町村コード
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub city_code: Option<String>,
+    /// 名前に含まれる文字列
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// 学校分類コード(カンマで区切った複数のコード)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category_code: Option<String>,
+    /// 管理者コード(カンマで区切った複数のコード)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub administrator_code: Option<String>,
+    /// 取得するフィーチャーの件数
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    /// 取得を開始するフィーチャーの位置
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u32>,
+}
+
+/// 学校のタイルに含める学校の分類。
+///
+/// それぞれの項目には、カンマで区切った複数のコードを指定できる。
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SchoolTileFilter {
+    /// 学校分類コード
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category_code: Option<String>,
+    /// 管理者コード
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub administrator_code: Option<String>,
+}
+
+/// 医療機関を取得する条件。
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MedicalInstitutionQuery {
+    /// 取得する範囲
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bbox: Option<Bbox>,
+    /// 範囲の座標の空間参照系(指定しない場合はWGS84経緯度)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub srid: Option<Srid>,
+    /// 返すジオメトリの空間参照系(指定しない場合はWGS84経緯度)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crs: Option<Srid>,
+    /// 医療機関が所在する都道府県の都道府県コード
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prefecture_code: Option<String>,
+    /// 名前に含まれる文字列
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// 診療科目
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub department: Option<String>,
+    /// 医療機関分類(カンマで区切った複数の分類)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub institution_type: Option<String>,
+    /// 取得するフィーチャーの件数
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    /// 取得を開始するフィーチャーの位置
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u32>,
+}
+
+/// 医療機関のタイルに含める医療機関の分類。
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MedicalInstitutionTileFilter {
+    /// 医療機関分類(カンマで区切った複数の分類)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub institution_type: Option<String>,
+}
+
+/// 河川を取得する条件。
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RiverQuery {
+    /// 取得する範囲
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bbox: Option<Bbox>,
+    /// 範囲の座標の空間参照系(指定しない場合はWGS84経緯度)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub srid: Option<Srid>,
+    /// 返すジオメトリの空間参照系(指定しない場合はWGS84経緯度)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crs: Option<Srid>,
+    /// 河川が所在する都道府県の都道府県コード
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prefecture_code: Option<String>,
+    /// 水系域コード
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub water_system_code: Option<String>,
+    /// 名前に含まれる文字列
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// 区間種別コード(カンマで区切った複数のコード)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub section_type: Option<String>,
+    /// 取得するフィーチャーの件数
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    /// 取得を開始するフィーチャーの位置
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u32>,
+}
+
+/// 河川のタイルに含める河川の区間種別。
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RiverTileFilter {
+    /// 区間種別コード(カンマで区切った複数のコード)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub section_type: Option<String>,
+}
+
+/// 郵便局のタイルに含める郵便局の分類。
+///
+/// それぞれの項目には、カンマで区切った複数のコードを指定できる。
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PostOfficeTileFilter {
+    /// 郵便局コード
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_office_code: Option<String>,
+    /// 分類コード
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category_code: Option<String>,
+    /// 細分類コード
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subcategory_code: Option<String>,
+}
+
+/// レディネスチェックの結果。
+#[derive(Debug, Clone, Deserialize)]
+pub struct Readiness {
+    /// 全体の状態(`ok`または`unavailable`)
+    pub status: String,
+    /// 確認した項目ごとの結果
+    pub checks: HashMap<String, ReadinessCheck>,
+}
+
+/// レディネスチェックの項目ごとの結果。
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadinessCheck {
+    /// 状態(`ok`または`unavailable`)
+    pub status: String,
+    /// 確認に要した時間(ミリ秒)
+    pub latency_ms: f64,
+    /// バージョン
+    pub version: Option<String>,
+    /// エラーの内容
+    pub error: Option<String>,
+}
+
+/// TileJSON。
+#[derive(Debug, Clone, Deserialize)]
+pub struct TileJson {
+    /// TileJSONのバージョン
+    pub tilejson: String,
+    /// レイヤー名
+    pub name: String,
+    /// 帰属表示
+    pub attribution: Option<String>,
+    /// タイル座標の体系
+    pub scheme: String,
+    /// タイルのURLテンプレート
+    pub tiles: Vec<String>,
+    /// 最小ズームレベル
+    pub minzoom: u8,
+    /// 最大ズームレベル
+    pub maxzoom: u8,
+    /// レイヤーの範囲(`west,south,east,north`のWGS84経緯度)
+    pub bounds: [f64; 4],
+    /// ベクタータイルのレイヤー
+    pub vector_layers: Vec<VectorLayer>,
+}
+
+/// 配信しているレイヤーの一覧。
+#[derive(Debug, Clone, Deserialize)]
+pub struct Layers {
+    /// レイヤーのメタデータ
+    pub layers: Vec<LayerMetadata>,
+}
+
+/// レイヤーのメタデータ。
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LayerMetadata {
+    /// レイヤー名
+    pub name: String,
+    /// カスタムレイヤーか
+    pub custom: bool,
+    /// ジオメトリの種類(カスタムレイヤーの場合はNone)
+    pub geometry_type: Option<String>,
+    /// フィーチャーの数(カスタムレイヤーの場合はNone)
+    pub features: Option<i64>,
+    /// フィーチャーの範囲(`west,south,east,north`のWGS84経緯度)
+    pub extent: Option<[f64; 4]>,
+    /// 最後にデータセットを登録した日時(UTC、RFC 3339形式)
+    pub last_imported_at: Option<String>,
+    /// 出典
+    pub attribution: String,
+    /// ベクタータイルのURLテンプレート
+    pub vector_tiles: Option<String>,
+    /// GeoJSONのタイルのURLテンプレート
+    pub geojson_tiles: String,
+}
+
+/// データセットを登録した履歴の一覧。
+#[derive(Debug, Clone, Deserialize)]
+pub struct Imports {
+    /// 新しい順に並べた登録の履歴
+    pub imports: Vec<ImportEntry>,
+}
+
+/// データセットを登録した履歴。
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportEntry {
+    /// 履歴のID
+    pub id: i64,
+    /// 登録したレイヤー名
+    pub layer: String,
+    /// 登録した都道府県コード
+    pub prefecture_code: Option<String>,
+    /// 登録したフィーチャーの数
+    pub features: i32,
+    /// データセットの種類(`N03`など)
+    pub dataset: Option<String>,
+    /// 登録したファイルの名前
+    pub source_file: Option<String>,
+    /// データセットの年度(西暦)
+    pub source_year: Option<i16>,
+    /// 登録した日時(UTC、RFC 3339形式)
+    pub imported_at: String,
+}
+
+/// ベクタータイルのレイヤー。
+#[derive(Debug, Clone, Deserialize)]
+pub struct VectorLayer {
+    /// レイヤー名
+    pub id: String,
+    /// 属性の名前と型
+    pub fields: HashMap<String, String>,
+    /// 最小ズームレベル
+    pub minzoom: u8,
+    /// 最大ズームレベル
+    pub maxzoom: u8,
+    /// レイヤーを描画するときの既定のスタイル
+    #[serde(default)]
+    pub style: Option<LayerStyle>,
+}
+
+/// レイヤーを描画するときの既定のスタイル。
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LayerStyle {
+    /// 塗りつぶしの色
+    pub fill_color: Option<String>,
+    /// 線の色
+    pub stroke_color: Option<String>,
+    /// ポイントを描画するアイコンの名前
+    pub icon: Option<String>,
+    /// ラベルを表示する最小ズームレベル
+    pub min_label_zoom: Option<u8>,
+}
+
+/// OGC API - Featuresのリンク。
+#[derive(Debug, Clone, Deserialize)]
+pub struct Link {
+    /// リンク先のURL
+    pub href: String,
+    /// リンクの関係
+    pub rel: String,
+    /// リンク先のメディアタイプ
+    #[serde(rename = "type")]
+    pub media_type: Option<String>,
+    /// リンクのタイトル
+    pub title: Option<String>,
+}
+
+/// OGC API - Featuresのフィーチャーコレクションの一覧。
+#[derive(Debug, Clone, Deserialize)]
+pub struct Collections {
+    /// リンク
+    pub links: Vec<Link>,
+    /// フィーチャーコレクション
+    pub collections: Vec<Collection>,
+}
+
+/// OGC API - Featuresのフィーチャーコレクションの情報。
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Collection {
+    /// フィーチャーコレクションのID
+    pub id: String,
+    /// タイトル
+    pub title: String,
+    /// 説明
+    pub description: String,
+    /// リンク
+    pub links: Vec<Link>,
+    /// フィーチャーの座標参照系
+    #[serde(default)]
+    pub crs: Vec<String>,
+    /// 範囲
+    pub extent: Option<CollectionExtent>,
+}
+
+/// OGC API - Featuresのフィーチャーコレクションの範囲。
+#[derive(Debug, Clone, Deserialize)]
+pub struct CollectionExtent {
+    /// 空間的な範囲
+    pub spatial: SpatialExtent,
+}
+
+/// OGC API - Featuresのフィーチャーコレクションの空間的な範囲。
+#[derive(Debug, Clone, Deserialize)]
+pub struct SpatialExtent {
+    /// 範囲(`west,south,east,north`のWGS84経緯度)
+    pub bbox: Vec<[f64; 4]>,
+    /// 範囲の座標参照系
+    pub crs: String,
+}
+
+/// OGC API - Featuresのフィーチャーを取得する条件。
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ItemsQuery {
+    /// 取得する範囲(WGS84経緯度)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bbox: Option<Bbox>,
+    /// 取得するフィーチャーの件数
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    /// 取得を開始するフィーチャーの位置
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u32>,
+    /// フィーチャーのプロパティとして返す属性の名前(カンマで区切る)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub properties: Option<String>,
+}
+
+/// OGC API - Featuresのフィーチャーの一覧。
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Items<P> {
+    /// 条件に一致したフィーチャーの件数
+    pub number_matched: u64,
+    /// 返されたフィーチャーの件数
+    pub number_returned: u64,
+    /// フィーチャーを取得した日時
+    pub time_stamp: String,
+    /// リンク
+    pub links: Vec<Link>,
+    /// フィーチャー
+    pub features: Vec<Feature<P>>,
+}
+
+/// 位置を含む都道府県。
+#[derive(Debug, Clone, Deserialize)]
+pub struct LocatedPrefecture {
+    /// 都道府県コード
+    pub code: String,
+    /// 都道府県名
+    pub name: String,
+}
+
+/// 位置を含む市区町村。
+#[derive(Debug, Clone, Deserialize)]
+pub struct LocatedCity {
+    /// 市区町村コード
+    pub code: String,
+    /// 郡名または支庁名
+    pub area: Option<String>,
+    /// 市区町村名
+    pub name: String,
+}
+
+/// 位置を含む都道府県と市区町村。
+#[derive(Debug, Clone, Deserialize)]
+pub struct Location {
+    /// 位置を含む都道府県(含む都道府県がない場合はNone)
+    pub prefecture: Option<LocatedPrefecture>,
+    /// 位置を含む市区町村(含む市区町村がない場合はNone)
+    pub city: Option<LocatedCity>,
+}
+
+/// 位置から最も近い郵便局。
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClosestPostOffice {
+    /// 郵便局コード
+    pub post_office_code: String,
+    /// 郵便局名
+    pub name: String,
+    /// 住所
+    pub address: String,
+    /// 位置からの距離(メートル)
+    pub distance: f64,
+}
+
+/// 逆ジオコーディングの結果。
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReverseGeocode {
+    /// 位置を含む都道府県(含む都道府県がない場合はNone)
+    pub prefecture: Option<LocatedPrefecture>,
+    /// 位置を含む市区町村(含む市区町村がない場合はNone)
+    pub city: Option<LocatedCity>,
+    /// 位置から最も近い郵便局(郵便局が登録されていない場合はNone)
+    pub post_office: Option<ClosestPostOffice>,
+}
+
+/// 都道府県ごとの集計。
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrefectureStats {
+    /// 都道府県コード
+    pub code: String,
+    /// 都道府県名
+    pub name: String,
+    /// 市区町村の数
+    pub cities: u64,
+    /// 郵便局の数
+    pub post_offices: u64,
+    /// 面積(平方キロメートル)
+    pub area_km2: f64,
+}
+
+/// すべての都道府県の合計。
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TotalStats {
+    /// 都道府県の数
+    pub prefectures: u64,
+    /// 市区町村の数
+    pub cities: u64,
+    /// 郵便局の数
+    pub post_offices: u64,
+    /// 面積(平方キロメートル)
+    pub area_km2: f64,
+}
+
+/// 都道府県ごとの集計と、すべての都道府県の合計。
+#[derive(Debug, Clone, Deserialize)]
+pub struct Stats {
+    /// 都道府県ごとの集計(都道府県コードの順)
+    pub prefectures: Vec<PrefectureStats>,
+    /// すべての都道府県の合計
+    pub total: TotalStats,
+}
+
+/// 都道府県または市区町村の面積と周囲長。
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Measure {
+    /// 都道府県コードまたは市区町村コード
+    pub code: String,
+    /// 名前
+    pub name: String,
+    /// 面積(平方キロメートル)
+    pub area_km2: f64,
+    /// 周囲長(キロメートル)
+    pub perimeter_km: f64,
+}
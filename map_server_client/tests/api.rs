@@ -0,0 +1,235 @@
+//! 地図APIサーバーを起動して、クライアントがレスポンスを型付きのモデルに読み込めるか確認するテスト。
+//!
+//! `test_support`クレートが起動したPostGISのコンテナ(環境変数TEST_DATABASE_URLを設定した場合はそのサーバー)に
+//! テストごとのデータベースを作成して、フィクスチャーを登録する。
+//! TEST_DATABASE_URLを設定しておらず、Dockerも利用できない場合はテストが失敗する。
+
+use std::net::TcpListener;
+
+use map_server::config::Settings;
+use map_server::custom_layers::CustomLayers;
+use map_server::startup::run;
+use map_server_client::models::{
+    Bbox, CityProperties, CityQuery, ItemsQuery, PostOfficeQuery, PostOfficeTileFilter,
+    PrefectureQuery, Srid,
+};
+use map_server_client::{Client, ClientError};
+use test_support::TestDatabase;
+
+/// 地図APIサーバーを起動して、クライアントを構築する。
+///
+/// # Returns
+///
+/// 起動した地図APIサーバーのクライアントと、地図APIサーバーが使用するデータベース
+/// (テストが終わるまで破棄しない)。
+async fn spawn_client() -> (Client, TestDatabase) {
+    let database = TestDatabase::with_fixtures().await;
+    let listener = TcpListener::bind("127.0.0.1:0").expect("ポートを割り当てできません。");
+    let port = listener.local_addr().unwrap().port();
+    let server = run(
+        listener,
+        database.pool.clone().into(),
+        &Settings::default(),
+        CustomLayers::default(),
+    )
+    .expect("Webサーバーを起動できません。");
+    tokio::spawn(server);
+
+    (Client::new(&format!("http://127.0.0.1:{}", port)), database)
+}
+
+#[tokio::test]
+async fn collection_endpoints_are_typed() {
+    let (client, _database) = spawn_client().await;
+    let bbox = Bbox {
+        min_x: 136.2,
+        min_y: 35.1,
+        max_x: 137.7,
+        max_y: 36.5,
+    };
+
+    client
+        .prefectures(&PrefectureQuery {
+            bbox: Some(bbox),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    let cities = client
+        .cities(&CityQuery {
+            code_prefix: Some("21".to_string()),
+            limit: Some(10),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(cities.number_returned, Some(cities.features.len() as u64));
+    client.search_postal_code("500-8570").await.unwrap();
+    let nearest = client
+        .nearest_post_offices(136.76, 35.42, Some(3))
+        .await
+        .unwrap();
+    assert!(nearest.features.len() <= 3);
+    assert!(nearest
+        .features
+        .windows(2)
+        .all(|pair| pair[0].properties.distance <= pair[1].properties.distance));
+    let location = client.locate(136.76, 35.42).await.unwrap();
+    if let Some(city) = location.city {
+        assert_eq!(city.code.len(), 5);
+    }
+    let geocoded = client.reverse_geocode(136.76, 35.42).await.unwrap();
+    if let Some(post_office) = geocoded.post_office {
+        assert!(0.0 <= post_office.distance);
+    }
+    let stats = client.stats().await.unwrap();
+    assert_eq!(stats.total.prefectures, stats.prefectures.len() as u64);
+    let found = client.search_name("岐阜", Some("cities")).await.unwrap();
+    assert!(found
+        .features
+        .iter()
+        .all(|feature| feature.properties.layer == "cities"));
+    client
+        .post_offices(&PostOfficeQuery {
+            bbox: Some(bbox),
+            srid: Some(Srid::Wgs84),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn ogc_api_endpoints_are_typed() {
+    let (client, _database) = spawn_client().await;
+
+    let collections = client.collections().await.unwrap();
+    let ids: Vec<&str> = collections
+        .collections
+        .iter()
+        .map(|collection| collection.id.as_str())
+        .collect();
+    assert_eq!(ids, ["prefectures", "cities", "post_offices"]);
+    let collection = client.collection("cities").await.unwrap();
+    assert_eq!(collection.id, "cities");
+    let items = client
+        .items::<CityProperties>(
+            "cities",
+            &ItemsQuery {
+                limit: Some(5),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert!(items.number_returned <= 5);
+    let items = client
+        .items::<serde_json::Map<String, serde_json::Value>>(
+            "cities",
+            &ItemsQuery {
+                limit: Some(5),
+                properties: Some("name".to_string()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert!(items
+        .features
+        .iter()
+        .all(|feature| feature.properties.keys().eq(["name"])));
+}
+
+#[tokio::test]
+async fn tile_json_is_typed() {
+    let (client, _database) = spawn_client().await;
+
+    let tile_json = client.tile_json("cities").await.unwrap();
+    assert_eq!(tile_json.vector_layers[0].id, "cities");
+    assert!(tile_json.tiles[0].ends_with("/mvt/cities/{z}/{x}/{y}.pbf"));
+    assert!(tile_json.vector_layers[0].style.is_some());
+}
+
+#[tokio::test]
+async fn post_office_tiles_are_filtered_by_category() {
+    let (client, _database) = spawn_client().await;
+
+    let filter = PostOfficeTileFilter {
+        post_office_code: Some("1,2".to_string()),
+        ..Default::default()
+    };
+    client
+        .post_office_vector_tile(10, 909, 403, &filter)
+        .await
+        .unwrap();
+    let filter = PostOfficeTileFilter {
+        category_code: Some("0;1".to_string()),
+        ..Default::default()
+    };
+    match client.post_office_vector_tile(10, 909, 403, &filter).await {
+        Err(ClientError::Api(e)) => assert_eq!(e.code, "invalid_query"),
+        result => panic!(
+            "不正なクエリパラメーターのエラーを返しませんでした。{:?}",
+            result
+        ),
+    }
+}
+
+#[tokio::test]
+async fn layers_are_exported() {
+    let (client, _database) = spawn_client().await;
+
+    let fgb = client.export_flatgeobuf("cities").await.unwrap();
+    assert_eq!(&fgb[..8], b"fgb\x03fgb\x00");
+    let csv = client.export_csv("post_offices").await.unwrap();
+    assert!(csv
+        .trim_start_matches('\u{feff}')
+        .starts_with("id,cityCode,"));
+    let rest = client.resume_export("cities", "fgb", 8).await.unwrap();
+    assert_eq!(rest, fgb[8..]);
+    let manifest = client.export_checksums("cities", "fgb").await.unwrap();
+    assert!(manifest.lines().any(|line| line.ends_with("  cities.fgb")));
+}
+
+#[tokio::test]
+async fn post_offices_are_returned_as_waypoints() {
+    let (client, _database) = spawn_client().await;
+    let query = PostOfficeQuery {
+        limit: Some(5),
+        ..Default::default()
+    };
+
+    let kml = client.post_offices_kml(&query).await.unwrap();
+    assert!(kml.contains(r#"<kml xmlns="http://www.opengis.net/kml/2.2">"#));
+    let gpx = client.post_offices_gpx(&query).await.unwrap();
+    assert!(gpx.contains(r#"<gpx version="1.1""#));
+}
+
+#[tokio::test]
+async fn api_errors_are_decoded() {
+    let (client, _database) = spawn_client().await;
+    let client = client.language("ja");
+
+    match client.tile_json("unknown").await {
+        Err(ClientError::Api(e)) => {
+            assert_eq!(e.status, 404);
+            assert_eq!(e.code, "unknown_layer");
+            assert!(e.localized_message.is_some());
+        }
+        result => panic!("レイヤーが存在しないエラーを返しませんでした。{:?}", result),
+    }
+    let query = ItemsQuery {
+        properties: Some("unknown".to_string()),
+        ..Default::default()
+    };
+    match client.items::<CityProperties>("cities", &query).await {
+        Err(ClientError::Api(e)) => {
+            assert_eq!(e.status, 400);
+            assert_eq!(e.code, "unknown_property");
+        }
+        result => panic!(
+            "プロパティが存在しないエラーを返しませんでした。{:?}",
+            result
+        ),
+    }
+}
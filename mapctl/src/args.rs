@@ -0,0 +1,95 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use clap::Args;
+use utils::coordinates::{BboxGuard, SwappedAxes};
+use utils::import::ImportOptions;
+use utils::prompt::{AutoYesPrompter, DefaultAnswerPrompter, Prompter, TerminalPrompter};
+use utils::spill::DEFAULT_MEMORY_BUDGET_MB;
+
+/// ジオメトリを持つデータを登録するサブコマンドに共通する引数。
+#[derive(Args, Debug)]
+pub struct ImportArgs {
+    /// 登録するジオメトリの座標が含まれなければならない範囲。
+    ///
+    /// `japan`(日本の領域に余白を加えた範囲)、`none`(範囲を確認しない)、または
+    /// `west,south,east,north`形式の経緯度で指定する。座標が範囲外のフィーチャーが存在する場合は、
+    /// 登録を中止する。
+    #[clap(long, value_parser, default_value = "japan")]
+    pub bbox_guard: BboxGuard,
+
+    /// 経度と緯度が入れ替わった座標を見つけたときの処理。
+    ///
+    /// 地理座標系の座標が経度、緯度の順ではなく、緯度、経度の順に記録されていると判断した場合、
+    /// `correct`を指定したときは警告を表示して経度と緯度を入れ替えて登録し、`fail`を指定したときは登録を中止する。
+    #[clap(long, value_parser, default_value = "fail")]
+    pub swapped_axes: SwappedAxes,
+
+    /// 登録するフィーチャーをメモリに保持する上限(MiB)。
+    ///
+    /// 変換したフィーチャーの大きさが上限を超えた場合は、超えた分を一時ファイルに書き出す。
+    #[clap(long, value_parser, default_value_t = DEFAULT_MEMORY_BUDGET_MB)]
+    pub memory_budget_mb: usize,
+
+    /// 登録の履歴に記録するデータセットの年度(西暦)。
+    ///
+    /// 指定しない場合は、国土数値情報のファイル名(例えば`P30-13_21.shp`)から判断する。`load-all`サブコマンドでは、
+    /// マニフェストに指定された年度を記録する。
+    #[clap(long, value_parser)]
+    pub source_year: Option<u16>,
+}
+
+impl ImportArgs {
+    /// 登録するときのオプションを返す。フィーチャーは1つの接続で、既存のレコードを削除して登録する。
+    pub fn options(&self) -> ImportOptions {
+        ImportOptions {
+            bbox_guard: self.bbox_guard,
+            swapped_axes: self.swapped_axes,
+            memory_budget: self.memory_budget_mb * 1024 * 1024,
+            jobs: 1,
+            upsert: false,
+            year: self.source_year,
+        }
+    }
+}
+
+/// 既存のレコードが登録されているときの処理を指定する引数。
+///
+/// いずれも指定しない場合は、既存のレコードを削除して登録するか確認する。標準入力が端末でない場合、
+/// または`--prompt-timeout`で指定した時間内に回答がない場合は、登録を中止する。
+#[derive(Args, Debug)]
+pub struct ExistingArgs {
+    /// 既存のレコードが登録されている場合は、確認せずに削除して登録する。
+    #[clap(short, long, action)]
+    pub yes: bool,
+
+    /// 既存のレコードが登録されている場合は、確認せずに登録を中止する。
+    #[clap(long, action, conflicts_with = "yes")]
+    pub abort_if_exists: bool,
+
+    /// 既存のレコードを削除して登録するか確認するときに、回答を待つ時間(秒)。
+    ///
+    /// 指定しない場合は、回答があるまで待つ。
+    #[clap(long, value_parser)]
+    pub prompt_timeout: Option<u64>,
+
+    /// 既存のレコードを削除せずに、自然キーで照合して変更があったレコードだけを登録する。
+    ///
+    /// 自然キーが一致する既存のレコードは値が変わった場合に限り更新し、登録しなかった既存のレコードは削除する。
+    /// 行政区域データ、郵便局データ及び学校データに限り指定できる。
+    #[clap(long, action, conflicts_with_all = &["yes", "abort-if-exists"])]
+    pub upsert: bool,
+}
+
+impl ExistingArgs {
+    /// 既存のレコードを削除して登録するか確認する処理を返す。
+    pub fn prompter(&self) -> Arc<dyn Prompter> {
+        match (self.yes, self.abort_if_exists) {
+            (true, _) => Arc::new(AutoYesPrompter),
+            (false, true) => Arc::new(DefaultAnswerPrompter),
+            (false, false) => Arc::new(
+                TerminalPrompter::new().timeout(self.prompt_timeout.map(Duration::from_secs)),
+            ),
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use database::{connect_to_database, connect_to_database_with};
+use sqlx::PgPool;
+
+use crate::LAYERS;
+
+/// レイヤーに登録されているレコードの統計。
+#[derive(Debug, PartialEq, sqlx::FromRow)]
+struct LayerStats {
+    /// レコード数
+    count: i64,
+    /// ジオメトリの範囲
+    extent: Option<String>,
+    /// ジオメトリのチェックサム
+    checksum: Option<String>,
+}
+
+/// レイヤーに登録されているレコードの統計を取得する。
+///
+/// ジオメトリのチェックサムは、ジオメトリごとのハッシュ値を並べ替えて連結した文字列のハッシュ値で、
+/// レコードの登録順やIDに依存しない。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+/// * `layer` - レイヤー(テーブル)の名前。
+///
+/// # Returns
+///
+/// レイヤーに登録されているレコードの統計。
+async fn layer_stats(pool: &PgPool, layer: &str) -> sqlx::Result<LayerStats> {
+    let sql = format!(
+        r#"
+        SELECT
+            COUNT(*) as count,
+            ST_Extent(geom)::text as extent,
+            md5(string_agg(h.hash, '' ORDER BY h.hash)) as checksum
+        FROM (
+            SELECT geom, md5(ST_AsEWKB(geom)) as hash FROM {}
+        ) h
+        "#,
+        layer
+    );
+
+    sqlx::query_as::<_, LayerStats>(&sql).fetch_one(pool).await
+}
+
+/// 環境変数DATABASE_URLに指定されたデータベースと、指定されたデータベースに登録されている
+/// レイヤーごとのレコード数、ジオメトリの範囲及びジオメトリのチェックサムを比較する。
+///
+/// # Arguments
+///
+/// * `other_url` - 比較するデータベースへの接続URL。
+///
+/// # Returns
+///
+/// すべてのレイヤーが一致した場合はtrue。
+pub async fn compare(other_url: &str) -> anyhow::Result<bool> {
+    let pool = connect_to_database().await?;
+    let other = connect_to_database_with(other_url, 5).await?;
+
+    let mut matched = true;
+    for layer in LAYERS {
+        let stats = layer_stats(&pool, layer).await?;
+        let other_stats = layer_stats(&other, layer).await?;
+        if stats == other_stats {
+            println!("[一致] {}: {}件", layer, stats.count);
+            continue;
+        }
+        matched = false;
+        println!("[差異] {}", layer);
+        if stats.count != other_stats.count {
+            println!("  レコード数: {} / {}", stats.count, other_stats.count);
+        }
+        if stats.extent != other_stats.extent {
+            println!(
+                "  範囲: {} / {}",
+                stats.extent.as_deref().unwrap_or("なし"),
+                other_stats.extent.as_deref().unwrap_or("なし")
+            );
+        }
+        if stats.checksum != other_stats.checksum {
+            println!(
+                "  チェックサム: {} / {}",
+                stats.checksum.as_deref().unwrap_or("なし"),
+                other_stats.checksum.as_deref().unwrap_or("なし")
+            );
+        }
+    }
+
+    Ok(matched)
+}
@@ -0,0 +1,33 @@
+use database::connect_to_database;
+use database::preflight::{preflight, ExtensionStatus};
+
+/// 環境変数DATABASE_URLに指定されたデータベースで、本アプリが使用する拡張を利用できるか確認して、
+/// 結果と利用できない拡張をインストールするSQL文を出力する。
+///
+/// # Returns
+///
+/// 必須の拡張をすべて利用できる場合はtrue。
+pub async fn doctor() -> anyhow::Result<bool> {
+    let pool = connect_to_database().await?;
+    let report = preflight(&pool).await?;
+
+    for check in &report.checks {
+        let label = match (check.is_ok(), check.required) {
+            (true, _) => "OK",
+            (false, true) => "不足",
+            (false, false) => "任意",
+        };
+        let status = match &check.status {
+            ExtensionStatus::Installed(version) => format!("インストール済み({})", version),
+            ExtensionStatus::BuiltIn => "PostgreSQLの標準機能で利用可能".to_string(),
+            ExtensionStatus::NotInstalled => "未インストール".to_string(),
+            ExtensionStatus::Unavailable => "サーバーで利用不可".to_string(),
+        };
+        println!("[{}] {}: {} - {}", label, check.name, status, check.purpose);
+        if let Some(guidance) = check.guidance() {
+            println!("  {}", guidance);
+        }
+    }
+
+    Ok(report.is_ok())
+}
@@ -0,0 +1,334 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use clap::Args;
+use utils::archive::ExtractedArchive;
+use utils::errors::Failure;
+use utils::prefecture::PrefectureCode;
+
+use crate::args::{ExistingArgs, ImportArgs};
+use crate::register::{register, RegisterCommand};
+
+/// 国土数値情報ダウンロードサイトのデータを配信するURL。
+const KSJ_DATA_URL: &str = "https://nlftp.mlit.go.jp/ksj/gml/data";
+
+/// 郵便局データ(P30)の空間参照ID(JGD2000)。
+const POST_OFFICE_SRID: i32 = 4612;
+
+/// 郵便局データ(P30)の属性データファイルのエンコーディング。
+const POST_OFFICE_ENCODING: &str = "shift_jis";
+
+/// 学校データ(P29)の空間参照ID(JGD2011)。
+const SCHOOL_SRID: i32 = 6668;
+
+/// 学校データ(P29)の属性データファイルのエンコーディング。
+const SCHOOL_ENCODING: &str = "shift_jis";
+
+/// 医療機関データ(P04)の空間参照ID(JGD2011)。
+const MEDICAL_INSTITUTION_SRID: i32 = 6668;
+
+/// 医療機関データ(P04)の属性データファイルのエンコーディング。
+const MEDICAL_INSTITUTION_ENCODING: &str = "shift_jis";
+
+/// ダウンロードする国土数値情報のデータセット。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DownloadDataset {
+    /// 行政区域データ(N03)
+    AdministrativeBoundaries,
+    /// 郵便局データ(P30)
+    PostOffices,
+    /// 学校データ(P29)
+    Schools,
+    /// 医療機関データ(P04)
+    MedicalInstitutions,
+}
+
+impl FromStr for DownloadDataset {
+    type Err = String;
+
+    /// `n03`、`p30`、`p29`または`p04`から、ダウンロードするデータセットを構築する。
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "n03" => Ok(DownloadDataset::AdministrativeBoundaries),
+            "p30" => Ok(DownloadDataset::PostOffices),
+            "p29" => Ok(DownloadDataset::Schools),
+            "p04" => Ok(DownloadDataset::MedicalInstitutions),
+            _ => Err(format!(
+                "データセット({})は、n03(行政区域データ)、p30(郵便局データ)、p29(学校データ)またはp04(医療機関データ)で指定してください。",
+                s
+            )),
+        }
+    }
+}
+
+impl DownloadDataset {
+    /// 年度を指定しない場合にダウンロードする年度を返す。
+    fn default_year(self) -> u16 {
+        match self {
+            DownloadDataset::AdministrativeBoundaries => 2022,
+            DownloadDataset::PostOffices => 2013,
+            DownloadDataset::Schools => 2021,
+            DownloadDataset::MedicalInstitutions => 2020,
+        }
+    }
+
+    /// 国土数値情報ダウンロードサイトの、データセットのZIPアーカイブのURLを返す。
+    ///
+    /// # Arguments
+    ///
+    /// * `year` - データの年度(西暦)。
+    /// * `code` - 都道府県コード。
+    fn url(self, year: u16, code: PrefectureCode) -> String {
+        match self {
+            DownloadDataset::AdministrativeBoundaries => format!(
+                "{}/N03/N03-{}/N03-{}0101_{}_GML.zip",
+                KSJ_DATA_URL, year, year, code
+            ),
+            DownloadDataset::PostOffices => format!(
+                "{}/P30/P30-{:02}/P30-{:02}_{}.zip",
+                KSJ_DATA_URL,
+                year % 100,
+                year % 100,
+                code
+            ),
+            DownloadDataset::Schools => format!(
+                "{}/P29/P29-{:02}/P29-{:02}_{}_GML.zip",
+                KSJ_DATA_URL,
+                year % 100,
+                year % 100,
+                code
+            ),
+            DownloadDataset::MedicalInstitutions => format!(
+                "{}/P04/P04-{:02}/P04-{:02}_{}_GML.zip",
+                KSJ_DATA_URL,
+                year % 100,
+                year % 100,
+                code
+            ),
+        }
+    }
+
+    /// ZIPアーカイブに含まれていなければならない、登録するファイルの拡張子を返す。
+    fn extensions(self) -> &'static [&'static str] {
+        match self {
+            DownloadDataset::AdministrativeBoundaries => &["geojson", "xml", "shp"],
+            DownloadDataset::PostOffices
+            | DownloadDataset::Schools
+            | DownloadDataset::MedicalInstitutions => &["shp", "geojson"],
+        }
+    }
+}
+
+/// `download`サブコマンドの引数。
+#[derive(Args, Debug)]
+pub struct DownloadArgs {
+    /// ダウンロードするデータセット(`n03`: 行政区域データ、`p30`: 郵便局データ、`p29`: 学校データ、
+    /// `p04`: 医療機関データ)。
+    #[clap(short, long, value_parser)]
+    dataset: DownloadDataset,
+
+    /// ダウンロードするデータの年度(西暦)。
+    ///
+    /// 指定しない場合は、行政区域データは2022年度、郵便局データは2013年度、学校データは2021年度、
+    /// 医療機関データは2020年度のデータをダウンロードする。
+    #[clap(long, value_parser)]
+    year: Option<u16>,
+
+    /// ダウンロードするデータの都道府県コード。
+    #[clap(short, long, value_parser)]
+    code: PrefectureCode,
+
+    /// ダウンロードしたZIPアーカイブを保存するディレクトリ。
+    #[clap(short, long, value_parser, default_value = "./resources")]
+    output_dir: PathBuf,
+
+    /// ダウンロードするURL。
+    ///
+    /// 指定しない場合は、データセット、年度及び都道府県コードから、国土数値情報ダウンロードサイトの
+    /// URLを決定する。
+    #[clap(long, value_parser)]
+    url: Option<String>,
+
+    /// ダウンロードしたデータを、続けてデータベースに登録する。
+    #[clap(long, action)]
+    register: bool,
+
+    #[clap(flatten)]
+    import: ImportArgs,
+
+    #[clap(flatten)]
+    existing: ExistingArgs,
+}
+
+/// URLからファイルをダウンロードして、ファイルに書き込む。
+///
+/// # Arguments
+///
+/// * `url` - ダウンロードするURL。
+/// * `path` - ダウンロードしたデータを書き込むファイルのパス。
+///
+/// # Returns
+///
+/// ダウンロードしたデータの大きさ(バイト)。
+async fn fetch(url: &str, path: &Path) -> anyhow::Result<u64> {
+    let mut response = reqwest::get(url)
+        .await
+        .map_err(|e| Failure::input(format!("{}をダウンロードできません。{}", url, e)))?;
+    let status = response.status();
+    if status == reqwest::StatusCode::NOT_FOUND {
+        return Err(Failure::argument(format!(
+            "{}が見つかりません。データセット、年度及び都道府県コードを確認してください。",
+            url
+        ))
+        .into());
+    }
+    if !status.is_success() {
+        return Err(Failure::input(format!(
+            "{}をダウンロードできません。ステータスコード: {}",
+            url, status
+        ))
+        .into());
+    }
+
+    let mut file = File::create(path).map_err(|e| {
+        Failure::input(format!(
+            "ファイル({})を作成できません。{}",
+            path.display(),
+            e
+        ))
+    })?;
+    let mut size = 0;
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| Failure::input(format!("{}をダウンロードできません。{}", url, e)))?
+    {
+        file.write_all(&chunk).map_err(|e| {
+            Failure::input(format!(
+                "ファイル({})に書き込めません。{}",
+                path.display(),
+                e
+            ))
+        })?;
+        size += chunk.len() as u64;
+    }
+
+    Ok(size)
+}
+
+/// 国土数値情報ダウンロードサイトからデータセットのZIPアーカイブをダウンロードして、保存する。
+///
+/// ダウンロードしたZIPアーカイブは、すべてのファイルを展開できること(CRC-32が一致すること)と、
+/// 登録するファイルが含まれていることを確認してから保存する。`--register`を指定した場合は、
+/// 保存したZIPアーカイブを続けてデータベースに登録する。
+///
+/// # Arguments
+///
+/// * `args` - `download`サブコマンドの引数。
+///
+/// # Returns
+///
+/// ダウンロードに成功して、登録する場合は登録にも成功したときにtrue。
+pub async fn download(mut args: DownloadArgs) -> anyhow::Result<bool> {
+    let year = args.year.unwrap_or_else(|| args.dataset.default_year());
+    let url = args
+        .url
+        .clone()
+        .unwrap_or_else(|| args.dataset.url(year, args.code));
+    let name = url
+        .rsplit('/')
+        .next()
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| {
+            Failure::argument(format!("URL({})にファイル名が含まれていません。", url))
+        })?;
+    std::fs::create_dir_all(&args.output_dir).map_err(|e| {
+        Failure::input(format!(
+            "ディレクトリ({})を作成できません。{}",
+            args.output_dir.display(),
+            e
+        ))
+    })?;
+    let path = args.output_dir.join(name);
+    // 確認するまでは、保存するファイルと異なる名前で書き込む
+    let part = args.output_dir.join(format!("{}.part", name));
+
+    println!("{}をダウンロードしています。", url);
+    let result = fetch(&url, &part).await.and_then(|size| {
+        let archive = ExtractedArchive::extract(&part.to_string_lossy())?;
+        archive.find(args.dataset.extensions())?;
+        Ok(size)
+    });
+    let size = match result {
+        Ok(size) => size,
+        Err(e) => {
+            let _ = std::fs::remove_file(&part);
+            return Err(e);
+        }
+    };
+    std::fs::rename(&part, &path).map_err(|e| {
+        Failure::input(format!(
+            "ファイル({})を保存できません。{}",
+            path.display(),
+            e
+        ))
+    })?;
+    println!(
+        "{}を保存しました({:.1}MiB)。",
+        path.display(),
+        size as f64 / 1024.0 / 1024.0
+    );
+    if !args.register {
+        return Ok(true);
+    }
+
+    // URLを指定せずにダウンロードした場合は、ダウンロードした年度を登録の履歴に記録する
+    if args.url.is_none() {
+        args.import.source_year.get_or_insert(year);
+    }
+    let file = path.to_string_lossy().into_owned();
+    let command = match args.dataset {
+        DownloadDataset::AdministrativeBoundaries => RegisterCommand::Prefecture {
+            file: Some(file),
+            code: Some(args.code.to_string()),
+            batch: None,
+            format: None,
+            schema: None,
+            srid: None,
+            import: args.import,
+            existing: args.existing,
+            dry_run: false,
+        },
+        DownloadDataset::PostOffices => RegisterCommand::PostOffice {
+            file,
+            code: args.code.to_string(),
+            srid: Some(POST_OFFICE_SRID),
+            encoding: POST_OFFICE_ENCODING.to_string(),
+            import: args.import,
+            existing: args.existing,
+            dry_run: false,
+        },
+        DownloadDataset::Schools => RegisterCommand::School {
+            file,
+            code: args.code.to_string(),
+            srid: Some(SCHOOL_SRID),
+            encoding: SCHOOL_ENCODING.to_string(),
+            import: args.import,
+            existing: args.existing,
+            dry_run: false,
+        },
+        DownloadDataset::MedicalInstitutions => RegisterCommand::MedicalInstitution {
+            file,
+            code: args.code.to_string(),
+            srid: Some(MEDICAL_INSTITUTION_SRID),
+            encoding: MEDICAL_INSTITUTION_ENCODING.to_string(),
+            import: args.import,
+            existing: args.existing,
+            dry_run: false,
+        },
+    };
+
+    register(command, 1).await
+}
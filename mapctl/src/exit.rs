@@ -0,0 +1,81 @@
+//! コマンドが失敗したときのエラーメッセージの出力と終了コード。
+
+use utils::errors::{failure_kind, FailureKind};
+
+/// 処理は完了したが、登録に失敗したデータ、または問題が見つかったデータがある場合の終了コード。
+/// 原因の種類を判断できないエラーで失敗した場合も、この終了コードで終了する。
+pub const EXIT_INCOMPLETE: i32 = 1;
+/// 引数が不正な場合の終了コード(clapが引数を解析できない場合と同じ)。
+pub const EXIT_ARGUMENT: i32 = 2;
+/// ファイルが存在しない、または読み込めない場合の終了コード。
+pub const EXIT_INPUT: i32 = 3;
+/// ファイルに記録されているデータが不正な場合の終了コード。
+pub const EXIT_DATA: i32 = 4;
+/// データベースに接続できない、またはデータベースの操作に失敗した場合の終了コード。
+pub const EXIT_DATABASE: i32 = 5;
+
+/// エラーの原因の種類から、終了コードを決定する。
+///
+/// # Arguments
+///
+/// * `e` - エラー。
+///
+/// # Returns
+///
+/// 終了コード。
+pub fn exit_code(e: &anyhow::Error) -> i32 {
+    match failure_kind(e) {
+        Some(FailureKind::Argument) => EXIT_ARGUMENT,
+        Some(FailureKind::Input) => EXIT_INPUT,
+        Some(FailureKind::Data) => EXIT_DATA,
+        Some(FailureKind::Database) => EXIT_DATABASE,
+        // 原因の種類を持たないデータベースのエラーも、データベースの操作の失敗とする
+        None if e.chain().any(|cause| cause.is::<sqlx::Error>()) => EXIT_DATABASE,
+        None => EXIT_INCOMPLETE,
+    }
+}
+
+/// エラーメッセージと、その原因を標準エラー出力に出力して、エラーの原因の種類に応じた終了コードで終了する。
+///
+/// # Arguments
+///
+/// * `e` - エラー。
+pub fn exit_with_error(e: anyhow::Error) -> ! {
+    eprintln!("エラー: {}", e);
+    for cause in e.chain().skip(1) {
+        eprintln!("  原因: {}", cause);
+    }
+
+    std::process::exit(exit_code(&e))
+}
+
+#[cfg(test)]
+mod tests {
+    use geo_types::{Geometry, Point};
+    use utils::coordinates::{BboxGuard, SwappedAxes};
+    use utils::import::ImportOptions;
+
+    use super::*;
+
+    #[test]
+    fn coordinates_outside_bbox_exit_with_data_error() {
+        // 緯度0度、経度0度のWebメルカトル座標は、日本の範囲の外側にある
+        let geom = Geometry::Point(Point::new(0.0, 0.0));
+        let e = BboxGuard::default().validate(&geom, "郵便局").unwrap_err();
+        assert_eq!(exit_code(&e), EXIT_DATA);
+    }
+
+    #[test]
+    fn swapped_axes_exit_with_data_error() {
+        let options = ImportOptions {
+            swapped_axes: SwappedAxes::Fail,
+            ..Default::default()
+        };
+        // 緯度、経度の順に記録された東京駅の座標
+        let mut geom = Geometry::Point(Point::new(35.681, 139.767));
+        let e = options
+            .fix_axis_order(&mut geom, 6668, "郵便局")
+            .unwrap_err();
+        assert_eq!(exit_code(&e), EXIT_DATA);
+    }
+}
@@ -0,0 +1,41 @@
+use database::connect_to_database;
+use database::imports::list_imports;
+use utils::errors::Failure;
+use utils::prefecture::PrefectureCode;
+
+/// データセットを登録した履歴を、新しい順に出力する。
+///
+/// # Arguments
+///
+/// * `layer` - 履歴を出力するレイヤー名。Noneの場合はすべてのレイヤーの履歴を出力する。
+/// * `code` - 履歴を出力する都道府県コード。Noneの場合はすべての都道府県の履歴を出力する。
+/// * `limit` - 出力する履歴の最大数。
+pub async fn imports(layer: Option<&str>, code: Option<&str>, limit: i64) -> anyhow::Result<()> {
+    if let Some(code) = code {
+        code.parse::<PrefectureCode>().map_err(Failure::argument)?;
+    }
+    let pool = connect_to_database().await?;
+    let records = list_imports(&pool, layer, code, limit)
+        .await
+        .map_err(|e| Failure::database(format!("登録の履歴を取得できません。{}", e)))?;
+    if records.is_empty() {
+        println!("登録の履歴はありません。");
+    }
+    for record in records {
+        println!(
+            "{} {:<20} {:<2} {:<8} {:>4} {:>8}件 {}",
+            record.imported_at,
+            record.layer,
+            record.prefecture_code.as_deref().unwrap_or("-"),
+            record.dataset.as_deref().unwrap_or("-"),
+            record
+                .source_year
+                .map(|year| year.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            record.features,
+            record.source_file.as_deref().unwrap_or("-")
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,291 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::anyhow;
+use geojson::{GeoJson, Value};
+use serde::Serialize;
+use utils::crs::{srid_from_geojson_crs, srid_from_prj};
+use utils::shape::{describe_field_value, iter_features, open_shape_file};
+
+/// ファイルに記録されているフィーチャーの概要。
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Summary {
+    /// ファイルのパス
+    file: String,
+    /// ファイルの形式(`geojson`または`shapefile`)
+    format: &'static str,
+    /// フィーチャーの数
+    features: usize,
+    /// ジオメトリの型ごとのフィーチャーの数
+    geometry_types: BTreeMap<String, usize>,
+    /// ファイルに記録されている空間参照系のSRID(記録されていない場合はNone)
+    srid: Option<i32>,
+    /// フィーチャーの範囲(最小X座標、最小Y座標、最大X座標、最大Y座標、フィーチャーがない場合はNone)
+    bbox: Option<[f64; 4]>,
+    /// プロパティ(属性)の名前ごとの概要
+    properties: BTreeMap<String, PropertySummary>,
+}
+
+/// プロパティ(属性)の概要。
+#[derive(Debug, Default, Serialize)]
+struct PropertySummary {
+    /// 値の型ごとのフィーチャーの数(値がない場合の型は`null`)
+    types: BTreeMap<&'static str, usize>,
+    /// 値の例(重複を除いて、最初に現れた順)
+    samples: Vec<String>,
+}
+
+impl PropertySummary {
+    /// プロパティの値を集計する。
+    ///
+    /// # Arguments
+    ///
+    /// * `type_name` - 値の型の名前。
+    /// * `value` - 値を文字列に変換した値。値がない場合はNone。
+    /// * `samples` - 記録する値の例の最大数。
+    fn add(&mut self, type_name: &'static str, value: Option<String>, samples: usize) {
+        match value {
+            Some(value) => {
+                *self.types.entry(type_name).or_default() += 1;
+                if self.samples.len() < samples && !self.samples.contains(&value) {
+                    self.samples.push(value);
+                }
+            }
+            None => *self.types.entry("null").or_default() += 1,
+        }
+    }
+}
+
+/// GeoJSONのジオメトリの型の名前を返す。
+fn geometry_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Point(_) => "Point",
+        Value::MultiPoint(_) => "MultiPoint",
+        Value::LineString(_) => "LineString",
+        Value::MultiLineString(_) => "MultiLineString",
+        Value::Polygon(_) => "Polygon",
+        Value::MultiPolygon(_) => "MultiPolygon",
+        Value::GeometryCollection(_) => "GeometryCollection",
+    }
+}
+
+/// 範囲を、座標を含むように広げる。
+///
+/// # Arguments
+///
+/// * `bbox` - 範囲。まだ座標を含めていない場合はNone。
+/// * `position` - 座標。
+fn extend_bbox(bbox: &mut Option<[f64; 4]>, position: &[f64]) {
+    if position.len() < 2 {
+        return;
+    }
+    let (x, y) = (position[0], position[1]);
+    *bbox = Some(match *bbox {
+        Some([min_x, min_y, max_x, max_y]) => {
+            [min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y)]
+        }
+        None => [x, y, x, y],
+    });
+}
+
+/// 範囲を、GeoJSONのジオメトリを含むように広げる。
+///
+/// # Arguments
+///
+/// * `bbox` - 範囲。まだ座標を含めていない場合はNone。
+/// * `value` - GeoJSONのジオメトリ。
+fn extend_bbox_with_geometry(bbox: &mut Option<[f64; 4]>, value: &Value) {
+    match value {
+        Value::Point(position) => extend_bbox(bbox, position),
+        Value::MultiPoint(positions) | Value::LineString(positions) => {
+            positions.iter().for_each(|p| extend_bbox(bbox, p))
+        }
+        Value::MultiLineString(lines) | Value::Polygon(lines) => {
+            lines.iter().flatten().for_each(|p| extend_bbox(bbox, p))
+        }
+        Value::MultiPolygon(polygons) => polygons
+            .iter()
+            .flatten()
+            .flatten()
+            .for_each(|p| extend_bbox(bbox, p)),
+        Value::GeometryCollection(geometries) => geometries
+            .iter()
+            .for_each(|geometry| extend_bbox_with_geometry(bbox, &geometry.value)),
+    }
+}
+
+/// GeoJSONのプロパティの値の型の名前と、値を文字列に変換した値を返す。
+fn describe_json_value(value: &serde_json::Value) -> (&'static str, Option<String>) {
+    match value {
+        serde_json::Value::Null => ("null", None),
+        serde_json::Value::Bool(value) => ("boolean", Some(value.to_string())),
+        serde_json::Value::Number(value) => ("number", Some(value.to_string())),
+        serde_json::Value::String(value) => ("string", Some(value.clone())),
+        serde_json::Value::Array(_) => ("array", Some(value.to_string())),
+        serde_json::Value::Object(_) => ("object", Some(value.to_string())),
+    }
+}
+
+/// GeoJSONファイルに記録されているフィーチャーの概要を求める。
+///
+/// # Arguments
+///
+/// * `path` - GeoJSONファイルのパス。
+/// * `samples` - プロパティごとに記録する値の例の最大数。
+///
+/// # Returns
+///
+/// フィーチャーの概要。
+fn inspect_geojson(path: &Path, samples: usize) -> anyhow::Result<Summary> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("ファイル({})を読み込めません。{}", path.display(), e))?;
+    let mut summary = Summary {
+        format: "geojson",
+        ..Default::default()
+    };
+    let features = match content.parse::<GeoJson>()? {
+        GeoJson::FeatureCollection(fc) => {
+            summary.srid = srid_from_geojson_crs(fc.foreign_members.as_ref());
+            fc.features
+        }
+        GeoJson::Feature(feature) => vec![feature],
+        GeoJson::Geometry(_) => {
+            return Err(anyhow!(
+                "ファイル({})にフィーチャーが記録されていません。",
+                path.display()
+            ))
+        }
+    };
+    for feature in features {
+        summary.features += 1;
+        let geometry_type = match feature.geometry.as_ref() {
+            Some(geometry) => {
+                extend_bbox_with_geometry(&mut summary.bbox, &geometry.value);
+                geometry_type_name(&geometry.value)
+            }
+            None => "null",
+        };
+        *summary
+            .geometry_types
+            .entry(geometry_type.to_string())
+            .or_default() += 1;
+        for (key, value) in feature.properties.iter().flatten() {
+            let (type_name, value) = describe_json_value(value);
+            summary
+                .properties
+                .entry(key.clone())
+                .or_default()
+                .add(type_name, value, samples);
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Shapeファイルに記録されているフィーチャーの概要を求める。
+///
+/// # Arguments
+///
+/// * `path` - Shapeファイル(*.shp)のパス。
+/// * `encoding` - Shapeファイルの属性データファイルのエンコーディング。
+/// * `samples` - プロパティごとに記録する値の例の最大数。
+///
+/// # Returns
+///
+/// フィーチャーの概要。
+fn inspect_shape_file(path: &Path, encoding: &str, samples: usize) -> anyhow::Result<Summary> {
+    let file = path.to_string_lossy();
+    let mut reader = open_shape_file(&file, encoding, &[])?;
+    let header = reader.header();
+    let shape_type = header.shape_type.to_string();
+    let mut summary = Summary {
+        format: "shapefile",
+        srid: srid_from_prj(&file),
+        ..Default::default()
+    };
+    let bbox = [
+        header.bbox.min.x,
+        header.bbox.min.y,
+        header.bbox.max.x,
+        header.bbox.max.y,
+    ];
+    for feature in iter_features(&mut reader) {
+        let feature = feature?;
+        summary.features += 1;
+        for (name, value) in feature.record.as_ref() {
+            let (type_name, value) = describe_field_value(value);
+            summary
+                .properties
+                .entry(name.clone())
+                .or_default()
+                .add(type_name, value, samples);
+        }
+    }
+    summary.geometry_types.insert(shape_type, summary.features);
+    if 0 < summary.features {
+        summary.bbox = Some(bbox);
+    }
+
+    Ok(summary)
+}
+
+/// GeoJSONファイルまたはShapeファイルに記録されているフィーチャーの数、ジオメトリの型、空間参照系、範囲、
+/// プロパティの名前と値の型及び値の例を出力する。
+///
+/// ファイルの形式は拡張子で判断する。
+///
+/// # Arguments
+///
+/// * `path` - GeoJSONファイル(*.geojson、*.json)またはShapeファイル(*.shp)のパス。
+/// * `encoding` - Shapeファイルの属性データファイルのエンコーディング。
+/// * `samples` - プロパティごとに出力する値の例の最大数。
+/// * `json` - 概要をJSONで出力する場合はtrue。
+pub fn inspect(path: &Path, encoding: &str, samples: usize, json: bool) -> anyhow::Result<()> {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+    let mut summary = match extension.as_deref() {
+        Some("geojson") | Some("json") => inspect_geojson(path, samples)?,
+        Some("shp") => inspect_shape_file(path, encoding, samples)?,
+        _ => {
+            return Err(anyhow!(
+                "ファイル({})の形式を拡張子から判断できません。",
+                path.display()
+            ))
+        }
+    };
+    summary.file = path.display().to_string();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+        return Ok(());
+    }
+    println!("ファイル: {}", summary.file);
+    println!("フィーチャー数: {}", summary.features);
+    println!("ジオメトリ:");
+    for (geometry_type, count) in &summary.geometry_types {
+        println!("  {}: {}件", geometry_type, count);
+    }
+    match summary.srid {
+        Some(srid) => println!("空間参照系: EPSG:{}", srid),
+        None => println!("空間参照系: 記録されていません"),
+    }
+    if let Some([min_x, min_y, max_x, max_y]) = summary.bbox {
+        println!("範囲: {}, {}, {}, {}", min_x, min_y, max_x, max_y);
+    }
+    println!("プロパティ:");
+    for (name, property) in &summary.properties {
+        let types: Vec<String> = property
+            .types
+            .iter()
+            .map(|(type_name, count)| format!("{} {}件", type_name, count))
+            .collect();
+        println!("  {} ({})", name, types.join(", "));
+        if !property.samples.is_empty() {
+            println!("    例: {}", property.samples.join(", "));
+        }
+    }
+
+    Ok(())
+}
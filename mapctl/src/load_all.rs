@@ -0,0 +1,311 @@
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use database::connect_to_database;
+use register_mapping::{import_mapped, BuiltinMapping};
+use register_post_office::import_post_offices;
+use register_prefecture::import_administrative_boundaries;
+use sqlx::PgPool;
+use tokio::sync::Semaphore;
+use utils::import::ImportOptions;
+use utils::prompt::AutoYesPrompter;
+
+use crate::manifest::{read_manifest, Dataset};
+
+/// データセットの登録結果の種類。
+pub enum Outcome {
+    /// 登録した
+    Registered(String),
+    /// 登録しなかった
+    Skipped(String),
+    /// 登録に失敗した
+    Failed(String),
+}
+
+impl Outcome {
+    /// 登録結果を出力するときのラベルを返す。
+    pub fn label(&self) -> &'static str {
+        match self {
+            Outcome::Registered(_) => "OK",
+            Outcome::Skipped(_) => "SKIP",
+            Outcome::Failed(_) => "NG",
+        }
+    }
+
+    /// 登録結果のメッセージを返す。
+    pub fn message(&self) -> &str {
+        match self {
+            Outcome::Registered(message) | Outcome::Skipped(message) | Outcome::Failed(message) => {
+                message
+            }
+        }
+    }
+}
+
+/// データセットの登録結果。
+struct ImportReport {
+    /// データセット
+    dataset: Dataset,
+    /// 登録結果の種類
+    outcome: Outcome,
+    /// 登録に要した時間
+    elapsed: Duration,
+}
+
+/// 組み込みのマッピングに従って、データセットをデータベースに登録する。
+///
+/// 既存のレコードが登録されている場合は、確認せずに削除して登録する。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+/// * `builtin` - 組み込みのマッピング。
+/// * `file` - データセットのファイルのパス。
+/// * `code` - 都道府県コード。
+/// * `srid` - データセットの空間参照系ID。
+/// * `encoding` - Shapeファイルの属性データファイルのエンコーディング。
+/// * `options` - 登録するときのオプション。
+///
+/// # Returns
+///
+/// 登録した場合は登録結果のメッセージ。登録を中止した場合はNone。
+async fn import_builtin(
+    pool: &PgPool,
+    builtin: BuiltinMapping,
+    file: &Path,
+    code: &str,
+    srid: Option<i32>,
+    encoding: &str,
+    options: &ImportOptions,
+) -> anyhow::Result<Option<String>> {
+    let mapping = builtin.mapping();
+    let count = import_mapped(
+        pool,
+        &mapping,
+        &file.to_string_lossy(),
+        Some(code),
+        srid,
+        Some(encoding),
+        options,
+        &AutoYesPrompter,
+    )
+    .await?;
+
+    Ok(count.map(|count| format!("{}{}件", mapping.name, count)))
+}
+
+/// データセットをデータベースに登録する。
+///
+/// 既存のレコードが登録されている場合は、確認せずに削除して登録する。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+/// * `dataset` - 登録するデータセット。
+/// * `options` - 登録するときのオプション。
+///
+/// # Returns
+///
+/// 登録結果。
+async fn import_dataset(pool: &PgPool, dataset: &Dataset, options: &ImportOptions) -> Outcome {
+    let result = match dataset {
+        Dataset::AdministrativeBoundaries { code, file, .. } => import_administrative_boundaries(
+            pool,
+            &file.to_string_lossy(),
+            None,
+            None,
+            code,
+            None,
+            options,
+            &AutoYesPrompter,
+        )
+        .await
+        .map(|summary| summary.map(|summary| summary.to_string())),
+        Dataset::PostOffices {
+            code,
+            file,
+            srid,
+            encoding,
+            ..
+        } => import_post_offices(
+            pool,
+            &file.to_string_lossy(),
+            code,
+            *srid,
+            encoding,
+            options,
+            &AutoYesPrompter,
+        )
+        .await
+        .map(|count| count.map(|count| format!("郵便局{}件", count))),
+        Dataset::Schools {
+            code,
+            file,
+            srid,
+            encoding,
+            ..
+        } => {
+            import_builtin(
+                pool,
+                BuiltinMapping::Schools,
+                file,
+                code,
+                *srid,
+                encoding,
+                options,
+            )
+            .await
+        }
+        Dataset::MedicalInstitutions {
+            code,
+            file,
+            srid,
+            encoding,
+            ..
+        } => {
+            import_builtin(
+                pool,
+                BuiltinMapping::MedicalInstitutions,
+                file,
+                code,
+                *srid,
+                encoding,
+                options,
+            )
+            .await
+        }
+    };
+
+    match result {
+        Ok(Some(message)) => Outcome::Registered(message),
+        Ok(None) => Outcome::Skipped("登録を中止しました。".to_string()),
+        Err(e) => Outcome::Failed(format!("{}", e)),
+    }
+}
+
+/// マニフェストに列挙されたデータセットを、依存関係の順にデータベースに登録する。
+///
+/// 同じ段階のデータセットは、都道府県ごとに並列で登録する。行政区域データの登録に失敗した
+/// 都道府県の郵便局データ、学校データ及び医療機関データは登録しない。
+///
+/// # Arguments
+///
+/// * `manifest` - マニフェストファイルのパス。
+/// * `jobs` - 並列で登録するデータセットの最大数。
+/// * `options` - 登録するときのオプション。
+///
+/// # Returns
+///
+/// すべてのデータセットの登録に成功した場合はtrue。失敗したデータセットがある場合はfalse。
+pub async fn load_all(
+    manifest: &Path,
+    jobs: usize,
+    options: ImportOptions,
+) -> anyhow::Result<bool> {
+    let manifest = read_manifest(manifest)?;
+    let pool = connect_to_database().await?;
+    let semaphore = Arc::new(Semaphore::new(jobs.max(1)));
+
+    let mut stages: Vec<u8> = manifest.datasets.iter().map(Dataset::stage).collect();
+    stages.sort_unstable();
+    stages.dedup();
+
+    let mut reports = Vec::new();
+    let mut failed_codes = HashSet::new();
+    for stage in stages {
+        let mut handles = Vec::new();
+        for dataset in manifest.datasets.iter().filter(|d| d.stage() == stage) {
+            // 前の段階で登録に失敗した都道府県のデータセットは登録しない
+            if failed_codes.contains(dataset.code()) {
+                reports.push(ImportReport {
+                    dataset: dataset.clone(),
+                    outcome: Outcome::Skipped(
+                        "前の段階のデータセットの登録に失敗したため、登録しませんでした。"
+                            .to_string(),
+                    ),
+                    elapsed: Duration::ZERO,
+                });
+                continue;
+            }
+            let pool = pool.clone();
+            let semaphore = semaphore.clone();
+            let task_dataset = dataset.clone();
+            let handle = tokio::spawn(async move {
+                let _permit = semaphore.acquire_owned().await.unwrap();
+                let started = Instant::now();
+                let options = ImportOptions {
+                    year: Some(task_dataset.year()),
+                    ..options
+                };
+                let outcome = import_dataset(&pool, &task_dataset, &options).await;
+                (outcome, started.elapsed())
+            });
+            handles.push((dataset.clone(), handle));
+        }
+        for (dataset, handle) in handles {
+            let (outcome, elapsed) = handle.await.unwrap_or_else(|e| {
+                (
+                    Outcome::Failed(format!("登録処理が異常終了しました。{}", e)),
+                    Duration::ZERO,
+                )
+            });
+            if let Outcome::Failed(_) = outcome {
+                failed_codes.insert(dataset.code().to_string());
+            }
+            reports.push(ImportReport {
+                dataset,
+                outcome,
+                elapsed,
+            });
+        }
+    }
+    print_reports(&reports);
+
+    Ok(reports
+        .iter()
+        .all(|report| !matches!(report.outcome, Outcome::Failed(_))))
+}
+
+/// データセットの登録結果を出力する。
+///
+/// # Arguments
+///
+/// * `reports` - データセットの登録結果を格納したスライス。
+fn print_reports(reports: &[ImportReport]) {
+    println!("登録結果:");
+    for report in reports {
+        println!(
+            "  [{:<4}] {} {:<25} {} {:>8.1}秒 {} {}",
+            report.outcome.label(),
+            report.dataset.code(),
+            report.dataset.kind(),
+            report.dataset.year(),
+            report.elapsed.as_secs_f64(),
+            report.dataset.file().display(),
+            report.outcome.message(),
+        );
+    }
+    print_totals(reports.iter().map(|report| &report.outcome));
+}
+
+/// 登録結果の種類ごとに、データセットの数を出力する。
+///
+/// # Arguments
+///
+/// * `outcomes` - データセットの登録結果の種類。
+pub fn print_totals<'a>(outcomes: impl Iterator<Item = &'a Outcome>) {
+    let (mut registered, mut skipped, mut failed) = (0, 0, 0);
+    for outcome in outcomes {
+        match outcome {
+            Outcome::Registered(_) => registered += 1,
+            Outcome::Skipped(_) => skipped += 1,
+            Outcome::Failed(_) => failed += 1,
+        }
+    }
+    println!(
+        "合計: 成功{}件、スキップ{}件、失敗{}件",
+        registered, skipped, failed
+    );
+}
@@ -0,0 +1,252 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+use dotenvy::dotenv;
+
+mod args;
+mod compare;
+mod doctor;
+mod download;
+mod exit;
+mod history;
+mod inspect;
+mod load_all;
+mod manifest;
+mod mbtiles;
+mod migrate;
+mod register;
+mod seed;
+mod snapshot;
+
+use args::ImportArgs;
+use register::RegisterCommand;
+
+/// 登録したデータを格納するレイヤー(テーブル)の名前。
+const LAYERS: [&str; 8] = [
+    "prefectures",
+    "cities",
+    "post_offices",
+    "railways",
+    "stations",
+    "schools",
+    "medical_institutions",
+    "rivers",
+];
+
+/// 国土数値情報などのデータを登録、管理するコマンドラインツール。
+///
+/// 終了コードは、成功した場合は0、登録に失敗したデータまたは問題が見つかったデータがある場合は1、
+/// 引数が不正な場合は2、ファイルを読み込めない場合は3、データが不正な場合は4、データベースの操作に
+/// 失敗した場合は5。
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Cli {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// データをデータベースに登録する。
+    ///
+    /// 指定された都道府県のレコードが登録されている場合は、削除して登録するか確認する。`--yes`を指定した場合は
+    /// 確認せずに削除して登録し、`--abort-if-exists`を指定した場合は確認せずに登録を中止する。
+    Register {
+        /// フィーチャーを並列に登録する接続の数。
+        ///
+        /// 2以上を指定した場合は、フィーチャーを作業用のテーブルに複数の接続で並列に登録してから、
+        /// 1つのデータベーストランザクションでテーブルに登録する。登録に失敗した場合は、すべての登録を取り消す。
+        #[clap(long, global = true, value_parser = clap::value_parser!(u16).range(1..), default_value_t = 1)]
+        jobs: u16,
+
+        #[clap(subcommand)]
+        dataset: RegisterCommand,
+    },
+
+    /// 国土数値情報ダウンロードサイトから、行政区域データ(N03)または郵便局データ(P30)をダウンロードする。
+    ///
+    /// `--register`を指定した場合は、ダウンロードしたデータを続けてデータベースに登録する。
+    Download(download::DownloadArgs),
+
+    /// GeoJSONファイルまたはShapeファイルに記録されているフィーチャーの概要を出力する。
+    ///
+    /// フィーチャーの数、ジオメトリの型、空間参照系、範囲、プロパティの名前と値の型及び値の例を出力する。
+    Inspect {
+        /// GeoJSONファイル(*.geojson、*.json)またはShapeファイル(*.shp)。
+        #[clap(short, long, value_parser)]
+        file: PathBuf,
+
+        /// Shapeファイルの属性データファイルのエンコーディング。
+        #[clap(short, long, value_parser, default_value = "shift_jis")]
+        encoding: String,
+
+        /// プロパティごとに出力する値の例の最大数。
+        #[clap(long, value_parser, default_value_t = 3)]
+        samples: usize,
+
+        /// 概要をJSONで出力する。
+        #[clap(long, action)]
+        json: bool,
+    },
+
+    /// マニフェストに列挙された国土数値情報のデータセットを、依存関係の順に登録する。
+    ///
+    /// 既存のレコードが登録されている場合は、確認せずに削除して登録する。
+    LoadAll {
+        /// 登録するデータセットを列挙したマニフェスト(TOML)ファイル。
+        #[clap(short, long, value_parser)]
+        manifest: PathBuf,
+
+        /// 並列で登録するデータセットの最大数。
+        #[clap(short, long, value_parser, default_value_t = 4)]
+        jobs: usize,
+
+        // メモリに保持する上限は、データセットごとに適用する
+        #[clap(flatten)]
+        import: ImportArgs,
+    },
+
+    /// 環境変数DATABASE_URLに指定されたデータベースと、別のデータベースに登録されているデータを比較する。
+    ///
+    /// レイヤーごとにレコード数、ジオメトリの範囲及びジオメトリのチェックサムを比較して、差異を出力する。
+    Compare {
+        /// 比較するデータベースへの接続URL。
+        #[clap(short, long, value_parser)]
+        other_url: String,
+    },
+
+    /// レイヤーのスナップショットを保存する。
+    ///
+    /// スナップショットは、データベースの`snapshots`スキーマにレイヤーのテーブルを複製して保存する。
+    Snapshot {
+        /// スナップショットを保存するレイヤー(prefectures、citiesまたはpost_offices)。
+        ///
+        /// 複数指定でき、指定しない場合はすべてのレイヤーのスナップショットを保存する。
+        #[clap(short, long, value_parser)]
+        layer: Vec<String>,
+
+        /// スナップショットのラベル(英数字とアンダースコア)。指定しない場合は現在日時。
+        #[clap(long, value_parser)]
+        label: Option<String>,
+    },
+
+    /// レイヤーを、保存したスナップショットの内容に1つのトランザクションで戻す。
+    Rollback {
+        /// 戻すスナップショットのラベル。
+        #[clap(long, value_parser)]
+        to: String,
+
+        /// 戻すレイヤー。指定しない場合は、スナップショットを保存したすべてのレイヤーを戻す。
+        #[clap(short, long, value_parser)]
+        layer: Vec<String>,
+    },
+
+    /// 保存されているスナップショットの一覧を出力する。
+    Snapshots,
+
+    /// データセットを登録した履歴を、新しい順に出力する。
+    ///
+    /// 登録した日時、レイヤー、都道府県コード、データセットの種類、年度、フィーチャーの数及びファイル名を出力する。
+    Imports {
+        /// 履歴を出力するレイヤー。指定しない場合は、すべてのレイヤーの履歴を出力する。
+        #[clap(short, long, value_parser)]
+        layer: Option<String>,
+
+        /// 履歴を出力する都道府県コード。指定しない場合は、すべての都道府県の履歴を出力する。
+        #[clap(short, long, value_parser)]
+        code: Option<String>,
+
+        /// 出力する履歴の最大数。
+        #[clap(long, value_parser = clap::value_parser!(i64).range(1..), default_value_t = 50)]
+        limit: i64,
+    },
+
+    /// 範囲とズームレベルに含まれるベクタータイルを生成して、ディレクトリに書き込む。
+    ///
+    /// タイルはWebサーバーの`/mvt/{layer}/{z}/{x}/{y}.pbf`と同じ処理で生成するため、静的なWebサーバーで
+    /// 配信すれば、データベースに接続せずに地図を表示できる。
+    SeedTiles(seed::SeedArgs),
+
+    /// レイヤーの範囲とズームレベルに含まれるタイルを生成して、MBTilesファイルに書き込む。
+    ///
+    /// MBTilesファイルは、モバイルのSDKやtileserver-glなどで、データベースに接続せずに配信できる。
+    ExportMbtiles(mbtiles::MbtilesArgs),
+
+    /// データベースで、本アプリが使用する拡張を利用できるか確認する。
+    ///
+    /// 利用できない拡張があれば、インストールする`CREATE EXTENSION`文を出力する。必須の拡張を
+    /// 利用できない場合は、終了コード1で終了する。
+    Doctor,
+
+    /// データベースに、適用していないマイグレーションを適用する。
+    ///
+    /// マイグレーションは、拡張の有効化と、テーブル及び空間インデックスの作成を含むため、空のデータベースを
+    /// 本アプリが利用できる状態にできる。
+    Migrate {
+        /// マイグレーションを適用せずに、適用状況を出力する。
+        ///
+        /// 適用したときと内容が異なるマイグレーションがある場合は、終了コード1で終了する。
+        #[clap(long, action)]
+        status: bool,
+    },
+}
+
+#[tokio::main]
+async fn main() {
+    // 環境変数を読み込み
+    dotenv().ok();
+
+    // コマンドライン引数を読み込み
+    let cli = Cli::parse();
+
+    // 処理が完了した場合は、登録に失敗したデータまたは問題が見つかったデータがないときにtrue
+    let result = match cli.command {
+        Command::Register { jobs, dataset } => register::register(dataset, jobs as usize).await,
+        Command::Download(args) => download::download(args).await,
+        Command::Inspect {
+            file,
+            encoding,
+            samples,
+            json,
+        } => inspect::inspect(&file, &encoding, samples, json).map(|_| true),
+        Command::LoadAll {
+            manifest,
+            jobs,
+            import,
+        } => load_all::load_all(&manifest, jobs, import.options()).await,
+        Command::Compare { other_url } => compare::compare(&other_url).await,
+        Command::Snapshot { layer, label } => snapshot::snapshot(&layer, label.as_deref())
+            .await
+            .map(|_| true),
+        Command::Rollback { to, layer } => snapshot::rollback(&to, &layer).await.map(|_| true),
+        Command::Snapshots => snapshot::list_snapshots().await.map(|_| true),
+        Command::Imports { layer, code, limit } => {
+            history::imports(layer.as_deref(), code.as_deref(), limit)
+                .await
+                .map(|_| true)
+        }
+        Command::SeedTiles(args) => seed::seed_tiles(args).await.map(|_| true),
+        Command::ExportMbtiles(args) => mbtiles::export_mbtiles(args).await.map(|_| true),
+        Command::Doctor => doctor::doctor().await,
+        Command::Migrate { status } => migrate::migrate(status).await,
+    };
+
+    match result {
+        Ok(true) => {}
+        Ok(false) => std::process::exit(exit::EXIT_INCOMPLETE),
+        Err(e) => exit::exit_with_error(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::Cli;
+
+    /// 引数の定義(`conflicts_with`などが参照する引数のIDなど)が正しいことを確認する。
+    #[test]
+    fn cli_is_valid() {
+        Cli::command().debug_assert();
+    }
+}
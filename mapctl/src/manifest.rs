@@ -0,0 +1,207 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use serde::Deserialize;
+
+/// 登録するデータセットを列挙したマニフェスト。
+///
+/// ```toml
+/// [[datasets]]
+/// kind = "administrative_boundaries"
+/// code = "21"
+/// year = 2022
+/// file = "./resources/gifu_prefecture-20220101.geojson"
+///
+/// [[datasets]]
+/// kind = "post_offices"
+/// code = "21"
+/// year = 2013
+/// file = "./resources/gifu_post_offices.shp"
+/// srid = 4612
+/// encoding = "shift_jis"
+///
+/// [[datasets]]
+/// kind = "schools"
+/// code = "21"
+/// year = 2021
+/// file = "./resources/P29-21_21.shp"
+/// encoding = "shift_jis"
+///
+/// [[datasets]]
+/// kind = "medical_institutions"
+/// code = "21"
+/// year = 2020
+/// file = "./resources/P04-20_21.shp"
+/// encoding = "shift_jis"
+/// ```
+#[derive(Debug, Deserialize)]
+pub struct Manifest {
+    /// 登録するデータセット
+    pub datasets: Vec<Dataset>,
+}
+
+/// マニフェストに記録されたデータセット。
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Dataset {
+    /// 行政区域データ(N03)
+    AdministrativeBoundaries {
+        /// 都道府県コード
+        code: String,
+        /// データの年度
+        year: u16,
+        /// GeoJSONファイルのパス
+        file: PathBuf,
+    },
+    /// 郵便局データ(P30)
+    PostOffices {
+        /// 都道府県コード
+        code: String,
+        /// データの年度
+        year: u16,
+        /// Shapeファイルのパス
+        file: PathBuf,
+        /// Shapeファイルの空間参照系ID。省略した場合は空間参照系ファイル(*.prj)から判断する
+        srid: Option<i32>,
+        /// Shapeファイルの属性データファイルのエンコーディング
+        encoding: String,
+    },
+    /// 学校データ(P29)
+    Schools {
+        /// 都道府県コード
+        code: String,
+        /// データの年度
+        year: u16,
+        /// ShapeファイルまたはGeoJSONファイルのパス
+        file: PathBuf,
+        /// 学校データの空間参照系ID。省略した場合は学校データから判断する
+        srid: Option<i32>,
+        /// Shapeファイルの属性データファイルのエンコーディング
+        encoding: String,
+    },
+    /// 医療機関データ(P04)
+    MedicalInstitutions {
+        /// 都道府県コード
+        code: String,
+        /// データの年度
+        year: u16,
+        /// ShapeファイルまたはGeoJSONファイルのパス
+        file: PathBuf,
+        /// 医療機関データの空間参照系ID。省略した場合は医療機関データから判断する
+        srid: Option<i32>,
+        /// Shapeファイルの属性データファイルのエンコーディング
+        encoding: String,
+    },
+}
+
+impl Dataset {
+    /// データセットの種類を返す。
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Dataset::AdministrativeBoundaries { .. } => "administrative_boundaries",
+            Dataset::PostOffices { .. } => "post_offices",
+            Dataset::Schools { .. } => "schools",
+            Dataset::MedicalInstitutions { .. } => "medical_institutions",
+        }
+    }
+
+    /// データセットの都道府県コードを返す。
+    pub fn code(&self) -> &str {
+        match self {
+            Dataset::AdministrativeBoundaries { code, .. } => code,
+            Dataset::PostOffices { code, .. } => code,
+            Dataset::Schools { code, .. } => code,
+            Dataset::MedicalInstitutions { code, .. } => code,
+        }
+    }
+
+    /// データセットの年度を返す。
+    pub fn year(&self) -> u16 {
+        match self {
+            Dataset::AdministrativeBoundaries { year, .. } => *year,
+            Dataset::PostOffices { year, .. } => *year,
+            Dataset::Schools { year, .. } => *year,
+            Dataset::MedicalInstitutions { year, .. } => *year,
+        }
+    }
+
+    /// データセットのファイルのパスを返す。
+    pub fn file(&self) -> &Path {
+        match self {
+            Dataset::AdministrativeBoundaries { file, .. } => file,
+            Dataset::PostOffices { file, .. } => file,
+            Dataset::Schools { file, .. } => file,
+            Dataset::MedicalInstitutions { file, .. } => file,
+        }
+    }
+
+    /// データセットを登録する段階を返す。
+    ///
+    /// 郵便局は市区町村から市区町村名を取得するため、行政区域データを登録した後に登録する。学校と
+    /// 医療機関は、郵便局と同じ段階で登録する。
+    ///
+    /// # Returns
+    ///
+    /// 登録する段階。値が小さい段階から順に登録する。
+    pub fn stage(&self) -> u8 {
+        match self {
+            Dataset::AdministrativeBoundaries { .. } => 0,
+            Dataset::PostOffices { .. }
+            | Dataset::Schools { .. }
+            | Dataset::MedicalInstitutions { .. } => 1,
+        }
+    }
+
+    /// ファイルのパスが相対パスの場合は、指定されたディレクトリからの相対パスとして解決する。
+    fn resolve_file(&mut self, base: &Path) {
+        let file = match self {
+            Dataset::AdministrativeBoundaries { file, .. } => file,
+            Dataset::PostOffices { file, .. } => file,
+            Dataset::Schools { file, .. } => file,
+            Dataset::MedicalInstitutions { file, .. } => file,
+        };
+        if file.is_relative() {
+            *file = base.join(&file);
+        }
+    }
+}
+
+/// マニフェストファイルを読み込む。
+///
+/// データセットのファイルのパスが相対パスの場合は、マニフェストファイルがあるディレクトリからの
+/// 相対パスとして解決する。
+///
+/// # Arguments
+///
+/// * `path` - マニフェストファイルのパス。
+///
+/// # Returns
+///
+/// マニフェスト。
+pub fn read_manifest(path: &Path) -> anyhow::Result<Manifest> {
+    let mut content = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut content))
+        .map_err(|e| {
+            anyhow!(
+                "マニフェストファイル({})を読み込めません。{}",
+                path.display(),
+                e
+            )
+        })?;
+    let mut manifest: Manifest = toml::from_str(&content).map_err(|e| {
+        anyhow!(
+            "マニフェストファイル({})の内容が不正です。{}",
+            path.display(),
+            e
+        )
+    })?;
+    let base = path.parent().unwrap_or_else(|| Path::new("."));
+    for dataset in manifest.datasets.iter_mut() {
+        dataset.resolve_file(base);
+    }
+
+    Ok(manifest)
+}
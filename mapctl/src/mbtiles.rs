@@ -0,0 +1,269 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use anyhow::anyhow;
+use clap::{Args, ValueEnum};
+use flate2::write::GzEncoder;
+use map_server::artifacts::finish_artifact;
+use map_server::guardrails::Bounds;
+use map_server::layers::Layer;
+use map_server::tile_cache::TileFormat;
+use rusqlite::{params, Connection};
+use utils::errors::Failure;
+
+use crate::seed::{generate_tiles, is_empty_tile, resolve_layers, SeedRange};
+
+/// MBTilesファイルに格納するタイルの形式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum MbtilesFormat {
+    /// Mapbox Vector Tile(gzipで圧縮して格納)
+    Mvt,
+    /// GeoJSON
+    Geojson,
+}
+
+impl MbtilesFormat {
+    /// タイルの形式を返す。
+    fn tile_format(self) -> TileFormat {
+        match self {
+            MbtilesFormat::Mvt => TileFormat::Mvt,
+            MbtilesFormat::Geojson => TileFormat::GeoJson,
+        }
+    }
+
+    /// メタデータの`format`に記録する値を返す。
+    fn metadata(self) -> &'static str {
+        match self {
+            MbtilesFormat::Mvt => "pbf",
+            MbtilesFormat::Geojson => "application/geo+json",
+        }
+    }
+}
+
+/// `export-mbtiles`サブコマンドの引数。
+#[derive(Args, Debug)]
+pub struct MbtilesArgs {
+    /// タイルを格納するレイヤー。
+    ///
+    /// prefectures、cities、post_offices、railways、stations、schools、medical_institutionsまたはriversを
+    /// 指定できる。
+    #[clap(short, long, value_parser)]
+    layer: String,
+
+    /// タイルの形式。
+    #[clap(short, long, value_enum, default_value = "mvt")]
+    format: MbtilesFormat,
+
+    /// タイルを格納する範囲(west,south,east,north形式のWGS84経緯度)。
+    #[clap(short, long, value_parser)]
+    bbox: Bounds,
+
+    /// タイルを格納するズームレベルの最小値。
+    #[clap(long, value_parser, default_value_t = 0)]
+    min_zoom: u8,
+
+    /// タイルを格納するズームレベルの最大値。
+    #[clap(long, value_parser)]
+    max_zoom: u8,
+
+    /// 書き込むMBTilesファイル。
+    #[clap(short, long, value_parser)]
+    output: PathBuf,
+
+    /// MBTilesファイルが存在する場合に上書きする。
+    #[clap(long, action)]
+    overwrite: bool,
+
+    /// 並列で生成するタイルの最大数。
+    #[clap(short, long, value_parser, default_value_t = 4)]
+    jobs: usize,
+
+    /// MBTilesファイルをbrotliで圧縮したファイル(拡張子.br)も書き込む。
+    #[clap(long, action)]
+    brotli: bool,
+}
+
+/// MBTilesファイルをbrotliで圧縮するときの圧縮レベル。
+const BROTLI_LEVEL: u32 = 9;
+
+/// MBTilesファイルを作成して、テーブルを作成する。
+///
+/// # Arguments
+///
+/// * `args` - `export-mbtiles`サブコマンドの引数。
+///
+/// # Returns
+///
+/// MBTilesファイルへの接続。
+fn create_mbtiles(args: &MbtilesArgs) -> anyhow::Result<Connection> {
+    if args.output.exists() {
+        if !args.overwrite {
+            return Err(Failure::argument(format!(
+                "MBTilesファイル({})が存在します。上書きする場合は、--overwriteを指定してください。",
+                args.output.display()
+            ))
+            .into());
+        }
+        std::fs::remove_file(&args.output)?;
+    }
+    let connection = Connection::open(&args.output)?;
+    connection.execute_batch(
+        r#"
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE UNIQUE INDEX name ON metadata (name);
+        CREATE TABLE tiles (
+            zoom_level INTEGER, tile_column INTEGER, tile_row INTEGER, tile_data BLOB
+        );
+        CREATE UNIQUE INDEX tile_index ON tiles (zoom_level, tile_column, tile_row);
+        "#,
+    )?;
+
+    Ok(connection)
+}
+
+/// MBTilesファイルのメタデータを返す。
+///
+/// # Arguments
+///
+/// * `args` - `export-mbtiles`サブコマンドの引数。
+///
+/// # Returns
+///
+/// メタデータの名前と値の組。
+fn metadata(args: &MbtilesArgs) -> Vec<(&'static str, String)> {
+    let bbox = args.bbox;
+    let layer = Layer::from_name(&args.layer);
+    let attribution = layer.unwrap_or(Layer::Cities).attribution();
+    let mut metadata = vec![
+        ("name", args.layer.clone()),
+        ("format", args.format.metadata().to_string()),
+        (
+            "bounds",
+            format!("{},{},{},{}", bbox.west, bbox.south, bbox.east, bbox.north),
+        ),
+        (
+            "center",
+            format!(
+                "{},{},{}",
+                (bbox.west + bbox.east) / 2.0,
+                (bbox.south + bbox.north) / 2.0,
+                args.min_zoom
+            ),
+        ),
+        ("minzoom", args.min_zoom.to_string()),
+        ("maxzoom", args.max_zoom.to_string()),
+        ("attribution", attribution.to_string()),
+        ("type", "overlay".to_string()),
+    ];
+    // ベクタータイルの場合は、タイルに含まれるレイヤーと属性を記録する
+    if let (MbtilesFormat::Mvt, Some(layer)) = (args.format, layer) {
+        let fields = layer
+            .fields()
+            .iter()
+            .map(|(name, field_type)| format!(r#""{}":"{}""#, name, field_type))
+            .collect::<Vec<String>>()
+            .join(",");
+        metadata.push((
+            "json",
+            format!(
+                r#"{{"vector_layers":[{{"id":"{}","fields":{{{}}},"minzoom":{},"maxzoom":{}}}]}}"#,
+                layer.name(),
+                fields,
+                args.min_zoom,
+                args.max_zoom
+            ),
+        ));
+    }
+
+    metadata
+}
+
+/// レイヤーの範囲とズームレベルに含まれるタイルを生成して、MBTilesファイルに書き込む。
+///
+/// タイルはWebサーバーと同じ処理で生成して、地物を含まないタイルは書き込まない。ベクタータイルは、
+/// MBTilesの仕様に従ってgzipで圧縮して書き込む。
+///
+/// # Arguments
+///
+/// * `args` - `export-mbtiles`サブコマンドの引数。
+pub async fn export_mbtiles(args: MbtilesArgs) -> anyhow::Result<()> {
+    let format = args.format.tile_format();
+    let layers = resolve_layers(std::slice::from_ref(&args.layer))?;
+    let range = SeedRange::new(args.bbox, args.min_zoom, args.max_zoom)?;
+    let total = range.tiles().count();
+    println!(
+        "{}の{}個のタイルを生成して、{}に書き込みます。",
+        args.layer,
+        total,
+        args.output.display()
+    );
+
+    let started = Instant::now();
+    let mut connection = create_mbtiles(&args)?;
+    let tx = connection.transaction()?;
+    for (name, value) in metadata(&args) {
+        tx.execute(
+            "INSERT INTO metadata (name, value) VALUES (?1, ?2)",
+            params![name, value],
+        )?;
+    }
+    let mut generated = generate_tiles(layers, format, range, args.jobs).await?;
+    let (mut written, mut skipped) = (0, 0);
+    while let Some(result) = generated.recv().await {
+        let (_, tile, body) = result?;
+        if is_empty_tile(format, &body) {
+            skipped += 1;
+            continue;
+        }
+        let body = match args.format {
+            MbtilesFormat::Mvt => {
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(&body)?;
+                encoder.finish()?
+            }
+            MbtilesFormat::Geojson => body,
+        };
+        // MBTilesのタイルの行は、TMS形式(南から北)で数える
+        let row = (1i64 << tile.zoom()) - 1 - tile.y() as i64;
+        tx.execute(
+            "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, ?2, ?3, ?4)",
+            params![tile.zoom(), tile.x(), row, body],
+        )
+        .map_err(|e| anyhow!("タイルをMBTilesファイルに書き込めません。{}", e))?;
+        written += 1;
+    }
+    tx.commit()?;
+    println!(
+        "{}個のタイルを書き込みました(地物を含まないタイル{}個を省略、{}秒)。",
+        written,
+        skipped,
+        started.elapsed().as_secs()
+    );
+
+    // MBTilesファイルに、brotliで圧縮したファイルとSHA-256のマニフェストを添える
+    drop(connection);
+    let file_name = args
+        .output
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let files = finish_artifact(
+        &args.output,
+        &file_name,
+        args.brotli.then_some(BROTLI_LEVEL),
+    )
+    .map_err(|e| anyhow!("MBTilesファイルのマニフェストを書き込めません。{}", e))?;
+    if let Some(brotli) = &files.brotli {
+        println!(
+            "brotliで圧縮したファイルを{}に書き込みました。",
+            brotli.display()
+        );
+    }
+    println!(
+        "チェックサムを{}に書き込みました。",
+        files.manifest.display()
+    );
+
+    Ok(())
+}
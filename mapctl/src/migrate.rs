@@ -0,0 +1,32 @@
+use database::connect_to_database;
+use database::migrations::{migration_statuses, run_migrations};
+
+/// 環境変数DATABASE_URLに指定されたデータベースに、適用していないマイグレーションを適用する。
+///
+/// # Arguments
+///
+/// * `status` - マイグレーションを適用せずに、適用状況を出力する場合はtrue。
+///
+/// # Returns
+///
+/// マイグレーションを適用した場合、または適用状況を確認して内容が変更されたマイグレーションがない場合はtrue。
+pub async fn migrate(status: bool) -> anyhow::Result<bool> {
+    let pool = connect_to_database().await?;
+    if !status {
+        let count = run_migrations(&pool).await?;
+        println!("{}件のマイグレーションを適用しました。", count);
+        return Ok(true);
+    }
+
+    let statuses = migration_statuses(&pool).await?;
+    for status in &statuses {
+        let label = match (status.applied, status.changed) {
+            (true, false) => "適用済み",
+            (true, true) => "変更あり",
+            (false, _) => "未適用",
+        };
+        println!("[{}] {}: {}", label, status.version, status.description);
+    }
+
+    Ok(statuses.iter().all(|status| !status.changed))
+}
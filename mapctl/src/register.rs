@@ -0,0 +1,1081 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::anyhow;
+use clap::Subcommand;
+use database::settings::DatabaseSettings;
+use register_mapping::{
+    import_mapped, import_mapped_sources, validate_mapped, validate_mapped_sources, BuiltinMapping,
+    MappedSource, Mapping,
+};
+use register_post_office::{import_post_offices, validate_post_offices};
+use register_postal_code::{import_postal_codes, validate_postal_codes};
+use register_prefecture::schema::BoundarySchema;
+use register_prefecture::{
+    import_administrative_boundaries, prefecture_code_from_file_name,
+    validate_administrative_boundaries, BoundaryFormat,
+};
+use sqlx::PgPool;
+use utils::archive::{is_zip_archive, ExtractedArchive};
+use utils::errors::Failure;
+use utils::import::ImportOptions;
+use utils::prompt::Prompter;
+
+use crate::args::{ExistingArgs, ImportArgs};
+use crate::load_all::{print_totals, Outcome};
+
+/// 登録するデータの種類。
+#[derive(Subcommand, Debug)]
+pub enum RegisterCommand {
+    /// 国土交通省が配信する行政区域データを、都道府県と市区町村として登録する。
+    Prefecture {
+        /// 国土交通省が配信する行政区域データを記録したGeoJSONファイル、JPGIS準拠のGMLファイル、
+        /// Shapeファイル、またはそれらを格納したZIPアーカイブ。
+        #[clap(short, long, value_parser, required_unless_present = "batch")]
+        file: Option<String>,
+
+        /// 行政区域データに記録されている都道府県のコード。
+        ///
+        /// 国土交通省が配信する行政区域データのファイル名から都道府県コードは得られるが、
+        /// ファイル名が変更されることを考慮して、明示的に引数で指定する。
+        #[clap(short, long, value_parser, required_unless_present = "batch")]
+        code: Option<String>,
+
+        /// 一括で登録する行政区域データを格納したディレクトリ、またはファイルのglobパターン。
+        ///
+        /// ディレクトリを指定した場合は、ディレクトリ内の拡張子が`.geojson`、`.xml`または`.shp`の
+        /// ファイルを登録する。
+        /// 都道府県コードは各ファイルの名前(例えば`N03-22_21_220101.geojson`)から取得して、
+        /// 都道府県コードの順に1つずつ登録する。
+        #[clap(short, long, value_parser, conflicts_with_all = &["file", "code"])]
+        batch: Option<String>,
+
+        /// 行政区域データのファイルの形式(`geojson`、`gml`または`shapefile`)。
+        ///
+        /// 指定しない場合は、ファイルの拡張子が`.xml`または`.gml`のときにGML、`.shp`のときに
+        /// Shapeファイル、それ以外のときにGeoJSONと判断する。
+        #[clap(long, value_parser)]
+        format: Option<BoundaryFormat>,
+
+        /// 行政区域データの属性の構成(`current`、`legacy`または`japanese`)。
+        ///
+        /// `current`は2022年以降、`legacy`は2022年より前の行政区域データ、`japanese`は属性の名前に
+        /// 日本語名を使用する行政区域データ。指定しない場合は、フィーチャーの属性から判断する。
+        #[clap(long, value_parser)]
+        schema: Option<BoundarySchema>,
+
+        /// 行政区域データの空間参照ID。
+        ///
+        /// 指定しない場合は、GeoJSONファイルは`crs`メンバー、GMLファイルは`srsName`属性、Shapeファイルは
+        /// 空間参照系ファイル(*.prj)から判断する。GeoJSONファイルに`crs`メンバーがない場合はEPSG:4326、
+        /// Shapeファイルの空間参照系を判断できない場合はEPSG:6668(JGD2011)とする。
+        #[clap(short, long, value_parser)]
+        srid: Option<i32>,
+
+        #[clap(flatten)]
+        import: ImportArgs,
+
+        #[clap(flatten)]
+        existing: ExistingArgs,
+
+        /// データベースに登録せずにデータを検証して、検証結果を出力する。
+        #[clap(long, action)]
+        dry_run: bool,
+    },
+
+    /// 国土数値情報の郵便局データを登録する。
+    PostOffice {
+        /// 国土数値情報の郵便局データを記録したShapeファイル、GeoJSONファイル、またはShapeファイルを
+        /// 格納したZIPアーカイブ。
+        ///
+        /// 拡張子が`.geojson`または`.json`の場合はGeoJSONファイル、それ以外の場合はShapeファイルと判断する。
+        #[clap(short, long, value_parser)]
+        file: String,
+
+        /// 郵便局データの都道府県コード。
+        #[clap(short, long, value_parser)]
+        code: String,
+
+        /// 郵便局データの空間参照ID。
+        ///
+        /// 指定しない場合は、Shapeファイルは空間参照系ファイル(*.prj)、GeoJSONファイルは`crs`メンバーから
+        /// 判断する。GeoJSONファイルに`crs`メンバーがない場合はEPSG:4326とする。
+        #[clap(short, long, value_parser)]
+        srid: Option<i32>,
+
+        /// Shapeファイルの属性データファイルのエンコーディング。GeoJSONファイルの場合は使用しない。
+        #[clap(short, long, value_parser, default_value = "shift_jis")]
+        encoding: String,
+
+        #[clap(flatten)]
+        import: ImportArgs,
+
+        #[clap(flatten)]
+        existing: ExistingArgs,
+
+        /// データベースに登録せずにデータを検証して、検証結果を出力する。
+        #[clap(long, action)]
+        dry_run: bool,
+    },
+
+    /// 国土数値情報の学校データを登録する。
+    School {
+        /// 国土数値情報の学校データを記録したShapeファイル、GeoJSONファイル、またはそれらを格納した
+        /// ZIPアーカイブ。
+        ///
+        /// 拡張子が`.geojson`または`.json`の場合はGeoJSONファイル、それ以外の場合はShapeファイルと判断する。
+        #[clap(short, long, value_parser)]
+        file: String,
+
+        /// 学校データの都道府県コード。
+        #[clap(short, long, value_parser)]
+        code: String,
+
+        /// 学校データの空間参照ID。
+        ///
+        /// 指定しない場合は、Shapeファイルは空間参照系ファイル(*.prj)、GeoJSONファイルは`crs`メンバーから
+        /// 判断する。GeoJSONファイルに`crs`メンバーがない場合はEPSG:4326とする。
+        #[clap(short, long, value_parser)]
+        srid: Option<i32>,
+
+        /// Shapeファイルの属性データファイルのエンコーディング。GeoJSONファイルの場合は使用しない。
+        #[clap(short, long, value_parser, default_value = "shift_jis")]
+        encoding: String,
+
+        #[clap(flatten)]
+        import: ImportArgs,
+
+        #[clap(flatten)]
+        existing: ExistingArgs,
+
+        /// データベースに登録せずにデータを検証して、検証結果を出力する。
+        #[clap(long, action)]
+        dry_run: bool,
+    },
+
+    /// 国土数値情報の医療機関データを登録する。
+    MedicalInstitution {
+        /// 国土数値情報の医療機関データを記録したShapeファイル、GeoJSONファイル、またはそれらを格納した
+        /// ZIPアーカイブ。
+        ///
+        /// 拡張子が`.geojson`または`.json`の場合はGeoJSONファイル、それ以外の場合はShapeファイルと判断する。
+        #[clap(short, long, value_parser)]
+        file: String,
+
+        /// 医療機関データの都道府県コード。
+        ///
+        /// 医療機関データには行政区域コードが記録されていないため、指定した都道府県コードを医療機関に記録する。
+        #[clap(short, long, value_parser)]
+        code: String,
+
+        /// 医療機関データの空間参照ID。
+        ///
+        /// 指定しない場合は、Shapeファイルは空間参照系ファイル(*.prj)、GeoJSONファイルは`crs`メンバーから
+        /// 判断する。GeoJSONファイルに`crs`メンバーがない場合はEPSG:4326とする。
+        #[clap(short, long, value_parser)]
+        srid: Option<i32>,
+
+        /// Shapeファイルの属性データファイルのエンコーディング。GeoJSONファイルの場合は使用しない。
+        #[clap(short, long, value_parser, default_value = "shift_jis")]
+        encoding: String,
+
+        #[clap(flatten)]
+        import: ImportArgs,
+
+        #[clap(flatten)]
+        existing: ExistingArgs,
+
+        /// データベースに登録せずにデータを検証して、検証結果を出力する。
+        #[clap(long, action)]
+        dry_run: bool,
+    },
+
+    /// 国土数値情報の河川データを登録する。
+    River {
+        /// 国土数値情報の河川データの流路(`Stream`)を記録したShapeファイルまたはGeoJSONファイル。
+        ///
+        /// 拡張子が`.geojson`または`.json`の場合はGeoJSONファイル、それ以外の場合はShapeファイルと判断する。
+        #[clap(short, long, value_parser)]
+        file: String,
+
+        /// 河川データの都道府県コード。
+        ///
+        /// 河川データには行政区域コードが記録されていないため、指定した都道府県コードを河川に記録する。
+        #[clap(short, long, value_parser)]
+        code: String,
+
+        /// 河川データの空間参照ID。
+        ///
+        /// 指定しない場合は、Shapeファイルは空間参照系ファイル(*.prj)、GeoJSONファイルは`crs`メンバーから
+        /// 判断する。GeoJSONファイルに`crs`メンバーがない場合はEPSG:4326とする。
+        #[clap(short, long, value_parser)]
+        srid: Option<i32>,
+
+        /// Shapeファイルの属性データファイルのエンコーディング。GeoJSONファイルの場合は使用しない。
+        #[clap(short, long, value_parser, default_value = "shift_jis")]
+        encoding: String,
+
+        #[clap(flatten)]
+        import: ImportArgs,
+
+        #[clap(flatten)]
+        existing: ExistingArgs,
+
+        /// データベースに登録せずにデータを検証して、検証結果を出力する。
+        #[clap(long, action)]
+        dry_run: bool,
+    },
+
+    /// マッピングファイルの定義に従って、ShapeファイルまたはGeoJSONファイルのデータセットを登録する。
+    ///
+    /// マッピングファイル(TOMLまたはJSON)には、登録するテーブル、ジオメトリの種類、属性とテーブルの列の
+    /// 対応及び都道府県のレコードを特定する列を定義する。
+    Mapping {
+        /// マッピングファイル。拡張子が`.json`の場合はJSON、それ以外の場合はTOMLとして読み込む。
+        #[clap(short, long, value_parser)]
+        mapping: String,
+
+        /// データセットを記録したShapeファイル、GeoJSONファイル、またはそれらを格納したZIPアーカイブ。
+        ///
+        /// 拡張子が`.geojson`または`.json`の場合はGeoJSONファイル、それ以外の場合はShapeファイルと判断する。
+        #[clap(short, long, value_parser)]
+        file: String,
+
+        /// データセットの都道府県コード。
+        ///
+        /// マッピングファイルに`prefecture`を定義していない全国のデータセットの場合は指定しない。
+        #[clap(short, long, value_parser)]
+        code: Option<String>,
+
+        /// データセットの空間参照ID。
+        ///
+        /// 指定しない場合はマッピングファイルの`srid`、マッピングファイルにもない場合はデータセットから
+        /// 判断する。
+        #[clap(short, long, value_parser)]
+        srid: Option<i32>,
+
+        /// Shapeファイルの属性データファイルのエンコーディング。
+        ///
+        /// 指定しない場合はマッピングファイルの`encoding`、マッピングファイルにもない場合はShift_JISとする。
+        #[clap(short, long, value_parser)]
+        encoding: Option<String>,
+
+        #[clap(flatten)]
+        import: ImportArgs,
+
+        #[clap(flatten)]
+        existing: ExistingArgs,
+
+        /// データベースに登録せずにデータを検証して、検証結果を出力する。
+        #[clap(long, action)]
+        dry_run: bool,
+    },
+
+    /// 国土数値情報の鉄道データを、全国の鉄道路線と駅として登録する。
+    Railway {
+        /// 国土数値情報の鉄道データの鉄道路線(`RailroadSection`)を記録したShapeファイル、または
+        /// GeoJSONファイル。
+        ///
+        /// 拡張子が`.geojson`または`.json`の場合はGeoJSONファイル、それ以外の場合はShapeファイルと判断する。
+        #[clap(short, long, value_parser)]
+        railways: String,
+
+        /// 国土数値情報の鉄道データの駅(`Station`)を記録したShapeファイル、またはGeoJSONファイル。
+        #[clap(long, value_parser)]
+        stations: String,
+
+        /// 鉄道データの空間参照ID。
+        ///
+        /// 指定しない場合は、Shapeファイルは空間参照系ファイル(*.prj)、GeoJSONファイルは`crs`メンバーから
+        /// 判断する。GeoJSONファイルに`crs`メンバーがない場合はEPSG:4326とする。
+        #[clap(short, long, value_parser)]
+        srid: Option<i32>,
+
+        /// Shapeファイルの属性データファイルのエンコーディング。GeoJSONファイルの場合は使用しない。
+        #[clap(short, long, value_parser, default_value = "shift_jis")]
+        encoding: String,
+
+        #[clap(flatten)]
+        import: ImportArgs,
+
+        #[clap(flatten)]
+        existing: ExistingArgs,
+
+        /// データベースに登録せずにデータを検証して、検証結果を出力する。
+        #[clap(long, action)]
+        dry_run: bool,
+    },
+
+    /// 日本郵便が公開している郵便番号データを登録する。
+    PostalCode {
+        /// 日本郵便が公開している郵便番号データ(KEN_ALL.CSV)、またはそれを格納したZIPアーカイブ。
+        #[clap(short, long, value_parser)]
+        file: String,
+
+        /// 登録する郵便番号の都道府県コード。
+        #[clap(short, long, value_parser)]
+        code: String,
+
+        /// 郵便番号データのエンコーディング。
+        #[clap(short, long, value_parser, default_value = "shift_jis")]
+        encoding: String,
+
+        #[clap(flatten)]
+        existing: ExistingArgs,
+
+        /// データベースに登録せずにデータを検証して、検証結果を出力する。
+        #[clap(long, action)]
+        dry_run: bool,
+    },
+}
+
+/// 環境変数DATABASE_URLに指定されたデータベースに、データを登録する。
+///
+/// 指定された都道府県のレコードが登録されている場合は、`--yes`または`--abort-if-exists`の指定に従って、
+/// 削除して登録するか判断する。`--upsert`を指定した場合は、既存のレコードを削除せずに、自然キーで照合して
+/// 変更があったレコードだけを登録する。`--dry-run`を指定した場合は、データベースに接続せずにデータを検証する。
+/// `--file`にZIPアーカイブを指定した場合は、一時ディレクトリに展開したファイルを登録する。
+///
+/// # Arguments
+///
+/// * `command` - 登録するデータの種類と引数。
+/// * `jobs` - フィーチャーを並列に登録する接続の数。
+///
+/// # Returns
+///
+/// 登録に失敗したデータがない場合はtrue。一括で登録した行政区域データに、登録に失敗したファイルがある場合、
+/// または検証したデータに問題が見つかった場合はfalse。
+pub async fn register(mut command: RegisterCommand, jobs: usize) -> anyhow::Result<bool> {
+    validate_upsert(&command)?;
+    // 展開したファイルは、登録が終わるまで削除しない
+    let _archive = extract_archive(&mut command)?;
+    if let Some(valid) = dry_run(&command)? {
+        return Ok(valid);
+    }
+    // 並列に登録する接続に加えて、データベーストランザクションの接続を確保する
+    let pool = DatabaseSettings::from_env()?
+        .reserve_connections(jobs as u32 + 1)
+        .connect()
+        .await?;
+    let options = |import: &ImportArgs, existing: &ExistingArgs| ImportOptions {
+        jobs,
+        upsert: existing.upsert,
+        ..import.options()
+    };
+
+    match command {
+        RegisterCommand::Prefecture {
+            batch: Some(batch),
+            format,
+            schema,
+            srid,
+            import,
+            existing,
+            ..
+        } => {
+            return register_prefecture_batch(
+                &pool,
+                &batch,
+                format,
+                schema,
+                srid,
+                options(&import, &existing),
+                existing.prompter(),
+            )
+            .await;
+        }
+        RegisterCommand::Prefecture {
+            file,
+            code,
+            format,
+            schema,
+            srid,
+            import,
+            existing,
+            ..
+        } => {
+            // `--batch`を指定しない場合、clapが`--file`と`--code`の指定を必須にする
+            let (file, code) = (file.unwrap(), code.unwrap());
+            let summary = import_administrative_boundaries(
+                &pool,
+                &file,
+                format,
+                schema,
+                &code,
+                srid,
+                &options(&import, &existing),
+                existing.prompter().as_ref(),
+            )
+            .await?;
+            if let Some(summary) = summary {
+                println!("{}を登録しました。", summary);
+            }
+        }
+        RegisterCommand::PostOffice {
+            file,
+            code,
+            srid,
+            encoding,
+            import,
+            existing,
+            ..
+        } => {
+            let count = import_post_offices(
+                &pool,
+                &file,
+                &code,
+                srid,
+                &encoding,
+                &options(&import, &existing),
+                existing.prompter().as_ref(),
+            )
+            .await?;
+            if let Some(count) = count {
+                println!("郵便局{}件を登録しました。", count);
+            }
+        }
+        RegisterCommand::School {
+            file,
+            code,
+            srid,
+            encoding,
+            import,
+            existing,
+            ..
+        } => {
+            import_builtin(
+                &pool,
+                BuiltinMapping::Schools,
+                &file,
+                &code,
+                srid,
+                &encoding,
+                &options(&import, &existing),
+                existing.prompter().as_ref(),
+            )
+            .await?;
+        }
+        RegisterCommand::MedicalInstitution {
+            file,
+            code,
+            srid,
+            encoding,
+            import,
+            existing,
+            ..
+        } => {
+            import_builtin(
+                &pool,
+                BuiltinMapping::MedicalInstitutions,
+                &file,
+                &code,
+                srid,
+                &encoding,
+                &options(&import, &existing),
+                existing.prompter().as_ref(),
+            )
+            .await?;
+        }
+        RegisterCommand::Mapping {
+            mapping,
+            file,
+            code,
+            srid,
+            encoding,
+            import,
+            existing,
+            ..
+        } => {
+            let mapping = Mapping::from_file(&mapping)?;
+            let count = import_mapped(
+                &pool,
+                &mapping,
+                &file,
+                code.as_deref(),
+                srid,
+                encoding.as_deref(),
+                &options(&import, &existing),
+                existing.prompter().as_ref(),
+            )
+            .await?;
+            if let Some(count) = count {
+                println!("{}{}件を登録しました。", mapping.name, count);
+            }
+        }
+        RegisterCommand::River {
+            file,
+            code,
+            srid,
+            encoding,
+            import,
+            existing,
+            ..
+        } => {
+            import_builtin(
+                &pool,
+                BuiltinMapping::Rivers,
+                &file,
+                &code,
+                srid,
+                &encoding,
+                &options(&import, &existing),
+                existing.prompter().as_ref(),
+            )
+            .await?;
+        }
+        RegisterCommand::Railway {
+            railways,
+            stations,
+            srid,
+            encoding,
+            import,
+            existing,
+            ..
+        } => {
+            let (railway, station) = (
+                BuiltinMapping::Railways.mapping(),
+                BuiltinMapping::Stations.mapping(),
+            );
+            let sources = railway_sources(&railway, &railways, &station, &stations);
+            let counts = import_mapped_sources(
+                &pool,
+                &sources,
+                None,
+                srid,
+                Some(&encoding),
+                &options(&import, &existing),
+                existing.prompter().as_ref(),
+            )
+            .await?;
+            if let Some(counts) = counts {
+                println!(
+                    "{}{}件、{}{}件を登録しました。",
+                    railway.name, counts[0], station.name, counts[1]
+                );
+            }
+        }
+        RegisterCommand::PostalCode {
+            file,
+            code,
+            encoding,
+            existing,
+            ..
+        } => {
+            let count =
+                import_postal_codes(&pool, &file, &code, &encoding, existing.prompter().as_ref())
+                    .await?;
+            if let Some(count) = count {
+                println!("郵便番号{}件を登録しました。", count);
+            }
+        }
+    }
+
+    Ok(true)
+}
+
+/// 組み込みのマッピングに従って、データセットをデータベースに登録する。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+/// * `builtin` - 組み込みのマッピング。
+/// * `file` - データセットを記録したShapeファイルまたはGeoJSONファイルのパス。
+/// * `code` - 都道府県コード。
+/// * `srid` - データセットの空間参照系ID。
+/// * `encoding` - Shapeファイルの属性データファイルのエンコーディング。
+/// * `options` - 登録するときのオプション。
+/// * `prompter` - 既存のレコードを削除して登録するか確認する処理。
+#[allow(clippy::too_many_arguments)]
+async fn import_builtin(
+    pool: &PgPool,
+    builtin: BuiltinMapping,
+    file: &str,
+    code: &str,
+    srid: Option<i32>,
+    encoding: &str,
+    options: &ImportOptions,
+    prompter: &dyn Prompter,
+) -> anyhow::Result<()> {
+    let mapping = builtin.mapping();
+    let count = import_mapped(
+        pool,
+        &mapping,
+        file,
+        Some(code),
+        srid,
+        Some(encoding),
+        options,
+        prompter,
+    )
+    .await?;
+    if let Some(count) = count {
+        println!("{}{}件を登録しました。", mapping.name, count);
+    }
+
+    Ok(())
+}
+
+/// 鉄道データの鉄道路線と駅のファイルを、マッピングと組み合わせる。
+fn railway_sources<'a>(
+    railway: &'a Mapping,
+    railways: &'a str,
+    station: &'a Mapping,
+    stations: &'a str,
+) -> [MappedSource<'a>; 2] {
+    [
+        MappedSource {
+            mapping: railway,
+            file: railways,
+        },
+        MappedSource {
+            mapping: station,
+            file: stations,
+        },
+    ]
+}
+
+/// `--upsert`に対応していないデータの種類に、`--upsert`が指定されていないか確認する。
+///
+/// # Arguments
+///
+/// * `command` - 登録するデータの種類と引数。
+///
+/// # Returns
+///
+/// `--upsert`に対応していないデータの種類に`--upsert`が指定された場合はエラー。
+fn validate_upsert(command: &RegisterCommand) -> anyhow::Result<()> {
+    let (name, existing) = match command {
+        RegisterCommand::PostalCode { existing, .. } => ("郵便番号データ", existing),
+        _ => return Ok(()),
+    };
+    if existing.upsert {
+        return Err(
+            Failure::argument(format!("{}の登録は`--upsert`に対応していません。", name)).into(),
+        );
+    }
+
+    Ok(())
+}
+
+/// `--file`にZIPアーカイブが指定された場合は、アーカイブを一時ディレクトリに展開して、`--file`を
+/// 展開した登録するファイルのパスに置き換える。
+///
+/// 行政区域データは拡張子が`.geojson`、`.xml`または`.shp`のファイル、郵便局データ、学校データ、
+/// 医療機関データ及びマッピングファイルで定義したデータセットは拡張子が`.shp`(なければ`.geojson`)の
+/// ファイル、郵便番号データは拡張子が`.csv`のファイルを登録する。
+///
+/// # Arguments
+///
+/// * `command` - 登録するデータの種類と引数。
+///
+/// # Returns
+///
+/// ZIPアーカイブを展開した場合は、展開したファイルを格納した一時ディレクトリ。展開しなかった場合はNone。
+fn extract_archive(command: &mut RegisterCommand) -> anyhow::Result<Option<ExtractedArchive>> {
+    let (file, extensions): (&mut String, &[&str]) = match command {
+        RegisterCommand::Prefecture {
+            file: Some(file), ..
+        } => (file, &["geojson", "xml", "shp"]),
+        RegisterCommand::PostOffice { file, .. } => (file, &["shp", "geojson"]),
+        RegisterCommand::School { file, .. } => (file, &["shp", "geojson"]),
+        RegisterCommand::MedicalInstitution { file, .. } => (file, &["shp", "geojson"]),
+        RegisterCommand::Mapping { file, .. } => (file, &["shp", "geojson"]),
+        RegisterCommand::PostalCode { file, .. } => (file, &["csv"]),
+        _ => return Ok(None),
+    };
+    if !is_zip_archive(file) {
+        return Ok(None);
+    }
+    let archive = ExtractedArchive::extract(file)?;
+    let member = archive.find(extensions)?;
+    println!(
+        "ZIPアーカイブ({})を展開しました: {}",
+        file,
+        Path::new(&member)
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+    );
+    *file = member;
+
+    Ok(Some(archive))
+}
+
+/// 行政区域データのファイルの登録結果。
+struct BatchReport {
+    /// ファイル名から取得した都道府県コード
+    code: Option<String>,
+    /// 行政区域データのファイル
+    file: PathBuf,
+    /// 登録結果の種類
+    outcome: Outcome,
+    /// 登録に要した時間
+    elapsed: Duration,
+}
+
+/// 一括で登録する行政区域データのファイルを列挙する。
+///
+/// # Arguments
+///
+/// * `batch` - 行政区域データを格納したディレクトリ、またはファイルのglobパターン。
+///
+/// # Returns
+///
+/// 行政区域データのファイルのパスを格納したベクタ。
+fn list_boundary_files(batch: &str) -> anyhow::Result<Vec<PathBuf>> {
+    let patterns = if Path::new(batch).is_dir() {
+        ["*.geojson", "*.xml", "*.shp"]
+            .iter()
+            .map(|pattern| {
+                Path::new(batch)
+                    .join(pattern)
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect()
+    } else {
+        vec![batch.to_string()]
+    };
+    let mut files = Vec::new();
+    for pattern in &patterns {
+        files.extend(
+            glob::glob(pattern)
+                .map_err(|e| anyhow!("globパターン({})が不正です。{}", pattern, e))?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| anyhow!("ファイルを列挙できません。{}", e))?,
+        );
+    }
+    files.retain(|file| file.is_file());
+    if files.is_empty() {
+        return Err(anyhow!(
+            "登録する行政区域データのファイル({})が見つかりません。",
+            patterns.join("、")
+        ));
+    }
+
+    Ok(files)
+}
+
+/// 一括で登録する行政区域データのファイルを列挙して、ファイル名から取得した都道府県コードの順に並べる。
+///
+/// # Arguments
+///
+/// * `batch` - 行政区域データを格納したディレクトリ、またはファイルのglobパターン。
+///
+/// # Returns
+///
+/// ファイル名から取得した都道府県コード(取得できない場合はNone)とファイルのパスのタプルを格納したベクタ。
+fn list_boundary_targets(batch: &str) -> anyhow::Result<Vec<(Option<String>, PathBuf)>> {
+    let mut targets: Vec<(Option<String>, PathBuf)> = list_boundary_files(batch)?
+        .into_iter()
+        .map(|file| {
+            let code = file
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(prefecture_code_from_file_name);
+            (code, file)
+        })
+        .collect();
+    targets.sort();
+
+    Ok(targets)
+}
+
+/// ディレクトリまたはglobパターンに一致する行政区域データを、都道府県コードの順に1つずつ登録して、
+/// 登録結果を出力する。
+///
+/// 都道府県コードはファイル名から取得する。都道府県コードを取得できないファイルと、同じ都道府県コードの
+/// 2つ目以降のファイルは登録しない。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+/// * `batch` - 行政区域データを格納したディレクトリ、またはファイルのglobパターン。
+/// * `format` - 行政区域データのファイルの形式。Noneの場合はファイルの拡張子から判断する。
+/// * `schema` - 行政区域データの属性の構成。Noneの場合はフィーチャーの属性から判断する。
+/// * `srid` - 行政区域データの空間参照ID。Noneの場合は行政区域データから判断する。
+/// * `options` - 登録するときのオプション。
+/// * `prompter` - 既存のレコードを削除して登録するか確認する処理。
+///
+/// # Returns
+///
+/// すべてのファイルの登録に成功した場合はtrue。失敗したファイルがある場合はfalse。
+async fn register_prefecture_batch(
+    pool: &PgPool,
+    batch: &str,
+    format: Option<BoundaryFormat>,
+    schema: Option<BoundarySchema>,
+    srid: Option<i32>,
+    options: ImportOptions,
+    prompter: Arc<dyn Prompter>,
+) -> anyhow::Result<bool> {
+    let targets = list_boundary_targets(batch)?;
+
+    let mut reports = Vec::new();
+    let mut codes = HashSet::new();
+    for (code, file) in targets {
+        let started = Instant::now();
+        let outcome = match code.as_deref() {
+            None => Outcome::Skipped("ファイル名から都道府県コードを取得できません。".to_string()),
+            Some(code) if !codes.insert(code.to_string()) => Outcome::Skipped(
+                "同じ都道府県コードのファイルを先に処理したため、登録しませんでした。".to_string(),
+            ),
+            Some(code) => {
+                println!(
+                    "都道府県({})の行政区域データを登録: {}",
+                    code,
+                    file.display()
+                );
+                let pool = pool.clone();
+                let prompter = prompter.clone();
+                let code = code.to_string();
+                let path = file.to_string_lossy().into_owned();
+                // 行政区域データを読み込めない場合のパニックを、そのファイルの登録の失敗として扱う
+                let handle = tokio::spawn(async move {
+                    import_administrative_boundaries(
+                        &pool,
+                        &path,
+                        format,
+                        schema,
+                        &code,
+                        srid,
+                        &options,
+                        prompter.as_ref(),
+                    )
+                    .await
+                });
+                match handle.await {
+                    Ok(Ok(Some(summary))) => Outcome::Registered(summary.to_string()),
+                    Ok(Ok(None)) => Outcome::Skipped("登録を中止しました。".to_string()),
+                    Ok(Err(e)) => Outcome::Failed(format!("{}", e)),
+                    Err(e) => Outcome::Failed(format!("登録処理が異常終了しました。{}", e)),
+                }
+            }
+        };
+        reports.push(BatchReport {
+            code,
+            file,
+            outcome,
+            elapsed: started.elapsed(),
+        });
+    }
+
+    println!("登録結果:");
+    for report in &reports {
+        println!(
+            "  [{:<4}] {} {:>8.1}秒 {} {}",
+            report.outcome.label(),
+            report.code.as_deref().unwrap_or("--"),
+            report.elapsed.as_secs_f64(),
+            report.file.display(),
+            report.outcome.message(),
+        );
+    }
+    print_totals(reports.iter().map(|report| &report.outcome));
+
+    Ok(reports
+        .iter()
+        .all(|report| !matches!(report.outcome, Outcome::Failed(_))))
+}
+
+/// `--dry-run`が指定された場合に、データベースに登録せずにデータを検証して、検証結果を出力する。
+///
+/// # Arguments
+///
+/// * `command` - 登録するデータの種類と引数。
+///
+/// # Returns
+///
+/// `--dry-run`が指定されていない場合はNone。指定された場合は、問題が見つからなかったときにtrue。
+fn dry_run(command: &RegisterCommand) -> anyhow::Result<Option<bool>> {
+    let report = match command {
+        RegisterCommand::Prefecture {
+            batch: Some(batch),
+            format,
+            schema,
+            srid,
+            import,
+            dry_run: true,
+            ..
+        } => {
+            return dry_run_prefecture_batch(batch, *format, *schema, *srid, &import.options())
+                .map(Some)
+        }
+        RegisterCommand::Prefecture {
+            file: Some(file),
+            code: Some(code),
+            format,
+            schema,
+            srid,
+            import,
+            dry_run: true,
+            ..
+        } => validate_administrative_boundaries(
+            file,
+            *format,
+            *schema,
+            code,
+            *srid,
+            &import.options(),
+        )?,
+        RegisterCommand::PostOffice {
+            file,
+            code,
+            srid,
+            encoding,
+            import,
+            dry_run: true,
+            ..
+        } => validate_post_offices(file, code, *srid, encoding, &import.options())?,
+        RegisterCommand::School {
+            file,
+            code,
+            srid,
+            encoding,
+            import,
+            dry_run: true,
+            ..
+        } => validate_mapped(
+            &BuiltinMapping::Schools.mapping(),
+            file,
+            Some(code),
+            *srid,
+            Some(encoding),
+            &import.options(),
+        )?,
+        RegisterCommand::MedicalInstitution {
+            file,
+            code,
+            srid,
+            encoding,
+            import,
+            dry_run: true,
+            ..
+        } => validate_mapped(
+            &BuiltinMapping::MedicalInstitutions.mapping(),
+            file,
+            Some(code),
+            *srid,
+            Some(encoding),
+            &import.options(),
+        )?,
+        RegisterCommand::Mapping {
+            mapping,
+            file,
+            code,
+            srid,
+            encoding,
+            import,
+            dry_run: true,
+            ..
+        } => validate_mapped(
+            &Mapping::from_file(mapping)?,
+            file,
+            code.as_deref(),
+            *srid,
+            encoding.as_deref(),
+            &import.options(),
+        )?,
+        RegisterCommand::River {
+            file,
+            code,
+            srid,
+            encoding,
+            import,
+            dry_run: true,
+            ..
+        } => validate_mapped(
+            &BuiltinMapping::Rivers.mapping(),
+            file,
+            Some(code),
+            *srid,
+            Some(encoding),
+            &import.options(),
+        )?,
+        RegisterCommand::Railway {
+            railways,
+            stations,
+            srid,
+            encoding,
+            import,
+            dry_run: true,
+            ..
+        } => {
+            let (railway, station) = (
+                BuiltinMapping::Railways.mapping(),
+                BuiltinMapping::Stations.mapping(),
+            );
+            validate_mapped_sources(
+                &railway_sources(&railway, railways, &station, stations),
+                None,
+                *srid,
+                Some(encoding),
+                &import.options(),
+            )?
+        }
+        RegisterCommand::PostalCode {
+            file,
+            code,
+            encoding,
+            dry_run: true,
+            ..
+        } => validate_postal_codes(file, code, encoding)?,
+        _ => return Ok(None),
+    };
+    report.print();
+
+    Ok(Some(report.is_valid()))
+}
+
+/// ディレクトリまたはglobパターンに一致する行政区域データを、データベースに登録せずに1つずつ検証して、
+/// 検証結果を出力する。
+///
+/// # Arguments
+///
+/// * `batch` - 行政区域データを格納したディレクトリ、またはファイルのglobパターン。
+/// * `format` - 行政区域データのファイルの形式。Noneの場合はファイルの拡張子から判断する。
+/// * `schema` - 行政区域データの属性の構成。Noneの場合はフィーチャーの属性から判断する。
+/// * `srid` - 行政区域データの空間参照ID。Noneの場合は行政区域データから判断する。
+/// * `options` - 登録するときのオプション。
+///
+/// # Returns
+///
+/// すべてのファイルに問題が見つからなかった場合はtrue。問題が見つかったファイルがある場合はfalse。
+fn dry_run_prefecture_batch(
+    batch: &str,
+    format: Option<BoundaryFormat>,
+    schema: Option<BoundarySchema>,
+    srid: Option<i32>,
+    options: &ImportOptions,
+) -> anyhow::Result<bool> {
+    let mut valid = true;
+    for (code, file) in list_boundary_targets(batch)? {
+        let path = file.to_string_lossy();
+        let code = match code {
+            Some(code) => code,
+            None => {
+                println!("ファイル: {}", path);
+                println!("問題: ファイル名から都道府県コードを取得できません。");
+                println!();
+                valid = false;
+                continue;
+            }
+        };
+        match validate_administrative_boundaries(&path, format, schema, &code, srid, options) {
+            Ok(report) => {
+                report.print();
+                valid &= report.is_valid();
+            }
+            Err(e) => {
+                println!("ファイル: {}", path);
+                println!("問題: {}", e);
+                valid = false;
+            }
+        }
+        println!();
+    }
+
+    Ok(valid)
+}
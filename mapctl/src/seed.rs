@@ -0,0 +1,334 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use anyhow::anyhow;
+use clap::Args;
+use database::connect_to_database;
+use map_server::config::TileSettings;
+use map_server::guardrails::Bounds;
+use map_server::handlers::{layer_geojson_tile, layer_vector_tile};
+use map_server::layers::{Layer, TILE_MAX_ZOOM};
+use map_server::tile_cache::{TileFormat, TILE_LAYERS};
+use sqlx::PgPool;
+use tokio::sync::mpsc;
+use utils::errors::Failure;
+use utils::tiles::{Tile, TileBounds};
+
+/// レイヤー名と、そのレイヤーのタイルを順に返すイテレーター。
+type TileQueue = Box<dyn Iterator<Item = (&'static str, Tile)> + Send>;
+
+/// 生成したタイル(レイヤー名、タイル、タイルの内容)。
+pub type GeneratedTile = (&'static str, Tile, Vec<u8>);
+
+/// `seed-tiles`サブコマンドの引数。
+#[derive(Args, Debug)]
+pub struct SeedArgs {
+    /// タイルを生成するレイヤー(cities、post_offices、railways、stations、schools、medical_institutions
+    /// またはrivers)。
+    ///
+    /// 複数指定でき、指定しない場合はすべてのレイヤーのタイルを生成する。
+    #[clap(short, long, value_parser)]
+    layer: Vec<String>,
+
+    /// タイルを生成する範囲(west,south,east,north形式のWGS84経緯度)。
+    #[clap(short, long, value_parser)]
+    bbox: Bounds,
+
+    /// タイルを生成するズームレベルの最小値。
+    #[clap(long, value_parser, default_value_t = 0)]
+    min_zoom: u8,
+
+    /// タイルを生成するズームレベルの最大値。
+    #[clap(long, value_parser)]
+    max_zoom: u8,
+
+    /// タイルを書き込むディレクトリ。
+    ///
+    /// タイルは`{layer}/{z}/{x}/{y}.pbf`に書き込む。
+    #[clap(short, long, value_parser)]
+    output: PathBuf,
+
+    /// 並列で生成するタイルの最大数。
+    #[clap(short, long, value_parser, default_value_t = 4)]
+    jobs: usize,
+
+    /// 地物を含まないタイルを書き込まない。
+    #[clap(long, action)]
+    skip_empty: bool,
+}
+
+/// 生成するタイルの範囲とズームレベル。
+#[derive(Debug, Clone, Copy)]
+pub struct SeedRange {
+    /// タイルを生成する範囲(WGS84経緯度)
+    pub bounds: TileBounds,
+    /// タイルを生成するズームレベルの最小値
+    pub min_zoom: u8,
+    /// タイルを生成するズームレベルの最大値
+    pub max_zoom: u8,
+}
+
+impl SeedRange {
+    /// 生成するタイルの範囲とズームレベルを構築する。
+    ///
+    /// # Arguments
+    ///
+    /// * `bbox` - タイルを生成する範囲。
+    /// * `min_zoom` - タイルを生成するズームレベルの最小値。
+    /// * `max_zoom` - タイルを生成するズームレベルの最大値。
+    ///
+    /// # Returns
+    ///
+    /// 生成するタイルの範囲とズームレベル。ズームレベルが不正な場合はエラー。
+    pub fn new(bbox: Bounds, min_zoom: u8, max_zoom: u8) -> anyhow::Result<Self> {
+        if TILE_MAX_ZOOM < max_zoom || max_zoom < min_zoom {
+            return Err(Failure::argument(format!(
+                "ズームレベルは、{}以下で最小値({})が最大値({})以下になるように指定してください。",
+                TILE_MAX_ZOOM, min_zoom, max_zoom
+            ))
+            .into());
+        }
+
+        Ok(Self {
+            bounds: TileBounds {
+                min_x: bbox.west,
+                min_y: bbox.south,
+                max_x: bbox.east,
+                max_y: bbox.north,
+            },
+            min_zoom,
+            max_zoom,
+        })
+    }
+
+    /// 範囲とズームレベルに含まれるタイルを、ズームレベルの昇順に返す。
+    pub fn tiles(self) -> impl Iterator<Item = Tile> {
+        (self.min_zoom..=self.max_zoom).flat_map(move |zoom| Tile::covering(zoom, self.bounds))
+    }
+}
+
+/// 指定されたレイヤーを検証する。
+///
+/// # Arguments
+///
+/// * `layers` - レイヤー名を格納したスライス。空の場合はすべてのレイヤー。
+///
+/// # Returns
+///
+/// レイヤー名を格納したベクタ。
+pub fn resolve_layers(layers: &[String]) -> anyhow::Result<Vec<&'static str>> {
+    if layers.is_empty() {
+        return Ok(TILE_LAYERS.to_vec());
+    }
+    layers
+        .iter()
+        .map(|name| {
+            TILE_LAYERS
+                .iter()
+                .find(|layer| *layer == name)
+                .copied()
+                .ok_or_else(|| Failure::argument(format!("レイヤー({})は存在しません。", name)))
+                .map_err(anyhow::Error::from)
+        })
+        .collect()
+}
+
+/// タイルが地物を含まないか確認する。
+///
+/// # Arguments
+///
+/// * `format` - タイルの形式。
+/// * `body` - タイルの内容。
+///
+/// # Returns
+///
+/// 地物を含まない場合はtrue。
+pub fn is_empty_tile(format: TileFormat, body: &[u8]) -> bool {
+    match format {
+        TileFormat::Mvt => body.is_empty(),
+        TileFormat::GeoJson => {
+            match std::str::from_utf8(body).map(str::parse::<geojson::FeatureCollection>) {
+                Ok(Ok(fc)) => fc.features.is_empty(),
+                _ => false,
+            }
+        }
+    }
+}
+
+/// タイルを、Webサーバーと同じ処理で生成する。
+///
+/// # Arguments
+///
+/// * `layer` - レイヤー名。
+/// * `format` - タイルの形式。
+/// * `tile` - タイル。
+/// * `tiles` - タイルの設定。
+/// * `pool` - データベースコネクションプール。
+///
+/// # Returns
+///
+/// タイルの内容。
+async fn generate_tile(
+    layer: &str,
+    format: TileFormat,
+    tile: &Tile,
+    tiles: &TileSettings,
+    pool: &PgPool,
+) -> anyhow::Result<Vec<u8>> {
+    let (zoom, x, y) = (tile.zoom(), tile.x(), tile.y());
+    let generate_error = |e: &dyn std::fmt::Display| {
+        Failure::database(format!(
+            "{}のタイル({}/{}/{})を生成できません。{}",
+            layer, zoom, x, y, e
+        ))
+    };
+    match format {
+        TileFormat::Mvt => {
+            let layer = Layer::from_name(layer)
+                .ok_or_else(|| anyhow!("レイヤー({})は存在しません。", layer))?;
+            layer_vector_tile(layer, zoom, x, y, tiles, pool)
+                .await
+                .map_err(|e| generate_error(&e).into())
+        }
+        TileFormat::GeoJson => layer_geojson_tile(layer, zoom, x, y, tiles, pool)
+            .await
+            .map(|body| body.to_vec())
+            .map_err(|e| generate_error(&e).into()),
+    }
+}
+
+/// キューからタイルを取り出して生成し、チャネルに送信する。
+///
+/// # Arguments
+///
+/// * `queue` - 生成するタイルのキュー。
+/// * `format` - タイルの形式。
+/// * `pool` - データベースコネクションプール。
+/// * `tiles` - タイルの設定。
+/// * `sender` - 生成したタイルを送信するチャネル。
+async fn generate_worker(
+    queue: Arc<Mutex<TileQueue>>,
+    format: TileFormat,
+    pool: PgPool,
+    tiles: TileSettings,
+    sender: mpsc::Sender<anyhow::Result<GeneratedTile>>,
+) {
+    loop {
+        let next = queue.lock().unwrap().next();
+        let (layer, tile) = match next {
+            Some(next) => next,
+            None => break,
+        };
+        let result = generate_tile(layer, format, &tile, &tiles, &pool)
+            .await
+            .map(|body| (layer, tile, body));
+        let failed = result.is_err();
+        // 受信側がタイルの処理を中止した場合、または生成に失敗した場合は、以降のタイルを生成しない
+        if sender.send(result).await.is_err() || failed {
+            break;
+        }
+    }
+}
+
+/// レイヤーごとに、範囲とズームレベルに含まれるすべてのタイルを並列で生成する。
+///
+/// タイルの設定は既定値とし、地物の絞り込みは適用しない。
+///
+/// # Arguments
+///
+/// * `layers` - タイルを生成するレイヤー名。
+/// * `format` - タイルの形式。
+/// * `range` - 生成するタイルの範囲とズームレベル。
+/// * `jobs` - 並列で生成するタイルの最大数。
+///
+/// # Returns
+///
+/// 生成したタイルを受信するチャネル。タイルは生成した順に受信する。
+pub async fn generate_tiles(
+    layers: Vec<&'static str>,
+    format: TileFormat,
+    range: SeedRange,
+    jobs: usize,
+) -> anyhow::Result<mpsc::Receiver<anyhow::Result<GeneratedTile>>> {
+    let queue: TileQueue = Box::new(
+        layers
+            .into_iter()
+            .flat_map(move |layer| range.tiles().map(move |tile| (layer, tile))),
+    );
+    let queue = Arc::new(Mutex::new(queue));
+    let pool = connect_to_database().await?;
+    let jobs = jobs.max(1);
+    let (sender, receiver) = mpsc::channel(jobs * 2);
+    for _ in 0..jobs {
+        tokio::spawn(generate_worker(
+            queue.clone(),
+            format,
+            pool.clone(),
+            TileSettings::default(),
+            sender.clone(),
+        ));
+    }
+
+    Ok(receiver)
+}
+
+/// タイルを書き込むファイルのパスを返す。
+///
+/// # Arguments
+///
+/// * `output` - タイルを書き込むディレクトリ。
+/// * `layer` - レイヤー名。
+/// * `tile` - タイル。
+///
+/// # Returns
+///
+/// タイルを書き込むファイルのパス。
+fn tile_path(output: &Path, layer: &str, tile: &Tile) -> PathBuf {
+    output
+        .join(layer)
+        .join(tile.zoom().to_string())
+        .join(tile.x().to_string())
+        .join(format!("{}.pbf", tile.y()))
+}
+
+/// 範囲とズームレベルに含まれるすべてのタイルを、`/mvt`と同じ処理で生成してディレクトリに書き込む。
+///
+/// # Arguments
+///
+/// * `args` - `seed-tiles`サブコマンドの引数。
+pub async fn seed_tiles(args: SeedArgs) -> anyhow::Result<()> {
+    let layers = resolve_layers(&args.layer)?;
+    let range = SeedRange::new(args.bbox, args.min_zoom, args.max_zoom)?;
+    let total = range.tiles().count() * layers.len();
+    println!(
+        "{}レイヤーの{}個のタイルを生成します。",
+        layers.len(),
+        total
+    );
+
+    let started = Instant::now();
+    let mut generated = generate_tiles(layers, TileFormat::Mvt, range, args.jobs).await?;
+    let (mut written, mut skipped) = (0, 0);
+    while let Some(result) = generated.recv().await {
+        let (layer, tile, mvt) = result?;
+        if args.skip_empty && is_empty_tile(TileFormat::Mvt, &mvt) {
+            skipped += 1;
+            continue;
+        }
+        let path = tile_path(&args.output, layer, &tile);
+        std::fs::create_dir_all(path.parent().unwrap())
+            .and_then(|_| std::fs::write(&path, &mvt))
+            .map_err(|e| anyhow!("タイル({})を書き込めません。{}", path.display(), e))?;
+        written += 1;
+    }
+    println!(
+        "{}個のタイルを{}に書き込みました(地物を含まないタイル{}個を省略、{}秒)。",
+        written,
+        args.output.display(),
+        skipped,
+        started.elapsed().as_secs()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,328 @@
+use anyhow::anyhow;
+use database::connect_to_database;
+use database::imports::{record_import, ImportSource};
+use sqlx::{PgPool, Postgres, Transaction};
+
+use crate::LAYERS;
+
+/// スナップショットのラベルの最大文字数。
+const MAX_LABEL_LENGTH: usize = 40;
+
+/// スナップショットの一覧に出力する、スナップショットの情報。
+#[derive(Debug, sqlx::FromRow)]
+struct SnapshotInfo {
+    /// ラベル
+    label: String,
+    /// レイヤー(テーブル)の名前
+    layer: String,
+    /// レコード数
+    records: i64,
+    /// 作成日時
+    created_at: String,
+}
+
+/// スナップショットのラベルを検証する。
+///
+/// ラベルはテーブル名の一部にするため、英数字とアンダースコアに限る。
+///
+/// # Arguments
+///
+/// * `label` - スナップショットのラベル。
+fn validate_label(label: &str) -> anyhow::Result<()> {
+    if label.is_empty()
+        || MAX_LABEL_LENGTH < label.len()
+        || !label.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return Err(anyhow!(
+            "ラベル({})は、{}文字以内の英数字とアンダースコアで指定してください。",
+            label,
+            MAX_LABEL_LENGTH
+        ));
+    }
+
+    Ok(())
+}
+
+/// 指定されたレイヤーを検証する。
+///
+/// # Arguments
+///
+/// * `layers` - レイヤー(テーブル)の名前を格納したスライス。空の場合はすべてのレイヤー。
+///
+/// # Returns
+///
+/// レイヤーの名前を格納したベクタ。
+fn resolve_layers(layers: &[String]) -> anyhow::Result<Vec<String>> {
+    if layers.is_empty() {
+        return Ok(LAYERS.iter().map(|layer| layer.to_string()).collect());
+    }
+    for layer in layers {
+        if !LAYERS.contains(&layer.as_str()) {
+            return Err(anyhow!(
+                "レイヤー({})は、{}のいずれかで指定してください。",
+                layer,
+                LAYERS.join("、")
+            ));
+        }
+    }
+
+    Ok(layers.to_vec())
+}
+
+/// スナップショットを保存するテーブルの名前を返す。
+///
+/// # Arguments
+///
+/// * `layer` - レイヤー(テーブル)の名前。
+/// * `label` - スナップショットのラベル。
+///
+/// # Returns
+///
+/// スキーマで修飾したテーブルの名前。
+fn snapshot_table(layer: &str, label: &str) -> String {
+    format!(r#"snapshots."{}__{}""#, layer, label)
+}
+
+/// スナップショットのテーブルの列名を取得する。
+///
+/// レイヤーの生成列(簡略化したジオメトリなど)には値を登録できないため、生成列の列名は含めない。
+///
+/// # Arguments
+///
+/// * `tx` - データベーストランザクション。
+/// * `layer` - レイヤー(テーブル)の名前。
+/// * `label` - スナップショットのラベル。
+///
+/// # Returns
+///
+/// 引用符で囲んだ列名をカンマで連結した文字列。
+async fn snapshot_columns(
+    tx: &mut Transaction<'_, Postgres>,
+    layer: &str,
+    label: &str,
+) -> anyhow::Result<String> {
+    let columns: Vec<(String,)> = sqlx::query_as(
+        r#"
+        SELECT s.column_name::text FROM information_schema.columns s
+        WHERE s.table_schema = 'snapshots' AND s.table_name = $1
+            AND NOT EXISTS (
+                SELECT 1 FROM information_schema.columns l
+                WHERE l.table_schema = 'public' AND l.table_name = $2
+                    AND l.column_name = s.column_name AND l.is_generated = 'ALWAYS'
+            )
+        ORDER BY s.ordinal_position
+        "#,
+    )
+    .bind(format!("{}__{}", layer, label))
+    .bind(layer)
+    .fetch_all(&mut *tx)
+    .await?;
+    if columns.is_empty() {
+        return Err(anyhow!(
+            "レイヤー({})のスナップショット({})のテーブルが存在しません。",
+            layer,
+            label
+        ));
+    }
+
+    Ok(columns
+        .iter()
+        .map(|(column,)| format!(r#""{}""#, column))
+        .collect::<Vec<_>>()
+        .join(", "))
+}
+
+/// レイヤーのスナップショットを保存する。
+///
+/// スナップショットは、`snapshots`スキーマにレイヤーのテーブルを複製して保存する。
+///
+/// # Arguments
+///
+/// * `layers` - スナップショットを保存するレイヤー(テーブル)の名前。空の場合はすべてのレイヤー。
+/// * `label` - スナップショットのラベル。指定しない場合は現在日時。
+///
+/// # Returns
+///
+/// 保存したスナップショットのラベル。
+pub async fn snapshot(layers: &[String], label: Option<&str>) -> anyhow::Result<String> {
+    let layers = resolve_layers(layers)?;
+    let pool = connect_to_database().await?;
+    let label = match label {
+        Some(label) => label.to_string(),
+        None => {
+            let (label,): (String,) = sqlx::query_as("SELECT to_char(now(), 'YYYYMMDD_HH24MISS')")
+                .fetch_one(&pool)
+                .await?;
+            label
+        }
+    };
+    validate_label(&label)?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| anyhow!("データベーストランザクションを開始できません。{}", e))?;
+    for layer in layers.iter() {
+        let table = snapshot_table(layer, &label);
+        sqlx::query(&format!("CREATE TABLE {} AS TABLE {}", table, layer))
+            .execute(&mut tx)
+            .await
+            .map_err(|e| {
+                anyhow!(
+                    "レイヤー({})のスナップショット({})を保存できません。{}",
+                    layer,
+                    label,
+                    e
+                )
+            })?;
+        let (records,): (i64,) = sqlx::query_as(&format!("SELECT COUNT(*) FROM {}", table))
+            .fetch_one(&mut tx)
+            .await?;
+        sqlx::query(
+            "INSERT INTO snapshots.layer_snapshots (label, layer, records) VALUES ($1, $2, $3)",
+        )
+        .bind(&label)
+        .bind(layer)
+        .bind(records)
+        .execute(&mut tx)
+        .await?;
+        println!(
+            "レイヤー({})のスナップショット({})を保存しました: {}件",
+            layer, label, records
+        );
+    }
+    tx.commit().await.map_err(|e| {
+        anyhow!(
+            "データベーストランザクションをコミットできませんでした。{}",
+            e
+        )
+    })?;
+
+    Ok(label)
+}
+
+/// レイヤーをスナップショットの内容に戻す。
+///
+/// すべてのレイヤーを1つのトランザクションで戻すため、いずれかのレイヤーを戻せなかった場合は
+/// どのレイヤーも変更しない。
+///
+/// # Arguments
+///
+/// * `label` - 戻すスナップショットのラベル。
+/// * `layers` - 戻すレイヤー(テーブル)の名前。空の場合はスナップショットを保存したすべてのレイヤー。
+pub async fn rollback(label: &str, layers: &[String]) -> anyhow::Result<()> {
+    validate_label(label)?;
+    let pool = connect_to_database().await?;
+    let saved = saved_layers(&pool, label).await?;
+    if saved.is_empty() {
+        return Err(anyhow!("スナップショット({})が存在しません。", label));
+    }
+    let layers = if layers.is_empty() {
+        saved
+    } else {
+        let layers = resolve_layers(layers)?;
+        if let Some(layer) = layers.iter().find(|layer| !saved.contains(layer)) {
+            return Err(anyhow!(
+                "スナップショット({})に、レイヤー({})は保存されていません。",
+                label,
+                layer
+            ));
+        }
+        layers
+    };
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| anyhow!("データベーストランザクションを開始できません。{}", e))?;
+    for layer in layers.iter() {
+        let columns = snapshot_columns(&mut tx, layer, label).await?;
+        sqlx::query(&format!("TRUNCATE TABLE {}", layer))
+            .execute(&mut tx)
+            .await?;
+        let result = sqlx::query(&format!(
+            "INSERT INTO {} ({}) SELECT {} FROM {}",
+            layer,
+            columns,
+            columns,
+            snapshot_table(layer, label)
+        ))
+        .execute(&mut tx)
+        .await
+        .map_err(|e| {
+            anyhow!(
+                "レイヤー({})をスナップショット({})に戻せません。{}",
+                layer,
+                label,
+                e
+            )
+        })?;
+        // データセットの版を更新するため、戻したフィーチャーの数を履歴に記録
+        let source = ImportSource {
+            dataset: "snapshot",
+            code: None,
+            file: label,
+            year: None,
+        };
+        record_import(&mut tx, &source, layer, result.rows_affected() as usize).await?;
+        println!(
+            "レイヤー({})をスナップショット({})に戻しました: {}件",
+            layer,
+            label,
+            result.rows_affected()
+        );
+    }
+    tx.commit().await.map_err(|e| {
+        anyhow!(
+            "データベーストランザクションをコミットできませんでした。{}",
+            e
+        )
+    })?;
+
+    Ok(())
+}
+
+/// スナップショットに保存されているレイヤーを取得する。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+/// * `label` - スナップショットのラベル。
+///
+/// # Returns
+///
+/// レイヤー(テーブル)の名前を格納したベクタ。
+async fn saved_layers(pool: &PgPool, label: &str) -> anyhow::Result<Vec<String>> {
+    let layers: Vec<(String,)> =
+        sqlx::query_as("SELECT layer FROM snapshots.layer_snapshots WHERE label = $1")
+            .bind(label)
+            .fetch_all(pool)
+            .await?;
+
+    Ok(layers.into_iter().map(|(layer,)| layer).collect())
+}
+
+/// 保存されているスナップショットの一覧を出力する。
+pub async fn list_snapshots() -> anyhow::Result<()> {
+    let pool = connect_to_database().await?;
+    let snapshots = sqlx::query_as::<_, SnapshotInfo>(
+        r#"
+        SELECT label, layer, records, to_char(created_at, 'YYYY-MM-DD HH24:MI:SS') as created_at
+        FROM snapshots.layer_snapshots
+        ORDER BY created_at, label, layer
+        "#,
+    )
+    .fetch_all(&pool)
+    .await?;
+    if snapshots.is_empty() {
+        println!("スナップショットは保存されていません。");
+    }
+    for snapshot in snapshots {
+        println!(
+            "{} {:<14} {:>8}件 {}",
+            snapshot.label, snapshot.layer, snapshot.records, snapshot.created_at
+        );
+    }
+
+    Ok(())
+}
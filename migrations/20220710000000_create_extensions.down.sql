@@ -0,0 +1,2 @@
+DROP EXTENSION IF EXISTS pgcrypto;
+DROP EXTENSION IF EXISTS postgis;
@@ -0,0 +1,3 @@
+-- ジオメトリを格納するPostGISと、UUIDを生成するgen_random_uuid関数(PostgreSQL 12以前)を提供するpgcryptoを有効にする。
+CREATE EXTENSION IF NOT EXISTS postgis;
+CREATE EXTENSION IF NOT EXISTS pgcrypto;
@@ -0,0 +1,5 @@
+DROP INDEX idx_post_offices_town;
+ALTER TABLE post_offices
+    DROP COLUMN city_name,
+    DROP COLUMN town,
+    DROP COLUMN block;
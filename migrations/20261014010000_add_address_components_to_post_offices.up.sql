@@ -0,0 +1,6 @@
+ALTER TABLE post_offices
+    ADD COLUMN city_name VARCHAR(80),
+    ADD COLUMN town VARCHAR(80),
+    ADD COLUMN block VARCHAR(40);
+
+CREATE INDEX idx_post_offices_town ON post_offices USING btree (town);
@@ -0,0 +1 @@
+DROP SCHEMA snapshots CASCADE;
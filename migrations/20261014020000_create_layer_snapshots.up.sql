@@ -0,0 +1,8 @@
+CREATE SCHEMA snapshots;
+CREATE TABLE snapshots.layer_snapshots (
+    label VARCHAR(40) NOT NULL,
+    layer VARCHAR(40) NOT NULL,
+    records BIGINT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    PRIMARY KEY (label, layer)
+);
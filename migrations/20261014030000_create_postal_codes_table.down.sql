@@ -0,0 +1,5 @@
+DROP FUNCTION likely_postal_code;
+DROP INDEX idx_post_offices_postal_code;
+ALTER TABLE post_offices
+    DROP COLUMN postal_code;
+DROP TABLE postal_codes;
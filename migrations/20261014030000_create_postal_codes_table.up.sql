@@ -0,0 +1,25 @@
+CREATE TABLE postal_codes (
+    postal_code CHAR(7) NOT NULL,
+    city_code CHAR(5) NOT NULL,
+    prefecture_name VARCHAR(10) NOT NULL,
+    city_name VARCHAR(80) NOT NULL,
+    town VARCHAR(80) NOT NULL,
+    PRIMARY KEY (postal_code, city_code, town)
+);
+
+CREATE INDEX idx_postal_codes_city_code ON postal_codes USING btree (city_code);
+
+ALTER TABLE post_offices
+    ADD COLUMN postal_code CHAR(7);
+
+CREATE INDEX idx_post_offices_postal_code ON post_offices USING btree (postal_code);
+
+-- 市区町村コードと町名から、最も可能性が高い郵便番号を返す。
+-- 町名の先頭に一致する町域のうち最も長い町域、一致しない場合は市区町村全体(町域が空文字列)の郵便番号を返す。
+CREATE FUNCTION likely_postal_code(city CHAR(5), town_name TEXT) RETURNS CHAR(7)
+LANGUAGE sql STABLE AS $$
+    SELECT postal_code FROM postal_codes
+    WHERE city_code = city AND (town = '' OR strpos(town_name, town) = 1)
+    ORDER BY length(town) DESC, postal_code
+    LIMIT 1
+$$;
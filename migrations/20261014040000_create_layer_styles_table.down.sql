@@ -0,0 +1 @@
+DROP TABLE layer_styles;
@@ -0,0 +1,9 @@
+CREATE TABLE layer_styles (
+    layer VARCHAR(40) NOT NULL,
+    fill_color VARCHAR(9),
+    stroke_color VARCHAR(9),
+    icon VARCHAR(40),
+    min_label_zoom SMALLINT,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    PRIMARY KEY (layer)
+);
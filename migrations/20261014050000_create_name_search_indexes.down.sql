@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_post_offices_name_trgm;
+DROP INDEX IF EXISTS idx_cities_name_trgm;
+DROP EXTENSION IF EXISTS pg_trgm;
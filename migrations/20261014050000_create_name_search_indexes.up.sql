@@ -0,0 +1,5 @@
+-- 市区町村と郵便局を名前で検索するときに、部分一致と類似度で検索できるように、トライグラムのインデックスを作成する。
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX idx_cities_name_trgm ON cities USING gin (name gin_trgm_ops);
+CREATE INDEX idx_post_offices_name_trgm ON post_offices USING gin (name gin_trgm_ops);
@@ -0,0 +1,7 @@
+DROP INDEX idx_cities_geom_mid;
+DROP INDEX idx_cities_geom_low;
+ALTER TABLE cities DROP COLUMN geom_mid, DROP COLUMN geom_low;
+
+DROP INDEX idx_prefectures_geom_mid;
+DROP INDEX idx_prefectures_geom_low;
+ALTER TABLE prefectures DROP COLUMN geom_mid, DROP COLUMN geom_low;
@@ -0,0 +1,18 @@
+-- 低いズームレベルのタイルを高速に生成できるように、簡略化したジオメトリを格納する列を追加する。
+-- 簡略化したジオメトリは生成列とするため、登録したときに自動的に計算される。
+-- 許容誤差(メートル)は、その列を使用する最も大きなズームレベルの1ピクセル(256ピクセルのタイル)より小さくする。
+ALTER TABLE prefectures
+    ADD COLUMN geom_low geometry(POLYGON, 3857)
+        GENERATED ALWAYS AS (ST_SimplifyPreserveTopology(geom, 1000)) STORED,
+    ADD COLUMN geom_mid geometry(POLYGON, 3857)
+        GENERATED ALWAYS AS (ST_SimplifyPreserveTopology(geom, 100)) STORED;
+CREATE INDEX idx_prefectures_geom_low ON prefectures USING gist (geom_low);
+CREATE INDEX idx_prefectures_geom_mid ON prefectures USING gist (geom_mid);
+
+ALTER TABLE cities
+    ADD COLUMN geom_low geometry(POLYGON, 3857)
+        GENERATED ALWAYS AS (ST_SimplifyPreserveTopology(geom, 1000)) STORED,
+    ADD COLUMN geom_mid geometry(POLYGON, 3857)
+        GENERATED ALWAYS AS (ST_SimplifyPreserveTopology(geom, 100)) STORED;
+CREATE INDEX idx_cities_geom_low ON cities USING gist (geom_low);
+CREATE INDEX idx_cities_geom_mid ON cities USING gist (geom_mid);
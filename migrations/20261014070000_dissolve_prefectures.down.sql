@@ -0,0 +1,20 @@
+DROP INDEX idx_prefectures_geom_mid;
+DROP INDEX idx_prefectures_geom_low;
+ALTER TABLE prefectures DROP COLUMN geom_mid, DROP COLUMN geom_low;
+
+ALTER TABLE prefectures ALTER COLUMN geom TYPE geometry(GEOMETRY, 3857);
+WITH dissolved AS (
+    DELETE FROM prefectures RETURNING code, name, geom
+)
+INSERT INTO prefectures (id, code, name, geom)
+SELECT gen_random_uuid(), code, name, (ST_Dump(geom)).geom
+FROM dissolved;
+ALTER TABLE prefectures ALTER COLUMN geom TYPE geometry(POLYGON, 3857);
+
+ALTER TABLE prefectures
+    ADD COLUMN geom_low geometry(POLYGON, 3857)
+        GENERATED ALWAYS AS (ST_SimplifyPreserveTopology(geom, 1000)) STORED,
+    ADD COLUMN geom_mid geometry(POLYGON, 3857)
+        GENERATED ALWAYS AS (ST_SimplifyPreserveTopology(geom, 100)) STORED;
+CREATE INDEX idx_prefectures_geom_low ON prefectures USING gist (geom_low);
+CREATE INDEX idx_prefectures_geom_mid ON prefectures USING gist (geom_mid);
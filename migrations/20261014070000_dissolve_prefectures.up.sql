@@ -0,0 +1,22 @@
+-- 都道府県を、都道府県ごとに1つのマルチポリゴンとして登録する。
+-- ジオメトリの型を変更するため、ジオメトリから生成する簡略化したジオメトリの列を削除して、型を変更した後で追加し直す。
+DROP INDEX idx_prefectures_geom_mid;
+DROP INDEX idx_prefectures_geom_low;
+ALTER TABLE prefectures DROP COLUMN geom_mid, DROP COLUMN geom_low;
+
+ALTER TABLE prefectures ALTER COLUMN geom TYPE geometry(MULTIPOLYGON, 3857) USING ST_Multi(geom);
+WITH dissolved AS (
+    DELETE FROM prefectures RETURNING code, name, geom
+)
+INSERT INTO prefectures (id, code, name, geom)
+SELECT gen_random_uuid(), code, name, ST_Multi(ST_CollectionExtract(ST_Union(geom), 3))
+FROM dissolved
+GROUP BY code, name;
+
+ALTER TABLE prefectures
+    ADD COLUMN geom_low geometry(MULTIPOLYGON, 3857)
+        GENERATED ALWAYS AS (ST_SimplifyPreserveTopology(geom, 1000)) STORED,
+    ADD COLUMN geom_mid geometry(MULTIPOLYGON, 3857)
+        GENERATED ALWAYS AS (ST_SimplifyPreserveTopology(geom, 100)) STORED;
+CREATE INDEX idx_prefectures_geom_low ON prefectures USING gist (geom_low);
+CREATE INDEX idx_prefectures_geom_mid ON prefectures USING gist (geom_mid);
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS stations;
+DROP TABLE IF EXISTS railways;
@@ -0,0 +1,29 @@
+-- 国土数値情報の鉄道データ(N02)の鉄道路線と駅を格納するテーブルを作成する。
+-- 駅は駅舎(プラットホーム)を線で記録しているため、地図に点で表示するための位置を生成列に格納する。
+CREATE TABLE railways (
+    id UUID PRIMARY KEY,
+    railway_type CHAR(2) NOT NULL,
+    operator_type CHAR(1) NOT NULL,
+    line_name VARCHAR(80) NOT NULL,
+    operator VARCHAR(80) NOT NULL,
+    geom geometry(MULTILINESTRING, 3857) NOT NULL
+);
+
+CREATE INDEX idx_railways_line_name ON railways USING btree (line_name);
+CREATE INDEX idx_railways_geom ON railways USING gist (geom);
+
+CREATE TABLE stations (
+    id UUID PRIMARY KEY,
+    railway_type CHAR(2) NOT NULL,
+    operator_type CHAR(1) NOT NULL,
+    line_name VARCHAR(80) NOT NULL,
+    operator VARCHAR(80) NOT NULL,
+    name VARCHAR(80) NOT NULL,
+    geom geometry(MULTILINESTRING, 3857) NOT NULL,
+    location geometry(POINT, 3857)
+        GENERATED ALWAYS AS (ST_ClosestPoint(geom, ST_Centroid(geom))) STORED
+);
+
+CREATE INDEX idx_stations_name ON stations USING btree (name);
+CREATE INDEX idx_stations_geom ON stations USING gist (geom);
+CREATE INDEX idx_stations_location ON stations USING gist (location);
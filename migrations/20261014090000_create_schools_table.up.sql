@@ -0,0 +1,15 @@
+-- 国土数値情報の学校データ(P29)の学校を格納するテーブルを作成する。
+CREATE TABLE schools (
+    id UUID PRIMARY KEY,
+    city_code CHAR(5) NOT NULL,
+    school_code VARCHAR(20) NOT NULL,
+    category_code CHAR(5) NOT NULL,
+    name VARCHAR(80) NOT NULL,
+    address VARCHAR(120) NOT NULL,
+    administrator_code CHAR(1),
+    geom geometry(POINT, 3857) NOT NULL
+);
+
+CREATE INDEX idx_schools_city_code ON schools USING btree (city_code);
+CREATE INDEX idx_schools_category_code ON schools USING btree (category_code);
+CREATE INDEX idx_schools_geom ON schools USING gist (geom);
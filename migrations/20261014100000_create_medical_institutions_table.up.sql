@@ -0,0 +1,17 @@
+-- 国土数値情報の医療機関データ(P04)の医療機関を格納するテーブルを作成する。
+-- 医療機関データには行政区域コードが記録されていないため、登録したときの都道府県コードを格納する。
+CREATE TABLE medical_institutions (
+    id UUID PRIMARY KEY,
+    prefecture_code CHAR(2) NOT NULL,
+    institution_type CHAR(1) NOT NULL,
+    name VARCHAR(120) NOT NULL,
+    address VARCHAR(160) NOT NULL,
+    departments TEXT,
+    geom geometry(POINT, 3857) NOT NULL
+);
+
+CREATE INDEX idx_medical_institutions_prefecture_code
+    ON medical_institutions USING btree (prefecture_code);
+CREATE INDEX idx_medical_institutions_institution_type
+    ON medical_institutions USING btree (institution_type);
+CREATE INDEX idx_medical_institutions_geom ON medical_institutions USING gist (geom);
@@ -0,0 +1,15 @@
+-- 国土数値情報の河川データ(W05)の河川(流路)を格納するテーブルを作成する。
+-- 河川データには行政区域コードが記録されていないため、登録したときの都道府県コードを格納する。
+CREATE TABLE rivers (
+    id UUID PRIMARY KEY,
+    prefecture_code CHAR(2) NOT NULL,
+    water_system_code VARCHAR(6) NOT NULL,
+    river_code VARCHAR(10) NOT NULL,
+    section_type CHAR(1) NOT NULL,
+    name VARCHAR(80),
+    geom geometry(MULTILINESTRING, 3857) NOT NULL
+);
+
+CREATE INDEX idx_rivers_prefecture_code ON rivers USING btree (prefecture_code);
+CREATE INDEX idx_rivers_river_code ON rivers USING btree (river_code);
+CREATE INDEX idx_rivers_geom ON rivers USING gist (geom);
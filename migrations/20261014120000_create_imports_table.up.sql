@@ -0,0 +1,12 @@
+-- データセットを登録した履歴を格納するテーブルを作成する。
+-- 登録処理は、レイヤーごとに登録したフィーチャーの数を、登録と同じトランザクションで記録する。
+-- 都道府県ごとに登録しないデータセット(鉄道データなど)は、都道府県コードをNULLとする。
+CREATE TABLE imports (
+    id BIGSERIAL PRIMARY KEY,
+    layer VARCHAR(40) NOT NULL,
+    prefecture_code CHAR(2),
+    features INTEGER NOT NULL,
+    imported_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now()
+);
+
+CREATE INDEX idx_imports_layer_imported_at ON imports USING btree (layer, imported_at);
@@ -0,0 +1,6 @@
+DROP INDEX idx_schools_school_code_key;
+DROP INDEX idx_post_offices_city_code_name_geom_key;
+DROP INDEX idx_cities_code_part_key;
+DROP INDEX idx_prefectures_code_key;
+
+ALTER TABLE cities DROP COLUMN part;
@@ -0,0 +1,15 @@
+-- `mapctl register --upsert`で既存のレコードと照合する自然キーの一意インデックスを作成する。
+-- 市区町村は同じ市区町村コードのポリゴンが複数あるため、登録するときに行政区域データに現れた順に市区町村コードごとに
+-- 0から始まる番号(`part`)を付けて、市区町村コードと番号を自然キーとする。登録済みの市区町村からは行政区域データに
+-- 現れた順を復元できないため、既存の市区町村の番号はNULLとする。番号がNULLの市区町村は自然キーが一致しないため、
+-- 次に行政区域データを登録したときに、登録したポリゴンに置き換わる。
+ALTER TABLE cities ADD COLUMN part INTEGER;
+-- 郵便局は同じ市区町村に同じ名称の郵便局があるため、市区町村コード、名称及び位置を自然キーとする。
+-- 自然キーが重複する既存の郵便局は、1つを残して削除する。
+DELETE FROM post_offices p
+USING post_offices d
+WHERE p.city_code = d.city_code AND p.name = d.name AND p.geom = d.geom AND p.ctid > d.ctid;
+CREATE UNIQUE INDEX idx_prefectures_code_key ON prefectures USING btree (code);
+CREATE UNIQUE INDEX idx_cities_code_part_key ON cities USING btree (code, part);
+CREATE UNIQUE INDEX idx_post_offices_city_code_name_geom_key ON post_offices USING btree (city_code, name, geom);
+CREATE UNIQUE INDEX idx_schools_school_code_key ON schools USING btree (school_code);
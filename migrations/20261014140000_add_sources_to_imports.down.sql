@@ -0,0 +1,6 @@
+DROP INDEX idx_imports_imported_at;
+
+ALTER TABLE imports
+    DROP COLUMN dataset,
+    DROP COLUMN source_file,
+    DROP COLUMN source_year;
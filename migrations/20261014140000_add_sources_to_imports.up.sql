@@ -0,0 +1,8 @@
+-- データセットを登録した履歴に、登録したデータセットの種類、ファイル名及び年度を追加する。
+-- 列を追加する前に記録した履歴は、いずれもNULLとする。
+ALTER TABLE imports
+    ADD COLUMN dataset VARCHAR(80),
+    ADD COLUMN source_file VARCHAR(255),
+    ADD COLUMN source_year SMALLINT;
+
+CREATE INDEX idx_imports_imported_at ON imports USING btree (imported_at);
@@ -0,0 +1,87 @@
+//! リポジトリの`mappings`ディレクトリに格納した、国土数値情報のデータセットのマッピング。
+//!
+//! マッピングファイルは、ビルドするときにクレートに埋め込むため、実行するときにマッピングファイルの
+//! パスを指定する必要はない。
+
+use crate::mapping::Mapping;
+
+/// 組み込みのマッピング。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuiltinMapping {
+    /// 学校データ(P29)
+    Schools,
+    /// 医療機関データ(P04)
+    MedicalInstitutions,
+    /// 河川データ(W05)の流路
+    Rivers,
+    /// 鉄道データ(N02)の鉄道路線
+    Railways,
+    /// 鉄道データ(N02)の駅
+    Stations,
+}
+
+impl BuiltinMapping {
+    /// すべての組み込みのマッピング。
+    pub const ALL: [BuiltinMapping; 5] = [
+        BuiltinMapping::Schools,
+        BuiltinMapping::MedicalInstitutions,
+        BuiltinMapping::Rivers,
+        BuiltinMapping::Railways,
+        BuiltinMapping::Stations,
+    ];
+
+    /// マッピングファイルのパスと内容を返す。
+    fn source(self) -> (&'static str, &'static str) {
+        match self {
+            BuiltinMapping::Schools => {
+                ("mappings/p29.toml", include_str!("../../mappings/p29.toml"))
+            }
+            BuiltinMapping::MedicalInstitutions => {
+                ("mappings/p04.toml", include_str!("../../mappings/p04.toml"))
+            }
+            BuiltinMapping::Rivers => {
+                ("mappings/w05.toml", include_str!("../../mappings/w05.toml"))
+            }
+            BuiltinMapping::Railways => (
+                "mappings/n02_railroad_section.toml",
+                include_str!("../../mappings/n02_railroad_section.toml"),
+            ),
+            BuiltinMapping::Stations => (
+                "mappings/n02_station.toml",
+                include_str!("../../mappings/n02_station.toml"),
+            ),
+        }
+    }
+
+    /// マッピングを返す。
+    ///
+    /// 組み込みのマッピングファイルが不正な場合はパニックする。
+    pub fn mapping(self) -> Mapping {
+        let (path, content) = self.source();
+        Mapping::from_toml(content, path)
+            .unwrap_or_else(|e| panic!("組み込みのマッピングが不正です。{}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_mappings_are_valid() {
+        for builtin in BuiltinMapping::ALL {
+            let mapping = builtin.mapping();
+            assert!(!mapping.columns.is_empty(), "{:?}", builtin);
+        }
+    }
+
+    #[test]
+    fn railway_mappings_are_nationwide() {
+        for builtin in [BuiltinMapping::Railways, BuiltinMapping::Stations] {
+            let mapping = builtin.mapping();
+            assert!(mapping.prefecture.is_none());
+            assert_eq!(mapping.dataset(), "N02");
+        }
+        assert_eq!(BuiltinMapping::Schools.mapping().keys, ["school_code"]);
+    }
+}
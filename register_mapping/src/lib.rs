@@ -0,0 +1,936 @@
+//! マッピングファイルの定義に従って、ShapeファイルまたはGeoJSONファイルのデータセットをデータベースに
+//! 登録する。
+//!
+//! データセットごとに登録するクレートを作成せずに、属性とテーブルの列の対応、ジオメトリの種類及び
+//! 登録するテーブルをマッピングファイルに定義して、読み込み、座標の変換、既存のレコードの削除の確認及び
+//! COPY文による登録を、すべてのデータセットで同じ方法で行う。
+
+use anyhow::Context;
+use database::copy::StagingTable;
+use database::imports::{record_import, ImportSource};
+use database::upsert::{delete_missing, on_conflict_update, UpsertSummary};
+use serde::{Deserialize, Serialize};
+use shapefile::dbase::Record;
+use sqlx::{PgPool, Postgres, Transaction};
+use utils::dry_run::{geometry_problem, srid_problem, DryRunReport};
+use utils::errors::Failure;
+use utils::features::{geojson_to_shape_feature, is_geojson_file, read_geojson, resolve_srid};
+use utils::import::ImportOptions;
+use utils::prefecture::PrefectureCode;
+use utils::progress::Progress;
+use utils::projection::transform_geometry;
+use utils::prompt::{confirm_replace, Prompter};
+use utils::shape::{count_shapes, iter_features, open_shape_file, read_string_field, ShapeFeature};
+use utils::spill::SpillQueue;
+use utils::{to_hex_ewkb, EPSG_WEB_MERCATOR};
+
+pub mod builtin;
+pub mod mapping;
+
+pub use builtin::BuiltinMapping;
+pub use mapping::Mapping;
+use mapping::{Attributes, CodeMatch, ColumnMapping, ColumnType, GeometryKind};
+
+/// 属性データファイルのエンコーディングを指定しない場合のエンコーディング。
+const DEFAULT_ENCODING: &str = "shift_jis";
+
+/// 全国のデータセットの既存のレコードを削除して登録するか確認するときに、都道府県コードの代わりに示す範囲。
+pub const NATIONWIDE: &str = "全国";
+
+/// 登録するデータセットのファイルと、ファイルを登録する方法を定義したマッピング。
+#[derive(Debug, Clone, Copy)]
+pub struct MappedSource<'a> {
+    /// マッピング
+    pub mapping: &'a Mapping,
+    /// データセットを記録したシェイプファイル(*.shp)またはGeoJSONファイル(*.geojson)のパス
+    pub file: &'a str,
+}
+
+/// マッピングに従って変換したフィーチャー。
+#[derive(Serialize, Deserialize)]
+struct MappedFeature {
+    /// ジオメトリ
+    geom: geo_types::Geometry,
+    /// マッピングの`columns`の順に格納した列の値
+    values: Vec<Option<String>>,
+}
+
+/// 属性の値を、列に登録する値の型に変換できるか確認する。
+fn is_valid_value(value: &str, column_type: ColumnType) -> bool {
+    match column_type {
+        ColumnType::Text => true,
+        ColumnType::Integer => value.parse::<i64>().is_ok(),
+        ColumnType::Double => value.parse::<f64>().is_ok(),
+    }
+}
+
+/// 属性から、列に登録する値を読み込む。
+///
+/// 空白だけの値は、値が記録されていないものとして扱う。
+///
+/// # Arguments
+///
+/// * `record` - 属性。
+/// * `column` - 属性と列の対応。
+///
+/// # Returns
+///
+/// 列に登録する値。値が記録されていない場合はNone。
+fn read_value(record: &Record, column: &ColumnMapping) -> Option<String> {
+    match &column.attribute {
+        Attributes::One(name) => read_string_field(record, name)
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty()),
+        Attributes::Many(names) => {
+            let words: Vec<String> = names
+                .iter()
+                .filter_map(|name| read_string_field(record, name))
+                .flat_map(|value| {
+                    value
+                        .split(|c: char| c.is_whitespace() || c == '、' || c == ',')
+                        .filter(|word| !word.is_empty())
+                        .map(str::to_string)
+                        .collect::<Vec<_>>()
+                })
+                .collect();
+
+            (!words.is_empty()).then(|| words.join(" "))
+        }
+    }
+}
+
+/// データセットのフィーチャーを、マッピングに従って変換する。
+///
+/// # Arguments
+///
+/// * `mapping` - マッピング。
+/// * `feature` - データセットから読み込んだフィーチャー。
+/// * `index` - フィーチャーの位置(0から始まる)。
+/// * `code` - 都道府県コード。全国のデータセットの場合はNone。
+/// * `srid` - データセットの空間参照系ID。
+/// * `options` - 登録するときのオプション。
+///
+/// # Returns
+///
+/// 変換したフィーチャー。
+fn convert_feature(
+    mapping: &Mapping,
+    feature: ShapeFeature,
+    index: usize,
+    code: Option<&str>,
+    srid: i32,
+    options: &ImportOptions,
+) -> anyhow::Result<MappedFeature> {
+    let ShapeFeature { geom, record } = feature;
+    let label = match mapping
+        .label_attribute
+        .as_ref()
+        .and_then(|name| read_string_field(&record, name))
+    {
+        Some(value) => format!("{}番目の{}({})", index, mapping.name, value),
+        None => format!("{}番目の{}", index, mapping.name),
+    };
+    // 列の値
+    let mut values = Vec::with_capacity(mapping.columns.len());
+    let prefix = match (&mapping.prefecture, code) {
+        (Some(scope), Some(code)) if scope.matching == CodeMatch::Prefix => {
+            Some((scope.column.as_str(), code))
+        }
+        _ => None,
+    };
+    for column in &mapping.columns {
+        let value = read_value(&record, column);
+        match &value {
+            None if column.required => {
+                return Err(Failure::data(format!(
+                    "{}: 属性{}がありません。",
+                    label, column.attribute
+                ))
+                .into());
+            }
+            Some(value) if !is_valid_value(value, column.column_type) => {
+                return Err(Failure::data(format!(
+                    "{}: 属性{}の値({})を{}に変換できません。",
+                    label,
+                    column.attribute,
+                    value,
+                    column.column_type.sql_type()
+                ))
+                .into());
+            }
+            Some(value)
+                if column
+                    .values
+                    .as_ref()
+                    .is_some_and(|values| !values.contains(value)) =>
+            {
+                return Err(Failure::data(format!(
+                    "{}: 属性{}の値({})が不正です。",
+                    label, column.attribute, value
+                ))
+                .into());
+            }
+            _ => {}
+        }
+        if let Some((scope, code)) = prefix {
+            if column.column == scope && !value.as_deref().unwrap_or_default().starts_with(code) {
+                return Err(Failure::data(format!(
+                    "{}: 属性{}の値({})が都道府県コード({})で始まっていません。",
+                    label,
+                    column.attribute,
+                    value.unwrap_or_default(),
+                    code
+                ))
+                .into());
+            }
+        }
+        values.push(value);
+    }
+    // ジオメトリ
+    let mut geom = mapping.geometry.convert(geom).ok_or_else(|| {
+        Failure::data(format!(
+            "{}: ジオメトリが{}ではありません。",
+            label,
+            mapping.geometry.label()
+        ))
+    })?;
+    if let Some(problem) = geometry_problem(&geom) {
+        return Err(Failure::data(format!("{}: {}", label, problem)).into());
+    }
+    options.fix_axis_order(&mut geom, srid, &label)?;
+    let from = format!("EPSG:{}", srid);
+    let to = format!("EPSG:{}", EPSG_WEB_MERCATOR);
+    transform_geometry(&mut geom, srid, EPSG_WEB_MERCATOR).map_err(|e| {
+        Failure::data(format!(
+            "{}の座標を{}から{}に変換できません。{}",
+            label, from, to, e
+        ))
+    })?;
+    // 座標が範囲内にあるか確認
+    options.bbox_guard.validate(&geom, &label)?;
+
+    Ok(MappedFeature { geom, values })
+}
+
+/// データセットのフィーチャーを1つずつ読み込み、関数に渡す。
+///
+/// # Arguments
+///
+/// * `mapping` - マッピング。
+/// * `file` - データセットのファイルのパス。
+/// * `srid` - データセットの空間参照系ID。Noneの場合はマッピング、マッピングにもない場合はデータセットから判断する。
+/// * `encoding` - シェイプファイルの属性データファイルのエンコーディング。Noneの場合はマッピングに従う。
+/// * `f` - フィーチャーの位置、判断した空間参照系ID及びフィーチャーを受け取る関数。
+fn for_each_feature<F>(
+    mapping: &Mapping,
+    file: &str,
+    srid: Option<i32>,
+    encoding: Option<&str>,
+    mut f: F,
+) -> anyhow::Result<()>
+where
+    F: FnMut(usize, i32, anyhow::Result<ShapeFeature>) -> anyhow::Result<()>,
+{
+    let srid = srid.or(mapping.srid);
+    if is_geojson_file(file) {
+        let fc = read_geojson(file)?;
+        let srid = resolve_srid(file, Some(&fc), srid)?;
+        let total = fc.features.len();
+        let features = Progress::new(&mapping.name, Some(total)).wrap(fc.features.into_iter());
+        for (index, feature) in features.enumerate() {
+            f(index, srid, geojson_to_shape_feature(feature, index))?;
+        }
+    } else {
+        let srid = resolve_srid(file, None, srid)?;
+        let encoding = encoding
+            .or(mapping.encoding.as_deref())
+            .unwrap_or(DEFAULT_ENCODING);
+        let mut reader = open_shape_file(file, encoding, &mapping.geometry.shape_types())?;
+        let features =
+            Progress::new(&mapping.name, count_shapes(file)).wrap(iter_features(&mut reader));
+        for (index, feature) in features.enumerate() {
+            f(index, srid, feature)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// 既存のレコードを特定する条件と、条件に結び付ける値を返す。
+///
+/// # Arguments
+///
+/// * `mapping` - マッピング。
+/// * `code` - 都道府県コード。全国のデータセットの場合はNone。
+///
+/// # Returns
+///
+/// 条件と条件に結び付ける値。全国のデータセットの場合はNone。
+fn scope_condition(mapping: &Mapping, code: Option<&str>) -> Option<(String, String)> {
+    let (scope, code) = mapping.prefecture.as_ref().zip(code)?;
+
+    Some(match scope.matching {
+        CodeMatch::Equal => (format!("{} = $1", scope.column), code.to_string()),
+        CodeMatch::Prefix => (format!("{} LIKE $1", scope.column), format!("{}%", code)),
+    })
+}
+
+/// 条件を満たすレコードを対象にするSQL文を返す。
+///
+/// # Arguments
+///
+/// * `sql` - SQL文。
+/// * `condition` - 既存のレコードを特定する条件。すべてのレコードを対象にする場合はNone。
+///
+/// # Returns
+///
+/// 条件を付けたSQL文。
+fn scoped_sql(sql: String, condition: Option<&(String, String)>) -> String {
+    match condition {
+        Some((condition, _)) => format!("{} WHERE {}", sql, condition),
+        None => sql,
+    }
+}
+
+/// 指定された都道府県のレコードがデータベースに登録されているか確認する。
+///
+/// # Arguments
+///
+/// * `tx` - データベーストランザクション。
+/// * `mapping` - マッピング。
+/// * `code` - 登録されているか確認する都道府県コード。全国のデータセットの場合はNone。
+///
+/// # Returns
+///
+/// 指定された都道府県のレコードが登録されている場合はtrue。登録されていない場合はfalse。
+async fn exists_records(
+    tx: &mut Transaction<'_, Postgres>,
+    mapping: &Mapping,
+    code: Option<&str>,
+) -> anyhow::Result<bool> {
+    let condition = scope_condition(mapping, code);
+    let sql = format!(
+        "SELECT EXISTS ({})",
+        scoped_sql(
+            format!("SELECT 1 FROM {}", mapping.table),
+            condition.as_ref()
+        )
+    );
+    let mut query = sqlx::query_as(&sql);
+    if let Some((_, value)) = condition {
+        query = query.bind(value);
+    }
+    let (exists,): (bool,) = query.fetch_one(tx).await.map_err(|e| {
+        Failure::database(format!(
+            "データベースに登録されている{}を確認するときにエラーが発生しました。{}",
+            mapping.name, e
+        ))
+    })?;
+
+    Ok(exists)
+}
+
+/// 指定された都道府県のレコードをデータベースから削除する。
+///
+/// # Arguments
+///
+/// * `tx` - データベーストランザクション。
+/// * `mapping` - マッピング。
+/// * `code` - レコードを削除する都道府県コード。全国のデータセットの場合はNone。
+async fn delete_records(
+    tx: &mut Transaction<'_, Postgres>,
+    mapping: &Mapping,
+    code: Option<&str>,
+) -> anyhow::Result<()> {
+    let condition = scope_condition(mapping, code);
+    let sql = scoped_sql(format!("DELETE FROM {}", mapping.table), condition.as_ref());
+    let mut query = sqlx::query(&sql);
+    if let Some((_, value)) = condition {
+        query = query.bind(value);
+    }
+    query.execute(tx).await.map_err(|e| {
+        Failure::database(format!(
+            "データベースから{}を削除するときにエラーが発生しました。{}",
+            mapping.name, e
+        ))
+    })?;
+
+    Ok(())
+}
+
+/// 変換したフィーチャーを、一時テーブルに登録する行に変換する。
+fn mapped_row(feature: MappedFeature) -> anyhow::Result<Vec<Option<String>>> {
+    let geom = to_hex_ewkb(&feature.geom, EPSG_WEB_MERCATOR)?;
+    let mut row = feature.values;
+    row.push(Some(geom));
+
+    Ok(row)
+}
+
+/// 変換したフィーチャーをデータベースに登録する。
+///
+/// フィーチャーはCOPY文で一時テーブルにまとめて登録してから、マッピングに定義されたテーブルに登録する。
+/// `options.jobs`が2以上の場合は、一時テーブルに複数の接続で並列に登録する。`options.upsert`がtrueの
+/// 場合は、マッピングの自然キーが一致する既存のレコードを値が変わったときに限り更新し、登録しなかった
+/// 指定された都道府県の既存のレコードを削除する。
+///
+/// # Arguments
+///
+/// * `tx` - データベーストランザクション。
+/// * `pool` - 一時テーブルに並列に登録する接続を取得するデータベースコネクションプール。
+/// * `mapping` - マッピング。
+/// * `code` - 都道府県コード。全国のデータセットの場合はNone。
+/// * `features` - 登録するフィーチャーを格納したキュー。
+/// * `options` - 登録するときのオプション。
+async fn register_features(
+    tx: &mut Transaction<'_, Postgres>,
+    pool: &PgPool,
+    mapping: &Mapping,
+    code: Option<&str>,
+    features: SpillQueue<MappedFeature>,
+    options: &ImportOptions,
+) -> anyhow::Result<()> {
+    let mut columns: Vec<&str> = mapping
+        .columns
+        .iter()
+        .map(|column| column.column.as_str())
+        .collect();
+    columns.push(&mapping.geometry_column);
+    let definitions = mapping
+        .columns
+        .iter()
+        .map(|column| format!("{} text", column.column))
+        .chain(std::iter::once(format!(
+            "{} geometry",
+            mapping.geometry_column
+        )))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let staging =
+        StagingTable::create(&mut *tx, pool, "mapped_rows", &definitions, options.jobs).await?;
+    let rows = features.into_items()?.map(|feature| mapped_row(feature?));
+    let rows = staging.copy(&mut *tx, &columns, rows).await?;
+
+    // 指定された都道府県コードを記録する列
+    let recorded = mapping
+        .prefecture
+        .as_ref()
+        .filter(|scope| scope.matching == CodeMatch::Equal)
+        .map(|scope| scope.column.as_str())
+        .zip(code);
+    let mut targets = vec![mapping.id_column.clone()];
+    let mut values = vec!["gen_random_uuid()".to_string()];
+    if let Some((column, _)) = recorded {
+        targets.push(column.to_string());
+        values.push("$1".to_string());
+    }
+    for column in &mapping.columns {
+        targets.push(column.column.clone());
+        values.push(format!(
+            "r.{}::{}",
+            column.column,
+            column.column_type.sql_type()
+        ));
+    }
+    targets.push(mapping.geometry_column.clone());
+    values.push(match mapping.geometry {
+        GeometryKind::Point => format!("r.{}", mapping.geometry_column),
+        GeometryKind::Line | GeometryKind::Polygon => {
+            format!("ST_Multi(r.{})", mapping.geometry_column)
+        }
+    });
+    let keys: Vec<&str> = mapping.keys.iter().map(String::as_str).collect();
+    let conflict = if options.upsert {
+        let updates: Vec<&str> = targets[1..]
+            .iter()
+            .map(String::as_str)
+            .filter(|column| !keys.contains(column))
+            .collect();
+        on_conflict_update(&mapping.table, &keys, &updates)
+    } else {
+        String::new()
+    };
+    let sql = format!(
+        "INSERT INTO {} ({}) SELECT {} FROM {} r {}",
+        mapping.table,
+        targets.join(", "),
+        values.join(", "),
+        staging.name(),
+        conflict
+    );
+    let result = async {
+        let mut query = sqlx::query(&sql);
+        if let Some((_, code)) = recorded {
+            query = query.bind(code);
+        }
+        let changed = query.execute(&mut *tx).await.map_err(|e| {
+            Failure::database(format!(
+                "データベースに{}を登録するときにエラーが発生しました。{}",
+                mapping.name, e
+            ))
+        })?;
+        if let (true, Some((condition, value))) = (options.upsert, scope_condition(mapping, code)) {
+            let deleted = delete_missing(
+                &mut *tx,
+                &mapping.table,
+                staging.name(),
+                &keys,
+                &condition,
+                &value,
+                &mapping.name,
+            )
+            .await?;
+            let summary = UpsertSummary::new(rows, changed.rows_affected(), deleted);
+            eprintln!("{}: {}", mapping.name, summary);
+        }
+
+        Ok(())
+    }
+    .await;
+    staging.finish(&mut *tx, result).await?;
+
+    Ok(())
+}
+
+/// マッピングに対して、都道府県コードの指定が正しいか確認する。
+///
+/// 都道府県のレコードを特定する列を定義したマッピングには都道府県コードを指定しなければならず、全国の
+/// データセットのマッピングには都道府県コードを指定できない。
+///
+/// # Arguments
+///
+/// * `mapping` - マッピング。
+/// * `code` - 都道府県コード。
+/// * `options` - 登録するときのオプション。
+fn check_arguments(
+    mapping: &Mapping,
+    code: Option<&str>,
+    options: &ImportOptions,
+) -> anyhow::Result<()> {
+    match (&mapping.prefecture, code) {
+        (Some(_), None) => {
+            return Err(Failure::argument(format!(
+                "{}を登録する都道府県コードを指定してください。",
+                mapping.name
+            ))
+            .into())
+        }
+        (None, Some(_)) => {
+            return Err(Failure::argument(format!(
+                "{}は全国のデータセットのため、都道府県コードを指定できません。",
+                mapping.name
+            ))
+            .into())
+        }
+        (_, Some(code)) => {
+            code.parse::<PrefectureCode>().map_err(Failure::argument)?;
+        }
+        (None, None) => {}
+    }
+    if options.upsert && mapping.keys.is_empty() {
+        return Err(Failure::argument(format!(
+            "{}のマッピングには自然キー(keys)が定義されていないため、`--upsert`に対応していません。",
+            mapping.name
+        ))
+        .into());
+    }
+
+    Ok(())
+}
+
+/// マッピングに従ってデータセット(ShapeファイルまたはGeoJSONファイル)を読み込み、データベースに登録する。
+///
+/// データセットの形式は、ファイルの拡張子から判断する。指定された都道府県のレコードが登録されている
+/// 場合は、ユーザーが許可したときに限り、既存のレコードを削除して登録する。`options.upsert`がtrueの場合は、
+/// 既存のレコードを削除せずに、自然キーで照合して変更があったレコードだけを登録する。変換できない
+/// フィーチャーが存在する場合は、登録を中止してエラーを返す。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+/// * `mapping` - マッピング。
+/// * `file` - データセットを記録したシェイプファイル(*.shp)またはGeoJSONファイル(*.geojson)のパス。
+/// * `code` - 都道府県コード。全国のデータセットの場合はNone。
+/// * `srid` - データセットの空間参照系ID。Noneの場合はマッピング、マッピングにもない場合はデータセットから判断する。
+/// * `encoding` - シェイプファイルの属性データファイルのエンコーディング。Noneの場合はマッピングに従う。
+/// * `options` - 登録するときのオプション。
+/// * `prompter` - 既存のレコードを削除して登録するか確認する処理。
+///
+/// # Returns
+///
+/// 登録した場合は登録したフィーチャーの数。登録を中止した場合はNone。
+#[allow(clippy::too_many_arguments)]
+pub async fn import_mapped(
+    pool: &PgPool,
+    mapping: &Mapping,
+    file: &str,
+    code: Option<&str>,
+    srid: Option<i32>,
+    encoding: Option<&str>,
+    options: &ImportOptions,
+    prompter: &dyn Prompter,
+) -> anyhow::Result<Option<usize>> {
+    let sources = [MappedSource { mapping, file }];
+    let counts =
+        import_mapped_sources(pool, &sources, code, srid, encoding, options, prompter).await?;
+
+    Ok(counts.map(|counts| counts[0]))
+}
+
+/// マッピングに従って読み込み、変換したデータセット。
+pub struct MappedDataset {
+    /// 変換したフィーチャーを格納したキュー
+    features: SpillQueue<MappedFeature>,
+}
+
+/// マッピングに従ってデータセット(ShapeファイルまたはGeoJSONファイル)を読み込み、変換する。
+///
+/// ファイルを同期的に読み込むため、非同期のタスクから呼び出す場合は、別のスレッドで呼び出すこと。
+///
+/// # Arguments
+///
+/// * `mapping` - マッピング。
+/// * `file` - データセットを記録したシェイプファイル(*.shp)またはGeoJSONファイル(*.geojson)のパス。
+/// * `code` - 都道府県コード。全国のデータセットの場合はNone。
+/// * `srid` - データセットの空間参照系ID。Noneの場合はマッピング、マッピングにもない場合はデータセットから判断する。
+/// * `encoding` - シェイプファイルの属性データファイルのエンコーディング。Noneの場合はマッピングに従う。
+/// * `options` - 登録するときのオプション。
+///
+/// # Returns
+///
+/// 変換したデータセット。変換できないフィーチャーが存在する場合はエラー。
+pub fn read_mapped(
+    mapping: &Mapping,
+    file: &str,
+    code: Option<&str>,
+    srid: Option<i32>,
+    encoding: Option<&str>,
+    options: &ImportOptions,
+) -> anyhow::Result<MappedDataset> {
+    let sources = [MappedSource { mapping, file }];
+    let mut queues = read_sources(&sources, code, srid, encoding, options)?;
+
+    Ok(MappedDataset {
+        features: queues.remove(0),
+    })
+}
+
+/// マッピングに従って変換したデータセットを、データベースに登録する。
+///
+/// 既存のレコードの扱いは、`import_mapped`と同じである。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+/// * `mapping` - マッピング。
+/// * `dataset` - 変換したデータセット。
+/// * `file` - データセットのファイルのパス(登録の履歴に記録する)。
+/// * `code` - 都道府県コード。全国のデータセットの場合はNone。
+/// * `options` - 登録するときのオプション。
+/// * `prompter` - 既存のレコードを削除して登録するか確認する処理。
+///
+/// # Returns
+///
+/// 登録した場合は登録したフィーチャーの数。登録を中止した場合はNone。
+pub async fn register_mapped_dataset(
+    pool: &PgPool,
+    mapping: &Mapping,
+    dataset: MappedDataset,
+    file: &str,
+    code: Option<&str>,
+    options: &ImportOptions,
+    prompter: &dyn Prompter,
+) -> anyhow::Result<Option<usize>> {
+    let sources = [MappedSource { mapping, file }];
+    let counts = register_sources(
+        pool,
+        &sources,
+        vec![dataset.features],
+        code,
+        options,
+        prompter,
+    )
+    .await?;
+
+    Ok(counts.map(|counts| counts[0]))
+}
+
+/// 複数のデータセットを、それぞれのマッピングに従って1つのトランザクションでデータベースに登録する。
+///
+/// 鉄道路線と駅のように、別のファイルで配信される地物をまとめて登録するときに使用する。いずれかの
+/// データセットの既存のレコードが登録されている場合は、ユーザーが許可したときに限り、すべての
+/// データセットの既存のレコードを削除して登録する。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+/// * `sources` - 登録するデータセットのファイルとマッピング。
+/// * `code` - 都道府県コード。全国のデータセットの場合はNone。
+/// * `srid` - データセットの空間参照系ID。Noneの場合はマッピング、マッピングにもない場合はデータセットから判断する。
+/// * `encoding` - シェイプファイルの属性データファイルのエンコーディング。Noneの場合はマッピングに従う。
+/// * `options` - 登録するときのオプション。
+/// * `prompter` - 既存のレコードを削除して登録するか確認する処理。
+///
+/// # Returns
+///
+/// 登録した場合はデータセットごとに登録したフィーチャーの数。登録を中止した場合はNone。
+pub async fn import_mapped_sources(
+    pool: &PgPool,
+    sources: &[MappedSource<'_>],
+    code: Option<&str>,
+    srid: Option<i32>,
+    encoding: Option<&str>,
+    options: &ImportOptions,
+    prompter: &dyn Prompter,
+) -> anyhow::Result<Option<Vec<usize>>> {
+    let queues = read_sources(sources, code, srid, encoding, options)?;
+
+    register_sources(pool, sources, queues, code, options, prompter).await
+}
+
+/// 複数のデータセットを読み込み、それぞれのマッピングに従って変換する。
+///
+/// # Arguments
+///
+/// * `sources` - 読み込むデータセットのファイルとマッピング。
+/// * `code` - 都道府県コード。全国のデータセットの場合はNone。
+/// * `srid` - データセットの空間参照系ID。Noneの場合はマッピング、マッピングにもない場合はデータセットから判断する。
+/// * `encoding` - シェイプファイルの属性データファイルのエンコーディング。Noneの場合はマッピングに従う。
+/// * `options` - 登録するときのオプション。
+///
+/// # Returns
+///
+/// データセットごとに変換したフィーチャーを格納したキュー。
+fn read_sources(
+    sources: &[MappedSource<'_>],
+    code: Option<&str>,
+    srid: Option<i32>,
+    encoding: Option<&str>,
+    options: &ImportOptions,
+) -> anyhow::Result<Vec<SpillQueue<MappedFeature>>> {
+    for source in sources {
+        check_arguments(source.mapping, code, options)?;
+    }
+
+    // データセットを読み込み、マッピングに従って変換
+    let mut queues = Vec::with_capacity(sources.len());
+    for MappedSource { mapping, file } in sources {
+        let mut features = SpillQueue::new(options.memory_budget);
+        for_each_feature(mapping, file, srid, encoding, |index, srid, feature| {
+            let feature = feature
+                .and_then(|feature| convert_feature(mapping, feature, index, code, srid, options))
+                .with_context(|| format!("{}番目のフィーチャーを変換できません。", index))?;
+            features.push(&feature)
+        })?;
+        queues.push(features);
+    }
+
+    Ok(queues)
+}
+
+/// データセットごとに変換したフィーチャーを、1つのトランザクションでデータベースに登録する。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+/// * `sources` - 登録するデータセットのファイルとマッピング。
+/// * `queues` - データセットごとに変換したフィーチャーを格納したキュー。
+/// * `code` - 都道府県コード。全国のデータセットの場合はNone。
+/// * `options` - 登録するときのオプション。
+/// * `prompter` - 既存のレコードを削除して登録するか確認する処理。
+///
+/// # Returns
+///
+/// 登録した場合はデータセットごとに登録したフィーチャーの数。登録を中止した場合はNone。
+async fn register_sources(
+    pool: &PgPool,
+    sources: &[MappedSource<'_>],
+    queues: Vec<SpillQueue<MappedFeature>>,
+    code: Option<&str>,
+    options: &ImportOptions,
+    prompter: &dyn Prompter,
+) -> anyhow::Result<Option<Vec<usize>>> {
+    let counts: Vec<usize> = queues.iter().map(|features| features.len()).collect();
+
+    // トランザクションを開始
+    let mut tx = pool.begin().await.map_err(|e| {
+        Failure::database(format!(
+            "データベーストランザクションを開始できません。{}",
+            e
+        ))
+    })?;
+
+    // 自然キーで照合して登録しない場合は、指定された都道府県のレコードが登録されているか確認
+    if !options.upsert {
+        let mut exists = false;
+        for source in sources {
+            if exists_records(&mut tx, source.mapping, code).await? {
+                exists = true;
+                break;
+            }
+        }
+        if exists {
+            // 指定された都道府県のレコードが登録されている場合は、削除して登録するか確認
+            if !confirm_replace(prompter, code.unwrap_or(NATIONWIDE)) {
+                return Ok(None);
+            }
+            for source in sources.iter().rev() {
+                delete_records(&mut tx, source.mapping, code).await?;
+            }
+        }
+    }
+
+    for (source, features) in sources.iter().zip(queues) {
+        // フィーチャーをデータベースに登録
+        register_features(&mut tx, pool, source.mapping, code, features, options).await?;
+    }
+    for (source, count) in sources.iter().zip(&counts) {
+        // 登録の履歴を記録
+        let import = ImportSource {
+            dataset: source.mapping.dataset(),
+            code,
+            file: source.file,
+            year: options.year,
+        };
+        record_import(&mut tx, &import, &source.mapping.table, *count).await?;
+    }
+
+    // トランザクションをコミット
+    tx.commit().await.map_err(|e| {
+        Failure::database(format!(
+            "データベーストランザクションをコミットできませんでした。{}",
+            e
+        ))
+    })?;
+
+    Ok(Some(counts))
+}
+
+/// マッピングに従ってデータセットを読み込み、データベースに登録せずに検証する。
+///
+/// 属性、空間参照系、ジオメトリ及び座標の範囲を登録するときと同様に確認して、フィーチャーの数とともに
+/// 検証結果に記録する。
+///
+/// # Arguments
+///
+/// * `mapping` - マッピング。
+/// * `file` - データセットを記録したシェイプファイル(*.shp)またはGeoJSONファイル(*.geojson)のパス。
+/// * `code` - 都道府県コード。全国のデータセットの場合はNone。
+/// * `srid` - データセットの空間参照系ID。Noneの場合はマッピング、マッピングにもない場合はデータセットから判断する。
+/// * `encoding` - シェイプファイルの属性データファイルのエンコーディング。Noneの場合はマッピングに従う。
+/// * `options` - 登録するときのオプション。
+///
+/// # Returns
+///
+/// 検証結果。
+pub fn validate_mapped(
+    mapping: &Mapping,
+    file: &str,
+    code: Option<&str>,
+    srid: Option<i32>,
+    encoding: Option<&str>,
+    options: &ImportOptions,
+) -> anyhow::Result<DryRunReport> {
+    validate_mapped_sources(
+        &[MappedSource { mapping, file }],
+        code,
+        srid,
+        encoding,
+        options,
+    )
+}
+
+/// 複数のデータセットを、それぞれのマッピングに従って読み込み、データベースに登録せずに検証する。
+///
+/// # Arguments
+///
+/// * `sources` - 検証するデータセットのファイルとマッピング。
+/// * `code` - 都道府県コード。全国のデータセットの場合はNone。
+/// * `srid` - データセットの空間参照系ID。Noneの場合はマッピング、マッピングにもない場合はデータセットから判断する。
+/// * `encoding` - シェイプファイルの属性データファイルのエンコーディング。Noneの場合はマッピングに従う。
+/// * `options` - 登録するときのオプション。
+///
+/// # Returns
+///
+/// 検証結果。
+pub fn validate_mapped_sources(
+    sources: &[MappedSource<'_>],
+    code: Option<&str>,
+    srid: Option<i32>,
+    encoding: Option<&str>,
+    options: &ImportOptions,
+) -> anyhow::Result<DryRunReport> {
+    for source in sources {
+        check_arguments(source.mapping, code, options)?;
+    }
+
+    let files: Vec<&str> = sources.iter().map(|source| source.file).collect();
+    let mut report = DryRunReport::new(&files.join("、"), code.unwrap_or(NATIONWIDE));
+    for MappedSource { mapping, file } in sources {
+        for_each_feature(mapping, file, srid, encoding, |index, srid, feature| {
+            if report.srid.is_none() {
+                report.srid = Some(srid);
+                if let Some(problem) = srid_problem(srid) {
+                    report.issue(problem);
+                }
+            }
+            // 件数の種類は静的な文字列に限られるため、地物の名前ではなくフィーチャーとして数える
+            report.count("フィーチャー");
+            let result = feature
+                .and_then(|feature| convert_feature(mapping, feature, index, code, srid, options));
+            if let Err(e) = result {
+                report.issue(e.to_string());
+            }
+            Ok(())
+        })?;
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use shapefile::dbase::FieldValue;
+
+    use super::*;
+
+    fn column(attribute: &str) -> ColumnMapping {
+        toml::from_str(&format!(
+            "column = \"departments\"\nattribute = {}\nrequired = false",
+            attribute
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn many_attributes_are_joined_with_spaces() {
+        let mut record = Record::default();
+        for (name, value) in [
+            ("P04_004", Some("内科 外科")),
+            ("P04_005", Some("眼科、耳鼻科,皮膚科")),
+            ("P04_006", None),
+        ] {
+            record.insert(
+                name.to_string(),
+                FieldValue::Character(value.map(str::to_string)),
+            );
+        }
+        let departments = column(r#"["P04_004", "P04_005", "P04_006"]"#);
+        assert_eq!(
+            read_value(&record, &departments).as_deref(),
+            Some("内科 外科 眼科 耳鼻科 皮膚科")
+        );
+        let missing = column(r#"["P04_006"]"#);
+        assert_eq!(read_value(&record, &missing), None);
+    }
+
+    #[test]
+    fn blank_value_is_missing() {
+        let mut record = Record::default();
+        record.insert(
+            "P29_006".to_string(),
+            FieldValue::Character(Some("  ".to_string())),
+        );
+        assert_eq!(read_value(&record, &column(r#""P29_006""#)), None);
+    }
+}
@@ -0,0 +1,388 @@
+//! 登録するデータセットの属性とテーブルの列の対応を定義する、マッピングファイル。
+//!
+//! マッピングファイルはTOMLまたはJSONで記述して、拡張子が`.json`の場合はJSON、それ以外の場合はTOMLとして
+//! 読み込む。
+//!
+//! 国土数値情報のデータセットのマッピングファイルを、リポジトリの`mappings`ディレクトリに格納している。
+//!
+//! ```toml
+//! name = "学校"
+//! dataset = "P29"
+//! table = "schools"
+//! geometry = "point"
+//! srid = 6668
+//! encoding = "shift_jis"
+//! keys = ["school_code"]
+//!
+//! [prefecture]
+//! column = "city_code"
+//! match = "prefix"
+//!
+//! [[columns]]
+//! column = "city_code"
+//! attribute = "P29_001"
+//!
+//! [[columns]]
+//! column = "administrator_code"
+//! attribute = "P29_006"
+//! required = false
+//! ```
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use geo_types::Geometry;
+use serde::Deserialize;
+use utils::coordinates::{to_multi_line_string, to_multi_polygon};
+use utils::errors::Failure;
+use utils::shape::POLYLINE_SHAPE_TYPES;
+
+/// 登録するジオメトリの種類。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GeometryKind {
+    /// ポイント
+    Point,
+    /// ライン(マルチラインストリングとして登録する)
+    Line,
+    /// ポリゴン(マルチポリゴンとして登録する)
+    Polygon,
+}
+
+impl GeometryKind {
+    /// ジオメトリの種類の名前を返す。
+    pub fn label(self) -> &'static str {
+        match self {
+            GeometryKind::Point => "ポイント",
+            GeometryKind::Line => "ライン",
+            GeometryKind::Polygon => "ポリゴン",
+        }
+    }
+
+    /// 受け付けるShapeファイルのシェイプタイプを返す。
+    pub fn shape_types(self) -> Vec<shapefile::ShapeType> {
+        use shapefile::ShapeType;
+        match self {
+            GeometryKind::Point => vec![ShapeType::Point, ShapeType::PointZ, ShapeType::PointM],
+            GeometryKind::Line => POLYLINE_SHAPE_TYPES.to_vec(),
+            GeometryKind::Polygon => {
+                vec![ShapeType::Polygon, ShapeType::PolygonZ, ShapeType::PolygonM]
+            }
+        }
+    }
+
+    /// ジオメトリを、登録するジオメトリの種類に変換する。
+    ///
+    /// # Arguments
+    ///
+    /// * `geom` - データセットから読み込んだジオメトリ。
+    ///
+    /// # Returns
+    ///
+    /// 登録するジオメトリ。ジオメトリの種類が異なる場合はNone。
+    pub fn convert(self, geom: Geometry<f64>) -> Option<Geometry<f64>> {
+        match self {
+            GeometryKind::Point => matches!(geom, Geometry::Point(_)).then_some(geom),
+            GeometryKind::Line => to_multi_line_string(geom),
+            GeometryKind::Polygon => to_multi_polygon(geom),
+        }
+    }
+}
+
+/// 都道府県コードとレコードを対応させる方法。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CodeMatch {
+    /// 登録するときに指定された都道府県コードを列に記録する
+    #[default]
+    Equal,
+    /// 属性から登録した列の値(市区町村コードなど)が、都道府県コードで始まる
+    Prefix,
+}
+
+/// 既存のレコードを削除するときに、都道府県のレコードを特定する列。
+#[derive(Debug, Clone, Deserialize)]
+pub struct PrefectureScope {
+    /// 列の名前
+    pub column: String,
+    /// 都道府県コードとレコードを対応させる方法
+    #[serde(rename = "match", default)]
+    pub matching: CodeMatch,
+}
+
+/// 列に登録する値の型。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnType {
+    /// 文字列
+    #[default]
+    Text,
+    /// 整数
+    Integer,
+    /// 浮動小数点数
+    Double,
+}
+
+impl ColumnType {
+    /// 一時テーブルの文字列を変換するPostgreSQLの型を返す。
+    pub fn sql_type(self) -> &'static str {
+        match self {
+            ColumnType::Text => "text",
+            ColumnType::Integer => "integer",
+            ColumnType::Double => "double precision",
+        }
+    }
+}
+
+/// 列に登録する値を読み込む属性。
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Attributes {
+    /// 1つの属性の値を登録する
+    One(String),
+    /// 複数の属性の値を空白、読点またはカンマで区切った語を、空白で連結して登録する
+    Many(Vec<String>),
+}
+
+impl Attributes {
+    /// 属性の名前を返す。
+    pub fn names(&self) -> &[String] {
+        match self {
+            Attributes::One(name) => std::slice::from_ref(name),
+            Attributes::Many(names) => names,
+        }
+    }
+}
+
+impl std::fmt::Display for Attributes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.names().join("、"))
+    }
+}
+
+/// データセットの属性と、テーブルの列の対応。
+#[derive(Debug, Clone, Deserialize)]
+pub struct ColumnMapping {
+    /// 列の名前
+    pub column: String,
+    /// 属性データファイルのフィールド(GeoJSONファイルの場合はプロパティ)の名前
+    pub attribute: Attributes,
+    /// 属性が必ず記録されていなければならない場合はtrue
+    #[serde(default = "default_required")]
+    pub required: bool,
+    /// 列に登録する値の型
+    #[serde(rename = "type", default)]
+    pub column_type: ColumnType,
+    /// 列に登録できる値。省略した場合は値を制限しない
+    pub values: Option<Vec<String>>,
+}
+
+fn default_required() -> bool {
+    true
+}
+
+fn default_geometry_column() -> String {
+    "geom".to_string()
+}
+
+fn default_id_column() -> String {
+    "id".to_string()
+}
+
+/// データセットを登録する方法を定義したマッピング。
+#[derive(Debug, Clone, Deserialize)]
+pub struct Mapping {
+    /// メッセージに表示する地物の名前
+    pub name: String,
+    /// 登録の履歴に記録するデータセットの名前。省略した場合は地物の名前を記録する
+    pub dataset: Option<String>,
+    /// 登録するテーブル
+    pub table: String,
+    /// 登録するジオメトリの種類
+    pub geometry: GeometryKind,
+    /// ジオメトリを登録する列
+    #[serde(default = "default_geometry_column")]
+    pub geometry_column: String,
+    /// UUIDを生成して登録する列
+    #[serde(default = "default_id_column")]
+    pub id_column: String,
+    /// データセットの空間参照系ID。省略した場合はデータセットから判断する
+    pub srid: Option<i32>,
+    /// Shapeファイルの属性データファイルのエンコーディング
+    pub encoding: Option<String>,
+    /// 都道府県のレコードを特定する列。省略した場合は全国のデータセットとして、既存のレコードをすべて
+    /// 削除して登録する
+    pub prefecture: Option<PrefectureScope>,
+    /// 属性と列の対応
+    pub columns: Vec<ColumnMapping>,
+    /// `--upsert`で既存のレコードと照合する自然キーの列。一意インデックスを作成しておくこと
+    #[serde(default)]
+    pub keys: Vec<String>,
+    /// 地物の名前としてメッセージに表示する属性
+    pub label_attribute: Option<String>,
+}
+
+/// テーブルや列の名前として使用できるか確認する。
+///
+/// SQL文に埋め込むため、英小文字、数字及びアンダースコアに限り、数字で始まる名前は使用できない。
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_lowercase() || c == '_')
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+impl Mapping {
+    /// マッピングファイルを読み込む。
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - マッピングファイルのパス。
+    ///
+    /// # Returns
+    ///
+    /// マッピング。
+    pub fn from_file(path: &str) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            Failure::input(format!(
+                "マッピングファイル({})を読み込めません。{}",
+                path, e
+            ))
+        })?;
+        let is_json = Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.eq_ignore_ascii_case("json"))
+            .unwrap_or(false);
+        let mapping = if is_json {
+            serde_json::from_str(&content).map_err(|e| e.to_string())
+        } else {
+            toml::from_str(&content).map_err(|e| e.to_string())
+        };
+
+        Self::validated(mapping, path)
+    }
+
+    /// TOMLで記述したマッピングを読み込む。
+    ///
+    /// # Arguments
+    ///
+    /// * `content` - マッピングファイルの内容。
+    /// * `path` - エラーメッセージに表示するマッピングファイルのパス。
+    ///
+    /// # Returns
+    ///
+    /// マッピング。
+    pub fn from_toml(content: &str, path: &str) -> anyhow::Result<Self> {
+        Self::validated(toml::from_str(content).map_err(|e| e.to_string()), path)
+    }
+
+    /// 読み込んだマッピングの定義が正しいか確認する。
+    fn validated(mapping: Result<Self, String>, path: &str) -> anyhow::Result<Self> {
+        let mapping = mapping
+            .and_then(|mapping| mapping.validate().map(|_| mapping))
+            .map_err(|e| {
+                Failure::argument(format!("マッピングファイル({})が不正です。{}", path, e))
+            })?;
+
+        Ok(mapping)
+    }
+
+    /// 登録の履歴に記録するデータセットの名前を返す。
+    pub fn dataset(&self) -> &str {
+        self.dataset.as_deref().unwrap_or(&self.name)
+    }
+
+    /// マッピングの定義が正しいか確認する。
+    fn validate(&self) -> Result<(), String> {
+        let mut names = HashSet::new();
+        let columns = std::iter::once(&self.table)
+            .chain([&self.geometry_column, &self.id_column])
+            .chain(self.prefecture.iter().map(|scope| &scope.column))
+            .chain(self.columns.iter().map(|column| &column.column));
+        for name in columns {
+            if !is_identifier(name) {
+                return Err(format!(
+                    "テーブルまたは列の名前({})には、英小文字、数字及びアンダースコアを使用してください。",
+                    name
+                ));
+            }
+        }
+        if self.columns.is_empty() {
+            return Err("属性と列の対応(columns)を1つ以上定義してください。".to_string());
+        }
+        for column in &self.columns {
+            if !names.insert(column.column.as_str()) {
+                return Err(format!("列({})が重複しています。", column.column));
+            }
+            if column.attribute.names().is_empty() {
+                return Err(format!(
+                    "列({})に対応させる属性を1つ以上指定してください。",
+                    column.column
+                ));
+            }
+            if matches!(column.attribute, Attributes::Many(_))
+                && column.column_type != ColumnType::Text
+            {
+                return Err(format!(
+                    "複数の属性を対応させる列({})の型は、文字列にしてください。",
+                    column.column
+                ));
+            }
+        }
+        for reserved in [&self.geometry_column, &self.id_column] {
+            if names.contains(reserved.as_str()) {
+                return Err(format!(
+                    "列({})には、属性を対応させることはできません。",
+                    reserved
+                ));
+            }
+        }
+        for key in &self.keys {
+            match self.columns.iter().find(|column| &column.column == key) {
+                Some(column) if column.required => {}
+                _ => {
+                    return Err(format!(
+                        "自然キーの列({})には、必須の属性を対応させてください。",
+                        key
+                    ))
+                }
+            }
+        }
+        let prefecture = match &self.prefecture {
+            Some(prefecture) => prefecture,
+            None if self.keys.is_empty() => return Ok(()),
+            None => {
+                return Err("全国のデータセットには、自然キー(keys)を定義できません。".to_string())
+            }
+        };
+        let scope = &prefecture.column;
+        match prefecture.matching {
+            CodeMatch::Equal if names.contains(scope.as_str()) => Err(format!(
+                "都道府県コードを記録する列({})には、属性を対応させることはできません。",
+                scope
+            )),
+            CodeMatch::Prefix => match self.scope_column() {
+                Some(column)
+                    if column.required
+                        && column.column_type == ColumnType::Text
+                        && matches!(column.attribute, Attributes::One(_)) =>
+                {
+                    Ok(())
+                }
+                _ => Err(format!(
+                    "都道府県コードで始まる列({})には、必須の文字列の属性を1つ対応させてください。",
+                    scope
+                )),
+            },
+            CodeMatch::Equal => Ok(()),
+        }
+    }
+
+    /// 都道府県コードで始まる値を登録する列の対応を返す。
+    pub fn scope_column(&self) -> Option<&ColumnMapping> {
+        let scope = self.prefecture.as_ref()?;
+        self.columns
+            .iter()
+            .find(|column| column.column == scope.column)
+    }
+}
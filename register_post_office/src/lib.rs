@@ -0,0 +1,712 @@
+use anyhow::Context;
+use database::copy::StagingTable;
+use database::imports::{record_import, ImportSource};
+use database::upsert::{delete_missing, on_conflict_update, UpsertSummary};
+use geojson::Feature;
+use serde::{Deserialize, Serialize};
+use shapefile::ShapeType;
+use sqlx::{PgPool, Postgres, Transaction};
+use utils::dry_run::{geometry_problem, srid_problem, DryRunReport};
+use utils::errors::Failure;
+use utils::features::{geojson_to_shape_feature, is_geojson_file, read_geojson, resolve_srid};
+use utils::import::ImportOptions;
+use utils::prefecture::PrefectureCode;
+use utils::progress::Progress;
+use utils::projection::transform_geometry;
+use utils::prompt::{confirm_replace, Prompter};
+use utils::shape::{
+    count_shapes, iter_features, open_shape_file, read_required_field, read_string_field,
+    ShapeFeature,
+};
+use utils::spill::SpillQueue;
+use utils::{to_hex_ewkb, EPSG_WEB_MERCATOR};
+
+/// 郵便局
+#[derive(Serialize, Deserialize)]
+struct PostOffice {
+    /// ジオメトリ
+    geom: geo_types::Geometry,
+    /// 市区町村コード
+    /// https://nlftp.mlit.go.jp/ksj/gml/codelist/AdminiBoundary_CD.xlsx
+    city_code: String,
+    /// 公共施設大分類コード
+    /// https://nlftp.mlit.go.jp/ksj/gml/codelist/PubFacMaclassCd.html
+    category_code: String,
+    /// 公共施設小分類コード
+    /// https://nlftp.mlit.go.jp/ksj/gml/codelist/PubFacMinclassCd.html
+    subcategory_code: String,
+    /// 郵便局分類コード
+    /// https://nlftp.mlit.go.jp/ksj/gml/codelist/postOfficeCd.html
+    post_office_code: String,
+    /// 郵便局の正式名称
+    name: String,
+    /// 郵便局の市区町村名を省いた所在地
+    address: String,
+    /// 所在地の町名
+    town: Option<String>,
+    /// 所在地の番地
+    block: Option<String>,
+}
+
+/// 漢数字か確認する。
+///
+/// # Arguments
+///
+/// * `c` - 確認する文字。
+///
+/// # Returns
+///
+/// 漢数字の場合はtrue。漢数字でない場合はfalse。
+fn is_kanji_numeral(c: char) -> bool {
+    "〇一二三四五六七八九十百千".contains(c)
+}
+
+/// 市区町村名を省いた所在地を、町名と番地に分割する。
+///
+/// 所在地に最初に数字(全角数字を含む)が現れた位置、または「丁目」が続く漢数字が現れた位置で、
+/// 所在地を町名と番地に分割する。
+///
+/// # Arguments
+///
+/// * `address` - 市区町村名を省いた所在地。
+///
+/// # Returns
+///
+/// 町名と番地のタプル。町名または番地が存在しない場合はNone。
+fn split_address(address: &str) -> (Option<String>, Option<String>) {
+    let address = address.trim();
+    let position = address.char_indices().find_map(|(position, c)| {
+        if c.is_ascii_digit() || ('０'..='９').contains(&c) {
+            return Some(position);
+        }
+        if is_kanji_numeral(c) {
+            let rest = &address[position..];
+            let numerals: usize = rest
+                .chars()
+                .take_while(|c| is_kanji_numeral(*c))
+                .map(char::len_utf8)
+                .sum();
+            if rest[numerals..].starts_with("丁目") {
+                return Some(position);
+            }
+        }
+        None
+    });
+    let (town, block) = match position {
+        Some(position) => address.split_at(position),
+        None => (address, ""),
+    };
+    let to_option = |value: &str| {
+        let value = value.trim();
+        (!value.is_empty()).then(|| value.to_string())
+    };
+
+    (to_option(town), to_option(block))
+}
+
+/// ポイントフィーチャーを郵便局に変換する。
+///
+/// # Arguments
+///
+/// * `feature` - 郵便局データから読み込んだポイントフィーチャー。
+/// * `srid` - 郵便局データの空間参照系ID。
+/// * `options` - 登録するときのオプション。
+///
+/// # Returns
+///
+/// 郵便局。
+fn feature_to_post_office(
+    feature: ShapeFeature,
+    srid: i32,
+    options: &ImportOptions,
+) -> anyhow::Result<PostOffice> {
+    let ShapeFeature { mut geom, record } = feature;
+    // 名称
+    let name = read_required_field(&record, "P30_005")?;
+    // ジオメトリ
+    let label = format!("郵便局({})", name);
+    options.fix_axis_order(&mut geom, srid, &label)?;
+    let from = format!("EPSG:{}", srid);
+    let to = format!("EPSG:{}", EPSG_WEB_MERCATOR);
+    transform_geometry(&mut geom, srid, EPSG_WEB_MERCATOR).map_err(|e| {
+        Failure::data(format!(
+            "{}の座標を{}から{}に変換できません。{}",
+            label, from, to, e
+        ))
+    })?;
+    // 行政区域コード
+    let city_code = read_required_field(&record, "P30_001")?;
+    // 公共施設大分類コード
+    let category_code = read_required_field(&record, "P30_002")?;
+    // 公共施設小分類コード
+    let subcategory_code = read_required_field(&record, "P30_003")?;
+    // 郵便局分類コード
+    let post_office_code = read_required_field(&record, "P30_004")?;
+    // 所在地
+    let address = read_required_field(&record, "P30_006")?;
+    // 所在地の町名と番地
+    let (town, block) = split_address(&address);
+    // 座標が範囲内にあるか確認
+    options.bbox_guard.validate(&geom, &label)?;
+
+    Ok(PostOffice {
+        city_code,
+        category_code,
+        subcategory_code,
+        post_office_code,
+        name,
+        address,
+        town,
+        block,
+        geom,
+    })
+}
+
+/// GeoJSONファイルのフィーチャーを、郵便局データのポイントフィーチャーに変換する。
+///
+/// # Arguments
+///
+/// * `feature` - 郵便局データ(GeoJSONファイル)のフィーチャー。
+/// * `index` - フィーチャーの位置(0から始まる)。
+///
+/// # Returns
+///
+/// Shapeファイルから読み込んだ場合と同じ属性を持つフィーチャー。ジオメトリがポイントでない場合はエラー。
+fn geojson_to_point_feature(feature: Feature, index: usize) -> anyhow::Result<ShapeFeature> {
+    let feature = geojson_to_shape_feature(feature, index)?;
+    if !matches!(feature.geom, geo_types::Geometry::Point(_)) {
+        return Err(Failure::data(format!(
+            "{}番目のフィーチャーのジオメトリがポイントではありません。",
+            index
+        ))
+        .into());
+    }
+
+    Ok(feature)
+}
+
+/// 郵便局データのフィーチャーを郵便局に変換する。
+///
+/// 変換した郵便局の数は、定期的に標準エラー出力に出力する。
+///
+/// # Arguments
+///
+/// * `features` - 郵便局データのフィーチャーを読み込むイテレーター。
+/// * `total` - 郵便局データに記録されている郵便局の数。不明な場合はNone。
+/// * `srid` - 郵便局データの空間参照系ID。
+/// * `options` - 登録するときのオプション。
+///
+/// # Returns
+///
+/// 郵便局を格納したキュー。
+fn features_to_post_offices<I>(
+    features: I,
+    total: Option<usize>,
+    srid: i32,
+    options: &ImportOptions,
+) -> anyhow::Result<SpillQueue<PostOffice>>
+where
+    I: Iterator<Item = anyhow::Result<ShapeFeature>>,
+{
+    let mut post_offices = SpillQueue::new(options.memory_budget);
+    let features = Progress::new("郵便局", total).wrap(features);
+    for (index, feature) in features.enumerate() {
+        let post_office = feature_to_post_office(feature?, srid, options)
+            .with_context(|| format!("{}番目のフィーチャーを変換できません。", index))?;
+        post_offices.push(&post_office)?;
+    }
+
+    Ok(post_offices)
+}
+
+/// 指定された都道府県の郵便局がデータベースにされているか確認する。
+///
+/// # Arguments
+///
+/// * `tx` - データベーストランザクション。
+/// * `code` - 登録されているか確認する都道府県コード。
+///
+/// # Returns
+///
+/// 指定された都道府県の郵便局がデータベースに登録されている場合はtrue。登録されていない場合はfalse。
+async fn exists_post_office(
+    tx: &mut Transaction<'_, Postgres>,
+    code: &str,
+) -> anyhow::Result<bool> {
+    let code_like = format!("{}%", code);
+    let result = sqlx::query!(
+        r#"
+        SELECT COUNT(*) offices FROM post_offices WHERE city_code LIKE $1
+        "#,
+        &code_like,
+    )
+    .fetch_one(tx)
+    .await
+    .map_err(|e| {
+        Failure::database(format!(
+            "データベースに登録されている郵便局の数を確認するときにエラーが発生しました。{}",
+            e
+        ))
+    })?;
+    if 0 < result.offices.unwrap() {
+        return Ok(true);
+    }
+
+    Ok(false)
+}
+
+/// 指定された都道府県コードの郵便局をデータベースから削除する。
+///
+/// # Arguments
+///
+/// * `tx` - データベーストランザクション。
+/// * `code` - 郵便局を削除する都道府県コード。
+///
+/// # Returns
+///
+/// 削除した郵便局の数。
+pub async fn delete_post_offices(
+    tx: &mut Transaction<'_, Postgres>,
+    code: &str,
+) -> anyhow::Result<u64> {
+    let code_like = format!("{}%", code);
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM post_offices WHERE city_code LIKE $1
+        "#,
+        &code_like,
+    )
+    .execute(tx)
+    .await
+    .map_err(|e| {
+        Failure::database(format!(
+            "データベースから郵便局を削除するときにエラーが発生しました。{}",
+            e
+        ))
+    })?;
+
+    Ok(result.rows_affected())
+}
+
+/// 郵便局を、郵便局を登録する行に変換する。
+///
+/// # Arguments
+///
+/// * `post_office` - 登録する郵便局。
+///
+/// # Returns
+///
+/// 郵便局の属性とジオメトリ(Webメルカトル投影法のEWKB)を、`POST_OFFICE_COLUMNS`の順に格納した行。
+fn post_office_row(post_office: PostOffice) -> anyhow::Result<Vec<Option<String>>> {
+    let geom = to_hex_ewkb(&post_office.geom, EPSG_WEB_MERCATOR)?;
+
+    Ok(vec![
+        Some(post_office.city_code),
+        Some(post_office.category_code),
+        Some(post_office.subcategory_code),
+        Some(post_office.post_office_code),
+        Some(post_office.name),
+        Some(post_office.address),
+        post_office.town,
+        post_office.block,
+        Some(geom),
+    ])
+}
+
+/// COPY文で郵便局の値を登録する一時テーブルの列。
+const POST_OFFICE_COLUMNS: [&str; 9] = [
+    "city_code",
+    "category_code",
+    "subcategory_code",
+    "post_office_code",
+    "name",
+    "address",
+    "town",
+    "block",
+    "geom",
+];
+
+/// COPY文で郵便局の値を登録する一時テーブルの列の定義。
+const POST_OFFICE_ROWS: &str = "city_code text, category_code text, subcategory_code text, \
+     post_office_code text, name text, address text, town text, block text, geom geometry";
+
+/// `--upsert`で既存の郵便局と照合する郵便局の自然キー。
+///
+/// 同じ市区町村に同じ名称の郵便局があるため、位置も自然キーに含める。
+const POST_OFFICE_KEYS: [&str; 3] = ["city_code", "name", "geom"];
+
+/// `--upsert`で自然キーが一致する既存の郵便局を更新する列。
+const POST_OFFICE_UPDATE_COLUMNS: [&str; 8] = [
+    "category_code",
+    "subcategory_code",
+    "post_office_code",
+    "address",
+    "city_name",
+    "town",
+    "block",
+    "postal_code",
+];
+
+/// 郵便局をデータベースに登録する。
+///
+/// 郵便局はCOPY文で一時テーブルにまとめて登録してから、郵便局テーブルに登録する。郵便局の市区町村名は、
+/// 市区町村コードが一致する市区町村から取得する。郵便番号は、郵便番号データを登録している場合に限り、
+/// 市区町村コードと町名から推定する。市区町村コード、名称及び位置が同じ郵便局は1つだけ登録する。
+/// `options.jobs`が2以上の場合は、一時テーブルに複数の接続で並列に登録する。
+/// `options.upsert`がtrueの場合は、市区町村コード、名称及び位置が一致する既存の郵便局を値が変わったときに限り更新し、
+/// 登録しなかった指定された都道府県の既存の郵便局を削除する。
+///
+/// # Arguments
+///
+/// * `tx` - データベーストランザクション。
+/// * `pool` - 一時テーブルに並列に登録する接続を取得するデータベースコネクションプール。
+/// * `post_offices` - 登録する郵便局を格納したキュー。
+/// * `code` - 都道府県コード。
+/// * `options` - 登録するときのオプション。
+async fn register_post_offices(
+    tx: &mut Transaction<'_, Postgres>,
+    pool: &PgPool,
+    post_offices: SpillQueue<PostOffice>,
+    code: &str,
+    options: &ImportOptions,
+) -> anyhow::Result<()> {
+    let staging = StagingTable::create(
+        &mut *tx,
+        pool,
+        "post_office_rows",
+        POST_OFFICE_ROWS,
+        options.jobs,
+    )
+    .await?;
+    let rows = post_offices
+        .into_items()?
+        .map(|post_office| post_office_row(post_office?));
+    let rows = staging.copy(&mut *tx, &POST_OFFICE_COLUMNS, rows).await?;
+    let result = async {
+        let conflict = if options.upsert {
+            on_conflict_update(
+                "post_offices",
+                &POST_OFFICE_KEYS,
+                &POST_OFFICE_UPDATE_COLUMNS,
+            )
+        } else {
+            String::new()
+        };
+        let sql = format!(
+            r#"
+            INSERT INTO post_offices (
+                id, city_code, category_code, subcategory_code, post_office_code,
+                name, address, city_name, town, block, postal_code, geom
+            )
+            SELECT DISTINCT ON (r.city_code, r.name, r.geom)
+                gen_random_uuid(), r.city_code, r.category_code, r.subcategory_code,
+                r.post_office_code, r.name, r.address,
+                (SELECT CONCAT(c.area, c.name) FROM cities c WHERE c.code = r.city_code LIMIT 1),
+                r.town, r.block, likely_postal_code(r.city_code, r.town), r.geom
+            FROM {} r
+            {}
+            "#,
+            staging.name(),
+            conflict
+        );
+        let changed = sqlx::query(&sql).execute(&mut *tx).await.map_err(|e| {
+            Failure::database(format!(
+                "データベースに郵便局を登録するときにエラーが発生しました。{}",
+                e
+            ))
+        })?;
+        if options.upsert {
+            let deleted = delete_missing(
+                &mut *tx,
+                "post_offices",
+                staging.name(),
+                &POST_OFFICE_KEYS,
+                "t.city_code LIKE $1",
+                &format!("{}%", code),
+                "郵便局",
+            )
+            .await?;
+            let summary = UpsertSummary::new(rows, changed.rows_affected(), deleted);
+            eprintln!("郵便局: {}", summary);
+        }
+
+        Ok(())
+    }
+    .await;
+    staging.finish(&mut *tx, result).await?;
+
+    Ok(())
+}
+
+/// 読み込んだ郵便局データ。
+pub struct PostOfficeDataset {
+    /// 郵便局を格納したキュー
+    post_offices: SpillQueue<PostOffice>,
+}
+
+/// 郵便局データ(ShapeファイルまたはGeoJSONファイル)を読み込み、郵便局に変換する。
+///
+/// 郵便局データの形式は、ファイルの拡張子から判断する。ファイルを同期的に読み込むため、非同期のタスクから
+/// 呼び出す場合は、別のスレッドで呼び出すこと。座標が範囲外の郵便局が存在する場合はエラーを返す。変換した
+/// 郵便局は、`options.memory_budget`を超えた分を一時ファイルに書き出す。
+///
+/// # Arguments
+///
+/// * `file` - 郵便局データを記録したシェイプファイル(*.shp)またはGeoJSONファイル(*.geojson)のパス。
+/// * `srid` - 郵便局データの空間参照系ID。Noneの場合は郵便局データから判断する。
+/// * `encoding` - シェイプファイルの属性データファイルのエンコーディング。GeoJSONファイルの場合は使用しない。
+/// * `options` - 登録するときのオプション。
+///
+/// # Returns
+///
+/// 読み込んだ郵便局データ。
+pub fn read_post_offices(
+    file: &str,
+    srid: Option<i32>,
+    encoding: &str,
+    options: &ImportOptions,
+) -> anyhow::Result<PostOfficeDataset> {
+    let post_offices = if is_geojson_file(file) {
+        let fc = read_geojson(file)?;
+        let srid = resolve_srid(file, Some(&fc), srid)?;
+        let total = fc.features.len();
+        let features = fc
+            .features
+            .into_iter()
+            .enumerate()
+            .map(|(index, feature)| geojson_to_point_feature(feature, index));
+        features_to_post_offices(features, Some(total), srid, options)?
+    } else {
+        let srid = resolve_srid(file, None, srid)?;
+        let mut reader = open_shape_file(file, encoding, &[ShapeType::Point])?;
+        features_to_post_offices(
+            iter_features(&mut reader),
+            count_shapes(file),
+            srid,
+            options,
+        )?
+    };
+
+    Ok(PostOfficeDataset { post_offices })
+}
+
+/// 郵便局データ(ShapeファイルまたはGeoJSONファイル)を読み込み、郵便局をデータベースに登録する。
+///
+/// 郵便局データの形式は、ファイルの拡張子から判断する。GeoJSONファイルは、Shapeファイルの属性データ
+/// ファイルと同じ名前の属性(`P30_001`から`P30_006`)を持たなければならない。指定された都道府県コードの
+/// 郵便局が登録されている場合は、ユーザーが許可したときに限り、既存のレコードを削除して登録する。
+/// `options.upsert`がtrueの場合は、既存のレコードを削除せずに、自然キーで照合して変更があった郵便局だけを登録する。
+/// 座標が範囲外の郵便局が存在する場合は、登録を中止してエラーを返す。登録する前の郵便局は、
+/// `options.memory_budget`を超えた分を一時ファイルに書き出す。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+/// * `file` - 郵便局データを記録したシェイプファイル(*.shp)またはGeoJSONファイル(*.geojson)のパス。
+/// * `code` - 都道府県コード。
+/// * `srid` - 郵便局データの空間参照系ID。Noneの場合は郵便局データから判断する。
+/// * `encoding` - シェイプファイルの属性データファイルのエンコーディング。GeoJSONファイルの場合は使用しない。
+/// * `options` - 登録するときのオプション。
+/// * `prompter` - 既存のレコードを削除して登録するか確認する処理。
+///
+/// # Returns
+///
+/// 登録した場合は登録した郵便局の数。登録を中止した場合はNone。
+pub async fn import_post_offices(
+    pool: &PgPool,
+    file: &str,
+    code: &str,
+    srid: Option<i32>,
+    encoding: &str,
+    options: &ImportOptions,
+    prompter: &dyn Prompter,
+) -> anyhow::Result<Option<usize>> {
+    code.parse::<PrefectureCode>().map_err(Failure::argument)?;
+    let dataset = read_post_offices(file, srid, encoding, options)?;
+
+    register_post_office_dataset(pool, dataset, file, code, options, prompter).await
+}
+
+/// 読み込んだ郵便局データの郵便局を、データベースに登録する。
+///
+/// 既存のレコードの扱いは、`import_post_offices`と同じである。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+/// * `dataset` - 読み込んだ郵便局データ。
+/// * `file` - 郵便局データのファイルのパス(登録の履歴に記録する)。
+/// * `code` - 都道府県コード。
+/// * `options` - 登録するときのオプション。
+/// * `prompter` - 既存のレコードを削除して登録するか確認する処理。
+///
+/// # Returns
+///
+/// 登録した場合は登録した郵便局の数。登録を中止した場合はNone。
+pub async fn register_post_office_dataset(
+    pool: &PgPool,
+    dataset: PostOfficeDataset,
+    file: &str,
+    code: &str,
+    options: &ImportOptions,
+    prompter: &dyn Prompter,
+) -> anyhow::Result<Option<usize>> {
+    code.parse::<PrefectureCode>().map_err(Failure::argument)?;
+    let features = dataset.post_offices;
+    let count = features.len();
+
+    // トランザクションを開始
+    let mut tx = pool.begin().await.map_err(|e| {
+        Failure::database(format!(
+            "データベーストランザクションを開始できません。{}",
+            e
+        ))
+    })?;
+
+    // 自然キーで照合して登録しない場合は、指定された都道府県コードが一致する郵便局が登録されているか確認
+    if !options.upsert && exists_post_office(&mut tx, code).await? {
+        // 指定された都道府県コードの郵便局が登録されている場合は、削除して登録するか確認
+        if !confirm_replace(prompter, code) {
+            return Ok(None);
+        }
+        // 指定された都道府県コードの郵便局を削除
+        delete_post_offices(&mut tx, code).await?;
+    }
+
+    // 郵便局をデータベースに登録
+    register_post_offices(&mut tx, pool, features, code, options).await?;
+    // 登録の履歴を記録
+    let source = ImportSource {
+        dataset: "P30",
+        code: Some(code),
+        file,
+        year: options.year,
+    };
+    record_import(&mut tx, &source, "post_offices", count).await?;
+
+    // トランザクションをコミット
+    tx.commit().await.map_err(|e| {
+        Failure::database(format!(
+            "データベーストランザクションをコミットできませんでした。{}",
+            e
+        ))
+    })?;
+
+    Ok(Some(count))
+}
+
+/// 郵便局データのポイントフィーチャーを、登録するときと同様に検証する。
+///
+/// # Arguments
+///
+/// * `report` - 見つかった問題を追加する検証結果。
+/// * `feature` - 郵便局データから読み込んだポイントフィーチャー。
+/// * `index` - フィーチャーの位置(0から始まる)。
+/// * `code` - 都道府県コード。
+/// * `srid` - 郵便局データの空間参照系ID。
+/// * `options` - 登録するときのオプション。
+fn validate_feature(
+    report: &mut DryRunReport,
+    feature: ShapeFeature,
+    index: usize,
+    code: &str,
+    srid: i32,
+    options: &ImportOptions,
+) {
+    let ShapeFeature { mut geom, record } = feature;
+    report.count("郵便局");
+    for name in [
+        "P30_001", "P30_002", "P30_003", "P30_004", "P30_005", "P30_006",
+    ] {
+        if read_string_field(&record, name).is_none() {
+            report.issue(format!(
+                "{}番目のフィーチャー: 属性{}がありません。",
+                index, name
+            ));
+            return;
+        }
+    }
+    let city_code = read_string_field(&record, "P30_001").unwrap();
+    let name = read_string_field(&record, "P30_005").unwrap();
+    let label = format!("{}番目のフィーチャー(郵便局 {})", index, name);
+    if !city_code.starts_with(code) {
+        report.issue(format!(
+            "{}: 市区町村コード({})が都道府県コード({})と一致しません。",
+            label, city_code, code
+        ));
+    }
+    if let Some(problem) = geometry_problem(&geom) {
+        report.issue(format!("{}: {}", label, problem));
+        return;
+    }
+    if let Err(e) = options.fix_axis_order(&mut geom, srid, &label) {
+        report.issue(e.to_string());
+        return;
+    }
+    let from = format!("EPSG:{}", srid);
+    let to = format!("EPSG:{}", EPSG_WEB_MERCATOR);
+    if let Err(e) = transform_geometry(&mut geom, srid, EPSG_WEB_MERCATOR) {
+        report.issue(format!(
+            "{}の座標を{}から{}に変換できません。{}",
+            label, from, to, e
+        ));
+        return;
+    }
+    if let Err(e) = options.bbox_guard.validate(&geom, &label) {
+        report.issue(e.to_string());
+    }
+}
+
+/// 郵便局データ(ShapeファイルまたはGeoJSONファイル)を読み込み、データベースに登録せずに検証する。
+///
+/// 郵便局の属性、市区町村コードと都道府県コードの一致、空間参照系、ジオメトリ及び座標の範囲を
+/// 登録するときと同様に確認して、郵便局の数とともに検証結果に記録する。
+///
+/// # Arguments
+///
+/// * `file` - 郵便局データを記録したシェイプファイル(*.shp)またはGeoJSONファイル(*.geojson)のパス。
+/// * `code` - 都道府県コード。
+/// * `srid` - 郵便局データの空間参照系ID。Noneの場合は郵便局データから判断する。
+/// * `encoding` - シェイプファイルの属性データファイルのエンコーディング。GeoJSONファイルの場合は使用しない。
+/// * `options` - 登録するときのオプション。
+///
+/// # Returns
+///
+/// 検証結果。
+pub fn validate_post_offices(
+    file: &str,
+    code: &str,
+    srid: Option<i32>,
+    encoding: &str,
+    options: &ImportOptions,
+) -> anyhow::Result<DryRunReport> {
+    code.parse::<PrefectureCode>().map_err(Failure::argument)?;
+
+    let fc = if is_geojson_file(file) {
+        Some(read_geojson(file)?)
+    } else {
+        None
+    };
+    let srid = resolve_srid(file, fc.as_ref(), srid)?;
+    let mut report = DryRunReport::new(file, code);
+    report.srid = Some(srid);
+    if let Some(problem) = srid_problem(srid) {
+        report.issue(problem);
+    }
+    let mut validate = |index: usize, feature: anyhow::Result<ShapeFeature>| match feature {
+        Ok(feature) => validate_feature(&mut report, feature, index, code, srid, options),
+        Err(e) => report.issue(e.to_string()),
+    };
+    if let Some(fc) = fc {
+        for (index, feature) in fc.features.into_iter().enumerate() {
+            validate(index, geojson_to_point_feature(feature, index));
+        }
+    } else {
+        let mut reader = open_shape_file(file, encoding, &[ShapeType::Point])?;
+        for (index, feature) in iter_features(&mut reader).enumerate() {
+            validate(index, feature);
+        }
+    }
+
+    Ok(report)
+}
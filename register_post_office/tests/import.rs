@@ -0,0 +1,147 @@
+//! 郵便局データをデータベースに登録して、登録した郵便局と登録の履歴を確認するテスト。
+//!
+//! `test_support`クレートが起動したPostGISのコンテナ(環境変数TEST_DATABASE_URLを設定した場合はそのサーバー)に
+//! テストごとのデータベースを作成する。
+//! TEST_DATABASE_URLを設定しておらず、Dockerも利用できない場合はテストが失敗する。
+
+use database::imports::list_imports;
+use register_post_office::import_post_offices;
+use test_support::{fixture_path, resource_path, TestDatabase};
+use utils::import::ImportOptions;
+use utils::prompt::ScriptedPrompter;
+
+/// テストで登録する郵便局データ(GeoJSONファイル)のパス。
+fn geojson_fixture() -> String {
+    fixture_path("P30-13_13.geojson")
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[tokio::test]
+async fn imports_geojson_and_records_history() {
+    let database = TestDatabase::new().await;
+    let prompter = ScriptedPrompter::default();
+
+    let count = import_post_offices(
+        &database.pool,
+        &geojson_fixture(),
+        "13",
+        None,
+        "utf-8",
+        &ImportOptions::default(),
+        &prompter,
+    )
+    .await
+    .unwrap();
+    assert_eq!(count, Some(2));
+    assert_eq!(database.count("post_offices").await, 2);
+    assert!(prompter.questions().is_empty());
+
+    let imports = list_imports(&database.pool, Some("post_offices"), Some("13"), 10)
+        .await
+        .unwrap();
+    assert_eq!(imports.len(), 1);
+    assert_eq!(imports[0].features, 2);
+    assert_eq!(imports[0].dataset.as_deref(), Some("P30"));
+    assert_eq!(imports[0].source_file.as_deref(), Some("P30-13_13.geojson"));
+    assert_eq!(imports[0].source_year, Some(2013));
+}
+
+#[tokio::test]
+async fn keeps_existing_post_offices_unless_replace_is_confirmed() {
+    let database = TestDatabase::new().await;
+    let file = geojson_fixture();
+    let options = ImportOptions::default();
+    import_post_offices(
+        &database.pool,
+        &file,
+        "13",
+        None,
+        "utf-8",
+        &options,
+        &ScriptedPrompter::default(),
+    )
+    .await
+    .unwrap();
+
+    // 既存のレコードを削除して登録することを許可しない場合は、登録を中止する
+    let prompter = ScriptedPrompter::new([false]);
+    let count = import_post_offices(
+        &database.pool,
+        &file,
+        "13",
+        None,
+        "utf-8",
+        &options,
+        &prompter,
+    )
+    .await
+    .unwrap();
+    assert_eq!(count, None);
+    assert_eq!(prompter.questions().len(), 1);
+
+    // 許可した場合は、既存のレコードを削除して登録する
+    let count = import_post_offices(
+        &database.pool,
+        &file,
+        "13",
+        None,
+        "utf-8",
+        &options,
+        &ScriptedPrompter::new([true]),
+    )
+    .await
+    .unwrap();
+    assert_eq!(count, Some(2));
+    assert_eq!(database.count("post_offices").await, 2);
+    assert_eq!(database.count("imports").await, 2);
+}
+
+#[tokio::test]
+async fn upsert_does_not_ask_to_replace() {
+    let database = TestDatabase::new().await;
+    let file = geojson_fixture();
+    let options = ImportOptions {
+        upsert: true,
+        ..Default::default()
+    };
+    let prompter = ScriptedPrompter::default();
+
+    for _ in 0..2 {
+        let count = import_post_offices(
+            &database.pool,
+            &file,
+            "13",
+            None,
+            "utf-8",
+            &options,
+            &prompter,
+        )
+        .await
+        .unwrap();
+        assert_eq!(count, Some(2));
+    }
+    assert_eq!(database.count("post_offices").await, 2);
+    assert!(prompter.questions().is_empty());
+}
+
+#[tokio::test]
+async fn imports_sample_shapefile() {
+    let database = TestDatabase::new().await;
+    let file = resource_path("gifu_post_offices.shp");
+
+    let count = import_post_offices(
+        &database.pool,
+        &file.to_string_lossy(),
+        "21",
+        None,
+        "shift_jis",
+        &ImportOptions::default(),
+        &ScriptedPrompter::default(),
+    )
+    .await
+    .unwrap()
+    .expect("郵便局を登録できません。");
+    assert!(count > 0);
+    assert_eq!(database.count("post_offices").await, count as i64);
+}
@@ -0,0 +1,422 @@
+use std::collections::HashSet;
+
+use database::imports::{record_import, ImportSource};
+use sqlx::{PgPool, Postgres, Transaction};
+use utils::dry_run::DryRunReport;
+use utils::errors::Failure;
+use utils::prefecture::PrefectureCode;
+use utils::prompt::{confirm_replace, Prompter};
+
+/// 郵便番号データ(KEN_ALL.CSV)の列の数。
+const COLUMNS: usize = 15;
+
+/// 町域名が記載されていない場合に、郵便番号データに記録されている町域名。
+const NO_TOWN: &str = "以下に掲載がない場合";
+
+/// 市区町村名の次に番地が続く場合に、郵便番号データの町域名の末尾に記録されている文字列。
+const BLOCK_FOLLOWS: &str = "の次に番地がくる場合";
+
+/// 郵便番号
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct PostalCode {
+    /// 郵便番号(7桁)
+    postal_code: String,
+    /// 全国地方公共団体コード(市区町村コード)
+    /// https://www.post.japanpost.jp/zipcode/dl/readme.html
+    city_code: String,
+    /// 都道府県名
+    prefecture_name: String,
+    /// 市区町村名
+    city_name: String,
+    /// 町域名(括弧書きを除き、市区町村全体を表す場合は空文字列)
+    town: String,
+}
+
+/// 郵便番号データの行を列に分割する。
+///
+/// 郵便番号データの列は、ダブルクォーテーションで囲まれている場合がある。
+///
+/// # Arguments
+///
+/// * `line` - 郵便番号データの行。
+///
+/// # Returns
+///
+/// 列のベクタ。
+fn split_columns(line: &str) -> Vec<String> {
+    let mut columns = Vec::new();
+    let mut column = String::new();
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => columns.push(std::mem::take(&mut column)),
+            _ => column.push(c),
+        }
+    }
+    columns.push(column);
+
+    columns
+}
+
+/// 郵便番号データの町域名を、登録する町域名に変換する。
+///
+/// 括弧書きを除き、町域名が記載されていない場合は空文字列に変換する。
+///
+/// # Arguments
+///
+/// * `town` - 郵便番号データの町域名。
+///
+/// # Returns
+///
+/// 登録する町域名。
+fn normalize_town(town: &str) -> String {
+    let town = match town.find('（') {
+        Some(position) => &town[..position],
+        None => town,
+    };
+    if town == NO_TOWN || town.ends_with(BLOCK_FOLLOWS) {
+        return String::new();
+    }
+
+    town.trim().to_string()
+}
+
+/// 郵便番号データを読み込み、指定された都道府県の郵便番号を取得する。
+///
+/// 町域名が長い場合、郵便番号データは町域名の括弧書きを複数の行に分割して記録しているため、
+/// 括弧が閉じるまでの行を1つの町域にまとめる。
+///
+/// # Arguments
+///
+/// * `content` - 郵便番号データの内容。
+/// * `code` - 都道府県コード。
+///
+/// # Returns
+///
+/// 郵便番号のベクタ。
+fn parse_postal_codes(content: &str, code: &str) -> anyhow::Result<Vec<PostalCode>> {
+    let mut postal_codes: Vec<PostalCode> = Vec::new();
+    let mut registered: HashSet<PostalCode> = HashSet::new();
+    // 括弧が閉じていない町域名
+    let mut continued: Option<String> = None;
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line.is_empty() {
+            continue;
+        }
+        let columns = split_columns(line);
+        if columns.len() != COLUMNS {
+            return Err(Failure::data(format!(
+                "郵便番号データの{}行目の列の数が{}ではありません。",
+                number + 1,
+                COLUMNS
+            ))
+            .into());
+        }
+        let town = match continued.take() {
+            Some(town) => town + &columns[8],
+            None => columns[8].clone(),
+        };
+        if town.contains('（') && !town.contains('）') {
+            continued = Some(town);
+            continue;
+        }
+        if !columns[0].starts_with(code) {
+            continue;
+        }
+        let postal_code = PostalCode {
+            postal_code: columns[2].clone(),
+            city_code: columns[0].clone(),
+            prefecture_name: columns[6].clone(),
+            city_name: columns[7].clone(),
+            town: normalize_town(&town),
+        };
+        if postal_code.postal_code.len() != 7
+            || !postal_code.postal_code.chars().all(|c| c.is_ascii_digit())
+        {
+            return Err(Failure::data(format!(
+                "郵便番号データの{}行目の郵便番号({})が不正です。",
+                number + 1,
+                postal_code.postal_code
+            ))
+            .into());
+        }
+        // 町域名の括弧書きを除いたため重複した郵便番号は、1つにまとめる
+        if registered.insert(postal_code.clone()) {
+            postal_codes.push(postal_code);
+        }
+    }
+
+    Ok(postal_codes)
+}
+
+/// 郵便番号データを読み込む。
+///
+/// # Arguments
+///
+/// * `file` - 郵便番号データのパス。
+/// * `encoding` - 郵便番号データのエンコーディング。
+///
+/// # Returns
+///
+/// 郵便番号データの内容。
+fn read_postal_code_file(file: &str, encoding: &str) -> anyhow::Result<String> {
+    let encoding = encoding_rs::Encoding::for_label(encoding.as_bytes())
+        .ok_or_else(|| Failure::argument(format!("エンコーディング({})が不正です。", encoding)))?;
+    let bytes = std::fs::read(file)
+        .map_err(|e| Failure::input(format!("郵便番号データ({})を読み込めません。{}", file, e)))?;
+    let (content, _, had_errors) = encoding.decode(&bytes);
+    if had_errors {
+        return Err(Failure::input(format!(
+            "郵便番号データ({})を{}で復号できません。",
+            file,
+            encoding.name()
+        ))
+        .into());
+    }
+
+    Ok(content.into_owned())
+}
+
+/// 指定された都道府県の郵便番号がデータベースに登録されているか確認する。
+///
+/// # Arguments
+///
+/// * `tx` - データベーストランザクション。
+/// * `code` - 登録されているか確認する都道府県コード。
+///
+/// # Returns
+///
+/// 指定された都道府県の郵便番号がデータベースに登録されている場合はtrue。登録されていない場合はfalse。
+async fn exists_postal_code(
+    tx: &mut Transaction<'_, Postgres>,
+    code: &str,
+) -> anyhow::Result<bool> {
+    let code_like = format!("{}%", code);
+    let result = sqlx::query!(
+        r#"
+        SELECT COUNT(*) postal_codes FROM postal_codes WHERE city_code LIKE $1
+        "#,
+        &code_like,
+    )
+    .fetch_one(tx)
+    .await
+    .map_err(|e| {
+        Failure::database(format!(
+            "データベースに登録されている郵便番号の数を確認するときにエラーが発生しました。{}",
+            e
+        ))
+    })?;
+
+    Ok(0 < result.postal_codes.unwrap())
+}
+
+/// 指定された都道府県の郵便番号をデータベースから削除する。
+///
+/// # Arguments
+///
+/// * `tx` - データベーストランザクション。
+/// * `code` - 郵便番号を削除する都道府県コード。
+async fn delete_postal_codes(tx: &mut Transaction<'_, Postgres>, code: &str) -> anyhow::Result<()> {
+    let code_like = format!("{}%", code);
+    let _ = sqlx::query!(
+        r#"
+        DELETE FROM postal_codes WHERE city_code LIKE $1
+        "#,
+        &code_like,
+    )
+    .execute(tx)
+    .await
+    .map_err(|e| {
+        Failure::database(format!(
+            "データベースから郵便番号を削除するときにエラーが発生しました。{}",
+            e
+        ))
+    })?;
+
+    Ok(())
+}
+
+/// 郵便番号をデータベースに登録する。
+///
+/// # Arguments
+///
+/// * `tx` - データベーストランザクション。
+/// * `postal_codes` - 登録する郵便番号。
+async fn register_postal_codes(
+    tx: &mut Transaction<'_, Postgres>,
+    postal_codes: &[PostalCode],
+) -> anyhow::Result<()> {
+    for postal_code in postal_codes {
+        sqlx::query!(
+            r#"
+            INSERT INTO postal_codes (postal_code, city_code, prefecture_name, city_name, town)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            postal_code.postal_code,
+            postal_code.city_code,
+            postal_code.prefecture_name,
+            postal_code.city_name,
+            postal_code.town,
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            Failure::database(format!(
+                "データベースに郵便番号({})を登録するときにエラーが発生しました。{}",
+                postal_code.postal_code, e
+            ))
+        })?;
+    }
+
+    Ok(())
+}
+
+/// 指定された都道府県の郵便局に、所在地から推定した郵便番号を設定する。
+///
+/// 郵便局の郵便番号は、郵便局の市区町村コードと町名から`likely_postal_code`関数で推定する。
+///
+/// # Arguments
+///
+/// * `tx` - データベーストランザクション。
+/// * `code` - 郵便番号を設定する郵便局の都道府県コード。
+///
+/// # Returns
+///
+/// 郵便番号を設定した郵便局の数。
+pub async fn enrich_post_offices(
+    tx: &mut Transaction<'_, Postgres>,
+    code: &str,
+) -> anyhow::Result<u64> {
+    let code_like = format!("{}%", code);
+    let result = sqlx::query!(
+        r#"
+        UPDATE post_offices SET postal_code = likely_postal_code(city_code, town)
+        WHERE city_code LIKE $1
+        "#,
+        &code_like,
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| Failure::database(format!("郵便局に郵便番号を設定できません。{}", e)))?;
+
+    Ok(result.rows_affected())
+}
+
+/// 郵便番号データ(KEN_ALL.CSV)を読み込み、指定された都道府県の郵便番号をデータベースに登録する。
+///
+/// 指定された都道府県の郵便番号が登録されている場合は、ユーザーが許可したときに限り、
+/// 既存のレコードを削除して登録する。登録した後で、都道府県の郵便局に郵便番号を設定する。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+/// * `file` - 郵便番号データのパス。
+/// * `code` - 都道府県コード。
+/// * `encoding` - 郵便番号データのエンコーディング。
+/// * `prompter` - 既存のレコードを削除して登録するか確認する処理。
+///
+/// # Returns
+///
+/// 登録した場合は登録した郵便番号の数。登録を中止した場合はNone。
+pub async fn import_postal_codes(
+    pool: &PgPool,
+    file: &str,
+    code: &str,
+    encoding: &str,
+    prompter: &dyn Prompter,
+) -> anyhow::Result<Option<usize>> {
+    code.parse::<PrefectureCode>().map_err(Failure::argument)?;
+
+    // 郵便番号データを読み込み、郵便番号を取得
+    let content = read_postal_code_file(file, encoding)?;
+    let postal_codes = parse_postal_codes(&content, code)?;
+    let count = postal_codes.len();
+
+    // トランザクションを開始
+    let mut tx = pool.begin().await.map_err(|e| {
+        Failure::database(format!(
+            "データベーストランザクションを開始できません。{}",
+            e
+        ))
+    })?;
+
+    // 指定された都道府県の郵便番号が登録されているか確認
+    if exists_postal_code(&mut tx, code).await? {
+        // 指定された都道府県の郵便番号が登録されている場合は、削除して登録するか確認
+        if !confirm_replace(prompter, code) {
+            return Ok(None);
+        }
+        // 指定された都道府県の郵便番号を削除
+        delete_postal_codes(&mut tx, code).await?;
+    }
+
+    // 郵便番号をデータベースに登録
+    register_postal_codes(&mut tx, &postal_codes).await?;
+
+    // 郵便局に郵便番号を設定
+    enrich_post_offices(&mut tx, code).await?;
+    // 登録の履歴を記録
+    let source = ImportSource {
+        dataset: "KEN_ALL",
+        code: Some(code),
+        file,
+        year: None,
+    };
+    record_import(&mut tx, &source, "postal_codes", count).await?;
+
+    // トランザクションをコミット
+    tx.commit().await.map_err(|e| {
+        Failure::database(format!(
+            "データベーストランザクションをコミットできませんでした。{}",
+            e
+        ))
+    })?;
+
+    Ok(Some(count))
+}
+
+/// 郵便番号データを読み込み、データベースに登録せずに検証する。
+///
+/// 登録するときと同様に郵便番号データを解析して、指定された都道府県の郵便番号と市区町村の数を
+/// 検証結果に記録する。
+///
+/// # Arguments
+///
+/// * `file` - 郵便番号データのパス。
+/// * `code` - 都道府県コード。
+/// * `encoding` - 郵便番号データのエンコーディング。
+///
+/// # Returns
+///
+/// 検証結果。
+pub fn validate_postal_codes(
+    file: &str,
+    code: &str,
+    encoding: &str,
+) -> anyhow::Result<DryRunReport> {
+    code.parse::<PrefectureCode>().map_err(Failure::argument)?;
+
+    let content = read_postal_code_file(file, encoding)?;
+    let mut report = DryRunReport::new(file, code);
+    match parse_postal_codes(&content, code) {
+        Ok(postal_codes) => {
+            let cities: HashSet<&str> = postal_codes
+                .iter()
+                .map(|postal_code| postal_code.city_code.as_str())
+                .collect();
+            report.counts.push(("郵便番号", postal_codes.len()));
+            report.counts.push(("市区町村", cities.len()));
+            if postal_codes.is_empty() {
+                report.issue(format!(
+                    "都道府県({})の郵便番号が記録されていません。",
+                    code
+                ));
+            }
+        }
+        Err(e) => report.issue(e.to_string()),
+    }
+
+    Ok(report)
+}
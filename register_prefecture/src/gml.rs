@@ -0,0 +1,667 @@
+//! 国土数値情報の行政区域データ(JPGIS2.1準拠のGML)のリーダー。
+//!
+//! 曲線(`gml:Curve`)と曲面(`gml:Surface`)を読み込み、行政区域(`ksj:AdministrativeBoundary`)が
+//! 参照する曲面をポリゴンに組み立てる。行政区域の属性は、GeoJSON形式の行政区域データと同じ
+//! `N03_001`から`N03_007`までの属性に対応させる。
+//!
+//! <https://nlftp.mlit.go.jp/ksj/gml/datalist/KsjTmplt-N03-v3_1.html>
+
+use std::collections::HashMap;
+
+use geojson::{Feature, FeatureCollection, Geometry, JsonObject, Value};
+use serde_json::json;
+use utils::errors::Failure;
+
+/// 行政区域の要素の名前と、GeoJSON形式の行政区域データの属性の名前。
+const PROPERTY_NAMES: [(&str, &str); 5] = [
+    ("prefectureName", "N03_001"),
+    ("subPrefectureName", "N03_002"),
+    ("countyName", "N03_003"),
+    ("cityName", "N03_004"),
+    ("administrativeAreaCode", "N03_007"),
+];
+
+/// XMLの要素または文字列。
+#[derive(Debug)]
+enum Event<'a> {
+    /// 開始タグまたは空要素タグ
+    Start {
+        name: &'a str,
+        attributes: Vec<(&'a str, String)>,
+    },
+    /// 終了タグ
+    End { name: &'a str },
+    /// 文字列
+    Text(&'a str),
+}
+
+/// XMLの要素と文字列を順に読み込むリーダー。
+///
+/// 行政区域データを読み込むために必要な構文に限り解釈する。
+struct XmlReader<'a> {
+    /// XML文書
+    xml: &'a str,
+    /// 次に読み込む位置
+    pos: usize,
+}
+
+impl<'a> XmlReader<'a> {
+    fn new(xml: &'a str) -> Self {
+        Self { xml, pos: 0 }
+    }
+
+    /// 次の要素または文字列を読み込む。
+    ///
+    /// # Returns
+    ///
+    /// 要素または文字列。XML文書の終わりに達した場合はNone。
+    fn next_event(&mut self) -> anyhow::Result<Option<Event<'a>>> {
+        loop {
+            let rest = &self.xml[self.pos..];
+            if rest.is_empty() {
+                return Ok(None);
+            }
+            if !rest.starts_with('<') {
+                let end = rest.find('<').unwrap_or(rest.len());
+                self.pos += end;
+                return Ok(Some(Event::Text(&rest[..end])));
+            }
+            // XML宣言、処理命令、コメント及び文書型宣言は読み飛ばす
+            let skipped = [("<?", "?>"), ("<!--", "-->"), ("<!", ">")]
+                .iter()
+                .find(|(open, _)| rest.starts_with(open));
+            if let Some((_, close)) = skipped {
+                let end = rest.find(close).ok_or_else(|| {
+                    self.syntax_error("閉じられていない宣言またはコメントがあります。")
+                })?;
+                self.pos += end + close.len();
+                continue;
+            }
+            let end = rest
+                .find('>')
+                .ok_or_else(|| self.syntax_error("閉じられていないタグがあります。"))?;
+            let tag = &rest[1..end];
+            self.pos += end + 1;
+            if let Some(name) = tag.strip_prefix('/') {
+                return Ok(Some(Event::End { name: name.trim() }));
+            }
+            let tag = tag.strip_suffix('/').unwrap_or(tag);
+            let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+            let attributes = self.parse_attributes(&tag[name_end..])?;
+            return Ok(Some(Event::Start {
+                name: &tag[..name_end],
+                attributes,
+            }));
+        }
+    }
+
+    /// タグの属性を解釈する。
+    fn parse_attributes(&self, mut s: &'a str) -> anyhow::Result<Vec<(&'a str, String)>> {
+        let mut attributes = Vec::new();
+        loop {
+            s = s.trim_start();
+            if s.is_empty() {
+                return Ok(attributes);
+            }
+            let eq = s
+                .find('=')
+                .ok_or_else(|| self.syntax_error("値のない属性があります。"))?;
+            let name = s[..eq].trim();
+            let value = s[eq + 1..].trim_start();
+            let quote = value
+                .chars()
+                .next()
+                .filter(|c| *c == '"' || *c == '\'')
+                .ok_or_else(|| self.syntax_error("引用符で囲まれていない属性の値があります。"))?;
+            let close = value[1..]
+                .find(quote)
+                .ok_or_else(|| self.syntax_error("閉じられていない属性の値があります。"))?;
+            attributes.push((name, unescape(&value[1..close + 1])));
+            s = &value[close + 2..];
+        }
+    }
+
+    /// 読み込んでいる位置を含めた、XML文書の構文のエラーを構築する。
+    fn syntax_error(&self, message: &str) -> anyhow::Error {
+        let line = self.xml[..self.pos].matches('\n').count() + 1;
+        Failure::input(format!("{}行目: {}", line, message)).into()
+    }
+}
+
+/// 文字参照と定義済みの実体参照を、参照する文字に置き換える。
+fn unescape(s: &str) -> String {
+    if !s.contains('&') {
+        return s.to_string();
+    }
+    let mut result = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = match rest.find(';') {
+            Some(end) => end,
+            None => break,
+        };
+        let entity = &rest[1..end];
+        let c = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16).ok())
+                .unwrap_or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match c {
+            Some(c) => result.push(c),
+            None => result.push_str(&rest[..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+    result.push_str(rest);
+
+    result
+}
+
+/// 名前空間の接頭辞を除いた、要素または属性の名前を返す。
+fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+/// 属性の値を、名前空間の接頭辞を除いた名前で取得する。
+fn attribute<'b>(attributes: &'b [(&str, String)], name: &str) -> Option<&'b str> {
+    attributes
+        .iter()
+        .find(|(key, _)| local_name(key) == name)
+        .map(|(_, value)| value.as_str())
+}
+
+/// 座標列(緯度、経度の順)を、経度、緯度の順の座標のベクタに変換する。
+fn parse_pos_list(s: &str) -> anyhow::Result<Vec<Vec<f64>>> {
+    let values = s
+        .split_whitespace()
+        .map(|value| {
+            value
+                .parse::<f64>()
+                .map_err(|_| Failure::data(format!("座標({})を数値に変換できません。", value)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if values.len() % 2 != 0 {
+        return Err(Failure::data("座標列の値の数が奇数です。").into());
+    }
+
+    Ok(values.chunks(2).map(|bl| vec![bl[1], bl[0]]).collect())
+}
+
+/// 空間参照系の名前から、EPSGコードを取得する。
+///
+/// JPGISの空間参照系(`JGD2011 / (B, L)`など)と、EPSGコードを含む名前に対応する。
+fn epsg_code_from_srs_name(name: &str) -> Option<i32> {
+    let lower = name.to_lowercase();
+    if let Some(code) = lower
+        .rsplit([':', '/'])
+        .next()
+        .filter(|_| lower.contains("epsg"))
+        .and_then(|code| code.trim().parse().ok())
+    {
+        return Some(code);
+    }
+    if lower.contains("jgd2011") {
+        Some(6668)
+    } else if lower.contains("jgd2000") {
+        Some(4612)
+    } else if lower.contains("tokyo") {
+        Some(4301)
+    } else {
+        None
+    }
+}
+
+/// リングを構成する部分。
+#[derive(Debug)]
+enum RingPart {
+    /// 参照する曲線のID
+    Curve(String),
+    /// 直接記録された座標
+    Coords(Vec<Vec<f64>>),
+}
+
+/// 読み込み中の曲面のポリゴン(外周と内周のリング)。
+type PolygonParts = Vec<Vec<RingPart>>;
+
+/// 読み込み中の行政区域。
+#[derive(Debug, Default)]
+struct Boundary {
+    /// 参照する曲面のID
+    surface: Option<String>,
+    /// 属性の名前と値
+    properties: HashMap<String, String>,
+}
+
+/// 行政区域データを読み込んだ結果。
+#[derive(Debug, Default)]
+struct Document {
+    /// 空間参照系のEPSGコード
+    srid: Option<i32>,
+    /// IDと曲線の座標
+    curves: HashMap<String, Vec<Vec<f64>>>,
+    /// IDと曲面のポリゴン
+    surfaces: HashMap<String, Vec<PolygonParts>>,
+    /// 行政区域
+    boundaries: Vec<Boundary>,
+}
+
+/// 行政区域データのXML文書を読み込む。
+fn parse_document(xml: &str) -> anyhow::Result<Document> {
+    let mut reader = XmlReader::new(xml);
+    let mut document = Document::default();
+    let mut text = String::new();
+    // 読み込み中の曲線、曲面、リング及び行政区域
+    let mut curve: Option<(String, Vec<Vec<f64>>)> = None;
+    let mut surface: Option<(String, Vec<PolygonParts>)> = None;
+    let mut ring: Option<Vec<RingPart>> = None;
+    let mut boundary: Option<Boundary> = None;
+    while let Some(event) = reader.next_event()? {
+        match event {
+            Event::Start { name, attributes } => {
+                text.clear();
+                if document.srid.is_none() {
+                    document.srid =
+                        attribute(&attributes, "srsName").and_then(epsg_code_from_srs_name);
+                }
+                let id = attribute(&attributes, "id").map(str::to_string);
+                match local_name(name) {
+                    "Curve" if surface.is_none() => {
+                        curve = Some((id.unwrap_or_default(), Vec::new()));
+                    }
+                    "Surface" => surface = Some((id.unwrap_or_default(), Vec::new())),
+                    "PolygonPatch" | "Polygon" => {
+                        if let Some((_, polygons)) = surface.as_mut() {
+                            polygons.push(Vec::new());
+                        }
+                    }
+                    "exterior" | "interior" if surface.is_some() => ring = Some(Vec::new()),
+                    "curveMember" => {
+                        if let (Some(ring), Some(href)) =
+                            (ring.as_mut(), attribute(&attributes, "href"))
+                        {
+                            ring.push(RingPart::Curve(href.trim_start_matches('#').to_string()));
+                        }
+                    }
+                    "AdministrativeBoundary" => boundary = Some(Boundary::default()),
+                    "bounds" => {
+                        if let (Some(boundary), Some(href)) =
+                            (boundary.as_mut(), attribute(&attributes, "href"))
+                        {
+                            boundary.surface = Some(href.trim_start_matches('#').to_string());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Event::Text(s) => text.push_str(s),
+            Event::End { name } => {
+                match local_name(name) {
+                    "posList" | "pos" => {
+                        let coords = parse_pos_list(&unescape(&text))?;
+                        if let Some((_, curve_coords)) = curve.as_mut() {
+                            append_coords(curve_coords, coords);
+                        } else if let Some(ring) = ring.as_mut() {
+                            ring.push(RingPart::Coords(coords));
+                        }
+                    }
+                    "Curve" => {
+                        if let Some((id, coords)) = curve.take() {
+                            document.curves.insert(id, coords);
+                        }
+                    }
+                    "exterior" | "interior" => {
+                        let polygon = surface
+                            .as_mut()
+                            .and_then(|(_, polygons)| polygons.last_mut());
+                        if let (Some(polygon), Some(ring)) = (polygon, ring.take()) {
+                            polygon.push(ring);
+                        }
+                    }
+                    "Surface" => {
+                        if let Some((id, polygons)) = surface.take() {
+                            document.surfaces.insert(id, polygons);
+                        }
+                    }
+                    "AdministrativeBoundary" => {
+                        if let Some(boundary) = boundary.take() {
+                            document.boundaries.push(boundary);
+                        }
+                    }
+                    local => {
+                        if let Some(boundary) = boundary.as_mut() {
+                            if PROPERTY_NAMES.iter().any(|(element, _)| *element == local) {
+                                boundary
+                                    .properties
+                                    .insert(local.to_string(), unescape(text.trim()));
+                            }
+                        }
+                    }
+                }
+                text.clear();
+            }
+        }
+    }
+
+    Ok(document)
+}
+
+/// 座標を追加する。追加する座標の始点が、追加される座標の終点と一致する場合は、始点を追加しない。
+fn append_coords(coords: &mut Vec<Vec<f64>>, mut appended: Vec<Vec<f64>>) {
+    if coords.last().is_some() && coords.last() == appended.first() {
+        appended.remove(0);
+    }
+    coords.extend(appended);
+}
+
+/// 曲面のポリゴンを、GeoJSONのジオメトリに組み立てる。
+///
+/// # Arguments
+///
+/// * `document` - 行政区域データを読み込んだ結果。
+/// * `id` - 曲面のID。
+///
+/// # Returns
+///
+/// ポリゴンが1つの場合はポリゴン、複数の場合はマルチポリゴン。
+fn build_geometry(document: &Document, id: &str) -> anyhow::Result<Geometry> {
+    let polygons = document
+        .surfaces
+        .get(id)
+        .ok_or_else(|| Failure::data(format!("曲面({})が見つかりません。", id)))?;
+    let mut built = Vec::new();
+    for polygon in polygons {
+        let mut rings = Vec::new();
+        for parts in polygon {
+            let mut coords = Vec::new();
+            for part in parts {
+                match part {
+                    RingPart::Curve(curve) => {
+                        let curve_coords = document.curves.get(curve).ok_or_else(|| {
+                            Failure::data(format!("曲線({})が見つかりません。", curve))
+                        })?;
+                        append_coords(&mut coords, curve_coords.clone());
+                    }
+                    RingPart::Coords(ring_coords) => {
+                        append_coords(&mut coords, ring_coords.clone())
+                    }
+                }
+            }
+            rings.push(coords);
+        }
+        built.push(rings);
+    }
+
+    Ok(match built.len() {
+        1 => Geometry::new(Value::Polygon(built.remove(0))),
+        _ => Geometry::new(Value::MultiPolygon(built)),
+    })
+}
+
+/// 行政区域データ(JPGIS2.1準拠のGML)を読み込み、GeoJSON形式の行政区域データと同じ属性を持つ
+/// フィーチャーコレクションに変換する。
+///
+/// 空間参照系は、GeoJSON形式の行政区域データと同様に、フィーチャーコレクションの`crs`に記録する。
+///
+/// # Arguments
+///
+/// * `xml` - 行政区域データのXML文書。
+///
+/// # Returns
+///
+/// フィーチャーコレクション。
+pub fn parse_administrative_boundaries(xml: &str) -> anyhow::Result<FeatureCollection> {
+    let document = parse_document(xml)?;
+    let mut features = Vec::with_capacity(document.boundaries.len());
+    for (index, boundary) in document.boundaries.iter().enumerate() {
+        let id = boundary.surface.as_deref().ok_or_else(|| {
+            Failure::data(format!("{}番目の行政区域が曲面を参照していません。", index))
+        })?;
+        let mut properties = JsonObject::new();
+        for (element, key) in PROPERTY_NAMES {
+            let value = match boundary.properties.get(element) {
+                Some(value) if !value.is_empty() => json!(value),
+                _ => serde_json::Value::Null,
+            };
+            properties.insert(key.to_string(), value);
+        }
+        features.push(Feature {
+            bbox: None,
+            geometry: Some(build_geometry(&document, id)?),
+            id: None,
+            properties: Some(properties),
+            foreign_members: None,
+        });
+    }
+    let foreign_members = document.srid.map(|srid| {
+        let mut members = JsonObject::new();
+        members.insert(
+            "crs".to_string(),
+            json!({
+                "type": "name",
+                "properties": { "name": format!("urn:ogc:def:crs:EPSG::{}", srid) }
+            }),
+        );
+        members
+    });
+
+    Ok(FeatureCollection {
+        bbox: None,
+        features,
+        foreign_members,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use utils::errors::{failure_kind, FailureKind};
+
+    use super::*;
+
+    /// 内周を持つ曲面と、座標を直接記録した2つのポリゴンからなる曲面を参照する行政区域データ。
+    ///
+    /// 座標は、JGD2011の地理座標系(EPSG:6668)の軸の順序に従って、緯度、経度の順で記録する。
+    const FIXTURE: &str = r##"<?xml version="1.0" encoding="UTF-8"?>
+<!-- 国土数値情報(行政区域データ)を模したフィクスチャー -->
+<ksj:Dataset xmlns:ksj="http://nlftp.mlit.go.jp/ksj/schemas/ksj-app" xmlns:gml="http://www.opengis.net/gml/3.2" xmlns:xlink="http://www.w3.org/1999/xlink" gml:id="Dataset1">
+  <gml:description>N03</gml:description>
+  <gml:boundedBy>
+    <gml:EnvelopeWithTimePeriod srsName="JGD2011 / (B, L)" frame="GC / JST">
+      <gml:lowerCorner>35.0 136.0</gml:lowerCorner>
+      <gml:upperCorner>36.0 137.0</gml:upperCorner>
+    </gml:EnvelopeWithTimePeriod>
+  </gml:boundedBy>
+  <gml:Curve gml:id="cv1">
+    <gml:segments>
+      <gml:LineStringSegment>
+        <gml:posList>35.0 136.0 35.0 137.0 36.0 137.0</gml:posList>
+      </gml:LineStringSegment>
+    </gml:segments>
+  </gml:Curve>
+  <gml:Curve gml:id="cv2">
+    <gml:segments>
+      <gml:LineStringSegment>
+        <gml:posList>36.0 137.0 36.0 136.0 35.0 136.0</gml:posList>
+      </gml:LineStringSegment>
+    </gml:segments>
+  </gml:Curve>
+  <gml:Curve gml:id="cv3">
+    <gml:segments>
+      <gml:LineStringSegment>
+        <gml:posList>
+          35.4 136.4 35.6 136.4 35.6 136.6 35.4 136.6 35.4 136.4
+        </gml:posList>
+      </gml:LineStringSegment>
+    </gml:segments>
+  </gml:Curve>
+  <gml:Surface gml:id="sf1">
+    <gml:patches>
+      <gml:PolygonPatch>
+        <gml:exterior>
+          <gml:Ring>
+            <gml:curveMember xlink:href="#cv1"/>
+            <gml:curveMember xlink:href="#cv2"/>
+          </gml:Ring>
+        </gml:exterior>
+        <gml:interior>
+          <gml:Ring>
+            <gml:curveMember xlink:href="#cv3"/>
+          </gml:Ring>
+        </gml:interior>
+      </gml:PolygonPatch>
+    </gml:patches>
+  </gml:Surface>
+  <gml:Surface gml:id="sf2">
+    <gml:patches>
+      <gml:PolygonPatch>
+        <gml:exterior>
+          <gml:LinearRing>
+            <gml:posList>35.0 138.0 35.0 139.0 36.0 139.0 35.0 138.0</gml:posList>
+          </gml:LinearRing>
+        </gml:exterior>
+      </gml:PolygonPatch>
+      <gml:PolygonPatch>
+        <gml:exterior>
+          <gml:LinearRing>
+            <gml:posList>37.0 138.0 37.0 139.0 38.0 139.0 37.0 138.0</gml:posList>
+          </gml:LinearRing>
+        </gml:exterior>
+      </gml:PolygonPatch>
+    </gml:patches>
+  </gml:Surface>
+  <ksj:AdministrativeBoundary gml:id="AB1">
+    <ksj:bounds xlink:href="#sf1"/>
+    <ksj:prefectureName>岐阜県</ksj:prefectureName>
+    <ksj:subPrefectureName/>
+    <ksj:countyName></ksj:countyName>
+    <ksj:cityName>岐阜市</ksj:cityName>
+    <ksj:administrativeAreaCode>21201</ksj:administrativeAreaCode>
+  </ksj:AdministrativeBoundary>
+  <ksj:AdministrativeBoundary gml:id="AB2">
+    <ksj:bounds xlink:href="#sf2"/>
+    <ksj:prefectureName>岐阜県</ksj:prefectureName>
+    <ksj:countyName>揖斐郡</ksj:countyName>
+    <ksj:cityName>揖斐川町&amp;テスト</ksj:cityName>
+    <ksj:administrativeAreaCode>21404</ksj:administrativeAreaCode>
+  </ksj:AdministrativeBoundary>
+</ksj:Dataset>
+"##;
+
+    /// フィーチャーのジオメトリを返す。
+    fn geometry(feature: &Feature) -> &Value {
+        &feature.geometry.as_ref().unwrap().value
+    }
+
+    /// フィーチャーの属性を返す。
+    fn property<'a>(feature: &'a Feature, name: &str) -> &'a serde_json::Value {
+        feature.properties.as_ref().unwrap().get(name).unwrap()
+    }
+
+    #[test]
+    fn parse_administrative_boundaries_reads_attributes_and_crs() {
+        let fc = parse_administrative_boundaries(FIXTURE).unwrap();
+        assert_eq!(fc.features.len(), 2);
+        let feature = &fc.features[0];
+        assert_eq!(property(feature, "N03_001"), "岐阜県");
+        assert!(property(feature, "N03_002").is_null());
+        assert!(property(feature, "N03_003").is_null());
+        assert_eq!(property(feature, "N03_004"), "岐阜市");
+        assert_eq!(property(feature, "N03_007"), "21201");
+        assert_eq!(property(&fc.features[1], "N03_003"), "揖斐郡");
+        assert_eq!(property(&fc.features[1], "N03_004"), "揖斐川町&テスト");
+        assert_eq!(
+            fc.foreign_members.unwrap()["crs"]["properties"]["name"],
+            "urn:ogc:def:crs:EPSG::6668"
+        );
+    }
+
+    #[test]
+    fn parse_administrative_boundaries_builds_polygon_with_interior_ring() {
+        let fc = parse_administrative_boundaries(FIXTURE).unwrap();
+        // 曲線は緯度、経度の順で記録されているため、経度、緯度の順に入れ替え、曲線の接続点は重複させない
+        let expected = Value::Polygon(vec![
+            vec![
+                vec![136.0, 35.0],
+                vec![137.0, 35.0],
+                vec![137.0, 36.0],
+                vec![136.0, 36.0],
+                vec![136.0, 35.0],
+            ],
+            vec![
+                vec![136.4, 35.4],
+                vec![136.4, 35.6],
+                vec![136.6, 35.6],
+                vec![136.6, 35.4],
+                vec![136.4, 35.4],
+            ],
+        ]);
+        assert_eq!(geometry(&fc.features[0]), &expected);
+    }
+
+    #[test]
+    fn parse_administrative_boundaries_builds_multipolygon_from_patches() {
+        let fc = parse_administrative_boundaries(FIXTURE).unwrap();
+        let expected = Value::MultiPolygon(vec![
+            vec![vec![
+                vec![138.0, 35.0],
+                vec![139.0, 35.0],
+                vec![139.0, 36.0],
+                vec![138.0, 35.0],
+            ]],
+            vec![vec![
+                vec![138.0, 37.0],
+                vec![139.0, 37.0],
+                vec![139.0, 38.0],
+                vec![138.0, 37.0],
+            ]],
+        ]);
+        assert_eq!(geometry(&fc.features[1]), &expected);
+    }
+
+    #[test]
+    fn parse_administrative_boundaries_rejects_malformed_coordinates() {
+        // 数値でない座標と、値の数が奇数の座標列
+        for (pos_list, message) in [("35.0 136.0 35.0 abc", "abc"), ("35.0 136.0 35.0", "奇数")] {
+            let xml = FIXTURE.replace("35.0 136.0 35.0 137.0 36.0 137.0", pos_list);
+            let e = parse_administrative_boundaries(&xml).unwrap_err();
+            assert_eq!(failure_kind(&e), Some(FailureKind::Data), "{}", e);
+            assert!(e.to_string().contains(message), "{}", e);
+        }
+    }
+
+    #[test]
+    fn parse_administrative_boundaries_rejects_missing_references() {
+        let xml = FIXTURE.replace(r##"xlink:href="#cv3""##, r##"xlink:href="#cv9""##);
+        let e = parse_administrative_boundaries(&xml).unwrap_err();
+        assert_eq!(failure_kind(&e), Some(FailureKind::Data));
+        assert!(e.to_string().contains("cv9"), "{}", e);
+    }
+
+    #[test]
+    fn parse_administrative_boundaries_rejects_unclosed_tags() {
+        let xml = &FIXTURE[..FIXTURE.find("<ksj:cityName").unwrap() + 4];
+        let e = parse_administrative_boundaries(xml).unwrap_err();
+        assert_eq!(failure_kind(&e), Some(FailureKind::Input));
+    }
+
+    #[test]
+    fn epsg_code_from_srs_name_accepts_jpgis_and_epsg_names() {
+        assert_eq!(epsg_code_from_srs_name("JGD2011 / (B, L)"), Some(6668));
+        assert_eq!(epsg_code_from_srs_name("JGD2000 / (B, L)"), Some(4612));
+        assert_eq!(epsg_code_from_srs_name("TOKYO / (B, L)"), Some(4301));
+        assert_eq!(
+            epsg_code_from_srs_name("http://www.opengis.net/def/crs/EPSG/0/6668"),
+            Some(6668)
+        );
+        assert_eq!(epsg_code_from_srs_name("EPSG:4326"), Some(4326));
+        assert_eq!(epsg_code_from_srs_name("WGS84"), None);
+    }
+}
@@ -0,0 +1,1205 @@
+pub mod gml;
+pub mod schema;
+pub mod shape;
+
+use std::{convert::TryInto, fs::File, io::Read, path::Path, str::FromStr};
+
+use anyhow::Context;
+use database::copy::StagingTable;
+use database::imports::{record_import, ImportSource};
+use database::upsert::{delete_missing, on_conflict_update, UpsertSummary};
+use geojson::{self, Feature, FeatureCollection, JsonObject};
+use regex::Regex;
+use serde_json::Value;
+use sqlx::{PgPool, Postgres, Transaction};
+use utils::crs::srid_from_geojson_crs;
+use utils::dry_run::{geometry_problem, srid_problem, DryRunReport};
+use utils::errors::Failure;
+use utils::import::ImportOptions;
+use utils::prefecture::PrefectureCode;
+use utils::progress::Progress;
+use utils::projection::transform_geometry;
+use utils::prompt::{confirm_replace, Prompter};
+use utils::spill::SpillQueue;
+use utils::{to_hex_ewkb, EPSG_WEB_MERCATOR, EPSG_WGS84};
+
+use crate::schema::BoundarySchema;
+
+/// 行政区域データのファイルの形式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoundaryFormat {
+    /// GeoJSON
+    GeoJson,
+    /// JPGIS2.1準拠のGML
+    Gml,
+    /// Shapeファイル
+    Shapefile,
+}
+
+impl BoundaryFormat {
+    /// ファイルの拡張子から、行政区域データのファイルの形式を判断する。
+    ///
+    /// 拡張子が`.xml`または`.gml`の場合はGML、`.shp`の場合はShapeファイル、それ以外の場合はGeoJSONと
+    /// 判断する。
+    ///
+    /// # Arguments
+    ///
+    /// * `file` - 行政区域データのファイルのパス。
+    ///
+    /// # Returns
+    ///
+    /// 行政区域データのファイルの形式。
+    pub fn from_path(file: &str) -> Self {
+        let extension = Path::new(file)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_lowercase());
+        match extension.as_deref() {
+            Some("xml") | Some("gml") => BoundaryFormat::Gml,
+            Some("shp") => BoundaryFormat::Shapefile,
+            _ => BoundaryFormat::GeoJson,
+        }
+    }
+}
+
+impl FromStr for BoundaryFormat {
+    type Err = String;
+
+    /// `geojson`、`gml`または`shapefile`から、行政区域データのファイルの形式を構築する。
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "geojson" => Ok(BoundaryFormat::GeoJson),
+            "gml" => Ok(BoundaryFormat::Gml),
+            "shapefile" | "shp" => Ok(BoundaryFormat::Shapefile),
+            _ => Err(format!(
+                "行政区域データの形式({})は、geojson、gmlまたはshapefileで指定してください。",
+                s
+            )),
+        }
+    }
+}
+
+/// 国土交通省国土数値情報ダウンロードサイトから取得した行政区域データ(GeoJSON、GMLまたはShapeファイル)を読み込み、
+/// フィーチャーの属性を2022年以降の行政区域データの属性に読み替える。
+///
+/// # Arguments
+///
+/// * `file`: 行政区域データ（GeoJSON、GMLまたはShapeファイル）ファイルのパス。
+/// * `format`: 行政区域データのファイルの形式。Noneの場合はファイルの拡張子から判断する。
+/// * `schema`: 行政区域データの属性の構成。Noneの場合はフィーチャーの属性から判断する。
+///
+/// # Returns
+///
+/// フィーチャーコレクション。
+fn read_features(
+    file: &str,
+    format: Option<BoundaryFormat>,
+    schema: Option<BoundarySchema>,
+) -> anyhow::Result<FeatureCollection> {
+    let fc = read_boundary_file(file, format)?;
+    let schema = match schema {
+        Some(schema) => schema,
+        None => BoundarySchema::detect(&fc)
+            .with_context(|| format!("ファイル({})の属性の構成を判断できません。", file))?,
+    };
+
+    Ok(schema.normalize(fc))
+}
+
+/// 行政区域データ(GeoJSON、GMLまたはShapeファイル)を、フィーチャーコレクションとして読み込む。
+///
+/// # Arguments
+///
+/// * `file`: 行政区域データ（GeoJSON、GMLまたはShapeファイル）ファイルのパス。
+/// * `format`: 行政区域データのファイルの形式。Noneの場合はファイルの拡張子から判断する。
+///
+/// # Returns
+///
+/// フィーチャーコレクション。
+fn read_boundary_file(
+    file: &str,
+    format: Option<BoundaryFormat>,
+) -> anyhow::Result<FeatureCollection> {
+    let format = format.unwrap_or_else(|| BoundaryFormat::from_path(file));
+    if format == BoundaryFormat::Shapefile {
+        return shape::read_administrative_boundaries(file).with_context(|| {
+            format!(
+                "ファイル({})をShapeファイルの行政区域データとして読み込めません。",
+                file
+            )
+        });
+    }
+
+    // GEOJSONファイルの内容を読み込み
+    let mut content = String::new();
+    File::open(file)
+        .and_then(|mut f| f.read_to_string(&mut content))
+        .map_err(|e| Failure::input(format!("ファイル({})を読み込めません。{}", file, e)))?;
+    if format == BoundaryFormat::Gml {
+        return gml::parse_administrative_boundaries(&content).with_context(|| {
+            format!(
+                "ファイル({})をJPGIS準拠のGMLの行政区域データとして読み込めません。",
+                file
+            )
+        });
+    }
+
+    // GEOJSONファイルの内容をフィーチャコレクションに変換
+    let fc = FeatureCollection::from_str(&content).map_err(|e| {
+        Failure::input(format!(
+            "ファイル({})をGeoJSONのフィーチャーコレクションとして読み込めません。{}",
+            file, e
+        ))
+    })?;
+
+    Ok(fc)
+}
+
+/// フィーチャーコレクションからEPSGコードを取得する。
+///
+/// # Arguments
+///
+/// * `fc` - フィーチャコレクション。
+///
+/// # Returns
+///
+/// EPSGコード。フィーチャーコレクションにEPSGコードが記録されていない場合はNone。
+fn get_epsg_code(fc: &FeatureCollection) -> Option<i32> {
+    srid_from_geojson_crs(fc.foreign_members.as_ref())
+}
+
+/// 登録する行政区域データの空間参照IDを決定する。
+///
+/// 空間参照IDが指定された場合は指定された空間参照ID、指定されていない場合はフィーチャーコレクションに
+/// 記録されたEPSGコードとする。RFC 7946に準拠したGeoJSONファイルのように、空間参照系が記録されて
+/// いない場合は、WGS84経緯度(EPSG:4326)とする。
+///
+/// # Arguments
+///
+/// * `fc` - フィーチャーコレクション。
+/// * `srid` - 指定された空間参照ID。
+///
+/// # Returns
+///
+/// 空間参照ID。
+fn resolve_srid(fc: &FeatureCollection, srid: Option<i32>) -> anyhow::Result<i32> {
+    match srid {
+        Some(srid) if srid <= 0 => {
+            Err(Failure::argument(format!("SRID({})が不正です。", srid)).into())
+        }
+        Some(srid) => Ok(srid),
+        None => Ok(get_epsg_code(fc).unwrap_or(EPSG_WGS84)),
+    }
+}
+
+/// フィーチャから属性を取得する。
+///
+/// # Arguments
+///
+/// * `f` - フィーチャー。
+/// * `key` - 属性のキー（名前）。
+///
+/// # Returns
+///
+/// 属性の値。属性が存在しない場合、または文字列とnull以外の値の場合はエラー。
+fn get_feature_property(f: &Feature, key: &str) -> anyhow::Result<Option<String>> {
+    match f
+        .properties
+        .as_ref()
+        .and_then(|properties| properties.get(key))
+    {
+        Some(Value::Null) => Ok(None),
+        Some(Value::String(value)) => Ok(Some(value.clone())),
+        Some(value) => Err(Failure::data(format!(
+            "属性{}の値({})が文字列ではありません。",
+            key, value
+        ))
+        .into()),
+        None => Err(Failure::data(format!("属性{}がありません。", key)).into()),
+    }
+}
+
+/// フィーチャから、値が必須の属性を取得する。
+///
+/// # Arguments
+///
+/// * `f` - フィーチャー。
+/// * `key` - 属性のキー（名前）。
+///
+/// # Returns
+///
+/// 属性の値。属性が存在しない場合、または値がnullの場合はエラー。
+fn get_required_property(f: &Feature, key: &str) -> anyhow::Result<String> {
+    get_feature_property(f, key)?
+        .ok_or_else(|| Failure::data(format!("属性{}の値がありません。", key)).into())
+}
+
+/// フィーチャーのジオメトリを取得する。
+///
+/// # Arguments
+///
+/// * `f` - フィーチャー。
+///
+/// # Returns
+///
+/// ジオメトリ。ジオメトリが存在しない場合、または変換できない場合はエラー。
+fn get_geometry(f: &Feature) -> anyhow::Result<geo_types::Geometry<f64>> {
+    let value = match &f.geometry {
+        Some(geometry) => geometry.value.clone(),
+        None => return Err(Failure::data("ジオメトリがありません。").into()),
+    };
+
+    value
+        .try_into()
+        .map_err(|e| Failure::data(format!("ジオメトリを変換できません。{}", e)).into())
+}
+
+/// フィーチャーが都道府県か確認する。
+///
+/// # Arguments
+///
+/// * `f` - フィーチャー。
+///
+/// # Returns
+///
+/// 都道府県の場合はtrue。市区町村の場合はfalse。
+fn is_prefecture(f: &Feature) -> anyhow::Result<bool> {
+    for num in 2..=4 {
+        let value = get_feature_property(f, &format!("N03_00{}", num))?;
+        if let Some(value) = value {
+            if !value.is_empty() {
+                return Ok(false);
+            }
+        }
+    }
+
+    Ok(true)
+}
+
+/// 行政区域データの属性を設定し直した、都道府県フィーチャーを作成する。
+///
+/// # Arguments
+///
+/// * `f` - 行政区域データの都道府県フィーチャー。
+///
+/// # Returns
+///
+/// 行政区域データの属性を設定し直した都道府県フィーチャー。
+fn create_prefecture_feature(f: &Feature) -> anyhow::Result<Feature> {
+    let name = get_required_property(f, "N03_001")?;
+    let mut properties = JsonObject::new();
+    properties.insert("name".to_owned(), name.into());
+
+    Ok(Feature {
+        bbox: None,
+        geometry: f.geometry.clone(),
+        id: None,
+        properties: Some(properties),
+        foreign_members: None,
+    })
+}
+
+/// 行政区域データの属性を設定し直した、 市区町村フィーチャーを作成する。
+///
+/// # Arguments
+///
+/// * `f` - 行政区域データの市区町村フィーチャー。
+///
+/// # Returns
+///
+/// 行政区域データの属性を設定し直した市区町村フィーチャー。
+fn create_city_feature(f: &Feature) -> anyhow::Result<Feature> {
+    let area = get_feature_property(f, "N03_003")?;
+    let name = get_required_property(f, "N03_004")?;
+    let code = get_required_property(f, "N03_007")?;
+    let mut properties = JsonObject::new();
+    properties.insert("code".to_owned(), code.into());
+    properties.insert(
+        "area".to_owned(),
+        if let Some(area) = area {
+            area.into()
+        } else {
+            Value::Null
+        },
+    );
+    properties.insert("name".to_owned(), name.into());
+
+    Ok(Feature {
+        bbox: None,
+        geometry: f.geometry.clone(),
+        id: None,
+        properties: Some(properties),
+        foreign_members: None,
+    })
+}
+
+/// 行政区域データから読み込んだフィーチャーを、都道府県フィーチャと市区町村フィーチャーに分割する。
+///
+/// 分割したフィーチャーは、それぞれ`memory_budget`の半分を超えた分を一時ファイルに書き出す。
+///
+/// # Arguments
+///
+/// * `fc` - 行政区域データから読み込んだフィーチャを格納したフィーチャーコレクション。
+/// * `memory_budget` - 分割したフィーチャーをメモリに保持する上限(バイト)。
+///
+/// # Returns
+///
+/// 都道府県フィーチャを格納したキューと市区町村フィーチャを格納したキューのタプル。
+fn divide_prefectures_and_cities(
+    fc: FeatureCollection,
+    memory_budget: usize,
+) -> anyhow::Result<(SpillQueue<Feature>, SpillQueue<Feature>)> {
+    let mut prefectures = SpillQueue::new(memory_budget / 2);
+    let mut cities = SpillQueue::new(memory_budget / 2);
+    for (index, f) in fc.features.into_iter().enumerate() {
+        let context = || format!("{}番目のフィーチャーを読み込めません。", index);
+        if is_prefecture(&f).with_context(context)? {
+            prefectures.push(&create_prefecture_feature(&f).with_context(context)?)?;
+        } else {
+            cities.push(&create_city_feature(&f).with_context(context)?)?;
+        }
+    }
+
+    Ok((prefectures, cities))
+}
+
+/// 指定された都道府県コードの都道府県または市区町村のデータが、データベースに登録されているか確認する。
+///
+/// # Arguments
+///
+/// * `tx` - データベーストランザクション。
+/// * `code` - 都道府県コード。
+///
+/// # Returns
+///
+/// 当該都道府県またはその市区町村のデータがデータベースに登録されている場合はtrue。登録されていない場合はfalse。
+async fn exists_prefecture(tx: &mut Transaction<'_, Postgres>, code: &str) -> anyhow::Result<bool> {
+    let code_like = format!("{}%", code);
+    let result = sqlx::query!(
+        r#"
+        SELECT p.prefs, c.cities FROM
+        (SELECT COUNT(*) prefs FROM prefectures WHERE code = $1) p,
+        (SELECT COUNT(*) cities FROM cities WHERE code LIKE $2) c;
+        "#,
+        code,
+        &code_like,
+    )
+    .fetch_one(tx)
+    .await
+    .map_err(|e| {
+        Failure::database(format!(
+            "データベースに登録されているレコード数を確認するときにエラーが発生しました。{}",
+            e
+        ))
+    })?;
+    if 0 < result.prefs.unwrap() || 0 < result.cities.unwrap() {
+        return Ok(true);
+    }
+
+    Ok(false)
+}
+
+/// 指定された都道府県コードの都道府県と市区町村をデータベースから削除する。
+///
+/// # Arguments
+///
+/// * `tx` - データベーストランザクション。
+/// * `code` - 都道府県コード。
+///
+/// # Returns
+///
+/// 削除した都道府県の数と市区町村の数。
+pub async fn delete_prefectures_and_cities(
+    tx: &mut Transaction<'_, Postgres>,
+    code: &str,
+) -> anyhow::Result<(u64, u64)> {
+    let delete_error = |e: sqlx::Error| {
+        Failure::database(format!(
+            "データベースから都道府県と市区町村を削除するときにエラーが発生しました。{}",
+            e
+        ))
+    };
+    let prefectures = sqlx::query!("DELETE FROM prefectures WHERE code = $1", code)
+        .execute(&mut *tx)
+        .await
+        .map_err(delete_error)?
+        .rows_affected();
+
+    let code_like = format!("{}%", code);
+    let cities = sqlx::query!("DELETE FROM cities WHERE code LIKE $1", code_like)
+        .execute(&mut *tx)
+        .await
+        .map_err(delete_error)?
+        .rows_affected();
+
+    Ok((prefectures, cities))
+}
+
+/// 行政区域データのジオメトリを、登録するWebメルカトル投影法のジオメトリに変換する。
+///
+/// 経度と緯度が入れ替わっていないか、変換した座標が範囲内にあるか確認する。
+///
+/// # Arguments
+///
+/// * `geom` - 行政区域データのジオメトリ。
+/// * `srid` - 空間参照ID。
+/// * `options` - 登録するときのオプション。
+/// * `label` - メッセージに含める、ジオメトリを持つフィーチャーの名前。
+///
+/// # Returns
+///
+/// Webメルカトル投影法のジオメトリ。
+fn to_web_mercator(
+    mut geom: geo_types::Geometry<f64>,
+    srid: i32,
+    options: &ImportOptions,
+    label: &str,
+) -> anyhow::Result<geo_types::Geometry<f64>> {
+    options.fix_axis_order(&mut geom, srid, label)?;
+    let from = format!("EPSG:{}", srid);
+    let to = format!("EPSG:{}", EPSG_WEB_MERCATOR);
+    transform_geometry(&mut geom, srid, EPSG_WEB_MERCATOR).map_err(|e| {
+        Failure::data(format!(
+            "{}の座標を{}から{}に変換できません。{}",
+            label, from, to, e
+        ))
+    })?;
+    options.bbox_guard.validate(&geom, label)?;
+
+    Ok(geom)
+}
+
+/// 都道府県フィーチャーを、都道府県を登録する行に変換する。
+///
+/// # Arguments
+///
+/// * `f` - 都道府県フィーチャー。
+/// * `srid` - 空間参照ID。
+/// * `options` - 登録するときのオプション。
+///
+/// # Returns
+///
+/// 都道府県名とジオメトリ(Webメルカトル投影法のEWKB)を格納した行。
+fn prefecture_row(
+    f: &Feature,
+    srid: i32,
+    options: &ImportOptions,
+) -> anyhow::Result<Vec<Option<String>>> {
+    let name = get_required_property(f, "name")?;
+    let geom = to_web_mercator(
+        get_geometry(f)?,
+        srid,
+        options,
+        &format!("都道府県({})", name),
+    )?;
+
+    Ok(vec![
+        Some(name),
+        Some(to_hex_ewkb(&geom, EPSG_WEB_MERCATOR)?),
+    ])
+}
+
+/// 一時テーブルに登録したジオメトリのうち、不正なジオメトリ(自己交差したポリゴンなど)を修復する。
+///
+/// 不正なジオメトリは`ST_MakeValid`関数で修復して、修復したジオメトリに含まれるポリゴンごとに1行として
+/// 登録し直す。修復したジオメトリと不正な理由は、標準エラー出力に出力する。修復してもポリゴンが得られない
+/// ジオメトリがある場合は、修復せずにエラーを返す。
+///
+/// # Arguments
+///
+/// * `tx` - データベーストランザクション。
+/// * `table` - ジオメトリを登録した一時テーブル。
+/// * `columns` - 一時テーブルのジオメトリ以外の列。
+/// * `label` - メッセージに含める、行の名前を示すSQLの式。
+/// * `kind` - メッセージに含める、行の種類。
+///
+/// # Returns
+///
+/// 修復したジオメトリの数。
+async fn repair_invalid_geometries(
+    tx: &mut Transaction<'_, Postgres>,
+    table: &str,
+    columns: &[&str],
+    label: &str,
+    kind: &str,
+) -> anyhow::Result<usize> {
+    let database_error = |e: sqlx::Error| {
+        Failure::database(format!(
+            "{}のジオメトリを検証するときにエラーが発生しました。{}",
+            kind, e
+        ))
+    };
+    let invalid: Vec<(Option<String>, Option<String>, Option<i32>)> = sqlx::query_as(&format!(
+        r#"
+        SELECT {}, ST_IsValidReason(geom),
+            ST_NumGeometries(ST_CollectionExtract(ST_MakeValid(geom), 3))
+        FROM {} WHERE NOT ST_IsValid(geom)
+        "#,
+        label, table
+    ))
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(database_error)?;
+    for (name, reason, parts) in &invalid {
+        let name = name.as_deref().unwrap_or_default();
+        let reason = reason.as_deref().unwrap_or_default();
+        match parts {
+            Some(parts) if 0 < *parts => eprintln!(
+                "警告: {}({})のジオメトリが不正なため、修復して{}個のポリゴンとして登録します。{}",
+                kind, name, parts, reason
+            ),
+            _ => {
+                return Err(Failure::data(format!(
+                    "{}({})のジオメトリが不正で、修復できません。{}",
+                    kind, name, reason
+                ))
+                .into())
+            }
+        }
+    }
+    if invalid.is_empty() {
+        return Ok(0);
+    }
+
+    let columns = columns.join(", ");
+    sqlx::query(&format!(
+        r#"
+        WITH repaired AS (
+            DELETE FROM {table} WHERE NOT ST_IsValid(geom) RETURNING {columns}, geom
+        )
+        INSERT INTO {table} ({columns}, geom)
+        SELECT {columns}, (ST_Dump(ST_CollectionExtract(ST_MakeValid(geom), 3))).geom
+        FROM repaired
+        "#,
+        table = table,
+        columns = columns
+    ))
+    .execute(&mut *tx)
+    .await
+    .map_err(database_error)?;
+
+    Ok(invalid.len())
+}
+
+/// 一時テーブルの行の数を数える。
+///
+/// # Arguments
+///
+/// * `tx` - データベーストランザクション。
+/// * `sql` - 行の数を返すSELECT文。
+/// * `kind` - メッセージに含める、行の種類。
+///
+/// # Returns
+///
+/// 行の数。
+async fn count_rows(
+    tx: &mut Transaction<'_, Postgres>,
+    sql: &str,
+    kind: &str,
+) -> anyhow::Result<u64> {
+    let (count,): (i64,) = sqlx::query_as(sql).fetch_one(&mut *tx).await.map_err(|e| {
+        Failure::database(format!(
+            "登録する{}の数を数えるときにエラーが発生しました。{}",
+            kind, e
+        ))
+    })?;
+
+    Ok(count as u64)
+}
+
+/// キューに格納された都道府県フィーチャを、都道府県としてデータベースに登録する。
+///
+/// 都道府県はCOPY文で一時テーブルにまとめて登録し、不正なジオメトリを修復してから、都道府県テーブルに
+/// 登録する。行政区域データは島ごとに都道府県フィーチャーを記録しているため、都道府県テーブルには
+/// 同じ都道府県のフィーチャーを結合した1つのマルチポリゴンを登録する。変換して送信した都道府県の数は、
+/// 定期的に標準エラー出力に出力する。`options.jobs`が2以上の場合は、一時テーブルに複数の接続で並列に登録する。
+/// `options.upsert`がtrueの場合は、都道府県コードが一致する既存の都道府県を値が変わったときに限り更新する。
+///
+/// # Arguments
+///
+/// * `tx` - データベーストランザクション。
+/// * `pool` - 一時テーブルに並列に登録する接続を取得するデータベースコネクションプール。
+/// * `pref_fs` - 都道府県フィーチャーを格納したキュー。
+/// * `code` - 都道府県コード。
+/// * `srid` - 空間参照ID。
+/// * `options` - 登録するときのオプション。
+///
+/// # Returns
+///
+/// 登録した都道府県の数と、修復したジオメトリの数。
+async fn register_prefectures(
+    tx: &mut Transaction<'_, Postgres>,
+    pool: &PgPool,
+    pref_fs: SpillQueue<Feature>,
+    code: &str,
+    srid: i32,
+    options: &ImportOptions,
+) -> anyhow::Result<(usize, usize)> {
+    let staging = StagingTable::create(
+        &mut *tx,
+        pool,
+        "prefecture_rows",
+        "name text, geom geometry",
+        options.jobs,
+    )
+    .await?;
+    let total = pref_fs.len();
+    let rows = Progress::new("都道府県", Some(total))
+        .wrap(pref_fs.into_items()?)
+        .map(|f| prefecture_row(&f?, srid, options));
+    staging.copy(&mut *tx, &["name", "geom"], rows).await?;
+    let result = async {
+        let repaired =
+            repair_invalid_geometries(&mut *tx, staging.name(), &["name"], "name", "都道府県")
+                .await?;
+        let conflict = if options.upsert {
+            on_conflict_update("prefectures", &["code"], &["name", "geom"])
+        } else {
+            String::new()
+        };
+        let result = sqlx::query(&format!(
+            r#"
+            INSERT INTO prefectures (id, code, name, geom)
+            SELECT
+                gen_random_uuid(), $1, name, ST_Multi(ST_CollectionExtract(ST_Union(geom), 3))
+            FROM {}
+            GROUP BY name
+            {}
+            "#,
+            staging.name(),
+            conflict
+        ))
+        .bind(code)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            Failure::database(format!(
+                "データベースに都道府県を登録するときにエラーが発生しました。{}",
+                e
+            ))
+        })?;
+        let prefectures = if options.upsert {
+            let rows = count_rows(
+                &mut *tx,
+                &format!("SELECT COUNT(DISTINCT name) FROM {}", staging.name()),
+                "都道府県",
+            )
+            .await?;
+            let deleted = delete_missing(
+                &mut *tx,
+                "prefectures",
+                staging.name(),
+                &["name"],
+                "t.code = $1",
+                code,
+                "都道府県",
+            )
+            .await?;
+            let summary = UpsertSummary::new(rows, result.rows_affected(), deleted);
+            eprintln!("都道府県: {}", summary);
+            rows as usize
+        } else {
+            result.rows_affected() as usize
+        };
+
+        Ok((prefectures, repaired))
+    }
+    .await;
+
+    staging.finish(&mut *tx, result).await
+}
+
+/// 市区町村フィーチャーを、市区町村を登録する行に変換する。
+///
+/// # Arguments
+///
+/// * `f` - 市区町村フィーチャー。
+/// * `ordinal` - 行政区域データに市区町村フィーチャーが現れた順番(0から始まる)。
+/// * `srid` - 空間参照ID。
+/// * `options` - 登録するときのオプション。
+///
+/// # Returns
+///
+/// 市区町村コード、郡名または支庁名、市区町村名、順番及びジオメトリ(Webメルカトル投影法のEWKB)を格納した行。
+fn city_row(
+    f: &Feature,
+    ordinal: usize,
+    srid: i32,
+    options: &ImportOptions,
+) -> anyhow::Result<Vec<Option<String>>> {
+    let code = get_required_property(f, "code")?;
+    let area = get_feature_property(f, "area")?;
+    let name = get_required_property(f, "name")?;
+    let label = format!("市区町村({} {})", code, name);
+    let geom = to_web_mercator(get_geometry(f)?, srid, options, &label)?;
+
+    Ok(vec![
+        Some(code),
+        area,
+        Some(name),
+        Some(ordinal.to_string()),
+        Some(to_hex_ewkb(&geom, EPSG_WEB_MERCATOR)?),
+    ])
+}
+
+/// キューに格納された市区町村フィーチャを、市区町村としてデータベースに登録する。
+///
+/// 市区町村はCOPY文で一時テーブルにまとめて登録し、不正なジオメトリを修復してから、市区町村テーブルに
+/// 登録する。同じ市区町村コードのポリゴンには、行政区域データに現れた順に0から始まる番号(`part`)を付ける。
+/// 変換して送信した市区町村の数は、定期的に標準エラー出力に出力する。`options.jobs`が2以上の
+/// 場合は、一時テーブルに複数の接続で並列に登録する。`options.upsert`がtrueの場合は、市区町村コードと
+/// 番号が一致する既存の市区町村を値が変わったときに限り更新し、登録しなかった指定された都道府県の
+/// 既存の市区町村を削除する。
+///
+/// # Arguments
+///
+/// * `tx` - データベーストランザクション。
+/// * `pool` - 一時テーブルに並列に登録する接続を取得するデータベースコネクションプール。
+/// * `city_fs` - 市区町村フィーチャーを格納したキュー。
+/// * `code` - 都道府県コード。
+/// * `srid` - 空間参照ID。
+/// * `options` - 登録するときのオプション。
+///
+/// # Returns
+///
+/// 修復したジオメトリの数。
+async fn register_cities(
+    tx: &mut Transaction<'_, Postgres>,
+    pool: &PgPool,
+    city_fs: SpillQueue<Feature>,
+    code: &str,
+    srid: i32,
+    options: &ImportOptions,
+) -> anyhow::Result<usize> {
+    let staging = StagingTable::create(
+        &mut *tx,
+        pool,
+        "city_rows",
+        "code text, area text, name text, ordinal integer, part integer, geom geometry",
+        options.jobs,
+    )
+    .await?;
+    let total = city_fs.len();
+    let rows = Progress::new("市区町村", Some(total))
+        .wrap(city_fs.into_items()?)
+        .enumerate()
+        .map(|(ordinal, f)| city_row(&f?, ordinal, srid, options));
+    staging
+        .copy(&mut *tx, &["code", "area", "name", "ordinal", "geom"], rows)
+        .await?;
+    let result = async {
+        let repaired = repair_invalid_geometries(
+            &mut *tx,
+            staging.name(),
+            &["code", "area", "name", "ordinal"],
+            "code || ' ' || name",
+            "市区町村",
+        )
+        .await?;
+        // 修復して複数のポリゴンに分割したジオメトリにも、重複しない番号を付ける
+        sqlx::query(&format!(
+            r#"
+            UPDATE {table} r SET part = n.part
+            FROM (
+                SELECT
+                    ctid,
+                    (row_number() OVER (
+                        PARTITION BY code ORDER BY ordinal, ST_XMin(geom), ST_YMin(geom)
+                    ) - 1)::integer AS part
+                FROM {table}
+            ) n
+            WHERE r.ctid = n.ctid
+            "#,
+            table = staging.name()
+        ))
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            Failure::database(format!(
+                "市区町村のポリゴンに番号を付けるときにエラーが発生しました。{}",
+                e
+            ))
+        })?;
+        let conflict = if options.upsert {
+            on_conflict_update("cities", &["code", "part"], &["area", "name", "geom"])
+        } else {
+            String::new()
+        };
+        let result = sqlx::query(&format!(
+            r#"
+            INSERT INTO cities (id, code, area, name, part, geom)
+            SELECT gen_random_uuid(), code, area, name, part, geom FROM {}
+            {}
+            "#,
+            staging.name(),
+            conflict
+        ))
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            Failure::database(format!(
+                "データベースに市区町村を登録するときにエラーが発生しました。{}",
+                e
+            ))
+        })?;
+        if options.upsert {
+            let rows = count_rows(
+                &mut *tx,
+                &format!("SELECT COUNT(*) FROM {}", staging.name()),
+                "市区町村",
+            )
+            .await?;
+            let deleted = delete_missing(
+                &mut *tx,
+                "cities",
+                staging.name(),
+                &["code", "part"],
+                "t.code LIKE $1",
+                &format!("{}%", code),
+                "市区町村",
+            )
+            .await?;
+            let summary = UpsertSummary::new(rows, result.rows_affected(), deleted);
+            eprintln!("市区町村: {}", summary);
+        }
+
+        Ok(repaired)
+    }
+    .await;
+
+    staging.finish(&mut *tx, result).await
+}
+
+/// 国土数値情報の行政区域データのファイル名から、都道府県コードを取得する。
+///
+/// 行政区域データのファイル名は、`N03-22_21_220101.geojson`のように、データの年度、都道府県コード及び
+/// 基準日をアンダースコアで区切る。全国のデータ(`N03-22_220101.geojson`)は、都道府県コードを含まない。
+///
+/// # Arguments
+///
+/// * `file_name` - 行政区域データのファイル名。
+///
+/// # Returns
+///
+/// 都道府県コード。ファイル名から都道府県コードを取得できない場合はNone。
+pub fn prefecture_code_from_file_name(file_name: &str) -> Option<String> {
+    let re = Regex::new(r"^N03-\d+_(\d{2})_\d+").unwrap();
+    let code = re.captures(file_name)?.get(1)?.as_str();
+
+    code.parse::<PrefectureCode>()
+        .ok()
+        .map(|code| code.to_string())
+}
+
+/// 行政区域データの登録結果。
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RegisterSummary {
+    /// 登録した都道府県の数
+    pub prefectures: usize,
+    /// 登録した市区町村の数
+    pub cities: usize,
+    /// 不正なため修復したジオメトリの数
+    pub repaired: usize,
+}
+
+impl std::fmt::Display for RegisterSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "都道府県{}件、市区町村{}件",
+            self.prefectures, self.cities
+        )?;
+        if 0 < self.repaired {
+            write!(f, "(修復したジオメトリ{}件)", self.repaired)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// 読み込んで、都道府県と市区町村に分割した行政区域データ。
+pub struct BoundaryDataset {
+    /// 行政区域データの空間参照ID
+    srid: i32,
+    /// 都道府県フィーチャーを格納したキュー
+    prefectures: SpillQueue<Feature>,
+    /// 市区町村フィーチャーを格納したキュー
+    cities: SpillQueue<Feature>,
+}
+
+/// 行政区域データ(GeoJSON、GMLまたはShapeファイル)を読み込み、都道府県と市区町村に分割する。
+///
+/// ファイルを同期的に読み込むため、非同期のタスクから呼び出す場合は、別のスレッドで呼び出すこと。
+/// 分割したフィーチャーは、`options.memory_budget`を超えた分を一時ファイルに書き出す。
+///
+/// # Arguments
+///
+/// * `file` - 行政区域データ（GeoJSON、GMLまたはShapeファイル）ファイルのパス。
+/// * `format` - 行政区域データのファイルの形式。Noneの場合はファイルの拡張子から判断する。
+/// * `schema` - 行政区域データの属性の構成。Noneの場合はフィーチャーの属性から判断する。
+/// * `srid` - 行政区域データの空間参照ID。Noneの場合は行政区域データに記録された空間参照系から判断する。
+/// * `options` - 登録するときのオプション。
+///
+/// # Returns
+///
+/// 読み込んだ行政区域データ。
+pub fn read_administrative_boundaries(
+    file: &str,
+    format: Option<BoundaryFormat>,
+    schema: Option<BoundarySchema>,
+    srid: Option<i32>,
+    options: &ImportOptions,
+) -> anyhow::Result<BoundaryDataset> {
+    // GEOJSONファイルの内容を読み込み
+    let fc = read_features(file, format, schema)?;
+    // EPSGコードを取得
+    let srid = resolve_srid(&fc, srid)?;
+    // 県と市区町村にフィーチャーを分割
+    let (prefectures, cities) = divide_prefectures_and_cities(fc, options.memory_budget)?;
+
+    Ok(BoundaryDataset {
+        srid,
+        prefectures,
+        cities,
+    })
+}
+
+/// 行政区域データ(GeoJSON、GMLまたはShapeファイル)を読み込み、都道府県と市区町村をデータベースに登録する。
+///
+/// 指定された都道府県コードの都道府県または市区町村が登録されている場合は、ユーザーが
+/// 許可したときに限り、既存のレコードを削除して登録する。`options.upsert`がtrueの場合は、既存のレコードを
+/// 削除せずに、自然キーで照合して変更があった都道府県と市区町村だけを登録する。座標が範囲外のフィーチャーが
+/// 存在する場合は、登録を中止してエラーを返す。不正なジオメトリは修復して登録し、修復できない場合は
+/// 登録を中止してエラーを返す。登録する前のフィーチャーは、`options.memory_budget`を超えた分を
+/// 一時ファイルに書き出す。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+/// * `file` - 行政区域データ（GeoJSON、GMLまたはShapeファイル）ファイルのパス。
+/// * `format` - 行政区域データのファイルの形式。Noneの場合はファイルの拡張子から判断する。
+/// * `schema` - 行政区域データの属性の構成。Noneの場合はフィーチャーの属性から判断する。
+/// * `code` - 都道府県コード。
+/// * `srid` - 行政区域データの空間参照ID。Noneの場合は行政区域データに記録された空間参照系から判断する。
+/// * `options` - 登録するときのオプション。
+/// * `prompter` - 既存のレコードを削除して登録するか確認する処理。
+///
+/// # Returns
+///
+/// 登録した場合は登録結果。登録を中止した場合はNone。
+#[allow(clippy::too_many_arguments)]
+pub async fn import_administrative_boundaries(
+    pool: &PgPool,
+    file: &str,
+    format: Option<BoundaryFormat>,
+    schema: Option<BoundarySchema>,
+    code: &str,
+    srid: Option<i32>,
+    options: &ImportOptions,
+    prompter: &dyn Prompter,
+) -> anyhow::Result<Option<RegisterSummary>> {
+    code.parse::<PrefectureCode>().map_err(Failure::argument)?;
+    let dataset = read_administrative_boundaries(file, format, schema, srid, options)?;
+
+    register_boundary_dataset(pool, dataset, file, code, options, prompter).await
+}
+
+/// 読み込んだ行政区域データの都道府県と市区町村を、データベースに登録する。
+///
+/// 既存のレコードの扱いは、`import_administrative_boundaries`と同じである。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+/// * `dataset` - 読み込んだ行政区域データ。
+/// * `file` - 行政区域データのファイルのパス(登録の履歴に記録する)。
+/// * `code` - 都道府県コード。
+/// * `options` - 登録するときのオプション。
+/// * `prompter` - 既存のレコードを削除して登録するか確認する処理。
+///
+/// # Returns
+///
+/// 登録した場合は登録結果。登録を中止した場合はNone。
+pub async fn register_boundary_dataset(
+    pool: &PgPool,
+    dataset: BoundaryDataset,
+    file: &str,
+    code: &str,
+    options: &ImportOptions,
+    prompter: &dyn Prompter,
+) -> anyhow::Result<Option<RegisterSummary>> {
+    code.parse::<PrefectureCode>().map_err(Failure::argument)?;
+    let BoundaryDataset {
+        srid: epsg,
+        prefectures: pref_fs,
+        cities: city_fs,
+    } = dataset;
+    let mut summary = RegisterSummary {
+        prefectures: 0,
+        cities: city_fs.len(),
+        repaired: 0,
+    };
+
+    // トランザクションを開始
+    let mut tx = pool.begin().await.map_err(|e| {
+        Failure::database(format!(
+            "データベーストランザクションを開始できません。{}",
+            e
+        ))
+    })?;
+
+    // 自然キーで照合して登録しない場合は、指定された都道府県コードの都道府県と市区町村が登録されているか確認
+    if !options.upsert && exists_prefecture(&mut tx, code).await? {
+        // 指定された都道府県コードの都道府県と市区町村が登録されている場合は、削除して登録するか確認
+        if !confirm_replace(prompter, code) {
+            return Ok(None);
+        }
+        // 指定された都道府県コードの都道府県と市区町村を削除
+        delete_prefectures_and_cities(&mut tx, code).await?;
+    }
+
+    // 都道府県を登録
+    let (prefectures, repaired) =
+        register_prefectures(&mut tx, pool, pref_fs, code, epsg, options).await?;
+    summary.prefectures = prefectures;
+    summary.repaired += repaired;
+    // 市区町村を登録
+    summary.repaired += register_cities(&mut tx, pool, city_fs, code, epsg, options).await?;
+    // 登録の履歴を記録
+    let source = ImportSource {
+        dataset: "N03",
+        code: Some(code),
+        file,
+        year: options.year,
+    };
+    record_import(&mut tx, &source, "prefectures", summary.prefectures).await?;
+    record_import(&mut tx, &source, "cities", summary.cities).await?;
+
+    // トランザクションをコミット
+    tx.commit().await.map_err(|e| {
+        Failure::database(format!(
+            "データベーストランザクションをコミットできませんでした。{}",
+            e
+        ))
+    })?;
+
+    Ok(Some(summary))
+}
+
+/// 行政区域データの属性の名前。
+const BOUNDARY_PROPERTIES: [&str; 5] = ["N03_001", "N03_002", "N03_003", "N03_004", "N03_007"];
+
+/// 行政区域データのフィーチャーを、登録するときと同様に検証する。
+///
+/// # Arguments
+///
+/// * `report` - 見つかった問題を追加する検証結果。
+/// * `f` - 行政区域データのフィーチャー。
+/// * `index` - フィーチャーの位置(0から始まる)。
+/// * `code` - 都道府県コード。
+/// * `srid` - 空間参照ID。
+/// * `options` - 登録するときのオプション。
+fn validate_feature(
+    report: &mut DryRunReport,
+    f: &Feature,
+    index: usize,
+    code: &str,
+    srid: i32,
+    options: &ImportOptions,
+) {
+    let properties = f.properties.as_ref();
+    for key in BOUNDARY_PROPERTIES {
+        match properties.and_then(|properties| properties.get(key)) {
+            Some(Value::Null) | Some(Value::String(_)) => {}
+            Some(_) => {
+                report.issue(format!(
+                    "{}番目のフィーチャー: 属性{}が文字列ではありません。",
+                    index, key
+                ));
+                return;
+            }
+            None => {
+                report.issue(format!(
+                    "{}番目のフィーチャー: 属性{}がありません。",
+                    index, key
+                ));
+                return;
+            }
+        }
+    }
+    let label = if is_prefecture(f).unwrap_or(false) {
+        report.count("都道府県");
+        format!("{}番目のフィーチャー(都道府県)", index)
+    } else {
+        report.count("市区町村");
+        let city_code = get_feature_property(f, "N03_007")
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        if !city_code.starts_with(code) {
+            report.issue(format!(
+                "{}番目のフィーチャー: 市区町村コード({})が都道府県コード({})と一致しません。",
+                index, city_code, code
+            ));
+        }
+        format!("{}番目のフィーチャー(市区町村 {})", index, city_code)
+    };
+    let geom = match get_geometry(f) {
+        Ok(geom) => geom,
+        Err(e) => {
+            report.issue(format!("{}: {}", label, e));
+            return;
+        }
+    };
+    if let Some(problem) = geometry_problem(&geom) {
+        report.issue(format!("{}: {}", label, problem));
+        return;
+    }
+    if let Err(e) = to_web_mercator(geom, srid, options, &label) {
+        report.issue(e.to_string());
+    }
+}
+
+/// 行政区域データ(GeoJSON、GMLまたはShapeファイル)を読み込み、データベースに登録せずに検証する。
+///
+/// フィーチャーの属性、市区町村コードと都道府県コードの一致、空間参照系、ジオメトリ及び座標の範囲を
+/// 登録するときと同様に確認して、都道府県と市区町村の数とともに検証結果に記録する。
+///
+/// # Arguments
+///
+/// * `file` - 行政区域データ（GeoJSON、GMLまたはShapeファイル）ファイルのパス。
+/// * `format` - 行政区域データのファイルの形式。Noneの場合はファイルの拡張子から判断する。
+/// * `schema` - 行政区域データの属性の構成。Noneの場合はフィーチャーの属性から判断する。
+/// * `code` - 都道府県コード。
+/// * `srid` - 行政区域データの空間参照ID。Noneの場合は行政区域データに記録された空間参照系から判断する。
+/// * `options` - 登録するときのオプション。
+///
+/// # Returns
+///
+/// 検証結果。
+pub fn validate_administrative_boundaries(
+    file: &str,
+    format: Option<BoundaryFormat>,
+    schema: Option<BoundarySchema>,
+    code: &str,
+    srid: Option<i32>,
+    options: &ImportOptions,
+) -> anyhow::Result<DryRunReport> {
+    code.parse::<PrefectureCode>().map_err(Failure::argument)?;
+
+    let fc = read_features(file, format, schema)?;
+    let mut report = DryRunReport::new(file, code);
+    let srid = resolve_srid(&fc, srid)?;
+    report.srid = Some(srid);
+    if let Some(problem) = srid_problem(srid) {
+        report.issue(problem);
+    }
+    for (index, f) in fc.features.iter().enumerate() {
+        validate_feature(&mut report, f, index, code, srid, options);
+    }
+    if report.counts.iter().all(|(kind, _)| *kind != "都道府県") {
+        report.issue("都道府県のフィーチャーがありません。");
+    }
+
+    Ok(report)
+}
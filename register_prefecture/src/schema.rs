@@ -0,0 +1,315 @@
+//! 年度によって異なる行政区域データの属性の構成を、2022年以降の行政区域データの属性の構成に変換する。
+//!
+//! 2022年以降の行政区域データは、`N03_001`(都道府県名)、`N03_002`(北海道の振興局名)、`N03_003`(郡・政令指定都市名)、
+//! `N03_004`(市区町村名)及び`N03_007`(行政区域コード)の属性を持つ。それより前の行政区域データは、属性が省略されて
+//! いたり、郡名を市区町村名に含めていたりするため、登録する前に属性を読み替える。
+//!
+//! <https://nlftp.mlit.go.jp/ksj/gml/datalist/KsjTmplt-N03-v3_1.html>
+
+use std::str::FromStr;
+
+use geojson::{Feature, FeatureCollection, JsonObject};
+use regex::Regex;
+use serde_json::Value;
+use utils::errors::Failure;
+
+/// 2022年以降の行政区域データの属性の名前と、属性の日本語名。
+const PROPERTY_LABELS: [(&str, &str); 5] = [
+    ("N03_001", "都道府県名"),
+    ("N03_002", "支庁・振興局名"),
+    ("N03_003", "郡・政令都市名"),
+    ("N03_004", "市区町村名"),
+    ("N03_007", "行政区域コード"),
+];
+
+/// 行政区域データの属性の構成。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoundarySchema {
+    /// 2022年以降の行政区域データ
+    Current,
+    /// 2022年より前の行政区域データ
+    ///
+    /// 省略された属性はnullとみなす。郡・政令指定都市名がない場合は、市区町村名の先頭に含まれる郡名
+    /// (例えば`揖斐郡池田町`の`揖斐郡`)を郡・政令指定都市名とする。
+    Legacy,
+    /// 属性の名前に、属性の日本語名(`都道府県名`、`支庁・振興局名`、`郡・政令都市名`、`市区町村名`及び
+    /// `行政区域コード`)を使用する行政区域データ
+    Japanese,
+}
+
+impl FromStr for BoundarySchema {
+    type Err = String;
+
+    /// `current`、`legacy`または`japanese`から、行政区域データの属性の構成を構築する。
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "current" | "2022" => Ok(BoundarySchema::Current),
+            "legacy" => Ok(BoundarySchema::Legacy),
+            "japanese" | "ja" => Ok(BoundarySchema::Japanese),
+            _ => Err(format!(
+                "行政区域データの属性の構成({})は、current、legacyまたはjapaneseで指定してください。",
+                s
+            )),
+        }
+    }
+}
+
+impl BoundarySchema {
+    /// フィーチャーの属性から、行政区域データの属性の構成を判断する。
+    ///
+    /// 属性の日本語名を使用している場合は`Japanese`とする。2022年以降の行政区域データの属性をすべて持ち、
+    /// 市区町村名に郡名を含むフィーチャーがない場合は`Current`、それ以外で`N03_001`を持つ場合は`Legacy`とする。
+    ///
+    /// # Arguments
+    ///
+    /// * `fc` - 行政区域データから読み込んだフィーチャーコレクション。
+    ///
+    /// # Returns
+    ///
+    /// 行政区域データの属性の構成。判断できない場合はエラー。
+    pub fn detect(fc: &FeatureCollection) -> anyhow::Result<Self> {
+        let properties = match fc.features.iter().find_map(|f| f.properties.as_ref()) {
+            Some(properties) => properties,
+            // フィーチャーがない場合は、登録するときに都道府県のフィーチャーがないことを報告する
+            None => return Ok(BoundarySchema::Current),
+        };
+        if properties.contains_key(PROPERTY_LABELS[4].1) {
+            return Ok(BoundarySchema::Japanese);
+        }
+        if PROPERTY_LABELS
+            .iter()
+            .all(|(key, _)| properties.contains_key(*key))
+        {
+            let county = county_pattern();
+            let legacy = fc.features.iter().any(|f| {
+                let property = |key| f.properties.as_ref().and_then(|p| p.get(key));
+                is_blank(property("N03_003"))
+                    && property("N03_004")
+                        .and_then(Value::as_str)
+                        .is_some_and(|name| county.is_match(name))
+            });
+            return Ok(if legacy {
+                BoundarySchema::Legacy
+            } else {
+                BoundarySchema::Current
+            });
+        }
+        if properties.contains_key("N03_001") {
+            return Ok(BoundarySchema::Legacy);
+        }
+
+        Err(Failure::data(
+            "行政区域データの属性の構成を判断できません。`--schema`で属性の構成を指定してください。",
+        )
+        .into())
+    }
+
+    /// フィーチャーコレクションのフィーチャーの属性を、2022年以降の行政区域データの属性に読み替える。
+    ///
+    /// # Arguments
+    ///
+    /// * `fc` - 行政区域データから読み込んだフィーチャーコレクション。
+    ///
+    /// # Returns
+    ///
+    /// 属性を読み替えたフィーチャーコレクション。
+    pub fn normalize(&self, mut fc: FeatureCollection) -> FeatureCollection {
+        if *self == BoundarySchema::Current {
+            return fc;
+        }
+        let county = county_pattern();
+        for f in fc.features.iter_mut() {
+            f.properties = Some(self.normalize_properties(f, &county));
+        }
+
+        fc
+    }
+
+    /// フィーチャーの属性を、2022年以降の行政区域データの属性に読み替える。
+    ///
+    /// 行政区域コードが数値の場合は、5桁の文字列に変換する。
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - 行政区域データのフィーチャー。
+    /// * `county` - 市区町村名の先頭に含まれる郡名に一致する正規表現。
+    ///
+    /// # Returns
+    ///
+    /// 2022年以降の行政区域データの属性。
+    fn normalize_properties(&self, f: &Feature, county: &Regex) -> JsonObject {
+        let source = f.properties.as_ref();
+        let mut properties = JsonObject::new();
+        for (key, label) in PROPERTY_LABELS {
+            let name = match self {
+                BoundarySchema::Japanese => label,
+                _ => key,
+            };
+            let value = match source.and_then(|p| p.get(name)) {
+                Some(Value::Number(code)) if key == "N03_007" => match code.as_u64() {
+                    Some(code) => Value::String(format!("{:05}", code)),
+                    None => Value::Number(code.clone()),
+                },
+                Some(value) => value.clone(),
+                None => Value::Null,
+            };
+            properties.insert(key.to_string(), value);
+        }
+        // 郡名を市区町村名に含めている場合は、郡名と市区町村名に分ける
+        if is_blank(properties.get("N03_003")) {
+            let split = properties
+                .get("N03_004")
+                .and_then(Value::as_str)
+                .and_then(|name| county.captures(name))
+                .map(|captures| (captures[1].to_string(), captures[2].to_string()));
+            if let Some((county, city)) = split {
+                properties.insert("N03_003".to_string(), county.into());
+                properties.insert("N03_004".to_string(), city.into());
+            }
+        }
+
+        properties
+    }
+}
+
+/// 市区町村名の先頭に含まれる郡名に一致する正規表現を返す。
+///
+/// `郡上市`や`大和郡山市`のように、郡を含む市の名前には一致しない。
+fn county_pattern() -> Regex {
+    Regex::new(r"^(.+?郡)(.+[町村])$").unwrap()
+}
+
+/// 属性が省略されているか、値がnullまたは空文字列であるか確認する。
+fn is_blank(value: Option<&Value>) -> bool {
+    match value {
+        None | Some(Value::Null) => true,
+        Some(Value::String(value)) => value.trim().is_empty(),
+        Some(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// 属性を持つフィーチャーのフィーチャーコレクションを構築する。
+    fn collection(properties: &[Value]) -> FeatureCollection {
+        let features: Vec<Value> = properties
+            .iter()
+            .map(|p| json!({"type": "Feature", "geometry": null, "properties": p}))
+            .collect();
+
+        json!({"type": "FeatureCollection", "features": features})
+            .to_string()
+            .parse()
+            .unwrap()
+    }
+
+    /// 2022年以降の行政区域データの属性を構築する。
+    fn current(county: Option<&str>, city: &str, code: &str) -> Value {
+        json!({
+            "N03_001": "岐阜県",
+            "N03_002": null,
+            "N03_003": county,
+            "N03_004": city,
+            "N03_007": code,
+        })
+    }
+
+    #[test]
+    fn from_str_accepts_schema_names() {
+        assert_eq!("current".parse(), Ok(BoundarySchema::Current));
+        assert_eq!("2022".parse(), Ok(BoundarySchema::Current));
+        assert_eq!(" Legacy ".parse(), Ok(BoundarySchema::Legacy));
+        assert_eq!("ja".parse(), Ok(BoundarySchema::Japanese));
+        assert!("2021".parse::<BoundarySchema>().is_err());
+    }
+
+    #[test]
+    fn detect_current_schema() {
+        let fc = collection(&[
+            current(None, "岐阜市", "21201"),
+            current(Some("揖斐郡"), "池田町", "21404"),
+        ]);
+        assert_eq!(
+            BoundarySchema::detect(&fc).unwrap(),
+            BoundarySchema::Current
+        );
+        assert_eq!(
+            BoundarySchema::detect(&collection(&[])).unwrap(),
+            BoundarySchema::Current
+        );
+    }
+
+    #[test]
+    fn detect_legacy_schema() {
+        // 郡名を市区町村名に含めている
+        let fc = collection(&[
+            current(None, "岐阜市", "21201"),
+            current(None, "揖斐郡池田町", "21404"),
+        ]);
+        assert_eq!(BoundarySchema::detect(&fc).unwrap(), BoundarySchema::Legacy);
+        // 属性が省略されている
+        let fc = collection(&[json!({"N03_001": "岐阜県", "N03_004": "岐阜市"})]);
+        assert_eq!(BoundarySchema::detect(&fc).unwrap(), BoundarySchema::Legacy);
+    }
+
+    #[test]
+    fn detect_japanese_schema() {
+        let fc = collection(&[json!({"都道府県名": "岐阜県", "行政区域コード": "21201"})]);
+        assert_eq!(
+            BoundarySchema::detect(&fc).unwrap(),
+            BoundarySchema::Japanese
+        );
+    }
+
+    #[test]
+    fn detect_rejects_unknown_properties() {
+        let fc = collection(&[json!({"name": "岐阜市", "code": "21201"})]);
+        assert!(BoundarySchema::detect(&fc).is_err());
+    }
+
+    #[test]
+    fn normalize_splits_county_and_pads_code() {
+        let fc = collection(&[
+            json!({"N03_001": "岐阜県", "N03_004": "揖斐郡池田町", "N03_007": 21404}),
+            json!({"N03_001": "岐阜県", "N03_004": "郡上市", "N03_007": "21219"}),
+        ]);
+        let fc = BoundarySchema::Legacy.normalize(fc);
+        let properties = fc.features[0].properties.as_ref().unwrap();
+        assert_eq!(properties["N03_002"], Value::Null);
+        assert_eq!(properties["N03_003"], "揖斐郡");
+        assert_eq!(properties["N03_004"], "池田町");
+        assert_eq!(properties["N03_007"], "21404");
+        let properties = fc.features[1].properties.as_ref().unwrap();
+        assert_eq!(properties["N03_003"], Value::Null);
+        assert_eq!(properties["N03_004"], "郡上市");
+    }
+
+    #[test]
+    fn normalize_reads_japanese_property_names() {
+        let fc = collection(&[json!({
+            "都道府県名": "岐阜県",
+            "郡・政令都市名": null,
+            "市区町村名": "岐阜市",
+            "行政区域コード": 1202,
+        })]);
+        let fc = BoundarySchema::Japanese.normalize(fc);
+        let properties = fc.features[0].properties.as_ref().unwrap();
+        assert_eq!(properties["N03_001"], "岐阜県");
+        assert_eq!(properties["N03_004"], "岐阜市");
+        assert_eq!(properties["N03_007"], "01202");
+        assert!(!properties.contains_key("市区町村名"));
+    }
+
+    #[test]
+    fn county_pattern_does_not_match_city_names_containing_county() {
+        let county = county_pattern();
+        assert!(county.is_match("揖斐郡池田町"));
+        assert!(county.is_match("本巣郡北方町"));
+        assert!(!county.is_match("郡上市"));
+        assert!(!county.is_match("大和郡山市"));
+    }
+}
@@ -0,0 +1,80 @@
+//! 国土数値情報の行政区域データ(Shapeファイル)のリーダー。
+//!
+//! ポリゴンをマルチポリゴンのジオメトリに変換し、属性データファイル(*.dbf)の`N03_001`から`N03_007`
+//! までのフィールドを、GeoJSON形式の行政区域データと同じ名前の属性に対応させる。
+//!
+//! <https://nlftp.mlit.go.jp/ksj/gml/datalist/KsjTmplt-N03-v3_1.html>
+
+use anyhow::Context;
+use geojson::{Feature, FeatureCollection, Geometry, JsonObject, Value};
+use serde_json::json;
+use shapefile::ShapeType;
+use utils::crs::srid_from_prj;
+use utils::shape::{iter_features, open_shape_file, read_code_page, read_string_field};
+
+/// GeoJSON形式の行政区域データと同じ名前で読み込む、属性データファイルのフィールドの名前。
+const FIELD_NAMES: [&str; 5] = ["N03_001", "N03_002", "N03_003", "N03_004", "N03_007"];
+
+/// コードページファイル(*.cpg)が存在しない場合の、属性データファイルのエンコーディング。
+const DEFAULT_ENCODING: &str = "shift_jis";
+
+/// 空間参照系ファイル(*.prj)から判断できない場合の、行政区域データのShapeファイルの空間参照ID(JGD2011)。
+const SHAPEFILE_SRID: i32 = 6668;
+
+/// 行政区域データ(Shapeファイル)を読み込み、GeoJSON形式の行政区域データと同じ属性を持つ
+/// フィーチャーコレクションに変換する。
+///
+/// 属性データファイルのエンコーディングは、コードページファイル(*.cpg)が存在する場合はその内容を、
+/// 存在しない場合はShift_JISとする。空間参照系は、空間参照系ファイル(*.prj)から判断して、判断できない
+/// 場合はJGD2011として、フィーチャーコレクションの`crs`に記録する。
+///
+/// # Arguments
+///
+/// * `file` - 行政区域データのShapeファイル(*.shp)のパス。
+///
+/// # Returns
+///
+/// フィーチャーコレクション。
+pub fn read_administrative_boundaries(file: &str) -> anyhow::Result<FeatureCollection> {
+    let encoding = read_code_page(file).unwrap_or_else(|| DEFAULT_ENCODING.to_string());
+    let mut reader = open_shape_file(
+        file,
+        &encoding,
+        &[ShapeType::Polygon, ShapeType::PolygonZ, ShapeType::PolygonM],
+    )?;
+    let mut features = Vec::new();
+    for (index, feature) in iter_features(&mut reader).enumerate() {
+        let feature =
+            feature.with_context(|| format!("{}番目の行政区域を読み込めません。", index))?;
+        let mut properties = JsonObject::new();
+        for name in FIELD_NAMES {
+            let value = match read_string_field(&feature.record, name) {
+                Some(value) if !value.trim().is_empty() => json!(value.trim()),
+                _ => serde_json::Value::Null,
+            };
+            properties.insert(name.to_string(), value);
+        }
+        features.push(Feature {
+            bbox: None,
+            geometry: Some(Geometry::new(Value::from(&feature.geom))),
+            id: None,
+            properties: Some(properties),
+            foreign_members: None,
+        });
+    }
+    let srid = srid_from_prj(file).unwrap_or(SHAPEFILE_SRID);
+    let mut foreign_members = JsonObject::new();
+    foreign_members.insert(
+        "crs".to_string(),
+        json!({
+            "type": "name",
+            "properties": { "name": format!("urn:ogc:def:crs:EPSG::{}", srid) }
+        }),
+    );
+
+    Ok(FeatureCollection {
+        bbox: None,
+        features,
+        foreign_members: Some(foreign_members),
+    })
+}
@@ -0,0 +1,120 @@
+//! 年度によって属性の構成が異なる行政区域データをデータベースに登録して、登録した市区町村を確認するテスト。
+//!
+//! `test_support`クレートが起動したPostGISのコンテナ(環境変数TEST_DATABASE_URLを設定した場合はそのサーバー)に
+//! テストごとのデータベースを作成する。
+//! TEST_DATABASE_URLを設定しておらず、Dockerも利用できない場合はテストが失敗する。
+
+use register_prefecture::import_administrative_boundaries;
+use register_prefecture::schema::BoundarySchema;
+use test_support::{fixture_path, TestDatabase};
+use utils::import::ImportOptions;
+use utils::prompt::ScriptedPrompter;
+
+/// テストで登録する、2022年より前の属性の構成の行政区域データ(GeoJSONファイル)のパス。
+fn legacy_fixture() -> String {
+    fixture_path("N03-10_21_legacy.geojson")
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// テストで登録する、2022年より前の属性の構成の行政区域データ(シェープファイル)のパス。
+fn legacy_shapefile_fixture() -> String {
+    fixture_path("N03-10_21_legacy.shp")
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// 登録した市区町村の市区町村コード、郡・政令指定都市名及び名前を、市区町村コードの順に返す。
+async fn registered_cities(database: &TestDatabase) -> Vec<(String, Option<String>, String)> {
+    sqlx::query_as("SELECT code::text, area::text, name::text FROM cities ORDER BY code")
+        .fetch_all(&database.pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn imports_legacy_schema_detected_from_properties() {
+    let database = TestDatabase::new().await;
+
+    let summary = import_administrative_boundaries(
+        &database.pool,
+        &legacy_fixture(),
+        None,
+        None,
+        "21",
+        None,
+        &ImportOptions::default(),
+        &ScriptedPrompter::default(),
+    )
+    .await
+    .unwrap()
+    .expect("行政区域データを登録できません。");
+    assert_eq!(summary.prefectures, 1);
+    assert_eq!(summary.cities, 2);
+
+    // 市区町村名に含まれる郡名は郡・政令指定都市名に分け、数値の行政区域コードは文字列に変換する
+    assert_eq!(
+        registered_cities(&database).await,
+        [
+            ("21219".to_string(), None, "郡上市".to_string()),
+            (
+                "21404".to_string(),
+                Some("揖斐郡".to_string()),
+                "池田町".to_string()
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn imports_legacy_schema_from_shapefile() {
+    let database = TestDatabase::new().await;
+
+    // 文字エンコーディングは.cpgファイル(UTF-8)から判定する
+    let summary = import_administrative_boundaries(
+        &database.pool,
+        &legacy_shapefile_fixture(),
+        None,
+        None,
+        "21",
+        None,
+        &ImportOptions::default(),
+        &ScriptedPrompter::default(),
+    )
+    .await
+    .unwrap()
+    .expect("行政区域データを登録できません。");
+    assert_eq!(summary.prefectures, 1);
+    assert_eq!(summary.cities, 2);
+    assert_eq!(
+        registered_cities(&database).await,
+        [
+            ("21219".to_string(), None, "郡上市".to_string()),
+            (
+                "21404".to_string(),
+                Some("揖斐郡".to_string()),
+                "池田町".to_string()
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn current_schema_rejects_legacy_properties() {
+    let database = TestDatabase::new().await;
+
+    // 2022年以降の属性の構成を指定した場合は、省略された属性をエラーにする
+    let result = import_administrative_boundaries(
+        &database.pool,
+        &legacy_fixture(),
+        None,
+        Some(BoundarySchema::Current),
+        "21",
+        None,
+        &ImportOptions::default(),
+        &ScriptedPrompter::default(),
+    )
+    .await;
+    assert!(result.is_err());
+    assert_eq!(database.count("cities").await, 0);
+}
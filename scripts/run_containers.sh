@@ -11,15 +11,6 @@ if ! [ -x "$(command -v psql)" ]; then
     exit 1
 fi
 
-# sqlxコマンドの存在を確認
-if ! [ -x "$(command -v sqlx)" ]; then
-    echo >&2 "Error: sqlx is not installed."
-    echo >&2 "Use:"
-    echo >&2 "    cargo install --version=0.6.0 sqlx-cli --no-default-features --features native-tls,postgres"
-    echo >&2 "to install it."
-    exit 1
-fi
-
 # 起動しているコンテナを確認
 CONTAINERS=$(docker ps --filter 'name=sample_map_server' | sed -e '1d' | wc -l)
 if [ 0 -lt $((${CONTAINERS})) ]; then
@@ -38,8 +29,6 @@ until PGPASSWORD="${POSTGRES_PASSWORD}" psql -h "${POSTGRES_HOST}" -U "${POSTGRE
 done
 
 # マイグレーションを実行
-if [ -d "./migrations" ]; then
-    sqlx migrate run
-fi
+cargo run --package mapctl -- migrate
 
 echo >&2 "postgres has been migrated, ready to go!"
@@ -0,0 +1,384 @@
+//! 統合テストで使用する、マイグレーションを実行したPostGISのデータベースとフィクスチャーを提供する。
+//!
+//! 環境変数TEST_DATABASE_URLが設定されている場合は、そのURLが示すPostGISが稼働しているデータベースサーバーに
+//! テストごとのデータベースを作成する。設定されていない場合は、testcontainersでPostGISのコンテナをテストごとに
+//! 起動する(Dockerが必要)。
+//!
+//! 環境変数TEST_DATABASE_URLが設定されておらず、Dockerも利用できない場合は、データベースを使用するテストを
+//! スキップせずにパニックする。テストごとのデータベースは、テストが終わったときに削除する。
+
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sqlx::{Connection, Executor, PgConnection, PgPool};
+use testcontainers::core::{IntoContainerPort, WaitFor};
+use testcontainers::runners::AsyncRunner;
+use testcontainers::{ContainerAsync, GenericImage, ImageExt};
+
+/// 起動するPostGISのコンテナのイメージ。
+pub const POSTGIS_IMAGE: &str = "postgis/postgis";
+
+/// 起動するPostGISのコンテナのイメージのタグ。
+pub const POSTGIS_TAG: &str = "14-3.3";
+
+/// PostGISのコンテナのデータベースのユーザー名とパスワード。
+const POSTGIS_USER: &str = "postgres";
+
+/// PostGISのコンテナに接続するポート。
+const POSTGIS_PORT: u16 = 5432;
+
+/// PostgreSQLが接続を受け付ける準備ができたときに出力するメッセージ。
+const READY_MESSAGE: &str = "database system is ready to accept connections";
+
+/// テストで使用する、マイグレーションを実行したデータベース。
+///
+/// この構造体を破棄したときに、コンテナを起動した場合はコンテナを停止して、環境変数TEST_DATABASE_URLを
+/// 使用した場合はテスト用のデータベースを削除する。地図APIサーバーや登録処理にコネクションプールを渡す場合は、
+/// テストが終わるまでこの構造体を破棄しないこと。
+pub struct TestDatabase {
+    /// テスト用のデータベースのコネクションプール
+    pub pool: PgPool,
+    /// テスト用のデータベースのURL
+    pub url: String,
+    /// テスト用のデータベースを作成したデータベースサーバーのURL
+    server_url: String,
+    /// テスト用のデータベースの名前
+    name: String,
+    /// 起動したPostGISのコンテナ(環境変数TEST_DATABASE_URLを使用した場合はNone)
+    container: Option<ContainerAsync<GenericImage>>,
+}
+
+impl TestDatabase {
+    /// テスト用のデータベースを作成して、マイグレーションを実行する。
+    ///
+    /// 環境変数TEST_DATABASE_URLが設定されておらず、Dockerも利用できない場合はパニックする。
+    ///
+    /// # Returns
+    ///
+    /// テスト用のデータベース。
+    pub async fn new() -> Self {
+        dotenvy::dotenv().ok();
+
+        let (server_url, container) = match std::env::var("TEST_DATABASE_URL") {
+            Ok(url) => (url, None),
+            Err(_) if !docker_available() => panic!(
+                "環境変数TEST_DATABASE_URLが設定されておらず、Dockerも利用できないため、テスト用のデータベースを作成できません。"
+            ),
+            Err(_) => {
+                let container = start_postgis().await;
+                let host = container
+                    .get_host()
+                    .await
+                    .expect("PostGISのコンテナのホストを取得できません。");
+                let port = container
+                    .get_host_port_ipv4(POSTGIS_PORT)
+                    .await
+                    .expect("PostGISのコンテナのポートを取得できません。");
+                let url = format!(
+                    "postgres://{}:{}@{}:{}/{}",
+                    POSTGIS_USER, POSTGIS_USER, host, port, POSTGIS_USER
+                );
+                (url, Some(container))
+            }
+        };
+        let (url, name) = create_database(&server_url).await;
+        let pool = PgPool::connect(&url)
+            .await
+            .expect("テスト用のデータベースと接続できません。");
+        database::migrations::MIGRATOR
+            .run(&pool)
+            .await
+            .expect("マイグレーションを実行できません。");
+
+        Self {
+            pool,
+            url,
+            server_url,
+            name,
+            container,
+        }
+    }
+
+    /// テスト用のデータベースを作成して、マイグレーションを実行した後にフィクスチャーを登録する。
+    ///
+    /// # Returns
+    ///
+    /// フィクスチャーを登録したテスト用のデータベース。
+    pub async fn with_fixtures() -> Self {
+        let database = Self::new().await;
+        load_fixtures(&database.pool).await;
+
+        database
+    }
+
+    /// テーブルのレコードの数を返す。
+    ///
+    /// # Arguments
+    ///
+    /// * `table` - テーブル名。
+    ///
+    /// # Returns
+    ///
+    /// レコードの数。
+    pub async fn count(&self, table: &str) -> i64 {
+        let (count,): (i64,) = sqlx::query_as(&format!("SELECT COUNT(*) FROM {}", table))
+            .fetch_one(&self.pool)
+            .await
+            .unwrap_or_else(|e| panic!("{}のレコードの数を取得できません。{}", table, e));
+
+        count
+    }
+}
+
+impl Drop for TestDatabase {
+    /// 環境変数TEST_DATABASE_URLを使用した場合は、テスト用のデータベースを削除する。
+    ///
+    /// テストのランタイムの中で破棄されるため、データベースは別のスレッドで起動したランタイムで削除する。
+    /// コンテナを起動した場合は、コンテナを停止するとデータベースも破棄されるため削除しない。
+    fn drop(&mut self) {
+        if self.container.is_some() {
+            return;
+        }
+        let server_url = self.server_url.clone();
+        let name = self.name.clone();
+        let result = std::thread::spawn(move || {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .map_err(|e| e.to_string())?
+                .block_on(drop_database(&server_url, &name))
+                .map_err(|e| e.to_string())
+        })
+        .join();
+        // テストがパニックしている場合に重ねてパニックしないように、削除できなかったことを出力するにとどめる
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => eprintln!(
+                "テスト用のデータベース({})を削除できません。{}",
+                self.name, e
+            ),
+            Err(_) => eprintln!("テスト用のデータベース({})を削除できません。", self.name),
+        }
+    }
+}
+
+/// testcontainersがPostGISのコンテナを起動するために、Dockerを利用できるか確認する。
+///
+/// testcontainersと同様に、環境変数DOCKER_HOSTが設定されているか、既定の場所にDockerのソケットが存在する場合は
+/// 利用できると判断する。
+///
+/// # Returns
+///
+/// Dockerを利用できる場合はtrue。
+fn docker_available() -> bool {
+    if !cfg!(unix) || std::env::var_os("DOCKER_HOST").is_some() {
+        return true;
+    }
+    let mut sockets = vec![PathBuf::from("/var/run/docker.sock")];
+    if let Some(dir) = std::env::var_os("XDG_RUNTIME_DIR") {
+        sockets.push(PathBuf::from(dir).join(".docker/run/docker.sock"));
+    }
+    if let Some(home) = std::env::var_os("HOME") {
+        let home = PathBuf::from(home);
+        sockets.push(home.join(".docker/run/docker.sock"));
+        sockets.push(home.join(".docker/desktop/docker.sock"));
+    }
+
+    sockets.iter().any(|socket| socket.exists())
+}
+
+/// PostGISのコンテナを起動する。
+///
+/// # Returns
+///
+/// 起動したPostGISのコンテナ。
+async fn start_postgis() -> ContainerAsync<GenericImage> {
+    // PostgreSQLのイメージは、初期化のために起動したサーバーを停止した後に、改めてサーバーを起動する
+    GenericImage::new(POSTGIS_IMAGE, POSTGIS_TAG)
+        .with_exposed_port(POSTGIS_PORT.tcp())
+        .with_wait_for(WaitFor::message_on_stderr(READY_MESSAGE))
+        .with_wait_for(WaitFor::message_on_stdout(READY_MESSAGE))
+        .with_env_var("POSTGRES_USER", POSTGIS_USER)
+        .with_env_var("POSTGRES_PASSWORD", POSTGIS_USER)
+        .start()
+        .await
+        .expect("PostGISのコンテナを起動できません。Dockerが稼働しているか確認してください。")
+}
+
+/// データベースサーバーに、テストごとのデータベースを作成する。
+///
+/// # Arguments
+///
+/// * `server_url` - データベースサーバーのいずれかのデータベースのURL。
+///
+/// # Returns
+///
+/// 作成したデータベースのURLと名前。
+async fn create_database(server_url: &str) -> (String, String) {
+    let (base_url, _) = server_url
+        .rsplit_once('/')
+        .expect("データベースのURLの値が不正です。");
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let database_name = format!("test_{}_{}", std::process::id(), nanos);
+
+    let mut connection = PgConnection::connect(server_url)
+        .await
+        .expect("データベースサーバーと接続できません。");
+    connection
+        .execute(format!(r#"CREATE DATABASE "{}""#, database_name).as_str())
+        .await
+        .expect("テスト用のデータベースを作成できません。");
+
+    (format!("{}/{}", base_url, database_name), database_name)
+}
+
+/// データベースサーバーから、テストごとのデータベースを削除する。
+///
+/// テスト用のデータベースに接続しているコネクションは、強制的に切断する(PostgreSQL 13以降)。
+///
+/// # Arguments
+///
+/// * `server_url` - データベースサーバーのいずれかのデータベースのURL。
+/// * `database_name` - 削除するデータベースの名前。
+async fn drop_database(server_url: &str, database_name: &str) -> Result<(), sqlx::Error> {
+    let sql = format!(
+        r#"DROP DATABASE IF EXISTS "{}" WITH (FORCE)"#,
+        database_name
+    );
+    let mut connection = PgConnection::connect(server_url).await?;
+    connection.execute(sql.as_str()).await?;
+
+    connection.close().await
+}
+
+/// フィクスチャーのパスを返す。
+///
+/// # Arguments
+///
+/// * `name` - `test_support/fixtures`ディレクトリにあるフィクスチャーのファイル名。
+///
+/// # Returns
+///
+/// フィクスチャーのパス。
+pub fn fixture_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("fixtures")
+        .join(name)
+}
+
+/// リポジトリに同梱したサンプルデータのパスを返す。
+///
+/// # Arguments
+///
+/// * `name` - `resources`ディレクトリにあるファイルの名前。
+///
+/// # Returns
+///
+/// サンプルデータのパス。
+pub fn resource_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("../resources")
+        .join(name)
+}
+
+/// フィクスチャーのフィーチャーを読み込む。
+///
+/// # Arguments
+///
+/// * `name` - `test_support/fixtures`ディレクトリにあるフィクスチャーのファイル名。
+///
+/// # Returns
+///
+/// フィーチャーのプロパティとジオメトリ(GeoJSON)のタプルのベクタ。
+pub fn read_fixture(name: &str) -> Vec<(serde_json::Value, String)> {
+    let path = fixture_path(name);
+    let content = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("{}を読み込めません。{}", path.display(), e));
+    let fc: serde_json::Value = serde_json::from_str(&content).unwrap();
+
+    fc["features"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|feature| {
+            (
+                feature["properties"].clone(),
+                feature["geometry"].to_string(),
+            )
+        })
+        .collect()
+}
+
+/// 都道府県、市区町村及び郵便局のフィクスチャーをデータベースに登録する。
+///
+/// フィクスチャーの座標は、Webメルカトル座標で表現している。
+///
+/// # Arguments
+///
+/// * `pool` - データベースコネクションプール。
+pub async fn load_fixtures(pool: &PgPool) {
+    for (p, geom) in read_fixture("prefectures.geojson") {
+        sqlx::query(
+            r#"
+            INSERT INTO prefectures (id, code, name, geom)
+            VALUES ($1::uuid, $2, $3, ST_Multi(ST_SetSRID(ST_GeomFromGeoJSON($4), 3857)))
+            "#,
+        )
+        .bind(p["id"].as_str())
+        .bind(p["code"].as_str())
+        .bind(p["name"].as_str())
+        .bind(geom)
+        .execute(pool)
+        .await
+        .expect("都道府県のフィクスチャーを登録できません。");
+    }
+
+    for (p, geom) in read_fixture("cities.geojson") {
+        sqlx::query(
+            r#"
+            INSERT INTO cities (id, code, area, name, geom)
+            VALUES ($1::uuid, $2, $3, $4, ST_SetSRID(ST_GeomFromGeoJSON($5), 3857))
+            "#,
+        )
+        .bind(p["id"].as_str())
+        .bind(p["code"].as_str())
+        .bind(p["area"].as_str())
+        .bind(p["name"].as_str())
+        .bind(geom)
+        .execute(pool)
+        .await
+        .expect("市区町村のフィクスチャーを登録できません。");
+    }
+
+    for (p, geom) in read_fixture("post_offices.geojson") {
+        sqlx::query(
+            r#"
+            INSERT INTO post_offices (
+                id, city_code, category_code, subcategory_code, post_office_code,
+                name, address, city_name, town, block, geom
+            )
+            VALUES (
+                $1::uuid, $2, $3, $4, $5, $6, $7, $8, $9, $10,
+                ST_SetSRID(ST_GeomFromGeoJSON($11), 3857)
+            )
+            "#,
+        )
+        .bind(p["id"].as_str())
+        .bind(p["city_code"].as_str())
+        .bind(p["category_code"].as_str())
+        .bind(p["subcategory_code"].as_str())
+        .bind(p["post_office_code"].as_str())
+        .bind(p["name"].as_str())
+        .bind(p["address"].as_str())
+        .bind(p["city_name"].as_str())
+        .bind(p["town"].as_str())
+        .bind(p["block"].as_str())
+        .bind(geom)
+        .execute(pool)
+        .await
+        .expect("郵便局のフィクスチャーを登録できません。");
+    }
+}
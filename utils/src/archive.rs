@@ -0,0 +1,566 @@
+//! 国土数値情報などが配信するZIPアーカイブを、一時ディレクトリに展開する。
+//!
+//! ZIPアーカイブは`zip`クレートで読み込む。UTF-8のフラグがないファイル名は、国土数値情報のアーカイブと同じ
+//! Shift_JISで解釈する。展開したファイルが、アーカイブに記録された大きさ、または展開するファイルの合計の
+//! 大きさの上限を超える場合は、展開を中止する。
+
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use encoding_rs::SHIFT_JIS;
+use tempfile::TempDir;
+use zip::read::ZipFile;
+use zip::ZipArchive;
+
+use crate::errors::Failure;
+
+/// 展開するファイルの合計の大きさの既定の上限(バイト)。
+pub const MAX_EXTRACTED_SIZE: u64 = 4 * 1024 * 1024 * 1024;
+
+/// ZIPアーカイブを展開した一時ディレクトリ。
+///
+/// 破棄したときに、一時ディレクトリと展開したファイルを削除する。
+#[derive(Debug)]
+pub struct ExtractedArchive {
+    /// 展開したファイルを格納した一時ディレクトリ
+    dir: TempDir,
+    /// 展開したファイルのパス
+    files: Vec<PathBuf>,
+}
+
+impl ExtractedArchive {
+    /// ZIPアーカイブに記録されたすべてのファイルを、一時ディレクトリ(環境変数`TMPDIR`のディレクトリに作成)に
+    /// 展開する。
+    ///
+    /// 展開するファイルの合計の大きさの上限は、`MAX_EXTRACTED_SIZE`とする。
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - ZIPアーカイブのパス。
+    ///
+    /// # Returns
+    ///
+    /// ZIPアーカイブを展開した一時ディレクトリ。
+    pub fn extract(path: &str) -> anyhow::Result<Self> {
+        Self::extract_with_limit(path, MAX_EXTRACTED_SIZE)
+    }
+
+    /// ZIPアーカイブに記録されたすべてのファイルを、展開するファイルの合計の大きさを制限して、一時ディレクトリに
+    /// 展開する。
+    ///
+    /// Shapeファイルの属性データファイルなどを同じディレクトリに展開するため、アーカイブ内のディレクトリは
+    /// 再現せずに、ファイル名だけを使用する。異なるディレクトリに同じ名前のファイルがある場合は、どちらを
+    /// 登録するか判断できないため展開しない。
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - ZIPアーカイブのパス。
+    /// * `max_size` - 展開するファイルの合計の大きさの上限(バイト)。
+    ///
+    /// # Returns
+    ///
+    /// ZIPアーカイブを展開した一時ディレクトリ。
+    pub fn extract_with_limit(path: &str, max_size: u64) -> anyhow::Result<Self> {
+        let file = File::open(path)
+            .map_err(|e| Failure::input(format!("ファイル({})を読み込めません。{}", path, e)))?;
+        let mut zip = ZipArchive::new(BufReader::new(file)).map_err(|e| {
+            Failure::input(format!(
+                "ファイル({})はZIPアーカイブではありません。{}",
+                path, e
+            ))
+        })?;
+        let dir = tempfile::tempdir()
+            .map_err(|e| Failure::input(format!("一時ディレクトリを作成できません。{}", e)))?;
+        let mut files: Vec<PathBuf> = Vec::new();
+        let mut remaining = max_size;
+        for index in 0..zip.len() {
+            let mut entry = zip.by_index(index).map_err(|e| {
+                Failure::input(format!("ファイル({})を読み込めません。{}", path, e))
+            })?;
+            // ディレクトリは展開しない
+            if entry.is_dir() {
+                continue;
+            }
+            let name = entry_name(&entry);
+            // 一時ディレクトリの外を指すファイルを含むアーカイブは、改ざんされている可能性があるため展開しない
+            if !is_safe_name(&name) {
+                return Err(Failure::input(format!(
+                    "アーカイブに不正なパスのファイル({})があります。",
+                    name
+                ))
+                .into());
+            }
+            let file_name = match Path::new(&name).file_name() {
+                Some(file_name) => file_name.to_owned(),
+                None => continue,
+            };
+            if files
+                .iter()
+                .filter_map(|file| file.file_name())
+                .any(|extracted| extracted.eq_ignore_ascii_case(&file_name))
+            {
+                return Err(Failure::input(format!(
+                    "アーカイブに同じ名前のファイル({})が複数あります。",
+                    file_name.to_string_lossy()
+                ))
+                .into());
+            }
+            let dest = dir.path().join(&file_name);
+            let written = extract_entry(&mut entry, &dest, remaining, max_size)
+                .map_err(|e| e.context(format!("{}を展開できません。", name)))?;
+            remaining -= written;
+            files.push(dest);
+        }
+
+        Ok(Self { dir, files })
+    }
+
+    /// 一時ディレクトリのパスを返す。
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// 展開したファイルから、拡張子が一致するファイルを探す。
+    ///
+    /// 拡張子は大文字と小文字を区別せずに指定した順に探して、最初に一致するファイルが見つかった拡張子の
+    /// ファイルを返す。国土数値情報のメタデータ(`KS-META-`で始まるファイル)は探さない。
+    /// Shapeファイル(`.shp`)が見つかった場合は、同じ名前の`.shx`と`.dbf`のファイルも展開されているか確認する。
+    ///
+    /// # Arguments
+    ///
+    /// * `extensions` - ファイルの拡張子(`.`を含めない)。
+    ///
+    /// # Returns
+    ///
+    /// ファイルのパス。ファイルが見つからない場合、同じ拡張子のファイルが複数ある場合、またはShapeファイルの
+    /// `.shx`か`.dbf`のファイルがない場合はエラー。
+    pub fn find(&self, extensions: &[&str]) -> anyhow::Result<String> {
+        for extension in extensions {
+            let found: Vec<&PathBuf> = self
+                .files
+                .iter()
+                .filter(|file| !is_metadata(file))
+                .filter(|file| has_extension(file, extension))
+                .collect();
+            match found.as_slice() {
+                [] => continue,
+                [file] => {
+                    if extension.eq_ignore_ascii_case("shp") {
+                        self.check_sidecars(file)?;
+                    }
+                    return Ok(file.to_string_lossy().into_owned());
+                }
+                files => {
+                    return Err(Failure::input(format!(
+                        "アーカイブに拡張子が.{}のファイルが複数あります({})。",
+                        extension,
+                        files
+                            .iter()
+                            .filter_map(|file| file.file_name())
+                            .map(|name| name.to_string_lossy())
+                            .collect::<Vec<_>>()
+                            .join("、")
+                    ))
+                    .into())
+                }
+            }
+        }
+
+        Err(Failure::input(format!(
+            "アーカイブに拡張子が{}のファイルがありません。",
+            extensions
+                .iter()
+                .map(|extension| format!(".{}", extension))
+                .collect::<Vec<_>>()
+                .join("または")
+        ))
+        .into())
+    }
+
+    /// Shapeファイルと同じ名前のインデックスファイル(`.shx`)と属性データファイル(`.dbf`)が展開されているか
+    /// 確認する。
+    ///
+    /// # Arguments
+    ///
+    /// * `shp` - Shapeファイルのパス。
+    fn check_sidecars(&self, shp: &Path) -> anyhow::Result<()> {
+        let missing: Vec<&str> = ["shx", "dbf"]
+            .into_iter()
+            .filter(|extension| {
+                !self.files.iter().any(|file| {
+                    file.file_stem() == shp.file_stem() && has_extension(file, extension)
+                })
+            })
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        Err(Failure::input(format!(
+            "アーカイブに{}と同じ名前の{}のファイルがありません。",
+            shp.file_name().unwrap_or_default().to_string_lossy(),
+            missing
+                .iter()
+                .map(|extension| format!(".{}", extension))
+                .collect::<Vec<_>>()
+                .join("と")
+        ))
+        .into())
+    }
+}
+
+/// アーカイブ内のパスが、展開する一時ディレクトリの外を指していないか確認する。
+///
+/// 親ディレクトリ(`..`)を含むパスと、絶対パスを不正なパスとする。
+fn is_safe_name(name: &str) -> bool {
+    !name.starts_with(['/', '\\'])
+        && !name.contains(':')
+        && name.split(['/', '\\']).all(|component| component != "..")
+}
+
+/// ファイルの拡張子が一致するか、大文字と小文字を区別せずに確認する。
+fn has_extension(file: &Path, extension: &str) -> bool {
+    file.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.eq_ignore_ascii_case(extension))
+        .unwrap_or(false)
+}
+
+/// 国土数値情報のメタデータのファイルか確認する。
+fn is_metadata(file: &Path) -> bool {
+    file.file_name()
+        .map(|name| name.to_string_lossy().starts_with("KS-META-"))
+        .unwrap_or(false)
+}
+
+/// ファイルの拡張子が`.zip`か確認する。
+///
+/// # Arguments
+///
+/// * `path` - ファイルのパス。
+///
+/// # Returns
+///
+/// 拡張子が`.zip`の場合はtrue。
+pub fn is_zip_archive(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.eq_ignore_ascii_case("zip"))
+        .unwrap_or(false)
+}
+
+/// アーカイブに記録されたファイル名を返す。
+///
+/// `zip`クレートは、UTF-8のフラグ(汎用ビットフラグのビット11)がないファイル名をCP437で解釈するため、
+/// Shift_JISで解釈し直す。
+fn entry_name(entry: &ZipFile) -> String {
+    let raw = entry.name_raw();
+    // UTF-8のフラグがある名前とASCIIだけの名前は、`zip`クレートが解釈した名前のバイト列が元のバイト列と一致する
+    if entry.name().as_bytes() == raw {
+        return entry.name().to_string();
+    }
+    let (name, _, _) = SHIFT_JIS.decode(raw);
+
+    name.into_owned()
+}
+
+/// アーカイブに記録されたファイルを展開する。
+///
+/// # Arguments
+///
+/// * `entry` - 展開するファイル。
+/// * `dest` - 展開したファイルを書き込むパス。
+/// * `remaining` - 展開できる残りの大きさ(バイト)。
+/// * `max_size` - 展開するファイルの合計の大きさの上限(バイト、エラーメッセージに使用)。
+///
+/// # Returns
+///
+/// 展開したファイルの大きさ(バイト)。
+fn extract_entry(
+    entry: &mut ZipFile,
+    dest: &Path,
+    remaining: u64,
+    max_size: u64,
+) -> anyhow::Result<u64> {
+    let declared = entry.size();
+    if remaining < declared {
+        return Err(Failure::input(format!(
+            "展開するファイルの合計の大きさが上限({}バイト)を超えます。",
+            max_size
+        ))
+        .into());
+    }
+    let mut output = File::create(dest).map_err(|e| {
+        Failure::input(format!(
+            "ファイル({})を作成できません。{}",
+            dest.display(),
+            e
+        ))
+    })?;
+    // 記録された大きさより大きく展開されるファイルを検出するため、1バイト多く読み込む
+    let mut data = Read::take(&mut *entry, declared + 1);
+    let written =
+        std::io::copy(&mut data, &mut output).map_err(|e| Failure::input(e.to_string()))?;
+    if declared < written {
+        return Err(Failure::input(format!(
+            "展開したファイルの大きさが、アーカイブに記録された大きさ({}バイト)を超えます。",
+            declared
+        ))
+        .into());
+    }
+
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::write::DeflateEncoder;
+    use flate2::{Compression, Crc};
+    use tempfile::NamedTempFile;
+
+    use super::*;
+
+    /// テストで作成するZIPアーカイブに記録するファイル。
+    struct TestEntry<'a> {
+        /// ファイル名のバイト列
+        name: &'a [u8],
+        /// 汎用ビットフラグ
+        flags: u16,
+        /// 展開したデータ
+        data: &'a [u8],
+        /// Deflateで圧縮するか
+        deflate: bool,
+        /// 記録する展開したデータの大きさ(Noneの場合は実際の大きさ)
+        declared_size: Option<u32>,
+    }
+
+    impl<'a> TestEntry<'a> {
+        /// 無圧縮(stored)で記録するファイルを構築する。ASCII以外の文字を含む名前には、UTF-8のフラグを付ける。
+        fn stored(name: &'a str, data: &'a str) -> Self {
+            Self {
+                name: name.as_bytes(),
+                flags: if name.is_ascii() { 0 } else { 0x0800 },
+                data: data.as_bytes(),
+                deflate: false,
+                declared_size: None,
+            }
+        }
+    }
+
+    /// ファイルを記録したZIPアーカイブを作成する。
+    fn write_archive(entries: &[TestEntry]) -> NamedTempFile {
+        let mut body = Vec::new();
+        let mut directory = Vec::new();
+        for entry in entries {
+            let mut crc = Crc::new();
+            crc.update(entry.data);
+            let (method, compressed) = if entry.deflate {
+                let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(entry.data).unwrap();
+                (8u16, encoder.finish().unwrap())
+            } else {
+                (0u16, entry.data.to_vec())
+            };
+            let size = entry.declared_size.unwrap_or(entry.data.len() as u32);
+            let offset = body.len() as u32;
+            // ローカルファイルヘッダーと中央ディレクトリのファイルヘッダーに共通する、バージョンから拡張フィールドの
+            // 長さまで
+            let mut common = Vec::new();
+            common.extend_from_slice(&20u16.to_le_bytes());
+            common.extend_from_slice(&entry.flags.to_le_bytes());
+            common.extend_from_slice(&method.to_le_bytes());
+            common.extend_from_slice(&0u32.to_le_bytes());
+            common.extend_from_slice(&crc.sum().to_le_bytes());
+            common.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+            common.extend_from_slice(&size.to_le_bytes());
+            common.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+            common.extend_from_slice(&0u16.to_le_bytes());
+
+            body.extend_from_slice(&0x04034b50u32.to_le_bytes());
+            body.extend_from_slice(&common);
+            body.extend_from_slice(entry.name);
+            body.extend_from_slice(&compressed);
+
+            directory.extend_from_slice(&0x02014b50u32.to_le_bytes());
+            directory.extend_from_slice(&20u16.to_le_bytes());
+            directory.extend_from_slice(&common);
+            directory.extend_from_slice(&[0; 10]);
+            directory.extend_from_slice(&offset.to_le_bytes());
+            directory.extend_from_slice(entry.name);
+        }
+        let mut end = Vec::new();
+        end.extend_from_slice(&0x06054b50u32.to_le_bytes());
+        end.extend_from_slice(&[0; 4]);
+        end.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        end.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        end.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+        end.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        end.extend_from_slice(&0u16.to_le_bytes());
+
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&body).unwrap();
+        file.write_all(&directory).unwrap();
+        file.write_all(&end).unwrap();
+        file.flush().unwrap();
+        file
+    }
+
+    /// 無圧縮(stored)のファイルを記録したZIPアーカイブを作成する。
+    fn zip_archive(files: &[(&str, &str)]) -> NamedTempFile {
+        let entries: Vec<TestEntry> = files
+            .iter()
+            .map(|(name, data)| TestEntry::stored(name, data))
+            .collect();
+        write_archive(&entries)
+    }
+
+    fn extract(file: &NamedTempFile) -> anyhow::Result<ExtractedArchive> {
+        ExtractedArchive::extract(file.path().to_str().unwrap())
+    }
+
+    #[test]
+    fn extract_writes_files_without_directories() {
+        let zip = zip_archive(&[("N03-20_21/", ""), ("N03-20_21/N03-20_21.geojson", "{}")]);
+        let archive = extract(&zip).unwrap();
+        let file = archive.find(&["geojson"]).unwrap();
+        assert_eq!(Path::new(&file).parent(), Some(archive.path()));
+        assert_eq!(std::fs::read(&file).unwrap(), b"{}");
+    }
+
+    #[test]
+    fn extract_rejects_path_traversal() {
+        for name in [
+            "../x.geojson",
+            "data/../../x.geojson",
+            "..\\x.geojson",
+            "/tmp/x.geojson",
+        ] {
+            let zip = zip_archive(&[(name, "{}")]);
+            assert!(extract(&zip).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn extract_inflates_deflated_files() {
+        let data = "{\"type\":\"FeatureCollection\",\"features\":[]}".repeat(10);
+        let zip = write_archive(&[TestEntry {
+            deflate: true,
+            ..TestEntry::stored("N03-20_21.geojson", &data)
+        }]);
+        let archive = extract(&zip).unwrap();
+        let file = archive.find(&["geojson"]).unwrap();
+        assert_eq!(std::fs::read_to_string(file).unwrap(), data);
+    }
+
+    #[test]
+    fn extract_decodes_file_names() {
+        // UTF-8のフラグがない名前はShift_JIS、フラグがある名前はUTF-8で解釈する
+        let (name, _, _) = SHIFT_JIS.encode("行政区域.geojson");
+        let zip = write_archive(&[
+            TestEntry {
+                name: &name,
+                flags: 0,
+                ..TestEntry::stored("", "{}")
+            },
+            TestEntry::stored("郵便局.shp", "shp"),
+        ]);
+        let archive = extract(&zip).unwrap();
+        assert!(archive
+            .find(&["geojson"])
+            .unwrap()
+            .ends_with("行政区域.geojson"));
+        assert!(archive.path().join("郵便局.shp").exists());
+    }
+
+    #[test]
+    fn extract_rejects_duplicate_file_names() {
+        let zip = zip_archive(&[("a/N03-20_21.shp", "a"), ("b/N03-20_21.SHP", "b")]);
+        let e = extract(&zip).unwrap_err();
+        assert!(e.to_string().contains("N03-20_21.SHP"), "{}", e);
+    }
+
+    #[test]
+    fn extract_rejects_files_larger_than_declared() {
+        // 記録された大きさより大きく展開されるファイル(ZIP爆弾)は展開しない
+        let data = "0".repeat(100_000);
+        let zip = write_archive(&[TestEntry {
+            deflate: true,
+            declared_size: Some(10),
+            ..TestEntry::stored("bomb.geojson", &data)
+        }]);
+        let e = extract(&zip).unwrap_err();
+        assert!(format!("{:#}", e).contains("記録された大きさ"), "{:#}", e);
+    }
+
+    #[test]
+    fn extract_limits_total_size() {
+        let zip = zip_archive(&[("a.geojson", "012345"), ("b.csv", "012345")]);
+        let path = zip.path().to_str().unwrap();
+        assert!(ExtractedArchive::extract_with_limit(path, 12).is_ok());
+        let e = ExtractedArchive::extract_with_limit(path, 10).unwrap_err();
+        assert!(format!("{:#}", e).contains("上限"), "{:#}", e);
+    }
+
+    #[test]
+    fn extract_rejects_non_zip_files() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(b"not a zip archive").unwrap();
+        assert!(extract(&file).is_err());
+    }
+
+    #[test]
+    fn find_ignores_extension_case_and_metadata() {
+        let zip = zip_archive(&[
+            ("KS-META-N03-20_21.xml", "<meta/>"),
+            ("N03-20_21.XML", "<gml/>"),
+        ]);
+        let archive = extract(&zip).unwrap();
+        let file = archive.find(&["geojson", "xml"]).unwrap();
+        assert!(file.ends_with("N03-20_21.XML"));
+        assert!(archive.find(&["csv"]).is_err());
+    }
+
+    #[test]
+    fn find_rejects_ambiguous_files() {
+        let zip = zip_archive(&[("a.geojson", "{}"), ("b.GeoJSON", "{}")]);
+        let archive = extract(&zip).unwrap();
+        assert!(archive.find(&["geojson"]).is_err());
+    }
+
+    #[test]
+    fn find_requires_shapefile_sidecars() {
+        let zip = zip_archive(&[
+            ("P30-13_21.shp", "shp"),
+            ("P30-13_21.SHX", "shx"),
+            ("P30-13_21.dbf", "dbf"),
+        ]);
+        let archive = extract(&zip).unwrap();
+        assert!(archive.find(&["shp"]).unwrap().ends_with("P30-13_21.shp"));
+
+        let zip = zip_archive(&[("P30-13_21.shp", "shp"), ("P30-13_21.dbf", "dbf")]);
+        let archive = extract(&zip).unwrap();
+        let e = archive.find(&["shp"]).unwrap_err();
+        assert!(e.to_string().contains(".shx"), "{}", e);
+
+        // 属性データファイルが別の名前の場合も、見つからないとする
+        let zip = zip_archive(&[
+            ("P30-13_21.shp", "shp"),
+            ("P30-13_21.shx", "shx"),
+            ("other.dbf", "dbf"),
+        ]);
+        let archive = extract(&zip).unwrap();
+        assert!(archive.find(&["shp"]).is_err());
+    }
+
+    #[test]
+    fn is_zip_archive_checks_extension() {
+        assert!(is_zip_archive("N03-20_21_GML.zip"));
+        assert!(is_zip_archive("N03-20_21_GML.ZIP"));
+        assert!(!is_zip_archive("N03-20_21.geojson"));
+        assert!(!is_zip_archive("zip"));
+    }
+}
@@ -0,0 +1,390 @@
+use std::f64::consts::PI;
+use std::str::FromStr;
+
+use geo_types::{
+    Coord, Geometry, LineString, MultiLineString, MultiPolygon, Polygon, Rect, Triangle,
+};
+
+use crate::errors::Failure;
+
+/// ジオメトリを、マルチラインストリングに変換する。
+///
+/// Shapeファイルのポリラインは、パートの数によってラインストリングまたはマルチラインストリングに
+/// 変換されるため、登録する前にマルチラインストリングにそろえる。
+///
+/// # Arguments
+///
+/// * `geom` - ジオメトリ。
+///
+/// # Returns
+///
+/// マルチラインストリング。ラインストリングまたはマルチラインストリングでない場合はNone。
+pub fn to_multi_line_string(geom: Geometry<f64>) -> Option<Geometry<f64>> {
+    match geom {
+        Geometry::LineString(line) => Some(Geometry::MultiLineString(MultiLineString(vec![line]))),
+        Geometry::MultiLineString(lines) => Some(Geometry::MultiLineString(lines)),
+        _ => None,
+    }
+}
+
+/// ジオメトリを、マルチポリゴンに変換する。
+///
+/// # Arguments
+///
+/// * `geom` - ジオメトリ。
+///
+/// # Returns
+///
+/// マルチポリゴン。ポリゴンまたはマルチポリゴンでない場合はNone。
+pub fn to_multi_polygon(geom: Geometry<f64>) -> Option<Geometry<f64>> {
+    match geom {
+        Geometry::Polygon(polygon) => Some(Geometry::MultiPolygon(MultiPolygon(vec![polygon]))),
+        Geometry::MultiPolygon(polygons) => Some(Geometry::MultiPolygon(polygons)),
+        _ => None,
+    }
+}
+
+/// Webメルカトル投影法で表現できる緯度の最大値。
+pub const WEB_MERCATOR_MAX_LATITUDE: f64 = 85.051_128_78;
+
+/// Webメルカトル投影法で使用する地球の半径(メートル)。
+const EARTH_RADIUS: f64 = 6_378_137.0;
+
+/// 座標を経緯度で表現する、国土数値情報で使用される地理座標系のEPSGコード。
+///
+/// 旧日本測地系(4301)、WGS84(4326)、JGD2000(4612)及びJGD2011(6668)。
+pub const GEOGRAPHIC_SRIDS: [i32; 4] = [4301, 4326, 4612, 6668];
+
+/// 経緯度で表現した矩形範囲。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    /// 西端の経度
+    pub west: f64,
+    /// 南端の緯度
+    pub south: f64,
+    /// 東端の経度
+    pub east: f64,
+    /// 北端の緯度
+    pub north: f64,
+}
+
+impl BoundingBox {
+    /// 日本の領域に余白を加えた範囲。
+    pub const JAPAN: BoundingBox = BoundingBox {
+        west: 122.0,
+        south: 20.0,
+        east: 155.0,
+        north: 46.5,
+    };
+
+    /// 経緯度が範囲に含まれるか確認する。
+    ///
+    /// # Arguments
+    ///
+    /// * `lon` - 経度。
+    /// * `lat` - 緯度。
+    ///
+    /// # Returns
+    ///
+    /// 経緯度が範囲に含まれる場合はtrue。
+    pub fn contains(&self, lon: f64, lat: f64) -> bool {
+        self.west <= lon && lon <= self.east && self.south <= lat && lat <= self.north
+    }
+}
+
+impl std::fmt::Display for BoundingBox {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{},{},{},{}",
+            self.west, self.south, self.east, self.north
+        )
+    }
+}
+
+impl FromStr for BoundingBox {
+    type Err = String;
+
+    /// `west,south,east,north`形式の文字列から範囲を構築する。
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s
+            .split(',')
+            .map(|value| value.trim().parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()
+            .map_err(|e| format!("範囲({})が不正です。{}", s, e))?;
+        if values.len() != 4 {
+            return Err(format!(
+                "範囲({})はwest,south,east,northの形式で指定してください。",
+                s
+            ));
+        }
+        let bbox = Self {
+            west: values[0],
+            south: values[1],
+            east: values[2],
+            north: values[3],
+        };
+        if bbox.east <= bbox.west || bbox.north <= bbox.south {
+            return Err(format!(
+                "範囲({})の西端と東端、または南端と北端が逆です。",
+                s
+            ));
+        }
+
+        Ok(bbox)
+    }
+}
+
+/// 登録するジオメトリの座標が含まれなければならない範囲。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BboxGuard {
+    /// 座標の範囲を確認しない
+    Disabled,
+    /// 座標が範囲に含まれるか確認する
+    Enabled(BoundingBox),
+}
+
+impl Default for BboxGuard {
+    fn default() -> Self {
+        BboxGuard::Enabled(BoundingBox::JAPAN)
+    }
+}
+
+impl FromStr for BboxGuard {
+    type Err = String;
+
+    /// `japan`、`none`または`west,south,east,north`形式の文字列から、座標の範囲を構築する。
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "japan" => Ok(BboxGuard::Enabled(BoundingBox::JAPAN)),
+            "none" => Ok(BboxGuard::Disabled),
+            _ => Ok(BboxGuard::Enabled(s.parse()?)),
+        }
+    }
+}
+
+impl BboxGuard {
+    /// Webメルカトル座標で表現されたジオメトリのすべての座標が、範囲に含まれるか確認する。
+    ///
+    /// 緯度がWebメルカトル投影法で表現できる範囲を超える座標と、数値として不正な座標は、
+    /// 範囲を確認しない場合でも範囲外とする。
+    ///
+    /// # Arguments
+    ///
+    /// * `geom` - Webメルカトル座標で表現されたジオメトリ。
+    ///
+    /// # Returns
+    ///
+    /// 範囲外の座標が存在する場合は、最初に見つかった範囲外の座標の経緯度。存在しない場合はNone。
+    pub fn find_outlier(&self, geom: &Geometry<f64>) -> Option<(f64, f64)> {
+        let mut outlier = None;
+        for_each_coord(geom, &mut |coord| {
+            if outlier.is_some() {
+                return;
+            }
+            let (lon, lat) = web_mercator_to_lon_lat(coord);
+            let valid = lon.is_finite()
+                && lat.is_finite()
+                && lat.abs() <= WEB_MERCATOR_MAX_LATITUDE
+                && match self {
+                    BboxGuard::Disabled => true,
+                    BboxGuard::Enabled(bbox) => bbox.contains(lon, lat),
+                };
+            if !valid {
+                outlier = Some((lon, lat));
+            }
+        });
+
+        outlier
+    }
+
+    /// Webメルカトル座標で表現されたジオメトリのすべての座標が、範囲に含まれるか検証する。
+    ///
+    /// # Arguments
+    ///
+    /// * `geom` - Webメルカトル座標で表現されたジオメトリ。
+    /// * `label` - エラーメッセージに含める、ジオメトリを持つフィーチャーの名前。
+    ///
+    /// # Returns
+    ///
+    /// 範囲外の座標が存在する場合は、その座標を示すエラー(データが不正であることを示す`Failure`)。
+    pub fn validate(&self, geom: &Geometry<f64>, label: &str) -> anyhow::Result<()> {
+        match self.find_outlier(geom) {
+            Some((lon, lat)) => {
+                let range = match self {
+                    BboxGuard::Disabled => "Webメルカトル投影法で表現できる範囲".to_string(),
+                    BboxGuard::Enabled(bbox) => format!("許容する範囲({})", bbox),
+                };
+                Err(Failure::data(format!(
+                    "{}の座標(経度{}, 緯度{})が{}の外側にあります。",
+                    label, lon, lat, range
+                ))
+                .into())
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// 経度と緯度が入れ替わった座標を見つけたときの処理。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SwappedAxes {
+    /// 経度と緯度を入れ替えて登録する
+    Correct,
+    /// 登録を中止する
+    #[default]
+    Fail,
+}
+
+impl FromStr for SwappedAxes {
+    type Err = String;
+
+    /// `correct`または`fail`から、経度と緯度が入れ替わった座標を見つけたときの処理を構築する。
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "correct" => Ok(SwappedAxes::Correct),
+            "fail" => Ok(SwappedAxes::Fail),
+            _ => Err(format!(
+                "経度と緯度が入れ替わった座標の処理({})は、correctまたはfailで指定してください。",
+                s
+            )),
+        }
+    }
+}
+
+/// 経緯度で表現されたジオメトリの経度と緯度が入れ替わっているか確認する。
+///
+/// ジオメトリのすべての座標が、経度と緯度を入れ替えたときに限り範囲に含まれる場合に、
+/// 経度と緯度が入れ替わっていると判断する。
+///
+/// # Arguments
+///
+/// * `geom` - 経緯度で表現されたジオメトリ。
+/// * `bbox` - ジオメトリの座標が含まれるべき範囲。
+///
+/// # Returns
+///
+/// 経度と緯度が入れ替わっている場合はtrue。
+pub fn has_swapped_axes(geom: &Geometry<f64>, bbox: &BoundingBox) -> bool {
+    let mut coords = 0;
+    let mut swapped = 0;
+    for_each_coord(geom, &mut |coord| {
+        coords += 1;
+        if !bbox.contains(coord.x, coord.y) && bbox.contains(coord.y, coord.x) {
+            swapped += 1;
+        }
+    });
+
+    0 < coords && coords == swapped
+}
+
+/// ジオメトリのすべての座標のX座標とY座標を入れ替える。
+///
+/// # Arguments
+///
+/// * `geom` - ジオメトリ。
+pub fn swap_axes(geom: &mut Geometry<f64>) {
+    let swap = |coord: &mut Coord<f64>| std::mem::swap(&mut coord.x, &mut coord.y);
+    let swap_line_string = |line_string: &mut LineString<f64>| {
+        line_string.0.iter_mut().for_each(swap);
+    };
+    let swap_polygon = |polygon: &mut Polygon<f64>| {
+        polygon.exterior_mut(swap_line_string);
+        polygon.interiors_mut(|interiors| interiors.iter_mut().for_each(swap_line_string));
+    };
+    match geom {
+        Geometry::Point(point) => swap(&mut point.0),
+        Geometry::Line(line) => {
+            swap(&mut line.start);
+            swap(&mut line.end);
+        }
+        Geometry::LineString(line_string) => swap_line_string(line_string),
+        Geometry::Polygon(polygon) => swap_polygon(polygon),
+        Geometry::MultiPoint(points) => points.0.iter_mut().for_each(|point| swap(&mut point.0)),
+        Geometry::MultiLineString(line_strings) => {
+            line_strings.0.iter_mut().for_each(swap_line_string)
+        }
+        Geometry::MultiPolygon(polygons) => polygons.0.iter_mut().for_each(swap_polygon),
+        Geometry::GeometryCollection(geoms) => geoms.0.iter_mut().for_each(swap_axes),
+        Geometry::Rect(rect) => {
+            let (mut min, mut max) = (rect.min(), rect.max());
+            swap(&mut min);
+            swap(&mut max);
+            *rect = Rect::new(min, max);
+        }
+        Geometry::Triangle(triangle) => {
+            let (mut v1, mut v2, mut v3) = (triangle.v1(), triangle.v2(), triangle.v3());
+            swap(&mut v1);
+            swap(&mut v2);
+            swap(&mut v3);
+            *triangle = Triangle::new(v1, v2, v3);
+        }
+    }
+}
+
+/// Webメルカトル座標を経緯度に変換する。
+///
+/// # Arguments
+///
+/// * `coord` - Webメルカトル座標。
+///
+/// # Returns
+///
+/// 経度と緯度のタプル。
+pub fn web_mercator_to_lon_lat(coord: &Coord<f64>) -> (f64, f64) {
+    let lon = coord.x / EARTH_RADIUS * 180.0 / PI;
+    let lat = (coord.y / EARTH_RADIUS).sinh().atan() * 180.0 / PI;
+
+    (lon, lat)
+}
+
+/// ジオメトリのすべての座標に対して関数を呼び出す。
+///
+/// # Arguments
+///
+/// * `geom` - ジオメトリ。
+/// * `f` - 座標ごとに呼び出す関数。
+pub fn for_each_coord<F: FnMut(&Coord<f64>)>(geom: &Geometry<f64>, f: &mut F) {
+    match geom {
+        Geometry::Point(point) => f(&point.0),
+        Geometry::Line(line) => {
+            f(&line.start);
+            f(&line.end);
+        }
+        Geometry::LineString(line_string) => line_string.0.iter().for_each(f),
+        Geometry::Polygon(polygon) => for_each_polygon_coord(polygon, f),
+        Geometry::MultiPoint(points) => points.0.iter().for_each(|point| f(&point.0)),
+        Geometry::MultiLineString(line_strings) => {
+            for line_string in line_strings.0.iter() {
+                line_string.0.iter().for_each(&mut *f);
+            }
+        }
+        Geometry::MultiPolygon(polygons) => {
+            for polygon in polygons.0.iter() {
+                for_each_polygon_coord(polygon, f);
+            }
+        }
+        Geometry::GeometryCollection(geoms) => {
+            for geom in geoms.0.iter() {
+                for_each_coord(geom, f);
+            }
+        }
+        Geometry::Rect(rect) => {
+            f(&rect.min());
+            f(&rect.max());
+        }
+        Geometry::Triangle(triangle) => {
+            f(&triangle.v1());
+            f(&triangle.v2());
+            f(&triangle.v3());
+        }
+    }
+}
+
+/// ポリゴンの外周と内周のすべての座標に対して関数を呼び出す。
+fn for_each_polygon_coord<F: FnMut(&Coord<f64>)>(polygon: &Polygon<f64>, f: &mut F) {
+    polygon.exterior().0.iter().for_each(&mut *f);
+    for interior in polygon.interiors() {
+        interior.0.iter().for_each(&mut *f);
+    }
+}
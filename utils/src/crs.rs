@@ -0,0 +1,285 @@
+//! 登録するデータに記録されている空間参照系から、空間参照IDを判断する。
+
+use std::path::Path;
+
+use serde_json::{Map, Value};
+
+use crate::EPSG_WGS84;
+
+/// 日本測地系2011(JGD2011)の地理座標系の空間参照ID。
+const EPSG_JGD2011: i32 = 6668;
+
+/// 日本測地系2000(JGD2000)の地理座標系の空間参照ID。
+const EPSG_JGD2000: i32 = 4612;
+
+/// 日本測地系(旧日本測地系)の地理座標系の空間参照ID。
+const EPSG_TOKYO: i32 = 4301;
+
+/// JGD2011の平面直角座標系第I系の空間参照ID。第II系以降は、系の番号の順に連番で割り当てられている。
+const EPSG_JGD2011_ZONE_1: i32 = 6669;
+
+/// JGD2000の平面直角座標系第I系の空間参照ID。第II系以降は、系の番号の順に連番で割り当てられている。
+const EPSG_JGD2000_ZONE_1: i32 = 2443;
+
+/// 平面直角座標系の系の数。
+const JAPAN_PLANE_ZONES: i32 = 19;
+
+/// 空間参照系の名前から、空間参照IDを判断する。
+///
+/// `urn:ogc:def:crs:EPSG::6668`や`EPSG:6668`のようにEPSGコードを含む名前と、WGS84経緯度を示す
+/// `urn:ogc:def:crs:OGC:1.3:CRS84`に対応する。
+///
+/// # Arguments
+///
+/// * `name` - 空間参照系の名前。
+///
+/// # Returns
+///
+/// 空間参照ID。判断できない場合はNone。
+pub fn srid_from_crs_name(name: &str) -> Option<i32> {
+    let name = name.trim().to_uppercase();
+    if name.ends_with("CRS84") {
+        return Some(EPSG_WGS84);
+    }
+    let position = name.find("EPSG")?;
+    // `urn:ogc:def:crs:EPSG:6.6:4326`のようにバージョンを含む場合があるため、末尾の数字をコードとする
+    let code = name[position + 4..]
+        .rsplit(|c: char| !c.is_ascii_digit())
+        .find(|code| !code.is_empty())?;
+
+    code.parse::<i32>().ok().filter(|srid| 0 < *srid)
+}
+
+/// GeoJSONのフィーチャーコレクションの`crs`メンバー(RFC 7946より前の仕様)から、空間参照IDを判断する。
+///
+/// # Arguments
+///
+/// * `foreign_members` - フィーチャーコレクションの仕様外のメンバー。
+///
+/// # Returns
+///
+/// 空間参照ID。`crs`メンバーが存在しない、または判断できない場合はNone。
+pub fn srid_from_geojson_crs(foreign_members: Option<&Map<String, Value>>) -> Option<i32> {
+    let name = foreign_members?
+        .get("crs")?
+        .get("properties")?
+        .get("name")?
+        .as_str()?;
+
+    srid_from_crs_name(name)
+}
+
+/// WKT形式の空間参照系から、最上位の空間参照系の`AUTHORITY["EPSG","<code>"]`を探す。
+///
+/// 最上位の空間参照系の`AUTHORITY`は、WKTの末尾に記録されている。
+fn authority_code(wkt: &str) -> Option<i32> {
+    let position = wkt.rfind("AUTHORITY[")?;
+    // 最上位の空間参照系の`AUTHORITY`の後には、閉じ括弧だけが続く
+    let rest = &wkt[position..];
+    let end = rest.find(']')?;
+    if !rest[end + 1..].trim().chars().all(|c| c == ']') {
+        return None;
+    }
+    let mut values = rest["AUTHORITY[".len()..end]
+        .split(',')
+        .map(|value| value.trim().trim_matches('"'));
+    if !values.next()?.eq_ignore_ascii_case("EPSG") {
+        return None;
+    }
+
+    values.next()?.parse::<i32>().ok()
+}
+
+/// WKT形式の空間参照系の名前から、平面直角座標系の系の番号を探す。
+///
+/// `JGD_2011_Japan_Zone_7`や`JGD2011 / Japan Plane Rectangular CS VII`のような名前に対応する。
+fn plane_zone(name: &str) -> Option<i32> {
+    const NUMERALS: [&str; JAPAN_PLANE_ZONES as usize] = [
+        "I", "II", "III", "IV", "V", "VI", "VII", "VIII", "IX", "X", "XI", "XII", "XIII", "XIV",
+        "XV", "XVI", "XVII", "XVIII", "XIX",
+    ];
+    let name = name.to_uppercase();
+    if let Some(position) = name.find("ZONE_") {
+        let digits: String = name[position + 5..]
+            .chars()
+            .take_while(|c| c.is_ascii_digit())
+            .collect();
+        return digits.parse::<i32>().ok();
+    }
+    if !name.contains("PLANE RECTANGULAR") {
+        return None;
+    }
+    let numeral = name.rsplit(' ').next()?;
+
+    NUMERALS
+        .iter()
+        .position(|n| *n == numeral)
+        .map(|index| index as i32 + 1)
+}
+
+/// WKT形式の空間参照系から、空間参照IDを判断する。
+///
+/// EPSGの`AUTHORITY`が記録されている場合はそのコードを、記録されていない場合(ESRI形式のWKTなど)は、
+/// 空間参照系の名前から日本で使用される地理座標系(JGD2011、JGD2000、日本測地系及びWGS84)と、
+/// JGD2011またはJGD2000の平面直角座標系を判断する。
+///
+/// # Arguments
+///
+/// * `wkt` - WKT形式の空間参照系。
+///
+/// # Returns
+///
+/// 空間参照ID。判断できない場合はNone。
+pub fn srid_from_wkt(wkt: &str) -> Option<i32> {
+    if let Some(srid) = authority_code(wkt) {
+        return Some(srid);
+    }
+    let (kind, rest) = wkt.trim().split_once('[')?;
+    let name = rest.split('"').nth(1)?;
+    let normalized = name.to_uppercase().replace(['_', ' '], "");
+    if kind.trim().eq_ignore_ascii_case("PROJCS") {
+        let zone = plane_zone(name).filter(|zone| (1..=JAPAN_PLANE_ZONES).contains(zone))?;
+        if normalized.contains("JGD2011") {
+            return Some(EPSG_JGD2011_ZONE_1 + zone - 1);
+        }
+        if normalized.contains("JGD2000") {
+            return Some(EPSG_JGD2000_ZONE_1 + zone - 1);
+        }
+        return None;
+    }
+    if normalized.contains("JGD2011") {
+        Some(EPSG_JGD2011)
+    } else if normalized.contains("JGD2000") {
+        Some(EPSG_JGD2000)
+    } else if normalized.contains("TOKYO") {
+        Some(EPSG_TOKYO)
+    } else if normalized.contains("WGS1984") || normalized.contains("WGS84") {
+        Some(EPSG_WGS84)
+    } else {
+        None
+    }
+}
+
+/// Shapeファイルの空間参照系ファイル(*.prj)から、空間参照IDを判断する。
+///
+/// # Arguments
+///
+/// * `path` - シェイプファイル(*.shp)のパス。
+///
+/// # Returns
+///
+/// 空間参照ID。空間参照系ファイルが存在しない、または判断できない場合はNone。
+pub fn srid_from_prj(path: &str) -> Option<i32> {
+    let wkt = std::fs::read_to_string(Path::new(path).with_extension("prj")).ok()?;
+
+    srid_from_wkt(&wkt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coordinates::GEOGRAPHIC_SRIDS;
+
+    #[test]
+    fn srid_from_crs_name_accepts_epsg_and_crs84_names() {
+        assert_eq!(srid_from_crs_name("urn:ogc:def:crs:EPSG::6668"), Some(6668));
+        assert_eq!(
+            srid_from_crs_name("urn:ogc:def:crs:EPSG:6.6:4326"),
+            Some(4326)
+        );
+        assert_eq!(srid_from_crs_name(" epsg:4612 "), Some(4612));
+        assert_eq!(
+            srid_from_crs_name("urn:ogc:def:crs:OGC:1.3:CRS84"),
+            Some(EPSG_WGS84)
+        );
+        for name in [
+            "",
+            "EPSG:",
+            "EPSG:0",
+            "urn:ogc:def:crs:ESRI::102100",
+            "JGD2011",
+        ] {
+            assert_eq!(srid_from_crs_name(name), None, "{}", name);
+        }
+    }
+
+    #[test]
+    fn srid_from_geojson_crs_reads_named_crs() {
+        let members = serde_json::json!({
+            "crs": { "type": "name", "properties": { "name": "urn:ogc:def:crs:EPSG::6668" } }
+        });
+        assert_eq!(srid_from_geojson_crs(members.as_object()), Some(6668));
+        assert_eq!(srid_from_geojson_crs(None), None);
+        assert_eq!(
+            srid_from_geojson_crs(serde_json::json!({ "crs": {} }).as_object()),
+            None
+        );
+    }
+
+    #[test]
+    fn srid_from_wkt_prefers_top_level_authority() {
+        let wkt = r#"GEOGCS["JGD2011",DATUM["Japanese_Geodetic_Datum_2011",SPHEROID["GRS 1980",6378137,298.257222101,AUTHORITY["EPSG","7019"]],AUTHORITY["EPSG","1128"]],PRIMEM["Greenwich",0],UNIT["degree",0.0174532925199433],AUTHORITY["EPSG","6668"]]"#;
+        assert_eq!(srid_from_wkt(wkt), Some(6668));
+        // 最上位の空間参照系にAUTHORITYがない場合は、入れ子のAUTHORITYではなく名前から判断する
+        let wkt = r#"GEOGCS["GCS_JGD_2000",DATUM["D_JGD_2000",SPHEROID["GRS_1980",6378137.0,298.257222101,AUTHORITY["EPSG","7019"]]],PRIMEM["Greenwich",0.0],UNIT["Degree",0.0174532925199433]]"#;
+        assert_eq!(srid_from_wkt(wkt), Some(EPSG_JGD2000));
+    }
+
+    #[test]
+    fn srid_from_wkt_accepts_supported_geographic_crs() {
+        for (name, srid) in [
+            ("GCS_JGD_2011", EPSG_JGD2011),
+            ("GCS_JGD_2000", EPSG_JGD2000),
+            ("GCS_Tokyo", EPSG_TOKYO),
+            ("GCS_WGS_1984", EPSG_WGS84),
+        ] {
+            let wkt = format!(
+                r#"GEOGCS["{}",DATUM["D",SPHEROID["S",6378137.0,298.257]]]"#,
+                name
+            );
+            assert_eq!(srid_from_wkt(&wkt), Some(srid), "{}", name);
+        }
+    }
+
+    #[test]
+    fn srid_from_wkt_accepts_plane_rectangular_zones() {
+        for (name, srid) in [
+            ("JGD_2011_Japan_Zone_1", 6669),
+            ("JGD_2011_Japan_Zone_7", 6675),
+            ("JGD2011 / Japan Plane Rectangular CS XIX", 6687),
+            ("JGD_2000_Japan_Zone_1", 2443),
+            ("JGD2000 / Japan Plane Rectangular CS IX", 2451),
+        ] {
+            let wkt = format!(r#"PROJCS["{}",GEOGCS["GCS_JGD_2011"]]"#, name);
+            assert_eq!(srid_from_wkt(&wkt), Some(srid), "{}", name);
+        }
+    }
+
+    #[test]
+    fn srid_from_wkt_rejects_unsupported_crs() {
+        for wkt in [
+            // 平面直角座標系は第XIX系まで
+            r#"PROJCS["JGD_2011_Japan_Zone_20",GEOGCS["GCS_JGD_2011"]]"#,
+            r#"PROJCS["Tokyo_Japan_Zone_7",GEOGCS["GCS_Tokyo"]]"#,
+            r#"PROJCS["WGS_1984_UTM_Zone_54N",GEOGCS["GCS_WGS_1984"]]"#,
+            r#"GEOGCS["GCS_North_American_1983",DATUM["D_North_American_1983"]]"#,
+            "",
+        ] {
+            assert_eq!(srid_from_wkt(wkt), None, "{}", wkt);
+        }
+    }
+
+    #[test]
+    fn geographic_srids_have_latitude_longitude_axes() {
+        // 地理座標系は、座標が緯度経度の順で記録されている可能性があるため、軸の順序を確認する
+        let geographic = srid_from_wkt(r#"GEOGCS["GCS_JGD_2011"]"#).unwrap();
+        assert!(GEOGRAPHIC_SRIDS.contains(&geographic));
+        // 平面直角座標系は、軸の順序を確認しない
+        for zone in 1..=JAPAN_PLANE_ZONES {
+            let wkt = format!(r#"PROJCS["JGD_2011_Japan_Zone_{}"]"#, zone);
+            let srid = srid_from_wkt(&wkt).unwrap();
+            assert_eq!(srid, EPSG_JGD2011_ZONE_1 + zone - 1);
+            assert!(!GEOGRAPHIC_SRIDS.contains(&srid), "{}", srid);
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! データベースに登録せずにデータを検証する、ドライランの検証結果。
+
+use geo_types::{Geometry, LineString, Polygon};
+
+use crate::coordinates::GEOGRAPHIC_SRIDS;
+use crate::EPSG_WEB_MERCATOR;
+
+/// 検証結果に出力する問題の最大数。
+const MAX_PRINTED_ISSUES: usize = 20;
+
+/// ドライランの検証結果。
+#[derive(Debug, Clone)]
+pub struct DryRunReport {
+    /// 検証したファイル
+    pub file: String,
+    /// 都道府県コード
+    pub code: String,
+    /// データの空間参照ID(ジオメトリを持たないデータ、または取得できなかった場合はNone)
+    pub srid: Option<i32>,
+    /// 種類ごとのフィーチャー(または行)の数
+    pub counts: Vec<(&'static str, usize)>,
+    /// 見つかった問題
+    pub issues: Vec<String>,
+}
+
+impl DryRunReport {
+    /// 検証結果を構築する。
+    ///
+    /// # Arguments
+    ///
+    /// * `file` - 検証するファイル。
+    /// * `code` - 都道府県コード。
+    ///
+    /// # Returns
+    ///
+    /// 問題が見つかっていない検証結果。
+    pub fn new(file: &str, code: &str) -> Self {
+        Self {
+            file: file.to_string(),
+            code: code.to_string(),
+            srid: None,
+            counts: Vec::new(),
+            issues: Vec::new(),
+        }
+    }
+
+    /// 種類ごとのフィーチャーの数に1を加える。
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - フィーチャーの種類。
+    pub fn count(&mut self, kind: &'static str) {
+        match self.counts.iter_mut().find(|(name, _)| *name == kind) {
+            Some((_, count)) => *count += 1,
+            None => self.counts.push((kind, 1)),
+        }
+    }
+
+    /// 見つかった問題を追加する。
+    ///
+    /// # Arguments
+    ///
+    /// * `issue` - 問題の内容。
+    pub fn issue(&mut self, issue: impl Into<String>) {
+        self.issues.push(issue.into());
+    }
+
+    /// 問題が見つからなかったか確認する。
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// 検証結果を出力する。
+    pub fn print(&self) {
+        println!("ファイル: {}", self.file);
+        println!("都道府県コード: {}", self.code);
+        if let Some(srid) = self.srid {
+            println!("空間参照系: EPSG:{}", srid);
+        }
+        println!("件数:");
+        for (kind, count) in &self.counts {
+            println!("  {}: {}件", kind, count);
+        }
+        if self.is_valid() {
+            println!("問題: なし");
+            return;
+        }
+        println!("問題: {}件", self.issues.len());
+        for issue in self.issues.iter().take(MAX_PRINTED_ISSUES) {
+            println!("  {}", issue);
+        }
+        if MAX_PRINTED_ISSUES < self.issues.len() {
+            println!("  ...他{}件", self.issues.len() - MAX_PRINTED_ISSUES);
+        }
+    }
+}
+
+/// 空間参照IDが、登録できる空間参照系のものか確認する。
+///
+/// 地理座標系(EPSG:4301、4326、4612、6668)、Webメルカトル投影法、平面直角座標系(JGD2000とJGD2011)を
+/// 登録できる空間参照系とする。
+///
+/// # Arguments
+///
+/// * `srid` - 空間参照ID。
+///
+/// # Returns
+///
+/// 登録できない空間参照系の場合は問題の内容。登録できる場合はNone。
+pub fn srid_problem(srid: i32) -> Option<String> {
+    if GEOGRAPHIC_SRIDS.contains(&srid)
+        || srid == EPSG_WEB_MERCATOR
+        || (2443..=2461).contains(&srid)
+        || (6669..=6687).contains(&srid)
+    {
+        return None;
+    }
+
+    Some(format!(
+        "空間参照系(EPSG:{})は、登録できる空間参照系ではありません。",
+        srid
+    ))
+}
+
+/// ラインストリングが、ポリゴンのリングとして不正か確認する。
+fn ring_problem(ring: &LineString<f64>) -> Option<&'static str> {
+    if ring.0.len() < 4 {
+        return Some("4点未満のリングを含んでいます。");
+    }
+    if ring.0.first() != ring.0.last() {
+        return Some("閉じていないリングを含んでいます。");
+    }
+
+    None
+}
+
+/// ラインストリングが、ラインとして不正か確認する。
+fn line_problem(line: &LineString<f64>) -> Option<&'static str> {
+    if line.0.len() < 2 {
+        return Some("2点未満のラインを含んでいます。");
+    }
+
+    None
+}
+
+/// ポリゴンが不正か確認する。
+fn polygon_problem(polygon: &Polygon<f64>) -> Option<&'static str> {
+    std::iter::once(polygon.exterior())
+        .chain(polygon.interiors())
+        .find_map(ring_problem)
+}
+
+/// ジオメトリが不正か確認する。
+///
+/// 空のジオメトリ、有限でない座標、2点未満のライン、4点未満または閉じていないポリゴンのリングを不正とする。
+///
+/// # Arguments
+///
+/// * `geom` - 確認するジオメトリ。
+///
+/// # Returns
+///
+/// ジオメトリが不正な場合は問題の内容。不正でない場合はNone。
+pub fn geometry_problem(geom: &Geometry<f64>) -> Option<&'static str> {
+    let mut coords = 0;
+    let mut finite = true;
+    crate::coordinates::for_each_coord(geom, &mut |coord| {
+        coords += 1;
+        finite &= coord.x.is_finite() && coord.y.is_finite();
+    });
+    if coords == 0 {
+        return Some("ジオメトリが空です。");
+    }
+    if !finite {
+        return Some("有限でない座標を含んでいます。");
+    }
+    match geom {
+        Geometry::Polygon(polygon) => polygon_problem(polygon),
+        Geometry::MultiPolygon(multi_polygon) => multi_polygon.0.iter().find_map(polygon_problem),
+        Geometry::LineString(line) => line_problem(line),
+        Geometry::MultiLineString(lines) => lines.0.iter().find_map(line_problem),
+        _ => None,
+    }
+}
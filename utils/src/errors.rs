@@ -0,0 +1,81 @@
+//! 登録処理が失敗した原因の種類を区別できるエラー。
+
+/// 登録処理が失敗した原因の種類。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    /// 都道府県コードなどの引数が不正
+    Argument,
+    /// ファイルが存在しない、または読み込めない
+    Input,
+    /// ファイルに記録されているデータ(空間参照系、属性、ジオメトリなど)が不正
+    Data,
+    /// データベースに接続できない、またはデータベースの操作に失敗した
+    Database,
+}
+
+/// 原因の種類を持つ、登録処理のエラー。
+#[derive(Debug)]
+pub struct Failure {
+    /// 原因の種類
+    pub kind: FailureKind,
+    /// エラーメッセージ
+    pub message: String,
+}
+
+impl Failure {
+    /// 引数が不正であることを示すエラーを構築する。
+    pub fn argument(message: impl Into<String>) -> Self {
+        Self {
+            kind: FailureKind::Argument,
+            message: message.into(),
+        }
+    }
+
+    /// ファイルを読み込めないことを示すエラーを構築する。
+    pub fn input(message: impl Into<String>) -> Self {
+        Self {
+            kind: FailureKind::Input,
+            message: message.into(),
+        }
+    }
+
+    /// ファイルに記録されているデータが不正であることを示すエラーを構築する。
+    pub fn data(message: impl Into<String>) -> Self {
+        Self {
+            kind: FailureKind::Data,
+            message: message.into(),
+        }
+    }
+
+    /// データベースの操作に失敗したことを示すエラーを構築する。
+    pub fn database(message: impl Into<String>) -> Self {
+        Self {
+            kind: FailureKind::Database,
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for Failure {}
+
+/// エラーの原因の種類を返す。
+///
+/// # Arguments
+///
+/// * `e` - エラー。
+///
+/// # Returns
+///
+/// エラーまたはその原因に`Failure`が含まれる場合は、最初に見つかった`Failure`の原因の種類。
+/// 含まれない場合はNone。
+pub fn failure_kind(e: &anyhow::Error) -> Option<FailureKind> {
+    e.chain()
+        .find_map(|cause| cause.downcast_ref::<Failure>())
+        .map(|failure| failure.kind)
+}
@@ -0,0 +1,147 @@
+//! ShapeファイルまたはGeoJSONファイルに記録されたフィーチャーを、同じ形式で読み込む。
+//!
+//! 国土数値情報の点や線のデータは、Shapeファイルの属性データファイルと同じ名前の属性を持つGeoJSONファイルでも
+//! 配信されている。GeoJSONファイルのフィーチャーは、Shapeファイルから読み込んだフィーチャーと同じ
+//! [`ShapeFeature`]に変換する。
+
+use std::convert::TryInto;
+use std::path::Path;
+use std::str::FromStr;
+
+use geojson::{Feature, FeatureCollection};
+use serde_json::Value;
+use shapefile::dbase::{FieldValue, Record};
+
+use crate::crs::{srid_from_geojson_crs, srid_from_prj};
+use crate::errors::Failure;
+use crate::shape::ShapeFeature;
+use crate::EPSG_WGS84;
+
+/// ファイルの拡張子から、GeoJSONファイルか確認する。
+///
+/// # Arguments
+///
+/// * `file` - ファイルのパス。
+///
+/// # Returns
+///
+/// 拡張子が`.geojson`または`.json`の場合はtrue。それ以外の場合はShapeファイルと判断してfalse。
+pub fn is_geojson_file(file: &str) -> bool {
+    let extension = Path::new(file)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_lowercase());
+
+    matches!(extension.as_deref(), Some("geojson") | Some("json"))
+}
+
+/// GeoJSONファイルを読み込み、フィーチャーコレクションを取得する。
+///
+/// # Arguments
+///
+/// * `file` - GeoJSONファイルのパス。
+///
+/// # Returns
+///
+/// フィーチャーコレクション。
+pub fn read_geojson(file: &str) -> anyhow::Result<FeatureCollection> {
+    let content = std::fs::read_to_string(file)
+        .map_err(|e| Failure::input(format!("ファイル({})を読み込めません。{}", file, e)))?;
+    let fc = FeatureCollection::from_str(&content).map_err(|e| {
+        Failure::input(format!(
+            "ファイル({})をGeoJSONのフィーチャーコレクションとして読み込めません。{}",
+            file, e
+        ))
+    })?;
+
+    Ok(fc)
+}
+
+/// 登録するデータの空間参照IDを決定する。
+///
+/// 空間参照IDが指定された場合は指定された空間参照ID、指定されていない場合は、Shapeファイルは
+/// 空間参照系ファイル(*.prj)から判断した空間参照ID、GeoJSONファイルはフィーチャーコレクションに
+/// 記録されたEPSGコードとする。GeoJSONファイルに空間参照系が記録されていない場合は、RFC 7946に従って
+/// WGS84経緯度(EPSG:4326)とする。
+///
+/// # Arguments
+///
+/// * `file` - データのファイルのパス。
+/// * `fc` - GeoJSONファイルの場合は、読み込んだフィーチャーコレクション。Shapeファイルの場合はNone。
+/// * `srid` - 指定された空間参照ID。
+///
+/// # Returns
+///
+/// 空間参照ID。Shapeファイルの空間参照系を判断できない場合はエラー。
+pub fn resolve_srid(
+    file: &str,
+    fc: Option<&FeatureCollection>,
+    srid: Option<i32>,
+) -> anyhow::Result<i32> {
+    match (srid, fc) {
+        (Some(srid), _) if srid <= 0 => {
+            Err(Failure::argument(format!("SRID({})が不正です。", srid)).into())
+        }
+        (Some(srid), _) => Ok(srid),
+        (None, Some(fc)) => {
+            Ok(srid_from_geojson_crs(fc.foreign_members.as_ref()).unwrap_or(EPSG_WGS84))
+        }
+        (None, None) => srid_from_prj(file).ok_or_else(|| {
+            Failure::argument(format!(
+                "Shapeファイル({})の空間参照系ファイル(*.prj)から空間参照系を判断できません。SRIDを指定してください。",
+                file
+            ))
+            .into()
+        }),
+    }
+}
+
+/// GeoJSONのフィーチャーを、Shapeファイルから読み込んだ場合と同じ属性を持つフィーチャーに変換する。
+///
+/// 属性の値は、文字列はそのまま、数値と真偽値は文字列に変換して、nullは値がないフィールドとする。
+///
+/// # Arguments
+///
+/// * `feature` - GeoJSONファイルのフィーチャー。
+/// * `index` - フィーチャーの位置(0から始まる)。
+///
+/// # Returns
+///
+/// Shapeファイルから読み込んだ場合と同じ属性を持つフィーチャー。
+pub fn geojson_to_shape_feature(feature: Feature, index: usize) -> anyhow::Result<ShapeFeature> {
+    let geom: geo_types::Geometry<f64> = feature
+        .geometry
+        .ok_or_else(|| {
+            Failure::data(format!(
+                "{}番目のフィーチャーにジオメトリがありません。",
+                index
+            ))
+        })?
+        .value
+        .try_into()
+        .map_err(|e| {
+            Failure::data(format!(
+                "{}番目のフィーチャーのジオメトリを変換できません。{}",
+                index, e
+            ))
+        })?;
+    let mut record = Record::default();
+    for (key, value) in feature.properties.unwrap_or_default() {
+        let value = match value {
+            Value::Null => None,
+            Value::String(value) => Some(value),
+            Value::Number(value) => Some(value.to_string()),
+            Value::Bool(value) => Some(value.to_string()),
+            value => {
+                return Err(Failure::data(format!(
+                    "{}番目のフィーチャーの属性{}の値({})が文字列ではありません。",
+                    index, key, value
+                ))
+                .into())
+            }
+        };
+        record.insert(key, FieldValue::Character(value));
+    }
+
+    Ok(ShapeFeature { geom, record })
+}
@@ -0,0 +1,87 @@
+use geo_types::Geometry;
+
+use crate::coordinates::{
+    has_swapped_axes, swap_axes, BboxGuard, BoundingBox, SwappedAxes, GEOGRAPHIC_SRIDS,
+};
+use crate::errors::Failure;
+use crate::spill::DEFAULT_MEMORY_BUDGET_MB;
+
+/// 国土数値情報のデータを登録するときのオプション。
+#[derive(Debug, Clone, Copy)]
+pub struct ImportOptions {
+    /// 登録するジオメトリの座標が含まれなければならない範囲
+    pub bbox_guard: BboxGuard,
+    /// 経度と緯度が入れ替わった座標を見つけたときの処理
+    pub swapped_axes: SwappedAxes,
+    /// 登録するフィーチャーをメモリに保持する上限(バイト)。上限を超えたフィーチャーは一時ファイルに書き出す
+    pub memory_budget: usize,
+    /// フィーチャーを並列に登録する接続の数。1の場合は、データベーストランザクションの接続で順に登録する
+    pub jobs: usize,
+    /// 既存のレコードを削除せずに、自然キーで照合して変更があったレコードだけを登録するか
+    pub upsert: bool,
+    /// 登録の履歴に記録するデータセットの年度(西暦)。Noneの場合はファイル名から判断する
+    pub year: Option<u16>,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self {
+            bbox_guard: BboxGuard::default(),
+            swapped_axes: SwappedAxes::default(),
+            memory_budget: DEFAULT_MEMORY_BUDGET_MB * 1024 * 1024,
+            jobs: 1,
+            upsert: false,
+            year: None,
+        }
+    }
+}
+
+impl ImportOptions {
+    /// 経緯度で表現されたジオメトリの経度と緯度が入れ替わっていないか確認する。
+    ///
+    /// 経度と緯度が入れ替わっている場合、`swapped_axes`が`Correct`のときは警告を表示して
+    /// 経度と緯度を入れ替え、`Fail`のときはエラーを返す。空間参照系が地理座標系でない場合は確認しない。
+    ///
+    /// # Arguments
+    ///
+    /// * `geom` - 変換する前のジオメトリ。
+    /// * `srid` - ジオメトリの空間参照系ID。
+    /// * `label` - メッセージに含める、ジオメトリを持つフィーチャーの名前。
+    ///
+    /// # Returns
+    ///
+    /// 登録を中止する場合はエラー(データが不正であることを示す`Failure`)。
+    pub fn fix_axis_order(
+        &self,
+        geom: &mut Geometry<f64>,
+        srid: i32,
+        label: &str,
+    ) -> anyhow::Result<()> {
+        if !GEOGRAPHIC_SRIDS.contains(&srid) {
+            return Ok(());
+        }
+        let bbox = match self.bbox_guard {
+            BboxGuard::Enabled(bbox) => bbox,
+            BboxGuard::Disabled => BoundingBox::JAPAN,
+        };
+        if !has_swapped_axes(geom, &bbox) {
+            return Ok(());
+        }
+        match self.swapped_axes {
+            SwappedAxes::Correct => {
+                eprintln!(
+                    "警告: {}の経度と緯度が入れ替わっていたため、入れ替えて登録します。",
+                    label
+                );
+                swap_axes(geom);
+                Ok(())
+            }
+            SwappedAxes::Fail => Err(Failure::data(format!(
+                "{}の経度と緯度が入れ替わっています(EPSG:{}では経度、緯度の順に記録してください)。\
+                 入れ替えて登録する場合は`--swapped-axes correct`を指定してください。",
+                label, srid
+            ))
+            .into()),
+        }
+    }
+}
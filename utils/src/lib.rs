@@ -1,49 +1,46 @@
-use std::io::Write;
+use std::fmt::Write as _;
+
+use geozero::{CoordDimensions, ToWkb};
+
+pub mod archive;
+pub mod coordinates;
+pub mod crs;
+pub mod dry_run;
+pub mod errors;
+pub mod features;
+pub mod import;
+pub mod prefecture;
+pub mod progress;
+pub mod projection;
+pub mod prompt;
+pub mod shape;
+pub mod spill;
+pub mod tiles;
 
 /// Webメルカトル投影法のEPSGコード。
 pub const EPSG_WGS84: i32 = 4326;
 pub const EPSG_WEB_MERCATOR: i32 = 3857;
 
-/// 文字列が都道府県コードと見なせるか判断する。
-///
-/// # Arguments
-///
-/// * `code` - 都道府県コードと見なせるか、検証する文字列。
+/// ジオメトリを、PostGISがジオメトリの値として受け付ける16進数表記のEWKBに変換する。
 ///
-/// # Returns
-///
-/// 文字列が都道府県コードと見なせる場合はtrue、見なせない場合はfalse。
-pub fn is_prefecture_code(code: &str) -> bool {
-    ("01"..="47").contains(&code)
-}
-
-/// 既存のデータを削除して登録することをユーザーに確認する。
+/// COPY文でジオメトリの列に値を登録するときに使用する。
 ///
 /// # Arguments
 ///
-/// * `code` - 都道府県コード。
+/// * `geom` - ジオメトリ。
+/// * `srid` - ジオメトリの空間参照系ID。
 ///
 /// # Returns
 ///
-/// ユーザーが許可した場合はtrue。許可しなかった場合はfalse。
-pub fn confirm_register(code: &str) -> bool {
-    println!("指定された都道府県({})のレコードが登録されています。", code);
-    loop {
-        print!("既存のレコードを削除して登録しますか? [y/n]: ");
-        std::io::stdout().flush().unwrap();
-        let mut answer = String::new();
-        std::io::stdin().read_line(&mut answer).ok();
-        let answer: String = answer.trim().parse().ok().unwrap();
-        if answer.is_empty() {
-            continue;
-        }
-        let answer = answer.to_lowercase();
-        if answer.starts_with('y') {
-            return true;
-        } else if answer.starts_with('n') {
-            break;
-        }
+/// 16進数表記のEWKB。
+pub fn to_hex_ewkb(geom: &geo_types::Geometry<f64>, srid: i32) -> anyhow::Result<String> {
+    let ewkb = geom
+        .to_ewkb(CoordDimensions::xy(), Some(srid))
+        .map_err(|e| anyhow::anyhow!("ジオメトリをEWKBに変換できません。{}", e))?;
+    let mut hex = String::with_capacity(ewkb.len() * 2);
+    for b in ewkb {
+        write!(hex, "{:02X}", b).unwrap();
     }
 
-    false
+    Ok(hex)
 }
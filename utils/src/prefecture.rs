@@ -0,0 +1,253 @@
+//! 都道府県コード(JIS X 0401)。
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// 都道府県コードの文字列表現。`CODES[n - 1]`は、都道府県コード`n`を表す。
+const CODES: [&str; 47] = [
+    "01", "02", "03", "04", "05", "06", "07", "08", "09", "10", "11", "12", "13", "14", "15", "16",
+    "17", "18", "19", "20", "21", "22", "23", "24", "25", "26", "27", "28", "29", "30", "31", "32",
+    "33", "34", "35", "36", "37", "38", "39", "40", "41", "42", "43", "44", "45", "46", "47",
+];
+
+/// 都道府県の名前。`NAMES[n - 1]`は、都道府県コード`n`の都道府県の名前を表す。
+const NAMES: [&str; 47] = [
+    "北海道",
+    "青森県",
+    "岩手県",
+    "宮城県",
+    "秋田県",
+    "山形県",
+    "福島県",
+    "茨城県",
+    "栃木県",
+    "群馬県",
+    "埼玉県",
+    "千葉県",
+    "東京都",
+    "神奈川県",
+    "新潟県",
+    "富山県",
+    "石川県",
+    "福井県",
+    "山梨県",
+    "長野県",
+    "岐阜県",
+    "静岡県",
+    "愛知県",
+    "三重県",
+    "滋賀県",
+    "京都府",
+    "大阪府",
+    "兵庫県",
+    "奈良県",
+    "和歌山県",
+    "鳥取県",
+    "島根県",
+    "岡山県",
+    "広島県",
+    "山口県",
+    "徳島県",
+    "香川県",
+    "愛媛県",
+    "高知県",
+    "福岡県",
+    "佐賀県",
+    "長崎県",
+    "熊本県",
+    "大分県",
+    "宮崎県",
+    "鹿児島県",
+    "沖縄県",
+];
+
+/// 都道府県コード(JIS X 0401)。
+///
+/// `01`(北海道)から`47`(沖縄県)までの2桁の数字で表す。市区町村コード(全国地方公共団体コード)の
+/// 上2桁は、市区町村が属する都道府県の都道府県コードである。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PrefectureCode(u8);
+
+impl PrefectureCode {
+    /// 番号から都道府県コードを構築する。
+    ///
+    /// # Arguments
+    ///
+    /// * `number` - 都道府県コードの番号(1から47まで)。
+    ///
+    /// # Returns
+    ///
+    /// 都道府県コード。番号が範囲外の場合はNone。
+    pub fn new(number: u8) -> Option<Self> {
+        (1..=CODES.len() as u8)
+            .contains(&number)
+            .then_some(Self(number))
+    }
+
+    /// すべての都道府県コードを、番号の順に返す。
+    pub fn all() -> impl Iterator<Item = Self> {
+        (1..=CODES.len() as u8).map(Self)
+    }
+
+    /// 都道府県コードの番号を返す。
+    pub fn number(self) -> u8 {
+        self.0
+    }
+
+    /// 都道府県コードを2桁の文字列で返す。
+    pub fn as_str(self) -> &'static str {
+        CODES[self.0 as usize - 1]
+    }
+
+    /// 都道府県の名前を返す。
+    pub fn name(self) -> &'static str {
+        NAMES[self.0 as usize - 1]
+    }
+
+    /// 都道府県の名前から、都道府県コードを探す。
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - 都道府県の名前(「岐阜県」など)。
+    ///
+    /// # Returns
+    ///
+    /// 都道府県コード。名前が一致する都道府県がない場合はNone。
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.trim();
+        NAMES
+            .iter()
+            .position(|n| *n == name)
+            .map(|index| Self(index as u8 + 1))
+    }
+
+    /// 市区町村コードから、市区町村が属する都道府県の都道府県コードを返す。
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - 5桁の市区町村コード、または検査数字を含めた6桁の市区町村コード。
+    ///
+    /// # Returns
+    ///
+    /// 都道府県コード。市区町村コードが不正な場合はNone。
+    pub fn from_municipality_code(code: &str) -> Option<Self> {
+        if !matches!(code.len(), 5 | 6) || !code.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+
+        code[..2].parse().ok()
+    }
+
+    /// 市区町村コードが、この都道府県の市区町村を表すか確認する。
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - 5桁の市区町村コード、または検査数字を含めた6桁の市区町村コード。
+    ///
+    /// # Returns
+    ///
+    /// 市区町村コードが、この都道府県の市区町村を表す場合はtrue。
+    pub fn contains_municipality(self, code: &str) -> bool {
+        Self::from_municipality_code(code) == Some(self)
+    }
+}
+
+impl FromStr for PrefectureCode {
+    type Err = String;
+
+    /// `01`から`47`までの2桁の数字から、都道府県コードを構築する。
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CODES
+            .iter()
+            .position(|code| *code == s)
+            .map(|index| Self(index as u8 + 1))
+            .ok_or_else(|| {
+                format!(
+                    "都道府県コード({})が不正です。01から47までの2桁の数字で指定してください。",
+                    s
+                )
+            })
+    }
+}
+
+impl fmt::Display for PrefectureCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for PrefectureCode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for PrefectureCode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+
+        code.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_accepts_numbers_from_1_to_47() {
+        assert_eq!(PrefectureCode::new(0), None);
+        assert_eq!(PrefectureCode::new(1).map(|code| code.as_str()), Some("01"));
+        assert_eq!(
+            PrefectureCode::new(47).map(|code| code.name()),
+            Some("沖縄県")
+        );
+        assert_eq!(PrefectureCode::new(48), None);
+        assert_eq!(PrefectureCode::all().count(), 47);
+        assert!(PrefectureCode::all().map(|code| code.number()).eq(1..=47));
+    }
+
+    #[test]
+    fn from_str_accepts_only_two_digit_codes() {
+        assert_eq!("21".parse::<PrefectureCode>().unwrap().name(), "岐阜県");
+        for code in ["00", "48", "1", "021", "ab", ""] {
+            assert!(code.parse::<PrefectureCode>().is_err(), "{}", code);
+        }
+    }
+
+    #[test]
+    fn from_name_finds_prefecture() {
+        assert_eq!(
+            PrefectureCode::from_name(" 東京都 "),
+            PrefectureCode::new(13)
+        );
+        assert_eq!(PrefectureCode::from_name("東京"), None);
+    }
+
+    #[test]
+    fn municipality_codes_belong_to_prefecture() {
+        let gifu = PrefectureCode::new(21).unwrap();
+        assert_eq!(PrefectureCode::from_municipality_code("21201"), Some(gifu));
+        assert_eq!(PrefectureCode::from_municipality_code("212016"), Some(gifu));
+        assert_eq!(PrefectureCode::from_municipality_code("2120"), None);
+        assert_eq!(PrefectureCode::from_municipality_code("2120a"), None);
+        assert_eq!(PrefectureCode::from_municipality_code("48201"), None);
+        assert!(gifu.contains_municipality("21201"));
+        assert!(!gifu.contains_municipality("13101"));
+    }
+
+    #[test]
+    fn serializes_as_two_digit_string() {
+        let code = PrefectureCode::new(1).unwrap();
+        assert_eq!(code.to_string(), "01");
+        assert_eq!(serde_json::to_string(&code).unwrap(), r#""01""#);
+        assert_eq!(
+            serde_json::from_str::<PrefectureCode>(r#""01""#).unwrap(),
+            code
+        );
+        assert!(serde_json::from_str::<PrefectureCode>(r#""99""#).is_err());
+        assert!(serde_json::from_str::<PrefectureCode>("1").is_err());
+    }
+}
@@ -0,0 +1,150 @@
+//! 時間がかかる登録処理の進捗を、定期的に標準エラー出力に出力する。
+
+use std::time::{Duration, Instant};
+
+/// 進捗を出力する間隔。
+const REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 登録処理の進捗。
+#[derive(Debug)]
+pub struct Progress {
+    /// 出力するときに表示する、処理している対象の名前
+    label: String,
+    /// 処理する対象の総数(不明な場合はNone)
+    total: Option<usize>,
+    /// 処理した対象の数
+    processed: usize,
+    /// 処理を開始した時刻
+    started: Instant,
+    /// 最後に進捗を出力した時刻
+    reported: Instant,
+}
+
+impl Progress {
+    /// 進捗を構築する。
+    ///
+    /// # Arguments
+    ///
+    /// * `label` - 処理している対象の名前。
+    /// * `total` - 処理する対象の総数。不明な場合はNone。
+    pub fn new(label: &str, total: Option<usize>) -> Self {
+        let now = Instant::now();
+        Self {
+            label: label.to_string(),
+            total,
+            processed: 0,
+            started: now,
+            reported: now,
+        }
+    }
+
+    /// 処理した対象の数に1を加えて、前回の出力から`REPORT_INTERVAL`以上経過している場合は進捗を出力する。
+    pub fn tick(&mut self) {
+        self.processed += 1;
+        if REPORT_INTERVAL <= self.reported.elapsed() {
+            self.report();
+            self.reported = Instant::now();
+        }
+    }
+
+    /// 1秒あたりに処理した対象の数を返す。
+    fn rate(&self) -> f64 {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        if elapsed <= 0.0 {
+            return 0.0;
+        }
+
+        self.processed as f64 / elapsed
+    }
+
+    /// 処理した対象の数、1秒あたりに処理した対象の数、総数が分かる場合は残りの時間の見込みを出力する。
+    fn report(&self) {
+        let rate = self.rate();
+        match self.total {
+            Some(total) if 0.0 < rate => {
+                let remaining = total.saturating_sub(self.processed) as f64 / rate;
+                eprintln!(
+                    "{}: {}/{}件 ({:.1}%、{:.0}件/秒、残り約{})",
+                    self.label,
+                    self.processed,
+                    total,
+                    self.processed as f64 / total.max(1) as f64 * 100.0,
+                    rate,
+                    format_duration(Duration::from_secs_f64(remaining)),
+                );
+            }
+            _ => eprintln!("{}: {}件 ({:.0}件/秒)", self.label, self.processed, rate),
+        }
+    }
+
+    /// 処理した対象の数と処理に要した時間を出力する。
+    pub fn finish(&self) {
+        eprintln!(
+            "{}: {}件を処理しました ({}、{:.0}件/秒)",
+            self.label,
+            self.processed,
+            format_duration(self.started.elapsed()),
+            self.rate(),
+        );
+    }
+
+    /// イテレーターが要素を返すたびに処理した対象の数に1を加え、要素をすべて返したときに
+    /// 処理した対象の数と処理に要した時間を出力するイテレーターを返す。
+    ///
+    /// # Arguments
+    ///
+    /// * `iter` - 処理する対象を返すイテレーター。
+    pub fn wrap<I: Iterator>(self, iter: I) -> ProgressIter<I> {
+        ProgressIter {
+            iter,
+            progress: Some(self),
+        }
+    }
+}
+
+/// 要素を返すたびに進捗を更新するイテレーター。
+pub struct ProgressIter<I> {
+    /// 処理する対象を返すイテレーター
+    iter: I,
+    /// 進捗(要素をすべて返した後はNone)
+    progress: Option<Progress>,
+}
+
+impl<I: Iterator> Iterator for ProgressIter<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.iter.next();
+        match (&item, &mut self.progress) {
+            (Some(_), Some(progress)) => progress.tick(),
+            (None, Some(progress)) => {
+                progress.finish();
+                self.progress = None;
+            }
+            _ => {}
+        }
+
+        item
+    }
+}
+
+/// 時間を「1時間2分3秒」のような形式の文字列に変換する。
+///
+/// # Arguments
+///
+/// * `duration` - 時間。
+///
+/// # Returns
+///
+/// 時間を表現した文字列。
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (hours, minutes, seconds) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if 0 < hours {
+        format!("{}時間{}分{}秒", hours, minutes, seconds)
+    } else if 0 < minutes {
+        format!("{}分{}秒", minutes, seconds)
+    } else {
+        format!("{:.1}秒", duration.as_secs_f64())
+    }
+}
@@ -0,0 +1,89 @@
+//! 空間参照系を変換する変換器(`Proj`)を、変換元と変換先の空間参照IDの組ごとにキャッシュする。
+//!
+//! 変換器の構築は、PROJのデータベースを検索するため時間がかかる。変換器は、最初に使用したときに構築して、
+//! 以後は同じ空間参照IDの組の変換に再利用する。PROJのコンテキストは複数のスレッドで共有できないため、
+//! キャッシュはスレッドごとに保持する。
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use proj::{Proj, Transform};
+
+thread_local! {
+    /// スレッドごとの、変換元と変換先の空間参照IDの組をキーとした変換器のキャッシュ。
+    static TRANSFORMERS: RefCell<HashMap<(i32, i32), Rc<Proj>>> = RefCell::new(HashMap::new());
+}
+
+/// 変換元と変換先の空間参照IDの組に対応する変換器を使用して、処理を実行する。
+///
+/// 変換器がキャッシュされていない場合は、構築してキャッシュする。構築できなかった場合はキャッシュしない。
+///
+/// # Arguments
+///
+/// * `from` - 変換元の空間参照ID。
+/// * `to` - 変換先の空間参照ID。
+/// * `f` - 変換器を使用する処理。
+///
+/// # Returns
+///
+/// 処理の結果。変換器を構築できない場合はエラー。
+pub fn with_transformer<T, F>(from: i32, to: i32, f: F) -> anyhow::Result<T>
+where
+    F: FnOnce(&Proj) -> T,
+{
+    let cached = TRANSFORMERS.with(|transformers| transformers.borrow().get(&(from, to)).cloned());
+    let transformer = match cached {
+        Some(transformer) => transformer,
+        None => {
+            let transformer = Rc::new(
+                Proj::new_known_crs(&format!("EPSG:{}", from), &format!("EPSG:{}", to), None)
+                    .map_err(|e| anyhow::anyhow!("{}", e))?,
+            );
+            TRANSFORMERS.with(|transformers| {
+                transformers
+                    .borrow_mut()
+                    .insert((from, to), transformer.clone())
+            });
+            transformer
+        }
+    };
+
+    Ok(f(&transformer))
+}
+
+/// ジオメトリの座標の空間参照系を変換する。
+///
+/// # Arguments
+///
+/// * `geom` - 座標を変換するジオメトリ。
+/// * `from` - 変換元の空間参照ID。
+/// * `to` - 変換先の空間参照ID。
+///
+/// # Returns
+///
+/// 座標を変換できない場合はエラー。
+pub fn transform_geometry(
+    geom: &mut geo_types::Geometry<f64>,
+    from: i32,
+    to: i32,
+) -> anyhow::Result<()> {
+    with_transformer(from, to, |transformer| geom.transform(transformer))?
+        .map_err(|e| anyhow::anyhow!("{}", e))
+}
+
+/// 座標の空間参照系を変換する。
+///
+/// # Arguments
+///
+/// * `from` - 変換元の空間参照ID。
+/// * `to` - 変換先の空間参照ID。
+/// * `point` - 変換する座標(x, y)。
+///
+/// # Returns
+///
+/// 変換した座標(x, y)。座標を変換できない場合はエラー。
+pub fn convert_point(from: i32, to: i32, point: (f64, f64)) -> anyhow::Result<(f64, f64)> {
+    with_transformer(from, to, |transformer| transformer.convert(point))?
+        .map_err(|e| anyhow::anyhow!("{}", e))
+}
@@ -0,0 +1,258 @@
+//! ユーザーに確認する処理を抽象化する。
+//!
+//! 登録処理は、標準入力を直接読み込まずに[`Prompter`]を介して確認するため、端末で対話的に確認するほか、
+//! すべて許可する、既定の回答をする、またはあらかじめ用意した回答をすることができる。
+
+use std::collections::VecDeque;
+use std::io::{BufRead, IsTerminal, Write};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+/// ユーザーに確認する処理。
+pub trait Prompter: Send + Sync {
+    /// ユーザーに確認する。
+    ///
+    /// # Arguments
+    ///
+    /// * `question` - 質問。
+    /// * `default` - 既定の回答。回答が空の場合、回答を待つ時間が経過した場合、または確認できない場合に
+    ///   使用する。Noneの場合は、回答が空であれば再度確認して、確認できなければ許可しない。
+    ///
+    /// # Returns
+    ///
+    /// 許可した場合はtrue。許可しなかった場合はfalse。
+    fn confirm(&self, question: &str, default: Option<bool>) -> bool;
+
+    /// 確認する前後に、ユーザーに状況を知らせる。
+    ///
+    /// 既定では標準出力に出力する。
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - 知らせる内容。
+    fn notify(&self, message: &str) {
+        println!("{}", message);
+    }
+}
+
+/// 既存のレコードを削除して登録することをユーザーに確認する。
+///
+/// 既定の回答は、登録を中止する(n)とする。
+///
+/// # Arguments
+///
+/// * `prompter` - ユーザーに確認する処理。
+/// * `code` - 都道府県コード。
+///
+/// # Returns
+///
+/// 既存のレコードを削除して登録する場合はtrue。登録を中止する場合はfalse。
+pub fn confirm_replace(prompter: &dyn Prompter, code: &str) -> bool {
+    prompter.notify(&format!(
+        "指定された都道府県({})のレコードが登録されています。",
+        code
+    ));
+    let replace = prompter.confirm("既存のレコードを削除して登録しますか?", Some(false));
+    if !replace {
+        prompter.notify(
+            "登録を中止します。既存のレコードを削除して登録する場合は、--yesを指定してください。",
+        );
+    }
+
+    replace
+}
+
+/// 回答の選択肢を表示する文字列を返す。
+fn choices(default: Option<bool>) -> &'static str {
+    match default {
+        Some(true) => "[Y/n]",
+        Some(false) => "[y/N]",
+        None => "[y/n]",
+    }
+}
+
+/// 回答(y/n)を解釈する。
+///
+/// # Arguments
+///
+/// * `answer` - 回答。
+///
+/// # Returns
+///
+/// `y`で始まる場合はSome(true)、`n`で始まる場合はSome(false)。それ以外はNone。
+fn parse_answer(answer: &str) -> Option<bool> {
+    let answer = answer.trim().to_lowercase();
+    if answer.starts_with('y') {
+        Some(true)
+    } else if answer.starts_with('n') {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// 端末で対話的にユーザーに確認する。
+///
+/// 標準入力が端末でない場合は、確認できないため既定の回答を使用する。回答を待つ時間を指定した場合は、
+/// 時間内に回答がなければ既定の回答を使用する。
+#[derive(Debug, Default)]
+pub struct TerminalPrompter {
+    /// 回答を待つ時間
+    timeout: Option<Duration>,
+    /// 標準入力から読み込んだ行を受け取るレシーバー
+    lines: OnceLock<Mutex<Receiver<String>>>,
+}
+
+impl TerminalPrompter {
+    /// 回答を待つ時間を指定せずに構築する。
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 回答を待つ時間を指定する。
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - 回答を待つ時間。Noneの場合は回答があるまで待つ。
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 標準入力から1行読み込む。
+    ///
+    /// 回答を待つ時間が経過しても読み込みを中断できないため、標準入力は別のスレッドで読み込む。
+    ///
+    /// # Returns
+    ///
+    /// 読み込んだ行。回答を待つ時間が経過した場合、または標準入力が閉じられた場合はNone。
+    fn read_line(&self) -> Option<String> {
+        let lines = self
+            .lines
+            .get_or_init(|| {
+                let (sender, receiver) = mpsc::channel();
+                std::thread::spawn(move || {
+                    for line in std::io::stdin().lock().lines().map_while(Result::ok) {
+                        if sender.send(line).is_err() {
+                            break;
+                        }
+                    }
+                });
+                Mutex::new(receiver)
+            })
+            .lock()
+            .unwrap();
+        // 確認する前に入力された行は、回答として扱わない
+        while lines.try_recv().is_ok() {}
+        match self.timeout {
+            Some(timeout) => match lines.recv_timeout(timeout) {
+                Ok(line) => Some(line),
+                Err(RecvTimeoutError::Timeout) => {
+                    println!();
+                    println!("{}秒以内に回答がありませんでした。", timeout.as_secs());
+                    None
+                }
+                Err(RecvTimeoutError::Disconnected) => None,
+            },
+            None => lines.recv().ok(),
+        }
+    }
+}
+
+impl Prompter for TerminalPrompter {
+    fn confirm(&self, question: &str, default: Option<bool>) -> bool {
+        if !std::io::stdin().is_terminal() {
+            println!("{} {}", question, choices(default));
+            println!("標準入力が端末でないため確認できません。");
+            return default.unwrap_or(false);
+        }
+        loop {
+            print!("{} {}: ", question, choices(default));
+            std::io::stdout().flush().unwrap();
+            let answer = match self.read_line() {
+                Some(answer) => answer,
+                None => return default.unwrap_or(false),
+            };
+            if answer.trim().is_empty() {
+                if let Some(default) = default {
+                    return default;
+                }
+                continue;
+            }
+            if let Some(answer) = parse_answer(&answer) {
+                return answer;
+            }
+        }
+    }
+}
+
+/// 確認せずに、すべて許可する。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AutoYesPrompter;
+
+impl Prompter for AutoYesPrompter {
+    fn confirm(&self, question: &str, _default: Option<bool>) -> bool {
+        println!("{} {}: y", question, choices(Some(true)));
+        true
+    }
+}
+
+/// 確認せずに、既定の回答をする。既定の回答がない場合は許可しない。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultAnswerPrompter;
+
+impl Prompter for DefaultAnswerPrompter {
+    fn confirm(&self, question: &str, default: Option<bool>) -> bool {
+        let answer = default.unwrap_or(false);
+        println!(
+            "{} {}: {}",
+            question,
+            choices(default),
+            if answer { "y" } else { "n" }
+        );
+        answer
+    }
+}
+
+/// あらかじめ用意した回答を、順に回答する。
+///
+/// 用意した回答を使い切った場合は、既定の回答をする。既定の回答がない場合は許可しない。受け取った質問は
+/// 記録するため、登録処理が確認した内容を検証できる。
+#[derive(Debug, Default)]
+pub struct ScriptedPrompter {
+    /// 用意した回答
+    answers: Mutex<VecDeque<bool>>,
+    /// 受け取った質問
+    questions: Mutex<Vec<String>>,
+}
+
+impl ScriptedPrompter {
+    /// 回答を用意して構築する。
+    ///
+    /// # Arguments
+    ///
+    /// * `answers` - 順に回答する回答。
+    pub fn new(answers: impl IntoIterator<Item = bool>) -> Self {
+        Self {
+            answers: Mutex::new(answers.into_iter().collect()),
+            questions: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// 受け取った質問を、受け取った順に返す。
+    pub fn questions(&self) -> Vec<String> {
+        self.questions.lock().unwrap().clone()
+    }
+}
+
+impl Prompter for ScriptedPrompter {
+    fn confirm(&self, question: &str, default: Option<bool>) -> bool {
+        self.questions.lock().unwrap().push(question.to_string());
+        self.answers
+            .lock()
+            .unwrap()
+            .pop_front()
+            .unwrap_or_else(|| default.unwrap_or(false))
+    }
+}
@@ -0,0 +1,225 @@
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use crate::errors::Failure;
+use shapefile::{
+    dbase::{FieldValue, Record},
+    ShapeType,
+};
+
+/// ポリラインのシェイプタイプ。
+pub const POLYLINE_SHAPE_TYPES: [ShapeType; 3] = [
+    ShapeType::Polyline,
+    ShapeType::PolylineZ,
+    ShapeType::PolylineM,
+];
+
+/// Shapeファイルリーダー。
+pub type ShapeReader = shapefile::Reader<BufReader<File>>;
+
+/// Shapeファイルから読み込んだフィーチャー。
+pub struct ShapeFeature {
+    /// ジオメトリ
+    pub geom: geo_types::Geometry<f64>,
+    /// 属性
+    pub record: Record,
+}
+
+/// ジオメトリに変換できるシェイプタイプか確認する。
+///
+/// # Arguments
+///
+/// * `shape_type` - シェイプタイプ。
+///
+/// # Returns
+///
+/// ジオメトリに変換できる場合はtrue。変換できない場合はfalse。
+pub fn is_supported_shape_type(shape_type: ShapeType) -> bool {
+    !matches!(shape_type, ShapeType::NullShape | ShapeType::Multipatch)
+}
+
+/// Shapeファイルを開く。
+///
+/// # Arguments
+///
+/// * `path` - シェイプファイル(*.shp)のパス。
+/// * `encoding` - シェイプファイルの属性データファイルのエンコーディング。
+/// * `shape_types` - 受け付けるシェイプタイプ。空の場合はジオメトリに変換できるすべてのシェイプタイプを受け付ける。
+///
+/// # Returns
+///
+/// Shapeファイルリーダー。
+pub fn open_shape_file(
+    path: &str,
+    encoding: &str,
+    shape_types: &[ShapeType],
+) -> anyhow::Result<ShapeReader> {
+    let reader = ShapeReader::from_path_with_label(path, encoding)
+        .map_err(|e| Failure::input(format!("Shapeファイル({})を開けません。{}", path, e)))?;
+    let shape_type = reader.header().shape_type;
+    if !is_supported_shape_type(shape_type) {
+        return Err(Failure::data(format!(
+            "Shapeファイルのシェイプタイプ({})は、サポートしていません。",
+            shape_type
+        ))
+        .into());
+    }
+    if !shape_types.is_empty() && !shape_types.contains(&shape_type) {
+        return Err(Failure::data(format!(
+            "Shapeファイルのシェイプタイプ({})が、{:?}のいずれでもありません。",
+            shape_type, shape_types
+        ))
+        .into());
+    }
+
+    Ok(reader)
+}
+
+/// Shapeファイルのインデックスファイル(*.shx)の大きさから、Shapeファイルに記録されているシェイプの数を求める。
+///
+/// インデックスファイルは、100バイトのヘッダーに続けて、シェイプごとに8バイトのレコードを記録している。
+///
+/// # Arguments
+///
+/// * `path` - シェイプファイル(*.shp)のパス。
+///
+/// # Returns
+///
+/// シェイプの数。インデックスファイルが存在しない場合はNone。
+pub fn count_shapes(path: &str) -> Option<usize> {
+    let len = std::fs::metadata(Path::new(path).with_extension("shx"))
+        .ok()?
+        .len();
+
+    usize::try_from(len.checked_sub(100)? / 8).ok()
+}
+
+/// Shapeファイルに記録されているシェイプと属性を、フィーチャーとして1つずつ読み込むイテレーターを返す。
+///
+/// ポイントはPoint、マルチポイントはMultiPoint、ポリラインはMultiLineString、ポリゴンは
+/// MultiPolygonのジオメトリに変換する。
+///
+/// # Arguments
+///
+/// * `reader` - Shapeファイルリーダー。
+///
+/// # Returns
+///
+/// フィーチャーを読み込むイテレーター。
+pub fn iter_features(
+    reader: &mut ShapeReader,
+) -> impl Iterator<Item = anyhow::Result<ShapeFeature>> + '_ {
+    reader
+        .iter_shapes_and_records()
+        .enumerate()
+        .map(|(index, result)| {
+            let (shape, record) = result.map_err(|e| {
+                Failure::input(format!(
+                    "{}番目のシェイプを読み込むときにエラーが発生しました。{}",
+                    index, e
+                ))
+            })?;
+            let geom = geo_types::Geometry::<f64>::try_from(shape).map_err(|e| {
+                Failure::data(format!(
+                    "{}番目のシェイプをジオメトリに変換できません。{}",
+                    index, e
+                ))
+            })?;
+
+            Ok(ShapeFeature { geom, record })
+        })
+}
+
+/// Shapeファイルに記録されているシェイプと属性を、フィーチャーとして読み込む。
+///
+/// # Arguments
+///
+/// * `reader` - Shapeファイルリーダー。
+///
+/// # Returns
+///
+/// フィーチャーを格納したベクタ。
+pub fn read_features(reader: &mut ShapeReader) -> anyhow::Result<Vec<ShapeFeature>> {
+    iter_features(reader).collect()
+}
+
+/// 属性から文字列型のフィールドの値を読み込む。
+///
+/// # Arguments
+///
+/// * `record` - 属性。
+/// * `name` - フィールド名。
+///
+/// # Returns
+///
+/// フィールドの値。フィールドが存在しない、値が存在しない、または文字列型でない場合はNone。
+pub fn read_string_field(record: &Record, name: &str) -> Option<String> {
+    match record.get(name) {
+        Some(FieldValue::Character(value)) => value.as_ref().cloned(),
+        _ => None,
+    }
+}
+
+/// 属性から、値が必須の文字列型のフィールドの値を読み込む。
+///
+/// # Arguments
+///
+/// * `record` - 属性。
+/// * `name` - フィールド名。
+///
+/// # Returns
+///
+/// フィールドの値。フィールドが存在しない、値が存在しない、または文字列型でない場合はエラー。
+pub fn read_required_field(record: &Record, name: &str) -> anyhow::Result<String> {
+    read_string_field(record, name).ok_or_else(|| {
+        Failure::data(format!("文字列型のフィールド{}の値がありません。", name)).into()
+    })
+}
+
+/// 属性のフィールドの値の型の名前と、値を文字列に変換した値を返す。
+///
+/// # Arguments
+///
+/// * `value` - フィールドの値。
+///
+/// # Returns
+///
+/// フィールドの型の名前と、値を文字列に変換した値。値が存在しない場合はNone。
+pub fn describe_field_value(value: &FieldValue) -> (&'static str, Option<String>) {
+    match value {
+        FieldValue::Character(value) => ("character", value.clone()),
+        FieldValue::Numeric(value) => ("numeric", value.map(|value| value.to_string())),
+        FieldValue::Logical(value) => ("logical", value.map(|value| value.to_string())),
+        FieldValue::Date(value) => (
+            "date",
+            value.map(|date| format!("{:04}-{:02}-{:02}", date.year(), date.month(), date.day())),
+        ),
+        FieldValue::Float(value) => ("float", value.map(|value| value.to_string())),
+        FieldValue::Integer(value) => ("integer", Some(value.to_string())),
+        FieldValue::Currency(value) => ("currency", Some(value.to_string())),
+        FieldValue::DateTime(value) => ("datetime", Some(format!("{:?}", value))),
+        FieldValue::Double(value) => ("double", Some(value.to_string())),
+        FieldValue::Memo(value) => ("memo", Some(value.clone())),
+    }
+}
+
+/// Shapeファイルのコードページファイル(*.cpg)から、属性データファイルのエンコーディングを読み込む。
+///
+/// # Arguments
+///
+/// * `path` - シェイプファイル(*.shp)のパス。
+///
+/// # Returns
+///
+/// エンコーディング。コードページファイルが存在しない、または空の場合はNone。
+pub fn read_code_page(path: &str) -> Option<String> {
+    let content = std::fs::read_to_string(Path::new(path).with_extension("cpg")).ok()?;
+    let encoding = content.trim();
+    if encoding.is_empty() {
+        return None;
+    }
+
+    Some(encoding.to_string())
+}
@@ -0,0 +1,139 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor, Read, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+
+use anyhow::anyhow;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// 登録するフィーチャーを保持するメモリの上限の既定値(MiB)。
+pub const DEFAULT_MEMORY_BUDGET_MB: usize = 256;
+
+/// 保持する要素の大きさがメモリの上限を超えたときに、要素を一時ファイルに書き出すキュー。
+///
+/// 要素はJSONに変換して1行ずつ保持し、メモリに保持している要素の大きさが上限を超えるたびに、
+/// 一時ファイルに追記する。一時ファイルは、キューを破棄したときに削除される。
+pub struct SpillQueue<T> {
+    /// メモリに保持する要素の大きさの上限(バイト)
+    budget: usize,
+    /// メモリに保持している要素
+    buffer: Vec<u8>,
+    /// 要素を書き出した一時ファイル
+    file: Option<File>,
+    /// 要素の数
+    len: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Serialize + DeserializeOwned> SpillQueue<T> {
+    /// キューを構築する。
+    ///
+    /// # Arguments
+    ///
+    /// * `budget` - メモリに保持する要素の大きさの上限(バイト)。
+    ///
+    /// # Returns
+    ///
+    /// キュー。
+    pub fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            buffer: Vec::new(),
+            file: None,
+            len: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    /// キューに要素を追加する。
+    ///
+    /// # Arguments
+    ///
+    /// * `item` - 追加する要素。
+    pub fn push(&mut self, item: &T) -> anyhow::Result<()> {
+        serde_json::to_writer(&mut self.buffer, item)
+            .map_err(|e| anyhow!("フィーチャーをキューに追加できません。{}", e))?;
+        self.buffer.push(b'\n');
+        self.len += 1;
+        if self.budget < self.buffer.len() {
+            self.spill()?;
+        }
+
+        Ok(())
+    }
+
+    /// メモリに保持している要素を一時ファイルに書き出す。
+    fn spill(&mut self) -> anyhow::Result<()> {
+        let file = match self.file.as_mut() {
+            Some(file) => file,
+            None => self.file.insert(
+                tempfile::tempfile().map_err(|e| anyhow!("一時ファイルを作成できません。{}", e))?,
+            ),
+        };
+        file.write_all(&self.buffer)
+            .map_err(|e| anyhow!("一時ファイルにフィーチャーを書き出せません。{}", e))?;
+        self.buffer.clear();
+
+        Ok(())
+    }
+
+    /// キューに追加した要素の数を返す。
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// キューが空か確認する。
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// キューに追加した順に要素を取り出すイテレーターを返す。
+    ///
+    /// # Returns
+    ///
+    /// 要素を取り出すイテレーター。
+    pub fn into_items(self) -> anyhow::Result<SpillItems<T>> {
+        let memory = Cursor::new(self.buffer);
+        let reader: Box<dyn BufRead + Send> = match self.file {
+            Some(mut file) => {
+                file.seek(SeekFrom::Start(0))
+                    .map_err(|e| anyhow!("一時ファイルを読み込めません。{}", e))?;
+                Box::new(BufReader::new(file).chain(memory))
+            }
+            None => Box::new(memory),
+        };
+
+        Ok(SpillItems {
+            reader,
+            line: String::new(),
+            _marker: PhantomData,
+        })
+    }
+}
+
+/// キューから要素を取り出すイテレーター。
+pub struct SpillItems<T> {
+    /// 一時ファイルとメモリに保持している要素を順に読み込むリーダー
+    reader: Box<dyn BufRead + Send>,
+    /// 読み込んだ行
+    line: String,
+    _marker: PhantomData<T>,
+}
+
+impl<T: DeserializeOwned> Iterator for SpillItems<T> {
+    type Item = anyhow::Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.line.clear();
+        match self.reader.read_line(&mut self.line) {
+            Ok(0) => None,
+            Ok(_) => Some(
+                serde_json::from_str(&self.line)
+                    .map_err(|e| anyhow!("キューから取り出したフィーチャーが不正です。{}", e)),
+            ),
+            Err(e) => Some(Err(anyhow!(
+                "キューからフィーチャーを取り出せません。{}",
+                e
+            ))),
+        }
+    }
+}
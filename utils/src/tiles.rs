@@ -0,0 +1,296 @@
+//! XYZタイル(Webメルカトル投影法)の範囲を計算する。
+//!
+//! タイルの範囲は、経緯度を経由せずにWebメルカトル座標(メートル)で直接計算する。
+
+use std::f64::consts::PI;
+
+/// Webメルカトル投影法で、座標の範囲の原点から東端(または北端)までの距離(メートル)。
+///
+/// Webメルカトル投影法の地球の半径(6378137メートル)に円周率を乗じた値。
+pub const WEB_MERCATOR_EXTENT: f64 = 20037508.342789244;
+
+/// タイルのX座標とY座標を32ビットの符号なし整数で表せる、ズームレベルの最大値。
+pub const MAX_ZOOM: u8 = 32;
+
+/// Webメルカトル投影法で表せる緯度の最大値(度)。
+pub const MAX_LATITUDE: f64 = 85.05112877980659;
+
+/// XYZタイル。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Tile {
+    /// ズームレベル
+    zoom: u8,
+    /// タイルのX座標(西から東)
+    x: u32,
+    /// タイルのY座標(北から南)
+    y: u32,
+}
+
+/// タイルの範囲。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TileBounds {
+    /// 西端
+    pub min_x: f64,
+    /// 南端
+    pub min_y: f64,
+    /// 東端
+    pub max_x: f64,
+    /// 北端
+    pub max_y: f64,
+}
+
+impl Tile {
+    /// タイルを構築する。
+    ///
+    /// # Arguments
+    ///
+    /// * `zoom` - ズームレベル。
+    /// * `x` - タイルのX座標。
+    /// * `y` - タイルのY座標。
+    ///
+    /// # Returns
+    ///
+    /// タイル。ズームレベルが[`MAX_ZOOM`]より大きい場合、またはタイルのX座標かY座標がズームレベルの
+    /// 範囲外の場合はNone。
+    pub fn new(zoom: u8, x: u32, y: u32) -> Option<Self> {
+        if MAX_ZOOM < zoom {
+            return None;
+        }
+        let count = 1u64 << zoom;
+        ((x as u64) < count && (y as u64) < count).then_some(Self { zoom, x, y })
+    }
+
+    /// 経緯度を含むタイルを返す。
+    ///
+    /// 緯度は[`MAX_LATITUDE`]の範囲に、タイルの座標はズームレベルの範囲に収める。
+    ///
+    /// # Arguments
+    ///
+    /// * `zoom` - ズームレベル。
+    /// * `lon` - 経度(度)。
+    /// * `lat` - 緯度(度)。
+    ///
+    /// # Returns
+    ///
+    /// タイル。ズームレベルが[`MAX_ZOOM`]より大きい場合はNone。
+    pub fn containing(zoom: u8, lon: f64, lat: f64) -> Option<Self> {
+        if MAX_ZOOM < zoom {
+            return None;
+        }
+        let count = (1u64 << zoom) as f64;
+        let lat = lat.clamp(-MAX_LATITUDE, MAX_LATITUDE).to_radians();
+        let x = (lon + 180.0) / 360.0 * count;
+        let y = (1.0 - lat.tan().asinh() / PI) / 2.0 * count;
+        let clamp = |value: f64| value.floor().clamp(0.0, count - 1.0) as u32;
+
+        Self::new(zoom, clamp(x), clamp(y))
+    }
+
+    /// 経緯度の範囲と交差するタイルを、X座標、Y座標の順に返す。
+    ///
+    /// # Arguments
+    ///
+    /// * `zoom` - ズームレベル。
+    /// * `bounds` - WGS84経緯度の範囲。
+    ///
+    /// # Returns
+    ///
+    /// 範囲と交差するタイルを返すイテレーター。ズームレベルが[`MAX_ZOOM`]より大きい場合は空。
+    pub fn covering(zoom: u8, bounds: TileBounds) -> impl Iterator<Item = Self> {
+        let corners = Self::containing(zoom, bounds.min_x, bounds.max_y).zip(Self::containing(
+            zoom,
+            bounds.max_x,
+            bounds.min_y,
+        ));
+        corners
+            .into_iter()
+            .flat_map(move |(north_west, south_east)| {
+                (north_west.x..=south_east.x).flat_map(move |x| {
+                    (north_west.y..=south_east.y).map(move |y| Self { zoom, x, y })
+                })
+            })
+    }
+
+    /// ズームレベルを返す。
+    pub fn zoom(&self) -> u8 {
+        self.zoom
+    }
+
+    /// タイルのX座標を返す。
+    pub fn x(&self) -> u32 {
+        self.x
+    }
+
+    /// タイルのY座標を返す。
+    pub fn y(&self) -> u32 {
+        self.y
+    }
+
+    /// タイルの幅(メートル)を返す。
+    pub fn size(&self) -> f64 {
+        2.0 * WEB_MERCATOR_EXTENT / (1u64 << self.zoom) as f64
+    }
+
+    /// タイルの範囲をWebメルカトル座標(メートル)で返す。
+    pub fn bounds(&self) -> TileBounds {
+        self.buffered_bounds(0.0)
+    }
+
+    /// タイルの範囲を、タイルの幅に対する割合で四方に拡張して、Webメルカトル座標(メートル)で返す。
+    ///
+    /// 隣接するタイルと重なるように範囲を拡張すると、タイルの境界でジオメトリやラベルが途切れることを防げる。
+    ///
+    /// # Arguments
+    ///
+    /// * `ratio` - 範囲を拡張する、タイルの幅に対する割合。
+    ///
+    /// # Returns
+    ///
+    /// 拡張したタイルの範囲。
+    pub fn buffered_bounds(&self, ratio: f64) -> TileBounds {
+        let size = self.size();
+        let buffer = size * ratio;
+        let min_x = -WEB_MERCATOR_EXTENT + self.x as f64 * size;
+        let max_y = WEB_MERCATOR_EXTENT - self.y as f64 * size;
+
+        TileBounds {
+            min_x: min_x - buffer,
+            min_y: max_y - size - buffer,
+            max_x: min_x + size + buffer,
+            max_y: max_y + buffer,
+        }
+    }
+
+    /// タイルの範囲をWGS84経緯度で返す。
+    pub fn lon_lat_bounds(&self) -> TileBounds {
+        let count = (1u64 << self.zoom) as f64;
+        let lon = |x: f64| x / count * 360.0 - 180.0;
+        let lat = |y: f64| (PI * (1.0 - 2.0 * y / count)).sinh().atan().to_degrees();
+
+        TileBounds {
+            min_x: lon(self.x as f64),
+            min_y: lat(self.y as f64 + 1.0),
+            max_x: lon(self.x as f64 + 1.0),
+            max_y: lat(self.y as f64),
+        }
+    }
+}
+
+impl TileBounds {
+    /// 範囲を示すポリゴンを、WKT形式で返す。
+    pub fn to_wkt(&self) -> String {
+        format!(
+            "POLYGON(({} {}, {} {}, {} {}, {} {}, {} {}))",
+            self.min_x,
+            self.min_y,
+            self.max_x,
+            self.min_y,
+            self.max_x,
+            self.max_y,
+            self.min_x,
+            self.max_y,
+            self.min_x,
+            self.min_y,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 範囲が、許容誤差(1ミリメートル)の範囲で一致するか確認する。
+    fn assert_bounds_eq(actual: TileBounds, expected: [f64; 4]) {
+        let actual = [actual.min_x, actual.min_y, actual.max_x, actual.max_y];
+        assert!(
+            actual
+                .iter()
+                .zip(expected.iter())
+                .all(|(a, e)| (a - e).abs() < 1e-3),
+            "{:?}と{:?}が一致しません。",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn new_rejects_coordinates_outside_zoom_level() {
+        assert!(Tile::new(0, 0, 0).is_some());
+        assert!(Tile::new(0, 1, 0).is_none());
+        assert!(Tile::new(0, 0, 1).is_none());
+        assert!(Tile::new(1, 1, 1).is_some());
+        assert!(Tile::new(1, 2, 0).is_none());
+        assert!(Tile::new(1, 0, 2).is_none());
+        assert!(Tile::new(MAX_ZOOM, u32::MAX, u32::MAX).is_some());
+        assert!(Tile::new(MAX_ZOOM + 1, 0, 0).is_none());
+    }
+
+    #[test]
+    fn bounds_match_web_mercator_extent() {
+        let e = WEB_MERCATOR_EXTENT;
+        assert_bounds_eq(Tile::new(0, 0, 0).unwrap().bounds(), [-e, -e, e, e]);
+        assert_bounds_eq(Tile::new(1, 0, 0).unwrap().bounds(), [-e, 0.0, 0.0, e]);
+        assert_bounds_eq(Tile::new(1, 1, 0).unwrap().bounds(), [0.0, 0.0, e, e]);
+        assert_bounds_eq(Tile::new(1, 0, 1).unwrap().bounds(), [-e, -e, 0.0, 0.0]);
+        assert_bounds_eq(Tile::new(1, 1, 1).unwrap().bounds(), [0.0, -e, e, 0.0]);
+    }
+
+    #[test]
+    fn buffered_bounds_expand_by_ratio_of_tile_size() {
+        let e = WEB_MERCATOR_EXTENT;
+        let tile = Tile::new(1, 0, 0).unwrap();
+        assert_bounds_eq(
+            tile.buffered_bounds(0.5),
+            [-1.5 * e, -0.5 * e, 0.5 * e, 1.5 * e],
+        );
+        assert_eq!(tile.buffered_bounds(0.0), tile.bounds());
+    }
+
+    #[test]
+    fn lon_lat_bounds_match_max_latitude() {
+        let bounds = Tile::new(0, 0, 0).unwrap().lon_lat_bounds();
+        assert!((bounds.min_x + 180.0).abs() < 1e-9);
+        assert!((bounds.max_x - 180.0).abs() < 1e-9);
+        assert!((bounds.min_y + MAX_LATITUDE).abs() < 1e-9);
+        assert!((bounds.max_y - MAX_LATITUDE).abs() < 1e-9);
+    }
+
+    #[test]
+    fn containing_returns_tile_of_lon_lat() {
+        assert_eq!(
+            Tile::containing(10, 139.7671, 35.6812),
+            Tile::new(10, 909, 403)
+        );
+        assert_eq!(Tile::containing(1, -90.0, 45.0), Tile::new(1, 0, 0));
+        assert_eq!(Tile::containing(1, 90.0, -45.0), Tile::new(1, 1, 1));
+        // 範囲外の経緯度は、ズームレベルの範囲のタイルに収める
+        assert_eq!(Tile::containing(1, 180.0, 90.0), Tile::new(1, 1, 0));
+        assert_eq!(Tile::containing(1, -200.0, -90.0), Tile::new(1, 0, 1));
+        assert_eq!(Tile::containing(MAX_ZOOM + 1, 0.0, 0.0), None);
+    }
+
+    #[test]
+    fn covering_returns_tiles_in_x_then_y_order() {
+        let world = TileBounds {
+            min_x: -180.0,
+            min_y: -MAX_LATITUDE,
+            max_x: 180.0,
+            max_y: MAX_LATITUDE,
+        };
+        let tiles: Vec<(u32, u32)> = Tile::covering(1, world)
+            .map(|tile| (tile.x(), tile.y()))
+            .collect();
+        assert_eq!(tiles, [(0, 0), (0, 1), (1, 0), (1, 1)]);
+
+        let point = TileBounds {
+            min_x: 139.7671,
+            min_y: 35.6812,
+            max_x: 139.7671,
+            max_y: 35.6812,
+        };
+        let tiles: Vec<Tile> = Tile::covering(10, point).collect();
+        assert_eq!(tiles, [Tile::new(10, 909, 403).unwrap()]);
+
+        assert_eq!(Tile::covering(MAX_ZOOM + 1, world).count(), 0);
+    }
+}